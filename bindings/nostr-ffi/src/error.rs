@@ -53,3 +53,9 @@ impl From<nostr::hashes::hex::Error> for NostrError {
         Self::Generic { err: e.to_string() }
     }
 }
+
+impl From<nostr::message::subscription::Error> for NostrError {
+    fn from(e: nostr::message::subscription::Error) -> NostrError {
+        Self::Generic { err: e.to_string() }
+    }
+}
@@ -12,9 +12,11 @@ use super::kind::Kind;
 use super::Event;
 use crate::contact::Contact;
 use crate::error::Result;
+use crate::helper::unwrap_or_clone_arc;
 use crate::key::Keys;
 use crate::metadata::Metadata;
 
+#[derive(Clone)]
 pub struct EventBuilder {
     builder: EventBuilderSdk,
 }
@@ -40,6 +42,13 @@ impl EventBuilder {
         }
     }
 
+    pub fn custom_created_at(self: Arc<Self>, timestamp: u64) -> Arc<Self> {
+        let mut builder = unwrap_or_clone_arc(self);
+        builder.builder = builder.builder.custom_created_at(timestamp);
+
+        Arc::new(builder)
+    }
+
     pub fn to_event(&self, keys: Arc<Keys>) -> Result<Arc<Event>> {
         let event = self.builder.clone().to_event(keys.deref())?;
         Ok(Arc::new(event.into()))
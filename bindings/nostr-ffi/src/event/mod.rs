@@ -29,17 +29,33 @@ impl Deref for Event {
 }
 
 impl Event {
+    pub fn id(&self) -> String {
+        self.event.id.to_string()
+    }
+
     pub fn pubkey(&self) -> String {
         self.event.pubkey.to_string()
     }
 
+    pub fn created_at(&self) -> u64 {
+        self.event.created_at
+    }
+
     pub fn kind(&self) -> Kind {
         self.event.kind.into()
     }
 
+    pub fn tags(&self) -> Vec<Vec<String>> {
+        self.event.tags.iter().map(|t| t.as_vec()).collect()
+    }
+
     pub fn content(&self) -> String {
         self.event.content.clone()
     }
+
+    pub fn signature(&self) -> String {
+        self.event.sig.to_string()
+    }
 }
 
 impl Event {
@@ -57,3 +73,60 @@ impl Event {
         Ok(self.event.as_json()?)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use nostr::event::kind::KindBase;
+
+    use super::builder::EventBuilder;
+    use super::*;
+    use crate::key::Keys;
+
+    #[test]
+    fn test_event_fields() -> Result<()> {
+        let keys = Arc::new(Keys::generate_from_os_random());
+        let tags = vec![
+            vec![String::from("t"), String::from("nostr")],
+            vec![
+                String::from("r"),
+                String::from("wss://relay.damus.io"),
+                String::from("read"),
+            ],
+        ];
+        let event = Arc::new(EventBuilder::new(
+            Kind::Base {
+                kind: KindBase::TextNote,
+            },
+            String::from("Hello"),
+            tags.clone(),
+        ))
+        .custom_created_at(1_690_000_000)
+        .to_event(keys.clone())?;
+
+        assert_eq!(event.pubkey(), keys.public_key());
+        assert_eq!(event.created_at(), 1_690_000_000);
+        assert_eq!(event.tags(), tags);
+        assert_eq!(event.content(), "Hello");
+        assert!(event.verify());
+
+        // Same values as the JSON, so apps don't need to parse it
+        let json = event.as_json()?;
+        assert!(json.contains(&format!(r#""id":"{}""#, event.id())));
+        assert!(json.contains(&format!(r#""sig":"{}""#, event.signature())));
+        assert_eq!(event.id().len(), 64);
+        assert_eq!(event.signature().len(), 128);
+
+        let parsed = Event::from_json(json.clone())?;
+        assert_eq!(parsed.id(), event.id());
+        assert_eq!(parsed.signature(), event.signature());
+        assert_eq!(parsed.tags(), event.tags());
+
+        // The id and the signature are checked when parsing
+        let forged = json.replace(r#""content":"Hello""#, r#""content":"Bye""#);
+        assert!(Event::from_json(forged).is_err());
+
+        Ok(())
+    }
+}
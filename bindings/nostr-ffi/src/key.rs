@@ -3,6 +3,7 @@
 
 use std::ops::Deref;
 use std::str::FromStr;
+use std::sync::Arc;
 
 use nostr::key::{FromBech32, Keys as KeysSdk, XOnlyPublicKey};
 use nostr::secp256k1::SecretKey;
@@ -64,11 +65,28 @@ impl Keys {
         })
     }
 
+    pub fn from_mnemonic_with_account(
+        mnemonic: String,
+        passphrase: Option<String>,
+        account: u32,
+    ) -> Result<Self> {
+        Ok(Self {
+            keys: KeysSdk::from_mnemonic_with_account(mnemonic, passphrase, account)
+                .map_err(|e| NostrError::Generic { err: e.to_string() })?,
+        })
+    }
+
     pub fn public_key(&self) -> String {
         self.keys.public_key_as_str()
     }
 
     pub fn secret_key(&self) -> Result<String> {
-        Ok(self.keys.secret_key_as_str()?)
+        Ok(self.keys.secret_key_as_zeroizing_str()?.to_string())
+    }
+
+    pub fn derive_app_key(&self, label: String) -> Result<Arc<Self>> {
+        Ok(Arc::new(Self {
+            keys: self.keys.derive_app_key(label)?,
+        }))
     }
 }
@@ -11,6 +11,7 @@ mod subscription;
 
 #[allow(missing_docs)]
 #[allow(unused_imports)]
+#[allow(clippy::all)]
 mod ffi {
     // Extenral
     pub use nostr::KindBase;
@@ -5,6 +5,7 @@ use std::ops::Deref;
 use std::str::FromStr;
 use std::sync::Arc;
 
+use nostr::message::HexPrefix;
 use nostr::secp256k1::XOnlyPublicKey;
 use nostr::{Sha256Hash, SubscriptionFilter as SubscriptionFilterSdk};
 
@@ -45,14 +46,19 @@ impl SubscriptionFilter {
 
     pub fn id(self: Arc<Self>, id: String) -> Result<Arc<Self>> {
         let mut builder = unwrap_or_clone_arc(self);
-        builder.sub_filter = builder.sub_filter.id(id);
+        builder.sub_filter = builder.sub_filter.id(HexPrefix::from_str(&id)?);
 
         Ok(Arc::new(builder))
     }
 
     pub fn ids(self: Arc<Self>, ids: Vec<String>) -> Result<Arc<Self>> {
+        let mut new_ids: Vec<HexPrefix> = Vec::with_capacity(ids.len());
+        for id in ids.into_iter() {
+            new_ids.push(HexPrefix::from_str(&id)?);
+        }
+
         let mut builder = unwrap_or_clone_arc(self);
-        builder.sub_filter = builder.sub_filter.ids(ids);
+        builder.sub_filter = builder.sub_filter.ids(new_ids);
 
         Ok(Arc::new(builder))
     }
@@ -82,6 +88,20 @@ impl SubscriptionFilter {
         Ok(Arc::new(builder))
     }
 
+    pub fn limit(self: Arc<Self>, limit: u16) -> Arc<Self> {
+        let mut builder = unwrap_or_clone_arc(self);
+        builder.sub_filter = builder.sub_filter.limit(limit);
+
+        Arc::new(builder)
+    }
+
+    pub fn search(self: Arc<Self>, text: String) -> Arc<Self> {
+        let mut builder = unwrap_or_clone_arc(self);
+        builder.sub_filter = builder.sub_filter.search(text);
+
+        Arc::new(builder)
+    }
+
     pub fn since(self: Arc<Self>, timestamp: u64) -> Arc<Self> {
         let mut builder = unwrap_or_clone_arc(self);
         builder.sub_filter = builder.sub_filter.since(timestamp);
@@ -97,9 +117,9 @@ impl SubscriptionFilter {
     }
 
     pub fn authors(self: Arc<Self>, authors: Vec<String>) -> Result<Arc<Self>> {
-        let mut new_authors: Vec<XOnlyPublicKey> = Vec::with_capacity(authors.len());
+        let mut new_authors: Vec<HexPrefix> = Vec::with_capacity(authors.len());
         for a in authors.into_iter() {
-            new_authors.push(XOnlyPublicKey::from_str(&a)?);
+            new_authors.push(HexPrefix::from_str(&a)?);
         }
 
         let mut builder = unwrap_or_clone_arc(self);
@@ -62,8 +62,8 @@ impl Client {
         crate::thread::spawn("client", move || {
             log::debug!("Client Thread Started");
             Ok(self.client.lock().handle_notifications(|notification| {
-                if let RelayPoolNotificationsSdk::ReceivedEvent(event) = notification {
-                    handler.handle(Arc::new(event.into()));
+                if let RelayPoolNotificationsSdk::ReceivedEvent { event, .. } = notification {
+                    handler.handle(Arc::new((*event).into()));
                 }
 
                 Ok(())
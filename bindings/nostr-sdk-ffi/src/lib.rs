@@ -7,12 +7,9 @@ mod logger;
 mod subscription;
 mod thread;
 
-trait FromResult<T>: Sized {
-    fn from_result(_: T) -> error::Result<Self>;
-}
-
 #[allow(missing_docs)]
 #[allow(unused_imports)]
+#[allow(clippy::all)]
 mod ffi {
     // Extenal
     pub use nostr::util::time::timestamp;
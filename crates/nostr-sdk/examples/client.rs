@@ -57,7 +57,7 @@ async fn main() -> Result<()> {
     loop {
         let mut notifications = client.notifications();
         while let Ok(notification) = notifications.recv().await {
-            if let RelayPoolNotifications::ReceivedEvent(event) = notification {
+            if let RelayPoolNotifications::ReceivedEvent { event, .. } = notification {
                 if event.kind == Kind::Base(KindBase::EncryptedDirectMessage) {
                     if let Ok(msg) = decrypt(&my_keys.secret_key()?, &event.pubkey, &event.content)
                     {
@@ -42,7 +42,7 @@ fn main() -> Result<()> {
     client.disconnect_relay("wss://relay.nostr.info")?;
 
     client.handle_notifications(|notification| {
-        if let RelayPoolNotifications::ReceivedEvent(event) = notification {
+        if let RelayPoolNotifications::ReceivedEvent { event, .. } = notification {
             if event.kind == Kind::Base(KindBase::EncryptedDirectMessage) {
                 if let Ok(msg) = decrypt(
                     &my_keys.secret_key().unwrap(),
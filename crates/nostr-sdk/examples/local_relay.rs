@@ -0,0 +1,34 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::time::Duration;
+
+use nostr::key::Keys;
+use nostr::SubscriptionFilter;
+use nostr_sdk::local_relay::LocalRelay;
+use nostr_sdk::store::MemoryStore;
+use nostr_sdk::{Client, Result};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::init();
+
+    let relay = LocalRelay::run("127.0.0.1:7777".parse()?, MemoryStore::new()).await?;
+    println!("Local relay listening on {}", relay.url()?);
+
+    let my_keys = Keys::generate_from_os_random();
+    let client = Client::new(&my_keys);
+    client.add_relay(relay.url()?.as_str(), None).await?;
+    client.connect().await?;
+
+    client
+        .publish_text_note("Hello from the local relay!", &[])
+        .await?;
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    let filter = SubscriptionFilter::new().author(my_keys.public_key());
+    let events = client.get_events_of(vec![filter], None).await?;
+    println!("{:#?}", events);
+
+    Ok(())
+}
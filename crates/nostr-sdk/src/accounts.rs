@@ -0,0 +1,192 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::collections::BTreeMap;
+
+use nostr::key::XOnlyPublicKey;
+use nostr::Keys;
+
+use crate::client::{Client, Error as ClientError};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Account not found
+    #[error("account not found: {0}")]
+    NotFound(String),
+    /// Client error
+    #[error("client error: {0}")]
+    Client(#[from] ClientError),
+}
+
+/// Named identities of a multi-account app
+///
+/// A single [`Client`] can be shared by all the accounts: [`Accounts::switch`] changes its
+/// active identity.
+///
+/// # Example
+/// ```rust,no_run
+/// use nostr_sdk::{Accounts, Client};
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let personal = Client::generate_keys();
+/// let work = Client::generate_keys();
+///
+/// let mut accounts = Accounts::new();
+/// accounts.add("personal", personal.clone());
+/// accounts.add("work", work);
+///
+/// let client = Client::new(&personal);
+/// accounts.switch(&client, "work").await.unwrap();
+/// client
+///     .publish_text_note("Hello from work", &[])
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Accounts {
+    accounts: BTreeMap<String, Keys>,
+    active: Option<String>,
+}
+
+impl Accounts {
+    /// New empty `Accounts`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add account, replacing (and returning) the keys of an account with the same name
+    pub fn add<S>(&mut self, name: S, keys: Keys) -> Option<Keys>
+    where
+        S: Into<String>,
+    {
+        self.accounts.insert(name.into(), keys)
+    }
+
+    /// Remove account
+    pub fn remove(&mut self, name: &str) -> Option<Keys> {
+        if self.active.as_deref() == Some(name) {
+            self.active = None;
+        }
+        self.accounts.remove(name)
+    }
+
+    /// Get the keys of an account
+    pub fn get(&self, name: &str) -> Option<&Keys> {
+        self.accounts.get(name)
+    }
+
+    fn account(&self, name: &str) -> Result<Keys, Error> {
+        self.accounts
+            .get(name)
+            .cloned()
+            .ok_or_else(|| Error::NotFound(name.to_string()))
+    }
+
+    /// Get account names
+    pub fn names(&self) -> Vec<String> {
+        self.accounts.keys().cloned().collect()
+    }
+
+    /// Find the name of the account with `public_key` (i.e. to label the received events)
+    pub fn find(&self, public_key: &XOnlyPublicKey) -> Option<&str> {
+        self.accounts
+            .iter()
+            .find(|(_, keys)| keys.public_key() == *public_key)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Get the name of the active account
+    pub fn active(&self) -> Option<&str> {
+        self.active.as_deref()
+    }
+
+    /// Set the active account, without changing any client
+    pub fn set_active(&mut self, name: &str) -> Result<Keys, Error> {
+        let keys = self.account(name)?;
+        self.active = Some(name.to_string());
+        Ok(keys)
+    }
+
+    /// Set the active account and switch the identity of `client` to it
+    ///
+    /// See [`Client::switch_keys`].
+    pub async fn switch(&mut self, client: &Client, name: &str) -> Result<Keys, Error> {
+        let keys = self.account(name)?;
+        client.switch_keys(&keys).await?;
+        self.active = Some(name.to_string());
+        Ok(keys)
+    }
+}
+
+#[cfg(all(test, feature = "local-relay"))]
+mod tests {
+    use std::time::Duration;
+
+    use nostr::SubscriptionFilter;
+
+    use super::*;
+    use crate::local_relay::LocalRelay;
+    use crate::store::MemoryStore;
+
+    #[tokio::test]
+    async fn test_switch() {
+        let local_relay = LocalRelay::run("127.0.0.1:0".parse().unwrap(), MemoryStore::new())
+            .await
+            .unwrap();
+        let personal = Keys::generate_from_os_random();
+        let work = Keys::generate_from_os_random();
+
+        let mut accounts = Accounts::new();
+        accounts.add("work", work.clone());
+        accounts.add("personal", personal.clone());
+        assert_eq!(accounts.names(), vec!["personal", "work"]);
+        assert_eq!(accounts.find(&work.public_key()), Some("work"));
+
+        let client = Client::new(&personal);
+        client
+            .add_relay(local_relay.url().unwrap().as_str(), None)
+            .await
+            .unwrap();
+        client
+            .connect_with_timeout(Duration::from_secs(5))
+            .await
+            .unwrap();
+        client
+            .subscribe(vec![SubscriptionFilter::new().pubkey(personal.public_key())])
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            accounts.switch(&client, "unknown").await,
+            Err(Error::NotFound(_))
+        ));
+        assert_eq!(accounts.active(), None);
+        accounts.switch(&client, "work").await.unwrap();
+        assert_eq!(accounts.active(), Some("work"));
+        assert_eq!(client.keys().public_key(), work.public_key());
+
+        // The subscription follows the identity
+        let relay = client.relays().await[&local_relay.url().unwrap()].clone();
+        assert!(relay
+            .subscriptions()
+            .await
+            .values()
+            .any(|filters| filters == &vec![SubscriptionFilter::new().pubkey(work.public_key())]));
+
+        client.publish_text_note("from work", &[]).await.unwrap();
+        let events = client
+            .get_events_of(
+                vec![SubscriptionFilter::new().author(work.public_key())],
+                Some(Duration::from_secs(5)),
+            )
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 1);
+
+        accounts.remove("work");
+        assert_eq!(accounts.active(), None);
+        assert_eq!(accounts.names(), vec!["personal"]);
+    }
+}
@@ -0,0 +1,212 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use nostr::hashes::hmac::{Hmac, HmacEngine};
+use nostr::hashes::sha256::Hash as Sha256;
+use nostr::hashes::{Hash, HashEngine};
+use nostr::key;
+use nostr::secp256k1::rand::rngs::OsRng;
+use nostr::secp256k1::rand::RngCore;
+use nostr::Keys;
+use scrypt::Params;
+use zeroize::Zeroizing;
+
+const VERSION: u8 = 0x01;
+const NONCE_SIZE: usize = 24;
+const SCRYPT_SALT: &[u8] = b"nostr-sdk-store";
+const HKDF_SALT: &[u8] = b"nostr-sdk-store";
+const HKDF_INFO: &[u8] = b"store-cipher-v1";
+
+/// Default scrypt `log_n` of [`StoreCipher::from_passphrase`]
+pub const DEFAULT_LOG_N: u8 = 16;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Key error
+    #[error("key error: {0}")]
+    Key(#[from] key::Error),
+    /// Invalid scrypt params
+    #[error("invalid scrypt params")]
+    InvalidScryptParams,
+    /// Impossible to encrypt
+    #[error("impossible to encrypt")]
+    Encryption,
+    /// Unsupported version
+    #[error("unsupported version: {0}")]
+    UnsupportedVersion(u8),
+    /// Wrong key or corrupted data
+    #[error("impossible to decrypt: wrong key or corrupted data")]
+    Decryption,
+}
+
+/// Cipher of the data stored on disk ([`Outbox`](crate::Outbox) and
+/// [`MemoryStore`](crate::store::MemoryStore) snapshots)
+///
+/// The key is derived with HKDF-SHA256 from the secret key or from the passphrase (stretched
+/// with scrypt first). The data is encrypted with XChaCha20-Poly1305 and a random nonce.
+///
+/// # Example
+/// ```rust,no_run
+/// use nostr_sdk::{Client, Outbox, StoreCipher};
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// #   let my_keys = Client::generate_keys();
+/// let client = Client::new(&my_keys);
+/// let cipher = StoreCipher::from_keys(&my_keys).unwrap();
+/// client
+///     .set_outbox(Some(Outbox::open_encrypted("outbox.bin", cipher).unwrap()))
+///     .await;
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct StoreCipher {
+    key: Zeroizing<[u8; 32]>,
+}
+
+impl std::fmt::Debug for StoreCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StoreCipher")
+            .field("key", &"<sensitive>")
+            .finish()
+    }
+}
+
+impl StoreCipher {
+    /// Derive `StoreCipher` from the secret key of `keys`
+    pub fn from_keys(keys: &Keys) -> Result<Self, Error> {
+        let secret_key = Zeroizing::new(keys.secret_key()?.secret_bytes());
+        Ok(Self::from_ikm(secret_key.as_slice()))
+    }
+
+    /// Derive `StoreCipher` from `passphrase`
+    ///
+    /// `log_n` is the scrypt work factor (see [`DEFAULT_LOG_N`]). The salt is fixed, so the
+    /// same passphrase and `log_n` always give the same key.
+    pub fn from_passphrase(passphrase: &str, log_n: u8) -> Result<Self, Error> {
+        let params = Params::new(log_n, 8, 1).map_err(|_| Error::InvalidScryptParams)?;
+        let mut stretched = Zeroizing::new([0u8; 32]);
+        scrypt::scrypt(
+            passphrase.as_bytes(),
+            SCRYPT_SALT,
+            &params,
+            stretched.as_mut_slice(),
+        )
+        .map_err(|_| Error::InvalidScryptParams)?;
+        Ok(Self::from_ikm(stretched.as_slice()))
+    }
+
+    /// HKDF-SHA256 (extract and a single expand block)
+    fn from_ikm(ikm: &[u8]) -> Self {
+        let prk = Zeroizing::new(hmac_sha256(HKDF_SALT, &[ikm]));
+        Self {
+            key: Zeroizing::new(hmac_sha256(prk.as_slice(), &[HKDF_INFO, &[0x01]])),
+        }
+    }
+
+    /// Encrypt `plaintext`
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut nonce = [0u8; NONCE_SIZE];
+        OsRng.fill_bytes(&mut nonce);
+
+        let cipher = XChaCha20Poly1305::new(self.key.as_slice().into());
+        let ciphertext = cipher
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: plaintext,
+                    aad: &[VERSION],
+                },
+            )
+            .map_err(|_| Error::Encryption)?;
+
+        let mut payload: Vec<u8> = Vec::with_capacity(1 + NONCE_SIZE + ciphertext.len());
+        payload.push(VERSION);
+        payload.extend_from_slice(&nonce);
+        payload.extend_from_slice(&ciphertext);
+        Ok(payload)
+    }
+
+    /// Decrypt data encrypted with [`StoreCipher::encrypt`]
+    pub fn decrypt(&self, payload: &[u8]) -> Result<Zeroizing<Vec<u8>>, Error> {
+        match payload.first() {
+            Some(&VERSION) => (),
+            Some(version) => return Err(Error::UnsupportedVersion(*version)),
+            None => return Err(Error::Decryption),
+        }
+        if payload.len() < 1 + NONCE_SIZE {
+            return Err(Error::Decryption);
+        }
+
+        let cipher = XChaCha20Poly1305::new(self.key.as_slice().into());
+        let plaintext = cipher
+            .decrypt(
+                XNonce::from_slice(&payload[1..1 + NONCE_SIZE]),
+                Payload {
+                    msg: &payload[1 + NONCE_SIZE..],
+                    aad: &[VERSION],
+                },
+            )
+            .map_err(|_| Error::Decryption)?;
+        Ok(Zeroizing::new(plaintext))
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[&[u8]]) -> [u8; 32] {
+    let mut engine: HmacEngine<Sha256> = HmacEngine::new(key);
+    for d in data.iter() {
+        engine.input(d);
+    }
+    Hmac::<Sha256>::from_engine(engine).into_inner()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let keys = Keys::generate_from_os_random();
+        let cipher = StoreCipher::from_keys(&keys).unwrap();
+
+        let payload = cipher.encrypt(b"secret dm").unwrap();
+        assert_eq!(cipher.decrypt(&payload).unwrap().as_slice(), b"secret dm");
+        // Random nonce
+        assert_ne!(cipher.encrypt(b"secret dm").unwrap(), payload);
+
+        // Same secret key, same cipher
+        let same = StoreCipher::from_keys(&Keys::new(keys.secret_key().unwrap())).unwrap();
+        assert_eq!(same.decrypt(&payload).unwrap().as_slice(), b"secret dm");
+
+        let other = StoreCipher::from_keys(&Keys::generate_from_os_random()).unwrap();
+        assert!(matches!(other.decrypt(&payload), Err(Error::Decryption)));
+
+        let mut tampered = payload.clone();
+        *tampered.last_mut().unwrap() ^= 0x01;
+        assert!(matches!(cipher.decrypt(&tampered), Err(Error::Decryption)));
+
+        assert!(matches!(
+            cipher.decrypt(&[0x02]),
+            Err(Error::UnsupportedVersion(0x02))
+        ));
+        assert!(matches!(cipher.decrypt(&[]), Err(Error::Decryption)));
+    }
+
+    #[test]
+    fn test_passphrase() {
+        let cipher = StoreCipher::from_passphrase("correct horse", 4).unwrap();
+        let payload = cipher.encrypt(b"draft").unwrap();
+
+        let same = StoreCipher::from_passphrase("correct horse", 4).unwrap();
+        assert_eq!(same.decrypt(&payload).unwrap().as_slice(), b"draft");
+
+        let wrong = StoreCipher::from_passphrase("wrong horse", 4).unwrap();
+        assert!(matches!(wrong.decrypt(&payload), Err(Error::Decryption)));
+
+        // Different work factor, different key
+        let other_log_n = StoreCipher::from_passphrase("correct horse", 5).unwrap();
+        assert!(other_log_n.decrypt(&payload).is_err());
+    }
+}
@@ -2,17 +2,43 @@
 // Distributed under the MIT software license
 
 use std::collections::HashMap;
+#[cfg(feature = "nip11")]
+use std::future::Future;
 use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
 
 use nostr::key::XOnlyPublicKey;
 use nostr::url::Url;
-use nostr::{Contact, Event, Keys, Metadata, Sha256Hash, SubscriptionFilter, Tag};
+#[cfg(feature = "nip49")]
+use nostr::util::backup::IdentityBundle;
+#[cfg(feature = "nip11")]
+use nostr::util::nips::nip11::RelayInformationDocument;
+use nostr::util::nips::nip13::PowOptions;
+use nostr::util::nips::nip29::{GroupId, GroupMetadata};
+use nostr::util::nips::nip38::UserStatus;
+use nostr::util::nips::nip65::RelayMetadata;
+use nostr::util::nips::nip66::RelayDiscovery;
+use nostr::util::nips::nip90::{JobRequest, JobResult};
+use nostr::{
+    ClientMessage, Contact, Event, EventBuilder, Keys, Metadata, NostrSigner, Sha256Hash,
+    SubscriptionFilter, Tag,
+};
 use tokio::sync::broadcast;
 
-use super::Error;
+#[cfg(any(feature = "nip04", feature = "nip59"))]
+use super::DirectMessageMode;
+use super::{Error, Options};
 use crate::client::Entity;
-use crate::relay::pool::RelayPoolNotifications;
-use crate::relay::Relay;
+#[cfg(feature = "nip46")]
+use crate::nip46::Nip46Signer;
+use crate::outbox::Outbox;
+use crate::relay::pool::{ConnectionReport, EventSource, RelayPoolNotifications};
+use crate::relay::{
+    Error as RelayError, Relay, RelayOptions, RelayStats, RelayStatus, RelayTransport, TlsOptions,
+};
+use crate::sink::NotificationSink;
+use crate::subscription::SubscriptionId;
 use crate::RUNTIME;
 
 #[derive(Debug, Clone)]
@@ -27,6 +53,39 @@ impl Client {
         }
     }
 
+    pub fn new_with_opts(keys: &Keys, opts: Options) -> Self {
+        Self {
+            client: super::Client::new_with_opts(keys, opts),
+        }
+    }
+
+    pub fn with_signer<T>(app_keys: &Keys, signer: T, opts: Options) -> Self
+    where
+        T: NostrSigner + 'static,
+    {
+        Self {
+            client: super::Client::with_signer(app_keys, signer, opts),
+        }
+    }
+
+    #[cfg(feature = "nip46")]
+    pub fn with_remote_signer(app_keys: &Keys, signer: Nip46Signer, opts: Options) -> Self {
+        Self {
+            client: super::Client::with_remote_signer(app_keys, signer, opts),
+        }
+    }
+
+    #[cfg(feature = "nip49")]
+    pub fn import_identity<S>(bundle: &IdentityBundle, password: S) -> Result<Self, Error>
+    where
+        S: Into<String>,
+    {
+        Ok(Self {
+            client: RUNTIME
+                .block_on(async { super::Client::import_identity(bundle, password).await })?,
+        })
+    }
+
     pub fn generate_keys() -> Keys {
         super::Client::generate_keys()
     }
@@ -36,15 +95,87 @@ impl Client {
         self.client.keys()
     }
 
+    #[cfg(feature = "nip46")]
+    pub fn remote_signer(&self) -> Option<Nip46Signer> {
+        self.client.remote_signer()
+    }
+
+    pub fn signer(&self) -> Arc<dyn NostrSigner> {
+        self.client.signer()
+    }
+
+    pub fn opts(&self) -> Options {
+        self.client.opts()
+    }
+
+    /// Switch the active identity to `keys`
+    pub fn switch_keys(&self, keys: &Keys) -> Result<(), Error> {
+        RUNTIME.block_on(async { self.client.switch_keys(keys).await })
+    }
+
     pub fn notifications(&self) -> broadcast::Receiver<RelayPoolNotifications> {
         self.client.notifications()
     }
 
+    pub fn add_notification_sink(&self, sink: NotificationSink) {
+        self.client.add_notification_sink(sink)
+    }
+
     /// Get relays
     pub fn relays(&self) -> HashMap<Url, Relay> {
         RUNTIME.block_on(async { self.client.relays().await })
     }
 
+    pub fn relay_status<S>(&self, url: S) -> Result<RelayStatus, Error>
+    where
+        S: Into<String>,
+    {
+        RUNTIME.block_on(async { self.client.relay_status(url).await })
+    }
+
+    pub fn relay_stats<S>(&self, url: S) -> Result<RelayStats, Error>
+    where
+        S: Into<String>,
+    {
+        RUNTIME.block_on(async { self.client.relay_stats(url).await })
+    }
+
+    pub fn event_source(&self, event_id: &Sha256Hash) -> Option<EventSource> {
+        RUNTIME.block_on(async { self.client.event_source(event_id).await })
+    }
+
+    pub fn set_outbox(&self, outbox: Option<Outbox>) {
+        RUNTIME.block_on(async { self.client.set_outbox(outbox).await })
+    }
+
+    pub fn outbox(&self) -> Option<Outbox> {
+        RUNTIME.block_on(async { self.client.outbox().await })
+    }
+
+    pub fn set_transport<T>(&self, transport: T)
+    where
+        T: RelayTransport + 'static,
+    {
+        RUNTIME.block_on(async { self.client.set_transport(transport).await })
+    }
+
+    #[cfg(feature = "nip11")]
+    pub fn relay_info<S>(&self, url: S) -> Result<RelayInformationDocument, Error>
+    where
+        S: Into<String>,
+    {
+        RUNTIME.block_on(async { self.client.relay_info(url).await })
+    }
+
+    #[cfg(feature = "nip11")]
+    pub fn set_payment_handler<F, Fut>(&self, func: F)
+    where
+        F: Fn(Url, Option<String>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = bool> + Send + 'static,
+    {
+        RUNTIME.block_on(async { self.client.set_payment_handler(func).await })
+    }
+
     /// Add multiple relays
     pub fn add_relays<S>(&self, relays: Vec<(S, Option<SocketAddr>)>) -> Result<(), Error>
     where
@@ -60,6 +191,31 @@ impl Client {
         RUNTIME.block_on(async { self.client.add_relay(url, proxy).await })
     }
 
+    pub fn add_relay_with_opts<S>(
+        &self,
+        url: S,
+        proxy: Option<SocketAddr>,
+        opts: RelayOptions,
+    ) -> Result<(), Error>
+    where
+        S: Into<String>,
+    {
+        RUNTIME.block_on(async { self.client.add_relay_with_opts(url, proxy, opts).await })
+    }
+
+    pub fn add_relay_with_tls<S>(
+        &self,
+        url: S,
+        proxy: Option<SocketAddr>,
+        opts: RelayOptions,
+        tls: TlsOptions,
+    ) -> Result<(), Error>
+    where
+        S: Into<String>,
+    {
+        RUNTIME.block_on(async { self.client.add_relay_with_tls(url, proxy, opts, tls).await })
+    }
+
     pub fn remove_relay<S>(&self, url: S) -> Result<(), Error>
     where
         S: Into<String>,
@@ -89,6 +245,10 @@ impl Client {
         RUNTIME.block_on(async { self.client.connect_and_wait().await })
     }
 
+    pub fn connect_with_timeout(&self, timeout: Duration) -> Result<ConnectionReport, Error> {
+        RUNTIME.block_on(async { self.client.connect_with_timeout(timeout).await })
+    }
+
     pub fn disconnect(&self) -> Result<(), Error> {
         RUNTIME.block_on(async { self.client.disconnect().await })
     }
@@ -97,19 +257,158 @@ impl Client {
         RUNTIME.block_on(async { self.client.subscribe(filters).await })
     }
 
-    pub fn get_events_of(&self, filters: Vec<SubscriptionFilter>) -> Result<Vec<Event>, Error> {
-        RUNTIME.block_on(async { self.client.get_events_of(filters).await })
+    pub fn add_subscription(
+        &self,
+        filters: Vec<SubscriptionFilter>,
+    ) -> Result<SubscriptionId, Error> {
+        RUNTIME.block_on(async { self.client.add_subscription(filters).await })
+    }
+
+    pub fn subscribe_with_id(
+        &self,
+        id: SubscriptionId,
+        filters: Vec<SubscriptionFilter>,
+    ) -> Result<(), Error> {
+        RUNTIME.block_on(async { self.client.subscribe_with_id(id, filters).await })
+    }
+
+    pub fn subscribe_to<S>(
+        &self,
+        urls: Vec<S>,
+        filters: Vec<SubscriptionFilter>,
+    ) -> Result<SubscriptionId, Error>
+    where
+        S: Into<String>,
+    {
+        RUNTIME.block_on(async { self.client.subscribe_to(urls, filters).await })
+    }
+
+    pub fn unsubscribe(&self, id: &SubscriptionId) -> Result<(), Error> {
+        RUNTIME.block_on(async { self.client.unsubscribe(id).await })
+    }
+
+    pub fn subscriptions(&self) -> HashMap<SubscriptionId, Vec<SubscriptionFilter>> {
+        RUNTIME.block_on(async { self.client.subscriptions().await })
+    }
+
+    pub fn get_events_of(
+        &self,
+        filters: Vec<SubscriptionFilter>,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<Event>, Error> {
+        RUNTIME.block_on(async { self.client.get_events_of(filters, timeout).await })
+    }
+
+    pub fn get_events_from<S>(
+        &self,
+        urls: Vec<S>,
+        filters: Vec<SubscriptionFilter>,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<Event>, Error>
+    where
+        S: Into<String>,
+    {
+        RUNTIME.block_on(async { self.client.get_events_from(urls, filters, timeout).await })
+    }
+
+    pub fn get_events_of_fastest(
+        &self,
+        filters: Vec<SubscriptionFilter>,
+        n: usize,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<Event>, Error> {
+        RUNTIME.block_on(async { self.client.get_events_of_fastest(filters, n, timeout).await })
     }
 
     /// Send event
+    pub fn send_client_msg(&self, msg: ClientMessage) -> Result<(), Error> {
+        RUNTIME.block_on(async { self.client.send_client_msg(msg).await })
+    }
+
+    pub fn send_client_msg_to<S>(&self, urls: Vec<S>, msg: ClientMessage) -> Result<(), Error>
+    where
+        S: Into<String>,
+    {
+        RUNTIME.block_on(async { self.client.send_client_msg_to(urls, msg).await })
+    }
+
     pub fn send_event(&self, event: Event) -> Result<(), Error> {
         RUNTIME.block_on(async { self.client.send_event(event).await })
     }
 
+    pub fn send_event_to<S>(&self, urls: Vec<S>, event: Event) -> Result<(), Error>
+    where
+        S: Into<String>,
+    {
+        RUNTIME.block_on(async { self.client.send_event_to(urls, event).await })
+    }
+
+    pub fn send_event_and_wait(
+        &self,
+        event: Event,
+        timeout: Duration,
+    ) -> Result<HashMap<Url, Result<(), RelayError>>, Error> {
+        RUNTIME.block_on(async { self.client.send_event_and_wait(event, timeout).await })
+    }
+
     pub fn update_profile(&self, metadata: Metadata) -> Result<(), Error> {
         RUNTIME.block_on(async { self.client.update_profile(metadata).await })
     }
 
+    pub fn set_status(&self, status: UserStatus) -> Result<(), Error> {
+        RUNTIME.block_on(async { self.client.set_status(status).await })
+    }
+
+    #[cfg(feature = "nip05")]
+    pub fn verify_nip05(&self, public_key: XOnlyPublicKey, nip05: &str) -> Result<(), Error> {
+        RUNTIME.block_on(async { self.client.verify_nip05(public_key, nip05).await })
+    }
+
+    pub fn set_relay_list(&self, list: Vec<(Url, Option<RelayMetadata>)>) -> Result<(), Error> {
+        RUNTIME.block_on(async { self.client.set_relay_list(list).await })
+    }
+
+    pub fn get_status_of(&self, public_key: XOnlyPublicKey) -> Result<Vec<UserStatus>, Error> {
+        RUNTIME.block_on(async { self.client.get_status_of(public_key).await })
+    }
+
+    pub fn publish_relay_discovery(&self, discovery: RelayDiscovery) -> Result<(), Error> {
+        RUNTIME.block_on(async { self.client.publish_relay_discovery(discovery).await })
+    }
+
+    pub fn get_relay_discoveries(
+        &self,
+        relay_url: &Url,
+    ) -> Result<HashMap<XOnlyPublicKey, RelayDiscovery>, Error> {
+        RUNTIME.block_on(async { self.client.get_relay_discoveries(relay_url).await })
+    }
+
+    #[cfg(feature = "nip49")]
+    pub fn export_identity<S>(&self, password: S) -> Result<IdentityBundle, Error>
+    where
+        S: Into<String>,
+    {
+        RUNTIME.block_on(async { self.client.export_identity(password).await })
+    }
+
+    pub fn get_profile(&self) -> Result<Option<Metadata>, Error> {
+        RUNTIME.block_on(async { self.client.get_profile().await })
+    }
+
+    pub fn update_metadata<F>(&self, f: F) -> Result<Metadata, Error>
+    where
+        F: FnOnce(Metadata) -> Metadata,
+    {
+        RUNTIME.block_on(async { self.client.update_metadata(f).await })
+    }
+
+    pub fn update_metadata_or_default<F>(&self, f: F) -> Result<Metadata, Error>
+    where
+        F: FnOnce(Metadata) -> Metadata,
+    {
+        RUNTIME.block_on(async { self.client.update_metadata_or_default(f).await })
+    }
+
     pub fn publish_text_note<S>(&self, content: S, tags: &[Tag]) -> Result<(), Error>
     where
         S: Into<String>,
@@ -133,6 +432,19 @@ impl Client {
         })
     }
 
+    pub fn mine_pow_event(
+        &self,
+        builder: EventBuilder,
+        difficulty: u8,
+        options: PowOptions,
+    ) -> Result<Event, Error> {
+        RUNTIME.block_on(async {
+            self.client
+                .mine_pow_event(builder, difficulty, options)
+                .await
+        })
+    }
+
     pub fn add_recommended_relay<S>(&self, url: S) -> Result<(), Error>
     where
         S: Into<String>,
@@ -156,6 +468,31 @@ impl Client {
         RUNTIME.block_on(async { self.client.send_direct_msg(recipient, msg).await })
     }
 
+    #[cfg(any(feature = "nip04", feature = "nip59"))]
+    pub fn send_direct_msg_to_many<S>(
+        &self,
+        recipients: &[Keys],
+        msg: S,
+        mode: DirectMessageMode,
+    ) -> Vec<(XOnlyPublicKey, Result<(), Error>)>
+    where
+        S: Into<String>,
+    {
+        RUNTIME.block_on(async {
+            self.client
+                .send_direct_msg_to_many(recipients, msg, mode)
+                .await
+        })
+    }
+
+    #[cfg(feature = "nip59")]
+    pub fn send_private_msg<S>(&self, receiver: XOnlyPublicKey, text: S) -> Result<(), Error>
+    where
+        S: Into<String>,
+    {
+        RUNTIME.block_on(async { self.client.send_private_msg(receiver, text).await })
+    }
+
     pub fn delete_event<S>(&self, event_id: Sha256Hash, reason: Option<S>) -> Result<(), Error>
     where
         S: Into<String>,
@@ -171,6 +508,10 @@ impl Client {
         RUNTIME.block_on(async { self.client.dislike(event).await })
     }
 
+    pub fn repost_event(&self, event: &Event, relay_url: Option<Url>) -> Result<(), Error> {
+        RUNTIME.block_on(async { self.client.repost_event(event, relay_url).await })
+    }
+
     pub fn new_channel(&self, metadata: Metadata) -> Result<(), Error> {
         RUNTIME.block_on(async { self.client.new_channel(metadata).await })
     }
@@ -230,7 +571,25 @@ impl Client {
         RUNTIME.block_on(async { self.client.get_channels().await })
     }
 
+    pub fn get_groups<S>(&self, relay_url: S) -> Result<Vec<GroupMetadata>, Error>
+    where
+        S: Into<String>,
+    {
+        RUNTIME.block_on(async { self.client.get_groups(relay_url).await })
+    }
+
+    pub fn send_group_msg<S>(&self, group_id: &GroupId, msg: S) -> Result<(), Error>
+    where
+        S: Into<String>,
+    {
+        RUNTIME.block_on(async { self.client.send_group_msg(group_id, msg).await })
+    }
+
     #[deprecated = "Use `get_entity_of` instead"]
+    pub fn submit_job(&self, job: &JobRequest, timeout: Duration) -> Result<JobResult, Error> {
+        RUNTIME.block_on(async { self.client.submit_job(job, timeout).await })
+    }
+
     pub fn get_entity_of_pubkey(&self, pubkey: XOnlyPublicKey) -> Result<Entity, Error> {
         RUNTIME.block_on(async { self.client.get_entity_of(pubkey.to_string()).await })
     }
@@ -249,3 +608,80 @@ impl Client {
         RUNTIME.block_on(async { self.client.handle_notifications(func).await })
     }
 }
+
+#[cfg(all(test, feature = "local-relay"))]
+mod tests {
+    use nostr::util::nips::nip38::StatusType;
+    use nostr::{Kind, KindBase};
+
+    use super::*;
+    use crate::local_relay::LocalRelay;
+    use crate::store::MemoryStore;
+
+    fn connect(keys: &Keys, url: &Url) -> Client {
+        let client = Client::new(keys);
+        client.add_relay(url.as_str(), None).unwrap();
+        client.connect_with_timeout(Duration::from_secs(5)).unwrap();
+        client
+    }
+
+    /// Called outside of any runtime: the facade drives its own
+    #[test]
+    fn test_blocking_client() {
+        let local_relay = RUNTIME
+            .block_on(LocalRelay::run(
+                "127.0.0.1:0".parse().unwrap(),
+                MemoryStore::new(),
+            ))
+            .unwrap();
+        let url = local_relay.url().unwrap();
+
+        let alice = Keys::generate_from_os_random();
+        let bob = Keys::generate_from_os_random();
+        let client = connect(&alice, &url);
+        let other = connect(&bob, &url);
+
+        let mut notifications = client.notifications();
+        client
+            .subscribe(vec![SubscriptionFilter::new()
+                .author(bob.public_key())
+                .kind(Kind::Base(KindBase::TextNote))])
+            .unwrap();
+
+        other.publish_text_note("Hello from bob", &[]).unwrap();
+        let note = loop {
+            match notifications.blocking_recv().unwrap() {
+                RelayPoolNotifications::ReceivedEvent { event, .. } => break *event,
+                _ => continue,
+            }
+        };
+        assert_eq!(note.content, "Hello from bob");
+
+        // Wrappers added to the facade
+        client.repost_event(&note, Some(url.clone())).unwrap();
+        let reposts = other
+            .get_events_of(
+                vec![SubscriptionFilter::new()
+                    .author(alice.public_key())
+                    .kind(Kind::Base(KindBase::Boost))],
+                Some(Duration::from_secs(5)),
+            )
+            .unwrap();
+        assert_eq!(reposts.len(), 1);
+        assert!(reposts[0]
+            .tags
+            .iter()
+            .any(|t| t.content() == Some(note.id.to_string().as_str())));
+
+        let status = UserStatus::new(StatusType::General, "Testing the blocking client");
+        client.set_status(status.clone()).unwrap();
+        assert_eq!(
+            other.get_status_of(alice.public_key()).unwrap(),
+            vec![status]
+        );
+
+        client.disconnect().unwrap();
+        other.disconnect().unwrap();
+        local_relay.shutdown();
+    }
+}
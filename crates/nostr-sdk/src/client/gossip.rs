@@ -0,0 +1,346 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, Ipv6Addr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use nostr::key::XOnlyPublicKey;
+use nostr::url::{Host, Url};
+use nostr::util::nips::nip65::{self, RelayMetadata};
+use nostr::Event;
+use tokio::sync::Mutex;
+
+/// Time after which a relay list is fetched again
+const RELAY_LIST_TTL: Duration = Duration::from_secs(60 * 60);
+/// Max time to wait for the relay lists
+pub(crate) const RELAY_LIST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Max relays used for each user
+const MAX_RELAYS_PER_USER: usize = 3;
+/// Max relays used for a single request
+const MAX_RELAYS: usize = 20;
+/// Max relays added to the pool by the gossip routing
+const MAX_POOL_RELAYS: usize = 50;
+/// Time after which an unused relay added by the gossip routing is removed from the pool
+const POOL_RELAY_IDLE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug)]
+struct RelayList {
+    /// `created_at` of the relay list event (`0` if the user hasn't published one)
+    created_at: u64,
+    relays: Vec<(Url, Option<RelayMetadata>)>,
+    fetched_at: Instant,
+}
+
+/// Relays used to reach a set of users
+#[derive(Debug, Default)]
+pub(crate) struct GossipRelays {
+    pub urls: HashSet<Url>,
+    /// `true` if some users haven't published any relay for the requested usage
+    pub missing: bool,
+}
+
+/// Cache of the NIP-65 relay lists, used by the gossip routing
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/65.md>
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Gossip {
+    lists: Arc<Mutex<HashMap<XOnlyPublicKey, RelayList>>>,
+    /// Relays added to the pool by the gossip routing, with their last use
+    pool_relays: Arc<Mutex<HashMap<Url, Instant>>>,
+}
+
+/// Check if `url` can be used by the gossip routing: only `wss` relays with a public host
+///
+/// Relay lists are published by anyone, so local and private addresses are rejected.
+fn is_allowed_url(url: &Url) -> bool {
+    if url.scheme() != "wss" {
+        return false;
+    }
+
+    match url.host() {
+        Some(Host::Domain(domain)) => {
+            let domain = domain.trim_end_matches('.').to_lowercase();
+            domain != "localhost" && !domain.ends_with(".localhost") && !domain.ends_with(".local")
+        }
+        Some(Host::Ipv4(ip)) => is_public_ip(IpAddr::V4(ip)),
+        Some(Host::Ipv6(ip)) => is_public_ip(IpAddr::V6(ip)),
+        None => false,
+    }
+}
+
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                // Shared address space (100.64.0.0/10)
+                || (ip.octets()[0] == 100 && (ip.octets()[1] & 0xc0) == 64))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_ip(IpAddr::V4(ip)),
+            None => !(ip.is_loopback() || ip.is_unspecified() || is_local_ipv6(&ip)),
+        },
+    }
+}
+
+/// Unique local (`fc00::/7`) and link-local (`fe80::/10`) addresses
+fn is_local_ipv6(ip: &Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    (first & 0xfe00) == 0xfc00 || (first & 0xffc0) == 0xfe80
+}
+
+impl Gossip {
+    /// Get the public keys whose relay list isn't cached (or is expired)
+    pub async fn outdated(&self, public_keys: &HashSet<XOnlyPublicKey>) -> HashSet<XOnlyPublicKey> {
+        let lists = self.lists.lock().await;
+        public_keys
+            .iter()
+            .filter(|pk| match lists.get(pk) {
+                Some(list) => list.fetched_at.elapsed() > RELAY_LIST_TTL,
+                None => true,
+            })
+            .copied()
+            .collect()
+    }
+
+    /// Update the relay lists of `public_keys` with the fetched events
+    ///
+    /// Users without a relay list are cached too, to not fetch it at every request.
+    pub async fn update(&self, public_keys: &HashSet<XOnlyPublicKey>, events: Vec<Event>) {
+        let mut lists = self.lists.lock().await;
+        let now = Instant::now();
+
+        for pk in public_keys.iter() {
+            let list = lists.entry(*pk).or_insert(RelayList {
+                created_at: 0,
+                relays: Vec::new(),
+                fetched_at: now,
+            });
+            list.fetched_at = now;
+        }
+
+        for event in events.into_iter() {
+            if let Some(list) = lists.get_mut(&event.pubkey) {
+                if event.created_at > list.created_at {
+                    list.created_at = event.created_at;
+                    list.relays = nip65::extract_relay_list(&event)
+                        .into_iter()
+                        .filter(|(url, _)| is_allowed_url(url))
+                        .collect();
+                }
+            }
+        }
+    }
+
+    /// Get the relays of `public_keys` for `metadata` (relays without marker are always included)
+    ///
+    /// At most [`MAX_RELAYS_PER_USER`] relays are used for each user and [`MAX_RELAYS`] in total.
+    pub async fn relays(
+        &self,
+        public_keys: &HashSet<XOnlyPublicKey>,
+        metadata: RelayMetadata,
+    ) -> GossipRelays {
+        let lists = self.lists.lock().await;
+        let mut relays = GossipRelays::default();
+
+        for pk in public_keys.iter() {
+            let urls: Vec<&Url> = lists
+                .get(pk)
+                .map(|list| {
+                    list.relays
+                        .iter()
+                        .filter(|(_, m)| m.is_none() || *m == Some(metadata))
+                        .map(|(url, _)| url)
+                        .take(MAX_RELAYS_PER_USER)
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            let mut reached = false;
+            for url in urls.into_iter() {
+                if relays.urls.contains(url) {
+                    reached = true;
+                } else if relays.urls.len() < MAX_RELAYS {
+                    relays.urls.insert(url.clone());
+                    reached = true;
+                }
+            }
+
+            if !reached {
+                relays.missing = true;
+            }
+        }
+
+        relays
+    }
+
+    /// Mark `urls` as used, tracking the relays in `added` as added to the pool by the gossip routing
+    pub async fn use_pool_relays(&self, urls: &HashSet<Url>, added: Vec<Url>) {
+        let mut pool_relays = self.pool_relays.lock().await;
+        let now = Instant::now();
+        for url in added.into_iter() {
+            pool_relays.insert(url, now);
+        }
+        for url in urls.iter() {
+            if let Some(last_use) = pool_relays.get_mut(url) {
+                *last_use = now;
+            }
+        }
+    }
+
+    /// Get the relays added by the gossip routing that are no longer needed: the ones unused
+    /// for [`POOL_RELAY_IDLE_TIMEOUT`] and the least recently used above [`MAX_POOL_RELAYS`]
+    pub async fn unneeded_pool_relays(&self, in_use: &HashSet<Url>) -> Vec<Url> {
+        let pool_relays = self.pool_relays.lock().await;
+        let mut relays: Vec<(&Url, &Instant)> = pool_relays
+            .iter()
+            .filter(|(url, _)| !in_use.contains(*url))
+            .collect();
+        relays.sort_by_key(|(_, last_use)| **last_use);
+
+        let excess = pool_relays.len().saturating_sub(MAX_POOL_RELAYS);
+        relays
+            .into_iter()
+            .enumerate()
+            .filter(|(i, (_, last_use))| {
+                *i < excess || last_use.elapsed() > POOL_RELAY_IDLE_TIMEOUT
+            })
+            .map(|(_, (url, _))| url.clone())
+            .collect()
+    }
+
+    /// Stop tracking a relay added by the gossip routing
+    pub async fn remove_pool_relay(&self, url: &Url) {
+        self.pool_relays.lock().await.remove(url);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys};
+
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    type UserRelays = (Keys, Vec<(Url, Option<RelayMetadata>)>);
+
+    async fn gossip_with(list: Vec<UserRelays>) -> Gossip {
+        let gossip = Gossip::default();
+        let mut public_keys = HashSet::new();
+        let mut events = Vec::new();
+        for (keys, relays) in list.into_iter() {
+            public_keys.insert(keys.public_key());
+            events.push(EventBuilder::relay_list(&relays).to_event(&keys).unwrap());
+        }
+        gossip.update(&public_keys, events).await;
+        gossip
+    }
+
+    #[test]
+    fn test_allowed_url() {
+        assert!(is_allowed_url(&url("wss://relay.damus.io")));
+        assert!(is_allowed_url(&url("wss://8.8.8.8")));
+        assert!(is_allowed_url(&url("wss://[2001:4860::8888]")));
+
+        assert!(!is_allowed_url(&url("ws://relay.damus.io")));
+        assert!(!is_allowed_url(&url("https://relay.damus.io")));
+        assert!(!is_allowed_url(&url("wss://localhost:7777")));
+        assert!(!is_allowed_url(&url("wss://relay.localhost")));
+        assert!(!is_allowed_url(&url("wss://printer.local")));
+        assert!(!is_allowed_url(&url("wss://127.0.0.1")));
+        assert!(!is_allowed_url(&url("wss://10.0.0.1")));
+        assert!(!is_allowed_url(&url("wss://192.168.1.1")));
+        assert!(!is_allowed_url(&url("wss://169.254.169.254")));
+        assert!(!is_allowed_url(&url("wss://100.64.0.1")));
+        assert!(!is_allowed_url(&url("wss://0.0.0.0")));
+        assert!(!is_allowed_url(&url("wss://[::1]")));
+        assert!(!is_allowed_url(&url("wss://[fd00::1]")));
+        assert!(!is_allowed_url(&url("wss://[fe80::1]")));
+        assert!(!is_allowed_url(&url("wss://[::ffff:127.0.0.1]")));
+    }
+
+    #[tokio::test]
+    async fn test_reject_local_relays() {
+        let keys = Keys::generate_from_os_random();
+        let gossip = gossip_with(vec![(
+            keys.clone(),
+            vec![
+                (url("wss://127.0.0.1"), None),
+                (url("ws://relay.example.com"), None),
+            ],
+        )])
+        .await;
+
+        let relays = gossip
+            .relays(&HashSet::from([keys.public_key()]), RelayMetadata::Write)
+            .await;
+        assert!(relays.urls.is_empty());
+        assert!(relays.missing);
+    }
+
+    #[tokio::test]
+    async fn test_relays_limits() {
+        let mut list = Vec::new();
+        for i in 0..10 {
+            let relays = (0..5)
+                .map(|j| (url(&format!("wss://relay{}-{}.example.com", i, j)), None))
+                .collect();
+            list.push((Keys::generate_from_os_random(), relays));
+        }
+        let public_keys: HashSet<XOnlyPublicKey> =
+            list.iter().map(|(keys, _)| keys.public_key()).collect();
+        let gossip = gossip_with(list.clone()).await;
+
+        let relays = gossip.relays(&public_keys, RelayMetadata::Read).await;
+        assert_eq!(relays.urls.len(), MAX_RELAYS);
+        assert!(relays.missing);
+
+        let (keys, user_relays) = &list[0];
+        let relays = gossip
+            .relays(&HashSet::from([keys.public_key()]), RelayMetadata::Read)
+            .await;
+        assert_eq!(relays.urls.len(), MAX_RELAYS_PER_USER);
+        for (url, _) in user_relays.iter().take(MAX_RELAYS_PER_USER) {
+            assert!(relays.urls.contains(url));
+        }
+        assert!(!relays.missing);
+    }
+
+    #[tokio::test]
+    async fn test_unneeded_pool_relays() {
+        let gossip = Gossip::default();
+        let added: Vec<Url> = (0..MAX_POOL_RELAYS + 2)
+            .map(|i| url(&format!("wss://relay{}.example.com", i)))
+            .collect();
+        for url in added.iter() {
+            gossip
+                .use_pool_relays(&HashSet::new(), vec![url.clone()])
+                .await;
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+
+        // The least recently used relays above the limit
+        let unneeded = gossip.unneeded_pool_relays(&HashSet::new()).await;
+        assert_eq!(unneeded, added[..2].to_vec());
+
+        // Relays in use are kept
+        let in_use = HashSet::from([added[0].clone()]);
+        let unneeded = gossip.unneeded_pool_relays(&in_use).await;
+        assert_eq!(unneeded, added[1..3].to_vec());
+
+        gossip.remove_pool_relay(&added[0]).await;
+        gossip.remove_pool_relay(&added[1]).await;
+        assert!(gossip
+            .unneeded_pool_relays(&HashSet::new())
+            .await
+            .is_empty());
+    }
+}
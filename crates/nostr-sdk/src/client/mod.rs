@@ -1,24 +1,70 @@
 // Copyright (c) 2022 Yuki Kishimoto
 // Distributed under the MIT software license
 
-use std::collections::HashMap;
+use std::cmp::Reverse;
+#[cfg(feature = "nip49")]
+use std::collections::BTreeSet;
+use std::collections::{HashMap, HashSet};
+#[cfg(feature = "nip11")]
+use std::future::Future;
 use std::net::SocketAddr;
 use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
+use futures_util::future;
+use futures_util::stream::{self, Stream, StreamExt};
 use nostr::event::builder::Error as EventBuilderError;
+#[cfg(feature = "nip04")]
+use nostr::event::TagData;
+use nostr::event::TagKind;
 use nostr::key::XOnlyPublicKey;
+use nostr::signer::Error as SignerError;
 use nostr::url::Url;
+#[cfg(feature = "nip49")]
+use nostr::util::backup::IdentityBundle;
+#[cfg(feature = "nip05")]
+use nostr::util::nips::nip05;
+#[cfg(feature = "nip11")]
+use nostr::util::nips::nip11::RelayInformationDocument;
+use nostr::util::nips::nip13::PowOptions;
+use nostr::util::nips::nip29::{GroupId, GroupMetadata};
+use nostr::util::nips::nip38::UserStatus;
+#[cfg(feature = "nip59")]
+use nostr::util::nips::nip59;
+use nostr::util::nips::nip65::RelayMetadata;
+use nostr::util::nips::nip66::RelayDiscovery;
+use nostr::util::nips::nip90::{self, JobRequest, JobResult};
 use nostr::{
     ClientMessage, Contact, Entity, Event, EventBuilder, Keys, Kind, KindBase, Metadata,
-    Sha256Hash, SubscriptionFilter, Tag,
+    NostrSigner, Sha256Hash, SubscriptionFilter, Tag,
 };
-use tokio::sync::broadcast;
+use tokio::sync::broadcast::{self, error::RecvError};
+use uuid::Uuid;
 
 #[cfg(feature = "blocking")]
 pub mod blocking;
+mod gossip;
+mod options;
 
-use crate::relay::pool::{Error as RelayPoolError, RelayPool, RelayPoolNotifications};
-use crate::Relay;
+use self::gossip::{Gossip, GossipRelays, RELAY_LIST_TIMEOUT};
+pub use self::options::Options;
+
+#[cfg(feature = "nip46")]
+use crate::nip46::{Error as Nip46Error, Nip46Signer};
+use crate::outbox::Outbox;
+use crate::relay::pool::{
+    ConnectionReport, Error as RelayPoolError, EventSource, RelayPool, RelayPoolNotifications,
+};
+use crate::relay::Error as RelayError;
+#[cfg(feature = "nip11")]
+use crate::relay::PaymentHandler;
+use crate::sink::NotificationSink;
+use crate::subscription::SubscriptionId;
+use crate::{Relay, RelayOptions, RelayStats, RelayStatus, RelayTransport, TlsOptions};
+
+/// Timeout of the queries for the user own events (i.e. profile, lists)
+const OWN_EVENTS_TIMEOUT: Duration = Duration::from_secs(10);
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -35,17 +81,108 @@ pub enum Error {
     Secp256k1(#[from] nostr::secp256k1::Error),
     #[error("hex decoding error: {0}")]
     Hex(#[from] nostr::hashes::hex::Error),
+    /// Metadata error
+    #[error("metadata error: {0}")]
+    Metadata(#[from] nostr::metadata::Error),
+    /// Identity backup error
+    #[cfg(feature = "nip49")]
+    #[error("backup error: {0}")]
+    Backup(#[from] nostr::util::backup::Error),
+    /// NIP-05 error
+    #[cfg(feature = "nip05")]
+    #[error("nip05 error: {0}")]
+    NIP05(#[from] nip05::Error),
+    /// NIP-46 remote signer error
+    #[cfg(feature = "nip46")]
+    #[error("nip46 error: {0}")]
+    Nip46(#[from] Nip46Error),
+    /// NIP-59 gift wrap error
+    #[cfg(feature = "nip59")]
+    #[error("nip59 error: {0}")]
+    NIP59(#[from] nip59::Error),
+    /// NIP-90 error
+    #[error("nip90 error: {0}")]
+    NIP90(#[from] nip90::Error),
+    /// Task join error
+    #[error("join error: {0}")]
+    Join(#[from] tokio::task::JoinError),
+    /// No response in time
+    #[error("timeout")]
+    Timeout,
+    /// No relay has the profile metadata
+    #[error("metadata not found")]
+    MetadataNotFound,
+    /// Signer error
+    #[error("signer error: {0}")]
+    Signer(#[from] SignerError),
+    /// Operation not supported with an external signer (i.e. NIP-46 remote signer)
+    #[error("operation not supported by the remote signer")]
+    UnsupportedByRemoteSigner,
+}
+
+fn parse_urls<S>(urls: Vec<S>) -> Result<Vec<Url>, Error>
+where
+    S: Into<String>,
+{
+    urls.into_iter()
+        .map(|url| Ok(Url::parse(&url.into())?))
+        .collect()
+}
+
+/// Active identity of the client
+#[derive(Debug, Clone)]
+struct Identity {
+    keys: Keys,
+    signer: Arc<dyn NostrSigner>,
+    /// `true` if the events are signed by an external signer instead of `keys`
+    #[cfg_attr(not(any(feature = "nip49", feature = "nip59")), allow(dead_code))]
+    external_signer: bool,
+    #[cfg(feature = "nip46")]
+    remote_signer: Option<Nip46Signer>,
+}
+
+impl Identity {
+    fn new(keys: &Keys) -> Self {
+        Self {
+            keys: keys.clone(),
+            signer: Arc::new(keys.clone()),
+            external_signer: false,
+            #[cfg(feature = "nip46")]
+            remote_signer: None,
+        }
+    }
+}
+
+/// Protocol of the direct messages sent by [`Client::send_direct_msg_to_many`]
+#[cfg(any(feature = "nip04", feature = "nip59"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DirectMessageMode {
+    /// NIP-04 encrypted direct message (kind 4)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/04.md>
+    #[cfg(feature = "nip04")]
+    Nip04,
+    /// NIP-17 private direct message, gift wrapped (kind 1059)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/17.md>
+    #[cfg(feature = "nip59")]
+    Nip17,
 }
 
 #[derive(Debug, Clone)]
 pub struct Client {
     pool: RelayPool,
-    keys: Keys,
+    identity: Arc<RwLock<Identity>>,
+    opts: Options,
+    gossip: Gossip,
 }
 
 impl Client {
     /// Create a new `Client`
     ///
+    /// With the `nip59` feature, the received NIP-17 private direct messages are automatically
+    /// unwrapped and notified with [`RelayPoolNotifications::PrivateDirectMessage`].
+    ///
     /// # Example
     /// ```rust,no_run
     /// use nostr_sdk::Client;
@@ -54,73 +191,147 @@ impl Client {
     /// let client = Client::new(&my_keys);
     /// ```
     pub fn new(keys: &Keys) -> Self {
+        Self::new_with_opts(keys, Options::default())
+    }
+
+    /// Create a new `Client` with custom options
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    ///
+    /// use nostr_sdk::{Client, Options};
+    ///
+    /// let my_keys = Client::generate_keys();
+    /// let opts = Options::new()
+    ///     .wait_for_send(true)
+    ///     .connection_timeout(Duration::from_secs(10))
+    ///     .difficulty(16);
+    /// let client = Client::new_with_opts(&my_keys, opts);
+    /// ```
+    pub fn new_with_opts(keys: &Keys, opts: Options) -> Self {
+        #[cfg(feature = "nip59")]
+        let unwrap_keys = Some(keys.clone());
+        #[cfg(not(feature = "nip59"))]
+        let unwrap_keys = None;
+
+        let auth_keys = opts.get_auto_authenticate().then(|| keys.clone());
+
         Self {
-            pool: RelayPool::new(),
-            keys: keys.clone(),
+            pool: RelayPool::build(unwrap_keys, auth_keys, opts.get_pool_opts()),
+            identity: Arc::new(RwLock::new(Identity::new(keys))),
+            opts,
+            gossip: Gossip::default(),
         }
     }
 
-    /// Generate new random keys using entorpy from OS
-    pub fn generate_keys() -> Keys {
-        Keys::generate_from_os_random()
+    /// Create a new `Client` that signs the events and encrypts the messages with an external signer
+    ///
+    /// The `app_keys` are used for everything else (i.e. relay authentication) and are returned
+    /// by [`Client::keys`]. Operations that need the user secret key
+    /// (i.e. NIP-59 gift wraps) return [`Error::UnsupportedByRemoteSigner`].
+    pub fn with_signer<T>(app_keys: &Keys, signer: T, opts: Options) -> Self
+    where
+        T: NostrSigner + 'static,
+    {
+        Self::with_identity(
+            Identity {
+                keys: app_keys.clone(),
+                signer: Arc::new(signer),
+                external_signer: true,
+                #[cfg(feature = "nip46")]
+                remote_signer: None,
+            },
+            opts,
+        )
     }
 
-    /// Get current [`Keys`]
-    pub fn keys(&self) -> Keys {
-        self.keys.clone()
+    /// Create a new `Client` that signs the events with a NIP-46 remote signer
+    ///
+    /// The `app_keys` are the ones used to talk with the remote signer (see [`Nip46Signer`])
+    /// and are returned by [`Client::keys`]. Call [`Nip46Signer::connect`] before using the client.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/46.md>
+    #[cfg(feature = "nip46")]
+    pub fn with_remote_signer(app_keys: &Keys, signer: Nip46Signer, opts: Options) -> Self {
+        Self::with_identity(
+            Identity {
+                keys: app_keys.clone(),
+                signer: Arc::new(signer.clone()),
+                external_signer: true,
+                remote_signer: Some(signer),
+            },
+            opts,
+        )
     }
 
-    /// Get new notification listener
-    pub fn notifications(&self) -> broadcast::Receiver<RelayPoolNotifications> {
-        self.pool.notifications()
-    }
+    fn with_identity(identity: Identity, opts: Options) -> Self {
+        // Gift wraps can't be unwrapped without the user secret key
+        let auth_keys = opts.get_auto_authenticate().then(|| identity.keys.clone());
 
-    /// Get relays
-    pub async fn relays(&self) -> HashMap<Url, Relay> {
-        self.pool.relays().await
+        Self {
+            pool: RelayPool::build(None, auth_keys, opts.get_pool_opts()),
+            identity: Arc::new(RwLock::new(identity)),
+            opts,
+            gossip: Gossip::default(),
+        }
     }
 
-    /// Add multiple relays
-    pub async fn add_relays<S>(&self, relays: Vec<(S, Option<SocketAddr>)>) -> Result<(), Error>
-    where
-        S: Into<String>,
-    {
-        for (url, proxy) in relays.into_iter() {
-            self.add_relay(url, proxy).await?;
-        }
-        Ok(())
+    /// Generate new random keys using entorpy from OS
+    pub fn generate_keys() -> Keys {
+        Keys::generate_from_os_random()
     }
 
-    /// Add new relay
+    /// Restore an identity exported with [`Client::export_identity`]
+    ///
+    /// Create a new `Client` with the decrypted keys and the relays of the bundle.
+    /// The events of the bundle can be published again with [`Client::send_event`].
     ///
     /// # Example
     /// ```rust,no_run
-    /// # use nostr_sdk::Client;
+    /// use nostr::util::backup::IdentityBundle;
+    /// use nostr_sdk::Client;
     ///
     /// # #[tokio::main]
     /// # async fn main() {
-    /// #   let my_keys = Client::generate_keys();
-    /// #   let client = Client::new(&my_keys);
-    /// client
-    ///     .add_relay("wss://relay.nostr.info", None)
-    ///     .await
-    ///     .unwrap();
-    /// client
-    ///     .add_relay("wss://relay.damus.io", None)
-    ///     .await
-    ///     .unwrap();
+    /// # let json = String::new();
+    /// let bundle = IdentityBundle::from_json(json).unwrap();
+    /// let client = Client::import_identity(&bundle, "password").await.unwrap();
     /// # }
     /// ```
-    pub async fn add_relay<S>(&self, url: S, proxy: Option<SocketAddr>) -> Result<(), Error>
+    #[cfg(feature = "nip49")]
+    pub async fn import_identity<S>(bundle: &IdentityBundle, password: S) -> Result<Self, Error>
     where
         S: Into<String>,
     {
-        let url = Url::parse(&url.into())?;
-        self.pool.add_relay(url, proxy).await;
-        Ok(())
+        let keys: Keys = bundle.keys(&password.into())?;
+        let client = Self::new(&keys);
+        for url in bundle.relays.iter() {
+            client.pool.add_relay(url.clone(), None).await;
+        }
+        Ok(client)
+    }
+
+    fn identity(&self) -> Identity {
+        match self.identity.read() {
+            Ok(identity) => identity.clone(),
+            Err(e) => e.into_inner().clone(),
+        }
+    }
+
+    /// Get current [`Keys`]
+    pub fn keys(&self) -> Keys {
+        self.identity().keys
     }
 
-    /// Disconnect and remove relay
+    /// Switch the active identity to `keys`
+    ///
+    /// The next events are signed with `keys` (replacing any external signer), the subscription
+    /// filters that reference the previous public key (`authors` and `#p`) are updated and sent
+    /// again to the relays, and the relay authentication and the gift wrap unwrapping
+    /// use the new keys.
+    ///
+    /// See [`Accounts`](crate::Accounts) to manage multiple named identities.
     ///
     /// # Example
     /// ```rust,no_run
@@ -130,107 +341,347 @@ impl Client {
     /// # async fn main() {
     /// #   let my_keys = Client::generate_keys();
     /// #   let client = Client::new(&my_keys);
-    /// client.remove_relay("wss://relay.nostr.info").await.unwrap();
+    /// let other_keys = Client::generate_keys();
+    /// client.switch_keys(&other_keys).await.unwrap();
     /// # }
     /// ```
-    pub async fn remove_relay<S>(&self, url: S) -> Result<(), Error>
-    where
-        S: Into<String>,
-    {
-        let url = Url::parse(&url.into())?;
-        self.pool.remove_relay(url).await;
+    pub async fn switch_keys(&self, keys: &Keys) -> Result<(), Error> {
+        // The previous signer may be unreachable (i.e. disconnected remote signer)
+        let old_public_key: Option<XOnlyPublicKey> = self.public_key().await.ok();
+
+        match self.identity.write() {
+            Ok(mut identity) => *identity = Identity::new(keys),
+            Err(e) => *e.into_inner() = Identity::new(keys),
+        }
+
+        if self.pool.auth_keys().await.is_some() {
+            self.pool.set_auth_keys(Some(keys.clone())).await;
+        }
+
+        #[cfg(feature = "nip59")]
+        self.pool.set_unwrap_keys(Some(keys.clone())).await;
+
+        if let Some(old_public_key) = old_public_key {
+            self.pool
+                .replace_public_key(old_public_key, keys.public_key())
+                .await?;
+        }
+
         Ok(())
     }
 
-    /// Connect relay
+    /// Get the NIP-46 remote signer, if any
+    #[cfg(feature = "nip46")]
+    pub fn remote_signer(&self) -> Option<Nip46Signer> {
+        self.identity().remote_signer
+    }
+
+    /// Get the [`NostrSigner`] of the user events
+    pub fn signer(&self) -> Arc<dyn NostrSigner> {
+        self.identity().signer
+    }
+
+    /// Get the public key of the user (the signer one)
+    async fn public_key(&self) -> Result<XOnlyPublicKey, Error> {
+        Ok(self.signer().public_key().await?)
+    }
+
+    /// Get options
+    pub fn opts(&self) -> Options {
+        self.opts
+    }
+
+    /// Build and sign event with the signer, mining it if a POW difficulty is set
+    async fn sign_event_builder(&self, builder: EventBuilder) -> Result<Event, Error> {
+        match self.opts.get_difficulty() {
+            0 => Ok(builder.sign(self.signer().as_ref()).await?),
+            difficulty => {
+                self.mine_pow_event(builder, difficulty, PowOptions::default())
+                    .await
+            }
+        }
+    }
+
+    /// Get the NIP-65 relays of `public_keys` for `metadata`, adding the missing ones to the pool
+    async fn gossip_relays(
+        &self,
+        public_keys: &HashSet<XOnlyPublicKey>,
+        metadata: RelayMetadata,
+    ) -> Result<GossipRelays, Error> {
+        let outdated = self.gossip.outdated(public_keys).await;
+        if !outdated.is_empty() {
+            let filter = SubscriptionFilter::new()
+                .authors(outdated.iter().copied().collect())
+                .kind(Kind::Base(KindBase::RelayList));
+            let events = self
+                .pool
+                .get_events_of(vec![filter], Some(RELAY_LIST_TIMEOUT))
+                .await?;
+            self.gossip.update(&outdated, events).await;
+        }
+
+        let relays = self.gossip.relays(public_keys, metadata).await;
+
+        let pool_relays = self.pool.relays().await;
+        let mut added: Vec<Url> = Vec::new();
+        for url in relays.urls.iter() {
+            if !pool_relays.contains_key(url) {
+                // Used only by the gossip routing
+                self.pool
+                    .add_relay_with_opts(url.clone(), None, RelayOptions::new(false, false))
+                    .await;
+                added.push(url.clone());
+                if let Some(relay) = self.pool.relays().await.get(url) {
+                    self.pool.connect_relay(relay, false).await?;
+                }
+            }
+        }
+        self.gossip.use_pool_relays(&relays.urls, added).await;
+        self.remove_unneeded_gossip_relays(&relays.urls).await;
+
+        Ok(relays)
+    }
+
+    /// Remove from the pool the relays added by the gossip routing that are no longer needed
     ///
-    /// # Example
-    /// ```rust,no_run
-    /// # use nostr_sdk::Client;
+    /// Relays targeted by a subscription, or that the user has made read/write relays, are kept.
+    async fn remove_unneeded_gossip_relays(&self, in_use: &HashSet<Url>) {
+        let unneeded = self.gossip.unneeded_pool_relays(in_use).await;
+        if unneeded.is_empty() {
+            return;
+        }
+
+        let pool_relays = self.pool.relays().await;
+        let subscribed = self.pool.subscriptions_relays().await;
+        for url in unneeded.into_iter() {
+            if subscribed.contains(&url) {
+                continue;
+            }
+
+            let gossip_only = pool_relays
+                .get(&url)
+                .map(|relay| !relay.opts().read() && !relay.opts().write())
+                .unwrap_or(false);
+            if gossip_only {
+                if let Err(e) = self.pool.remove_relay(url.clone()).await {
+                    log::error!("Impossible to remove gossip relay {}: {}", url, e);
+                    continue;
+                }
+            }
+            self.gossip.remove_pool_relay(&url).await;
+        }
+    }
+
+    /// Get the relays to send the filters to with the gossip routing: the write relays
+    /// of the `authors` and the read relays of the tagged users (`#p`)
     ///
-    /// # #[tokio::main]
-    /// # async fn main() {
-    /// #   let my_keys = Client::generate_keys();
-    /// #   let client = Client::new(&my_keys);
-    /// client
-    ///     .connect_relay("wss://relay.nostr.info", true)
-    ///     .await
-    ///     .unwrap();
-    /// # }
-    /// ```
-    pub async fn connect_relay<S>(&self, url: S, wait_for_connection: bool) -> Result<(), Error>
-    where
-        S: Into<String>,
-    {
-        let url = Url::parse(&url.into())?;
-        if let Some(relay) = self.pool.relays().await.get(&url) {
-            return Ok(self.pool.connect_relay(relay, wait_for_connection).await?);
+    /// The read relays of the client are included for the filters (or the users) that can't be routed.
+    /// Return `None` if the gossip routing is disabled or no filter can be routed.
+    async fn gossip_filters_relays(
+        &self,
+        filters: &[SubscriptionFilter],
+    ) -> Result<Option<Vec<Url>>, Error> {
+        if !self.opts.get_gossip() {
+            return Ok(None);
         }
-        Err(Error::RelayNotFound)
+
+        let mut authors: HashSet<XOnlyPublicKey> = HashSet::new();
+        let mut tagged: HashSet<XOnlyPublicKey> = HashSet::new();
+        let mut missing = false;
+        for filter in filters.iter() {
+            let filter_authors = filter.authors.clone().unwrap_or_default();
+            let filter_tagged = filter.pubkeys.clone().unwrap_or_default();
+            if filter_authors.is_empty() && filter_tagged.is_empty() {
+                missing = true;
+            }
+
+            for author in filter_authors.iter() {
+                // Public key prefixes can't be routed
+                match XOnlyPublicKey::from_str(author.as_str()) {
+                    Ok(pk) => {
+                        authors.insert(pk);
+                    }
+                    Err(_) => missing = true,
+                }
+            }
+            tagged.extend(filter_tagged);
+        }
+
+        if authors.is_empty() && tagged.is_empty() {
+            return Ok(None);
+        }
+
+        let write = self.gossip_relays(&authors, RelayMetadata::Write).await?;
+        let read = self.gossip_relays(&tagged, RelayMetadata::Read).await?;
+
+        let mut urls: HashSet<Url> = write.urls;
+        urls.extend(read.urls);
+        if missing || write.missing || read.missing {
+            for relay in self.pool.relays().await.into_values() {
+                if relay.opts().read() {
+                    urls.insert(relay.url());
+                }
+            }
+        }
+
+        if urls.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(urls.into_iter().collect()))
     }
 
-    /// Disconnect relay
+    /// Get the read relays of the users tagged in `event` (`p` tags) that aren't write relays
+    /// of the client, if the gossip routing is enabled
+    async fn gossip_event_relays(&self, event: &Event) -> Result<Vec<Url>, Error> {
+        if !self.opts.get_gossip() {
+            return Ok(Vec::new());
+        }
+
+        let tagged: HashSet<XOnlyPublicKey> = event
+            .tags
+            .iter()
+            .filter(|tag| matches!(tag.kind(), Ok(TagKind::P)))
+            .filter_map(|tag| XOnlyPublicKey::from_str(tag.content()?).ok())
+            .filter(|pk| pk != &event.pubkey)
+            .collect();
+        if tagged.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let relays = self.gossip_relays(&tagged, RelayMetadata::Read).await?;
+        let pool_relays = self.pool.relays().await;
+        Ok(relays
+            .urls
+            .into_iter()
+            .filter(|url| {
+                pool_relays
+                    .get(url)
+                    .map(|relay| !relay.opts().write())
+                    .unwrap_or(true)
+            })
+            .collect())
+    }
+
+    /// Get new notification listener
+    pub fn notifications(&self) -> broadcast::Receiver<RelayPoolNotifications> {
+        self.pool.notifications()
+    }
+
+    /// Get new notification listener as a [`Stream`]
+    ///
+    /// The notifications lost by a slow consumer are skipped.
     ///
     /// # Example
     /// ```rust,no_run
     /// # use nostr_sdk::Client;
+    /// use futures_util::{future, StreamExt};
+    /// use nostr_sdk::RelayPoolNotifications;
     ///
     /// # #[tokio::main]
     /// # async fn main() {
     /// #   let my_keys = Client::generate_keys();
     /// #   let client = Client::new(&my_keys);
-    /// client
-    ///     .disconnect_relay("wss://relay.nostr.info")
-    ///     .await
-    ///     .unwrap();
+    /// let mut messages = client.notifications_stream().filter_map(|notification| {
+    ///     future::ready(match notification {
+    ///         RelayPoolNotifications::Message { relay_url, message } => Some((relay_url, message)),
+    ///         _ => None,
+    ///     })
+    /// });
+    ///
+    /// while let Some((relay_url, message)) = messages.next().await {
+    ///     println!("{}: {:?}", relay_url, message);
+    /// }
     /// # }
     /// ```
-    pub async fn disconnect_relay<S>(&self, url: S) -> Result<(), Error>
-    where
-        S: Into<String>,
-    {
-        let url = Url::parse(&url.into())?;
-        if let Some(relay) = self.pool.relays().await.get(&url) {
-            return Ok(self.pool.disconnect_relay(relay).await?);
-        }
-        Err(Error::RelayNotFound)
+    pub fn notifications_stream(
+        &self,
+    ) -> impl Stream<Item = RelayPoolNotifications> + Send + Unpin {
+        stream::unfold(self.notifications(), |mut notifications| async move {
+            loop {
+                match notifications.recv().await {
+                    Ok(notification) => return Some((notification, notifications)),
+                    Err(RecvError::Lagged(n)) => {
+                        log::warn!("Notification stream lagged: {} notifications skipped", n)
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        })
+        .boxed()
     }
 
-    /// Connect to all added relays without waiting for connection and keep connection alive
+    /// Get the new events matching `filters` as a [`Stream`]
+    ///
+    /// Only the events received for the active subscriptions are yielded, once:
+    /// subscribe to the filters too.
     ///
     /// # Example
     /// ```rust,no_run
     /// # use nostr_sdk::Client;
+    /// use futures_util::StreamExt;
+    /// use nostr::{Kind, KindBase, SubscriptionFilter};
     ///
     /// # #[tokio::main]
     /// # async fn main() {
     /// #   let my_keys = Client::generate_keys();
     /// #   let client = Client::new(&my_keys);
-    /// client.connect().await.unwrap();
+    /// let filter = SubscriptionFilter::new().kind(Kind::Base(KindBase::TextNote));
+    /// client.subscribe(vec![filter.clone()]).await.unwrap();
+    ///
+    /// let mut notes = client.events_of(vec![filter]).take(10);
+    /// while let Some(event) = notes.next().await {
+    ///     println!("{}", event.content);
+    /// }
     /// # }
     /// ```
-    pub async fn connect(&self) -> Result<(), Error> {
-        Ok(self.pool.connect(false).await?)
+    pub fn events_of(
+        &self,
+        filters: Vec<SubscriptionFilter>,
+    ) -> impl Stream<Item = Event> + Send + Unpin {
+        self.notifications_stream()
+            .filter_map(move |notification| {
+                let event = match notification {
+                    RelayPoolNotifications::ReceivedEvent { event, .. }
+                        if filters.iter().any(|f| f.match_event(&event)) =>
+                    {
+                        Some(*event)
+                    }
+                    _ => None,
+                };
+                future::ready(event)
+            })
+            .boxed()
     }
 
-    /// Connect to all added relays waiting for initial connection and keep connection alive
+    /// Forward notifications to a sink (channel, async callback or webhook)
     ///
     /// # Example
     /// ```rust,no_run
     /// # use nostr_sdk::Client;
+    /// use nostr_sdk::NotificationSink;
     ///
     /// # #[tokio::main]
     /// # async fn main() {
     /// #   let my_keys = Client::generate_keys();
     /// #   let client = Client::new(&my_keys);
-    /// client.connect_and_wait().await.unwrap();
+    /// let (sender, mut receiver) = tokio::sync::mpsc::channel(1024);
+    /// client.add_notification_sink(NotificationSink::Channel(sender));
+    ///
+    /// client.add_notification_sink(NotificationSink::callback(|notification| async move {
+    ///     println!("{:?}", notification);
+    /// }));
+    ///
+    /// while let Some(notification) = receiver.recv().await {
+    ///     println!("{:?}", notification);
+    /// }
     /// # }
     /// ```
-    pub async fn connect_and_wait(&self) -> Result<(), Error> {
-        Ok(self.pool.connect(true).await?)
+    pub fn add_notification_sink(&self, sink: NotificationSink) {
+        self.pool.add_notification_sink(sink)
     }
 
-    /// Disconnect from all relays
+    /// Get relays
     ///
     /// # Example
     /// ```rust,no_run
@@ -240,102 +691,1110 @@ impl Client {
     /// # async fn main() {
     /// #   let my_keys = Client::generate_keys();
     /// #   let client = Client::new(&my_keys);
-    /// client.disconnect().await.unwrap();
+    /// for (url, relay) in client.relays().await.into_iter() {
+    ///     let opts = relay.opts();
+    ///     println!(
+    ///         "{}: {} (read: {}, write: {})",
+    ///         url,
+    ///         relay.status().await,
+    ///         opts.read(),
+    ///         opts.write()
+    ///     );
+    /// }
     /// # }
     /// ```
-    pub async fn disconnect(&self) -> Result<(), Error> {
-        Ok(self.pool.disconnect().await?)
+    pub async fn relays(&self) -> HashMap<Url, Relay> {
+        self.pool.relays().await
     }
 
-    /// Subscribe to filters
+    /// Get relays from which an event has been received and its first-seen timestamp
+    pub async fn event_source(&self, event_id: &Sha256Hash) -> Option<EventSource> {
+        self.pool.event_source(event_id).await
+    }
+
+    /// Set the outbox of the events not delivered to any relay (`None` to disable)
+    ///
+    /// While set, the events published with no write relay connected, or whose sending
+    /// fails, are queued and sent again as soon as a write relay is connected.
+    /// Use [`Outbox::open`] to keep them also after a restart.
     ///
     /// # Example
     /// ```rust,no_run
     /// # use nostr_sdk::Client;
-    /// use nostr::util::time;
-    /// use nostr::SubscriptionFilter;
+    /// use nostr_sdk::Outbox;
     ///
     /// # #[tokio::main]
     /// # async fn main() {
     /// #   let my_keys = Client::generate_keys();
     /// #   let client = Client::new(&my_keys);
-    /// let subscription = SubscriptionFilter::new()
-    ///     .pubkeys(vec![my_keys.public_key()])
-    ///     .since(time::timestamp());
+    /// let outbox = Outbox::open("outbox.jsonl").unwrap();
+    /// client.set_outbox(Some(outbox.clone())).await;
     ///
-    /// client.subscribe(vec![subscription]).await.unwrap();
+    /// client.publish_text_note("Hello", &[]).await.unwrap();
+    /// println!("{} events waiting for a relay", outbox.len());
     /// # }
     /// ```
-    pub async fn subscribe(&self, filters: Vec<SubscriptionFilter>) -> Result<(), Error> {
-        Ok(self.pool.subscribe(filters).await?)
+    pub async fn set_outbox(&self, outbox: Option<Outbox>) {
+        self.pool.set_outbox(outbox).await
+    }
+
+    /// Get the outbox, if set
+    pub async fn outbox(&self) -> Option<Outbox> {
+        self.pool.outbox().await
+    }
+
+    /// Set the transport used to connect to the relays (default: [`WebSocketTransport`](crate::WebSocketTransport))
+    ///
+    /// Applied at the next (re)connection of each relay. See [`RelayTransport`].
+    pub async fn set_transport<T>(&self, transport: T)
+    where
+        T: RelayTransport + 'static,
+    {
+        self.pool.set_transport(transport).await
     }
 
-    /// Get events of filters
+    /// Set the handler called when a relay requires payment
+    ///
+    /// The connection to a paid relay (NIP-11 `limitation.payment_required`) is postponed
+    /// until the handler returns `true`. A [`RelayPoolNotifications::RelayRequiresPayment`]
+    /// notification is sent in any case.
+    ///
+    /// The handler is called once per relay: after a refusal the relay isn't connected
+    /// until a new handler is set.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/11.md>
     ///
     /// # Example
     /// ```rust,no_run
     /// # use nostr_sdk::Client;
-    /// use nostr::util::time;
-    /// use nostr::SubscriptionFilter;
     ///
     /// # #[tokio::main]
     /// # async fn main() {
     /// #   let my_keys = Client::generate_keys();
     /// #   let client = Client::new(&my_keys);
-    /// let subscription = SubscriptionFilter::new()
-    ///     .pubkeys(vec![my_keys.public_key()])
+    /// client
+    ///     .set_payment_handler(|relay_url, payments_url| async move {
+    ///         println!("Pay {} at {:?}", relay_url, payments_url);
+    ///         // Complete the payment here
+    ///         true
+    ///     })
+    ///     .await;
+    /// # }
+    /// ```
+    #[cfg(feature = "nip11")]
+    pub async fn set_payment_handler<F, Fut>(&self, func: F)
+    where
+        F: Fn(Url, Option<String>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = bool> + Send + 'static,
+    {
+        self.pool
+            .set_payment_handler(PaymentHandler::new(func))
+            .await
+    }
+
+    /// Add multiple relays
+    pub async fn add_relays<S>(&self, relays: Vec<(S, Option<SocketAddr>)>) -> Result<(), Error>
+    where
+        S: Into<String>,
+    {
+        for (url, proxy) in relays.into_iter() {
+            self.add_relay(url, proxy).await?;
+        }
+        Ok(())
+    }
+
+    /// Add new relay
+    ///
+    /// `proxy` overrides the one of the client (see [`Options::proxy`]).
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use nostr_sdk::Client;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Client::generate_keys();
+    /// #   let client = Client::new(&my_keys);
+    /// client
+    ///     .add_relay("wss://relay.nostr.info", None)
+    ///     .await
+    ///     .unwrap();
+    /// client
+    ///     .add_relay("wss://relay.damus.io", None)
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub async fn add_relay<S>(&self, url: S, proxy: Option<SocketAddr>) -> Result<(), Error>
+    where
+        S: Into<String>,
+    {
+        self.add_relay_with_opts(url, proxy, RelayOptions::default())
+            .await
+    }
+
+    /// Add new relay with [`RelayOptions`] (i.e. read-only or write-only relay)
+    ///
+    /// The subscriptions are sent only to the read relays and the events only to the write ones.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use nostr_sdk::Client;
+    /// use nostr_sdk::RelayOptions;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Client::generate_keys();
+    /// #   let client = Client::new(&my_keys);
+    /// client
+    ///     .add_relay_with_opts("wss://relay.nostr.info", None, RelayOptions::read_only())
+    ///     .await
+    ///     .unwrap();
+    /// client
+    ///     .add_relay_with_opts("wss://relay.damus.io", None, RelayOptions::new(true, true))
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub async fn add_relay_with_opts<S>(
+        &self,
+        url: S,
+        proxy: Option<SocketAddr>,
+        opts: RelayOptions,
+    ) -> Result<(), Error>
+    where
+        S: Into<String>,
+    {
+        let url = Url::parse(&url.into())?;
+        self.pool.add_relay_with_opts(url, proxy, opts).await;
+        Ok(())
+    }
+
+    /// Add new relay with [`RelayOptions`] and [`TlsOptions`] (i.e. private relay with a self-signed certificate)
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use nostr_sdk::Client;
+    /// use nostr_sdk::{RelayOptions, TlsOptions};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Client::generate_keys();
+    /// #   let client = Client::new(&my_keys);
+    /// let pem = std::fs::read("relay.pem").unwrap();
+    /// let tls = TlsOptions::new().root_certificate_pem(pem);
+    /// client
+    ///     .add_relay_with_tls("wss://relay.home.lan", None, RelayOptions::default(), tls)
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub async fn add_relay_with_tls<S>(
+        &self,
+        url: S,
+        proxy: Option<SocketAddr>,
+        opts: RelayOptions,
+        tls: TlsOptions,
+    ) -> Result<(), Error>
+    where
+        S: Into<String>,
+    {
+        let url = Url::parse(&url.into())?;
+        self.pool.add_relay_with_tls(url, proxy, opts, tls).await;
+        Ok(())
+    }
+
+    /// Close the subscriptions, disconnect and remove relay
+    ///
+    /// Return [`RelayPoolError::RelayNotFound`] if the relay isn't in the pool.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use nostr_sdk::Client;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Client::generate_keys();
+    /// #   let client = Client::new(&my_keys);
+    /// client.remove_relay("wss://relay.nostr.info").await.unwrap();
+    /// # }
+    /// ```
+    pub async fn remove_relay<S>(&self, url: S) -> Result<(), Error>
+    where
+        S: Into<String>,
+    {
+        let url = Url::parse(&url.into())?;
+        Ok(self.pool.remove_relay(url).await?)
+    }
+
+    /// Connect relay
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use nostr_sdk::Client;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Client::generate_keys();
+    /// #   let client = Client::new(&my_keys);
+    /// client
+    ///     .connect_relay("wss://relay.nostr.info", true)
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub async fn connect_relay<S>(&self, url: S, wait_for_connection: bool) -> Result<(), Error>
+    where
+        S: Into<String>,
+    {
+        let url = Url::parse(&url.into())?;
+        if let Some(relay) = self.pool.relays().await.get(&url) {
+            return Ok(self.pool.connect_relay(relay, wait_for_connection).await?);
+        }
+        Err(Error::RelayNotFound)
+    }
+
+    /// Disconnect relay
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use nostr_sdk::Client;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Client::generate_keys();
+    /// #   let client = Client::new(&my_keys);
+    /// client
+    ///     .disconnect_relay("wss://relay.nostr.info")
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub async fn disconnect_relay<S>(&self, url: S) -> Result<(), Error>
+    where
+        S: Into<String>,
+    {
+        let url = Url::parse(&url.into())?;
+        if let Some(relay) = self.pool.relays().await.get(&url) {
+            return Ok(self.pool.disconnect_relay(relay).await?);
+        }
+        Err(Error::RelayNotFound)
+    }
+
+    /// Get relay connection status
+    ///
+    /// Status changes are notified with [`RelayPoolNotifications::RelayStatusChanged`].
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use nostr_sdk::Client;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Client::generate_keys();
+    /// #   let client = Client::new(&my_keys);
+    /// let status = client.relay_status("wss://relay.damus.io").await.unwrap();
+    /// println!("{}", status);
+    /// # }
+    /// ```
+    pub async fn relay_status<S>(&self, url: S) -> Result<RelayStatus, Error>
+    where
+        S: Into<String>,
+    {
+        let url = Url::parse(&url.into())?;
+        match self.pool.relays().await.get(&url) {
+            Some(relay) => Ok(relay.status().await),
+            None => Err(Error::RelayNotFound),
+        }
+    }
+
+    /// Get health statistics of a relay: connection uptime, average round-trip times
+    /// (`REQ` to `EOSE` and `EVENT` to `OK`), number of notices and last error
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use nostr_sdk::Client;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Client::generate_keys();
+    /// #   let client = Client::new(&my_keys);
+    /// let stats = client.relay_stats("wss://relay.damus.io").await.unwrap();
+    /// println!("Uptime: {:?}", stats.uptime);
+    /// println!("REQ latency: {:?}", stats.req_latency);
+    /// println!("OK latency: {:?}", stats.ok_latency);
+    /// if let Some(error) = stats.last_error {
+    ///     println!("Last error: {}", error);
+    /// }
+    /// # }
+    /// ```
+    pub async fn relay_stats<S>(&self, url: S) -> Result<RelayStats, Error>
+    where
+        S: Into<String>,
+    {
+        let url = Url::parse(&url.into())?;
+        match self.pool.relays().await.get(&url) {
+            Some(relay) => Ok(relay.stats().await),
+            None => Err(Error::RelayNotFound),
+        }
+    }
+
+    /// Get the information document of a relay of the pool (cached, see [`INFORMATION_DOCUMENT_TTL`](crate::relay::INFORMATION_DOCUMENT_TTL))
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/11.md>
+    #[cfg(feature = "nip11")]
+    pub async fn relay_info<S>(&self, url: S) -> Result<RelayInformationDocument, Error>
+    where
+        S: Into<String>,
+    {
+        let url = Url::parse(&url.into())?;
+        match self.pool.relays().await.get(&url) {
+            Some(relay) => Ok(relay
+                .cached_information_document()
+                .await
+                .map_err(RelayPoolError::from)?),
+            None => Err(Error::RelayNotFound),
+        }
+    }
+
+    /// Connect to all added relays without waiting for connection and keep connection alive
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use nostr_sdk::Client;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Client::generate_keys();
+    /// #   let client = Client::new(&my_keys);
+    /// client.connect().await.unwrap();
+    /// # }
+    /// ```
+    pub async fn connect(&self) -> Result<(), Error> {
+        Ok(self.pool.connect(false).await?)
+    }
+
+    /// Connect to all added relays waiting for initial connection and keep connection alive
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use nostr_sdk::Client;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Client::generate_keys();
+    /// #   let client = Client::new(&my_keys);
+    /// client.connect_and_wait().await.unwrap();
+    /// # }
+    /// ```
+    pub async fn connect_and_wait(&self) -> Result<(), Error> {
+        Ok(self.pool.connect(true).await?)
+    }
+
+    /// Connect to all added relays, waiting at most `timeout` for the initial connections, and keep connection alive
+    ///
+    /// Return the relays connected and the ones that failed: these are reconnected in the background.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    ///
+    /// # use nostr_sdk::Client;
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Client::generate_keys();
+    /// #   let client = Client::new(&my_keys);
+    /// let report = client
+    ///     .connect_with_timeout(Duration::from_secs(5))
+    ///     .await
+    ///     .unwrap();
+    /// for url in report.failed.iter() {
+    ///     println!("Impossible to connect to {}", url);
+    /// }
+    /// # }
+    /// ```
+    pub async fn connect_with_timeout(&self, timeout: Duration) -> Result<ConnectionReport, Error> {
+        Ok(self.pool.connect_with_timeout(timeout).await?)
+    }
+
+    /// Disconnect from all relays
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use nostr_sdk::Client;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Client::generate_keys();
+    /// #   let client = Client::new(&my_keys);
+    /// client.disconnect().await.unwrap();
+    /// # }
+    /// ```
+    pub async fn disconnect(&self) -> Result<(), Error> {
+        Ok(self.pool.disconnect().await?)
+    }
+
+    /// Subscribe to filters
+    ///
+    /// Replace the filters of the previous call. Use [`Client::add_subscription`] for
+    /// multiple concurrent subscriptions.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use nostr_sdk::Client;
+    /// use nostr::util::time;
+    /// use nostr::SubscriptionFilter;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Client::generate_keys();
+    /// #   let client = Client::new(&my_keys);
+    /// let subscription = SubscriptionFilter::new()
+    ///     .pubkeys(vec![my_keys.public_key()])
+    ///     .since(time::timestamp());
+    ///
+    /// client.subscribe(vec![subscription]).await.unwrap();
+    /// # }
+    /// ```
+    pub async fn subscribe(&self, filters: Vec<SubscriptionFilter>) -> Result<(), Error> {
+        Ok(self.pool.subscribe(filters).await?)
+    }
+
+    /// Add a subscription to filters, alongside the other ones, and get its [`SubscriptionId`]
+    ///
+    /// Close it with [`Client::unsubscribe`].
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use nostr_sdk::Client;
+    /// use nostr::{Kind, KindBase, SubscriptionFilter};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Client::generate_keys();
+    /// #   let client = Client::new(&my_keys);
+    /// let mentions = SubscriptionFilter::new().pubkey(my_keys.public_key());
+    /// let notes = SubscriptionFilter::new().kind(Kind::Base(KindBase::TextNote));
+    ///
+    /// let mentions_id = client.add_subscription(vec![mentions]).await.unwrap();
+    /// let _notes_id = client.add_subscription(vec![notes]).await.unwrap();
+    ///
+    /// client.unsubscribe(&mentions_id).await.unwrap();
+    /// # }
+    /// ```
+    pub async fn add_subscription(
+        &self,
+        filters: Vec<SubscriptionFilter>,
+    ) -> Result<SubscriptionId, Error> {
+        let id = SubscriptionId::generate();
+        self.subscribe_with_id(id.clone(), filters).await?;
+        Ok(id)
+    }
+
+    /// Add a subscription to filters and get a [`Stream`] of its events
+    ///
+    /// Only the events received for this subscription are yielded, once per subscription
+    /// (not deduplicated against the other ones). The stream ends when the subscription is
+    /// closed with [`Client::unsubscribe`].
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use nostr_sdk::Client;
+    /// use futures_util::StreamExt;
+    /// use nostr::{Kind, KindBase, SubscriptionFilter};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Client::generate_keys();
+    /// #   let client = Client::new(&my_keys);
+    /// let notes = SubscriptionFilter::new().kind(Kind::Base(KindBase::TextNote));
+    /// let (id, mut events) = client.add_subscription_stream(vec![notes]).await.unwrap();
+    ///
+    /// while let Some(event) = events.next().await {
+    ///     println!("{}: {}", id, event.content);
+    /// }
+    /// # }
+    /// ```
+    pub async fn add_subscription_stream(
+        &self,
+        filters: Vec<SubscriptionFilter>,
+    ) -> Result<(SubscriptionId, impl Stream<Item = Event> + Send + Unpin), Error> {
+        let id = SubscriptionId::generate();
+        let receiver = self
+            .pool
+            .subscribe_with_channel(id.clone(), filters)
+            .await?;
+        let events = stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|event| (event, receiver))
+        })
+        .boxed();
+        Ok((id, events))
+    }
+
+    /// Subscribe to filters with a specific id, alongside the other subscriptions
+    ///
+    /// If a subscription with the same id exists, its filters are replaced.
+    ///
+    /// With the gossip routing (see [`Options::gossip`]), the filters are sent to the NIP-65
+    /// relays of the authors and of the tagged users.
+    pub async fn subscribe_with_id(
+        &self,
+        id: SubscriptionId,
+        filters: Vec<SubscriptionFilter>,
+    ) -> Result<(), Error> {
+        if let Some(urls) = self.gossip_filters_relays(&filters).await? {
+            return Ok(self.pool.subscribe_to(id, urls, filters).await?);
+        }
+
+        Ok(self.pool.subscribe_with_id(id, filters).await?)
+    }
+
+    /// Subscribe to filters only on specific relays, alongside the other subscriptions
+    ///
+    /// The filters are sent only to `urls` (i.e. direct messages only to a private relay),
+    /// also after a reconnection. Close the subscription with [`Client::unsubscribe`].
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use nostr_sdk::Client;
+    /// use nostr::{Kind, KindBase, SubscriptionFilter};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Client::generate_keys();
+    /// #   let client = Client::new(&my_keys);
+    /// let dms = SubscriptionFilter::new()
+    ///     .pubkey(my_keys.public_key())
+    ///     .kind(Kind::Base(KindBase::EncryptedDirectMessage));
+    ///
+    /// let _id = client
+    ///     .subscribe_to(vec!["wss://relay.example.com"], vec![dms])
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub async fn subscribe_to<S>(
+        &self,
+        urls: Vec<S>,
+        filters: Vec<SubscriptionFilter>,
+    ) -> Result<SubscriptionId, Error>
+    where
+        S: Into<String>,
+    {
+        let urls = parse_urls(urls)?;
+        let id = SubscriptionId::generate();
+        self.pool.subscribe_to(id.clone(), urls, filters).await?;
+        Ok(id)
+    }
+
+    /// Close the subscription with `id` (send `CLOSE` only for it)
+    pub async fn unsubscribe(&self, id: &SubscriptionId) -> Result<(), Error> {
+        Ok(self.pool.unsubscribe_with_id(id).await?)
+    }
+
+    /// Get the subscriptions added with [`Client::add_subscription`] or [`Client::subscribe_with_id`]
+    pub async fn subscriptions(&self) -> HashMap<SubscriptionId, Vec<SubscriptionFilter>> {
+        self.pool.subscriptions().await
+    }
+
+    /// Get events of filters from the read relays
+    ///
+    /// Send a temporary `REQ`, collect the stored events until every relay sent `EOSE`
+    /// (or until `timeout`), then close it. Events are deduplicated.
+    ///
+    /// If a filter `limit` exceeds the `max_limit` of a relay, the query is automatically paginated.
+    /// With the gossip routing (see [`Options::gossip`]), the filters are sent to the NIP-65
+    /// relays of the authors and of the tagged users.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use nostr_sdk::Client;
+    /// use std::time::Duration;
+    ///
+    /// use nostr::util::time;
+    /// use nostr::SubscriptionFilter;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Client::generate_keys();
+    /// #   let client = Client::new(&my_keys);
+    /// let subscription = SubscriptionFilter::new()
+    ///     .pubkeys(vec![my_keys.public_key()])
     ///     .since(time::timestamp());
     ///
-    /// let _events = client.get_events_of(vec![subscription]).await.unwrap();
+    /// let _events = client
+    ///     .get_events_of(vec![subscription], Some(Duration::from_secs(10)))
+    ///     .await
+    ///     .unwrap();
     /// # }
     /// ```
     pub async fn get_events_of(
         &self,
         filters: Vec<SubscriptionFilter>,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<Event>, Error> {
+        if let Some(urls) = self.gossip_filters_relays(&filters).await? {
+            return Ok(self.pool.get_events_from(urls, filters, timeout).await?);
+        }
+
+        Ok(self.pool.get_events_of(filters, timeout).await?)
+    }
+
+    /// Get events of filters only from specific relays
+    ///
+    /// Same as [`Client::get_events_of`], but the relays must be already added to the client.
+    pub async fn get_events_from<S>(
+        &self,
+        urls: Vec<S>,
+        filters: Vec<SubscriptionFilter>,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<Event>, Error>
+    where
+        S: Into<String>,
+    {
+        let urls = parse_urls(urls)?;
+        Ok(self.pool.get_events_from(urls, filters, timeout).await?)
+    }
+
+    /// Get events of filters from the `n` fastest relays, falling back to the others on miss
+    ///
+    /// Relays are ranked by their measured REQ round-trip time.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use nostr_sdk::Client;
+    /// use nostr::SubscriptionFilter;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Client::generate_keys();
+    /// #   let client = Client::new(&my_keys);
+    /// let subscription = SubscriptionFilter::new()
+    ///     .authors(vec![my_keys.public_key()])
+    ///     .limit(10);
+    ///
+    /// let _events = client
+    ///     .get_events_of_fastest(vec![subscription], 3, None)
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub async fn get_events_of_fastest(
+        &self,
+        filters: Vec<SubscriptionFilter>,
+        n: usize,
+        timeout: Option<Duration>,
     ) -> Result<Vec<Event>, Error> {
-        Ok(self.pool.get_events_of(filters).await?)
+        Ok(self.pool.get_events_of_fastest(filters, n, timeout).await?)
+    }
+
+    /// Send client message
+    pub async fn send_client_msg(&self, msg: ClientMessage) -> Result<(), Error> {
+        Ok(self.pool.send_client_msg(msg).await?)
+    }
+
+    /// Send client message only to specific relays
+    pub async fn send_client_msg_to<S>(&self, urls: Vec<S>, msg: ClientMessage) -> Result<(), Error>
+    where
+        S: Into<String>,
+    {
+        let urls = parse_urls(urls)?;
+        Ok(self.pool.send_client_msg_to(urls, msg).await?)
+    }
+
+    /// Send event
+    ///
+    /// With the gossip routing (see [`Options::gossip`]), the event is sent also to the read
+    /// relays of the tagged users.
+    pub async fn send_event(&self, event: Event) -> Result<(), Error> {
+        let urls = self.gossip_event_relays(&event).await?;
+        self.pool
+            .send_client_msg(ClientMessage::new_event(event.clone()))
+            .await?;
+
+        if !urls.is_empty() {
+            self.pool
+                .send_client_msg_to(urls, ClientMessage::new_event(event))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Send event only to specific relays (i.e. a direct message to the relays of the receiver)
+    ///
+    /// The relays must be already added to the client, also as read-only relays.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use nostr_sdk::Client;
+    /// use nostr::EventBuilder;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Client::generate_keys();
+    /// #   let client = Client::new(&my_keys);
+    /// let event = EventBuilder::new_text_note("Only for my community", &[])
+    ///     .to_event(&my_keys)
+    ///     .unwrap();
+    ///
+    /// client
+    ///     .send_event_to(vec!["wss://relay.example.com"], event)
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub async fn send_event_to<S>(&self, urls: Vec<S>, event: Event) -> Result<(), Error>
+    where
+        S: Into<String>,
+    {
+        self.send_client_msg_to(urls, ClientMessage::new_event(event))
+            .await
+    }
+
+    /// Send event and wait for the acknowledgment (`OK` message) of each write relay
+    ///
+    /// Return, for each relay, whether the event has been accepted, rejected
+    /// ([`RelayError::EventNotPublished`] with the reason given by the relay) or not acknowledged
+    /// within `timeout` ([`RelayError::Timeout`]).
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/20.md>
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use nostr_sdk::Client;
+    /// use std::time::Duration;
+    ///
+    /// use nostr::EventBuilder;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Client::generate_keys();
+    /// #   let client = Client::new(&my_keys);
+    /// let event = EventBuilder::new_text_note("Hello", &[])
+    ///     .to_event(&my_keys)
+    ///     .unwrap();
+    ///
+    /// let results = client
+    ///     .send_event_and_wait(event, Duration::from_secs(10))
+    ///     .await
+    ///     .unwrap();
+    /// for (url, result) in results.into_iter() {
+    ///     match result {
+    ///         Ok(()) => println!("Published to {}", url),
+    ///         Err(e) => println!("Not published to {}: {}", url, e),
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub async fn send_event_and_wait(
+        &self,
+        event: Event,
+        timeout: Duration,
+    ) -> Result<HashMap<Url, Result<(), RelayError>>, Error> {
+        Ok(self.pool.send_event_and_wait(event, timeout).await?)
+    }
+
+    /// Update profile metadata
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/01.md>
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use nostr_sdk::Client;
+    /// use nostr::url::Url;
+    /// use nostr_sdk::nostr::Metadata;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Client::generate_keys();
+    /// #   let client = Client::new(&my_keys);
+    /// let metadata = Metadata::new()
+    ///     .name("username")
+    ///     .display_name("My Username")
+    ///     .about("Description")
+    ///     .picture(Url::parse("https://example.com/avatar.png").unwrap())
+    ///     .nip05("username@example.com");
+    ///
+    /// client.update_profile(metadata).await.unwrap();
+    /// # }
+    /// ```
+    pub async fn update_profile(&self, metadata: Metadata) -> Result<(), Error> {
+        let event: Event = self
+            .sign_event_builder(EventBuilder::set_metadata(metadata)?)
+            .await?;
+        self.send_event(event).await
+    }
+
+    /// Get profile metadata of the current keys
+    ///
+    /// Return `None` if no metadata was found within 10 secs.
+    pub async fn get_profile(&self) -> Result<Option<Metadata>, Error> {
+        let filter = SubscriptionFilter::new()
+            .author(self.public_key().await?)
+            .kind(Kind::Base(KindBase::Metadata))
+            .limit(1);
+        let events: Vec<Event> = self
+            .get_events_of(vec![filter], Some(OWN_EVENTS_TIMEOUT))
+            .await?;
+
+        match events.into_iter().max_by_key(|e| e.created_at) {
+            Some(event) => Ok(Some(Metadata::from_event(&event)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Update profile metadata, keeping the fields that are not changed
+    ///
+    /// Fetch the current metadata, apply `f` and publish the result.
+    /// Unknown fields of the current metadata are preserved.
+    ///
+    /// Nothing is published if the current metadata can't be fetched: [`Error::Timeout`] if
+    /// a relay didn't answer in time, [`Error::MetadataNotFound`] if no relay has it (use
+    /// [`Client::update_metadata_or_default`] for new users).
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/01.md>
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use nostr_sdk::Client;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Client::generate_keys();
+    /// #   let client = Client::new(&my_keys);
+    /// client
+    ///     .update_metadata(|metadata| metadata.about("New bio"))
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub async fn update_metadata<F>(&self, f: F) -> Result<Metadata, Error>
+    where
+        F: FnOnce(Metadata) -> Metadata,
+    {
+        let metadata = self
+            .fetch_own_metadata()
+            .await?
+            .ok_or(Error::MetadataNotFound)?;
+        let metadata: Metadata = f(metadata);
+        self.update_profile(metadata.clone()).await?;
+        Ok(metadata)
+    }
+
+    /// Same as [`Client::update_metadata`], but apply `f` to an empty [`Metadata`] if no relay
+    /// has the profile yet (i.e. new user)
+    ///
+    /// Still fails with [`Error::Timeout`] if a relay didn't answer in time.
+    pub async fn update_metadata_or_default<F>(&self, f: F) -> Result<Metadata, Error>
+    where
+        F: FnOnce(Metadata) -> Metadata,
+    {
+        let metadata: Metadata = f(self.fetch_own_metadata().await?.unwrap_or_default());
+        self.update_profile(metadata.clone()).await?;
+        Ok(metadata)
+    }
+
+    /// Get the latest profile metadata of the current keys
+    ///
+    /// Return [`Error::Timeout`] if it's not found and a relay didn't send `EOSE`: the relay
+    /// may have it.
+    async fn fetch_own_metadata(&self) -> Result<Option<Metadata>, Error> {
+        let filters = vec![SubscriptionFilter::new()
+            .author(self.public_key().await?)
+            .kind(Kind::Base(KindBase::Metadata))
+            .limit(1)];
+        let urls = self.gossip_filters_relays(&filters).await?;
+        let (events, complete) = self
+            .pool
+            .get_events_with_eose(urls, filters, Some(OWN_EVENTS_TIMEOUT))
+            .await?;
+
+        match events.into_iter().max_by_key(|e| e.created_at) {
+            Some(event) => Ok(Some(Metadata::from_event(&event)?)),
+            None if complete => Ok(None),
+            None => Err(Error::Timeout),
+        }
+    }
+
+    /// Set user status (an empty content clears it)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/38.md>
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use nostr_sdk::Client;
+    /// use nostr::util::nips::nip38::{StatusType, UserStatus};
+    /// use nostr::util::time;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Client::generate_keys();
+    /// #   let client = Client::new(&my_keys);
+    /// let status =
+    ///     UserStatus::new(StatusType::General, "In a meeting").expiration(time::timestamp() + 3600);
+    /// client.set_status(status).await.unwrap();
+    /// # }
+    /// ```
+    pub async fn set_status(&self, status: UserStatus) -> Result<(), Error> {
+        let event: Event = self
+            .sign_event_builder(EventBuilder::user_status(&status))
+            .await?;
+        self.send_event(event).await
+    }
+
+    /// Verify the NIP-05 address of `public_key`, through the proxy of the client
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/05.md>
+    #[cfg(feature = "nip05")]
+    pub async fn verify_nip05(&self, public_key: XOnlyPublicKey, nip05: &str) -> Result<(), Error> {
+        Ok(nip05::verify_async(public_key, nip05, self.opts.get_proxy()).await?)
+    }
+
+    /// Publish relay list metadata
+    ///
+    /// A relay without [`RelayMetadata`] is used both for reading and writing.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/65.md>
+    pub async fn set_relay_list(
+        &self,
+        list: Vec<(Url, Option<RelayMetadata>)>,
+    ) -> Result<(), Error> {
+        let event: Event = self
+            .sign_event_builder(EventBuilder::relay_list(&list))
+            .await?;
+        self.send_event(event).await
+    }
+
+    /// Get the current statuses of `public_key` (at most one for each status type)
+    ///
+    /// Cleared and expired statuses are skipped.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/38.md>
+    pub async fn get_status_of(
+        &self,
+        public_key: XOnlyPublicKey,
+    ) -> Result<Vec<UserStatus>, Error> {
+        let filter = SubscriptionFilter::new()
+            .author(public_key)
+            .kind(Kind::Base(KindBase::UserStatus));
+        let mut events: Vec<Event> = self.get_events_of(vec![filter], None).await?;
+        events.sort_by_key(|e| Reverse(e.created_at));
+
+        let mut statuses: Vec<UserStatus> = Vec::new();
+        let mut seen: Vec<Option<String>> = Vec::new();
+        for event in events.into_iter() {
+            let status_type = event.identifier().map(|d| d.to_string());
+            if seen.contains(&status_type) {
+                continue;
+            }
+            seen.push(status_type);
+
+            if event.content.is_empty() || event.is_expired() {
+                continue;
+            }
+            match UserStatus::from_event(&event) {
+                Ok(status) => statuses.push(status),
+                Err(e) => log::error!("Impossible to parse user status: {}", e),
+            }
+        }
+
+        Ok(statuses)
+    }
+
+    /// Publish the monitoring data of a relay (relay monitors only)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/66.md>
+    pub async fn publish_relay_discovery(&self, discovery: RelayDiscovery) -> Result<(), Error> {
+        let event: Event = self
+            .sign_event_builder(EventBuilder::relay_discovery(&discovery))
+            .await?;
+        self.send_event(event).await
     }
 
-    /// Send client message
-    pub async fn send_client_msg(&self, msg: ClientMessage) -> Result<(), Error> {
-        Ok(self.pool.send_client_msg(msg).await?)
-    }
+    /// Get the latest monitoring data of `relay_url` published by each relay monitor
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/66.md>
+    pub async fn get_relay_discoveries(
+        &self,
+        relay_url: &Url,
+    ) -> Result<HashMap<XOnlyPublicKey, RelayDiscovery>, Error> {
+        let mut events: Vec<Event> = self
+            .get_events_of(vec![RelayDiscovery::filter(relay_url)], None)
+            .await?;
+        events.sort_by_key(|e| Reverse(e.created_at));
+
+        let mut discoveries: HashMap<XOnlyPublicKey, RelayDiscovery> = HashMap::new();
+        for event in events.into_iter() {
+            if discoveries.contains_key(&event.pubkey) {
+                continue;
+            }
+            match RelayDiscovery::from_event(&event) {
+                Ok(discovery) => {
+                    discoveries.insert(event.pubkey, discovery);
+                }
+                Err(e) => log::error!("Impossible to parse relay discovery: {}", e),
+            }
+        }
 
-    /// Send event
-    pub async fn send_event(&self, event: Event) -> Result<(), Error> {
-        Ok(self
-            .pool
-            .send_client_msg(ClientMessage::new_event(event))
-            .await?)
+        Ok(discoveries)
     }
 
-    /// Update profile metadata
+    /// Export identity: keys (encrypted with `password`), relays, contact list and lists
     ///
-    /// <https://github.com/nostr-protocol/nips/blob/master/01.md>
+    /// Not supported with an external signer, since the secret key is unknown.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/49.md>
     ///
     /// # Example
     /// ```rust,no_run
     /// # use nostr_sdk::Client;
-    /// use nostr::url::Url;
-    /// use nostr_sdk::nostr::Metadata;
     ///
     /// # #[tokio::main]
     /// # async fn main() {
     /// #   let my_keys = Client::generate_keys();
     /// #   let client = Client::new(&my_keys);
-    /// let metadata = Metadata::new()
-    ///     .name("username")
-    ///     .display_name("My Username")
-    ///     .about("Description")
-    ///     .picture(Url::parse("https://example.com/avatar.png").unwrap())
-    ///     .nip05("username@example.com");
-    ///
-    /// client.update_profile(metadata).await.unwrap();
+    /// let bundle = client.export_identity("password").await.unwrap();
+    /// println!("{}", bundle.as_json().unwrap());
     /// # }
     /// ```
-    pub async fn update_profile(&self, metadata: Metadata) -> Result<(), Error> {
-        let event: Event = EventBuilder::set_metadata(metadata)?.to_event(&self.keys)?;
-        self.send_event(event).await
+    #[cfg(feature = "nip49")]
+    pub async fn export_identity<S>(&self, password: S) -> Result<IdentityBundle, Error>
+    where
+        S: Into<String>,
+    {
+        let identity = self.identity();
+        if identity.external_signer {
+            return Err(Error::UnsupportedByRemoteSigner);
+        }
+
+        // Contact list and NIP-51 lists (mute, pin, bookmarks and categorized people)
+        let filter = SubscriptionFilter::new()
+            .author(identity.keys.public_key())
+            .kinds(vec![
+                Kind::Base(KindBase::ContactList),
+                Kind::Base(KindBase::MuteList),
+                Kind::Base(KindBase::PinList),
+                Kind::Base(KindBase::Bookmarks),
+                Kind::Base(KindBase::PeopleList),
+            ]);
+        let mut events: Vec<Event> = self
+            .get_events_of(vec![filter], Some(OWN_EVENTS_TIMEOUT))
+            .await?;
+
+        // Keep only the latest version of each replaceable event
+        events.sort_by_key(|e| std::cmp::Reverse(e.created_at));
+        let mut keys = BTreeSet::new();
+        events.retain(|e| keys.insert((e.kind, e.identifier().map(|d| d.to_string()))));
+
+        let relays: Vec<Url> = self.relays().await.into_keys().collect();
+
+        Ok(IdentityBundle::new(
+            &identity.keys,
+            &password.into(),
+            relays,
+            events,
+        )?)
     }
 
     /// Publish text note
@@ -360,7 +1819,9 @@ impl Client {
     where
         S: Into<String>,
     {
-        let event: Event = EventBuilder::new_text_note(content, tags).to_event(&self.keys)?;
+        let event: Event = self
+            .sign_event_builder(EventBuilder::new_text_note(content, tags))
+            .await?;
         self.send_event(event).await
     }
 
@@ -391,11 +1852,50 @@ impl Client {
     where
         S: Into<String>,
     {
-        let event: Event =
-            EventBuilder::new_text_note(content, tags).to_pow_event(&self.keys, difficulty)?;
+        let builder = EventBuilder::new_text_note(content, tags);
+        let event: Event = self
+            .mine_pow_event(builder, difficulty, PowOptions::default())
+            .await?;
         self.send_event(event).await
     }
 
+    /// Mine POW event on a blocking thread and sign it with the current keys or with the remote signer
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/13.md>
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr_sdk::nostr::util::nips::nip13::{CancellationToken, PowOptions};
+    /// use nostr_sdk::nostr::EventBuilder;
+    /// # use nostr_sdk::Client;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Client::generate_keys();
+    /// #   let client = Client::new(&my_keys);
+    /// let token = CancellationToken::new();
+    /// let options = PowOptions::new()
+    ///     .cancellation_token(token.clone())
+    ///     .progress(|hashes| println!("{} hashes", hashes));
+    /// let builder = EventBuilder::new_text_note("My first POW text note from Nostr SDK!", &[]);
+    /// let event = client.mine_pow_event(builder, 20, options).await.unwrap();
+    /// # }
+    /// ```
+    pub async fn mine_pow_event(
+        &self,
+        builder: EventBuilder,
+        difficulty: u8,
+        options: PowOptions,
+    ) -> Result<Event, Error> {
+        let pubkey: XOnlyPublicKey = self.public_key().await?;
+        let unsigned = tokio::task::spawn_blocking(move || {
+            builder.to_unsigned_pow_event_with_options(pubkey, difficulty, &options)
+        })
+        .await??;
+
+        Ok(self.signer().sign_event(unsigned).await?)
+    }
+
     /// Add recommended relay
     ///
     /// <https://github.com/nostr-protocol/nips/blob/master/01.md>
@@ -419,7 +1919,9 @@ impl Client {
         S: Into<String>,
     {
         let url = Url::parse(&url.into())?;
-        let event: Event = EventBuilder::add_recommended_relay(&url).to_event(&self.keys)?;
+        let event: Event = self
+            .sign_event_builder(EventBuilder::add_recommended_relay(&url))
+            .await?;
         self.send_event(event).await
     }
 
@@ -427,7 +1929,9 @@ impl Client {
     ///
     /// <https://github.com/nostr-protocol/nips/blob/master/02.md>
     pub async fn set_contact_list(&self, list: Vec<Contact>) -> Result<(), Error> {
-        let event: Event = EventBuilder::set_contact_list(list).to_event(&self.keys)?;
+        let event: Event = self
+            .sign_event_builder(EventBuilder::set_contact_list(list))
+            .await?;
         self.send_event(event).await
     }
 
@@ -450,10 +1954,10 @@ impl Client {
         let mut contact_list: Vec<Contact> = Vec::new();
 
         let filter = SubscriptionFilter::new()
-            .authors(vec![self.keys.public_key()])
+            .authors(vec![self.public_key().await?])
             .kind(Kind::Base(KindBase::ContactList))
             .limit(1);
-        let events: Vec<Event> = self.get_events_of(vec![filter]).await?;
+        let events: Vec<Event> = self.get_events_of(vec![filter], None).await?;
 
         for event in events.into_iter() {
             for tag in event.tags.into_iter() {
@@ -510,9 +2014,151 @@ impl Client {
     where
         S: Into<String>,
     {
-        let event: Event = EventBuilder::new_encrypted_direct_msg(&self.keys, recipient, msg)?
-            .to_event(&self.keys)?;
-        self.send_event(event).await
+        let content = self
+            .signer()
+            .nip04_encrypt(recipient.public_key(), msg.into())
+            .await?;
+        let builder = EventBuilder::new(
+            Kind::Base(KindBase::EncryptedDirectMessage),
+            content,
+            &[Tag::new(TagData::PubKey(recipient.public_key()))],
+        );
+        self.send_event(self.sign_event_builder(builder).await?)
+            .await
+    }
+
+    /// Send the same encrypted direct message to many recipients
+    ///
+    /// The message is encrypted separately for every recipient, as a NIP-04 direct message or
+    /// as a NIP-17 gift wrap (see [`DirectMessageMode`]).
+    /// With the gossip routing (see [`Options::gossip`]), each message is sent also to the read
+    /// relays of its recipient: the relay lists of all the recipients are fetched with a single
+    /// request.
+    /// Return the result of each recipient, in the same order of `recipients`.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr::key::{FromBech32, Keys};
+    /// use nostr_sdk::{Client, DirectMessageMode};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Client::generate_keys();
+    /// #   let client = Client::new(&my_keys);
+    /// let alice_keys = Keys::from_bech32_public_key(
+    ///     "npub14f8usejl26twx0dhuxjh9cas7keav9vr0v8nvtwtrjqx3vycc76qqh9nsy",
+    /// )
+    /// .unwrap();
+    /// let bob_keys = Keys::generate_from_os_random();
+    ///
+    /// let results = client
+    ///     .send_direct_msg_to_many(
+    ///         &[alice_keys, bob_keys],
+    ///         "Hello everyone!",
+    ///         DirectMessageMode::Nip17,
+    ///     )
+    ///     .await;
+    /// for (public_key, result) in results {
+    ///     if let Err(e) = result {
+    ///         println!("Impossible to send DM to {}: {}", public_key, e);
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    #[cfg(any(feature = "nip04", feature = "nip59"))]
+    pub async fn send_direct_msg_to_many<S>(
+        &self,
+        recipients: &[Keys],
+        msg: S,
+        mode: DirectMessageMode,
+    ) -> Vec<(XOnlyPublicKey, Result<(), Error>)>
+    where
+        S: Into<String>,
+    {
+        if self.opts.get_gossip() {
+            let public_keys: HashSet<XOnlyPublicKey> =
+                recipients.iter().map(|r| r.public_key()).collect();
+            // On failure, every message fetches its own relays
+            if let Err(e) = self.gossip_relays(&public_keys, RelayMetadata::Read).await {
+                log::warn!("Impossible to get the relays of the recipients: {}", e);
+            }
+        }
+
+        let msg: String = msg.into();
+        let mut results = Vec::with_capacity(recipients.len());
+        for recipient in recipients.iter() {
+            let result = match mode {
+                #[cfg(feature = "nip04")]
+                DirectMessageMode::Nip04 => self.send_direct_msg(recipient, msg.clone()).await,
+                #[cfg(feature = "nip59")]
+                DirectMessageMode::Nip17 => {
+                    self.send_private_msg(recipient.public_key(), msg.clone())
+                        .await
+                }
+            };
+            results.push((recipient.public_key(), result));
+        }
+        results
+    }
+
+    /// Send NIP-17 private direct message
+    ///
+    /// The message is gift wrapped for the `receiver` and, to be readable from other devices,
+    /// for the sender too.
+    /// Not supported with a NIP-46 remote signer.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/17.md>
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr::key::{FromBech32, Keys};
+    /// use nostr_sdk::{Client, RelayPoolNotifications};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Client::generate_keys();
+    /// #   let client = Client::new(&my_keys);
+    /// let alice_keys = Keys::from_bech32_public_key(
+    ///     "npub14f8usejl26twx0dhuxjh9cas7keav9vr0v8nvtwtrjqx3vycc76qqh9nsy",
+    /// )
+    /// .unwrap();
+    ///
+    /// client
+    ///     .send_private_msg(alice_keys.public_key(), "Hello Alice!")
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// let mut notifications = client.notifications();
+    /// while let Ok(notification) = notifications.recv().await {
+    ///     if let RelayPoolNotifications::PrivateDirectMessage { sender, rumor, .. } = notification {
+    ///         println!("{}: {}", sender, rumor.content);
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    #[cfg(feature = "nip59")]
+    pub async fn send_private_msg<S>(&self, receiver: XOnlyPublicKey, text: S) -> Result<(), Error>
+    where
+        S: Into<String>,
+    {
+        let identity = self.identity();
+        if identity.external_signer {
+            return Err(Error::UnsupportedByRemoteSigner);
+        }
+
+        let keys = identity.keys;
+        let rumor =
+            EventBuilder::private_msg_rumor(receiver, text).to_unsigned_event(keys.public_key());
+
+        let event: Event = nip59::gift_wrap(&keys, &receiver, &rumor)?;
+        self.send_event(event).await?;
+
+        if receiver != keys.public_key() {
+            let event: Event = nip59::gift_wrap(&keys, &keys.public_key(), &rumor)?;
+            self.send_event(event).await?;
+        }
+
+        Ok(())
     }
 
     /// Delete event
@@ -526,7 +2172,9 @@ impl Client {
     where
         S: Into<String>,
     {
-        let event: Event = EventBuilder::delete(vec![event_id], reason).to_event(&self.keys)?;
+        let event: Event = self
+            .sign_event_builder(EventBuilder::delete(vec![event_id], reason))
+            .await?;
         self.send_event(event).await
     }
 
@@ -557,7 +2205,9 @@ impl Client {
     /// # }
     /// ```
     pub async fn like(&self, event: &Event) -> Result<(), Error> {
-        let event: Event = EventBuilder::new_reaction(event, true).to_event(&self.keys)?;
+        let event: Event = self
+            .sign_event_builder(EventBuilder::new_reaction(event, true))
+            .await?;
         self.send_event(event).await
     }
 
@@ -588,7 +2238,19 @@ impl Client {
     /// # }
     /// ```
     pub async fn dislike(&self, event: &Event) -> Result<(), Error> {
-        let event: Event = EventBuilder::new_reaction(event, false).to_event(&self.keys)?;
+        let event: Event = self
+            .sign_event_builder(EventBuilder::new_reaction(event, false))
+            .await?;
+        self.send_event(event).await
+    }
+
+    /// Repost event
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/18.md>
+    pub async fn repost_event(&self, event: &Event, relay_url: Option<Url>) -> Result<(), Error> {
+        let event: Event = self
+            .sign_event_builder(EventBuilder::repost(event, relay_url))
+            .await?;
         self.send_event(event).await
     }
 
@@ -596,7 +2258,9 @@ impl Client {
     ///
     /// <https://github.com/nostr-protocol/nips/blob/master/28.md>
     pub async fn new_channel(&self, metadata: Metadata) -> Result<(), Error> {
-        let event: Event = EventBuilder::new_channel(metadata)?.to_event(&self.keys)?;
+        let event: Event = self
+            .sign_event_builder(EventBuilder::new_channel(metadata)?)
+            .await?;
         self.send_event(event).await
     }
 
@@ -609,8 +2273,11 @@ impl Client {
         relay_url: Url,
         metadata: Metadata,
     ) -> Result<(), Error> {
-        let event: Event = EventBuilder::set_channel_metadata(channel_id, relay_url, metadata)?
-            .to_event(&self.keys)?;
+        let event: Event = self
+            .sign_event_builder(EventBuilder::set_channel_metadata(
+                channel_id, relay_url, metadata,
+            )?)
+            .await?;
         self.send_event(event).await
     }
 
@@ -626,8 +2293,9 @@ impl Client {
     where
         S: Into<String>,
     {
-        let event: Event =
-            EventBuilder::new_channel_msg(channel_id, relay_url, msg).to_event(&self.keys)?;
+        let event: Event = self
+            .sign_event_builder(EventBuilder::new_channel_msg(channel_id, relay_url, msg))
+            .await?;
         self.send_event(event).await
     }
 
@@ -642,8 +2310,9 @@ impl Client {
     where
         S: Into<String>,
     {
-        let event: Event =
-            EventBuilder::hide_channel_msg(message_id, reason).to_event(&self.keys)?;
+        let event: Event = self
+            .sign_event_builder(EventBuilder::hide_channel_msg(message_id, reason))
+            .await?;
         self.send_event(event).await
     }
 
@@ -658,18 +2327,178 @@ impl Client {
     where
         S: Into<String>,
     {
-        let event: Event = EventBuilder::mute_channel_user(pubkey, reason).to_event(&self.keys)?;
+        let event: Event = self
+            .sign_event_builder(EventBuilder::mute_channel_user(pubkey, reason))
+            .await?;
         self.send_event(event).await
     }
 
+    /// Get groups hosted by a relay
+    ///
+    /// The relay must be already added to the client.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/29.md>
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use nostr_sdk::Client;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Client::generate_keys();
+    /// #   let client = Client::new(&my_keys);
+    /// client
+    ///     .add_relay("wss://groups.fiatjaf.com", None)
+    ///     .await
+    ///     .unwrap();
+    /// client.connect().await.unwrap();
+    ///
+    /// let groups = client.get_groups("wss://groups.fiatjaf.com").await.unwrap();
+    /// # }
+    /// ```
+    pub async fn get_groups<S>(&self, relay_url: S) -> Result<Vec<GroupMetadata>, Error>
+    where
+        S: Into<String>,
+    {
+        let url = Url::parse(&relay_url.into())?;
+        let events: Vec<Event> = self
+            .pool
+            .get_events_from(
+                vec![url],
+                vec![SubscriptionFilter::new().kind(Kind::Base(KindBase::GroupMetadata))],
+                None,
+            )
+            .await?;
+
+        Ok(events
+            .iter()
+            .filter_map(|event| GroupMetadata::from_event(event).ok())
+            .collect())
+    }
+
+    /// Send message to a group
+    ///
+    /// The message is sent only to the relay hosting the group, that must be already added to the client.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/29.md>
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use std::str::FromStr;
+    ///
+    /// use nostr::util::nips::nip29::GroupId;
+    /// # use nostr_sdk::Client;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Client::generate_keys();
+    /// #   let client = Client::new(&my_keys);
+    /// let group_id = GroupId::from_str("groups.fiatjaf.com'nostr").unwrap();
+    /// client
+    ///     .add_relay(group_id.relay_url().unwrap(), None)
+    ///     .await
+    ///     .unwrap();
+    /// client.connect().await.unwrap();
+    ///
+    /// client.send_group_msg(&group_id, "Hello!").await.unwrap();
+    /// # }
+    /// ```
+    pub async fn send_group_msg<S>(&self, group_id: &GroupId, msg: S) -> Result<(), Error>
+    where
+        S: Into<String>,
+    {
+        let url = group_id.relay_url()?;
+        let event: Event = self
+            .sign_event_builder(EventBuilder::new_group_chat_msg(group_id, msg))
+            .await?;
+        Ok(self
+            .pool
+            .send_client_msg_to(vec![url], ClientMessage::new_event(event))
+            .await?)
+    }
+
     /// Get a list of channels
     pub async fn get_channels(&self) -> Result<Vec<Event>, Error> {
-        self.get_events_of(vec![
-            SubscriptionFilter::new().kind(Kind::Base(KindBase::ChannelCreation))
-        ])
+        self.get_events_of(
+            vec![SubscriptionFilter::new().kind(Kind::Base(KindBase::ChannelCreation))],
+            None,
+        )
         .await
     }
 
+    /// Submit a job request and wait for the first result
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/90.md>
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    ///
+    /// # use nostr_sdk::Client;
+    /// use nostr::util::nips::nip90::{JobInput, JobInputType, JobRequest};
+    /// use nostr::Kind;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Client::generate_keys();
+    /// #   let client = Client::new(&my_keys);
+    /// let job = JobRequest::new(Kind::Custom(5002))
+    ///     .unwrap()
+    ///     .input(JobInput::new("Hello world", JobInputType::Text))
+    ///     .param("language", "es");
+    ///
+    /// let result = client
+    ///     .submit_job(&job, Duration::from_secs(60))
+    ///     .await
+    ///     .unwrap();
+    /// println!("{}", result.payload);
+    /// # }
+    /// ```
+    pub async fn submit_job(
+        &self,
+        job: &JobRequest,
+        timeout: Duration,
+    ) -> Result<JobResult, Error> {
+        let event: Event = self
+            .sign_event_builder(EventBuilder::job_request(job))
+            .await?;
+        let request_id = event.id;
+
+        let subscription_id = Uuid::new_v4().to_string();
+        let filter = SubscriptionFilter::new()
+            .kind(job.result_kind())
+            .event(request_id);
+        let mut notifications = self.notifications();
+        self.send_client_msg(ClientMessage::new_req(
+            subscription_id.clone(),
+            vec![filter],
+        ))
+        .await?;
+        self.send_event(event).await?;
+
+        let result = tokio::time::timeout(timeout, async {
+            while let Ok(notification) = notifications.recv().await {
+                if let RelayPoolNotifications::ReceivedEvent { event, .. } = notification {
+                    if event.kind != job.result_kind() {
+                        continue;
+                    }
+                    match JobResult::from_event(&event) {
+                        Ok(result) if result.request_id == request_id => return Some(result),
+                        Ok(_) => (),
+                        Err(e) => log::error!("Impossible to parse job result: {}", e),
+                    }
+                }
+            }
+            None
+        })
+        .await;
+
+        self.send_client_msg(ClientMessage::close(subscription_id))
+            .await?;
+
+        result.map_err(|_| Error::Timeout)?.ok_or(Error::Timeout)
+    }
+
     #[deprecated = "Use `get_entity_of` instead"]
     pub async fn get_entity_of_pubkey(&self, pubkey: XOnlyPublicKey) -> Result<Entity, Error> {
         self.get_entity_of(pubkey.to_string()).await
@@ -681,15 +2510,21 @@ impl Client {
     {
         let entity: String = entity.into();
         let events: Vec<Event> = self
-            .get_events_of(vec![SubscriptionFilter::new()
-                .id(&entity)
-                .kind(Kind::Base(KindBase::ChannelCreation))
-                .limit(1)])
+            .get_events_of(
+                vec![SubscriptionFilter::new()
+                    .id(Sha256Hash::from_str(&entity)?)
+                    .kind(Kind::Base(KindBase::ChannelCreation))
+                    .limit(1)],
+                None,
+            )
             .await?;
         if events.is_empty() {
             let pubkey = XOnlyPublicKey::from_str(&entity)?;
             let events: Vec<Event> = self
-                .get_events_of(vec![SubscriptionFilter::new().author(pubkey).limit(1)])
+                .get_events_of(
+                    vec![SubscriptionFilter::new().author(pubkey).limit(1)],
+                    None,
+                )
                 .await?;
             if events.is_empty() {
                 Ok(Entity::Unknown)
@@ -714,3 +2549,227 @@ impl Client {
         }
     }
 }
+
+#[cfg(all(test, feature = "local-relay"))]
+mod tests {
+    use super::*;
+    use crate::local_relay::{run_silent_relay, LocalRelay};
+    use crate::store::MemoryStore;
+
+    async fn connect(client: &Client, urls: &[&Url]) {
+        for url in urls.iter() {
+            client.add_relay(url.as_str(), None).await.unwrap();
+        }
+        client
+            .connect_with_timeout(Duration::from_secs(5))
+            .await
+            .unwrap();
+    }
+
+    fn own_metadata(keys: &Keys) -> Vec<SubscriptionFilter> {
+        vec![SubscriptionFilter::new()
+            .author(keys.public_key())
+            .kind(Kind::Base(KindBase::Metadata))]
+    }
+
+    #[tokio::test]
+    async fn test_update_metadata() {
+        let local_relay = LocalRelay::run("127.0.0.1:0".parse().unwrap(), MemoryStore::new())
+            .await
+            .unwrap();
+        let url = local_relay.url().unwrap();
+        let keys = Keys::generate_from_os_random();
+        let client = Client::new(&keys);
+        connect(&client, &[&url]).await;
+
+        // No profile yet: only published with the explicit opt-in
+        assert!(matches!(
+            client.update_metadata(|m| m.about("New bio")).await,
+            Err(Error::MetadataNotFound)
+        ));
+        let events = client
+            .get_events_of(own_metadata(&keys), Some(Duration::from_secs(5)))
+            .await
+            .unwrap();
+        assert!(events.is_empty());
+
+        client
+            .update_metadata_or_default(|m| m.name("alice").about("Old bio"))
+            .await
+            .unwrap();
+        // Replaceable events with the same `created_at` are ambiguous
+        tokio::time::sleep(Duration::from_secs(1)).await;
+
+        // Fields not changed by the closure are kept
+        let metadata = client
+            .update_metadata(|m| m.about("New bio"))
+            .await
+            .unwrap();
+        assert_eq!(metadata, Metadata::new().name("alice").about("New bio"));
+        assert_eq!(client.get_profile().await.unwrap(), Some(metadata));
+    }
+
+    #[tokio::test]
+    async fn test_update_metadata_relay_not_responding() {
+        let local_relay = LocalRelay::run("127.0.0.1:0".parse().unwrap(), MemoryStore::new())
+            .await
+            .unwrap();
+        let url = local_relay.url().unwrap();
+        let silent_url = run_silent_relay().await;
+        let keys = Keys::generate_from_os_random();
+        let client = Client::new(&keys);
+        connect(&client, &[&url, &silent_url]).await;
+
+        // The silent relay may have the profile: nothing is published, not even with the opt-in
+        assert!(matches!(
+            client.update_metadata(|m| m.about("New bio")).await,
+            Err(Error::Timeout)
+        ));
+        assert!(matches!(
+            client
+                .update_metadata_or_default(|m| m.about("New bio"))
+                .await,
+            Err(Error::Timeout)
+        ));
+        let events = client
+            .get_events_from(
+                vec![url.to_string()],
+                own_metadata(&keys),
+                Some(Duration::from_secs(5)),
+            )
+            .await
+            .unwrap();
+        assert!(events.is_empty());
+    }
+
+    #[cfg(all(feature = "nip04", feature = "nip59"))]
+    #[tokio::test]
+    async fn test_send_direct_msg_to_many() {
+        let local_relay = LocalRelay::run("127.0.0.1:0".parse().unwrap(), MemoryStore::new())
+            .await
+            .unwrap();
+        let url = local_relay.url().unwrap();
+        let keys = Keys::generate_from_os_random();
+        let client = Client::new(&keys);
+        connect(&client, &[&url]).await;
+
+        let bob = Keys::generate_from_os_random();
+        let carol = Keys::generate_from_os_random();
+        let recipients = [bob.clone(), carol.clone()];
+
+        let results = client
+            .send_direct_msg_to_many(&recipients, "Hello everyone!", DirectMessageMode::Nip17)
+            .await;
+        assert_eq!(
+            results.iter().map(|(pk, _)| *pk).collect::<Vec<_>>(),
+            vec![bob.public_key(), carol.public_key()]
+        );
+        assert!(results.iter().all(|(_, result)| result.is_ok()));
+
+        // One gift wrap for each recipient, readable only by the recipient
+        for recipient in recipients.iter() {
+            let filter = SubscriptionFilter::new()
+                .kind(Kind::Base(KindBase::GiftWrap))
+                .pubkey(recipient.public_key());
+            let events = client
+                .get_events_of(vec![filter], Some(Duration::from_secs(5)))
+                .await
+                .unwrap();
+            assert_eq!(events.len(), 1);
+            assert_ne!(events[0].pubkey, keys.public_key());
+            let gift = nip59::unwrap(recipient, &events[0]).unwrap();
+            assert_eq!(gift.sender, keys.public_key());
+            assert_eq!(gift.rumor.content, "Hello everyone!");
+            assert!(nip59::unwrap(&keys, &events[0]).is_err());
+        }
+        // And a copy of each conversation for the sender
+        let filter = SubscriptionFilter::new()
+            .kind(Kind::Base(KindBase::GiftWrap))
+            .pubkey(keys.public_key());
+        let events = client
+            .get_events_of(vec![filter], Some(Duration::from_secs(5)))
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 2);
+
+        let results = client
+            .send_direct_msg_to_many(&recipients, "Hello again!", DirectMessageMode::Nip04)
+            .await;
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|(_, result)| result.is_ok()));
+        for recipient in recipients.iter() {
+            let filter = SubscriptionFilter::new()
+                .author(keys.public_key())
+                .kind(Kind::Base(KindBase::EncryptedDirectMessage))
+                .pubkey(recipient.public_key());
+            let events = client
+                .get_events_of(vec![filter], Some(Duration::from_secs(5)))
+                .await
+                .unwrap();
+            assert_eq!(events.len(), 1);
+            assert_ne!(events[0].content, "Hello again!");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_event_streams() {
+        let relay_a = LocalRelay::run("127.0.0.1:0".parse().unwrap(), MemoryStore::new())
+            .await
+            .unwrap();
+        let relay_b = LocalRelay::run("127.0.0.1:0".parse().unwrap(), MemoryStore::new())
+            .await
+            .unwrap();
+        let urls = [relay_a.url().unwrap(), relay_b.url().unwrap()];
+
+        let bob = Keys::generate_from_os_random();
+        let sender = Client::new(&bob);
+        connect(&sender, &[&urls[0], &urls[1]]).await;
+
+        let client = Client::new(&Keys::generate_from_os_random());
+        connect(&client, &[&urls[0], &urls[1]]).await;
+        client
+            .subscribe(vec![SubscriptionFilter::new().author(bob.public_key())])
+            .await
+            .unwrap();
+
+        let notes_filter = SubscriptionFilter::new()
+            .author(bob.public_key())
+            .kind(Kind::Base(KindBase::TextNote));
+        let mut notes = client.events_of(vec![notes_filter]);
+        let mut received = client.notifications_stream().filter_map(|n| {
+            future::ready(match n {
+                RelayPoolNotifications::ReceivedEvent { event, .. } => Some(event.kind),
+                _ => None,
+            })
+        });
+
+        sender
+            .update_metadata_or_default(|m| m.name("bob"))
+            .await
+            .unwrap();
+        sender.publish_text_note("Hello", &[]).await.unwrap();
+
+        // Only the events matching the filters, once, also if received from both relays
+        let note = tokio::time::timeout(Duration::from_secs(5), notes.next())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(note.content, "Hello");
+        assert!(tokio::time::timeout(Duration::from_secs(1), notes.next())
+            .await
+            .is_err());
+
+        // The notification stream has all of them
+        let mut kinds = Vec::new();
+        for _ in 0..2 {
+            kinds.push(
+                tokio::time::timeout(Duration::from_secs(5), received.next())
+                    .await
+                    .unwrap()
+                    .unwrap(),
+            );
+        }
+        assert!(kinds.contains(&Kind::Base(KindBase::Metadata)));
+        assert!(kinds.contains(&Kind::Base(KindBase::TextNote)));
+    }
+}
@@ -0,0 +1,219 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use crate::relay::pool::RelayPoolOptions;
+use crate::relay::ScoringOptions;
+
+/// Options of the [`Client`](super::Client)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Options {
+    difficulty: u8,
+    auto_authenticate: bool,
+    gossip: bool,
+    pool: RelayPoolOptions,
+}
+
+impl Options {
+    /// New default `Options`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wait until the messages have been written to the relays (default: `false`)
+    ///
+    /// See [`RelayPoolOptions::wait_for_send`].
+    pub fn wait_for_send(self, wait: bool) -> Self {
+        Self {
+            pool: self.pool.wait_for_send(wait),
+            ..self
+        }
+    }
+
+    /// Set max time to wait for a message to be sent (default: 20 secs)
+    pub fn send_timeout(self, timeout: Duration) -> Self {
+        Self {
+            pool: self.pool.send_timeout(timeout),
+            ..self
+        }
+    }
+
+    /// Set relay connection timeout (default: 60 secs)
+    pub fn connection_timeout(self, timeout: Duration) -> Self {
+        Self {
+            pool: self.pool.connection_timeout(timeout),
+            ..self
+        }
+    }
+
+    /// Reconnect automatically to the relays after a disconnection (default: `true`)
+    pub fn reconnect(self, reconnect: bool) -> Self {
+        Self {
+            pool: self.pool.reconnect(reconnect),
+            ..self
+        }
+    }
+
+    /// Set interval between the WebSocket pings sent to keep the connections alive (default: 60 secs)
+    pub fn ping_interval(self, interval: Duration) -> Self {
+        Self {
+            pool: self.pool.ping_interval(interval),
+            ..self
+        }
+    }
+
+    /// Set max time to wait for a frame from a relay after a ping, before reconnecting (default: 20 secs)
+    ///
+    /// See [`RelayPoolOptions::pong_timeout`].
+    pub fn pong_timeout(self, timeout: Duration) -> Self {
+        Self {
+            pool: self.pool.pong_timeout(timeout),
+            ..self
+        }
+    }
+
+    /// Temporarily disable the relays that repeatedly fail (default: disabled)
+    ///
+    /// See [`ScoringOptions`].
+    pub fn scoring(self, scoring: ScoringOptions) -> Self {
+        Self {
+            pool: self.pool.scoring(scoring),
+            ..self
+        }
+    }
+
+    /// Set SOCKS5 proxy for all the traffic of the client (default: none)
+    ///
+    /// Used for the relay connections, the NIP-11 requests and the NIP-05 verifications.
+    /// The proxy passed to [`Client::add_relay`](super::Client::add_relay) overrides it
+    /// for a single relay. Webhook notification sinks don't use it.
+    pub fn proxy(self, proxy: Option<SocketAddr>) -> Self {
+        Self {
+            pool: self.pool.proxy(proxy),
+            ..self
+        }
+    }
+
+    /// Set proof of work difficulty of the published events (default: `0`, disabled)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/13.md>
+    pub fn difficulty(self, difficulty: u8) -> Self {
+        Self { difficulty, ..self }
+    }
+
+    /// Automatically authenticate to the relays with the client keys (default: `false`)
+    ///
+    /// When a relay sends an `AUTH` challenge, the pool responds with a signed kind-22242 event,
+    /// then sends again the events and the subscriptions rejected with `auth-required:`.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/42.md>
+    pub fn auto_authenticate(self, enabled: bool) -> Self {
+        Self {
+            auto_authenticate: enabled,
+            ..self
+        }
+    }
+
+    /// Route the subscriptions and the events with the NIP-65 relay lists (default: `false`)
+    ///
+    /// The relay lists (kind 10002) of the authors and of the tagged users are fetched from the
+    /// read relays and cached. Then filters with `authors` (or `#p`) are sent to the write
+    /// relays of the authors (or to the read relays of the tagged users) and the events are
+    /// published also to the read relays of the users tagged with `p`. Relays missing from the
+    /// pool are added as neither read nor write relays, to be used only by this routing.
+    ///
+    /// Applies to [`Client::subscribe_with_id`](super::Client::subscribe_with_id),
+    /// [`Client::add_subscription`](super::Client::add_subscription),
+    /// [`Client::get_events_of`](super::Client::get_events_of) and
+    /// [`Client::send_event`](super::Client::send_event).
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/65.md>
+    pub fn gossip(self, enabled: bool) -> Self {
+        Self {
+            gossip: enabled,
+            ..self
+        }
+    }
+
+    /// Drop received events that are expired (default: `false`)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/40.md>
+    pub fn drop_expired(self, enabled: bool) -> Self {
+        Self {
+            pool: self.pool.drop_expired(enabled),
+            ..self
+        }
+    }
+
+    /// Drop received events with a proof of work below `difficulty` (default: `0`, disabled)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/13.md>
+    pub fn min_pow(self, difficulty: u8) -> Self {
+        Self {
+            pool: self.pool.min_pow(difficulty),
+            ..self
+        }
+    }
+
+    /// Resubscribe only to the new events after a relay reconnection (default: `false`)
+    ///
+    /// The active subscriptions are always sent again when a relay reconnects. If enabled,
+    /// the `since` of their filters is moved to the last event received from that relay.
+    pub fn resubscribe_since(self, enabled: bool) -> Self {
+        Self {
+            pool: self.pool.resubscribe_since(enabled),
+            ..self
+        }
+    }
+
+    /// Set size of the notification channel (default: 64)
+    pub fn notification_channel_size(self, size: usize) -> Self {
+        Self {
+            pool: self.pool.notification_channel_size(size),
+            ..self
+        }
+    }
+
+    /// Set size of the queue of the messages received from the relays (default: 64)
+    pub fn task_channel_size(self, size: usize) -> Self {
+        Self {
+            pool: self.pool.task_channel_size(size),
+            ..self
+        }
+    }
+
+    /// Set size of the queue of the messages to send to each relay (default: 64)
+    pub fn relay_channel_size(self, size: usize) -> Self {
+        Self {
+            pool: self.pool.relay_channel_size(size),
+            ..self
+        }
+    }
+
+    /// Proof of work difficulty of the published events
+    pub fn get_difficulty(&self) -> u8 {
+        self.difficulty
+    }
+
+    /// Check if the client automatically authenticates to the relays
+    pub fn get_auto_authenticate(&self) -> bool {
+        self.auto_authenticate
+    }
+
+    /// Check if the gossip routing is enabled
+    pub fn get_gossip(&self) -> bool {
+        self.gossip
+    }
+
+    /// SOCKS5 proxy of the client
+    pub fn get_proxy(&self) -> Option<SocketAddr> {
+        self.pool.get_proxy()
+    }
+
+    /// Relay pool options
+    pub fn get_pool_opts(&self) -> RelayPoolOptions {
+        self.pool
+    }
+}
@@ -11,15 +11,45 @@ use tokio::runtime::{Builder, Runtime};
 pub use nostr;
 pub use nostr::Result;
 
+pub mod accounts;
+pub mod cipher;
 pub mod client;
+#[cfg(feature = "local-relay")]
+pub mod local_relay;
+#[cfg(feature = "nip39")]
+pub mod nip39;
+#[cfg(feature = "nip46")]
+pub mod nip46;
+#[cfg(feature = "nip47")]
+pub mod nip47;
+#[cfg(feature = "nip96")]
+pub mod nip96;
+pub mod outbox;
 pub mod relay;
+pub mod sink;
+pub mod store;
 pub mod subscription;
+mod util;
 
+pub use self::accounts::Accounts;
+pub use self::cipher::StoreCipher;
 #[cfg(feature = "blocking")]
 pub use self::client::blocking;
-pub use self::client::Client;
-pub use self::relay::pool::{RelayPool, RelayPoolNotifications};
-pub use self::relay::{Relay, RelayStatus};
+#[cfg(any(feature = "nip04", feature = "nip59"))]
+pub use self::client::DirectMessageMode;
+pub use self::client::{Client, Options};
+pub use self::outbox::Outbox;
+pub use self::relay::pool::{
+    ConnectionReport, EventSource, RelayPool, RelayPoolNotifications, RelayPoolOptions,
+};
+#[cfg(feature = "nip11")]
+pub use self::relay::PaymentHandler;
+pub use self::relay::{
+    Relay, RelayOptions, RelayStats, RelayStatus, RelayTransport, ScoringOptions, TlsOptions,
+    WebSocketTransport,
+};
+pub use self::sink::NotificationSink;
+pub use self::subscription::SubscriptionId;
 
 #[cfg(feature = "blocking")]
 static RUNTIME: Lazy<Runtime> = Lazy::new(|| Runtime::new().expect("Can't start Tokio runtime"));
@@ -0,0 +1,388 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+#[cfg(feature = "nip11")]
+use std::sync::RwLock;
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use nostr::url::Url;
+#[cfg(feature = "nip11")]
+use nostr::util::nips::nip11::RelayInformationDocument;
+use nostr::{ClientMessage, Event, Kind, RelayMessage, SubscriptionFilter};
+#[cfg(feature = "nip11")]
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio_tungstenite::tungstenite::Message;
+
+#[cfg(feature = "blocking")]
+use crate::new_current_thread;
+use crate::store::EventStore;
+
+/// Interval between the [`EventStore::vacuum`] calls
+const VACUUM_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// I/O error
+    #[error("io error: {0}")]
+    IO(#[from] std::io::Error),
+    /// Url error
+    #[error("url error: {0}")]
+    Url(#[from] nostr::url::ParseError),
+}
+
+/// Embedded relay (NIP-01, NIP-15 and NIP-20, plus NIP-11 with [`LocalRelay::set_information_document`])
+///
+/// Listen for WebSocket connections and serve the events of an [`EventStore`],
+/// so it can be used as a local cache also by other clients on the same machine.
+#[derive(Debug, Clone)]
+pub struct LocalRelay {
+    addr: SocketAddr,
+    shutdown: broadcast::Sender<()>,
+    #[cfg(feature = "nip11")]
+    document: Arc<RwLock<Option<RelayInformationDocument>>>,
+}
+
+impl LocalRelay {
+    /// Bind to `addr` and start serving the events of `store`
+    ///
+    /// Use port `0` to let the OS choose a free port (see [`LocalRelay::url`]).
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr_sdk::local_relay::LocalRelay;
+    /// use nostr_sdk::store::MemoryStore;
+    /// use nostr_sdk::Client;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Client::generate_keys();
+    /// let relay = LocalRelay::run("127.0.0.1:0".parse().unwrap(), MemoryStore::new())
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// let client = Client::new(&my_keys);
+    /// client
+    ///     .add_relay(relay.url().unwrap().as_str(), None)
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub async fn run<S>(addr: SocketAddr, store: S) -> Result<Self, Error>
+    where
+        S: EventStore + 'static,
+    {
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        let (shutdown, mut shutdown_receiver) = broadcast::channel::<()>(1);
+
+        let store: Arc<dyn EventStore> = Arc::new(store);
+        let (new_events, _) = broadcast::channel::<Event>(1024);
+        #[cfg(feature = "nip11")]
+        let document: Arc<RwLock<Option<RelayInformationDocument>>> = Arc::new(RwLock::new(None));
+        #[cfg(feature = "nip11")]
+        let doc = document.clone();
+        let connections_shutdown = shutdown.clone();
+
+        let func = async move {
+            log::info!("Local relay listening on {}", addr);
+            let mut vacuum = tokio::time::interval(VACUUM_INTERVAL);
+            loop {
+                tokio::select! {
+                    res = listener.accept() => match res {
+                        Ok((stream, peer)) => {
+                            #[cfg(feature = "nip11")]
+                            let document = match doc.read() {
+                                Ok(document) => document.clone(),
+                                Err(poisoned) => poisoned.into_inner().clone(),
+                            };
+                            tokio::task::spawn(handle_connection(
+                                stream,
+                                peer,
+                                store.clone(),
+                                new_events.clone(),
+                                connections_shutdown.subscribe(),
+                                #[cfg(feature = "nip11")]
+                                document,
+                            ));
+                        }
+                        Err(e) => log::error!("Impossible to accept connection: {}", e),
+                    },
+                    _ = vacuum.tick() => {
+                        let removed = store.vacuum();
+                        if removed > 0 {
+                            log::debug!("Local relay {}: {} events removed by the retention policy", addr, removed);
+                        }
+                    }
+                    _ = shutdown_receiver.recv() => break,
+                }
+            }
+            log::info!("Local relay {} stopped", addr);
+        };
+
+        #[cfg(feature = "blocking")]
+        match new_current_thread() {
+            Ok(rt) => {
+                std::thread::spawn(move || {
+                    rt.block_on(func);
+                    rt.shutdown_timeout(Duration::from_millis(100));
+                });
+            }
+            Err(e) => log::error!("Impossible to create new thread: {:?}", e),
+        };
+
+        #[cfg(not(feature = "blocking"))]
+        tokio::task::spawn(func);
+
+        Ok(Self {
+            addr,
+            shutdown,
+            #[cfg(feature = "nip11")]
+            document,
+        })
+    }
+
+    /// Serve `document` to the NIP-11 requests (`None` to not serve any)
+    ///
+    /// The `max_limit` of the document is enforced on the new connections.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/11.md>
+    #[cfg(feature = "nip11")]
+    pub fn set_information_document(&self, document: Option<RelayInformationDocument>) {
+        match self.document.write() {
+            Ok(mut d) => *d = document,
+            Err(poisoned) => *poisoned.into_inner() = document,
+        }
+    }
+
+    /// Get the address the relay is listening on
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Get relay url (`ws://<addr>`)
+    pub fn url(&self) -> Result<Url, Error> {
+        Ok(Url::parse(&format!("ws://{}", self.addr))?)
+    }
+
+    /// Stop accepting new connections and close the active ones
+    pub fn shutdown(&self) {
+        let _ = self.shutdown.send(());
+    }
+}
+
+/// Ephemeral events are forwarded to the subscribers but not stored
+fn is_ephemeral(kind: &Kind) -> bool {
+    (20000..30000).contains(&u64::from(*kind))
+}
+
+/// Read the HTTP request headers without consuming them
+///
+/// Return `None` if they are not received in time.
+#[cfg(feature = "nip11")]
+async fn peek_request(stream: &TcpStream) -> Option<String> {
+    let mut buf = [0u8; 4096];
+    for _ in 0..50 {
+        let n = stream.peek(&mut buf).await.ok()?;
+        let request = String::from_utf8_lossy(&buf[..n]).to_lowercase();
+        if request.contains("\r\n\r\n") || n == buf.len() {
+            return Some(request);
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    None
+}
+
+/// Respond to a NIP-11 request (`Accept: application/nostr+json` without WebSocket upgrade)
+#[cfg(feature = "nip11")]
+async fn send_information_document(
+    mut stream: TcpStream,
+    request: &str,
+    document: &RelayInformationDocument,
+) -> std::io::Result<()> {
+    let mut buf = vec![
+        0u8;
+        request
+            .find("\r\n\r\n")
+            .map(|i| i + 4)
+            .unwrap_or(request.len())
+    ];
+    stream.read_exact(&mut buf).await?;
+
+    let body = serde_json::to_string(document)?;
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/nostr+json\r\nAccess-Control-Allow-Origin: *\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+async fn handle_connection(
+    stream: TcpStream,
+    peer: SocketAddr,
+    store: Arc<dyn EventStore>,
+    new_events: broadcast::Sender<Event>,
+    mut shutdown: broadcast::Receiver<()>,
+    #[cfg(feature = "nip11")] document: Option<RelayInformationDocument>,
+) {
+    #[cfg(feature = "nip11")]
+    let max_limit: Option<u16> = match document {
+        Some(document) => match peek_request(&stream).await {
+            Some(request)
+                if request.contains("application/nostr+json")
+                    && !request.contains("upgrade: websocket") =>
+            {
+                if let Err(e) = send_information_document(stream, &request, &document).await {
+                    log::debug!("Impossible to send information document to {}: {}", peer, e);
+                }
+                return;
+            }
+            _ => document
+                .max_limit()
+                .map(|max_limit| max_limit.min(u16::MAX as u64) as u16),
+        },
+        None => None,
+    };
+    #[cfg(not(feature = "nip11"))]
+    let max_limit: Option<u16> = None;
+
+    let ws_stream = match tokio_tungstenite::accept_async(stream).await {
+        Ok(ws_stream) => ws_stream,
+        Err(e) => {
+            log::debug!("WebSocket handshake with {} failed: {}", peer, e);
+            return;
+        }
+    };
+    log::debug!("New connection from {}", peer);
+
+    let (mut ws_tx, mut ws_rx) = ws_stream.split();
+    let mut events_receiver = new_events.subscribe();
+    let mut subscriptions: HashMap<String, Vec<SubscriptionFilter>> = HashMap::new();
+
+    loop {
+        let replies: Vec<RelayMessage> = tokio::select! {
+            msg = ws_rx.next() => match msg {
+                Some(Ok(Message::Text(json))) => match ClientMessage::from_json(&json) {
+                    Ok(msg) => handle_client_msg(msg, &store, &new_events, &mut subscriptions, max_limit),
+                    Err(e) => vec![RelayMessage::new_notice(format!("error: {}", e))],
+                },
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => continue,
+            },
+            event = events_receiver.recv() => match event {
+                Ok(event) => subscriptions
+                    .iter()
+                    .filter(|(_, filters)| filters.iter().any(|f| f.match_event(&event)))
+                    .map(|(id, _)| RelayMessage::new_event(id.clone(), event.clone()))
+                    .collect(),
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    log::warn!("Local relay connection {} lagged: {} events skipped", peer, n);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = shutdown.recv() => {
+                let _ = ws_tx.close().await;
+                break;
+            }
+        };
+
+        for reply in replies.into_iter() {
+            if let Err(e) = ws_tx.send(Message::Text(reply.to_json())).await {
+                log::error!("Impossible to send message to {}: {}", peer, e);
+                return;
+            }
+        }
+    }
+
+    log::debug!("Connection from {} closed", peer);
+}
+
+fn handle_client_msg(
+    msg: ClientMessage,
+    store: &Arc<dyn EventStore>,
+    new_events: &broadcast::Sender<Event>,
+    subscriptions: &mut HashMap<String, Vec<SubscriptionFilter>>,
+    max_limit: Option<u16>,
+) -> Vec<RelayMessage> {
+    match msg {
+        ClientMessage::Event { event } => {
+            let id = event.id;
+            if event.is_expired() {
+                return vec![RelayMessage::new_ok(
+                    id,
+                    false,
+                    String::from("invalid: event is expired"),
+                )];
+            }
+            if is_ephemeral(&event.kind) || store.save_event(&event) {
+                let _ = new_events.send(event);
+                vec![RelayMessage::new_ok(id, true, String::new())]
+            } else {
+                vec![RelayMessage::new_ok(
+                    id,
+                    true,
+                    String::from("duplicate: already have this event"),
+                )]
+            }
+        }
+        ClientMessage::Req {
+            subscription_id,
+            filters,
+        } => {
+            let filters: Vec<SubscriptionFilter> = match max_limit {
+                Some(max_limit) => filters
+                    .into_iter()
+                    .map(|f| {
+                        let limit = f.limit.unwrap_or(max_limit).min(max_limit);
+                        f.limit(limit)
+                    })
+                    .collect(),
+                None => filters,
+            };
+            let mut replies: Vec<RelayMessage> = store
+                .query(&filters)
+                .into_iter()
+                .map(|event| RelayMessage::new_event(subscription_id.clone(), event))
+                .collect();
+            replies.push(RelayMessage::new_eose(subscription_id.clone()));
+            subscriptions.insert(subscription_id, filters);
+            replies
+        }
+        ClientMessage::Close { subscription_id } => {
+            subscriptions.remove(&subscription_id);
+            Vec::new()
+        }
+        // The local relay never sends AUTH challenges
+        ClientMessage::Auth { event } => vec![RelayMessage::new_ok(
+            event.id,
+            false,
+            String::from("error: authentication not supported"),
+        )],
+    }
+}
+
+/// Relay accepting the WebSocket connections and then never reading from them
+#[cfg(test)]
+pub(crate) async fn run_silent_relay() -> Url {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = Url::parse(&format!("ws://{}", listener.local_addr().unwrap())).unwrap();
+    tokio::task::spawn(async move {
+        // The information document request fails the handshake
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::task::spawn(async move {
+                if let Ok(_ws_stream) = tokio_tungstenite::accept_async(stream).await {
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                }
+            });
+        }
+    });
+    url
+}
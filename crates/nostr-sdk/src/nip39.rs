@@ -0,0 +1,76 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::net::SocketAddr;
+
+use nostr::key::{self, ToBech32, XOnlyPublicKey};
+use nostr::util::nips::nip39::{self, Identity};
+use reqwest::{Client, Proxy};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("reqwest error: {0}")]
+    Reqwest(#[from] reqwest::Error),
+    #[error("key error: {0}")]
+    Key(#[from] key::Error),
+    #[error("nip39 error: {0}")]
+    NIP39(#[from] nip39::Error),
+    /// The proof doesn't contain the public key
+    #[error("impossible to verify")]
+    ImpossibleToVerify,
+}
+
+/// Verify an external identity claim (NIP-39)
+///
+/// Fetch the proof url and check that it contains the bech32 encoded `public_key`.
+/// Pages that are rendered client-side (ex. Twitter) may require a proxy returning the post text.
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/39.md>
+///
+/// # Example
+/// ```rust,no_run
+/// use nostr::util::nips::nip39::{ExternalIdentity, Identity};
+/// use nostr::Keys;
+/// use nostr_sdk::nip39;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// # let keys = Keys::generate_from_os_random();
+/// let identity = Identity::new(
+///     ExternalIdentity::GitHub,
+///     "semisol",
+///     "9721ce4ee4fceb91c9711ca2a6c9a5ab",
+/// );
+/// nip39::verify(&keys.public_key(), &identity, None)
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+pub async fn verify(
+    public_key: &XOnlyPublicKey,
+    identity: &Identity,
+    proxy: Option<SocketAddr>,
+) -> Result<(), Error> {
+    let npub: String = public_key.to_bech32()?;
+    let mut builder = Client::builder();
+
+    if let Some(proxy) = proxy {
+        let proxy = format!("socks5h://{}", proxy);
+        builder = builder.proxy(Proxy::all(proxy)?);
+    }
+
+    let body: String = builder
+        .build()?
+        .get(identity.proof_url()?)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+
+    if body.contains(&npub) {
+        Ok(())
+    } else {
+        Err(Error::ImpossibleToVerify)
+    }
+}
@@ -0,0 +1,793 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::collections::HashSet;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use nostr::event::builder::Error as EventBuilderError;
+use nostr::key::XOnlyPublicKey;
+use nostr::signer::{Error as SignerError, NostrSigner, SignerFuture};
+use nostr::url::Url;
+use nostr::util::nips::nip04;
+use nostr::util::nips::nip46::{self, Message, NostrConnectURI, Request};
+use nostr::util::time::timestamp;
+use nostr::{
+    ClientMessage, Event, EventBuilder, Keys, Kind, KindBase, SubscriptionFilter, UnsignedEvent,
+};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::Mutex;
+
+use crate::relay::pool::{Error as RelayPoolError, RelayPool, RelayPoolNotifications};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("relay pool error: {0}")]
+    RelayPool(#[from] RelayPoolError),
+    #[error("event builder error: {0}")]
+    EventBuilder(#[from] EventBuilderError),
+    #[error("event error: {0}")]
+    Event(#[from] nostr::event::Error),
+    #[error("nip46 error: {0}")]
+    NIP46(#[from] nip46::Error),
+    /// The `nostrconnect://` URI isn't for the app keys
+    #[error("uri public key mismatch")]
+    PublicKeyMismatch,
+    /// Not connected to the remote signer
+    #[error("remote signer not connected")]
+    NotConnected,
+    /// The remote signer returned an unexpected result
+    #[error("unexpected result: {0}")]
+    UnexpectedResult(String),
+    /// No response in time
+    #[error("timeout")]
+    Timeout,
+}
+
+/// NIP-46 remote signer client
+///
+/// Send the requests to the remote signer (`bunker`) as NIP-04 encrypted ephemeral events
+/// and wait for its responses on the relays of the [`NostrConnectURI`].
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/46.md>
+#[derive(Debug, Clone)]
+pub struct Nip46Signer {
+    uri: NostrConnectURI,
+    app_keys: Keys,
+    pool: RelayPool,
+    signer_public_key: Arc<Mutex<Option<XOnlyPublicKey>>>,
+    public_key: Arc<Mutex<Option<XOnlyPublicKey>>>,
+    timeout: Duration,
+}
+
+impl Nip46Signer {
+    /// New NIP-46 remote signer client
+    ///
+    /// With a `bunker://` URI the app connects to the remote signer,
+    /// with a `nostrconnect://` URI (for `app_keys`) the app waits for the remote signer to connect.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use std::str::FromStr;
+    /// use std::time::Duration;
+    ///
+    /// use nostr::util::nips::nip46::NostrConnectURI;
+    /// use nostr_sdk::nip46::Nip46Signer;
+    /// use nostr_sdk::{Client, Options};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let app_keys = Client::generate_keys();
+    /// let uri = NostrConnectURI::from_str("bunker://79dff8f82963424e0bb02708a22e44b4980893e3a4be0fa3cb60a43b946764e3?relay=wss://relay.nsec.app").unwrap();
+    /// let signer = Nip46Signer::new(uri, app_keys.clone(), Duration::from_secs(60)).unwrap();
+    /// signer.connect().await.unwrap();
+    ///
+    /// let client = Client::with_remote_signer(&app_keys, signer, Options::default());
+    /// # }
+    /// ```
+    pub fn new(uri: NostrConnectURI, app_keys: Keys, timeout: Duration) -> Result<Self, Error> {
+        let signer_public_key = match &uri {
+            NostrConnectURI::Bunker {
+                signer_public_key, ..
+            } => Some(*signer_public_key),
+            NostrConnectURI::Client { public_key, .. } => {
+                if *public_key != app_keys.public_key() {
+                    return Err(Error::PublicKeyMismatch);
+                }
+                None
+            }
+        };
+
+        Ok(Self {
+            uri,
+            app_keys,
+            pool: RelayPool::new(),
+            signer_public_key: Arc::new(Mutex::new(signer_public_key)),
+            public_key: Arc::new(Mutex::new(None)),
+            timeout,
+        })
+    }
+
+    /// Get the [`NostrConnectURI`]
+    pub fn uri(&self) -> NostrConnectURI {
+        self.uri.clone()
+    }
+
+    /// Get the remote signer public key, if connected
+    pub async fn signer_public_key(&self) -> Option<XOnlyPublicKey> {
+        *self.signer_public_key.lock().await
+    }
+
+    /// Connect to the relays and to the remote signer
+    pub async fn connect(&self) -> Result<(), Error> {
+        for url in self.uri.relays().iter() {
+            self.pool.add_relay(url.clone(), None).await;
+        }
+        self.pool.connect(true).await?;
+
+        let filter = SubscriptionFilter::new()
+            .kind(Kind::Base(KindBase::NostrConnect))
+            .pubkey(self.app_keys.public_key())
+            .since(timestamp());
+        let mut notifications = self.pool.notifications();
+        self.pool.subscribe(vec![filter]).await?;
+
+        match &self.uri {
+            NostrConnectURI::Bunker {
+                signer_public_key,
+                secret,
+                ..
+            } => {
+                self.send_request(Request::Connect {
+                    signer_public_key: *signer_public_key,
+                    secret: secret.clone(),
+                })
+                .await?;
+            }
+            NostrConnectURI::Client { .. } => {
+                let signer_public_key = self.wait_for_signer(&mut notifications).await?;
+                *self.signer_public_key.lock().await = Some(signer_public_key);
+            }
+        }
+
+        // Cache the user public key
+        self.public_key().await?;
+
+        Ok(())
+    }
+
+    /// Disconnect from the relays
+    pub async fn disconnect(&self) -> Result<(), Error> {
+        Ok(self.pool.disconnect().await?)
+    }
+
+    /// Get the public key of the user (may be different from the remote signer one)
+    pub async fn public_key(&self) -> Result<XOnlyPublicKey, Error> {
+        let mut public_key = self.public_key.lock().await;
+        match *public_key {
+            Some(pk) => Ok(pk),
+            None => {
+                let result = self.send_request(Request::GetPublicKey).await?;
+                let pk = XOnlyPublicKey::from_str(&result)
+                    .map_err(|_| Error::UnexpectedResult(result))?;
+                *public_key = Some(pk);
+                Ok(pk)
+            }
+        }
+    }
+
+    /// Sign an [`UnsignedEvent`] of the user
+    pub async fn sign_event(&self, unsigned: UnsignedEvent) -> Result<Event, Error> {
+        let id = unsigned.id;
+        let result = self.send_request(Request::SignEvent(unsigned)).await?;
+        let event = Event::from_json(result)?;
+        if event.id != id {
+            return Err(Error::UnexpectedResult(event.id.to_string()));
+        }
+        Ok(event)
+    }
+
+    /// Encrypt text for `public_key` (NIP-04)
+    pub async fn nip04_encrypt<S>(
+        &self,
+        public_key: XOnlyPublicKey,
+        text: S,
+    ) -> Result<String, Error>
+    where
+        S: Into<String>,
+    {
+        self.send_request(Request::Nip04Encrypt {
+            public_key,
+            text: text.into(),
+        })
+        .await
+    }
+
+    /// Decrypt content received from `public_key` (NIP-04)
+    pub async fn nip04_decrypt<S>(
+        &self,
+        public_key: XOnlyPublicKey,
+        ciphertext: S,
+    ) -> Result<String, Error>
+    where
+        S: Into<String>,
+    {
+        self.send_request(Request::Nip04Decrypt {
+            public_key,
+            ciphertext: ciphertext.into(),
+        })
+        .await
+    }
+
+    /// Check that the remote signer is alive
+    pub async fn ping(&self) -> Result<(), Error> {
+        let result = self.send_request(Request::Ping).await?;
+        if result != "pong" {
+            return Err(Error::UnexpectedResult(result));
+        }
+        Ok(())
+    }
+
+    /// Send request and wait for the result
+    async fn send_request(&self, req: Request) -> Result<String, Error> {
+        let signer_public_key = self.signer_public_key().await.ok_or(Error::NotConnected)?;
+
+        let msg = Message::request(req)?;
+        let event: Event = EventBuilder::nostr_connect(&self.app_keys, signer_public_key, &msg)?
+            .to_event(&self.app_keys)?;
+
+        let mut notifications = self.pool.notifications();
+        self.pool
+            .send_client_msg(ClientMessage::new_event(event))
+            .await?;
+
+        let response = tokio::time::timeout(self.timeout, async {
+            while let Ok(notification) = notifications.recv().await {
+                if let RelayPoolNotifications::ReceivedEvent { event, .. } = notification {
+                    if event.pubkey != signer_public_key {
+                        continue;
+                    }
+                    match Message::from_event(&self.app_keys, &event) {
+                        Ok(response) if response.is_response() && response.id() == msg.id() => {
+                            return Some(response)
+                        }
+                        Ok(_) => (),
+                        Err(e) => log::error!("Impossible to parse NIP-46 message: {}", e),
+                    }
+                }
+            }
+            None
+        })
+        .await
+        .map_err(|_| Error::Timeout)?
+        .ok_or(Error::Timeout)?;
+
+        Ok(response.result()?.to_string())
+    }
+
+    /// Wait for the `connect` of the remote signer (`nostrconnect://` flow)
+    async fn wait_for_signer(
+        &self,
+        notifications: &mut tokio::sync::broadcast::Receiver<RelayPoolNotifications>,
+    ) -> Result<XOnlyPublicKey, Error> {
+        tokio::time::timeout(self.timeout, async {
+            while let Ok(notification) = notifications.recv().await {
+                if let RelayPoolNotifications::ReceivedEvent { event, .. } = notification {
+                    match Message::from_event(&self.app_keys, &event) {
+                        Ok(msg) => {
+                            if let Ok(Request::Connect { .. }) = msg.to_request() {
+                                let signer_public_key = event.pubkey;
+                                let ack =
+                                    Message::response(msg.id(), Some(String::from("ack")), None);
+                                let event = EventBuilder::nostr_connect(
+                                    &self.app_keys,
+                                    signer_public_key,
+                                    &ack,
+                                )?
+                                .to_event(&self.app_keys)?;
+                                self.pool
+                                    .send_client_msg(ClientMessage::new_event(event))
+                                    .await?;
+                                return Ok(signer_public_key);
+                            }
+                        }
+                        Err(e) => log::error!("Impossible to parse NIP-46 message: {}", e),
+                    }
+                }
+            }
+            Err(Error::Timeout)
+        })
+        .await
+        .map_err(|_| Error::Timeout)?
+    }
+}
+
+impl From<Error> for SignerError {
+    fn from(e: Error) -> Self {
+        Self::Backend(e.to_string())
+    }
+}
+
+impl NostrSigner for Nip46Signer {
+    fn public_key(&self) -> SignerFuture<'_, XOnlyPublicKey> {
+        Box::pin(async move { Ok(Nip46Signer::public_key(self).await?) })
+    }
+
+    fn sign_event(&self, unsigned: UnsignedEvent) -> SignerFuture<'_, Event> {
+        Box::pin(async move { Ok(Nip46Signer::sign_event(self, unsigned).await?) })
+    }
+
+    fn nip04_encrypt(&self, public_key: XOnlyPublicKey, text: String) -> SignerFuture<'_, String> {
+        Box::pin(async move { Ok(Nip46Signer::nip04_encrypt(self, public_key, text).await?) })
+    }
+
+    fn nip04_decrypt(
+        &self,
+        public_key: XOnlyPublicKey,
+        ciphertext: String,
+    ) -> SignerFuture<'_, String> {
+        Box::pin(async move { Ok(Nip46Signer::nip04_decrypt(self, public_key, ciphertext).await?) })
+    }
+}
+
+type ApprovalFuture = Pin<Box<dyn Future<Output = bool> + Send>>;
+
+/// Approval callback of the [`Nip46Bunker`]
+#[derive(Clone)]
+pub struct ApprovalHandler {
+    func: Arc<dyn Fn(XOnlyPublicKey, Request) -> ApprovalFuture + Send + Sync>,
+}
+
+impl fmt::Debug for ApprovalHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ApprovalHandler").finish()
+    }
+}
+
+impl ApprovalHandler {
+    /// Create new `ApprovalHandler`
+    ///
+    /// The function receives the app public key and the request, and returns `true` to approve it.
+    pub fn new<F, Fut>(func: F) -> Self
+    where
+        F: Fn(XOnlyPublicKey, Request) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = bool> + Send + 'static,
+    {
+        Self {
+            func: Arc::new(move |app_public_key, req| Box::pin(func(app_public_key, req))),
+        }
+    }
+
+    async fn approve(&self, app_public_key: XOnlyPublicKey, req: Request) -> bool {
+        (self.func)(app_public_key, req).await
+    }
+}
+
+/// NIP-46 remote signer (`bunker`)
+///
+/// Hold the user [`Keys`], listen for the requests of the connected apps
+/// and respond with signatures, public key and NIP-04 encryption/decryption.
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/46.md>
+#[derive(Debug, Clone)]
+pub struct Nip46Bunker {
+    keys: Keys,
+    relays: Vec<Url>,
+    secret: Option<String>,
+    pool: RelayPool,
+    approval_handler: Option<ApprovalHandler>,
+    apps: Arc<Mutex<HashSet<XOnlyPublicKey>>>,
+}
+
+impl Nip46Bunker {
+    /// New NIP-46 remote signer
+    ///
+    /// An app can `connect` only if it knows the `secret` (from the [`Nip46Bunker::uri`])
+    /// or if the [`Nip46Bunker::approval_handler`] approves it.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr::url::Url;
+    /// use nostr_sdk::nip46::Nip46Bunker;
+    /// use nostr_sdk::Client;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Client::generate_keys();
+    /// let bunker = Nip46Bunker::new(
+    ///     my_keys,
+    ///     vec![Url::parse("wss://relay.nsec.app").unwrap()],
+    ///     Some(String::from("secret")),
+    /// )
+    /// .approval_handler(|app_public_key, req| async move {
+    ///     println!("Approve {} from {}?", req.method(), app_public_key);
+    ///     true
+    /// });
+    ///
+    /// println!("{}", bunker.uri());
+    /// bunker.run().await.unwrap();
+    /// # }
+    /// ```
+    pub fn new(keys: Keys, relays: Vec<Url>, secret: Option<String>) -> Self {
+        Self {
+            keys,
+            relays,
+            secret,
+            pool: RelayPool::new(),
+            approval_handler: None,
+            apps: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// Set the callback that approves `connect`, `sign_event` and NIP-04 requests
+    ///
+    /// Without it, `connect` requires the secret and every `sign_event` and NIP-04 request is rejected.
+    pub fn approval_handler<F, Fut>(self, func: F) -> Self
+    where
+        F: Fn(XOnlyPublicKey, Request) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = bool> + Send + 'static,
+    {
+        Self {
+            approval_handler: Some(ApprovalHandler::new(func)),
+            ..self
+        }
+    }
+
+    /// Get the `bunker://` URI to share with the apps
+    pub fn uri(&self) -> NostrConnectURI {
+        NostrConnectURI::Bunker {
+            signer_public_key: self.keys.public_key(),
+            relays: self.relays.clone(),
+            secret: self.secret.clone(),
+        }
+    }
+
+    /// Get the public keys of the connected apps
+    pub async fn apps(&self) -> HashSet<XOnlyPublicKey> {
+        self.apps.lock().await.clone()
+    }
+
+    /// Connect to an app that shared a `nostrconnect://` URI
+    ///
+    /// The relays of the URI are added to the bunker ones: call it before [`Nip46Bunker::run`].
+    pub async fn connect_app(&self, uri: &NostrConnectURI) -> Result<(), Error> {
+        let app_public_key = match uri {
+            NostrConnectURI::Client { public_key, .. } => *public_key,
+            NostrConnectURI::Bunker { .. } => return Err(Error::NIP46(nip46::Error::InvalidURI)),
+        };
+
+        for url in uri.relays().iter() {
+            self.pool.add_relay(url.clone(), None).await;
+        }
+        self.pool.connect(true).await?;
+
+        self.apps.lock().await.insert(app_public_key);
+
+        let msg = Message::request(Request::Connect {
+            signer_public_key: self.keys.public_key(),
+            secret: None,
+        })?;
+        let event: Event =
+            EventBuilder::nostr_connect(&self.keys, app_public_key, &msg)?.to_event(&self.keys)?;
+        self.pool
+            .send_client_msg(ClientMessage::new_event(event))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Connect to the relays and serve the requests of the apps
+    ///
+    /// Never returns, unless an error occurs.
+    pub async fn run(&self) -> Result<(), Error> {
+        for url in self.relays.iter() {
+            self.pool.add_relay(url.clone(), None).await;
+        }
+        self.pool.connect(true).await?;
+
+        let filter = SubscriptionFilter::new()
+            .kind(Kind::Base(KindBase::NostrConnect))
+            .pubkey(self.keys.public_key())
+            .since(timestamp());
+        let mut notifications = self.pool.notifications();
+        self.pool.subscribe(vec![filter]).await?;
+
+        loop {
+            match notifications.recv().await {
+                Ok(RelayPoolNotifications::ReceivedEvent { event, .. }) => {
+                    if event.kind != Kind::Base(KindBase::NostrConnect) {
+                        continue;
+                    }
+
+                    let msg = match Message::from_event(&self.keys, &event) {
+                        Ok(msg) => msg,
+                        Err(e) => {
+                            log::error!("Impossible to parse NIP-46 message: {}", e);
+                            continue;
+                        }
+                    };
+
+                    // Responses are sent only by apps that we connected with `connect_app`
+                    if msg.is_response() {
+                        continue;
+                    }
+
+                    let response = match msg.to_request() {
+                        Ok(req) => match self.handle_request(event.pubkey, req).await {
+                            Ok(result) => Message::response(msg.id(), Some(result), None),
+                            Err(e) => Message::response(msg.id(), None, Some(e)),
+                        },
+                        Err(e) => Message::response(msg.id(), None, Some(e.to_string())),
+                    };
+
+                    if let Err(e) = self.send_response(event.pubkey, &response).await {
+                        log::error!("Impossible to send NIP-46 response: {}", e);
+                    }
+                }
+                Ok(_) => (),
+                Err(RecvError::Lagged(n)) => {
+                    log::warn!("NIP-46 bunker lagged: {} notifications skipped", n)
+                }
+                Err(RecvError::Closed) => return Ok(()),
+            }
+        }
+    }
+
+    async fn send_response(
+        &self,
+        app_public_key: XOnlyPublicKey,
+        response: &Message,
+    ) -> Result<(), Error> {
+        let event: Event = EventBuilder::nostr_connect(&self.keys, app_public_key, response)?
+            .to_event(&self.keys)?;
+        self.pool
+            .send_client_msg(ClientMessage::new_event(event))
+            .await?;
+        Ok(())
+    }
+
+    /// Handle a request, returning the result or the error message
+    async fn handle_request(
+        &self,
+        app_public_key: XOnlyPublicKey,
+        req: Request,
+    ) -> Result<String, String> {
+        if let Request::Connect {
+            signer_public_key,
+            secret,
+        } = &req
+        {
+            if *signer_public_key != self.keys.public_key() {
+                return Err(String::from("invalid signer public key"));
+            }
+            match &self.secret {
+                Some(_) if *secret != self.secret => return Err(String::from("invalid secret")),
+                None if self.approval_handler.is_none() => {
+                    return Err(String::from("unauthorized: no secret or approval handler"))
+                }
+                _ => (),
+            }
+        } else if !self.apps.lock().await.contains(&app_public_key) {
+            return Err(String::from("unauthorized: connect first"));
+        }
+
+        if !matches!(req, Request::GetPublicKey | Request::Ping) {
+            match &self.approval_handler {
+                Some(handler) => {
+                    if !handler.approve(app_public_key, req.clone()).await {
+                        return Err(String::from("request rejected"));
+                    }
+                }
+                // Secret already checked above
+                None if matches!(req, Request::Connect { .. }) => (),
+                None => return Err(String::from("request rejected: no approval handler")),
+            }
+        }
+
+        match req {
+            Request::Connect { .. } => {
+                self.apps.lock().await.insert(app_public_key);
+                Ok(String::from("ack"))
+            }
+            Request::GetPublicKey => Ok(self.keys.public_key().to_string()),
+            Request::SignEvent(unsigned) => unsigned
+                .sign(&self.keys)
+                .map_err(|e| e.to_string())?
+                .as_json()
+                .map_err(|e| e.to_string()),
+            Request::Nip04Encrypt { public_key, text } => {
+                let secret_key = self.keys.secret_key().map_err(|e| e.to_string())?;
+                nip04::encrypt(&secret_key, &public_key, text).map_err(|e| e.to_string())
+            }
+            Request::Nip04Decrypt {
+                public_key,
+                ciphertext,
+            } => {
+                let secret_key = self.keys.secret_key().map_err(|e| e.to_string())?;
+                nip04::decrypt(&secret_key, &public_key, ciphertext).map_err(|e| e.to_string())
+            }
+            Request::Ping => Ok(String::from("pong")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::EventBuilder;
+
+    use super::*;
+
+    fn connect(bunker: &Nip46Bunker, secret: Option<&str>) -> Request {
+        Request::Connect {
+            signer_public_key: bunker.keys.public_key(),
+            secret: secret.map(String::from),
+        }
+    }
+
+    fn sign_event(bunker: &Nip46Bunker) -> Request {
+        Request::SignEvent(
+            EventBuilder::new_text_note("test", &[]).to_unsigned_event(bunker.keys.public_key()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_bunker_connect_secret() {
+        let app = Keys::generate_from_os_random();
+        let bunker = Nip46Bunker::new(
+            Keys::generate_from_os_random(),
+            Vec::new(),
+            Some(String::from("secret")),
+        );
+
+        assert!(bunker
+            .handle_request(app.public_key(), connect(&bunker, None))
+            .await
+            .is_err());
+        assert!(bunker
+            .handle_request(app.public_key(), connect(&bunker, Some("wrong")))
+            .await
+            .is_err());
+        assert!(bunker.apps().await.is_empty());
+
+        let other = Request::Connect {
+            signer_public_key: app.public_key(),
+            secret: Some(String::from("secret")),
+        };
+        assert!(bunker
+            .handle_request(app.public_key(), other)
+            .await
+            .is_err());
+
+        assert_eq!(
+            bunker
+                .handle_request(app.public_key(), connect(&bunker, Some("secret")))
+                .await,
+            Ok(String::from("ack"))
+        );
+        assert!(bunker.apps().await.contains(&app.public_key()));
+        assert_eq!(
+            bunker
+                .handle_request(app.public_key(), Request::GetPublicKey)
+                .await,
+            Ok(bunker.keys.public_key().to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bunker_default_deny() {
+        let app = Keys::generate_from_os_random();
+
+        // No secret and no approval handler: nobody can connect
+        let bunker = Nip46Bunker::new(Keys::generate_from_os_random(), Vec::new(), None);
+        assert!(bunker
+            .handle_request(app.public_key(), connect(&bunker, None))
+            .await
+            .is_err());
+        assert!(bunker
+            .handle_request(app.public_key(), Request::Ping)
+            .await
+            .is_err());
+
+        // Connected with the secret but no approval handler: signing is rejected
+        let bunker = Nip46Bunker::new(
+            Keys::generate_from_os_random(),
+            Vec::new(),
+            Some(String::from("secret")),
+        );
+        bunker
+            .handle_request(app.public_key(), connect(&bunker, Some("secret")))
+            .await
+            .unwrap();
+        assert!(bunker
+            .handle_request(app.public_key(), sign_event(&bunker))
+            .await
+            .is_err());
+        let decrypt = Request::Nip04Decrypt {
+            public_key: app.public_key(),
+            ciphertext: String::from("invalid"),
+        };
+        assert_eq!(
+            bunker.handle_request(app.public_key(), decrypt).await,
+            Err(String::from("request rejected: no approval handler"))
+        );
+        assert_eq!(
+            bunker.handle_request(app.public_key(), Request::Ping).await,
+            Ok(String::from("pong"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bunker_approval_handler() {
+        let approved = Keys::generate_from_os_random();
+        let rejected = Keys::generate_from_os_random();
+        let approved_pk = approved.public_key();
+        let bunker = Nip46Bunker::new(Keys::generate_from_os_random(), Vec::new(), None)
+            .approval_handler(
+                move |app_public_key, _req| async move { app_public_key == approved_pk },
+            );
+
+        assert_eq!(
+            bunker
+                .handle_request(rejected.public_key(), connect(&bunker, None))
+                .await,
+            Err(String::from("request rejected"))
+        );
+        assert!(bunker
+            .handle_request(rejected.public_key(), sign_event(&bunker))
+            .await
+            .is_err());
+
+        bunker
+            .handle_request(approved.public_key(), connect(&bunker, None))
+            .await
+            .unwrap();
+        let json = bunker
+            .handle_request(approved.public_key(), sign_event(&bunker))
+            .await
+            .unwrap();
+        let event = Event::from_json(json).unwrap();
+        assert_eq!(event.pubkey, bunker.keys.public_key());
+        assert_eq!(bunker.apps().await, HashSet::from([approved.public_key()]));
+    }
+
+    #[cfg(feature = "local-relay")]
+    #[tokio::test]
+    async fn test_bunker_with_remote_signer() {
+        use crate::local_relay::LocalRelay;
+        use crate::store::MemoryStore;
+
+        let relay = LocalRelay::run("127.0.0.1:0".parse().unwrap(), MemoryStore::new())
+            .await
+            .unwrap();
+        let user = Keys::generate_from_os_random();
+        let bunker = Nip46Bunker::new(
+            user.clone(),
+            vec![relay.url().unwrap()],
+            Some(String::from("secret")),
+        )
+        .approval_handler(|_, req| async move { !matches!(req, Request::Nip04Encrypt { .. }) });
+        let uri = bunker.uri();
+        tokio::spawn(async move { bunker.run().await });
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        let app = Keys::generate_from_os_random();
+        let signer = Nip46Signer::new(uri, app.clone(), Duration::from_secs(5)).unwrap();
+        signer.connect().await.unwrap();
+        assert_eq!(signer.public_key().await.unwrap(), user.public_key());
+        signer.ping().await.unwrap();
+
+        let unsigned =
+            EventBuilder::new_text_note("remote", &[]).to_unsigned_event(user.public_key());
+        let event = signer.sign_event(unsigned).await.unwrap();
+        assert!(event.verify().is_ok());
+
+        assert!(signer
+            .nip04_encrypt(app.public_key(), "text")
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,162 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::time::Duration;
+
+use nostr::event::builder::Error as EventBuilderError;
+use nostr::util::nips::nip47::{
+    self, GetInfoResponseResult, NostrWalletConnectURI, Request, Response,
+};
+use nostr::util::time::timestamp;
+use nostr::{ClientMessage, Event, EventBuilder, Keys, Kind, KindBase, SubscriptionFilter};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::relay::pool::{Error as RelayPoolError, RelayPool, RelayPoolNotifications};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("relay pool error: {0}")]
+    RelayPool(#[from] RelayPoolError),
+    #[error("event builder error: {0}")]
+    EventBuilder(#[from] EventBuilderError),
+    #[error("nip47 error: {0}")]
+    NIP47(#[from] nip47::Error),
+    /// No response in time
+    #[error("timeout")]
+    Timeout,
+}
+
+/// NIP-47 Nostr Wallet Connect client
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/47.md>
+#[derive(Debug, Clone)]
+pub struct NwcClient {
+    uri: NostrWalletConnectURI,
+    keys: Keys,
+    pool: RelayPool,
+    timeout: Duration,
+}
+
+impl NwcClient {
+    /// New Nostr Wallet Connect client
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use std::str::FromStr;
+    /// use std::time::Duration;
+    ///
+    /// use nostr::util::nips::nip47::NostrWalletConnectURI;
+    /// use nostr_sdk::nip47::NwcClient;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// # let uri = String::new();
+    /// let uri = NostrWalletConnectURI::from_str(&uri).unwrap();
+    /// let nwc = NwcClient::new(uri, Duration::from_secs(60));
+    /// nwc.connect().await.unwrap();
+    ///
+    /// let balance = nwc.get_balance().await.unwrap();
+    /// println!("Balance: {} msats", balance);
+    ///
+    /// let preimage = nwc.pay_invoice("lnbc210n1...").await.unwrap();
+    /// # }
+    /// ```
+    pub fn new(uri: NostrWalletConnectURI, timeout: Duration) -> Self {
+        Self {
+            keys: Keys::new(uri.secret),
+            uri,
+            pool: RelayPool::new(),
+            timeout,
+        }
+    }
+
+    /// Get the [`NostrWalletConnectURI`]
+    pub fn uri(&self) -> NostrWalletConnectURI {
+        self.uri.clone()
+    }
+
+    /// Connect to the relay of the wallet service
+    pub async fn connect(&self) -> Result<(), Error> {
+        self.pool.add_relay(self.uri.relay_url.clone(), None).await;
+        self.pool.connect(true).await?;
+
+        let filter = SubscriptionFilter::new()
+            .kind(Kind::Base(KindBase::WalletConnectResponse))
+            .author(self.uri.public_key)
+            .pubkey(self.keys.public_key())
+            .since(timestamp());
+        self.pool.subscribe(vec![filter]).await?;
+
+        Ok(())
+    }
+
+    /// Disconnect from the relay
+    pub async fn disconnect(&self) -> Result<(), Error> {
+        Ok(self.pool.disconnect().await?)
+    }
+
+    /// Pay a bolt11 invoice and return the preimage
+    pub async fn pay_invoice<S>(&self, invoice: S) -> Result<String, Error>
+    where
+        S: Into<String>,
+    {
+        let response = self
+            .send_request(Request::PayInvoice {
+                invoice: invoice.into(),
+                amount: None,
+            })
+            .await?;
+        Ok(response.to_pay_invoice()?.preimage)
+    }
+
+    /// Get the wallet balance (millisats)
+    pub async fn get_balance(&self) -> Result<u64, Error> {
+        let response = self.send_request(Request::GetBalance).await?;
+        Ok(response.to_get_balance()?.balance)
+    }
+
+    /// Get info about the wallet service
+    pub async fn get_info(&self) -> Result<GetInfoResponseResult, Error> {
+        let response = self.send_request(Request::GetInfo).await?;
+        Ok(response.to_get_info()?)
+    }
+
+    /// Send request and wait for the response
+    async fn send_request(&self, req: Request) -> Result<Response, Error> {
+        let event: Event =
+            EventBuilder::nostr_wallet_connect_request(&self.uri, &req)?.to_event(&self.keys)?;
+        let request_id = event.id;
+
+        let mut notifications = self.pool.notifications();
+        self.pool
+            .send_client_msg(ClientMessage::new_event(event))
+            .await?;
+
+        tokio::time::timeout(self.timeout, async {
+            loop {
+                match notifications.recv().await {
+                    Ok(RelayPoolNotifications::ReceivedEvent { event, .. }) => {
+                        if event.pubkey != self.uri.public_key
+                            || Response::request_id(&event) != Some(request_id)
+                        {
+                            continue;
+                        }
+                        match Response::from_event(&self.uri, &event) {
+                            Ok(response) => return Some(response),
+                            Err(e) => log::error!("Impossible to parse NIP-47 response: {}", e),
+                        }
+                    }
+                    Ok(_) => (),
+                    // The response may still come: keep waiting until the timeout
+                    Err(RecvError::Lagged(n)) => {
+                        log::warn!("NIP-47 client lagged: {} notifications skipped", n)
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        })
+        .await
+        .map_err(|_| Error::Timeout)?
+        .ok_or(Error::Timeout)
+    }
+}
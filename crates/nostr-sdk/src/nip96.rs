@@ -0,0 +1,147 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::net::SocketAddr;
+
+use nostr::event::builder::Error as EventBuilderError;
+use nostr::hashes::sha256::Hash as Sha256Hash;
+use nostr::hashes::Hash;
+use nostr::url::{self, Url};
+use nostr::util::nips::nip96::{self, Nip94Event, ServerConfig, UploadResponse, UploadStatus};
+use nostr::util::nips::nip98::{self, HttpData, HttpMethod};
+use nostr::{Event, EventBuilder, Keys};
+use reqwest::multipart::{Form, Part};
+use reqwest::{Client, Proxy};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("impossible to parse URL: {0}")]
+    Url(#[from] url::ParseError),
+    #[error("reqwest error: {0}")]
+    Reqwest(#[from] reqwest::Error),
+    #[error("event builder error: {0}")]
+    EventBuilder(#[from] EventBuilderError),
+    #[error("nip98 error: {0}")]
+    NIP98(#[from] nip98::Error),
+    /// Upload rejected by the server
+    #[error("upload failed: {0}")]
+    Upload(String),
+    /// The file is still being processed by the server
+    #[error("file still being processed")]
+    Processing,
+    /// Url not found in the NIP-94 event
+    #[error("url not found")]
+    UrlNotFound,
+}
+
+/// HTTP client, through the SOCKS5 `proxy` if set
+fn http_client(proxy: Option<SocketAddr>) -> Result<Client, Error> {
+    let mut builder = Client::builder();
+
+    if let Some(proxy) = proxy {
+        let proxy = format!("socks5h://{}", proxy);
+        builder = builder.proxy(Proxy::all(proxy)?);
+    }
+
+    Ok(builder.build()?)
+}
+
+/// Get the configuration of a NIP-96 HTTP file storage server
+///
+/// If the server delegates the uploads, the configuration of the delegated server is returned.
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/96.md>
+pub async fn get_server_config(
+    server_url: &Url,
+    proxy: Option<SocketAddr>,
+) -> Result<ServerConfig, Error> {
+    let client = http_client(proxy)?;
+    let config: ServerConfig = client
+        .get(nip96::well_known_url(server_url)?)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    match &config.delegated_to_url {
+        Some(delegated_to_url) => Ok(client
+            .get(nip96::well_known_url(delegated_to_url)?)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?),
+        None => Ok(config),
+    }
+}
+
+/// Upload a file, authorized with a NIP-98 HTTP auth event signed by `keys`
+///
+/// Return the NIP-94 file metadata, which contains the url of the file.
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/96.md>
+///
+/// # Example
+/// ```rust,no_run
+/// use nostr::url::Url;
+/// use nostr::Keys;
+/// use nostr_sdk::nip96;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let my_keys = Keys::generate_from_os_random();
+/// let server_url = Url::parse("https://nostr.build").unwrap();
+/// let config = nip96::get_server_config(&server_url, None).await.unwrap();
+///
+/// let file: Vec<u8> = std::fs::read("image.png").unwrap();
+/// let metadata = nip96::upload(&my_keys, &config, file, Some("image/png"), None)
+///     .await
+///     .unwrap();
+/// println!("Uploaded to {:?}", metadata.url());
+/// # }
+/// ```
+pub async fn upload(
+    keys: &Keys,
+    config: &ServerConfig,
+    file: Vec<u8>,
+    mime_type: Option<&str>,
+    proxy: Option<SocketAddr>,
+) -> Result<Nip94Event, Error> {
+    let data =
+        HttpData::new(config.api_url.clone(), HttpMethod::POST).payload(Sha256Hash::hash(&file));
+    let auth: Event = EventBuilder::http_auth(&data).to_event(keys)?;
+    let authorization = nip98::authorization_header(&auth)?;
+
+    let size = file.len();
+    let mut part = Part::bytes(file).file_name("file");
+    if let Some(mime_type) = mime_type {
+        part = part.mime_str(mime_type)?;
+    }
+    let mut form = Form::new()
+        .part("file", part)
+        .text("size", size.to_string());
+    if let Some(mime_type) = mime_type {
+        form = form.text("content_type", mime_type.to_string());
+    }
+
+    let response: UploadResponse = http_client(proxy)?
+        .post(config.api_url.clone())
+        .header("Authorization", authorization)
+        .multipart(form)
+        .send()
+        .await?
+        .json()
+        .await?;
+
+    match response.status {
+        UploadStatus::Error => Err(Error::Upload(response.message)),
+        _ => {
+            let event = response.nip94_event.ok_or(Error::Processing)?;
+            if event.url().is_none() {
+                return Err(Error::UrlNotFound);
+            }
+            Ok(event)
+        }
+    }
+}
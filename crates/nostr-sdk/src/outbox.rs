@@ -0,0 +1,252 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use nostr::event::Error as EventError;
+use nostr::{Event, Sha256Hash};
+
+use crate::cipher::{self, StoreCipher};
+use crate::util;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// I/O error
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    /// Event error
+    #[error("event error: {0}")]
+    Event(#[from] EventError),
+    /// Cipher error
+    #[error("cipher error: {0}")]
+    Cipher(#[from] cipher::Error),
+}
+
+/// Signed events not delivered to any relay, sent again when a relay is connected
+///
+/// The events are kept in memory or, if opened with [`Outbox::open`], also in a file
+/// (one JSON event per line) so that they survive restarts. Use [`Outbox::open_encrypted`]
+/// to encrypt the file.
+///
+/// # Example
+/// ```rust,no_run
+/// use nostr_sdk::{Client, Outbox};
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// #   let my_keys = Client::generate_keys();
+/// let client = Client::new(&my_keys);
+/// client
+///     .set_outbox(Some(Outbox::open("outbox.jsonl").unwrap()))
+///     .await;
+///
+/// // Queued if no relay is connected
+/// client
+///     .publish_text_note("Hello offline", &[])
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Outbox {
+    events: Arc<Mutex<Vec<Event>>>,
+    path: Option<PathBuf>,
+    cipher: Option<StoreCipher>,
+}
+
+impl Outbox {
+    /// New in-memory `Outbox`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open `Outbox` persisted at `path`, loading the events already queued
+    ///
+    /// Malformed lines are skipped.
+    pub fn open<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        Self::open_with_cipher(path, None)
+    }
+
+    /// Open `Outbox` persisted at `path` and encrypted with `cipher`
+    ///
+    /// Fails with [`cipher::Error::Decryption`] if the file was written in clear or
+    /// with another key.
+    pub fn open_encrypted<P>(path: P, cipher: StoreCipher) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        Self::open_with_cipher(path, Some(cipher))
+    }
+
+    fn open_with_cipher<P>(path: P, cipher: Option<StoreCipher>) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref().to_path_buf();
+        let events: Vec<Event> = util::read_events::<Error>(&path, cipher.as_ref())?;
+
+        Ok(Self {
+            events: Arc::new(Mutex::new(events)),
+            path: Some(path),
+            cipher,
+        })
+    }
+
+    /// Get file path, if persisted
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<Event>> {
+        match self.events.lock() {
+            Ok(events) => events,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// Write the events to the file, if persisted
+    fn save(&self, events: &[Event]) {
+        if let Some(path) = &self.path {
+            if let Err(e) = util::write_events::<_, Error>(path, events, self.cipher.as_ref()) {
+                log::error!("Impossible to save outbox to {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    /// Queue event
+    ///
+    /// Return `false` if it's already queued.
+    pub fn push(&self, event: Event) -> bool {
+        let mut events = self.lock();
+        if events.iter().any(|e| e.id == event.id) {
+            return false;
+        }
+        events.push(event);
+        self.save(&events);
+        true
+    }
+
+    /// Remove event from the queue
+    pub fn remove(&self, event_id: &Sha256Hash) -> Option<Event> {
+        let mut events = self.lock();
+        let index = events.iter().position(|e| &e.id == event_id)?;
+        let event = events.remove(index);
+        self.save(&events);
+        Some(event)
+    }
+
+    /// Get the queued events, from the oldest
+    pub fn events(&self) -> Vec<Event> {
+        self.lock().clone()
+    }
+
+    /// Number of queued events
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Check if there are no queued events
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Remove all the queued events
+    pub fn clear(&self) {
+        let mut events = self.lock();
+        events.clear();
+        self.save(&events);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use nostr::{EventBuilder, Keys};
+
+    use super::*;
+
+    fn tmp_path() -> PathBuf {
+        std::env::temp_dir().join(format!("outbox-{}.jsonl", uuid::Uuid::new_v4()))
+    }
+
+    fn new_event(keys: &Keys, content: &str) -> Event {
+        EventBuilder::new_text_note(content, &[])
+            .to_event(keys)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_persistence() {
+        let path = tmp_path();
+        let keys = Keys::generate_from_os_random();
+        let first = new_event(&keys, "first");
+        let second = new_event(&keys, "second");
+
+        let outbox = Outbox::open(&path).unwrap();
+        assert!(outbox.is_empty());
+        assert!(outbox.push(first.clone()));
+        assert!(outbox.push(second.clone()));
+        assert!(!outbox.push(first.clone()));
+
+        let reopened = Outbox::open(&path).unwrap();
+        assert_eq!(reopened.events(), vec![first.clone(), second.clone()]);
+
+        assert_eq!(reopened.remove(&first.id), Some(first));
+        assert_eq!(Outbox::open(&path).unwrap().events(), vec![second]);
+
+        // No leftover temporary file
+        let mut tmp = path.clone().into_os_string();
+        tmp.push(".tmp");
+        assert!(!PathBuf::from(tmp).exists());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_skip_malformed_lines() {
+        let path = tmp_path();
+        let keys = Keys::generate_from_os_random();
+        let event = new_event(&keys, "valid");
+        let content = format!(
+            "{{\"id\":\"truncated\n\n{}\nnot json\n",
+            event.as_json().unwrap()
+        );
+        fs::write(&path, content).unwrap();
+
+        let outbox = Outbox::open(&path).unwrap();
+        assert_eq!(outbox.events(), vec![event]);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_encrypted_persistence() {
+        let path = tmp_path();
+        let keys = Keys::generate_from_os_random();
+        let event = new_event(&keys, "not for the disk");
+        let cipher = StoreCipher::from_keys(&keys).unwrap();
+
+        let outbox = Outbox::open_encrypted(&path, cipher.clone()).unwrap();
+        assert!(outbox.push(event.clone()));
+
+        let content = fs::read(&path).unwrap();
+        let needle = b"not for the disk";
+        assert!(!content.windows(needle.len()).any(|w| w == needle));
+
+        let reopened = Outbox::open_encrypted(&path, cipher).unwrap();
+        assert_eq!(reopened.events(), vec![event]);
+
+        let other = StoreCipher::from_keys(&Keys::generate_from_os_random()).unwrap();
+        assert!(matches!(
+            Outbox::open_encrypted(&path, other),
+            Err(Error::Cipher(cipher::Error::Decryption))
+        ));
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,117 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use nostr::message::MachineReadablePrefix;
+use nostr::{ClientMessage, RelayMessage, Sha256Hash};
+
+/// Time after which an event still waiting for an `OK` is forgotten
+const PENDING_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Action to take after a message received from the relay
+#[derive(Debug)]
+pub(crate) enum AuthAction {
+    /// Message not related to the authentication
+    None,
+    /// Message rejected with `auth-required:`, held until the authentication
+    Held,
+    /// Authentication accepted: send the held messages again
+    Replay(Vec<ClientMessage>),
+    /// Authentication rejected: the held messages are dropped
+    Failed(Vec<ClientMessage>),
+}
+
+/// Keep track of the messages sent to a relay and send them again
+/// after the NIP-42 authentication when the relay replies with an `auth-required:` `OK` or `CLOSED`
+#[derive(Debug, Default)]
+pub(crate) struct AuthReplay {
+    events: HashMap<Sha256Hash, (ClientMessage, Instant)>,
+    subscriptions: HashMap<String, ClientMessage>,
+    /// `AUTH` events waiting for an `OK`
+    auth_events: HashSet<Sha256Hash>,
+    authenticated: bool,
+    held: Vec<ClientMessage>,
+}
+
+impl AuthReplay {
+    /// Track a message that is being sent to the relay
+    pub fn track(&mut self, msg: &ClientMessage) {
+        let now = Instant::now();
+        self.events
+            .retain(|_, (_, sent_at)| now.duration_since(*sent_at) < PENDING_TIMEOUT);
+
+        match msg {
+            ClientMessage::Event { event } => {
+                self.events.insert(event.id, (msg.clone(), now));
+            }
+            ClientMessage::Auth { event } => {
+                self.auth_events.insert(event.id);
+                self.authenticated = false;
+            }
+            ClientMessage::Req {
+                subscription_id, ..
+            } => {
+                self.subscriptions
+                    .insert(subscription_id.clone(), msg.clone());
+            }
+            ClientMessage::Close { subscription_id } => {
+                self.subscriptions.remove(subscription_id);
+                self.held.retain(|held| match held {
+                    ClientMessage::Req {
+                        subscription_id: id,
+                        ..
+                    } => id != subscription_id,
+                    _ => true,
+                });
+            }
+        }
+    }
+
+    /// Handle a message received from the relay
+    pub fn handle(&mut self, msg: &RelayMessage) -> AuthAction {
+        match msg {
+            RelayMessage::Ok {
+                event_id,
+                status,
+                message,
+            } => {
+                if self.auth_events.remove(event_id) {
+                    let held = std::mem::take(&mut self.held);
+                    return if *status {
+                        self.authenticated = true;
+                        AuthAction::Replay(held)
+                    } else {
+                        AuthAction::Failed(held)
+                    };
+                }
+
+                match self.events.remove(event_id) {
+                    Some((msg, _)) if !status => self.hold(msg, message),
+                    _ => AuthAction::None,
+                }
+            }
+            RelayMessage::Closed {
+                subscription_id,
+                message,
+            } => match self.subscriptions.remove(subscription_id) {
+                Some(msg) => self.hold(msg, message),
+                None => AuthAction::None,
+            },
+            _ => AuthAction::None,
+        }
+    }
+
+    fn hold(&mut self, msg: ClientMessage, message: &str) -> AuthAction {
+        // Already authenticated: the relay doesn't accept the message anyway
+        if self.authenticated
+            || MachineReadablePrefix::parse(message) != Some(MachineReadablePrefix::AuthRequired)
+        {
+            return AuthAction::None;
+        }
+
+        self.held.push(msg);
+        AuthAction::Held
+    }
+}
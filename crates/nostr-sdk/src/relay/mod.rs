@@ -1,35 +1,78 @@
 // Copyright (c) 2022 Yuki Kishimoto
 // Distributed under the MIT software license
 
+use std::collections::HashMap;
 use std::fmt;
+#[cfg(feature = "nip11")]
+use std::future::Future;
 use std::net::SocketAddr;
+#[cfg(feature = "nip11")]
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use futures_util::{SinkExt, StreamExt};
 use nostr::url::Url;
-use nostr::{ClientMessage, RelayMessage};
+#[cfg(feature = "nip11")]
+use nostr::util::nips::nip11::{self, RelayInformationDocument};
+use nostr::{ClientMessage, Event, RelayMessage, Sha256Hash, SubscriptionFilter};
 use tokio::sync::mpsc::error::SendError;
 use tokio::sync::mpsc::{self, Receiver, Sender};
-use tokio::sync::Mutex;
-use tokio_tungstenite::tungstenite::Message;
+use tokio::sync::{oneshot, Mutex, Notify};
 
+mod auth;
 mod net;
 pub mod pool;
+mod rate_limit;
+mod stats;
+mod transport;
 
-use self::pool::RelayPoolEvent;
+use self::auth::{AuthAction, AuthReplay};
+pub use self::net::TlsOptions;
+use self::pool::{RelayPoolEvent, RelayPoolOptions};
+use self::rate_limit::RateLimiter;
+use self::stats::StatsTracker;
+pub use self::stats::{RelayStats, ScoringOptions};
+pub use self::transport::{
+    Error as TransportError, RelayTransport, TransportFuture, TransportMessage, TransportSink,
+    TransportStream, WebSocketTransport,
+};
 
 #[cfg(feature = "blocking")]
 use crate::{new_current_thread, RUNTIME};
 
+/// Time after which the cached relay information document is fetched again
+#[cfg(feature = "nip11")]
+pub const INFORMATION_DOCUMENT_TTL: Duration = Duration::from_secs(60 * 60);
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("impossible to send relay event: {0}")]
     RelayEventSender(#[from] SendError<RelayEvent>),
+    /// NIP11 error
+    #[cfg(feature = "nip11")]
+    #[error("nip11 error: {0}")]
+    NIP11(#[from] nip11::Error),
+    /// Task join error
+    #[error("join error: {0}")]
+    Join(#[from] tokio::task::JoinError),
+    /// Invalid relay url
+    #[error("invalid relay url")]
+    InvalidUrl,
+    /// Event rejected by the relay (`OK` with `false` status)
+    #[error("event not published: {0}")]
+    EventNotPublished(String),
+    /// No `OK` received from the relay in time
+    #[error("timeout")]
+    Timeout,
+    /// Message not written to the relay socket
+    #[error("message not sent")]
+    MessageNotSent,
 }
 
 /// Relay connection status
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 pub enum RelayStatus {
     /// Relay initialized
     Initialized,
@@ -55,9 +98,156 @@ impl fmt::Display for RelayStatus {
     }
 }
 
+/// Relay options
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct RelayOptions {
+    read: bool,
+    write: bool,
+    connection_timeout: Option<Duration>,
+}
+
+impl Default for RelayOptions {
+    fn default() -> Self {
+        Self::new(true, true)
+    }
+}
+
+impl RelayOptions {
+    /// New `RelayOptions`
+    ///
+    /// The subscriptions are sent only to the `read` relays and the events only to the `write` ones.
+    pub fn new(read: bool, write: bool) -> Self {
+        Self {
+            read,
+            write,
+            connection_timeout: None,
+        }
+    }
+
+    /// Read-only relay
+    pub fn read_only() -> Self {
+        Self::new(true, false)
+    }
+
+    /// Write-only relay
+    pub fn write_only() -> Self {
+        Self::new(false, true)
+    }
+
+    /// Check if the subscriptions are sent to the relay
+    pub fn read(&self) -> bool {
+        self.read
+    }
+
+    /// Check if the events are published to the relay
+    pub fn write(&self) -> bool {
+        self.write
+    }
+
+    /// Set connection timeout of the relay, overriding the one of the pool
+    /// (see [`RelayPoolOptions::connection_timeout`])
+    ///
+    /// Also bounds the fetch of the NIP-11 information document before connecting.
+    pub fn connection_timeout(self, timeout: Duration) -> Self {
+        Self {
+            connection_timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Connection timeout of the relay (`None` to use the one of the pool)
+    pub fn get_connection_timeout(&self) -> Option<Duration> {
+        self.connection_timeout
+    }
+}
+
+#[cfg(feature = "nip11")]
+type PaymentFuture = Pin<Box<dyn Future<Output = bool> + Send>>;
+
+/// Relay payment handler
+///
+/// Called when a relay requires payment (NIP-11 `limitation.payment_required`) before
+/// connecting to it. Must return `true` once the payment has been completed.
+#[cfg(feature = "nip11")]
+#[derive(Clone)]
+pub struct PaymentHandler {
+    func: Arc<dyn Fn(Url, Option<String>) -> PaymentFuture + Send + Sync>,
+}
+
+#[cfg(feature = "nip11")]
+impl fmt::Debug for PaymentHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PaymentHandler").finish()
+    }
+}
+
+#[cfg(feature = "nip11")]
+impl PaymentHandler {
+    /// Create new `PaymentHandler`
+    ///
+    /// The function receives the relay url and the `payments_url` of its information document.
+    pub fn new<F, Fut>(func: F) -> Self
+    where
+        F: Fn(Url, Option<String>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = bool> + Send + 'static,
+    {
+        Self {
+            func: Arc::new(move |relay_url, payments_url| Box::pin(func(relay_url, payments_url))),
+        }
+    }
+
+    async fn pay(&self, relay_url: Url, payments_url: Option<String>) -> bool {
+        (self.func)(relay_url, payments_url).await
+    }
+}
+
+/// Payment state of a relay that requires payment (see [`PaymentHandler`])
+#[cfg(feature = "nip11")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PaymentStatus {
+    /// Payment not requested yet
+    Unknown,
+    /// Payment completed
+    Paid,
+    /// Payment required but no handler set: the relay is connected anyway
+    Unhandled,
+    /// Payment refused by the handler: the relay isn't connected until a new handler is set
+    Refused,
+}
+
+/// Subscription sent to the relay, sent again after a reconnection
+#[derive(Debug, Clone)]
+struct ActiveSubscription {
+    filters: Vec<SubscriptionFilter>,
+    /// Most recent `created_at` of the received events
+    last_event: Option<u64>,
+}
+
+impl ActiveSubscription {
+    /// Get the filters to send again, with `since` moved to the last received event if `adjust_since`
+    fn filters(&self, adjust_since: bool) -> Vec<SubscriptionFilter> {
+        match self.last_event {
+            Some(last_event) if adjust_since => self
+                .filters
+                .iter()
+                .cloned()
+                .map(|f| {
+                    let since = f.since.map_or(last_event, |since| since.max(last_event));
+                    f.since(since)
+                })
+                .collect(),
+            _ => self.filters.clone(),
+        }
+    }
+}
+
+/// Waiter of the `OK` message of an event
+type AckSender = oneshot::Sender<Result<(), Error>>;
+
 #[derive(Debug)]
 pub enum RelayEvent {
-    SendMsg(Box<ClientMessage>),
+    /// Send message, notifying if it has been written to the socket
+    SendMsg(Box<ClientMessage>, Option<oneshot::Sender<bool>>),
     Ping,
     Close,
     Terminate,
@@ -67,29 +257,131 @@ pub enum RelayEvent {
 pub struct Relay {
     url: Url,
     proxy: Option<SocketAddr>,
+    tls: TlsOptions,
+    /// Transport used to connect to the relay (shared with the pool)
+    transport: Arc<Mutex<Arc<dyn RelayTransport>>>,
+    opts: RelayOptions,
+    /// Connection timeout (`None` for the default one)
+    connection_timeout: Option<Duration>,
+    /// Reconnect automatically after a disconnection
+    reconnect: bool,
+    /// Interval between the keepalive pings
+    ping_interval: Duration,
+    /// Max time to wait for a frame after a ping, before recycling the connection
+    pong_timeout: Duration,
+    /// Demote the relay after too many failures
+    scoring: Option<ScoringOptions>,
     status: Arc<Mutex<RelayStatus>>,
     scheduled_for_termination: Arc<Mutex<bool>>,
     pool_sender: Sender<RelayPoolEvent>,
     relay_sender: Sender<RelayEvent>,
     relay_receiver: Arc<Mutex<Receiver<RelayEvent>>>,
+    rate_limiter: Arc<Mutex<RateLimiter>>,
+    stats: Arc<Mutex<StatsTracker>>,
+    subscriptions: Arc<Mutex<HashMap<String, ActiveSubscription>>>,
+    resubscribe_since: Arc<AtomicBool>,
+    /// Hold the messages rejected with `auth-required:` and send them again after the authentication
+    auto_auth: Arc<AtomicBool>,
+    auth_replay: Arc<Mutex<AuthReplay>>,
+    connected_once: Arc<AtomicBool>,
+    /// Waiters of the `OK` message of the sent events
+    acks: Arc<Mutex<HashMap<Sha256Hash, Vec<AckSender>>>>,
+    #[cfg(feature = "nip11")]
+    payment_handler: Arc<Mutex<Option<PaymentHandler>>>,
+    #[cfg(feature = "nip11")]
+    payment: Arc<Mutex<PaymentStatus>>,
+    #[cfg(feature = "nip11")]
+    document: Arc<Mutex<Option<(RelayInformationDocument, Instant)>>>,
 }
 
 impl Relay {
     /// Create new `Relay`
-    pub fn new(url: Url, pool_sender: Sender<RelayPoolEvent>, proxy: Option<SocketAddr>) -> Self {
+    pub fn new(
+        url: Url,
+        pool_sender: Sender<RelayPoolEvent>,
+        proxy: Option<SocketAddr>,
+        opts: RelayOptions,
+    ) -> Self {
         let (relay_sender, relay_receiver) = mpsc::channel::<RelayEvent>(64);
 
         Self {
             url,
             proxy,
+            tls: TlsOptions::default(),
+            transport: Arc::new(Mutex::new(Arc::new(WebSocketTransport))),
+            opts,
+            connection_timeout: None,
+            reconnect: true,
+            ping_interval: Duration::from_secs(60),
+            pong_timeout: Duration::from_secs(20),
+            scoring: None,
             status: Arc::new(Mutex::new(RelayStatus::Initialized)),
             scheduled_for_termination: Arc::new(Mutex::new(false)),
             pool_sender,
             relay_sender,
             relay_receiver: Arc::new(Mutex::new(relay_receiver)),
+            rate_limiter: Arc::new(Mutex::new(RateLimiter::default())),
+            stats: Arc::new(Mutex::new(StatsTracker::default())),
+            subscriptions: Arc::new(Mutex::new(HashMap::new())),
+            resubscribe_since: Arc::new(AtomicBool::new(false)),
+            auto_auth: Arc::new(AtomicBool::new(false)),
+            auth_replay: Arc::new(Mutex::new(AuthReplay::default())),
+            connected_once: Arc::new(AtomicBool::new(false)),
+            acks: Arc::new(Mutex::new(HashMap::new())),
+            #[cfg(feature = "nip11")]
+            payment_handler: Arc::new(Mutex::new(None)),
+            #[cfg(feature = "nip11")]
+            payment: Arc::new(Mutex::new(PaymentStatus::Unknown)),
+            #[cfg(feature = "nip11")]
+            document: Arc::new(Mutex::new(None)),
         }
     }
 
+    #[cfg(feature = "nip11")]
+    pub(crate) fn with_payment_handler(
+        self,
+        payment_handler: Arc<Mutex<Option<PaymentHandler>>>,
+    ) -> Self {
+        Self {
+            payment_handler,
+            ..self
+        }
+    }
+
+    pub(crate) fn with_pool_opts(self, opts: &RelayPoolOptions) -> Self {
+        let (relay_sender, relay_receiver) =
+            mpsc::channel::<RelayEvent>(opts.get_relay_channel_size());
+        Self {
+            connection_timeout: opts.get_connection_timeout(),
+            reconnect: opts.get_reconnect(),
+            ping_interval: opts.get_ping_interval(),
+            pong_timeout: opts.get_pong_timeout(),
+            scoring: opts.get_scoring(),
+            relay_sender,
+            relay_receiver: Arc::new(Mutex::new(relay_receiver)),
+            ..self
+        }
+    }
+
+    pub(crate) fn with_tls(self, tls: TlsOptions) -> Self {
+        Self { tls, ..self }
+    }
+
+    pub(crate) fn with_transport(self, transport: Arc<Mutex<Arc<dyn RelayTransport>>>) -> Self {
+        Self { transport, ..self }
+    }
+
+    pub(crate) fn with_resubscribe_since(self, resubscribe_since: Arc<AtomicBool>) -> Self {
+        Self {
+            resubscribe_since,
+            ..self
+        }
+    }
+
+    pub(crate) fn with_auto_auth(self, auto_auth: Arc<AtomicBool>) -> Self {
+        Self { auto_auth, ..self }
+    }
+
     /// Get relay url
     pub fn url(&self) -> Url {
         self.url.clone()
@@ -100,6 +392,16 @@ impl Relay {
         self.proxy
     }
 
+    /// Get TLS options
+    pub fn tls(&self) -> TlsOptions {
+        self.tls.clone()
+    }
+
+    /// Get relay options
+    pub fn opts(&self) -> RelayOptions {
+        self.opts
+    }
+
     #[cfg(feature = "blocking")]
     pub fn status_blocking(&self) -> RelayStatus {
         RUNTIME.block_on(async { self.status().await })
@@ -107,12 +409,247 @@ impl Relay {
 
     pub async fn status(&self) -> RelayStatus {
         let status = self.status.lock().await;
-        status.clone()
+        *status
     }
 
     async fn set_status(&self, status: RelayStatus) {
-        let mut s = self.status.lock().await;
-        *s = status;
+        {
+            let mut s = self.status.lock().await;
+            if *s == status {
+                return;
+            }
+            *s = status;
+        }
+
+        {
+            let mut stats = self.stats.lock().await;
+            match status {
+                RelayStatus::Connected => stats.connected(),
+                _ => stats.disconnected(),
+            }
+        }
+
+        if let Err(err) = self
+            .pool_sender
+            .send(RelayPoolEvent::RelayStatusChanged {
+                relay_url: self.url(),
+                status,
+            })
+            .await
+        {
+            log::error!("Impossible to send RelayStatusChanged to pool: {}", &err);
+        }
+    }
+
+    /// Get REQ round-trip time (time to EOSE), averaged over the last queries
+    ///
+    /// Return `None` if no query has been completed yet
+    pub async fn latency(&self) -> Option<Duration> {
+        self.stats.lock().await.req_latency()
+    }
+
+    /// Get connection uptime, round-trip times, number of notices and last error
+    pub async fn stats(&self) -> RelayStats {
+        self.stats.lock().await.stats()
+    }
+
+    /// Get the active subscriptions (id and filters), sent again after a reconnection
+    pub async fn subscriptions(&self) -> HashMap<String, Vec<SubscriptionFilter>> {
+        let subscriptions = self.subscriptions.lock().await;
+        subscriptions
+            .iter()
+            .map(|(id, sub)| (id.clone(), sub.filters.clone()))
+            .collect()
+    }
+
+    /// Record a failure (connection error, timeout or malformed data) and demote the relay
+    /// if it failed too many times in a row
+    pub(crate) async fn record_failure<S>(&self, error: S)
+    where
+        S: Into<String>,
+    {
+        let demoted = {
+            let mut stats = self.stats.lock().await;
+            stats.error(error);
+            stats.failure(self.scoring)
+        };
+
+        if let (true, Some(scoring)) = (demoted, self.scoring) {
+            let duration = scoring.get_demotion_time();
+            log::warn!("{} demoted for {:?}: too many failures", self.url, duration);
+
+            if let Err(err) = self
+                .pool_sender
+                .send(RelayPoolEvent::RelayDemoted {
+                    relay_url: self.url(),
+                    duration,
+                })
+                .await
+            {
+                log::error!("Impossible to send RelayDemoted to pool: {}", &err);
+            }
+
+            if self.status().await == RelayStatus::Connected {
+                if let Err(err) = self.disconnect().await {
+                    log::error!("Impossible to disconnect {}: {}", self.url, err);
+                }
+            }
+        }
+    }
+
+    /// Check if the relay is demoted, notifying when it's restored
+    async fn is_demoted(&self) -> bool {
+        let demoted = self.stats.lock().await.demoted();
+        match demoted {
+            Some(true) => true,
+            Some(false) => {
+                log::info!("{} restored", self.url);
+                if let Err(err) = self
+                    .pool_sender
+                    .send(RelayPoolEvent::RelayRestored {
+                        relay_url: self.url(),
+                    })
+                    .await
+                {
+                    log::error!("Impossible to send RelayRestored to pool: {}", &err);
+                }
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Keep track of the sent REQs and CLOSEs
+    async fn track_subscription(&self, msg: &ClientMessage) {
+        let mut subscriptions = self.subscriptions.lock().await;
+        match msg {
+            ClientMessage::Req {
+                subscription_id,
+                filters,
+            } => {
+                subscriptions.insert(
+                    subscription_id.clone(),
+                    ActiveSubscription {
+                        filters: filters.clone(),
+                        last_event: None,
+                    },
+                );
+            }
+            ClientMessage::Close { subscription_id } => {
+                subscriptions.remove(subscription_id);
+            }
+            _ => (),
+        }
+    }
+
+    /// Keep track of the last event received and of the subscriptions closed by the relay
+    async fn track_relay_msg(&self, msg: &RelayMessage) {
+        let mut subscriptions = self.subscriptions.lock().await;
+        match msg {
+            RelayMessage::Event {
+                subscription_id,
+                event,
+            } => {
+                if let Some(sub) = subscriptions.get_mut(subscription_id) {
+                    sub.last_event = Some(
+                        sub.last_event
+                            .map_or(event.created_at, |last| last.max(event.created_at)),
+                    );
+                }
+            }
+            RelayMessage::Closed {
+                subscription_id, ..
+            } => {
+                subscriptions.remove(subscription_id);
+            }
+            _ => (),
+        }
+    }
+
+    /// Notify the waiters of an `OK` message
+    async fn handle_ok(&self, msg: &RelayMessage) {
+        if let RelayMessage::Ok {
+            event_id,
+            status,
+            message,
+        } = msg
+        {
+            let waiters = self.acks.lock().await.remove(event_id);
+            for waiter in waiters.into_iter().flatten() {
+                let res = if *status {
+                    Ok(())
+                } else {
+                    Err(Error::EventNotPublished(message.clone()))
+                };
+                let _ = waiter.send(res);
+            }
+        }
+    }
+
+    /// Hold the messages rejected until the authentication and send them again once authenticated
+    ///
+    /// Return `true` if `msg` has been held.
+    async fn handle_auth(&self, msg: &RelayMessage) -> bool {
+        if !self.auto_auth.load(Ordering::SeqCst) {
+            return false;
+        }
+
+        let action = self.auth_replay.lock().await.handle(msg);
+        match action {
+            AuthAction::None => false,
+            AuthAction::Held => {
+                log::debug!("{} requires authentication: message held", self.url);
+                true
+            }
+            AuthAction::Replay(msgs) => {
+                for msg in msgs.into_iter() {
+                    log::debug!("Authenticated to {}: sending message again", self.url);
+                    if let Err(e) = self
+                        .send_relay_event(RelayEvent::SendMsg(Box::new(msg), None))
+                        .await
+                    {
+                        log::error!("Impossible to send message again to {}: {}", self.url, e);
+                    }
+                }
+                false
+            }
+            AuthAction::Failed(msgs) => {
+                log::warn!("Authentication to {} rejected", self.url);
+                for msg in msgs.into_iter() {
+                    if let ClientMessage::Event { event } = msg {
+                        let rejected = RelayMessage::new_ok(
+                            event.id,
+                            false,
+                            String::from("auth-required: authentication rejected"),
+                        );
+                        self.handle_ok(&rejected).await;
+                    }
+                }
+                false
+            }
+        }
+    }
+
+    /// Send again the active subscriptions
+    async fn resubscribe(&self) {
+        let adjust_since = self.resubscribe_since.load(Ordering::SeqCst);
+        let msgs: Vec<ClientMessage> = {
+            let subscriptions = self.subscriptions.lock().await;
+            subscriptions
+                .iter()
+                .map(|(id, sub)| ClientMessage::new_req(id.clone(), sub.filters(adjust_since)))
+                .collect()
+        };
+
+        for msg in msgs.into_iter() {
+            log::debug!("Resubscribing to {}", self.url);
+            if let Err(e) = self
+                .send_relay_event(RelayEvent::SendMsg(Box::new(msg), None))
+                .await
+            {
+                log::error!("Impossible to resubscribe to {}: {}", self.url, e);
+            }
+        }
     }
 
     async fn is_scheduled_for_termination(&self) -> bool {
@@ -137,6 +674,7 @@ impl Relay {
 
             let relay = self.clone();
             let connection_thread = async move {
+                let mut attempted = wait_for_connection;
                 loop {
                     // Schedule relay for termination
                     // Needed to terminate the auto reconnect loop, also if the relay is not connected yet.
@@ -149,7 +687,15 @@ impl Relay {
 
                     // Check status
                     match relay.status().await {
-                        RelayStatus::Disconnected => relay.try_connect().await,
+                        RelayStatus::Disconnected if attempted && !relay.reconnect => {
+                            relay.set_status(RelayStatus::Terminated).await;
+                            log::debug!("Auto reconnection disabled for {}", relay.url);
+                            break;
+                        }
+                        RelayStatus::Disconnected => {
+                            relay.try_connect().await;
+                            attempted = true;
+                        }
                         RelayStatus::Terminated => {
                             log::debug!("Auto connect loop terminated for {}", relay.url);
                             break;
@@ -157,8 +703,6 @@ impl Relay {
                         _ => (),
                     };
 
-                    // TODO: if disconnected and connected again, get subscription filters from store (sled or something else) and send it again
-
                     tokio::time::sleep(Duration::from_secs(20)).await;
                 }
             };
@@ -167,7 +711,7 @@ impl Relay {
             match new_current_thread() {
                 Ok(rt) => {
                     std::thread::spawn(move || {
-                        rt.block_on(async move { connection_thread.await });
+                        rt.block_on(connection_thread);
                         rt.shutdown_timeout(Duration::from_millis(100));
                     });
                 }
@@ -179,13 +723,193 @@ impl Relay {
         }
     }
 
+    /// Get relay information document
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/11.md>
+    #[cfg(feature = "nip11")]
+    pub async fn information_document(&self) -> Result<RelayInformationDocument, Error> {
+        let mut url = self.url();
+        let scheme = if url.scheme() == "wss" {
+            "https"
+        } else {
+            "http"
+        };
+        url.set_scheme(scheme).map_err(|_| Error::InvalidUrl)?;
+
+        Ok(nip11::get_relay_information_document_async(url, self.proxy).await?)
+    }
+
+    /// Get relay information document, fetched at most once every [`INFORMATION_DOCUMENT_TTL`]
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/11.md>
+    #[cfg(feature = "nip11")]
+    pub async fn cached_information_document(&self) -> Result<RelayInformationDocument, Error> {
+        if let Some((info, fetched_at)) = self.document.lock().await.as_ref() {
+            if fetched_at.elapsed() < INFORMATION_DOCUMENT_TTL {
+                return Ok(info.clone());
+            }
+        }
+
+        let info = self.information_document().await?;
+        let mut document = self.document.lock().await;
+        *document = Some((info.clone(), Instant::now()));
+        Ok(info)
+    }
+
+    /// Get max `limit` accepted by the relay for a subscription filter
+    ///
+    /// Return `None` if the relay doesn't advertise it in its information document (NIP-11)
+    pub async fn max_limit(&self) -> Option<u16> {
+        #[cfg(feature = "nip11")]
+        {
+            let document = self.document.lock().await;
+            document
+                .as_ref()
+                .and_then(|(d, _)| d.max_limit())
+                .map(|max_limit| max_limit.clamp(1, u16::MAX as u64) as u16)
+        }
+
+        #[cfg(not(feature = "nip11"))]
+        None
+    }
+
+    /// Fetch the relay information document, if expired, and keep it for later use
+    #[cfg(feature = "nip11")]
+    async fn update_information_document(&self) -> Option<RelayInformationDocument> {
+        match self.cached_information_document().await {
+            Ok(info) => Some(info),
+            Err(e) => {
+                log::debug!(
+                    "Impossible to get information document of {}: {}",
+                    self.url,
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    /// Forget the outcome of the payment handler, to call it again at the next connection
+    #[cfg(feature = "nip11")]
+    pub(crate) async fn reset_payment(&self) {
+        let mut payment = self.payment.lock().await;
+        if *payment != PaymentStatus::Paid {
+            *payment = PaymentStatus::Unknown;
+        }
+    }
+
+    /// Check if the relay requires payment and, in case, call the payment handler
+    ///
+    /// The relay requiring payment is notified and the handler is called only once:
+    /// after a refusal the connection is postponed until [`Relay::reset_payment`].
+    ///
+    /// Return `false` if the connection must be postponed
+    #[cfg(feature = "nip11")]
+    async fn check_payment(&self, info: Option<RelayInformationDocument>) -> bool {
+        let mut payment = self.payment.lock().await;
+        match *payment {
+            PaymentStatus::Paid | PaymentStatus::Unhandled => return true,
+            PaymentStatus::Refused => return false,
+            PaymentStatus::Unknown => (),
+        }
+
+        let info = match info {
+            Some(info) => info,
+            None => return true,
+        };
+
+        if !info.payment_required() {
+            return true;
+        }
+
+        log::warn!("{} requires payment", self.url);
+
+        if let Err(err) = self
+            .pool_sender
+            .send(RelayPoolEvent::RelayRequiresPayment {
+                relay_url: self.url(),
+                payments_url: info.payments_url.clone(),
+            })
+            .await
+        {
+            log::error!("Impossible to send RelayRequiresPayment to pool: {}", &err);
+        }
+
+        let handler = self.payment_handler.lock().await.clone();
+        match handler {
+            Some(handler) => {
+                let paid = handler.pay(self.url(), info.payments_url).await;
+                if paid {
+                    log::info!("Payment completed for {}", self.url);
+                    *payment = PaymentStatus::Paid;
+                } else {
+                    *payment = PaymentStatus::Refused;
+                }
+                paid
+            }
+            None => {
+                *payment = PaymentStatus::Unhandled;
+                true
+            }
+        }
+    }
+
+    /// Send again a rate-limited message once `delay` is elapsed, if still pending
+    ///
+    /// The retry is scheduled on a separate task to not block the relay tasks while waiting.
+    fn schedule_retry(&self, msg: ClientMessage, delay: Duration) {
+        let relay = self.clone();
+        tokio::task::spawn(async move {
+            tokio::time::sleep(delay).await;
+            if !relay.rate_limiter.lock().await.is_pending(&msg) {
+                return;
+            }
+            if let Err(err) = relay
+                .send_relay_event(RelayEvent::SendMsg(Box::new(msg), None))
+                .await
+            {
+                log::error!("Impossible to retry message to {}: {}", relay.url, err);
+            }
+        });
+    }
+
     async fn try_connect(&self) {
         let url: String = self.url.to_string();
 
+        if self.is_demoted().await {
+            self.set_status(RelayStatus::Disconnected).await;
+            log::debug!("{} demoted: connection postponed", url);
+            return;
+        }
+
         self.set_status(RelayStatus::Connecting).await;
         log::debug!("Connecting to {}", url);
 
-        match net::get_connection(&self.url, self.proxy, None).await {
+        let timeout = self.opts.connection_timeout.or(self.connection_timeout);
+
+        #[cfg(feature = "nip11")]
+        let info = tokio::time::timeout(
+            timeout.unwrap_or(net::DEFAULT_CONNECTION_TIMEOUT),
+            self.update_information_document(),
+        )
+        .await
+        .unwrap_or_else(|_| {
+            log::debug!("Timeout while getting information document of {}", url);
+            None
+        });
+
+        #[cfg(feature = "nip11")]
+        if !self.check_payment(info).await {
+            self.set_status(RelayStatus::Disconnected).await;
+            log::warn!("Payment not completed for {}: connection postponed", url);
+            return;
+        }
+
+        let transport = self.transport.lock().await.clone();
+        match transport
+            .connect(&self.url, self.proxy, &self.tls, timeout)
+            .await
+        {
             Ok((mut ws_tx, mut ws_rx)) => {
                 self.set_status(RelayStatus::Connected).await;
                 log::info!("Connected to {}", url);
@@ -195,14 +919,25 @@ impl Relay {
                     log::debug!("Relay Event Thread Started");
                     while let Some(relay_event) = relay.relay_receiver.lock().await.recv().await {
                         match relay_event {
-                            RelayEvent::SendMsg(msg) => {
+                            RelayEvent::SendMsg(msg, sent) => {
+                                relay.rate_limiter.lock().await.track(&msg);
+                                relay.auth_replay.lock().await.track(&msg);
                                 log::trace!("Sending message {}", msg.to_json());
-                                if let Err(e) = ws_tx.send(Message::Text(msg.to_json())).await {
-                                    log::error!("RelayEvent::SendMsg error: {:?}", e);
+                                let res = ws_tx.send(TransportMessage::Text(msg.to_json())).await;
+                                match &res {
+                                    Ok(()) => relay.stats.lock().await.sent(&msg),
+                                    Err(e) => {
+                                        log::error!("RelayEvent::SendMsg error: {:?}", e);
+                                        relay.stats.lock().await.error(e.to_string());
+                                    }
                                 };
+                                if let Some(sent) = sent {
+                                    let _ = sent.send(res.is_ok());
+                                }
                             }
                             RelayEvent::Ping => {
-                                if let Err(e) = ws_tx.send(Message::Ping(Vec::new())).await {
+                                if let Err(e) = ws_tx.send(TransportMessage::Ping(Vec::new())).await
+                                {
                                     log::error!("Ping error: {:?}", e);
                                     break;
                                 }
@@ -232,7 +967,7 @@ impl Relay {
                 match new_current_thread() {
                     Ok(rt) => {
                         std::thread::spawn(move || {
-                            rt.block_on(async move { func_relay_event.await });
+                            rt.block_on(func_relay_event);
                             rt.shutdown_timeout(Duration::from_millis(100));
                         });
                     }
@@ -242,17 +977,63 @@ impl Relay {
                 #[cfg(not(feature = "blocking"))]
                 tokio::task::spawn(func_relay_event);
 
+                // REQs are lost on disconnection
+                if self.connected_once.swap(true, Ordering::SeqCst) {
+                    self.resubscribe().await;
+                }
+
+                // Keepalive state of this connection
+                let alive = Arc::new(AtomicBool::new(true));
+                let last_activity = Arc::new(Mutex::new(Instant::now()));
+                let dead = Arc::new(Notify::new());
+
                 let relay = self.clone();
+                let msg_alive = alive.clone();
+                let msg_last_activity = last_activity.clone();
+                let msg_dead = dead.clone();
                 let func_relay_msg = async move {
                     log::debug!("Relay Message Thread Started");
-                    while let Some(msg_res) = ws_rx.next().await {
+                    loop {
+                        let msg_res = tokio::select! {
+                            msg_res = ws_rx.next() => msg_res,
+                            _ = msg_dead.notified() => break,
+                        };
+                        let msg_res = match msg_res {
+                            Some(msg_res) => msg_res,
+                            None => break,
+                        };
+
                         if let Ok(msg) = msg_res {
+                            *msg_last_activity.lock().await = Instant::now();
+
+                            // Ping and pong frames are not relay messages
+                            let is_data = msg.is_data();
                             let data: Vec<u8> = msg.into_data();
 
                             match String::from_utf8(data) {
                                 Ok(data) => match RelayMessage::from_json(&data) {
                                     Ok(msg) => {
                                         log::trace!("Received message to {}: {:?}", relay.url, msg);
+                                        relay.track_relay_msg(&msg).await;
+                                        relay.stats.lock().await.received(&msg);
+
+                                        let retry = relay.rate_limiter.lock().await.handle(&msg);
+                                        if let Some((retry, delay)) = retry {
+                                            log::warn!(
+                                                "Rate limited by {}: retrying in {:?}",
+                                                relay.url,
+                                                delay
+                                            );
+                                            relay.schedule_retry(retry, delay);
+                                            continue;
+                                        }
+
+                                        if relay.handle_auth(&msg).await {
+                                            continue;
+                                        }
+
+                                        relay.handle_ok(&msg).await;
+
                                         if let Err(err) = relay
                                             .pool_sender
                                             .send(RelayPoolEvent::ReceivedMsg {
@@ -269,14 +1050,30 @@ impl Relay {
                                     }
                                     Err(err) => {
                                         log::error!("{}: {}", err, data);
+                                        if is_data {
+                                            relay
+                                                .record_failure(format!(
+                                                    "malformed message: {}",
+                                                    err
+                                                ))
+                                                .await;
+                                        }
                                     }
                                 },
-                                Err(err) => log::error!("{}", err),
+                                Err(err) => {
+                                    log::error!("{}", err);
+                                    if is_data {
+                                        relay
+                                            .record_failure(format!("malformed message: {}", err))
+                                            .await;
+                                    }
+                                }
                             }
                         }
                     }
 
                     log::debug!("Exited from Message Thread of {}", relay.url);
+                    msg_alive.store(false, Ordering::SeqCst);
 
                     if relay.status().await != RelayStatus::Terminated {
                         if let Err(err) = relay.disconnect().await {
@@ -289,7 +1086,7 @@ impl Relay {
                 match new_current_thread() {
                     Ok(rt) => {
                         std::thread::spawn(move || {
-                            rt.block_on(async move { func_relay_msg.await });
+                            rt.block_on(func_relay_msg);
                             rt.shutdown_timeout(Duration::from_millis(100));
                         });
                     }
@@ -299,16 +1096,20 @@ impl Relay {
                 #[cfg(not(feature = "blocking"))]
                 tokio::task::spawn(func_relay_msg);
 
-                // Ping thread
+                // Ping thread: recycle the connection if nothing is received after a ping
                 let relay = self.clone();
                 let func_relay_ping = async move {
                     log::debug!("Relay Ping Thread Started");
 
                     loop {
-                        tokio::time::sleep(Duration::from_secs(60)).await;
-                        if relay.status().await == RelayStatus::Terminated {
+                        tokio::time::sleep(relay.ping_interval).await;
+                        if !alive.load(Ordering::SeqCst)
+                            || relay.status().await == RelayStatus::Terminated
+                        {
                             break;
                         }
+
+                        let sent_at = Instant::now();
                         match relay.ping().await {
                             Ok(_) => log::debug!("Ping {}", relay.url),
                             Err(err) => {
@@ -316,22 +1117,32 @@ impl Relay {
                                 break;
                             }
                         }
-                    }
-
-                    log::debug!("Exited from Ping Thread of {}", relay.url);
 
-                    if relay.status().await != RelayStatus::Terminated {
-                        if let Err(err) = relay.disconnect().await {
-                            log::error!("Impossible to disconnect {}: {}", relay.url, err);
+                        tokio::time::sleep(relay.pong_timeout).await;
+                        if !alive.load(Ordering::SeqCst) {
+                            break;
+                        }
+                        if *last_activity.lock().await < sent_at {
+                            log::warn!(
+                                "No pong from {} within {:?}: reconnecting",
+                                relay.url,
+                                relay.pong_timeout
+                            );
+                            relay.stats.lock().await.error("pong timeout");
+                            // The message thread exits and disconnects the relay
+                            dead.notify_one();
+                            break;
                         }
                     }
+
+                    log::debug!("Exited from Ping Thread of {}", relay.url);
                 };
 
                 #[cfg(feature = "blocking")]
                 match new_current_thread() {
                     Ok(rt) => {
                         std::thread::spawn(move || {
-                            rt.block_on(async move { func_relay_ping.await });
+                            rt.block_on(func_relay_ping);
                             rt.shutdown_timeout(Duration::from_millis(100));
                         });
                     }
@@ -344,6 +1155,7 @@ impl Relay {
             Err(err) => {
                 self.set_status(RelayStatus::Disconnected).await;
                 log::error!("Impossible to connect to {}: {}", url, err);
+                self.record_failure(err.to_string()).await;
             }
         };
     }
@@ -370,7 +1182,245 @@ impl Relay {
 
     /// Send msg to relay
     pub async fn send_msg(&self, msg: ClientMessage) -> Result<(), Error> {
-        self.send_relay_event(RelayEvent::SendMsg(Box::new(msg)))
+        self.track_subscription(&msg).await;
+        self.send_relay_event(RelayEvent::SendMsg(Box::new(msg), None))
             .await
     }
+
+    /// Send msg to relay and wait until it has been written to the socket
+    ///
+    /// Messages are queued while the relay is disconnected: return [`Error::Timeout`]
+    /// if it isn't sent within `timeout`.
+    pub async fn send_msg_and_wait(
+        &self,
+        msg: ClientMessage,
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        self.track_subscription(&msg).await;
+        let (tx, rx) = oneshot::channel();
+        self.send_relay_event(RelayEvent::SendMsg(Box::new(msg), Some(tx)))
+            .await?;
+        match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(true)) => Ok(()),
+            Ok(Ok(false)) | Ok(Err(_)) => Err(Error::MessageNotSent),
+            Err(_) => Err(Error::Timeout),
+        }
+    }
+
+    /// Send event and wait for the `OK` message of the relay
+    ///
+    /// Return [`Error::EventNotPublished`] with the relay message if the event has been
+    /// rejected (i.e. `pow: difficulty 25>=24`) or [`Error::Timeout`] if no `OK` has been received.
+    /// Rate-limited events are sent again before giving up.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/20.md>
+    pub async fn send_event(&self, event: Event, timeout: Duration) -> Result<(), Error> {
+        let event_id = event.id;
+        let (tx, rx) = oneshot::channel();
+        self.acks.lock().await.entry(event_id).or_default().push(tx);
+
+        if let Err(e) = self.send_msg(ClientMessage::new_event(event)).await {
+            self.acks.lock().await.remove(&event_id);
+            return Err(e);
+        }
+
+        match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(res)) => res,
+            Ok(Err(_)) | Err(_) => {
+                let mut acks = self.acks.lock().await;
+                if let Some(waiters) = acks.get_mut(&event_id) {
+                    waiters.retain(|waiter| !waiter.is_closed());
+                    if waiters.is_empty() {
+                        acks.remove(&event_id);
+                    }
+                }
+                drop(acks);
+                self.record_failure(format!("no OK for event {}", event_id))
+                    .await;
+                Err(Error::Timeout)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::{sink, stream};
+    use nostr::event::TagData;
+    use nostr::{EventBuilder, Keys, Kind, KindBase, Tag};
+    use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+
+    use super::pool::RelayPool;
+    use super::*;
+
+    /// Transport connected to the test instead of a relay
+    #[derive(Debug)]
+    struct MockTransport {
+        /// Frames written by the relay
+        sent: UnboundedSender<TransportMessage>,
+        /// Frames to deliver to the relay (single connection)
+        received: std::sync::Mutex<Option<UnboundedReceiver<TransportMessage>>>,
+    }
+
+    impl MockTransport {
+        fn new() -> (
+            Self,
+            UnboundedReceiver<TransportMessage>,
+            UnboundedSender<TransportMessage>,
+        ) {
+            let (sent, sent_rx) = mpsc::unbounded_channel();
+            let (received_tx, received) = mpsc::unbounded_channel();
+            let transport = Self {
+                sent,
+                received: std::sync::Mutex::new(Some(received)),
+            };
+            (transport, sent_rx, received_tx)
+        }
+    }
+
+    impl RelayTransport for MockTransport {
+        fn connect<'a>(
+            &'a self,
+            _url: &'a Url,
+            _proxy: Option<SocketAddr>,
+            _tls: &'a TlsOptions,
+            _timeout: Option<Duration>,
+        ) -> TransportFuture<'a> {
+            Box::pin(async move {
+                let received = self.received.lock().unwrap().take().ok_or_else(|| {
+                    TransportError::Custom(Box::new(std::io::Error::from(
+                        std::io::ErrorKind::ConnectionRefused,
+                    )))
+                })?;
+                let sink = sink::unfold(self.sent.clone(), |sent, msg| async move {
+                    sent.send(msg)
+                        .map_err(|e| TransportError::Custom(Box::new(e)))?;
+                    Ok::<_, TransportError>(sent)
+                });
+                let stream = stream::unfold(received, |mut received| async move {
+                    let msg = received.recv().await?;
+                    Some((Ok(msg), received))
+                });
+                let sink: TransportSink = Box::new(Box::pin(sink));
+                let stream: TransportStream = Box::new(Box::pin(stream));
+                Ok((sink, stream))
+            })
+        }
+    }
+
+    /// Next client message written by the relay, skipping the other frames
+    async fn next_client_msg(sent: &mut UnboundedReceiver<TransportMessage>) -> ClientMessage {
+        loop {
+            let msg = tokio::time::timeout(Duration::from_secs(5), sent.recv())
+                .await
+                .expect("no message in time")
+                .expect("transport closed");
+            // The empty subscription of the pool (`REQ` without filters) isn't parsed
+            if let TransportMessage::Text(text) = msg {
+                if let Ok(msg) = ClientMessage::from_json(&text) {
+                    return msg;
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_retry_is_delayed() {
+        let (transport, mut sent, received) = MockTransport::new();
+        let pool = RelayPool::new();
+        pool.set_transport(transport).await;
+        // Nothing listens there: the NIP-11 request fails immediately
+        pool.add_relay(Url::parse("ws://127.0.0.1:1").unwrap(), None)
+            .await;
+        pool.connect(true).await.unwrap();
+
+        let keys = Keys::generate_from_os_random();
+        let event = EventBuilder::new_text_note("test", &[])
+            .to_event(&keys)
+            .unwrap();
+        pool.send_client_msg(ClientMessage::new_event(event.clone()))
+            .await
+            .unwrap();
+        assert_eq!(
+            next_client_msg(&mut sent).await,
+            ClientMessage::new_event(event.clone())
+        );
+
+        let rate_limited_at = Instant::now();
+        let ok = RelayMessage::new_ok(
+            event.id,
+            false,
+            String::from("rate-limited: try again in 500ms"),
+        );
+        received.send(TransportMessage::Text(ok.to_json())).unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // The other messages are sent while waiting for the retry
+        let req = ClientMessage::new_req("test", vec![SubscriptionFilter::new().limit(1)]);
+        pool.send_client_msg(req.clone()).await.unwrap();
+        assert_eq!(next_client_msg(&mut sent).await, req);
+        assert!(rate_limited_at.elapsed() < Duration::from_millis(400));
+
+        assert_eq!(
+            next_client_msg(&mut sent).await,
+            ClientMessage::new_event(event)
+        );
+        assert!(rate_limited_at.elapsed() >= Duration::from_millis(500));
+    }
+
+    #[tokio::test]
+    async fn test_auto_authentication() {
+        let (transport, mut sent, received) = MockTransport::new();
+        let keys = Keys::generate_from_os_random();
+        let pool = RelayPool::new();
+        pool.set_transport(transport).await;
+        pool.set_auth_keys(Some(keys.clone())).await;
+        let url = Url::parse("ws://127.0.0.1:1").unwrap();
+        pool.add_relay(url.clone(), None).await;
+        pool.connect(true).await.unwrap();
+
+        let event = EventBuilder::new_text_note("test", &[])
+            .to_event(&keys)
+            .unwrap();
+        pool.send_client_msg(ClientMessage::new_event(event.clone()))
+            .await
+            .unwrap();
+        assert_eq!(
+            next_client_msg(&mut sent).await,
+            ClientMessage::new_event(event.clone())
+        );
+
+        let reply = |msg: RelayMessage| {
+            received
+                .send(TransportMessage::Text(msg.to_json()))
+                .unwrap();
+        };
+        reply(RelayMessage::new_auth("challenge"));
+        reply(RelayMessage::new_ok(
+            event.id,
+            false,
+            String::from("auth-required: we only accept events from registered users"),
+        ));
+
+        let auth = match next_client_msg(&mut sent).await {
+            ClientMessage::Auth { event } => event,
+            msg => panic!("unexpected message: {:?}", msg),
+        };
+        assert_eq!(auth.pubkey, keys.public_key());
+        assert_eq!(auth.kind, Kind::Base(KindBase::Authentication));
+        assert_eq!(
+            auth.tags,
+            vec![
+                Tag::new(TagData::Challenge(String::from("challenge"))),
+                Tag::new(TagData::Relay(url)),
+            ]
+        );
+
+        // Sent again once authenticated
+        reply(RelayMessage::new_ok(auth.id, true, String::new()));
+        assert_eq!(
+            next_client_msg(&mut sent).await,
+            ClientMessage::new_event(event)
+        );
+    }
 }
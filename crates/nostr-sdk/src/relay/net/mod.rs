@@ -2,16 +2,14 @@
 // Distributed under the MIT software license
 
 use std::net::SocketAddr;
-use std::sync::Arc;
 use std::time::Duration;
 
 use futures_util::stream::{SplitSink, SplitStream};
 use futures_util::StreamExt;
 use nostr::url::{ParseError, Url};
 use tokio::net::TcpStream;
-use tokio_rustls::client::TlsStream;
-use tokio_rustls::rustls::{ClientConfig, OwnedTrustAnchor, RootCertStore, ServerName};
-use tokio_rustls::TlsConnector;
+#[cfg(not(any(feature = "rustls", feature = "native-tls")))]
+use tokio_tungstenite::tungstenite::error::UrlError;
 use tokio_tungstenite::tungstenite::{Error as WsError, Message};
 use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
 
@@ -20,8 +18,14 @@ type Sink = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
 type Stream = SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>;
 
 mod socks;
+mod tls;
 
 use self::socks::TpcSocks5Stream;
+use self::tls::Error as TlsError;
+pub use self::tls::TlsOptions;
+
+/// Connection timeout used when none is set
+pub(crate) const DEFAULT_CONNECTION_TIMEOUT: Duration = Duration::from_secs(60);
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -36,9 +40,9 @@ pub enum Error {
     /// Timeout
     #[error("timeout")]
     Timeout,
-    /// Invalid DNS name
-    #[error("invalid DNS name")]
-    InvalidDNSName,
+    /// TLS error
+    #[error("tls error: {0}")]
+    Tls(#[from] TlsError),
     /// Url parse error
     #[error("impossible to parse URL: {0}")]
     Url(#[from] nostr::url::ParseError),
@@ -47,67 +51,43 @@ pub enum Error {
 pub(crate) async fn get_connection(
     url: &Url,
     proxy: Option<SocketAddr>,
+    tls: &TlsOptions,
     timeout: Option<Duration>,
 ) -> Result<(Sink, Stream), Error> {
-    let stream = match proxy {
-        Some(proxy) => connect_proxy(url, proxy, timeout).await?,
-        None => connect_direct(url, timeout).await?,
-    };
-    Ok(stream.split())
-}
-
-async fn connect_direct(url: &Url, timeout: Option<Duration>) -> Result<WebSocket, Error> {
-    let timeout = timeout.unwrap_or(Duration::from_secs(60));
-    let (stream, _) = tokio::time::timeout(timeout, tokio_tungstenite::connect_async(url))
-        .await
-        .map_err(|_| Error::Timeout)??;
-    Ok(stream)
-}
-
-async fn connect_proxy(
-    url: &Url,
-    proxy: SocketAddr,
-    timeout: Option<Duration>,
-) -> Result<WebSocket, Error> {
-    let timeout = timeout.unwrap_or(Duration::from_secs(60));
+    let timeout = timeout.unwrap_or(DEFAULT_CONNECTION_TIMEOUT);
     let addr: String = match url.host_str() {
         Some(host) => match url.port_or_known_default() {
             Some(port) => format!("{}:{}", host, port),
-            None => return Err(Error::Url(ParseError::EmptyHost)),
+            None => return Err(Error::Url(ParseError::InvalidPort)),
         },
-        None => return Err(Error::Url(ParseError::InvalidPort)),
+        None => return Err(Error::Url(ParseError::EmptyHost)),
     };
+    let stream = tokio::time::timeout(timeout, async {
+        let conn = match proxy {
+            Some(proxy) => TpcSocks5Stream::connect(proxy, addr).await?,
+            None => TcpStream::connect(addr).await?,
+        };
+        handshake(url, conn, tls).await
+    })
+    .await
+    .map_err(|_| Error::Timeout)??;
 
-    let conn = TpcSocks5Stream::connect(proxy, addr.clone()).await?;
-    let conn = match connect_with_tls(conn, url).await {
-        Ok(stream) => MaybeTlsStream::Rustls(stream),
-        Err(_) => {
-            let conn = TpcSocks5Stream::connect(proxy, addr).await?;
-            MaybeTlsStream::Plain(conn)
-        }
-    };
+    Ok(stream.split())
+}
 
-    let (stream, _) = tokio::time::timeout(timeout, tokio_tungstenite::client_async(url, conn))
-        .await
-        .map_err(|_| Error::Timeout)??;
+#[cfg(any(feature = "rustls", feature = "native-tls"))]
+async fn handshake(url: &Url, conn: TcpStream, tls: &TlsOptions) -> Result<WebSocket, Error> {
+    let connector = tls.connector()?;
+    let (stream, _) =
+        tokio_tungstenite::client_async_tls_with_config(url, conn, None, connector).await?;
     Ok(stream)
 }
 
-async fn connect_with_tls(stream: TcpStream, url: &Url) -> Result<TlsStream<TcpStream>, Error> {
-    let mut root_cert_store = RootCertStore::empty();
-    root_cert_store.add_server_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.0.iter().map(|ta| {
-        OwnedTrustAnchor::from_subject_spki_name_constraints(
-            ta.subject,
-            ta.spki,
-            ta.name_constraints,
-        )
-    }));
-    let config = ClientConfig::builder()
-        .with_safe_defaults()
-        .with_root_certificates(root_cert_store)
-        .with_no_client_auth();
-    let connector = TlsConnector::from(Arc::new(config));
-    let domain = url.domain().ok_or(Error::InvalidDNSName)?;
-    let domain = ServerName::try_from(domain).map_err(|_| Error::InvalidDNSName)?;
-    Ok(connector.connect(domain, stream).await?)
+#[cfg(not(any(feature = "rustls", feature = "native-tls")))]
+async fn handshake(url: &Url, conn: TcpStream, _tls: &TlsOptions) -> Result<WebSocket, Error> {
+    if url.scheme() == "wss" {
+        return Err(Error::Ws(WsError::Url(UrlError::TlsFeatureNotEnabled)));
+    }
+    let (stream, _) = tokio_tungstenite::client_async(url, MaybeTlsStream::Plain(conn)).await?;
+    Ok(stream)
 }
@@ -0,0 +1,168 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+#[cfg(all(feature = "rustls", not(feature = "native-tls")))]
+use std::sync::Arc;
+#[cfg(all(feature = "rustls", not(feature = "native-tls")))]
+use std::time::SystemTime;
+
+#[cfg(all(feature = "rustls", not(feature = "native-tls")))]
+use rustls::client::{ServerCertVerified, ServerCertVerifier};
+#[cfg(all(feature = "rustls", not(feature = "native-tls")))]
+use rustls::{Certificate as RustlsCertificate, ClientConfig, OwnedTrustAnchor, RootCertStore};
+#[cfg(all(feature = "rustls", not(feature = "native-tls")))]
+use rustls::{Error as RustlsError, ServerName};
+#[cfg(any(feature = "rustls", feature = "native-tls"))]
+use tokio_tungstenite::Connector;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Invalid certificate
+    #[error("invalid certificate")]
+    #[cfg_attr(not(any(feature = "rustls", feature = "native-tls")), allow(dead_code))]
+    InvalidCertificate,
+    /// Native TLS error
+    #[cfg(feature = "native-tls")]
+    #[error("native tls error: {0}")]
+    NativeTls(#[from] native_tls::Error),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Certificate {
+    Der(Vec<u8>),
+    Pem(Vec<u8>),
+}
+
+/// TLS options of the relay connections (i.e. for private relays with self-signed certificates)
+///
+/// The TLS backend is selected with the `rustls` (default) or `native-tls` feature.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TlsOptions {
+    #[cfg_attr(not(any(feature = "rustls", feature = "native-tls")), allow(dead_code))]
+    root_certificates: Vec<Certificate>,
+    accept_invalid_certs: bool,
+}
+
+impl TlsOptions {
+    /// New default `TlsOptions`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trust a DER-encoded root certificate, in addition to the default ones
+    pub fn root_certificate_der(mut self, der: Vec<u8>) -> Self {
+        self.root_certificates.push(Certificate::Der(der));
+        self
+    }
+
+    /// Trust the PEM-encoded root certificates, in addition to the default ones
+    pub fn root_certificate_pem(mut self, pem: Vec<u8>) -> Self {
+        self.root_certificates.push(Certificate::Pem(pem));
+        self
+    }
+
+    /// Accept any certificate, including the self-signed and expired ones (default: false)
+    ///
+    /// **Warning**: the connection is exposed to man-in-the-middle attacks.
+    /// Prefer [`TlsOptions::root_certificate_pem`] when the certificate is known.
+    pub fn danger_accept_invalid_certs(self, accept_invalid_certs: bool) -> Self {
+        Self {
+            accept_invalid_certs,
+            ..self
+        }
+    }
+
+    /// Check if any certificate is accepted
+    pub fn get_danger_accept_invalid_certs(&self) -> bool {
+        self.accept_invalid_certs
+    }
+
+    /// Build the TLS connector (`None` to use the default one)
+    #[cfg(any(feature = "rustls", feature = "native-tls"))]
+    pub(crate) fn connector(&self) -> Result<Option<Connector>, Error> {
+        if self == &Self::default() {
+            return Ok(None);
+        }
+
+        #[cfg(feature = "native-tls")]
+        return self.native_tls_connector().map(Some);
+
+        #[cfg(all(feature = "rustls", not(feature = "native-tls")))]
+        return self.rustls_connector().map(Some);
+    }
+
+    #[cfg(feature = "native-tls")]
+    fn native_tls_connector(&self) -> Result<Connector, Error> {
+        let mut builder = native_tls::TlsConnector::builder();
+        for certificate in self.root_certificates.iter() {
+            let certificate = match certificate {
+                Certificate::Der(der) => native_tls::Certificate::from_der(der),
+                Certificate::Pem(pem) => native_tls::Certificate::from_pem(pem),
+            }
+            .map_err(|_| Error::InvalidCertificate)?;
+            builder.add_root_certificate(certificate);
+        }
+        builder.danger_accept_invalid_certs(self.accept_invalid_certs);
+        Ok(Connector::NativeTls(builder.build()?))
+    }
+
+    #[cfg(all(feature = "rustls", not(feature = "native-tls")))]
+    fn rustls_connector(&self) -> Result<Connector, Error> {
+        let mut root_cert_store = RootCertStore::empty();
+        root_cert_store.add_server_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.0.iter().map(
+            |ta| {
+                OwnedTrustAnchor::from_subject_spki_name_constraints(
+                    ta.subject,
+                    ta.spki,
+                    ta.name_constraints,
+                )
+            },
+        ));
+
+        for certificate in self.root_certificates.iter() {
+            let ders = match certificate {
+                Certificate::Der(der) => vec![der.clone()],
+                Certificate::Pem(pem) => rustls_pemfile::certs(&mut pem.as_slice())
+                    .map_err(|_| Error::InvalidCertificate)?,
+            };
+            if ders.is_empty() {
+                return Err(Error::InvalidCertificate);
+            }
+            for der in ders.into_iter() {
+                root_cert_store
+                    .add(&RustlsCertificate(der))
+                    .map_err(|_| Error::InvalidCertificate)?;
+            }
+        }
+
+        let mut config = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(root_cert_store)
+            .with_no_client_auth();
+        if self.accept_invalid_certs {
+            config
+                .dangerous()
+                .set_certificate_verifier(Arc::new(NoCertificateVerification));
+        }
+        Ok(Connector::Rustls(Arc::new(config)))
+    }
+}
+
+/// Certificate verifier accepting any certificate
+#[cfg(all(feature = "rustls", not(feature = "native-tls")))]
+struct NoCertificateVerification;
+
+#[cfg(all(feature = "rustls", not(feature = "native-tls")))]
+impl ServerCertVerifier for NoCertificateVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &RustlsCertificate,
+        _intermediates: &[RustlsCertificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, RustlsError> {
+        Ok(ServerCertVerified::assertion())
+    }
+}
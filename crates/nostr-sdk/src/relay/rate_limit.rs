@@ -0,0 +1,275 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use nostr::message::MachineReadablePrefix;
+use nostr::{ClientMessage, RelayMessage, Sha256Hash};
+
+/// Max number of retries for a rate-limited message
+const MAX_RETRIES: u8 = 3;
+/// Max backoff delay
+const MAX_DELAY: Duration = Duration::from_secs(60);
+/// Time after which an event still waiting for an `OK` is forgotten
+const PENDING_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone)]
+struct Pending {
+    msg: ClientMessage,
+    retries: u8,
+    sent_at: Instant,
+}
+
+impl Pending {
+    fn new(msg: ClientMessage) -> Self {
+        Self {
+            msg,
+            retries: 0,
+            sent_at: Instant::now(),
+        }
+    }
+}
+
+/// Keep track of the messages sent to a relay and retry them
+/// when the relay replies with a `rate-limited:` `OK` or `CLOSED`
+#[derive(Debug, Default)]
+pub(crate) struct RateLimiter {
+    events: HashMap<Sha256Hash, Pending>,
+    subscriptions: HashMap<String, Pending>,
+}
+
+impl RateLimiter {
+    /// Check if `msg` is still waiting for a response (i.e. the subscription hasn't been
+    /// closed or replaced in the meantime)
+    pub fn is_pending(&self, msg: &ClientMessage) -> bool {
+        match msg {
+            ClientMessage::Event { event } | ClientMessage::Auth { event } => {
+                self.events.contains_key(&event.id)
+            }
+            ClientMessage::Req {
+                subscription_id, ..
+            } => self
+                .subscriptions
+                .get(subscription_id)
+                .map(|pending| &pending.msg == msg)
+                .unwrap_or(false),
+            ClientMessage::Close { .. } => false,
+        }
+    }
+
+    /// Track a message that is being sent to the relay
+    pub fn track(&mut self, msg: &ClientMessage) {
+        let now = Instant::now();
+        self.events
+            .retain(|_, pending| now.duration_since(pending.sent_at) < PENDING_TIMEOUT);
+
+        match msg {
+            ClientMessage::Event { event } | ClientMessage::Auth { event } => {
+                self.events
+                    .entry(event.id)
+                    .and_modify(|pending| pending.sent_at = now)
+                    .or_insert_with(|| Pending::new(msg.clone()));
+            }
+            ClientMessage::Req {
+                subscription_id, ..
+            } => {
+                self.subscriptions
+                    .entry(subscription_id.clone())
+                    .and_modify(|pending| {
+                        pending.msg = msg.clone();
+                        pending.sent_at = now;
+                    })
+                    .or_insert_with(|| Pending::new(msg.clone()));
+            }
+            ClientMessage::Close { subscription_id } => {
+                self.subscriptions.remove(subscription_id);
+            }
+        }
+    }
+
+    /// Handle a message received from the relay
+    ///
+    /// Return the message to send again if the relay rate-limited it, with the delay to wait
+    /// before sending it. Check [`RateLimiter::is_pending`] when the delay is elapsed.
+    pub fn handle(&mut self, msg: &RelayMessage) -> Option<(ClientMessage, Duration)> {
+        match msg {
+            RelayMessage::Ok {
+                event_id,
+                status,
+                message,
+            } => {
+                let pending = self.events.remove(event_id)?;
+                if *status {
+                    return None;
+                }
+                let (pending, delay) = Self::retry(pending, message)?;
+                let msg = pending.msg.clone();
+                self.events.insert(*event_id, pending);
+                Some((msg, delay))
+            }
+            RelayMessage::Closed {
+                subscription_id,
+                message,
+            } => {
+                let pending = self.subscriptions.remove(subscription_id)?;
+                let (pending, delay) = Self::retry(pending, message)?;
+                let msg = pending.msg.clone();
+                self.subscriptions.insert(subscription_id.clone(), pending);
+                Some((msg, delay))
+            }
+            _ => None,
+        }
+    }
+
+    fn retry(mut pending: Pending, message: &str) -> Option<(Pending, Duration)> {
+        if MachineReadablePrefix::parse(message) != Some(MachineReadablePrefix::RateLimited)
+            || pending.retries >= MAX_RETRIES
+        {
+            return None;
+        }
+
+        let delay = suggested_delay(message)
+            .unwrap_or_else(|| Duration::from_secs(1 << pending.retries))
+            .min(MAX_DELAY);
+        pending.retries += 1;
+        // Not forgotten before the retry is sent
+        pending.sent_at = Instant::now() + delay;
+
+        Some((pending, delay))
+    }
+}
+
+/// Extract the delay suggested by the relay (ex. `rate-limited: try again in 10 seconds`)
+fn suggested_delay(message: &str) -> Option<Duration> {
+    let start = message.find(|c: char| c.is_ascii_digit())?;
+    let rest = &message[start..];
+    let end = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    let value: u64 = rest[..end].parse().ok()?;
+    let unit = rest[end..]
+        .trim_start()
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .next()
+        .unwrap_or_default();
+
+    match unit {
+        "ms" | "millis" | "milliseconds" => Some(Duration::from_millis(value)),
+        "s" | "sec" | "secs" | "second" | "seconds" => Some(Duration::from_secs(value)),
+        "m" | "min" | "mins" | "minute" | "minutes" => Some(Duration::from_secs(value * 60)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys, SubscriptionFilter};
+
+    use super::*;
+
+    fn event_msg() -> ClientMessage {
+        let keys = Keys::generate_from_os_random();
+        let event = EventBuilder::new_text_note("test", &[])
+            .to_event(&keys)
+            .unwrap();
+        ClientMessage::new_event(event)
+    }
+
+    fn event_id(msg: &ClientMessage) -> Sha256Hash {
+        match msg {
+            ClientMessage::Event { event } => event.id,
+            _ => panic!("not an event"),
+        }
+    }
+
+    #[test]
+    fn test_suggested_delay() {
+        assert_eq!(
+            suggested_delay("rate-limited: try again in 10 seconds"),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(
+            suggested_delay("rate-limited: slow down, 500ms"),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(
+            suggested_delay("rate-limited: wait 2 min"),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(suggested_delay("rate-limited: slow down"), None);
+        assert_eq!(suggested_delay("rate-limited: 3 apples"), None);
+    }
+
+    #[test]
+    fn test_retry_event() {
+        let mut limiter = RateLimiter::default();
+        let msg = event_msg();
+        let id = event_id(&msg);
+        limiter.track(&msg);
+
+        // Exponential backoff without a suggested delay
+        for retries in 0..MAX_RETRIES {
+            let ok = RelayMessage::new_ok(id, false, String::from("rate-limited: slow down"));
+            let (retry, delay) = limiter.handle(&ok).unwrap();
+            assert_eq!(retry, msg);
+            assert_eq!(delay, Duration::from_secs(1 << retries));
+            assert!(limiter.is_pending(&msg));
+            limiter.track(&retry);
+        }
+
+        // Too many retries
+        let ok = RelayMessage::new_ok(id, false, String::from("rate-limited: slow down"));
+        assert!(limiter.handle(&ok).is_none());
+        assert!(!limiter.is_pending(&msg));
+    }
+
+    #[test]
+    fn test_no_retry() {
+        let mut limiter = RateLimiter::default();
+        let msg = event_msg();
+        let id = event_id(&msg);
+
+        // Not tracked
+        let ok = RelayMessage::new_ok(id, false, String::from("rate-limited: slow down"));
+        assert!(limiter.handle(&ok).is_none());
+
+        // Not rate-limited
+        limiter.track(&msg);
+        let ok = RelayMessage::new_ok(id, false, String::from("blocked: spam"));
+        assert!(limiter.handle(&ok).is_none());
+
+        // Accepted
+        limiter.track(&msg);
+        assert!(limiter.is_pending(&msg));
+        let ok = RelayMessage::new_ok(id, true, String::new());
+        assert!(limiter.handle(&ok).is_none());
+        assert!(!limiter.is_pending(&msg));
+    }
+
+    #[test]
+    fn test_retry_subscription() {
+        let mut limiter = RateLimiter::default();
+        let req = ClientMessage::new_req("sub", vec![SubscriptionFilter::new().limit(10)]);
+        limiter.track(&req);
+
+        let closed = RelayMessage::new_closed(
+            String::from("sub"),
+            String::from("rate-limited: try again in 3 seconds"),
+        );
+        let (retry, delay) = limiter.handle(&closed).unwrap();
+        assert_eq!(retry, req);
+        assert_eq!(delay, Duration::from_secs(3));
+        assert!(limiter.is_pending(&req));
+
+        // Replaced while waiting: the old REQ must not be sent again
+        let new_req = ClientMessage::new_req("sub", vec![SubscriptionFilter::new().limit(20)]);
+        limiter.track(&new_req);
+        assert!(!limiter.is_pending(&req));
+        assert!(limiter.is_pending(&new_req));
+
+        // Closed while waiting
+        limiter.track(&ClientMessage::close(String::from("sub")));
+        assert!(!limiter.is_pending(&new_req));
+    }
+}
@@ -0,0 +1,227 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use nostr::{ClientMessage, RelayMessage, Sha256Hash};
+
+/// Time after which a message still waiting for a response is forgotten
+const PENDING_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Policy to temporarily disable the relays that repeatedly fail
+///
+/// A relay is demoted (disconnected and not reconnected for [`ScoringOptions::demotion_time`])
+/// after [`ScoringOptions::max_failures`] consecutive failures: connection errors, timeouts or
+/// malformed data. Any `EOSE` or `OK` received from the relay resets the failures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScoringOptions {
+    max_failures: u32,
+    demotion_time: Duration,
+}
+
+impl Default for ScoringOptions {
+    fn default() -> Self {
+        Self {
+            max_failures: 5,
+            demotion_time: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+impl ScoringOptions {
+    /// New default `ScoringOptions`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set number of consecutive failures after which the relay is demoted (default: 5)
+    pub fn max_failures(self, max_failures: u32) -> Self {
+        Self {
+            max_failures,
+            ..self
+        }
+    }
+
+    /// Set for how long a demoted relay is disabled (default: 5 min)
+    pub fn demotion_time(self, demotion_time: Duration) -> Self {
+        Self {
+            demotion_time,
+            ..self
+        }
+    }
+
+    /// Number of consecutive failures after which the relay is demoted
+    pub fn get_max_failures(&self) -> u32 {
+        self.max_failures
+    }
+
+    /// For how long a demoted relay is disabled
+    pub fn get_demotion_time(&self) -> Duration {
+        self.demotion_time
+    }
+}
+
+/// Relay health statistics
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RelayStats {
+    /// Time since the connection has been established (`None` if not connected)
+    pub uptime: Option<Duration>,
+    /// Round-trip time from `REQ` to `EOSE`, averaged over the last subscriptions
+    pub req_latency: Option<Duration>,
+    /// Round-trip time from `EVENT` to `OK`, averaged over the last events
+    pub ok_latency: Option<Duration>,
+    /// Number of `NOTICE` messages received
+    pub notices: u64,
+    /// Last error (connection, sending or event rejected by the relay)
+    pub last_error: Option<String>,
+    /// Consecutive failures (connection errors, timeouts or malformed data)
+    pub failures: u32,
+    /// Remaining time before the relay is restored, if demoted (see [`ScoringOptions`])
+    pub demoted: Option<Duration>,
+}
+
+/// Exponential moving average
+fn average(avg: Option<Duration>, rtt: Duration) -> Duration {
+    match avg {
+        Some(avg) => (avg * 4 + rtt) / 5,
+        None => rtt,
+    }
+}
+
+/// Keep track of the messages exchanged with a relay
+#[derive(Debug, Default)]
+pub(crate) struct StatsTracker {
+    connected_at: Option<Instant>,
+    req_latency: Option<Duration>,
+    ok_latency: Option<Duration>,
+    notices: u64,
+    last_error: Option<String>,
+    failures: u32,
+    demoted_until: Option<Instant>,
+    /// `REQ`s waiting for an `EOSE`
+    reqs: HashMap<String, Instant>,
+    /// Events waiting for an `OK`
+    events: HashMap<Sha256Hash, Instant>,
+}
+
+impl StatsTracker {
+    pub fn connected(&mut self) {
+        self.connected_at = Some(Instant::now());
+    }
+
+    pub fn disconnected(&mut self) {
+        self.connected_at = None;
+        self.reqs.clear();
+        self.events.clear();
+    }
+
+    pub fn error<S>(&mut self, error: S)
+    where
+        S: Into<String>,
+    {
+        self.last_error = Some(error.into());
+    }
+
+    /// Record a failure
+    ///
+    /// Return `true` if the relay has just been demoted.
+    pub fn failure(&mut self, scoring: Option<ScoringOptions>) -> bool {
+        self.failures = self.failures.saturating_add(1);
+
+        match scoring {
+            Some(scoring)
+                if self.demoted_until.is_none() && self.failures >= scoring.max_failures =>
+            {
+                self.demoted_until = Some(Instant::now() + scoring.demotion_time);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Check if the relay is demoted
+    ///
+    /// Return `Some(false)` if the demotion has just expired: the failures are reset.
+    pub fn demoted(&mut self) -> Option<bool> {
+        let until = self.demoted_until?;
+        if until > Instant::now() {
+            return Some(true);
+        }
+
+        self.demoted_until = None;
+        self.failures = 0;
+        Some(false)
+    }
+
+    pub fn req_latency(&self) -> Option<Duration> {
+        self.req_latency
+    }
+
+    /// Track a message written to the relay
+    pub fn sent(&mut self, msg: &ClientMessage) {
+        let now = Instant::now();
+        self.reqs
+            .retain(|_, sent_at| now.duration_since(*sent_at) < PENDING_TIMEOUT);
+        self.events
+            .retain(|_, sent_at| now.duration_since(*sent_at) < PENDING_TIMEOUT);
+
+        match msg {
+            ClientMessage::Event { event } => {
+                self.events.insert(event.id, now);
+            }
+            ClientMessage::Req {
+                subscription_id, ..
+            } => {
+                self.reqs.insert(subscription_id.clone(), now);
+            }
+            ClientMessage::Close { subscription_id } => {
+                self.reqs.remove(subscription_id);
+            }
+            ClientMessage::Auth { .. } => (),
+        }
+    }
+
+    /// Track a message received from the relay
+    pub fn received(&mut self, msg: &RelayMessage) {
+        match msg {
+            RelayMessage::EndOfStoredEvents { subscription_id } => {
+                if let Some(sent_at) = self.reqs.remove(subscription_id) {
+                    self.req_latency = Some(average(self.req_latency, sent_at.elapsed()));
+                }
+                self.failures = 0;
+            }
+            RelayMessage::Ok {
+                event_id,
+                status,
+                message,
+            } => {
+                if let Some(sent_at) = self.events.remove(event_id) {
+                    self.ok_latency = Some(average(self.ok_latency, sent_at.elapsed()));
+                }
+                self.failures = 0;
+                if !status {
+                    self.last_error = Some(format!("event {} rejected: {}", event_id, message));
+                }
+            }
+            RelayMessage::Notice { .. } => self.notices += 1,
+            _ => (),
+        }
+    }
+
+    pub fn stats(&self) -> RelayStats {
+        RelayStats {
+            uptime: self.connected_at.map(|connected_at| connected_at.elapsed()),
+            req_latency: self.req_latency,
+            ok_latency: self.ok_latency,
+            notices: self.notices,
+            last_error: self.last_error.clone(),
+            failures: self.failures,
+            demoted: self.demoted_until.map(|until| {
+                until
+                    .checked_duration_since(Instant::now())
+                    .unwrap_or_default()
+            }),
+        }
+    }
+}
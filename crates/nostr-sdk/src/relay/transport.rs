@@ -0,0 +1,144 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::fmt;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::time::Duration;
+
+use futures_util::{future, Sink, SinkExt, Stream, StreamExt};
+use nostr::url::Url;
+use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+
+use super::net::{self, TlsOptions};
+
+/// Future returned by [`RelayTransport::connect`]
+pub type TransportFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<(TransportSink, TransportStream), Error>> + Send + 'a>>;
+
+/// Sending half of a relay connection
+pub type TransportSink = Box<dyn Sink<TransportMessage, Error = Error> + Send + Unpin>;
+/// Receiving half of a relay connection
+pub type TransportStream = Box<dyn Stream<Item = Result<TransportMessage, Error>> + Send + Unpin>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// WebSocket connection error
+    #[error(transparent)]
+    Net(Box<net::Error>),
+    /// Error of a custom transport
+    #[error("transport error: {0}")]
+    Custom(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl From<net::Error> for Error {
+    fn from(e: net::Error) -> Self {
+        Self::Net(Box::new(e))
+    }
+}
+
+impl From<WsError> for Error {
+    fn from(e: WsError) -> Self {
+        Self::from(net::Error::from(e))
+    }
+}
+
+/// Frame exchanged with a relay
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransportMessage {
+    /// Text frame
+    Text(String),
+    /// Binary frame
+    Binary(Vec<u8>),
+    /// Ping frame
+    Ping(Vec<u8>),
+    /// Pong frame
+    Pong(Vec<u8>),
+    /// Close frame
+    Close,
+}
+
+impl TransportMessage {
+    /// Check if the frame carries data (text or binary)
+    pub fn is_data(&self) -> bool {
+        matches!(self, Self::Text(_) | Self::Binary(_))
+    }
+
+    /// Get the payload of the frame
+    pub fn into_data(self) -> Vec<u8> {
+        match self {
+            Self::Text(text) => text.into_bytes(),
+            Self::Binary(data) | Self::Ping(data) | Self::Pong(data) => data,
+            Self::Close => Vec::new(),
+        }
+    }
+}
+
+impl From<Message> for TransportMessage {
+    fn from(msg: Message) -> Self {
+        match msg {
+            Message::Text(text) => Self::Text(text),
+            Message::Binary(data) => Self::Binary(data),
+            Message::Ping(data) => Self::Ping(data),
+            Message::Pong(data) => Self::Pong(data),
+            Message::Close(_) => Self::Close,
+            Message::Frame(frame) => Self::Binary(frame.into_data()),
+        }
+    }
+}
+
+impl From<TransportMessage> for Message {
+    fn from(msg: TransportMessage) -> Self {
+        match msg {
+            TransportMessage::Text(text) => Self::Text(text),
+            TransportMessage::Binary(data) => Self::Binary(data),
+            TransportMessage::Ping(data) => Self::Ping(data),
+            TransportMessage::Pong(data) => Self::Pong(data),
+            TransportMessage::Close => Self::Close(None),
+        }
+    }
+}
+
+/// Transport used to connect to the relays
+///
+/// Implement it to plug a different connection layer (i.e. WASM WebSocket, Tor streams or
+/// test doubles) into the [`RelayPool`](super::pool::RelayPool). The relays write their messages
+/// into the [`TransportSink`] and read the ones of the relay from the [`TransportStream`].
+pub trait RelayTransport: fmt::Debug + Send + Sync {
+    /// Open a connection to `url`
+    ///
+    /// `proxy`, `tls` and `timeout` are the settings of the relay: a transport may ignore them.
+    fn connect<'a>(
+        &'a self,
+        url: &'a Url,
+        proxy: Option<SocketAddr>,
+        tls: &'a TlsOptions,
+        timeout: Option<Duration>,
+    ) -> TransportFuture<'a>;
+}
+
+/// Default [`RelayTransport`]: WebSocket over TCP (or SOCKS5 proxy)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WebSocketTransport;
+
+impl RelayTransport for WebSocketTransport {
+    fn connect<'a>(
+        &'a self,
+        url: &'a Url,
+        proxy: Option<SocketAddr>,
+        tls: &'a TlsOptions,
+        timeout: Option<Duration>,
+    ) -> TransportFuture<'a> {
+        Box::pin(async move {
+            let (ws_tx, ws_rx) = net::get_connection(url, proxy, tls, timeout).await?;
+            // Errors converted by `with`: `sink_map_err` panics at the second error
+            let ws_tx =
+                ws_tx.with(|msg: TransportMessage| future::ok::<Message, Error>(msg.into()));
+            let ws_rx = ws_rx.map(|res| res.map(TransportMessage::from).map_err(Error::from));
+            let sink: TransportSink = Box::new(ws_tx);
+            let stream: TransportStream = Box::new(ws_rx);
+            Ok((sink, stream))
+        })
+    }
+}
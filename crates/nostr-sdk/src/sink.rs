@@ -0,0 +1,147 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+#[cfg(feature = "webhook")]
+use nostr::url::Url;
+use tokio::sync::mpsc::Sender;
+
+use crate::RelayPoolNotifications;
+
+type CallbackFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Destination of the relay pool notifications
+#[derive(Clone)]
+pub enum NotificationSink {
+    /// Forward notifications to an mpsc channel
+    Channel(Sender<RelayPoolNotifications>),
+    /// Call an async callback for every notification
+    Callback(Arc<dyn Fn(RelayPoolNotifications) -> CallbackFuture + Send + Sync>),
+    /// POST notifications as JSON to an HTTP endpoint
+    #[cfg(feature = "webhook")]
+    Webhook(Url),
+}
+
+impl fmt::Debug for NotificationSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Channel(sender) => f.debug_tuple("Channel").field(sender).finish(),
+            Self::Callback(_) => f.debug_tuple("Callback").finish(),
+            #[cfg(feature = "webhook")]
+            Self::Webhook(url) => f.debug_tuple("Webhook").field(url).finish(),
+        }
+    }
+}
+
+impl NotificationSink {
+    /// New async callback sink
+    pub fn callback<F, Fut>(func: F) -> Self
+    where
+        F: Fn(RelayPoolNotifications) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        Self::Callback(Arc::new(move |notification| Box::pin(func(notification))))
+    }
+
+    /// Forward the notification to the sink
+    ///
+    /// Return `false` if the sink is closed and must be removed
+    pub(crate) async fn send(&self, notification: RelayPoolNotifications) -> bool {
+        match self {
+            Self::Channel(sender) => sender.send(notification).await.is_ok(),
+            Self::Callback(func) => {
+                func(notification).await;
+                true
+            }
+            #[cfg(feature = "webhook")]
+            Self::Webhook(url) => {
+                webhook::post(url, notification).await;
+                true
+            }
+        }
+    }
+}
+
+#[cfg(feature = "webhook")]
+mod webhook {
+    use nostr::url::Url;
+    use once_cell::sync::Lazy;
+    use reqwest::Client;
+    use serde_json::{json, Value};
+
+    use crate::RelayPoolNotifications;
+
+    static CLIENT: Lazy<Client> = Lazy::new(Client::new);
+
+    fn to_json(notification: RelayPoolNotifications) -> Value {
+        match notification {
+            RelayPoolNotifications::ReceivedEvent {
+                relay_url,
+                first_seen,
+                event,
+            } => json!({
+                "type": "event",
+                "relay_url": relay_url,
+                "first_seen": first_seen,
+                "event": event,
+            }),
+            RelayPoolNotifications::Message { relay_url, message } => json!({
+                "type": "message",
+                "relay_url": relay_url,
+                "message": serde_json::from_str::<Value>(&message.to_json()).unwrap_or_default(),
+            }),
+            RelayPoolNotifications::RelayStatusChanged { relay_url, status } => json!({
+                "type": "relay_status_changed",
+                "relay_url": relay_url,
+                "status": status.to_string(),
+            }),
+            #[cfg(feature = "nip11")]
+            RelayPoolNotifications::RelayRequiresPayment {
+                relay_url,
+                payments_url,
+            } => json!({
+                "type": "relay_requires_payment",
+                "relay_url": relay_url,
+                "payments_url": payments_url,
+            }),
+            RelayPoolNotifications::RelayDemoted {
+                relay_url,
+                duration,
+            } => json!({
+                "type": "relay_demoted",
+                "relay_url": relay_url,
+                "duration": duration.as_secs(),
+            }),
+            RelayPoolNotifications::RelayRestored { relay_url } => json!({
+                "type": "relay_restored",
+                "relay_url": relay_url,
+            }),
+            #[cfg(feature = "nip59")]
+            RelayPoolNotifications::PrivateDirectMessage {
+                relay_url,
+                sender,
+                rumor,
+            } => json!({
+                "type": "private_direct_message",
+                "relay_url": relay_url,
+                "sender": sender,
+                "rumor": rumor,
+            }),
+        }
+    }
+
+    pub(super) async fn post(url: &Url, notification: RelayPoolNotifications) {
+        let body = to_json(notification);
+        match CLIENT.post(url.clone()).json(&body).send().await {
+            Ok(res) if !res.status().is_success() => {
+                log::warn!("Webhook {} replied with {}", url, res.status())
+            }
+            Ok(_) => (),
+            Err(e) => log::error!("Impossible to send notification to webhook {}: {}", url, e),
+        }
+    }
+}
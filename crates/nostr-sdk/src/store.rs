@@ -0,0 +1,560 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::Path;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::Duration;
+
+use nostr::secp256k1::XOnlyPublicKey;
+use nostr::util::time::timestamp;
+use nostr::{Event, Kind, Sha256Hash, SubscriptionFilter};
+
+use crate::cipher::{self, StoreCipher};
+use crate::util;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// I/O error
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    /// Cipher error
+    #[error("cipher error: {0}")]
+    Cipher(#[from] cipher::Error),
+}
+
+/// Events storage
+pub trait EventStore: Send + Sync {
+    /// Save event
+    ///
+    /// Return `false` if the event was already stored or a newer version of it exists
+    /// (replaceable events).
+    fn save_event(&self, event: &Event) -> bool;
+
+    /// Get events matching any of the filters, from the newest (expired events excluded)
+    fn query(&self, filters: &[SubscriptionFilter]) -> Vec<Event>;
+
+    /// Remove the events not retained anymore and return how many were removed
+    ///
+    /// Called periodically by the [`LocalRelay`](crate::local_relay::LocalRelay).
+    fn vacuum(&self) -> usize {
+        0
+    }
+}
+
+/// Retention policy of a [`MemoryStore`] (default: keep everything)
+///
+/// # Example
+/// ```rust
+/// use std::time::Duration;
+///
+/// use nostr::{Kind, KindBase};
+/// use nostr_sdk::store::{MemoryStore, RetentionPolicy};
+///
+/// const DAY: Duration = Duration::from_secs(60 * 60 * 24);
+///
+/// // Keep DMs forever, drop reactions after 30 days and the rest after a year
+/// let policy = RetentionPolicy::new()
+///     .max_events(100_000)
+///     .max_age(DAY * 365)
+///     .kind_max_age(Kind::Base(KindBase::EncryptedDirectMessage), None)
+///     .kind_max_age(Kind::Base(KindBase::Reaction), Some(DAY * 30));
+/// let store = MemoryStore::with_retention(policy);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RetentionPolicy {
+    max_events: Option<usize>,
+    max_age: Option<Duration>,
+    /// Max age of the events of a kind (`None` to keep them forever), overrides `max_age`
+    kinds: HashMap<u64, Option<Duration>>,
+}
+
+impl RetentionPolicy {
+    /// New default `RetentionPolicy`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Max number of stored events: the oldest ones are removed first
+    ///
+    /// Events kept forever by [`RetentionPolicy::kind_max_age`] are removed only if the others
+    /// aren't enough.
+    pub fn max_events(self, max_events: usize) -> Self {
+        Self {
+            max_events: Some(max_events),
+            ..self
+        }
+    }
+
+    /// Max age of the stored events, compared with their `created_at`
+    pub fn max_age(self, max_age: Duration) -> Self {
+        Self {
+            max_age: Some(max_age),
+            ..self
+        }
+    }
+
+    /// Max age of the events of `kind` (`None` to keep them forever), overriding
+    /// [`RetentionPolicy::max_age`]
+    pub fn kind_max_age(mut self, kind: Kind, max_age: Option<Duration>) -> Self {
+        self.kinds.insert(kind.into(), max_age);
+        self
+    }
+
+    fn event_max_age(&self, event: &Event) -> Option<Duration> {
+        let kind: u64 = event.kind.into();
+        match self.kinds.get(&kind) {
+            Some(max_age) => *max_age,
+            None => self.max_age,
+        }
+    }
+
+    fn is_kept_forever(&self, event: &Event) -> bool {
+        let kind: u64 = event.kind.into();
+        matches!(self.kinds.get(&kind), Some(None))
+    }
+
+    fn is_too_old(&self, event: &Event, now: u64) -> bool {
+        match self.event_max_age(event) {
+            Some(max_age) => event.created_at < now.saturating_sub(max_age.as_secs()),
+            None => false,
+        }
+    }
+
+    /// Remove the oldest events above `max_events`
+    fn remove_exceeding(&self, index: &mut Index) {
+        if let Some(max_events) = self.max_events {
+            while index.events.len() > max_events {
+                match index.oldest() {
+                    Some(id) => {
+                        index.remove(&id);
+                    }
+                    None => break,
+                }
+            }
+        }
+    }
+
+    /// Remove the events older than their max age and return how many were removed
+    fn remove_too_old(&self, index: &mut Index, now: u64) -> usize {
+        let ids: Vec<Sha256Hash> = index
+            .events
+            .values()
+            .filter(|e| self.is_too_old(e, now))
+            .map(|e| e.id)
+            .collect();
+        for id in ids.iter() {
+            index.remove(id);
+        }
+        ids.len()
+    }
+}
+
+/// Author, kind and `d` tag of a replaceable event
+type ReplaceableKey = (XOnlyPublicKey, u64, String);
+
+/// Kinds for which only the latest event of each author (and `d` tag) is kept
+fn replaceable_key(event: &Event) -> Option<ReplaceableKey> {
+    let kind: u64 = event.kind.into();
+
+    match kind {
+        0 | 3 | 10000..=19999 => Some((event.pubkey, kind, String::new())),
+        30000..=39999 => Some((
+            event.pubkey,
+            kind,
+            event.identifier().unwrap_or_default().to_string(),
+        )),
+        _ => None,
+    }
+}
+
+/// Events of a [`MemoryStore`], indexed by id, `created_at` and replaceable key
+#[derive(Debug, Default)]
+struct Index {
+    events: HashMap<Sha256Hash, Event>,
+    /// All the events, from the oldest
+    ordered: BTreeSet<(u64, Sha256Hash)>,
+    /// Events not kept forever by the retention policy, from the oldest
+    evictable: BTreeSet<(u64, Sha256Hash)>,
+    /// Latest event of each replaceable key
+    replaceable: HashMap<ReplaceableKey, Sha256Hash>,
+}
+
+impl Index {
+    fn insert(&mut self, event: Event, evictable: bool) {
+        let key = (event.created_at, event.id);
+        if let Some(replaceable_key) = replaceable_key(&event) {
+            self.replaceable.insert(replaceable_key, event.id);
+        }
+        self.ordered.insert(key);
+        if evictable {
+            self.evictable.insert(key);
+        }
+        self.events.insert(event.id, event);
+    }
+
+    fn remove(&mut self, id: &Sha256Hash) -> Option<Event> {
+        let event = self.events.remove(id)?;
+        let key = (event.created_at, event.id);
+        self.ordered.remove(&key);
+        self.evictable.remove(&key);
+        if let Some(replaceable_key) = replaceable_key(&event) {
+            if self.replaceable.get(&replaceable_key) == Some(id) {
+                self.replaceable.remove(&replaceable_key);
+            }
+        }
+        Some(event)
+    }
+
+    /// Oldest event to remove when the store is full: the ones kept forever come last
+    fn oldest(&self) -> Option<Sha256Hash> {
+        self.evictable
+            .iter()
+            .next()
+            .or_else(|| self.ordered.iter().next())
+            .map(|(_, id)| *id)
+    }
+
+    /// Iterate the events from the oldest
+    fn ordered_events(&self) -> impl DoubleEndedIterator<Item = &Event> {
+        self.ordered
+            .iter()
+            .filter_map(|(_, id)| self.events.get(id))
+    }
+}
+
+/// In-memory [`EventStore`]
+///
+/// Unbounded by default: use [`MemoryStore::with_retention`] for long-running processes.
+#[derive(Debug)]
+pub struct MemoryStore {
+    index: RwLock<Index>,
+    retention: RetentionPolicy,
+    clock: fn() -> u64,
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self::with_retention(RetentionPolicy::default())
+    }
+}
+
+impl MemoryStore {
+    /// New empty `MemoryStore`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// New empty `MemoryStore` with a [`RetentionPolicy`]
+    ///
+    /// The size limit is applied when saving the events, the age limits by
+    /// [`EventStore::vacuum`] (too old events are also refused when saved).
+    pub fn with_retention(retention: RetentionPolicy) -> Self {
+        Self {
+            index: RwLock::new(Index::default()),
+            retention,
+            clock: timestamp,
+        }
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, Index> {
+        match self.index.read() {
+            Ok(index) => index,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, Index> {
+        match self.index.write() {
+            Ok(index) => index,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// Number of stored events
+    pub fn len(&self) -> usize {
+        self.read().events.len()
+    }
+
+    /// Check if no event is stored
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Write the stored events to `path` (one JSON event per line), encrypted with `cipher`
+    /// if set
+    pub fn save_snapshot<P>(&self, path: P, cipher: Option<&StoreCipher>) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        let index = self.read();
+        util::write_events(path.as_ref(), index.ordered_events(), cipher)
+    }
+
+    /// Save the events of a snapshot written by [`MemoryStore::save_snapshot`] and return how
+    /// many were saved
+    ///
+    /// A missing file has no events. Malformed lines are skipped.
+    pub fn load_snapshot<P>(&self, path: P, cipher: Option<&StoreCipher>) -> Result<usize, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let events: Vec<Event> = util::read_events::<Error>(path.as_ref(), cipher)?;
+        Ok(events.iter().filter(|e| self.save_event(e)).count())
+    }
+}
+
+impl EventStore for MemoryStore {
+    fn save_event(&self, event: &Event) -> bool {
+        let mut index = self.write();
+
+        if index.events.contains_key(&event.id) || self.retention.is_too_old(event, (self.clock)())
+        {
+            return false;
+        }
+
+        if let Some(key) = replaceable_key(event) {
+            if let Some(id) = index.replaceable.get(&key).copied() {
+                match index.events.get(&id) {
+                    Some(existing) if existing.created_at >= event.created_at => return false,
+                    _ => {
+                        index.remove(&id);
+                    }
+                }
+            }
+        }
+
+        index.insert(event.clone(), !self.retention.is_kept_forever(event));
+        self.retention.remove_exceeding(&mut index);
+        index.events.contains_key(&event.id)
+    }
+
+    fn query(&self, filters: &[SubscriptionFilter]) -> Vec<Event> {
+        let index = self.read();
+
+        let mut ids: HashSet<Sha256Hash> = HashSet::new();
+        let mut result: Vec<Event> = Vec::new();
+
+        for filter in filters.iter() {
+            let limit = filter.limit.map(|l| l as usize).unwrap_or(usize::MAX);
+            for event in index
+                .ordered_events()
+                .rev()
+                .filter(|e| !e.is_expired() && filter.match_event(e))
+                .take(limit)
+            {
+                if ids.insert(event.id) {
+                    result.push(event.clone());
+                }
+            }
+        }
+
+        result.sort_by_key(|e| std::cmp::Reverse(e.created_at));
+        result
+    }
+
+    fn vacuum(&self) -> usize {
+        let mut index = self.write();
+        self.retention.remove_too_old(&mut index, (self.clock)())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use nostr::event::TagData;
+    use nostr::{EventBuilder, Keys, KindBase, Tag};
+
+    use super::*;
+
+    const DAY: u64 = 60 * 60 * 24;
+
+    fn event(keys: &Keys, kind: KindBase, created_at: u64) -> Event {
+        EventBuilder::new(Kind::Base(kind), "test", &[])
+            .custom_created_at(created_at)
+            .to_event(keys)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_max_events() {
+        let keys = Keys::generate_from_os_random();
+        let now = timestamp();
+        let store = MemoryStore::with_retention(RetentionPolicy::new().max_events(2));
+
+        let oldest = event(&keys, KindBase::TextNote, now - 30);
+        let older = event(&keys, KindBase::TextNote, now - 20);
+        let newest = event(&keys, KindBase::TextNote, now - 10);
+        assert!(store.save_event(&older));
+        assert!(store.save_event(&newest));
+        // Older than the stored events: dropped immediately
+        assert!(!store.save_event(&oldest));
+        assert_eq!(store.len(), 2);
+
+        let new = event(&keys, KindBase::TextNote, now);
+        assert!(store.save_event(&new));
+        assert_eq!(store.query(&[SubscriptionFilter::new()]), vec![new, newest]);
+    }
+
+    #[test]
+    fn test_max_events_kept_forever() {
+        let keys = Keys::generate_from_os_random();
+        let now = timestamp();
+        let policy = RetentionPolicy::new()
+            .max_events(2)
+            .kind_max_age(Kind::Base(KindBase::EncryptedDirectMessage), None);
+        let store = MemoryStore::with_retention(policy);
+
+        let dm = event(&keys, KindBase::EncryptedDirectMessage, now - 30);
+        let note = event(&keys, KindBase::TextNote, now - 20);
+        let new_note = event(&keys, KindBase::TextNote, now - 10);
+        assert!(store.save_event(&dm));
+        assert!(store.save_event(&note));
+        assert!(store.save_event(&new_note));
+        assert_eq!(
+            store.query(&[SubscriptionFilter::new()]),
+            vec![new_note, dm]
+        );
+    }
+
+    #[test]
+    fn test_max_age() {
+        let keys = Keys::generate_from_os_random();
+        let now = timestamp();
+        let policy = RetentionPolicy::new()
+            .max_age(Duration::from_secs(DAY * 365))
+            .kind_max_age(Kind::Base(KindBase::EncryptedDirectMessage), None)
+            .kind_max_age(
+                Kind::Base(KindBase::Reaction),
+                Some(Duration::from_secs(DAY * 30)),
+            );
+        let store = MemoryStore::with_retention(policy);
+
+        assert!(!store.save_event(&event(&keys, KindBase::TextNote, now - DAY * 400)));
+        assert!(!store.save_event(&event(&keys, KindBase::Reaction, now - DAY * 31)));
+
+        let old_dm = event(&keys, KindBase::EncryptedDirectMessage, now - DAY * 400);
+        let note = event(&keys, KindBase::TextNote, now - DAY * 31);
+        let reaction = event(&keys, KindBase::Reaction, now - DAY * 29);
+        assert!(store.save_event(&old_dm));
+        assert!(store.save_event(&note));
+        assert!(store.save_event(&reaction));
+        assert_eq!(store.len(), 3);
+        assert_eq!(store.vacuum(), 0);
+    }
+
+    #[test]
+    fn test_vacuum() {
+        static NOW: AtomicU64 = AtomicU64::new(0);
+
+        fn clock() -> u64 {
+            NOW.load(Ordering::SeqCst)
+        }
+
+        let keys = Keys::generate_from_os_random();
+        let now = timestamp();
+        NOW.store(now, Ordering::SeqCst);
+        let mut store =
+            MemoryStore::with_retention(RetentionPolicy::new().max_age(Duration::from_secs(DAY)));
+        store.clock = clock;
+
+        // Saved before expiring
+        let events = [
+            event(&keys, KindBase::TextNote, now - DAY + 2),
+            event(&keys, KindBase::TextNote, now),
+        ];
+        for event in events.iter() {
+            assert!(store.save_event(event));
+        }
+        assert_eq!(store.vacuum(), 0);
+
+        NOW.store(now + 3, Ordering::SeqCst);
+        assert_eq!(store.vacuum(), 1);
+        assert_eq!(
+            store.query(&[SubscriptionFilter::new()]),
+            vec![events[1].clone()]
+        );
+    }
+
+    #[test]
+    fn test_replaceable() {
+        let keys = Keys::generate_from_os_random();
+        let now = timestamp();
+        let store = MemoryStore::new();
+
+        let metadata = event(&keys, KindBase::Metadata, now - 10);
+        let old_metadata = event(&keys, KindBase::Metadata, now - 20);
+        let new_metadata = event(&keys, KindBase::Metadata, now);
+        assert!(store.save_event(&metadata));
+        assert!(!store.save_event(&old_metadata));
+        assert!(store.save_event(&new_metadata));
+
+        // Parameterized replaceable events are replaced by `d` tag
+        let list = |d: &str, created_at: u64| {
+            EventBuilder::new(
+                Kind::Custom(30000),
+                "",
+                &[Tag::new(TagData::Identifier(d.to_string()))],
+            )
+            .custom_created_at(created_at)
+            .to_event(&keys)
+            .unwrap()
+        };
+        let first = list("first", now - 10);
+        let second = list("second", now - 10);
+        let new_first = list("first", now - 5);
+        assert!(store.save_event(&first));
+        assert!(store.save_event(&second));
+        assert!(store.save_event(&new_first));
+
+        assert_eq!(
+            store.query(&[SubscriptionFilter::new()]),
+            vec![new_metadata, new_first, second]
+        );
+    }
+
+    #[test]
+    fn test_encrypted_snapshot() {
+        let path = std::env::temp_dir().join(format!("store-{}.bin", uuid::Uuid::new_v4()));
+        let keys = Keys::generate_from_os_random();
+        let cipher = StoreCipher::from_keys(&keys).unwrap();
+        let now = timestamp();
+
+        let store = MemoryStore::new();
+        let dm = event(&keys, KindBase::EncryptedDirectMessage, now - 10);
+        let note = event(&keys, KindBase::TextNote, now);
+        assert!(store.save_event(&dm));
+        assert!(store.save_event(&note));
+        store.save_snapshot(&path, Some(&cipher)).unwrap();
+
+        let content = std::fs::read(&path).unwrap();
+        let needle = dm.id.to_string();
+        assert!(!content
+            .windows(needle.len())
+            .any(|w| w == needle.as_bytes()));
+
+        let restored = MemoryStore::new();
+        assert_eq!(restored.load_snapshot(&path, Some(&cipher)).unwrap(), 2);
+        assert_eq!(restored.query(&[SubscriptionFilter::new()]), vec![note, dm]);
+        // Already stored
+        assert_eq!(restored.load_snapshot(&path, Some(&cipher)).unwrap(), 0);
+
+        let other = StoreCipher::from_keys(&Keys::generate_from_os_random()).unwrap();
+        assert!(matches!(
+            MemoryStore::new().load_snapshot(&path, Some(&other)),
+            Err(Error::Cipher(cipher::Error::Decryption))
+        ));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_unbounded_by_default() {
+        let keys = Keys::generate_from_os_random();
+        let store = MemoryStore::new();
+        assert!(store.save_event(&event(&keys, KindBase::TextNote, 0)));
+        assert_eq!(store.vacuum(), 0);
+        assert_eq!(store.len(), 1);
+    }
+}
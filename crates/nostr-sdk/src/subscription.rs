@@ -2,11 +2,70 @@
 // Distributed under the MIT software license
 
 use std::collections::HashMap;
+use std::fmt;
 
+use nostr::key::XOnlyPublicKey;
+use nostr::message::subscription::HexPrefix;
 use nostr::url::Url;
 use nostr::SubscriptionFilter;
 use uuid::Uuid;
 
+/// Subscription id
+#[derive(Debug, Clone, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub struct SubscriptionId(String);
+
+impl SubscriptionId {
+    /// New `SubscriptionId`
+    pub fn new<S>(id: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self(id.into())
+    }
+
+    /// Generate new random `SubscriptionId`
+    pub fn generate() -> Self {
+        Self(Uuid::new_v4().to_string())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for SubscriptionId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Replace `old` with `new` in the `authors` and `#p` of the filters
+///
+/// Return `true` if any filter changed.
+pub(crate) fn replace_public_key(
+    filters: &mut [SubscriptionFilter],
+    old: XOnlyPublicKey,
+    new: XOnlyPublicKey,
+) -> bool {
+    let old_prefix = HexPrefix::from(old);
+    let mut changed = false;
+    for filter in filters.iter_mut() {
+        if let Some(authors) = filter.authors.as_mut() {
+            for author in authors.iter_mut().filter(|a| **a == old_prefix) {
+                *author = HexPrefix::from(new);
+                changed = true;
+            }
+        }
+        if let Some(pubkeys) = filter.pubkeys.as_mut() {
+            for pubkey in pubkeys.iter_mut().filter(|p| **p == old) {
+                *pubkey = new;
+                changed = true;
+            }
+        }
+    }
+    changed
+}
+
 #[derive(Debug, Clone)]
 pub struct Subscription {
     filters: Vec<SubscriptionFilter>,
@@ -37,6 +96,13 @@ impl Subscription {
         self.filters.clone()
     }
 
+    /// Replace `old` with `new` in the `authors` and `#p` of the filters
+    ///
+    /// Return `true` if any filter changed.
+    pub fn replace_public_key(&mut self, old: XOnlyPublicKey, new: XOnlyPublicKey) -> bool {
+        replace_public_key(&mut self.filters, old, new)
+    }
+
     /// Add new subscription channel
     pub fn add_channel(&mut self, relay_url: &Url, channel: Channel) {
         self.channels.insert(relay_url.clone(), channel);
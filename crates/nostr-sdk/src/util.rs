@@ -0,0 +1,89 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::fs;
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use nostr::Event;
+
+use crate::cipher::{self, StoreCipher};
+
+/// Write `content` to a temporary file and rename it to `path`,
+/// so that a crash never leaves a truncated file
+pub(crate) fn write_atomic(path: &Path, content: &[u8]) -> io::Result<()> {
+    let mut tmp_path = path.to_path_buf().into_os_string();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+
+    let mut file = fs::File::create(&tmp_path)?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, path)
+}
+
+/// Read the events at `path` (one JSON event per line), decrypted with `cipher` if set
+///
+/// A missing file has no events. Malformed lines are skipped.
+pub(crate) fn read_events<E>(path: &Path, cipher: Option<&StoreCipher>) -> Result<Vec<Event>, E>
+where
+    E: From<io::Error> + From<cipher::Error>,
+{
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let data = match cipher {
+        Some(cipher) => cipher.decrypt(&data)?.to_vec(),
+        None => data,
+    };
+    let content = String::from_utf8(data)
+        .map_err(|e| io::Error::new(ErrorKind::InvalidData, e.utf8_error()))?;
+
+    Ok(content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .filter_map(|(index, line)| match Event::from_json(line) {
+            Ok(event) => Some(event),
+            Err(e) => {
+                log::warn!(
+                    "Skipping malformed event at {}:{}: {}",
+                    path.display(),
+                    index + 1,
+                    e
+                );
+                None
+            }
+        })
+        .collect())
+}
+
+/// Write `events` to `path` (one JSON event per line), encrypted with `cipher` if set
+pub(crate) fn write_events<'a, I, E>(
+    path: &Path,
+    events: I,
+    cipher: Option<&StoreCipher>,
+) -> Result<(), E>
+where
+    I: IntoIterator<Item = &'a Event>,
+    E: From<io::Error> + From<cipher::Error>,
+{
+    let mut content = String::new();
+    for event in events.into_iter() {
+        match event.as_json() {
+            Ok(json) => {
+                content.push_str(&json);
+                content.push('\n');
+            }
+            Err(e) => log::error!("Impossible to serialize event {}: {}", event.id, e),
+        }
+    }
+
+    match cipher {
+        Some(cipher) => write_atomic(path, &cipher.encrypt(content.as_bytes())?)?,
+        None => write_atomic(path, content.as_bytes())?,
+    }
+    Ok(())
+}
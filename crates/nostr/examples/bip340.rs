@@ -7,8 +7,8 @@ use std::str::FromStr;
 
 use nostr::hashes::hex::FromHex;
 use nostr::secp256k1::schnorr::Signature;
-use nostr::secp256k1::{KeyPair, Message, Secp256k1, XOnlyPublicKey};
-use nostr::{Result, Sha256Hash};
+use nostr::secp256k1::{KeyPair, Message, XOnlyPublicKey};
+use nostr::{Result, Sha256Hash, SECP256K1};
 
 /// This is an incomplete attempt to test I'm doing bip340 correctly
 /// csv from https://github.com/bitcoin/bips/blob/master/bip-0340/test-vectors.csv
@@ -19,8 +19,6 @@ fn main() -> Result<()> {
         let record: Record = result?;
         let index = record.get("index").ok_or("Couldn't get index");
         println!("Testing index {}", index?);
-        let secp = Secp256k1::new();
-
         let message_str = record.get("message").ok_or("Couldn't get message");
         let message_hash = Sha256Hash::from_hex(message_str?)?;
         let message = Message::from_slice(&message_hash)?;
@@ -37,10 +35,10 @@ fn main() -> Result<()> {
             }
         };
 
-        let _verify_result = secp.verify_schnorr(&sig, &message, &pubkey);
+        let _verify_result = SECP256K1.verify_schnorr(&sig, &message, &pubkey);
         let secretkey_record = record.get("secret key").ok_or("Couldn't get secret key");
 
-        let _keypair = match KeyPair::from_seckey_str(&secp, secretkey_record?) {
+        let _keypair = match KeyPair::from_seckey_str(&SECP256K1, secretkey_record?) {
             Ok(keypair) => keypair,
             Err(e) => {
                 eprintln!("Invalid secret key: {}", e);
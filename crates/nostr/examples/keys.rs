@@ -12,7 +12,7 @@ fn main() -> Result<()> {
 
     println!("Public key: {}", public_key);
     println!("Public key bech32: {}", public_key.to_bech32()?);
-    println!("Secret key: {}", keys.secret_key_as_str()?);
+    println!("Secret key: {}", *keys.secret_key_as_zeroizing_str()?);
     println!("Secret key bech32: {}", secret_key.to_bech32()?);
 
     // Bech32 keys
@@ -77,6 +77,15 @@ fn main() -> Result<()> {
                 } => {
                     println!("Got OK message: {} - {} - {}", event_id, status, message);
                 }
+                RelayMessage::Closed {
+                    subscription_id,
+                    message,
+                } => {
+                    println!("Subscription {} closed: {}", subscription_id, message);
+                }
+                RelayMessage::Auth { challenge } => {
+                    println!("Got AUTH challenge: {}", challenge);
+                }
                 RelayMessage::Event {
                     event,
                     subscription_id: _,
@@ -14,7 +14,7 @@ fn main() -> Result<()> {
         "b2d670de53b27691c0c3400225b65c35a26d06093bcc41f48ffc71e0907f9d4a",
     )?;
 
-    if nip05::verify(public_key, "0xtr@oxtr.dev").is_ok() {
+    if nip05::verify(public_key, "0xtr@oxtr.dev", None).is_ok() {
         println!("NIP-05 verified");
     } else {
         println!("NIP-05 NOT verified");
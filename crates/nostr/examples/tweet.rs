@@ -32,10 +32,10 @@ fn main() -> Result<()> {
     let bob_keys = Keys::new(SecretKey::from_str(BOB_SK)?);
 
     let alice_says_hi = ClientMessage::new_event(
-        EventBuilder::new_text_note("hi from alice", &vec![]).to_event(&alice_keys)?,
+        EventBuilder::new_text_note("hi from alice", &[]).to_event(&alice_keys)?,
     );
     let bob_says_hi = ClientMessage::new_event(
-        EventBuilder::new_text_note("bob says hello", &vec![]).to_event(&bob_keys)?,
+        EventBuilder::new_text_note("bob says hello", &[]).to_event(&bob_keys)?,
     );
 
     let subscribe_to_alice = ClientMessage::new_req(
@@ -82,6 +82,15 @@ fn main() -> Result<()> {
                 } => {
                     println!("Got OK message: {} - {} - {}", event_id, status, message);
                 }
+                RelayMessage::Closed {
+                    subscription_id,
+                    message,
+                } => {
+                    println!("Subscription {} closed: {}", subscription_id, message);
+                }
+                RelayMessage::Auth { challenge } => {
+                    println!("Got AUTH challenge: {}", challenge);
+                }
                 RelayMessage::Empty => {
                     println!("Empty message");
                 }
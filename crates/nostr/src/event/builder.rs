@@ -1,10 +1,14 @@
 // Copyright (c) 2022 Yuki Kishimoto
 // Distributed under the MIT software license
 
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
 use std::time::Instant;
 
 use bitcoin::hashes::Hash;
-use bitcoin::secp256k1::{KeyPair, Message, Secp256k1, XOnlyPublicKey};
+use bitcoin::secp256k1::{KeyPair, Message, XOnlyPublicKey};
 use once_cell::sync::Lazy;
 use regex::Regex;
 use serde_json::{json, Value};
@@ -12,12 +16,30 @@ use url::Url;
 
 pub use super::kind::{Kind, KindBase};
 pub use super::tag::{Marker, Tag, TagData, TagKind};
-use super::Event;
+use super::{Event, UnsignedEvent};
 use crate::key::{self, Keys};
 use crate::metadata::Metadata;
 use crate::util::nips;
+use crate::util::nips::nip13::{self, PowOptions};
+#[cfg(feature = "nip04")]
+use crate::util::nips::nip15::CheckoutMessage;
+use crate::util::nips::nip15::{Product, Stall};
+use crate::util::nips::nip23::LongFormContent;
+use crate::util::nips::nip26::Delegation;
+use crate::util::nips::nip29::{GroupId, GroupMetadata};
+use crate::util::nips::nip34::{GitIssue, GitPatch, GitRepositoryAnnouncement};
+use crate::util::nips::nip38::UserStatus;
+use crate::util::nips::nip56::{Report, ReportTarget, ReportType};
+use crate::util::nips::nip57::{self, ZapRequestData};
+use crate::util::nips::nip58::{BadgeAward, BadgeDefinition, ProfileBadges};
+use crate::util::nips::nip65::{self, RelayMetadata};
+use crate::util::nips::nip66::{RelayDiscovery, RelayMonitor};
+use crate::util::nips::nip84::Highlight;
+use crate::util::nips::nip89::{HandlerInformation, HandlerRecommendation};
+use crate::util::nips::nip90::{self, JobAmount, JobFeedback, JobRequest, JobResult, JobStatus};
+use crate::util::nips::nip99::ClassifiedListing;
 use crate::util::time::timestamp;
-use crate::{Contact, Sha256Hash};
+use crate::{Contact, Sha256Hash, SECP256K1};
 
 static REGEX_NAME: Lazy<Regex> =
     Lazy::new(|| Regex::new(r#"^[a-zA-Z0-9][a-zA-Z_\-0-9]+[a-zA-Z0-9]$"#).expect("Invalid regex"));
@@ -32,10 +54,48 @@ pub enum Error {
     /// Invalid metadata name
     #[error("invalid name")]
     InvalidName,
+    /// Unsigned event error
+    #[error("unsigned event error: {0}")]
+    Unsigned(#[from] super::unsigned::Error),
     /// NIP04 error
     #[cfg(feature = "nip04")]
     #[error("nip04 error: {0}")]
     NIP04(#[from] nips::nip04::Error),
+    /// NIP44 error
+    #[cfg(feature = "nip44")]
+    #[error("nip44 error: {0}")]
+    NIP44(#[from] nips::nip44::Error),
+    /// NIP13 error
+    #[error("nip13 error: {0}")]
+    NIP13(#[from] nip13::Error),
+    /// NIP15 error
+    #[error("nip15 error: {0}")]
+    NIP15(#[from] nips::nip15::Error),
+    /// NIP58 error
+    #[error("nip58 error: {0}")]
+    NIP58(#[from] nips::nip58::Error),
+    /// NIP90 error
+    #[error("nip90 error: {0}")]
+    NIP90(#[from] nips::nip90::Error),
+    /// NIP89 error
+    #[error("nip89 error: {0}")]
+    NIP89(#[from] nips::nip89::Error),
+    /// NIP51 error
+    #[cfg(feature = "nip51")]
+    #[error("nip51 error: {0}")]
+    NIP51(#[from] nips::nip51::Error),
+}
+
+/// Encryption scheme of direct messages
+#[cfg(any(feature = "nip04", feature = "nip44"))]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum EncryptionScheme {
+    /// NIP-04 (AES-256-CBC)
+    #[cfg(feature = "nip04")]
+    NIP04,
+    /// NIP-44 (versioned ChaCha20 + HMAC-SHA256 with padding)
+    #[cfg(feature = "nip44")]
+    NIP44,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -43,6 +103,7 @@ pub struct EventBuilder {
     kind: Kind,
     tags: Vec<Tag>,
     content: String,
+    created_at: Option<u64>,
 }
 
 impl EventBuilder {
@@ -54,6 +115,7 @@ impl EventBuilder {
             kind,
             tags: tags.to_vec(),
             content: content.into(),
+            created_at: None,
         }
     }
 
@@ -69,12 +131,89 @@ impl EventBuilder {
         Sha256Hash::hash(event_str.as_bytes())
     }
 
+    /// Add NIP-26 `delegation` tag
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/26.md>
+    pub fn delegation(mut self, delegation: &Delegation) -> Self {
+        self.tags.push(delegation.tag());
+        self
+    }
+
+    /// Add NIP-36 `content-warning` tag, with an optional reason
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/36.md>
+    pub fn content_warning<S>(mut self, reason: Option<S>) -> Self
+    where
+        S: Into<String>,
+    {
+        self.tags.push(Tag::new(TagData::ContentWarning {
+            reason: reason.map(|r| r.into()),
+        }));
+        self
+    }
+
+    /// Add NIP-40 expiration unix timestamp
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/40.md>
+    pub fn expiration(mut self, timestamp: u64) -> Self {
+        self.tags.push(Tag::new(TagData::Expiration(timestamp)));
+        self
+    }
+
+    /// Add NIP-30 custom emoji, referenced as `:shortcode:` in the content
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/30.md>
+    pub fn emoji<S>(mut self, shortcode: S, url: Url) -> Self
+    where
+        S: Into<String>,
+    {
+        self.tags.push(Tag::new(TagData::Emoji {
+            shortcode: shortcode.into(),
+            url,
+        }));
+        self
+    }
+
+    /// Add NIP-14 subject
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/14.md>
+    pub fn subject<S>(mut self, subject: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.tags.push(Tag::new(TagData::Subject(subject.into())));
+        self
+    }
+
+    /// Add NIP-33 identifier (`d` tag) of a parameterized replaceable event
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/33.md>
+    pub fn identifier<S>(mut self, identifier: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.tags
+            .push(Tag::new(TagData::Identifier(identifier.into())));
+        self
+    }
+
+    /// Set a custom `created_at` unix timestamp, instead of the current time
+    pub fn custom_created_at(self, timestamp: u64) -> Self {
+        Self {
+            created_at: Some(timestamp),
+            ..self
+        }
+    }
+
+    fn created_at(&self) -> u64 {
+        self.created_at.unwrap_or_else(timestamp)
+    }
+
     /// Build `Event`
     pub fn to_event(self, keys: &Keys) -> Result<Event, Error> {
-        let secp = Secp256k1::new();
         let keypair: &KeyPair = &keys.key_pair()?;
         let pubkey: XOnlyPublicKey = keys.public_key();
-        let created_at: u64 = timestamp();
+        let created_at: u64 = self.created_at();
 
         let id: Sha256Hash =
             Self::gen_id(&pubkey, created_at, &self.kind, &self.tags, &self.content);
@@ -87,53 +226,139 @@ impl EventBuilder {
             kind: self.kind,
             tags: self.tags,
             content: self.content,
-            sig: secp.sign_schnorr(&message, keypair),
+            sig: SECP256K1.sign_schnorr(&message, keypair),
         })
     }
 
     /// Build POW `Event`
     pub fn to_pow_event(self, keys: &Keys, difficulty: u8) -> Result<Event, Error> {
-        let mut nonce: u128 = 0;
-        let mut tags: Vec<Tag> = self.tags;
+        Ok(self
+            .to_unsigned_pow_event(keys.public_key(), difficulty)
+            .sign(keys)?)
+    }
+
+    /// Build POW `Event`, with custom mining options
+    ///
+    /// Return [`nip13::Error::Cancelled`] if the cancellation token is triggered.
+    pub fn to_pow_event_with_options(
+        self,
+        keys: &Keys,
+        difficulty: u8,
+        options: &PowOptions,
+    ) -> Result<Event, Error> {
+        Ok(self
+            .to_unsigned_pow_event_with_options(keys.public_key(), difficulty, options)?
+            .sign(keys)?)
+    }
+
+    /// Build `UnsignedEvent`, to be signed by the owner of `pubkey`
+    pub fn to_unsigned_event(self, pubkey: XOnlyPublicKey) -> UnsignedEvent {
+        let created_at: u64 = self.created_at();
+        let id: Sha256Hash =
+            Self::gen_id(&pubkey, created_at, &self.kind, &self.tags, &self.content);
 
-        let pubkey = keys.public_key();
+        UnsignedEvent {
+            id,
+            pubkey,
+            created_at,
+            kind: self.kind,
+            tags: self.tags,
+            content: self.content,
+        }
+    }
+
+    /// Build POW `UnsignedEvent`, to be signed by the owner of `pubkey`
+    ///
+    /// The nonce space is split across all the available cores.
+    pub fn to_unsigned_pow_event(self, pubkey: XOnlyPublicKey, difficulty: u8) -> UnsignedEvent {
+        self.mine(pubkey, difficulty, &PowOptions::default())
+            .expect("POW mining without cancellation token")
+    }
+
+    /// Build POW `UnsignedEvent`, to be signed by the owner of `pubkey`, with custom mining options
+    ///
+    /// Return [`nip13::Error::Cancelled`] if the cancellation token is triggered.
+    pub fn to_unsigned_pow_event_with_options(
+        self,
+        pubkey: XOnlyPublicKey,
+        difficulty: u8,
+        options: &PowOptions,
+    ) -> Result<UnsignedEvent, Error> {
+        Ok(self
+            .mine(pubkey, difficulty, options)
+            .ok_or(nip13::Error::Cancelled)?)
+    }
+
+    /// Mine the event, splitting the nonce space across the worker threads
+    ///
+    /// Return `None` if cancelled.
+    fn mine(
+        &self,
+        pubkey: XOnlyPublicKey,
+        difficulty: u8,
+        options: &PowOptions,
+    ) -> Option<UnsignedEvent> {
+        let threads: usize = options.get_threads();
+        let found = AtomicBool::new(false);
+        let hashes = AtomicU64::new(0);
+        let result: Mutex<Option<UnsignedEvent>> = Mutex::new(None);
 
         let now = Instant::now();
 
-        loop {
-            nonce += 1;
-
-            tags.push(Tag::new(TagData::POW { nonce, difficulty }));
-
-            let created_at: u64 = timestamp();
-            let id: Sha256Hash =
-                Self::gen_id(&pubkey, created_at, &self.kind, &tags, &self.content);
-
-            if nips::nip13::get_leading_zero_bits(id) >= difficulty {
-                log::debug!(
-                    "{} iterations in {} ms. Avg rate {} hashes/second",
-                    nonce,
-                    now.elapsed().as_millis(),
-                    nonce * 1000 / std::cmp::max(1, now.elapsed().as_millis())
-                );
-
-                let secp = Secp256k1::new();
-                let keypair: &KeyPair = &keys.key_pair()?;
-                let message = Message::from_slice(&id)?;
-
-                return Ok(Event {
-                    id,
-                    pubkey,
-                    created_at,
-                    kind: self.kind,
-                    tags,
-                    content: self.content,
-                    sig: secp.sign_schnorr(&message, keypair),
+        thread::scope(|s| {
+            for worker in 0..threads {
+                let (found, hashes, result) = (&found, &hashes, &result);
+                s.spawn(move || {
+                    let mut nonce: u128 = worker as u128;
+                    let mut tags: Vec<Tag> = self.tags.clone();
+
+                    while !found.load(Ordering::Relaxed) && !options.is_cancelled() {
+                        nonce += 1;
+
+                        tags.push(Tag::new(TagData::POW { nonce, difficulty }));
+
+                        let created_at: u64 = self.created_at();
+                        let id: Sha256Hash =
+                            Self::gen_id(&pubkey, created_at, &self.kind, &tags, &self.content);
+
+                        if nip13::get_leading_zero_bits(id) >= difficulty {
+                            if !found.swap(true, Ordering::SeqCst) {
+                                if let Ok(mut result) = result.lock() {
+                                    *result = Some(UnsignedEvent {
+                                        id,
+                                        pubkey,
+                                        created_at,
+                                        kind: self.kind,
+                                        tags,
+                                        content: self.content.clone(),
+                                    });
+                                }
+                            }
+                            return;
+                        }
+
+                        tags.pop();
+                        nonce += threads as u128 - 1;
+
+                        let total: u64 = hashes.fetch_add(1, Ordering::Relaxed) + 1;
+                        if total % nip13::PROGRESS_INTERVAL == 0 {
+                            options.report_progress(total);
+                        }
+                    }
                 });
             }
+        });
 
-            tags.pop();
-        }
+        let hashes: u64 = hashes.into_inner();
+        log::debug!(
+            "{} iterations in {} ms with {} threads. Avg rate {} hashes/second",
+            hashes,
+            now.elapsed().as_millis(),
+            threads,
+            hashes as u128 * 1000 / std::cmp::max(1, now.elapsed().as_millis())
+        );
+
+        result.into_inner().ok().flatten()
     }
 }
 
@@ -158,36 +383,17 @@ impl EventBuilder {
     /// let builder = EventBuilder::set_metadata(metadata).unwrap();
     /// ```
     pub fn set_metadata(metadata: Metadata) -> Result<Self, Error> {
-        let name = metadata.name;
-        let display_name = metadata.display_name;
-        let about = metadata.about;
-        let picture = metadata.picture;
-        let nip05_str = metadata.nip05;
-
-        if let Some(name) = name.clone() {
-            if !REGEX_NAME.is_match(&name) {
+        if let Some(name) = metadata.name.as_ref() {
+            if !REGEX_NAME.is_match(name) {
                 return Err(Error::InvalidName);
             }
         }
 
-        let mut metadata: Value = json!({
-            "name": name.unwrap_or_default(),
-            "display_name": display_name.unwrap_or_default(),
-            "about": about.unwrap_or_default(),
-        });
-
-        if let Some(picture) = picture {
-            metadata["picture"] = json!(picture);
-        }
-
-        if let Some(nip05_str) = nip05_str {
-            metadata["nip05"] = json!(nip05_str);
-        }
-
+        let tags: Vec<Tag> = metadata.identities.iter().map(|i| i.tag()).collect();
         Ok(Self::new(
             Kind::Base(KindBase::Metadata),
-            metadata.to_string(),
-            &[],
+            json!(metadata).to_string(),
+            &tags,
         ))
     }
 
@@ -213,6 +419,161 @@ impl EventBuilder {
         Self::new(Kind::Base(KindBase::TextNote), content, tags)
     }
 
+    /// Reply to an event
+    ///
+    /// The root is taken from `reply_to` if not provided. The `e` tags are marked with
+    /// `root`/`reply` and the authors of `reply_to` (`p` tags) are tagged.
+    /// The NIP-14 subject of `reply_to` is kept, prefixed with `Re:`.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/10.md>
+    pub fn new_reply<S>(
+        content: S,
+        reply_to: &Event,
+        root: Option<&Event>,
+        relay_url: Option<Url>,
+    ) -> Self
+    where
+        S: Into<String>,
+    {
+        let e_tag = |id: Sha256Hash, marker: Marker| {
+            Tag::from(vec![
+                TagKind::E.to_string(),
+                id.to_string(),
+                relay_url
+                    .as_ref()
+                    .map(|u| u.to_string())
+                    .unwrap_or_default(),
+                marker.to_string(),
+            ])
+        };
+
+        let root_id: Sha256Hash = root
+            .map(|r| r.id)
+            .or_else(|| reply_to.root_id())
+            .unwrap_or(reply_to.id);
+
+        let mut tags: Vec<Tag> = vec![e_tag(root_id, Marker::Root)];
+        if root_id != reply_to.id {
+            tags.push(e_tag(reply_to.id, Marker::Reply));
+        }
+
+        let mut pubkeys: Vec<XOnlyPublicKey> = vec![reply_to.pubkey];
+        for tag in reply_to.tags.iter() {
+            if let (Ok(TagKind::P), Some(pk)) = (tag.kind(), tag.content()) {
+                if let Ok(pk) = XOnlyPublicKey::from_str(pk) {
+                    if !pubkeys.contains(&pk) {
+                        pubkeys.push(pk);
+                    }
+                }
+            }
+        }
+        tags.extend(pubkeys.into_iter().map(|pk| Tag::new(TagData::PubKey(pk))));
+
+        if let Some(subject) = reply_to.subject() {
+            let subject = if subject.starts_with("Re:") {
+                subject.to_string()
+            } else {
+                format!("Re: {}", subject)
+            };
+            tags.push(Tag::new(TagData::Subject(subject)));
+        }
+
+        Self::new(Kind::Base(KindBase::TextNote), content, &tags)
+    }
+
+    /// Long-form content (markdown)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/23.md>
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr::util::nips::nip23::LongFormContent;
+    /// use nostr::EventBuilder;
+    ///
+    /// let metadata = LongFormContent::new("my-article")
+    ///     .title("My article")
+    ///     .summary("About nostr");
+    /// let builder = EventBuilder::long_form_text_note("# My article", &metadata);
+    /// ```
+    pub fn long_form_text_note<S>(content: S, metadata: &LongFormContent) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::new(
+            Kind::Base(KindBase::LongFormTextNote),
+            content,
+            &metadata.tags(),
+        )
+    }
+
+    /// Long-form content draft
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/23.md>
+    pub fn long_form_text_note_draft<S>(content: S, metadata: &LongFormContent) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::new(
+            Kind::Base(KindBase::LongFormTextNoteDraft),
+            content,
+            &metadata.tags(),
+        )
+    }
+
+    /// Classified listing (markdown content)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/99.md>
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr::util::nips::nip99::{ClassifiedListing, Price};
+    /// use nostr::EventBuilder;
+    ///
+    /// let listing = ClassifiedListing::new("my-bike")
+    ///     .title("Road bike")
+    ///     .location("Berlin")
+    ///     .price(Price::new("300", "EUR", None));
+    /// let builder = EventBuilder::classified_listing("Used, good condition", &listing);
+    /// ```
+    pub fn classified_listing<S>(content: S, listing: &ClassifiedListing) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::new(
+            Kind::Base(KindBase::ClassifiedListing),
+            content,
+            &listing.tags(),
+        )
+    }
+
+    /// List (mute, pin, bookmarks or people), with private items encrypted to self
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/51.md>
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr::util::nips::nip51::{List, ListItem, ListKind};
+    /// use nostr::{EventBuilder, Keys};
+    ///
+    /// let my_keys = Keys::generate_from_os_random();
+    /// let spammer = Keys::generate_from_os_random();
+    ///
+    /// let mut list = List::new(ListKind::Mute);
+    /// list.add_private(ListItem::PubKey(spammer.public_key()));
+    /// let event = EventBuilder::list(&my_keys, &list)
+    ///     .unwrap()
+    ///     .to_event(&my_keys)
+    ///     .unwrap();
+    /// ```
+    #[cfg(feature = "nip51")]
+    pub fn list(keys: &Keys, list: &nips::nip51::List) -> Result<Self, Error> {
+        Ok(Self::new(
+            list.kind.kind(),
+            list.encrypted_content(keys)?,
+            &list.tags(),
+        ))
+    }
+
     /// Set contact list
     pub fn set_contact_list(list: Vec<Contact>) -> Self {
         let tags: Vec<Tag> = list
@@ -239,11 +600,56 @@ impl EventBuilder {
     where
         S: Into<String>,
     {
-        let msg = nips::nip04::encrypt(
-            &sender_keys.secret_key()?,
-            &receiver_keys.public_key(),
-            content.into(),
-        )?;
+        Self::new_encrypted_direct_msg_with_scheme(
+            sender_keys,
+            receiver_keys,
+            content,
+            EncryptionScheme::NIP04,
+        )
+    }
+
+    /// Create encrypted direct msg event, choosing the encryption scheme
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr::event::builder::EncryptionScheme;
+    /// use nostr::{EventBuilder, Keys};
+    ///
+    /// let my_keys = Keys::generate_from_os_random();
+    /// let receiver_keys = Keys::generate_from_os_random();
+    ///
+    /// let event = EventBuilder::new_encrypted_direct_msg_with_scheme(
+    ///     &my_keys,
+    ///     &receiver_keys,
+    ///     "Hello",
+    ///     EncryptionScheme::NIP44,
+    /// )
+    /// .unwrap()
+    /// .to_event(&my_keys)
+    /// .unwrap();
+    /// ```
+    #[cfg(any(feature = "nip04", feature = "nip44"))]
+    pub fn new_encrypted_direct_msg_with_scheme<S>(
+        sender_keys: &Keys,
+        receiver_keys: &Keys,
+        content: S,
+        scheme: EncryptionScheme,
+    ) -> Result<Self, Error>
+    where
+        S: Into<String>,
+    {
+        let secret_key = sender_keys.secret_key()?;
+        let public_key = receiver_keys.public_key();
+        let msg = match scheme {
+            #[cfg(feature = "nip04")]
+            EncryptionScheme::NIP04 => {
+                nips::nip04::encrypt(&secret_key, &public_key, content.into())?
+            }
+            #[cfg(feature = "nip44")]
+            EncryptionScheme::NIP44 => {
+                nips::nip44::encrypt(&secret_key, &public_key, content.into())?
+            }
+        };
 
         Ok(Self::new(
             Kind::Base(KindBase::EncryptedDirectMessage),
@@ -252,6 +658,55 @@ impl EventBuilder {
         ))
     }
 
+    /// Private direct message (kind 14), to be sent as rumor of a NIP-59 gift wrap
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/17.md>
+    pub fn private_msg_rumor<S>(receiver: XOnlyPublicKey, message: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::new(
+            Kind::Base(KindBase::PrivateDirectMessage),
+            message,
+            &[Tag::new(TagData::PubKey(receiver))],
+        )
+    }
+
+    /// Nostr Connect request or response, NIP-04 encrypted for `receiver`
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/46.md>
+    #[cfg(feature = "nip46")]
+    pub fn nostr_connect(
+        sender_keys: &Keys,
+        receiver: XOnlyPublicKey,
+        msg: &nips::nip46::Message,
+    ) -> Result<Self, Error> {
+        let content = nips::nip04::encrypt(&sender_keys.secret_key()?, &receiver, msg.as_json())?;
+
+        Ok(Self::new(
+            Kind::Base(KindBase::NostrConnect),
+            content,
+            &[Tag::new(TagData::PubKey(receiver))],
+        ))
+    }
+
+    /// Nostr Wallet Connect request, signed with the keys of the `uri` secret
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/47.md>
+    #[cfg(feature = "nip47")]
+    pub fn nostr_wallet_connect_request(
+        uri: &nips::nip47::NostrWalletConnectURI,
+        req: &nips::nip47::Request,
+    ) -> Result<Self, Error> {
+        let content = nips::nip04::encrypt(&uri.secret, &uri.public_key, req.as_json())?;
+
+        Ok(Self::new(
+            Kind::Base(KindBase::WalletConnectRequest),
+            content,
+            &[Tag::new(TagData::PubKey(uri.public_key))],
+        ))
+    }
+
     /// Create delete event
     pub fn delete<S>(ids: Vec<Sha256Hash>, reason: Option<S>) -> Self
     where
@@ -284,6 +739,57 @@ impl EventBuilder {
         Self::new(Kind::Base(KindBase::Reaction), content, tags)
     }
 
+    /// Custom emoji reaction
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/30.md>
+    pub fn new_emoji_reaction<S>(event: &Event, shortcode: S, url: Url) -> Self
+    where
+        S: Into<String>,
+    {
+        let shortcode: String = shortcode.into();
+        let tags: &[Tag] = &[
+            Tag::new(TagData::EventId(event.id)),
+            Tag::new(TagData::PubKey(event.pubkey)),
+        ];
+
+        Self::new(
+            Kind::Base(KindBase::Reaction),
+            format!(":{}:", shortcode),
+            tags,
+        )
+        .emoji(shortcode, url)
+    }
+
+    /// Repost event
+    ///
+    /// Text notes are reposted with kind 6, other events with a generic repost (kind 16).
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/18.md>
+    pub fn repost(event: &Event, relay_url: Option<Url>) -> Self {
+        let mut tags: Vec<Tag> = vec![
+            match relay_url {
+                Some(relay_url) => Tag::new(TagData::Nip10E(event.id, relay_url, None)),
+                None => Tag::new(TagData::EventId(event.id)),
+            },
+            Tag::new(TagData::PubKey(event.pubkey)),
+        ];
+
+        let kind = if event.kind == Kind::Base(KindBase::TextNote) {
+            Kind::Base(KindBase::Boost)
+        } else {
+            if let Some(coordinate) = event.coordinate() {
+                tags.push(Tag::new(TagData::Coordinate(coordinate)));
+            }
+            tags.push(Tag::from(vec![
+                String::from("k"),
+                u64::from(event.kind).to_string(),
+            ]));
+            Kind::Base(KindBase::GenericRepost)
+        };
+
+        Self::new(kind, event.as_json().unwrap_or_default(), &tags)
+    }
+
     /// Create new channel
     ///
     /// <https://github.com/nostr-protocol/nips/blob/master/28.md>
@@ -374,6 +880,32 @@ impl EventBuilder {
         )
     }
 
+    /// Reply to a channel message
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/28.md>
+    pub fn new_channel_msg_reply<S>(
+        channel_id: Sha256Hash, // event id of kind 40
+        relay_url: Url,
+        reply_to: Sha256Hash, // event id of kind 42
+        content: S,
+    ) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::new(
+            Kind::Base(KindBase::ChannelMessage),
+            content,
+            &[
+                Tag::new(TagData::Nip10E(
+                    channel_id,
+                    relay_url.clone(),
+                    Some(Marker::Root),
+                )),
+                Tag::new(TagData::Nip10E(reply_to, relay_url, Some(Marker::Reply))),
+            ],
+        )
+    }
+
     /// Hide message
     ///
     /// <https://github.com/nostr-protocol/nips/blob/master/28.md>
@@ -390,7 +922,7 @@ impl EventBuilder {
 
         Self::new(
             Kind::Base(KindBase::ChannelHideMessage),
-            &content.to_string(),
+            content.to_string(),
             &[Tag::new(TagData::EventId(message_id))],
         )
     }
@@ -408,8 +940,506 @@ impl EventBuilder {
 
         Self::new(
             Kind::Base(KindBase::ChannelMuteUser),
-            &content.to_string(),
+            content.to_string(),
             &[Tag::new(TagData::PubKey(pubkey))],
         )
     }
+
+    /// New group chat message
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/29.md>
+    pub fn new_group_chat_msg<S>(group_id: &GroupId, content: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::new(
+            Kind::Base(KindBase::GroupChatMessage),
+            content,
+            &[Tag::new(TagData::Group(group_id.id.clone()))],
+        )
+    }
+
+    /// Request to join a group
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/29.md>
+    pub fn group_join_request<S>(group_id: &GroupId, reason: Option<S>) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::new(
+            Kind::Base(KindBase::GroupJoinRequest),
+            reason.map(|s| s.into()).unwrap_or_default(),
+            &[Tag::new(TagData::Group(group_id.id.clone()))],
+        )
+    }
+
+    /// Request to leave a group
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/29.md>
+    pub fn group_leave_request<S>(group_id: &GroupId, reason: Option<S>) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::new(
+            Kind::Base(KindBase::GroupLeaveRequest),
+            reason.map(|s| s.into()).unwrap_or_default(),
+            &[Tag::new(TagData::Group(group_id.id.clone()))],
+        )
+    }
+
+    /// Add user to a group (admin action)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/29.md>
+    pub fn group_put_user(group_id: &GroupId, pubkey: XOnlyPublicKey) -> Self {
+        Self::new(
+            Kind::Base(KindBase::GroupPutUser),
+            "",
+            &[
+                Tag::new(TagData::Group(group_id.id.clone())),
+                Tag::new(TagData::PubKey(pubkey)),
+            ],
+        )
+    }
+
+    /// Remove user from a group (admin action)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/29.md>
+    pub fn group_remove_user(group_id: &GroupId, pubkey: XOnlyPublicKey) -> Self {
+        Self::new(
+            Kind::Base(KindBase::GroupRemoveUser),
+            "",
+            &[
+                Tag::new(TagData::Group(group_id.id.clone())),
+                Tag::new(TagData::PubKey(pubkey)),
+            ],
+        )
+    }
+
+    /// Edit group metadata (admin action)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/29.md>
+    pub fn group_edit_metadata(group_id: &GroupId, metadata: &GroupMetadata) -> Self {
+        let mut tags = vec![Tag::new(TagData::Group(group_id.id.clone()))];
+        tags.extend(metadata.tags());
+        Self::new(Kind::Base(KindBase::GroupEditMetadata), "", &tags)
+    }
+
+    /// Delete event from a group (admin action)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/29.md>
+    pub fn group_delete_event(group_id: &GroupId, event_id: Sha256Hash) -> Self {
+        Self::new(
+            Kind::Base(KindBase::GroupDeleteEvent),
+            "",
+            &[
+                Tag::new(TagData::Group(group_id.id.clone())),
+                Tag::new(TagData::EventId(event_id)),
+            ],
+        )
+    }
+
+    /// Report a public key or an event
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/56.md>
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr::util::nips::nip56::{ReportTarget, ReportType};
+    /// use nostr::{EventBuilder, Keys};
+    ///
+    /// let spammer = Keys::generate_from_os_random();
+    /// let builder = EventBuilder::report(
+    ///     ReportTarget::PubKey(spammer.public_key()),
+    ///     ReportType::Spam,
+    ///     "Sending the same reply everywhere",
+    /// );
+    /// ```
+    pub fn report<S>(target: ReportTarget, report_type: ReportType, reason: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::new(
+            Kind::Base(KindBase::Reporting),
+            reason,
+            &Report::tags(&target, report_type),
+        )
+    }
+
+    /// Zap request (not published, sent to the recipient lnurl server)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/57.md>
+    pub fn new_zap_request<S>(data: ZapRequestData, message: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::new(Kind::Base(KindBase::ZapRequest), message, &data.tags())
+    }
+
+    /// Zap receipt (published by the recipient lnurl server once the invoice is paid)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/57.md>
+    pub fn new_zap_receipt<S>(bolt11: S, preimage: Option<String>, zap_request: &Event) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::new(
+            Kind::Base(KindBase::ZapReceipt),
+            "",
+            &nip57::zap_receipt_tags(bolt11.into(), preimage, zap_request),
+        )
+    }
+
+    /// HTTP auth event, to be sent in the `Authorization` header
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/98.md>
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr::url::Url;
+    /// use nostr::util::nips::nip98::{self, HttpData, HttpMethod};
+    /// use nostr::{EventBuilder, Keys};
+    ///
+    /// let my_keys = Keys::generate_from_os_random();
+    /// let url = Url::parse("https://api.example.com/items").unwrap();
+    /// let event = EventBuilder::http_auth(&HttpData::new(url, HttpMethod::GET))
+    ///     .to_event(&my_keys)
+    ///     .unwrap();
+    /// let header = nip98::authorization_header(&event).unwrap();
+    /// ```
+    #[cfg(feature = "nip98")]
+    pub fn http_auth(data: &nips::nip98::HttpData) -> Self {
+        Self::new(Kind::Base(KindBase::HttpAuth), "", &data.tags())
+    }
+
+    /// Badge definition
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/58.md>
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr::util::nips::nip58::{BadgeDefinition, BadgeImage};
+    /// use nostr::EventBuilder;
+    ///
+    /// let badge = BadgeDefinition::new("bravery")
+    ///     .name("Medal of Bravery")
+    ///     .image(BadgeImage::new("https://example.com/bravery.png", None));
+    /// let builder = EventBuilder::define_badge(&badge);
+    /// ```
+    pub fn define_badge(badge: &BadgeDefinition) -> Self {
+        Self::new(Kind::Base(KindBase::BadgeDefinition), "", &badge.tags())
+    }
+
+    /// Award the badge of a kind 30009 badge definition event
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/58.md>
+    pub fn award_badge(
+        badge_definition: &Event,
+        awarded: &[XOnlyPublicKey],
+    ) -> Result<Self, Error> {
+        Ok(Self::new(
+            Kind::Base(KindBase::BadgeAward),
+            "",
+            &BadgeAward::tags(badge_definition, awarded)?,
+        ))
+    }
+
+    /// Profile badges, as pairs of badge definition and badge award events
+    ///
+    /// Every award must be issued by the author of the badge definition to `public_key`.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/58.md>
+    pub fn profile_badges(
+        badges: &[(Event, Event)],
+        public_key: &XOnlyPublicKey,
+    ) -> Result<Self, Error> {
+        Ok(Self::new(
+            Kind::Base(KindBase::ProfileBadges),
+            "",
+            &ProfileBadges::tags(badges, public_key)?,
+        ))
+    }
+
+    /// Handler information: the kinds handled by an application
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/89.md>
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr::util::nips::nip89::{HandlerInformation, HandlerPlatform};
+    /// use nostr::{EventBuilder, Kind, KindBase};
+    ///
+    /// let info = HandlerInformation::new("my-reader")
+    ///     .kind(Kind::Base(KindBase::LongFormTextNote))
+    ///     .platform(HandlerPlatform::new(
+    ///         "web",
+    ///         "https://reader.example.com/a/<bech32>",
+    ///         Some("naddr"),
+    ///     ));
+    /// let builder = EventBuilder::handler_information(&info).unwrap();
+    /// ```
+    pub fn handler_information(info: &HandlerInformation) -> Result<Self, Error> {
+        Ok(Self::new(
+            Kind::Base(KindBase::HandlerInformation),
+            info.content()?,
+            &info.tags(),
+        ))
+    }
+
+    /// Handler recommendation: the handlers recommended for a kind
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/89.md>
+    pub fn handler_recommendation(recommendation: &HandlerRecommendation) -> Self {
+        Self::new(
+            Kind::Base(KindBase::HandlerRecommendation),
+            "",
+            &recommendation.tags(),
+        )
+    }
+
+    /// Marketplace stall
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/15.md>
+    pub fn new_stall(stall: &Stall) -> Result<Self, Error> {
+        Ok(Self::new(
+            Kind::Base(KindBase::Stall),
+            stall.as_json()?,
+            &stall.tags(),
+        ))
+    }
+
+    /// Marketplace product
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/15.md>
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr::util::nips::nip15::Product;
+    /// use nostr::EventBuilder;
+    ///
+    /// let product = Product::new("product-1", "stall-1", "T-shirt", "EUR", 20.0)
+    ///     .image("https://example.com/t-shirt.png")
+    ///     .quantity(5)
+    ///     .category("clothing");
+    /// let builder = EventBuilder::new_product(&product).unwrap();
+    /// ```
+    pub fn new_product(product: &Product) -> Result<Self, Error> {
+        Ok(Self::new(
+            Kind::Base(KindBase::Product),
+            product.as_json()?,
+            &product.tags(),
+        ))
+    }
+
+    /// Marketplace checkout message (order, payment request or order status), sent as
+    /// NIP-04 encrypted direct message
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/15.md>
+    #[cfg(feature = "nip04")]
+    pub fn new_checkout_msg(
+        sender_keys: &Keys,
+        receiver_keys: &Keys,
+        msg: &CheckoutMessage,
+    ) -> Result<Self, Error> {
+        Self::new_encrypted_direct_msg(sender_keys, receiver_keys, msg.as_json()?)
+    }
+
+    /// User status
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/38.md>
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr::url::Url;
+    /// use nostr::util::nips::nip38::{StatusLink, StatusType, UserStatus};
+    /// use nostr::EventBuilder;
+    ///
+    /// let status = UserStatus::new(StatusType::General, "Working")
+    ///     .link(StatusLink::Url(Url::parse("https://example.com").unwrap()));
+    /// let builder = EventBuilder::user_status(&status);
+    /// ```
+    pub fn user_status(status: &UserStatus) -> Self {
+        Self::new(
+            Kind::Base(KindBase::UserStatus),
+            &status.content,
+            &status.tags(),
+        )
+    }
+
+    /// Git repository announcement
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/34.md>
+    pub fn git_repository_announcement(repository: &GitRepositoryAnnouncement) -> Self {
+        Self::new(
+            Kind::Base(KindBase::GitRepositoryAnnouncement),
+            "",
+            &repository.tags(),
+        )
+    }
+
+    /// Git patch
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/34.md>
+    pub fn git_patch(patch: &GitPatch) -> Self {
+        Self::new(
+            Kind::Base(KindBase::GitPatch),
+            &patch.content,
+            &patch.tags(),
+        )
+    }
+
+    /// Git issue
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/34.md>
+    pub fn git_issue(issue: &GitIssue) -> Self {
+        Self::new(
+            Kind::Base(KindBase::GitIssue),
+            &issue.content,
+            &issue.tags(),
+        )
+    }
+
+    /// Relay list metadata
+    ///
+    /// A relay without [`RelayMetadata`] is used both for reading and writing.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/65.md>
+    pub fn relay_list(list: &[(Url, Option<RelayMetadata>)]) -> Self {
+        Self::new(
+            Kind::Base(KindBase::RelayList),
+            "",
+            &nip65::relay_list_tags(list),
+        )
+    }
+
+    /// Relay discovery
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/66.md>
+    pub fn relay_discovery(discovery: &RelayDiscovery) -> Self {
+        Self::new(
+            Kind::Base(KindBase::RelayDiscovery),
+            discovery.info.clone().unwrap_or_default(),
+            &discovery.tags(),
+        )
+    }
+
+    /// Relay monitor announcement
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/66.md>
+    pub fn relay_monitor_announcement(monitor: &RelayMonitor) -> Self {
+        Self::new(
+            Kind::Base(KindBase::RelayMonitorAnnouncement),
+            "",
+            &monitor.tags(),
+        )
+    }
+
+    /// Highlight
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/84.md>
+    pub fn highlight(highlight: &Highlight) -> Self {
+        Self::new(
+            Kind::Base(KindBase::Highlight),
+            &highlight.content,
+            &highlight.tags(),
+        )
+    }
+
+    /// OpenTimestamps attestation of an event (`ots` is the proof file)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/03.md>
+    #[cfg(feature = "nip03")]
+    pub fn opentimestamps(event_id: Sha256Hash, relay_url: Option<Url>, ots: &[u8]) -> Self {
+        let mut tag = vec![TagKind::E.to_string(), event_id.to_string()];
+        if let Some(relay_url) = relay_url {
+            tag.push(relay_url.to_string());
+        }
+        let tags: &[Tag] = &[
+            Tag::from(tag),
+            Tag::from(vec![
+                String::from("alt"),
+                String::from("opentimestamps attestation"),
+            ]),
+        ];
+
+        Self::new(
+            Kind::Base(KindBase::OpenTimestamps),
+            base64::encode(ots),
+            tags,
+        )
+    }
+
+    /// Job request
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/90.md>
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr::util::nips::nip90::{JobInput, JobInputType, JobRequest};
+    /// use nostr::{EventBuilder, Kind};
+    ///
+    /// let job = JobRequest::new(Kind::Custom(5002))
+    ///     .unwrap()
+    ///     .input(JobInput::new("Hello world", JobInputType::Text))
+    ///     .param("language", "es")
+    ///     .bid(1000);
+    /// let builder = EventBuilder::job_request(&job);
+    /// ```
+    pub fn job_request(job: &JobRequest) -> Self {
+        Self::new(job.kind, "", &job.tags())
+    }
+
+    /// Job result, published by the service provider
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/90.md>
+    pub fn job_result<S>(
+        request: &Event,
+        payload: S,
+        amount: Option<JobAmount>,
+    ) -> Result<Self, Error>
+    where
+        S: Into<String>,
+    {
+        if !request.kind.is_job_request() {
+            return Err(Error::NIP90(nip90::Error::InvalidKind));
+        }
+
+        Ok(Self::new(
+            Kind::from(u64::from(request.kind) + 1000),
+            payload,
+            &JobResult::tags(request, amount)?,
+        ))
+    }
+
+    /// Job feedback, published by the service provider
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/90.md>
+    pub fn job_feedback(
+        request: &Event,
+        status: JobStatus,
+        extra_info: Option<String>,
+        amount: Option<JobAmount>,
+    ) -> Self {
+        Self::new(
+            Kind::Base(KindBase::JobFeedback),
+            "",
+            &JobFeedback::tags(request, status, extra_info, amount),
+        )
+    }
+
+    /// Authentication of clients to relays
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/42.md>
+    pub fn auth<S>(challenge: S, relay_url: Url) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::new(
+            Kind::Base(KindBase::Authentication),
+            "",
+            &[
+                Tag::new(TagData::Challenge(challenge.into())),
+                Tag::new(TagData::Relay(relay_url)),
+            ],
+        )
+    }
 }
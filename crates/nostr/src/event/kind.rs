@@ -3,8 +3,10 @@
 
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
+use crate::util::nips::nip90::{JOB_REQUEST_RANGE, JOB_RESULT_RANGE};
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Serialize_repr, Deserialize_repr)]
-#[repr(u8)]
+#[repr(u64)]
 pub enum KindBase {
     Metadata = 0,
     TextNote = 1,
@@ -14,11 +16,99 @@ pub enum KindBase {
     EventDeletion = 5,
     Boost = 6,
     Reaction = 7,
+    /// Badge award (NIP-58)
+    BadgeAward = 8,
+    GroupChatMessage = 9,
+    GroupThread = 11,
+    GroupThreadReply = 12,
+    /// Seal (NIP-59)
+    Seal = 13,
+    /// Private direct message (NIP-17)
+    PrivateDirectMessage = 14,
+    /// Generic repost (NIP-18)
+    GenericRepost = 16,
     ChannelCreation = 40,
     ChannelMetadata = 41,
     ChannelMessage = 42,
     ChannelHideMessage = 43,
     ChannelMuteUser = 44,
+    /// OpenTimestamps attestation (NIP-03)
+    OpenTimestamps = 1040,
+    /// Gift wrap (NIP-59)
+    GiftWrap = 1059,
+    /// Git patch (NIP-34)
+    GitPatch = 1617,
+    /// Git issue (NIP-34)
+    GitIssue = 1621,
+    /// Reporting (NIP-56)
+    Reporting = 1984,
+    /// Job feedback (NIP-90)
+    JobFeedback = 7000,
+    GroupPutUser = 9000,
+    GroupRemoveUser = 9001,
+    GroupEditMetadata = 9002,
+    GroupDeleteEvent = 9005,
+    GroupCreate = 9007,
+    GroupDelete = 9008,
+    GroupJoinRequest = 9021,
+    GroupLeaveRequest = 9022,
+    /// Zap request (NIP-57)
+    ZapRequest = 9734,
+    /// Zap receipt (NIP-57)
+    ZapReceipt = 9735,
+    /// Highlight (NIP-84)
+    Highlight = 9802,
+    /// Mute list (NIP-51)
+    MuteList = 10000,
+    /// Pin list (NIP-51)
+    PinList = 10001,
+    /// Relay list metadata (NIP-65)
+    RelayList = 10002,
+    /// Bookmarks (NIP-51)
+    Bookmarks = 10003,
+    /// Relay monitor announcement (NIP-66)
+    RelayMonitorAnnouncement = 10166,
+    /// Wallet service info (NIP-47)
+    WalletConnectInfo = 13194,
+    /// Client authentication (NIP-42)
+    Authentication = 22242,
+    /// Wallet request (NIP-47)
+    WalletConnectRequest = 23194,
+    /// Wallet response (NIP-47)
+    WalletConnectResponse = 23195,
+    /// Nostr Connect request or response (NIP-46)
+    NostrConnect = 24133,
+    /// HTTP Auth (NIP-98)
+    HttpAuth = 27235,
+    /// Categorized people list (NIP-51)
+    PeopleList = 30000,
+    /// Profile badges (NIP-58)
+    ProfileBadges = 30008,
+    /// Badge definition (NIP-58)
+    BadgeDefinition = 30009,
+    /// Marketplace stall (NIP-15)
+    Stall = 30017,
+    /// Marketplace product (NIP-15)
+    Product = 30018,
+    LongFormTextNote = 30023,
+    /// Long-form content draft (NIP-23)
+    LongFormTextNoteDraft = 30024,
+    /// Relay discovery (NIP-66)
+    RelayDiscovery = 30166,
+    /// User status (NIP-38)
+    UserStatus = 30315,
+    /// Classified listing (NIP-99)
+    ClassifiedListing = 30402,
+    /// Git repository announcement (NIP-34)
+    GitRepositoryAnnouncement = 30617,
+    /// Handler recommendation (NIP-89)
+    HandlerRecommendation = 31989,
+    /// Handler information (NIP-89)
+    HandlerInformation = 31990,
+    GroupMetadata = 39000,
+    GroupAdmins = 39001,
+    GroupMembers = 39002,
+    GroupRoles = 39003,
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
@@ -27,3 +117,162 @@ pub enum Kind {
     Base(KindBase),
     Custom(u64),
 }
+
+impl From<u64> for Kind {
+    fn from(kind: u64) -> Self {
+        match kind {
+            0 => Self::Base(KindBase::Metadata),
+            1 => Self::Base(KindBase::TextNote),
+            2 => Self::Base(KindBase::RecommendRelay),
+            3 => Self::Base(KindBase::ContactList),
+            4 => Self::Base(KindBase::EncryptedDirectMessage),
+            5 => Self::Base(KindBase::EventDeletion),
+            6 => Self::Base(KindBase::Boost),
+            7 => Self::Base(KindBase::Reaction),
+            8 => Self::Base(KindBase::BadgeAward),
+            9 => Self::Base(KindBase::GroupChatMessage),
+            11 => Self::Base(KindBase::GroupThread),
+            12 => Self::Base(KindBase::GroupThreadReply),
+            13 => Self::Base(KindBase::Seal),
+            14 => Self::Base(KindBase::PrivateDirectMessage),
+            16 => Self::Base(KindBase::GenericRepost),
+            40 => Self::Base(KindBase::ChannelCreation),
+            41 => Self::Base(KindBase::ChannelMetadata),
+            42 => Self::Base(KindBase::ChannelMessage),
+            43 => Self::Base(KindBase::ChannelHideMessage),
+            44 => Self::Base(KindBase::ChannelMuteUser),
+            1040 => Self::Base(KindBase::OpenTimestamps),
+            1059 => Self::Base(KindBase::GiftWrap),
+            1617 => Self::Base(KindBase::GitPatch),
+            1621 => Self::Base(KindBase::GitIssue),
+            1984 => Self::Base(KindBase::Reporting),
+            7000 => Self::Base(KindBase::JobFeedback),
+            9000 => Self::Base(KindBase::GroupPutUser),
+            9001 => Self::Base(KindBase::GroupRemoveUser),
+            9002 => Self::Base(KindBase::GroupEditMetadata),
+            9005 => Self::Base(KindBase::GroupDeleteEvent),
+            9007 => Self::Base(KindBase::GroupCreate),
+            9008 => Self::Base(KindBase::GroupDelete),
+            9021 => Self::Base(KindBase::GroupJoinRequest),
+            9022 => Self::Base(KindBase::GroupLeaveRequest),
+            9734 => Self::Base(KindBase::ZapRequest),
+            9735 => Self::Base(KindBase::ZapReceipt),
+            9802 => Self::Base(KindBase::Highlight),
+            10000 => Self::Base(KindBase::MuteList),
+            10001 => Self::Base(KindBase::PinList),
+            10002 => Self::Base(KindBase::RelayList),
+            10003 => Self::Base(KindBase::Bookmarks),
+            10166 => Self::Base(KindBase::RelayMonitorAnnouncement),
+            13194 => Self::Base(KindBase::WalletConnectInfo),
+            22242 => Self::Base(KindBase::Authentication),
+            23194 => Self::Base(KindBase::WalletConnectRequest),
+            23195 => Self::Base(KindBase::WalletConnectResponse),
+            24133 => Self::Base(KindBase::NostrConnect),
+            27235 => Self::Base(KindBase::HttpAuth),
+            30000 => Self::Base(KindBase::PeopleList),
+            30008 => Self::Base(KindBase::ProfileBadges),
+            30009 => Self::Base(KindBase::BadgeDefinition),
+            30017 => Self::Base(KindBase::Stall),
+            30018 => Self::Base(KindBase::Product),
+            30023 => Self::Base(KindBase::LongFormTextNote),
+            30024 => Self::Base(KindBase::LongFormTextNoteDraft),
+            30166 => Self::Base(KindBase::RelayDiscovery),
+            30315 => Self::Base(KindBase::UserStatus),
+            30402 => Self::Base(KindBase::ClassifiedListing),
+            30617 => Self::Base(KindBase::GitRepositoryAnnouncement),
+            31989 => Self::Base(KindBase::HandlerRecommendation),
+            31990 => Self::Base(KindBase::HandlerInformation),
+            39000 => Self::Base(KindBase::GroupMetadata),
+            39001 => Self::Base(KindBase::GroupAdmins),
+            39002 => Self::Base(KindBase::GroupMembers),
+            39003 => Self::Base(KindBase::GroupRoles),
+            x => Self::Custom(x),
+        }
+    }
+}
+
+impl Kind {
+    /// Check if it's a parameterized replaceable kind (NIP-33)
+    pub fn is_parameterized_replaceable(&self) -> bool {
+        (30000..40000).contains(&u64::from(*self))
+    }
+
+    /// Check if it's a job request kind (NIP-90)
+    pub fn is_job_request(&self) -> bool {
+        JOB_REQUEST_RANGE.contains(&u64::from(*self))
+    }
+
+    /// Check if it's a job result kind (NIP-90)
+    pub fn is_job_result(&self) -> bool {
+        JOB_RESULT_RANGE.contains(&u64::from(*self))
+    }
+}
+
+impl From<Kind> for u64 {
+    fn from(kind: Kind) -> Self {
+        match kind {
+            Kind::Base(kind) => kind as u64,
+            Kind::Custom(kind) => kind,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kind_from_u64() {
+        assert_eq!(Kind::from(1), Kind::Base(KindBase::TextNote));
+        assert_eq!(Kind::from(30023), Kind::Base(KindBase::LongFormTextNote));
+        assert_eq!(Kind::from(22242), Kind::Base(KindBase::Authentication));
+        assert_eq!(Kind::from(9735), Kind::Base(KindBase::ZapReceipt));
+        assert_eq!(Kind::from(24133), Kind::Base(KindBase::NostrConnect));
+        assert_eq!(
+            Kind::from(23195),
+            Kind::Base(KindBase::WalletConnectResponse)
+        );
+        assert_eq!(Kind::from(1059), Kind::Base(KindBase::GiftWrap));
+        assert_eq!(Kind::from(14), Kind::Base(KindBase::PrivateDirectMessage));
+        assert_eq!(Kind::from(10000), Kind::Base(KindBase::MuteList));
+        assert_eq!(Kind::from(30000), Kind::Base(KindBase::PeopleList));
+        assert_eq!(Kind::from(1984), Kind::Base(KindBase::Reporting));
+        assert_eq!(Kind::from(8), Kind::Base(KindBase::BadgeAward));
+        assert_eq!(Kind::from(30009), Kind::Base(KindBase::BadgeDefinition));
+        assert_eq!(Kind::from(27235), Kind::Base(KindBase::HttpAuth));
+        assert_eq!(
+            Kind::from(31989),
+            Kind::Base(KindBase::HandlerRecommendation)
+        );
+        assert_eq!(Kind::from(7000), Kind::Base(KindBase::JobFeedback));
+        assert_eq!(Kind::from(30315), Kind::Base(KindBase::UserStatus));
+        assert_eq!(Kind::from(30017), Kind::Base(KindBase::Stall));
+        assert_eq!(Kind::from(30402), Kind::Base(KindBase::ClassifiedListing));
+        assert_eq!(Kind::from(1040), Kind::Base(KindBase::OpenTimestamps));
+        assert_eq!(Kind::from(16), Kind::Base(KindBase::GenericRepost));
+        assert_eq!(Kind::from(9802), Kind::Base(KindBase::Highlight));
+        assert_eq!(Kind::from(1617), Kind::Base(KindBase::GitPatch));
+        assert_eq!(Kind::from(1621), Kind::Base(KindBase::GitIssue));
+        assert_eq!(
+            Kind::from(30617),
+            Kind::Base(KindBase::GitRepositoryAnnouncement)
+        );
+        assert_eq!(
+            Kind::from(10166),
+            Kind::Base(KindBase::RelayMonitorAnnouncement)
+        );
+        assert_eq!(Kind::from(30166), Kind::Base(KindBase::RelayDiscovery));
+        assert_eq!(Kind::from(10002), Kind::Base(KindBase::RelayList));
+        assert_eq!(Kind::from(123), Kind::Custom(123));
+        assert_eq!(u64::from(Kind::Base(KindBase::ContactList)), 3);
+        assert_eq!(u64::from(Kind::Custom(123)), 123);
+    }
+
+    #[test]
+    fn test_parameterized_replaceable() {
+        assert!(Kind::Base(KindBase::LongFormTextNote).is_parameterized_replaceable());
+        assert!(Kind::Custom(30000).is_parameterized_replaceable());
+        assert!(!Kind::Custom(40000).is_parameterized_replaceable());
+        assert!(!Kind::Base(KindBase::TextNote).is_parameterized_replaceable());
+    }
+}
@@ -6,17 +6,23 @@ use std::str::FromStr;
 
 use bitcoin::hashes::hex::FromHex;
 use bitcoin::secp256k1::schnorr::Signature;
-use bitcoin::secp256k1::{Secp256k1, XOnlyPublicKey};
+use bitcoin::secp256k1::XOnlyPublicKey;
 use serde::{Deserialize, Deserializer};
+use url::Url;
 
 pub mod builder;
 pub mod kind;
 pub mod tag;
+pub mod unsigned;
 
 pub use self::builder::EventBuilder;
 pub use self::kind::{Kind, KindBase};
 pub use self::tag::{Marker, Tag, TagData, TagKind};
-use crate::Sha256Hash;
+pub use self::unsigned::UnsignedEvent;
+use crate::util::nips::nip13;
+use crate::util::nips::nip19::Coordinate;
+use crate::util::time::timestamp;
+use crate::{Sha256Hash, SECP256K1};
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -28,6 +34,9 @@ pub enum Error {
     /// Hex decoding error
     #[error("hex decoding error: {0}")]
     Hex(#[from] bitcoin::hashes::hex::Error),
+    /// The event id doesn't match the hash of the canonical serialization
+    #[error("invalid event id")]
+    InvalidId,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
@@ -52,9 +61,8 @@ where
 }
 
 impl Event {
-    /// Verify event
+    /// Verify event id and signature
     pub fn verify(&self) -> Result<(), Error> {
-        let secp = Secp256k1::new();
         let id = EventBuilder::gen_id(
             &self.pubkey,
             self.created_at,
@@ -62,24 +70,192 @@ impl Event {
             &self.tags,
             &self.content,
         );
+        if id != self.id {
+            return Err(Error::InvalidId);
+        }
         let message = bitcoin::secp256k1::Message::from_slice(&id)?;
-        Ok(secp.verify_schnorr(&self.sig, &message, &self.pubkey)?)
+        Ok(SECP256K1.verify_schnorr(&self.sig, &message, &self.pubkey)?)
     }
 
     /// New event from json string
+    ///
+    /// Reject events with forged id or invalid signature.
     pub fn from_json<S>(json: S) -> Result<Self, Error>
     where
         S: Into<String>,
     {
-        let event: Self = serde_json::from_str(&json.into())?;
+        let event: Self = Self::from_json_unchecked(json)?;
         event.verify()?;
         Ok(event)
     }
 
+    /// New event from json string, without checking id and signature
+    pub fn from_json_unchecked<S>(json: S) -> Result<Self, Error>
+    where
+        S: Into<String>,
+    {
+        Ok(serde_json::from_str(&json.into())?)
+    }
+
     /// Get event as json string
     pub fn as_json(&self) -> Result<String, Error> {
         Ok(serde_json::to_string(&self)?)
     }
+
+    /// Get the content warning
+    ///
+    /// Return `None` if the event has no `content-warning` tag, otherwise the optional reason.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/36.md>
+    pub fn content_warning(&self) -> Option<Option<&str>> {
+        self.tags
+            .iter()
+            .find(|t| matches!(t.kind(), Ok(TagKind::ContentWarning)))
+            .map(|t| t.content())
+    }
+
+    /// Get the expiration unix timestamp
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/40.md>
+    pub fn expiration(&self) -> Option<u64> {
+        self.tags
+            .iter()
+            .find(|t| matches!(t.kind(), Ok(TagKind::Expiration)))
+            .and_then(|t| t.content())
+            .and_then(|t| t.parse().ok())
+    }
+
+    /// Get the subject
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/14.md>
+    pub fn subject(&self) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|t| matches!(t.kind(), Ok(TagKind::Subject)))
+            .and_then(|t| t.content())
+    }
+
+    /// Get the custom emojis (`shortcode`, `url`)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/30.md>
+    pub fn emojis(&self) -> Vec<(String, Url)> {
+        self.tags
+            .iter()
+            .filter(|t| matches!(t.kind(), Ok(TagKind::Emoji)))
+            .filter_map(|t| {
+                let tag = t.as_vec();
+                let url = Url::parse(tag.get(2)?).ok()?;
+                Some((tag.get(1)?.clone(), url))
+            })
+            .collect()
+    }
+
+    /// Check if the event is expired
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/40.md>
+    pub fn is_expired(&self) -> bool {
+        self.expiration()
+            .map(|expiration| expiration <= timestamp())
+            .unwrap_or(false)
+    }
+
+    /// Get the identifier (`d` tag) of a parameterized replaceable event
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/33.md>
+    pub fn identifier(&self) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|t| matches!(t.kind(), Ok(TagKind::D)))
+            .and_then(|t| t.content())
+    }
+
+    /// Get the coordinate (`kind:pubkey:d`) of a parameterized replaceable event
+    ///
+    /// A missing `d` tag is treated as an empty identifier.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/33.md>
+    pub fn coordinate(&self) -> Option<Coordinate> {
+        if self.kind.is_parameterized_replaceable() {
+            Some(Coordinate::new(
+                self.kind,
+                self.pubkey,
+                self.identifier().unwrap_or_default(),
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Get the `e` tags (event id and marker)
+    fn event_refs(&self) -> Vec<(Sha256Hash, Option<Marker>)> {
+        self.tags
+            .iter()
+            .filter(|t| matches!(t.kind(), Ok(TagKind::E)))
+            .filter_map(|t| {
+                let tag = t.as_vec();
+                let id = Sha256Hash::from_str(tag.get(1)?).ok()?;
+                Some((id, tag.get(3).and_then(|m| Marker::from_str(m).ok())))
+            })
+            .collect()
+    }
+
+    /// Get the id of the root event of the thread
+    ///
+    /// Support both marked and (deprecated) positional `e` tags.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/10.md>
+    pub fn root_id(&self) -> Option<Sha256Hash> {
+        let refs = self.event_refs();
+        if refs.iter().any(|(_, marker)| marker.is_some()) {
+            refs.into_iter()
+                .find(|(_, marker)| *marker == Some(Marker::Root))
+                .map(|(id, _)| id)
+        } else {
+            refs.first().map(|(id, _)| *id)
+        }
+    }
+
+    /// Get the id of the event this event is replying to
+    ///
+    /// Support both marked and (deprecated) positional `e` tags.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/10.md>
+    pub fn reply_to(&self) -> Option<Sha256Hash> {
+        let refs = self.event_refs();
+        if refs.iter().any(|(_, marker)| marker.is_some()) {
+            refs.iter()
+                .find(|(_, marker)| *marker == Some(Marker::Reply))
+                .or_else(|| {
+                    refs.iter()
+                        .find(|(_, marker)| *marker == Some(Marker::Root))
+                })
+                .map(|(id, _)| *id)
+        } else {
+            refs.last().map(|(id, _)| *id)
+        }
+    }
+
+    /// Check if the event id has at least `difficulty` leading zero bits
+    ///
+    /// If the `nonce` tag commits to a target difficulty, the target must be at least `difficulty` too.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/13.md>
+    pub fn check_pow(&self, difficulty: u8) -> bool {
+        if difficulty == 0 {
+            return true;
+        }
+
+        let target: Option<u8> = self
+            .tags
+            .iter()
+            .find(|t| matches!(t.kind(), Ok(TagKind::Nonce)))
+            .and_then(|t| t.as_vec().get(2).and_then(|d| d.parse().ok()));
+        if matches!(target, Some(target) if target < difficulty) {
+            return false;
+        }
+
+        nip13::get_leading_zero_bits(self.id) >= difficulty
+    }
 }
 
 impl Event {
@@ -129,6 +305,18 @@ mod tests {
         assert_eq!(ev_ser.as_json().unwrap(), sample_event);
     }
 
+    #[test]
+    fn test_from_json_forged_id() {
+        let forged_event = r#"{"id":"0000000000000000000000000000000000000000000000000000000000000000","pubkey":"f86c44a2de95d9149b51c6a29afeabba264c18e2fa7c49de93424a0c56947785","created_at":1640839235,"kind":4,"tags":[["p","13adc511de7e1cfcf1c6b7f6365fb5a03442d7bcacf565ea57fa7770912c023d"]],"content":"uRuvYr585B80L6rSJiHocw==?iv=oh6LVqdsYYol3JfFnXTbPA==","sig":"a5d9290ef9659083c490b303eb7ee41356d8778ff19f2f91776c8dc4443388a64ffcf336e61af4c25c05ac3ae952d1ced889ed655b67790891222aaa15b99fdd"}"#;
+        assert!(matches!(
+            Event::from_json(forged_event),
+            Err(Error::InvalidId)
+        ));
+
+        let event = Event::from_json_unchecked(forged_event).unwrap();
+        assert!(matches!(event.verify(), Err(Error::InvalidId)));
+    }
+
     #[test]
     fn test_custom_kind() {
         let keys = Keys::generate_from_os_random();
@@ -143,4 +331,355 @@ mod tests {
         assert_eq!(Kind::Custom(123), e.kind);
         assert_eq!(Kind::Custom(123), deserialized.kind);
     }
+
+    #[test]
+    fn test_content_warning() {
+        let keys = Keys::generate_from_os_random();
+
+        let event = EventBuilder::new_text_note("Sensitive", &[])
+            .content_warning(Some("spoiler"))
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(event.content_warning(), Some(Some("spoiler")));
+
+        let event = EventBuilder::new_text_note("Sensitive", &[])
+            .content_warning::<String>(None)
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(event.content_warning(), Some(None));
+
+        let event = EventBuilder::new_text_note("Safe", &[])
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(event.content_warning(), None);
+    }
+
+    #[test]
+    fn test_emojis() {
+        let keys = Keys::generate_from_os_random();
+        let url = Url::parse("https://gleasonator.com/emoji/Gleasonator/soapbox.png").unwrap();
+
+        let event = EventBuilder::new_text_note("Hello :soapbox:", &[])
+            .emoji("soapbox", url.clone())
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(event.emojis(), vec![(String::from("soapbox"), url.clone())]);
+
+        let reaction = EventBuilder::new_emoji_reaction(&event, "soapbox", url.clone())
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(reaction.content, ":soapbox:");
+        assert_eq!(reaction.emojis(), vec![(String::from("soapbox"), url)]);
+
+        let event = EventBuilder::new_text_note("No emoji", &[])
+            .to_event(&keys)
+            .unwrap();
+        assert!(event.emojis().is_empty());
+    }
+
+    #[test]
+    fn test_expiration() {
+        let keys = Keys::generate_from_os_random();
+        let now = timestamp();
+
+        let event = EventBuilder::new_text_note("Expired", &[])
+            .expiration(now - 1)
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(event.expiration(), Some(now - 1));
+        assert!(event.is_expired());
+
+        let event = EventBuilder::new_text_note("Not expired", &[])
+            .expiration(now + 3600)
+            .to_event(&keys)
+            .unwrap();
+        assert!(!event.is_expired());
+
+        let event = EventBuilder::new_text_note("No expiration", &[])
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(event.expiration(), None);
+        assert!(!event.is_expired());
+    }
+
+    #[test]
+    fn test_check_pow() {
+        let keys = Keys::generate_from_os_random();
+
+        let event = EventBuilder::new_text_note("PoW", &[])
+            .to_pow_event(&keys, 8)
+            .unwrap();
+        assert!(event.check_pow(0));
+        assert!(event.check_pow(8));
+        // Committed target is lower than the required difficulty
+        assert!(!event.check_pow(9));
+
+        let event = EventBuilder::new_text_note("No PoW", &[])
+            .to_event(&keys)
+            .unwrap();
+        assert!(event.check_pow(0));
+        assert!(!event.check_pow(64));
+    }
+
+    #[test]
+    fn test_reply_threading() {
+        let keys = Keys::generate_from_os_random();
+        let relay_url = Url::parse("wss://relay.damus.io").unwrap();
+
+        let root = EventBuilder::new_text_note("Root", &[])
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(root.root_id(), None);
+        assert_eq!(root.reply_to(), None);
+
+        let reply = EventBuilder::new_reply("Reply", &root, None, Some(relay_url.clone()))
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(reply.root_id(), Some(root.id));
+        assert_eq!(reply.reply_to(), Some(root.id));
+
+        let nested = EventBuilder::new_reply("Nested reply", &reply, None, None)
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(nested.root_id(), Some(root.id));
+        assert_eq!(nested.reply_to(), Some(reply.id));
+
+        // Positional `e` tags
+        let positional = EventBuilder::new_text_note(
+            "Positional",
+            &[
+                Tag::new(TagData::EventId(root.id)),
+                Tag::new(TagData::EventId(reply.id)),
+            ],
+        )
+        .to_event(&keys)
+        .unwrap();
+        assert_eq!(positional.root_id(), Some(root.id));
+        assert_eq!(positional.reply_to(), Some(reply.id));
+    }
+
+    #[test]
+    fn test_subject() {
+        let keys = Keys::generate_from_os_random();
+
+        let event = EventBuilder::new_text_note("Hello", &[])
+            .subject("Greetings")
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(event.subject(), Some("Greetings"));
+
+        let reply = EventBuilder::new_reply("Hi", &event, None, None)
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(reply.subject(), Some("Re: Greetings"));
+
+        let reply = EventBuilder::new_reply("Hi again", &reply, None, None)
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(reply.subject(), Some("Re: Greetings"));
+    }
+
+    #[test]
+    fn test_custom_created_at() {
+        let keys = Keys::generate_from_os_random();
+
+        let event = EventBuilder::new_text_note("Imported", &[])
+            .custom_created_at(1612809991)
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(event.created_at, 1612809991);
+        assert!(event.verify().is_ok());
+
+        let event = EventBuilder::new_text_note("Imported", &[])
+            .custom_created_at(1612809991)
+            .to_pow_event(&keys, 4)
+            .unwrap();
+        assert_eq!(event.created_at, 1612809991);
+        assert!(event.check_pow(4));
+    }
+
+    #[test]
+    fn test_identifier_invalid_tags() {
+        let keys = Keys::generate_from_os_random();
+
+        let tags: Vec<Tag> = serde_json::from_str(r#"[["d"]]"#).unwrap();
+        let event = EventBuilder::new(Kind::Base(KindBase::LongFormTextNote), "", &tags)
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(event.identifier(), None);
+        assert_eq!(
+            event.coordinate(),
+            Some(Coordinate::new(
+                Kind::Base(KindBase::LongFormTextNote),
+                keys.public_key(),
+                ""
+            ))
+        );
+
+        // Not a parameterized replaceable event
+        let tags: Vec<Tag> = serde_json::from_str(r#"[["d", "lorem-ipsum"]]"#).unwrap();
+        let event = EventBuilder::new_text_note("", &tags)
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(event.identifier(), Some("lorem-ipsum"));
+        assert_eq!(event.coordinate(), None);
+    }
+
+    #[test]
+    fn test_expiration_invalid_tags() {
+        let keys = Keys::generate_from_os_random();
+
+        let tags: Vec<Tag> = serde_json::from_str(r#"[["expiration", "1600000000"]]"#).unwrap();
+        let event = EventBuilder::new_text_note("", &tags)
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(event.expiration(), Some(1600000000));
+        assert!(event.is_expired());
+
+        for tags in [
+            r#"[["expiration"]]"#,
+            r#"[["expiration", "tomorrow"]]"#,
+            r#"[["expiration", "-1"]]"#,
+        ] {
+            let tags: Vec<Tag> = serde_json::from_str(tags).unwrap();
+            let event = EventBuilder::new_text_note("", &tags)
+                .to_event(&keys)
+                .unwrap();
+            assert_eq!(event.expiration(), None);
+            assert!(!event.is_expired());
+        }
+    }
+
+    #[test]
+    fn test_content_warning_spec_example() {
+        let keys = Keys::generate_from_os_random();
+
+        let tags: Vec<Tag> = serde_json::from_str(
+            r#"[["t", "hastag"], ["L", "nsfw"], ["l", "NS", "nsfw"], ["content-warning", "reason"]]"#,
+        )
+        .unwrap();
+        let event = EventBuilder::new_text_note("sensitive content", &tags)
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(event.content_warning(), Some(Some("reason")));
+
+        let tags: Vec<Tag> = serde_json::from_str(r#"[["content-warning"]]"#).unwrap();
+        let event = EventBuilder::new_text_note("sensitive content", &tags)
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(event.content_warning(), Some(None));
+    }
+
+    #[test]
+    fn test_emojis_spec_example() {
+        let keys = Keys::generate_from_os_random();
+
+        let tags: Vec<Tag> = serde_json::from_str(
+            r#"[
+                ["emoji", "gleasonator", "https://gleasonator.com/emoji/Gleasonator/gleasonator.png"],
+                ["emoji", "ablobcatrainbow", "https://gleasonator.com/emoji/blobcat/ablobcatrainbow.png"],
+                ["emoji", "disputed", "https://gleasonator.com/emoji/Fun/disputed.png"],
+                ["emoji"],
+                ["emoji", "soapbox"],
+                ["emoji", "soapbox", "soapbox.png"]
+            ]"#,
+        )
+        .unwrap();
+        let event = EventBuilder::new_text_note(
+            "Good morning :gleasonator: :ablobcatrainbow: :disputed:",
+            &tags,
+        )
+        .to_event(&keys)
+        .unwrap();
+        assert_eq!(
+            event
+                .emojis()
+                .into_iter()
+                .map(|(shortcode, url)| (shortcode, url.to_string()))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    String::from("gleasonator"),
+                    String::from("https://gleasonator.com/emoji/Gleasonator/gleasonator.png")
+                ),
+                (
+                    String::from("ablobcatrainbow"),
+                    String::from("https://gleasonator.com/emoji/blobcat/ablobcatrainbow.png")
+                ),
+                (
+                    String::from("disputed"),
+                    String::from("https://gleasonator.com/emoji/Fun/disputed.png")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reply_threading_invalid_tags() {
+        let keys = Keys::generate_from_os_random();
+        let root = EventBuilder::new_text_note("Root", &[])
+            .to_event(&keys)
+            .unwrap();
+        let reply = EventBuilder::new_text_note("Reply", &[])
+            .to_event(&keys)
+            .unwrap();
+        let quoted = EventBuilder::new_text_note("Quoted", &[])
+            .to_event(&keys)
+            .unwrap();
+
+        // Marked tags in any order, with mentions and invalid ids skipped
+        let tags: Vec<Tag> = serde_json::from_str(&format!(
+            r#"[
+                ["e", "{}", "", "mention"],
+                ["e", "not an event id", "", "reply"],
+                ["e", "{}", "wss://relay.damus.io", "reply"],
+                ["e", "{}", "wss://relay.damus.io", "root"],
+                ["e"]
+            ]"#,
+            quoted.id, reply.id, root.id
+        ))
+        .unwrap();
+        let event = EventBuilder::new_text_note("", &tags)
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(event.root_id(), Some(root.id));
+        assert_eq!(event.reply_to(), Some(reply.id));
+
+        // Only mentions
+        let tags: Vec<Tag> =
+            serde_json::from_str(&format!(r#"[["e", "{}", "", "mention"]]"#, quoted.id)).unwrap();
+        let event = EventBuilder::new_text_note("", &tags)
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(event.root_id(), None);
+        assert_eq!(event.reply_to(), None);
+
+        // Positional tags with unknown markers and invalid ids
+        let tags: Vec<Tag> = serde_json::from_str(&format!(
+            r#"[["e", "{}", "", "parent"], ["e", "{}"], ["e", "not an event id"]]"#,
+            root.id, reply.id
+        ))
+        .unwrap();
+        let event = EventBuilder::new_text_note("", &tags)
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(event.root_id(), Some(root.id));
+        assert_eq!(event.reply_to(), Some(reply.id));
+    }
+
+    #[test]
+    fn test_subject_invalid_tags() {
+        let keys = Keys::generate_from_os_random();
+
+        let tags: Vec<Tag> = serde_json::from_str(r#"[["subject"]]"#).unwrap();
+        let event = EventBuilder::new_text_note("Hello", &tags)
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(event.subject(), None);
+
+        let reply = EventBuilder::new_reply("Hi", &event, None, None)
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(reply.subject(), None);
+    }
 }
@@ -8,6 +8,7 @@ use bitcoin::secp256k1::schnorr::Signature;
 use bitcoin::secp256k1::XOnlyPublicKey;
 use url::Url;
 
+use crate::util::nips::nip19::Coordinate;
 use crate::Sha256Hash;
 
 #[derive(Debug, thiserror::Error)]
@@ -22,6 +23,7 @@ pub enum Error {
 pub enum Marker {
     Root,
     Reply,
+    Mention,
 }
 
 impl fmt::Display for Marker {
@@ -29,6 +31,7 @@ impl fmt::Display for Marker {
         match self {
             Self::Root => write!(f, "root"),
             Self::Reply => write!(f, "reply"),
+            Self::Mention => write!(f, "mention"),
         }
     }
 }
@@ -40,6 +43,7 @@ impl FromStr for Marker {
         match s {
             "root" => Ok(Self::Root),
             "reply" => Ok(Self::Reply),
+            "mention" => Ok(Self::Mention),
             _ => Err(Error::MarkerParseError),
         }
     }
@@ -49,9 +53,17 @@ impl FromStr for Marker {
 pub enum TagKind {
     P,
     E,
+    D,
+    A,
+    H,
     Nonce,
     Delegation,
     ContentWarning,
+    Relay,
+    Challenge,
+    Expiration,
+    Emoji,
+    Subject,
     Custom(String),
 }
 
@@ -60,9 +72,17 @@ impl fmt::Display for TagKind {
         match self {
             Self::P => write!(f, "p"),
             Self::E => write!(f, "e"),
+            Self::D => write!(f, "d"),
+            Self::A => write!(f, "a"),
+            Self::H => write!(f, "h"),
             Self::Nonce => write!(f, "nonce"),
             Self::Delegation => write!(f, "delegation"),
             Self::ContentWarning => write!(f, "content-warning"),
+            Self::Relay => write!(f, "relay"),
+            Self::Challenge => write!(f, "challenge"),
+            Self::Expiration => write!(f, "expiration"),
+            Self::Emoji => write!(f, "emoji"),
+            Self::Subject => write!(f, "subject"),
             Self::Custom(tag) => write!(f, "{}", tag),
         }
     }
@@ -77,9 +97,17 @@ where
         match s.as_str() {
             "p" => Self::P,
             "e" => Self::E,
+            "d" => Self::D,
+            "a" => Self::A,
+            "h" => Self::H,
             "nonce" => Self::Nonce,
             "delegation" => Self::Delegation,
             "content-warning" => Self::ContentWarning,
+            "relay" => Self::Relay,
+            "challenge" => Self::Challenge,
+            "expiration" => Self::Expiration,
+            "emoji" => Self::Emoji,
+            "subject" => Self::Subject,
             tag => Self::Custom(tag.to_string()),
         }
     }
@@ -107,12 +135,31 @@ pub enum TagData {
     ContentWarning {
         reason: Option<String>,
     },
+    /// NIP-29 group id
+    Group(String),
+    /// NIP-33 identifier (`d` tag)
+    Identifier(String),
+    /// NIP-33 event coordinate (`a` tag), with the first relay as hint
+    Coordinate(Coordinate),
+    /// NIP-42 relay url
+    Relay(Url),
+    /// NIP-42 challenge
+    Challenge(String),
+    /// NIP-40 expiration unix timestamp
+    Expiration(u64),
+    /// NIP-30 custom emoji (`shortcode` without colons, only alphanumeric and `_`)
+    Emoji {
+        shortcode: String,
+        url: Url,
+    },
+    /// NIP-14 subject
+    Subject(String),
 }
 
 impl From<TagData> for Vec<String> {
     fn from(data: TagData) -> Self {
         match data {
-            TagData::Generic(kind, data) => vec![vec![kind.to_string()], data].concat(),
+            TagData::Generic(kind, data) => [vec![kind.to_string()], data].concat(),
             TagData::EventId(id) => vec![TagKind::E.to_string(), id.to_string()],
             TagData::PubKey(pk) => vec![TagKind::P.to_string(), pk.to_string()],
             TagData::ContactList {
@@ -153,6 +200,24 @@ impl From<TagData> for Vec<String> {
                 }
                 tag
             }
+            TagData::Group(id) => vec![TagKind::H.to_string(), id],
+            TagData::Identifier(identifier) => vec![TagKind::D.to_string(), identifier],
+            TagData::Coordinate(coordinate) => {
+                let mut tag = vec![TagKind::A.to_string(), coordinate.to_string()];
+                if let Some(relay) = coordinate.relays.first() {
+                    tag.push(relay.clone());
+                }
+                tag
+            }
+            TagData::Relay(url) => vec![TagKind::Relay.to_string(), url.to_string()],
+            TagData::Challenge(challenge) => vec![TagKind::Challenge.to_string(), challenge],
+            TagData::Expiration(timestamp) => {
+                vec![TagKind::Expiration.to_string(), timestamp.to_string()]
+            }
+            TagData::Emoji { shortcode, url } => {
+                vec![TagKind::Emoji.to_string(), shortcode, url.to_string()]
+            }
+            TagData::Subject(subject) => vec![TagKind::Subject.to_string(), subject],
         }
     }
 }
@@ -0,0 +1,125 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use bitcoin::secp256k1::schnorr::Signature;
+use bitcoin::secp256k1::{KeyPair, Message, XOnlyPublicKey};
+
+use super::{Event, Kind, Tag};
+use crate::key::{self, Keys};
+use crate::{Sha256Hash, SECP256K1};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Key error
+    #[error("key error: {0}")]
+    Key(#[from] key::Error),
+    #[error("secp256k1 error: {0}")]
+    Secp256k1(#[from] bitcoin::secp256k1::Error),
+    /// Error serializing or deserializing JSON data
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+    /// Event error
+    #[error("event error: {0}")]
+    Event(#[from] super::Error),
+    /// The signing keys don't match the event public key
+    #[error("public key mismatch")]
+    PublicKeyMismatch,
+}
+
+/// Event with computed id but without signature
+///
+/// Used when the event is signed by someone else (i.e. a NIP-46 remote signer).
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct UnsignedEvent {
+    pub id: Sha256Hash,
+    pub pubkey: XOnlyPublicKey,
+    pub created_at: u64,
+    pub kind: Kind,
+    pub tags: Vec<Tag>,
+    pub content: String,
+}
+
+impl UnsignedEvent {
+    /// Sign the event
+    pub fn sign(self, keys: &Keys) -> Result<Event, Error> {
+        if keys.public_key() != self.pubkey {
+            return Err(Error::PublicKeyMismatch);
+        }
+
+        let keypair: &KeyPair = &keys.key_pair()?;
+        let message = Message::from_slice(&self.id)?;
+
+        Ok(Event {
+            id: self.id,
+            pubkey: self.pubkey,
+            created_at: self.created_at,
+            kind: self.kind,
+            tags: self.tags,
+            content: self.content,
+            sig: SECP256K1.sign_schnorr(&message, keypair),
+        })
+    }
+
+    /// Add a signature produced elsewhere (i.e. by a remote or hardware signer)
+    ///
+    /// The signature is verified before returning the [`Event`].
+    pub fn add_signature(self, sig: Signature) -> Result<Event, Error> {
+        let event = Event {
+            id: self.id,
+            pubkey: self.pubkey,
+            created_at: self.created_at,
+            kind: self.kind,
+            tags: self.tags,
+            content: self.content,
+            sig,
+        };
+        event.verify()?;
+        Ok(event)
+    }
+
+    /// New unsigned event from json string
+    pub fn from_json<S>(json: S) -> Result<Self, Error>
+    where
+        S: Into<String>,
+    {
+        Ok(serde_json::from_str(&json.into())?)
+    }
+
+    /// Get unsigned event as json string
+    pub fn as_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string(&self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EventBuilder, Result};
+
+    #[test]
+    fn test_add_signature() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        let unsigned =
+            EventBuilder::new_text_note("Sign me", &[]).to_unsigned_event(keys.public_key());
+
+        let message = Message::from_slice(&unsigned.id)?;
+        let sig = SECP256K1.sign_schnorr(&message, &keys.key_pair()?);
+
+        let event = unsigned.clone().add_signature(sig)?;
+        assert_eq!(event.id, unsigned.id);
+        assert!(event.verify().is_ok());
+
+        let other = Keys::generate_from_os_random();
+        let wrong_sig = SECP256K1.sign_schnorr(&message, &other.key_pair()?);
+        assert!(matches!(
+            unsigned.clone().add_signature(wrong_sig),
+            Err(Error::Event(..))
+        ));
+
+        let mut tampered = unsigned;
+        tampered.content = String::from("Tampered");
+        assert!(tampered.add_signature(sig).is_err());
+
+        Ok(())
+    }
+}
@@ -2,14 +2,30 @@
 // Copyright (c) 2022 Yuki Kishimoto
 // Distributed under the MIT software license
 
+use std::fmt;
 use std::str::FromStr;
 
 use bitcoin::bech32::{self, FromBase32, ToBase32, Variant};
+use bitcoin::hashes::hmac::{Hmac, HmacEngine};
+use bitcoin::hashes::sha256::Hash as Sha256Hash;
+use bitcoin::hashes::{Hash, HashEngine};
 use bitcoin::secp256k1::rand::rngs::OsRng;
+use bitcoin::secp256k1::ONE_KEY;
 pub use bitcoin::secp256k1::{KeyPair, Secp256k1, SecretKey, XOnlyPublicKey};
+use once_cell::sync::Lazy;
+use serde::de::{Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+use zeroize::Zeroizing;
+
+use crate::SECP256K1;
 
 const PREFIX_BECH32_SECRET_KEY: &str = "nsec";
 const PREFIX_BECH32_PUBLIC_KEY: &str = "npub";
+const APP_KEY_DOMAIN: &[u8] = b"nostr/app-key";
+
+/// Key pair written over the erased ones
+static ERASED_KEY_PAIR: Lazy<KeyPair> =
+    Lazy::new(|| KeyPair::from_secret_key(&SECP256K1, &ONE_KEY));
 
 #[derive(Debug, Eq, PartialEq, thiserror::Error)]
 pub enum Error {
@@ -45,11 +61,10 @@ pub trait FromPkStr: Sized {
     fn from_pk_str(public_key: &str) -> Result<Self, Self::Err>;
 }
 
+/// Parse bech32 entity (i.e. [`Keys`] from `nsec`, NIP-19 entities)
 pub trait FromBech32: Sized {
-    fn from_bech32<S>(secret_key: S) -> Result<Self, Error>
-    where
-        S: Into<String>;
-    fn from_bech32_public_key<S>(public_key: S) -> Result<Self, Error>
+    type Err;
+    fn from_bech32<S>(s: S) -> Result<Self, Self::Err>
     where
         S: Into<String>;
 }
@@ -85,24 +100,71 @@ impl ToBech32 for SecretKey {
     }
 }
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+/// [`KeyPair`] overwritten on drop
+#[derive(Clone)]
+struct ErasableKeyPair(KeyPair);
+
+impl Drop for ErasableKeyPair {
+    fn drop(&mut self) {
+        // SAFETY: `self.0` is a valid and aligned `KeyPair`. The volatile write is not
+        // optimized away (as a plain assignment before the drop would be).
+        unsafe { std::ptr::write_volatile(&mut self.0, *ERASED_KEY_PAIR) };
+    }
+}
+
+/// Keys
+///
+/// Only the public key and the [`KeyPair`] used for signing are kept: the key pair is computed
+/// once and overwritten on drop.
+///
+/// **Limits**: [`SecretKey`] and [`KeyPair`] are `Copy`, so the values passed to [`Keys::new`] and
+/// returned by [`Keys::secret_key`] and [`Keys::key_pair`] (and any copy of them) are NOT wiped:
+/// keep them as short-lived as possible.
+#[derive(Clone)]
 pub struct Keys {
     public_key: XOnlyPublicKey,
-    key_pair: Option<KeyPair>,
-    secret_key: Option<SecretKey>,
+    key_pair: Option<ErasableKeyPair>,
+}
+
+/// Compare the secret keys in constant time
+impl PartialEq for Keys {
+    fn eq(&self, other: &Self) -> bool {
+        let secret_keys_eq = match (&self.key_pair, &other.key_pair) {
+            (Some(a), Some(b)) => {
+                let a = Zeroizing::new(a.0.secret_bytes());
+                let b = Zeroizing::new(b.0.secret_bytes());
+                a.iter()
+                    .zip(b.iter())
+                    .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+                    == 0
+            }
+            (None, None) => true,
+            _ => false,
+        };
+        self.public_key == other.public_key && secret_keys_eq
+    }
+}
+
+impl Eq for Keys {}
+
+impl fmt::Debug for Keys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Keys")
+            .field("public_key", &self.public_key)
+            .field("has_secret_key", &self.key_pair.is_some())
+            .finish()
+    }
 }
 
 impl Keys {
     /// Initialize from secret key.
     pub fn new(secret_key: SecretKey) -> Self {
-        let secp = Secp256k1::new();
-        let key_pair = KeyPair::from_secret_key(&secp, &secret_key);
+        let key_pair = KeyPair::from_secret_key(&SECP256K1, &secret_key);
         let public_key = XOnlyPublicKey::from_keypair(&key_pair).0;
 
         Self {
             public_key,
-            key_pair: Some(key_pair),
-            secret_key: Some(secret_key),
+            key_pair: Some(ErasableKeyPair(key_pair)),
         }
     }
 
@@ -111,15 +173,13 @@ impl Keys {
         Self {
             public_key,
             key_pair: None,
-            secret_key: None,
         }
     }
 
     /// Generate a new random keys
     pub fn generate_from_os_random() -> Self {
-        let secp = Secp256k1::new();
-        let mut rng = OsRng::default();
-        let (secret_key, _) = secp.generate_keypair(&mut rng);
+        let mut rng = OsRng;
+        let (secret_key, _) = SECP256K1.generate_keypair(&mut rng);
         Self::new(secret_key)
     }
 
@@ -129,32 +189,63 @@ impl Keys {
     }
 
     /// Get secret key
+    ///
+    /// The returned copy is not wiped on drop.
     pub fn secret_key(&self) -> Result<SecretKey, Error> {
-        if let Some(secret_key) = self.secret_key {
-            Ok(secret_key)
-        } else {
-            Err(Error::SkMissing)
+        match &self.key_pair {
+            Some(key_pair) => Ok(SecretKey::from_keypair(&key_pair.0)),
+            None => Err(Error::SkMissing),
         }
     }
 
     /// Get keypair
+    ///
+    /// The returned copy is not wiped on drop.
     pub fn key_pair(&self) -> Result<KeyPair, Error> {
-        if let Some(key_pair) = self.key_pair {
-            Ok(key_pair)
-        } else {
-            Err(Error::KeyPairMissing)
+        match &self.key_pair {
+            Some(key_pair) => Ok(key_pair.0),
+            None => Err(Error::KeyPairMissing),
         }
     }
 
     /// Get secret key as string
+    #[deprecated(
+        note = "the returned string is not wiped on drop: use `secret_key_as_zeroizing_str`"
+    )]
     pub fn secret_key_as_str(&self) -> Result<String, Error> {
         Ok(self.secret_key()?.display_secret().to_string())
     }
 
+    /// Get secret key as string, wiped on drop
+    pub fn secret_key_as_zeroizing_str(&self) -> Result<Zeroizing<String>, Error> {
+        Ok(Zeroizing::new(
+            self.secret_key()?.display_secret().to_string(),
+        ))
+    }
+
     /// Get public key as string
     pub fn public_key_as_str(&self) -> String {
         self.public_key.to_string()
     }
+
+    /// Derive app-specific child keys
+    ///
+    /// The child secret key is `HMAC-SHA256(secret_key, "nostr/app-key" || label)`:
+    /// the same keys and label always give the same child keys, so they can be recovered
+    /// from the backup of the master keys. The master keys can't be derived from the child keys.
+    pub fn derive_app_key<S>(&self, label: S) -> Result<Self, Error>
+    where
+        S: Into<String>,
+    {
+        let secret_key = self.secret_key()?;
+        let mut engine = HmacEngine::<Sha256Hash>::new(&secret_key.secret_bytes());
+        engine.input(APP_KEY_DOMAIN);
+        engine.input(label.into().as_bytes());
+        let hmac = Hmac::<Sha256Hash>::from_engine(engine);
+        let child_secret_bytes = Zeroizing::new(hmac.into_inner());
+        let child_secret_key = SecretKey::from_slice(child_secret_bytes.as_ref())?;
+        Ok(Self::new(child_secret_key))
+    }
 }
 
 impl FromSkStr for Keys {
@@ -188,7 +279,10 @@ impl FromPkStr for Keys {
 }
 
 impl FromBech32 for Keys {
-    fn from_bech32<S>(secret_key: S) -> Result<Self, Error>
+    type Err = Error;
+
+    /// Init [`Keys`] from `bech32` secret key (`nsec`)
+    fn from_bech32<S>(secret_key: S) -> Result<Self, Self::Err>
     where
         S: Into<String>,
     {
@@ -199,23 +293,19 @@ impl FromBech32 for Keys {
             return Err(Error::Bech32SkParseError);
         }
 
-        let data = Vec::<u8>::from_base32(&data).map_err(|_| Error::Bech32SkParseError)?;
+        let data =
+            Zeroizing::new(Vec::<u8>::from_base32(&data).map_err(|_| Error::Bech32SkParseError)?);
 
         let secret_key =
             SecretKey::from_slice(data.as_slice()).map_err(|_| Error::Bech32SkParseError)?;
 
-        let secp = Secp256k1::new();
-        let key_pair = KeyPair::from_secret_key(&secp, &secret_key);
-        let public_key = XOnlyPublicKey::from_keypair(&key_pair).0;
-
-        Ok(Self {
-            public_key,
-            key_pair: Some(key_pair),
-            secret_key: Some(secret_key),
-        })
+        Ok(Self::new(secret_key))
     }
+}
 
-    fn from_bech32_public_key<S>(public_key: S) -> Result<Self, Error>
+impl Keys {
+    /// Init public-only [`Keys`] from `bech32` public key (`npub`)
+    pub fn from_bech32_public_key<S>(public_key: S) -> Result<Self, Error>
     where
         S: Into<String>,
     {
@@ -230,11 +320,80 @@ impl FromBech32 for Keys {
 
         let public_key = XOnlyPublicKey::from_slice(data.as_slice())?;
 
-        Ok(Keys {
-            public_key,
-            key_pair: None,
-            secret_key: None,
-        })
+        Ok(Self::from_public_key(public_key))
+    }
+}
+
+/// Serialize only the public key (as hex): the secret key is never serialized
+impl Serialize for Keys {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        hex::serialize(&self.public_key, serializer)
+    }
+}
+
+/// Deserialize public-only [`Keys`] from `hex` or `bech32` public key
+impl<'de> Deserialize<'de> for Keys {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(Self::from_public_key(hex::deserialize(deserializer)?))
+    }
+}
+
+fn deserialize_public_key<'de, D>(deserializer: D) -> Result<XOnlyPublicKey, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let public_key = String::deserialize(deserializer)?;
+    Keys::from_pk_str(&public_key)
+        .map(|keys| keys.public_key())
+        .map_err(serde::de::Error::custom)
+}
+
+/// (De)serialize `XOnlyPublicKey` as `hex`, accepting also `bech32` when deserializing
+///
+/// Use with `#[serde(with = "nostr::key::hex")]`
+pub mod hex {
+    use super::*;
+
+    pub fn serialize<S>(public_key: &XOnlyPublicKey, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&public_key.to_string())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<XOnlyPublicKey, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize_public_key(deserializer)
+    }
+}
+
+/// (De)serialize `XOnlyPublicKey` as `bech32` (`npub`), accepting also `hex` when deserializing
+///
+/// Use with `#[serde(with = "nostr::key::npub")]`
+pub mod npub {
+    use super::*;
+
+    pub fn serialize<S>(public_key: &XOnlyPublicKey, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let npub = public_key.to_bech32().map_err(serde::ser::Error::custom)?;
+        serializer.serialize_str(&npub)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<XOnlyPublicKey, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize_public_key(deserializer)
     }
 }
 
@@ -266,4 +425,117 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn derive_app_key() -> Result<()> {
+        let keys =
+            Keys::from_bech32("nsec1j4c6269y9w0q2er2xjw8sv2ehyrtfxq3jwgdlxj6qfn8z4gjsq5qfvfk99")?;
+
+        let bot = keys.derive_app_key("bot")?;
+        assert_eq!(bot, keys.derive_app_key("bot")?);
+        assert_ne!(bot, keys);
+        assert_ne!(bot, keys.derive_app_key("chat")?);
+
+        let public_keys = Keys::from_public_key(keys.public_key());
+        assert_eq!(public_keys.derive_app_key("bot"), Err(Error::SkMissing));
+
+        Ok(())
+    }
+
+    #[test]
+    fn keys_eq() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        assert_eq!(keys, Keys::new(keys.secret_key()?));
+        assert_eq!(keys, keys.clone());
+        assert_ne!(keys, Keys::generate_from_os_random());
+
+        // Same public key, without the secret key
+        let public_keys = Keys::from_public_key(keys.public_key());
+        assert_ne!(keys, public_keys);
+        assert_eq!(public_keys, Keys::from_public_key(keys.public_key()));
+
+        Ok(())
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn secret_key_as_str() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        assert_eq!(
+            keys.secret_key_as_str()?,
+            keys.secret_key_as_zeroizing_str()?.as_str()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn secret_key_from_key_pair() -> Result<()> {
+        let secret_key = SecretKey::from_str(
+            "6b911fd37cdf5c81d4c0adb1ab7fa822ed253ab0ad9aa18d77257c88b29b718e",
+        )?;
+        let keys = Keys::new(secret_key);
+        assert_eq!(keys.secret_key()?, secret_key);
+        assert_eq!(
+            keys.secret_key_as_zeroizing_str()?.as_str(),
+            "6b911fd37cdf5c81d4c0adb1ab7fa822ed253ab0ad9aa18d77257c88b29b718e"
+        );
+
+        let public_keys = Keys::from_public_key(keys.public_key());
+        assert_eq!(public_keys.secret_key(), Err(Error::SkMissing));
+
+        Ok(())
+    }
+
+    #[test]
+    fn debug_without_secret_key() -> Result<()> {
+        let keys =
+            Keys::from_bech32("nsec1j4c6269y9w0q2er2xjw8sv2ehyrtfxq3jwgdlxj6qfn8z4gjsq5qfvfk99")?;
+        let debug = format!("{:?}", keys);
+        assert!(debug.contains("has_secret_key: true"));
+        assert!(!debug.contains(keys.secret_key_as_zeroizing_str()?.as_str()));
+        assert_eq!(
+            keys.key_pair()?,
+            KeyPair::from_secret_key(&SECP256K1, &keys.secret_key()?)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn serde_public_key() -> Result<()> {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Config {
+            #[serde(with = "hex")]
+            owner: XOnlyPublicKey,
+            #[serde(with = "npub")]
+            bot: XOnlyPublicKey,
+            keys: Keys,
+        }
+
+        let owner = Keys::generate_from_os_random();
+        let config = Config {
+            owner: owner.public_key(),
+            bot: owner.derive_app_key("bot")?.public_key(),
+            keys: owner.clone(),
+        };
+
+        let json = serde_json::to_string(&config)?;
+        assert!(json.contains(&format!(r#""owner":"{}""#, owner.public_key())));
+        assert!(json.contains(r#""bot":"npub1"#));
+        assert!(!json.contains(&*owner.secret_key_as_zeroizing_str()?));
+
+        let deserialized: Config = serde_json::from_str(&json)?;
+        assert_eq!(deserialized.owner, config.owner);
+        assert_eq!(deserialized.bot, config.bot);
+        assert_eq!(deserialized.keys, Keys::from_public_key(owner.public_key()));
+
+        let npub = owner.public_key().to_bech32()?;
+        let keys: Keys = serde_json::from_str(&format!(r#""{}""#, npub))?;
+        assert_eq!(keys.public_key(), owner.public_key());
+        assert!(serde_json::from_str::<Keys>(
+            r#""nsec1j4c6269y9w0q2er2xjw8sv2ehyrtfxq3jwgdlxj6qfn8z4gjsq5qfvfk99""#
+        )
+        .is_err());
+
+        Ok(())
+    }
 }
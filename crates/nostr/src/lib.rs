@@ -6,6 +6,9 @@
 #[macro_use]
 extern crate serde;
 
+use bitcoin::secp256k1::{All, Secp256k1};
+use once_cell::sync::Lazy;
+
 pub use bitcoin::hashes;
 pub use bitcoin::hashes::sha256::Hash as Sha256Hash;
 pub use bitcoin::secp256k1;
@@ -17,18 +20,26 @@ pub mod event;
 pub mod key;
 pub mod message;
 pub mod metadata;
+pub mod signer;
 pub mod util;
 
 pub use self::contact::Contact;
 pub use self::entity::Entity;
-pub use self::event::{Event, EventBuilder, Kind, KindBase, Tag};
+pub use self::event::{Event, EventBuilder, Kind, KindBase, Tag, UnsignedEvent};
 pub use self::key::Keys;
 pub use self::message::{ClientMessage, RelayMessage, SubscriptionFilter};
 pub use self::metadata::Metadata;
+pub use self::signer::NostrSigner;
+
+/// Shared secp256k1 context (signing and verification), randomized on first use
+///
+/// Creating a context is expensive: use this instead of `Secp256k1::new()`.
+pub static SECP256K1: Lazy<Secp256k1<All>> = Lazy::new(Secp256k1::new);
 
 pub type Result<T, E = Box<dyn std::error::Error>> = std::result::Result<T, E>;
 
 #[cfg(test)]
+#[allow(clippy::redundant_static_lifetimes, clippy::useless_vec)]
 mod tests {
     use std::str::FromStr;
 
@@ -79,6 +90,54 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn shared_secp256k1_context() -> Result<()> {
+        use bitcoin::secp256k1::{Message, Secp256k1};
+
+        use crate::SECP256K1;
+
+        // Same keys and valid signatures as with a fresh context
+        let keys = Keys::new(SecretKey::from_str(
+            "6b911fd37cdf5c81d4c0adb1ab7fa822ed253ab0ad9aa18d77257c88b29b718e",
+        )?);
+        let secp = Secp256k1::new();
+        assert_eq!(
+            keys.public_key(),
+            keys.secret_key()?.x_only_public_key(&secp).0
+        );
+        let event = EventBuilder::new_text_note("hello", &[]).to_event(&keys)?;
+        secp.verify_schnorr(
+            &event.sig,
+            &Message::from_slice(event.id.as_ref())?,
+            &event.pubkey,
+        )?;
+
+        // Used from many threads at once
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                std::thread::spawn(move || {
+                    let keys = Keys::generate_from_os_random();
+                    let event = EventBuilder::new_text_note(format!("thread {}", i), &[])
+                        .to_event(&keys)
+                        .unwrap();
+                    event.verify().is_ok()
+                        && SECP256K1
+                            .verify_schnorr(
+                                &event.sig,
+                                &Message::from_slice(event.id.as_ref()).unwrap(),
+                                &keys.public_key(),
+                            )
+                            .is_ok()
+                })
+            })
+            .collect();
+        for handle in handles.into_iter() {
+            assert!(handle.join().unwrap());
+        }
+
+        Ok(())
+    }
+
     #[test]
     #[cfg(feature = "nip04")]
     fn test_encrypted_direct_msg() -> Result<()> {
@@ -95,4 +154,34 @@ mod tests {
 
         Ok(event.verify()?)
     }
+
+    #[test]
+    #[cfg(feature = "nip44")]
+    fn test_nip44_encrypted_direct_msg() -> Result<()> {
+        use crate::event::builder::EncryptionScheme;
+        use crate::util::nips::nip44;
+
+        let sender_keys = Keys::generate_from_os_random();
+        let receiver_keys = Keys::generate_from_os_random();
+
+        let content = "Mercury, the Winged Messenger";
+        let event = EventBuilder::new_encrypted_direct_msg_with_scheme(
+            &sender_keys,
+            &receiver_keys,
+            content,
+            EncryptionScheme::NIP44,
+        )?
+        .to_event(&sender_keys)?;
+
+        assert_eq!(
+            nip44::decrypt(
+                &receiver_keys.secret_key()?,
+                &sender_keys.public_key(),
+                &event.content
+            )?,
+            content
+        );
+
+        Ok(())
+    }
 }
@@ -2,8 +2,9 @@
 // Copyright (c) 2022 Yuki Kishimoto
 // Distributed under the MIT software license
 
-use serde_json::json;
+use serde_json::{json, Value};
 
+use super::MessageHandleError;
 use crate::{Event, SubscriptionFilter};
 
 /// Messages sent by clients, received by relays
@@ -19,6 +20,10 @@ pub enum ClientMessage {
     Close {
         subscription_id: String,
     },
+    /// NIP-42 authentication event
+    Auth {
+        event: Event,
+    },
 }
 
 impl ClientMessage {
@@ -37,6 +42,10 @@ impl ClientMessage {
         Self::Close { subscription_id }
     }
 
+    pub fn new_auth(event: Event) -> Self {
+        Self::Auth { event }
+    }
+
     pub fn to_json(&self) -> String {
         match self {
             Self::Event { event } => json!(["EVENT", event]).to_string(),
@@ -56,7 +65,75 @@ impl ClientMessage {
                 json.to_string()
             }
             Self::Close { subscription_id } => json!(["CLOSE", subscription_id]).to_string(),
+            Self::Auth { event } => json!(["AUTH", event]).to_string(),
+        }
+    }
+
+    pub fn from_json(msg: &str) -> Result<Self, MessageHandleError> {
+        let v: Vec<Value> =
+            serde_json::from_str(msg).map_err(|_| MessageHandleError::JsonDeserializationFailed)?;
+
+        if v.is_empty() {
+            return Err(MessageHandleError::InvalidMessageFormat);
+        }
+
+        // Event
+        // Client message format: ["EVENT", <event JSON>]
+        if v[0] == "EVENT" {
+            if v.len() != 2 {
+                return Err(MessageHandleError::InvalidMessageFormat);
+            }
+
+            let event = Event::from_json(v[1].to_string())
+                .map_err(|_| MessageHandleError::JsonDeserializationFailed)?;
+
+            return Ok(Self::new_event(event));
+        }
+
+        // Req
+        // Client message format: ["REQ", <subscription_id>, <filters JSON>...]
+        if v[0] == "REQ" {
+            if v.len() < 3 {
+                return Err(MessageHandleError::InvalidMessageFormat);
+            }
+
+            let subscription_id: String = serde_json::from_value(v[1].clone())
+                .map_err(|_| MessageHandleError::JsonDeserializationFailed)?;
+
+            let filters: Vec<SubscriptionFilter> =
+                serde_json::from_value(Value::Array(v[2..].to_vec()))
+                    .map_err(|_| MessageHandleError::JsonDeserializationFailed)?;
+
+            return Ok(Self::new_req(subscription_id, filters));
+        }
+
+        // Close
+        // Client message format: ["CLOSE", <subscription_id>]
+        if v[0] == "CLOSE" {
+            if v.len() != 2 {
+                return Err(MessageHandleError::InvalidMessageFormat);
+            }
+
+            let subscription_id: String = serde_json::from_value(v[1].clone())
+                .map_err(|_| MessageHandleError::JsonDeserializationFailed)?;
+
+            return Ok(Self::close(subscription_id));
         }
+
+        // Auth (NIP-42)
+        // Client message format: ["AUTH", <event JSON>]
+        if v[0] == "AUTH" {
+            if v.len() != 2 {
+                return Err(MessageHandleError::InvalidMessageFormat);
+            }
+
+            let event = Event::from_json(v[1].to_string())
+                .map_err(|_| MessageHandleError::JsonDeserializationFailed)?;
+
+            return Ok(Self::new_auth(event));
+        }
+
+        Err(MessageHandleError::InvalidMessageFormat)
     }
 }
 
@@ -68,7 +145,8 @@ mod tests {
 
     use bitcoin::secp256k1::XOnlyPublicKey;
 
-    use crate::{Kind, KindBase};
+    use crate::url::Url;
+    use crate::{EventBuilder, Keys, Kind, KindBase};
 
     #[test]
     fn test_client_message_req() {
@@ -105,4 +183,39 @@ mod tests {
             r##"["REQ","test",{"kinds":[22]},{"#p":["379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe"]}]"##
         );
     }
+
+    #[test]
+    fn test_client_message_from_json() {
+        let req = r##"["REQ","test",{"kinds":[4]},{"#p":["379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe"]}]"##;
+        let msg = ClientMessage::from_json(req).unwrap();
+        assert_eq!(msg.to_json(), req);
+
+        assert_eq!(
+            ClientMessage::from_json(r#"["CLOSE","test"]"#).unwrap(),
+            ClientMessage::close(String::from("test"))
+        );
+
+        assert_eq!(
+            ClientMessage::from_json(r#"["REQ","test"]"#).unwrap_err(),
+            MessageHandleError::InvalidMessageFormat
+        );
+        assert_eq!(
+            ClientMessage::from_json(r#"["AUTH"]"#).unwrap_err(),
+            MessageHandleError::InvalidMessageFormat
+        );
+    }
+
+    #[test]
+    fn test_client_message_auth() {
+        let keys = Keys::generate_from_os_random();
+        let relay_url = Url::parse("wss://relay.example.com").unwrap();
+        let event = EventBuilder::auth("challenge-string", relay_url)
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(event.kind, Kind::Base(KindBase::Authentication));
+
+        let msg = ClientMessage::new_auth(event);
+        assert!(msg.to_json().starts_with(r#"["AUTH",{"#));
+        assert_eq!(ClientMessage::from_json(&msg.to_json()).unwrap(), msg);
+    }
 }
@@ -6,5 +6,5 @@ pub mod relay;
 pub mod subscription;
 
 pub use self::client::ClientMessage;
-pub use self::relay::RelayMessage;
-pub use self::subscription::SubscriptionFilter;
+pub use self::relay::{MachineReadablePrefix, MessageHandleError, RelayMessage};
+pub use self::subscription::{HexPrefix, SubscriptionFilter};
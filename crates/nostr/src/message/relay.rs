@@ -2,6 +2,8 @@
 // Copyright (c) 2022 Yuki Kishimoto
 // Distributed under the MIT software license
 
+use std::fmt;
+
 use serde_json::{json, Value};
 
 use crate::{Event, Sha256Hash};
@@ -14,6 +16,54 @@ pub enum MessageHandleError {
     JsonDeserializationFailed,
 }
 
+/// Machine-readable prefix of `OK` and `CLOSED` messages
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/01.md>
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum MachineReadablePrefix {
+    Duplicate,
+    Pow,
+    Blocked,
+    RateLimited,
+    Invalid,
+    Error,
+    AuthRequired,
+    Restricted,
+}
+
+impl fmt::Display for MachineReadablePrefix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Duplicate => write!(f, "duplicate:"),
+            Self::Pow => write!(f, "pow:"),
+            Self::Blocked => write!(f, "blocked:"),
+            Self::RateLimited => write!(f, "rate-limited:"),
+            Self::Invalid => write!(f, "invalid:"),
+            Self::Error => write!(f, "error:"),
+            Self::AuthRequired => write!(f, "auth-required:"),
+            Self::Restricted => write!(f, "restricted:"),
+        }
+    }
+}
+
+impl MachineReadablePrefix {
+    /// Parse the prefix of an `OK` or `CLOSED` message
+    pub fn parse(message: &str) -> Option<Self> {
+        let (prefix, _) = message.split_once(':')?;
+        match prefix.trim() {
+            "duplicate" => Some(Self::Duplicate),
+            "pow" => Some(Self::Pow),
+            "blocked" => Some(Self::Blocked),
+            "rate-limited" => Some(Self::RateLimited),
+            "invalid" => Some(Self::Invalid),
+            "error" => Some(Self::Error),
+            "auth-required" => Some(Self::AuthRequired),
+            "restricted" => Some(Self::Restricted),
+            _ => None,
+        }
+    }
+}
+
 /// Messages sent by relays, received by clients
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum RelayMessage {
@@ -32,6 +82,14 @@ pub enum RelayMessage {
         status: bool,
         message: String,
     },
+    Closed {
+        subscription_id: String,
+        message: String,
+    },
+    /// NIP-42 authentication challenge
+    Auth {
+        challenge: String,
+    },
     Empty,
 }
 
@@ -60,6 +118,22 @@ impl RelayMessage {
         }
     }
 
+    pub fn new_closed(subscription_id: String, message: String) -> Self {
+        Self::Closed {
+            subscription_id,
+            message,
+        }
+    }
+
+    pub fn new_auth<S>(challenge: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::Auth {
+            challenge: challenge.into(),
+        }
+    }
+
     pub fn to_json(&self) -> String {
         match self {
             Self::Event {
@@ -75,6 +149,11 @@ impl RelayMessage {
                 status,
                 message,
             } => json!(["OK", event_id, status, message]).to_string(),
+            Self::Closed {
+                subscription_id,
+                message,
+            } => json!(["CLOSED", subscription_id, message]).to_string(),
+            Self::Auth { challenge } => json!(["AUTH", challenge]).to_string(),
             Self::Empty => String::new(),
         }
     }
@@ -145,6 +224,35 @@ impl RelayMessage {
             return Ok(Self::new_ok(event_id, status, message));
         }
 
+        // Closed
+        // Relay response format: ["CLOSED", <subscription_id>, <message>]
+        if v[0] == "CLOSED" {
+            if v.len() != 3 {
+                return Err(MessageHandleError::InvalidMessageFormat);
+            }
+
+            let subscription_id: String = serde_json::from_value(v[1].clone())
+                .map_err(|_| MessageHandleError::JsonDeserializationFailed)?;
+
+            let message: String = serde_json::from_value(v[2].clone())
+                .map_err(|_| MessageHandleError::JsonDeserializationFailed)?;
+
+            return Ok(Self::new_closed(subscription_id, message));
+        }
+
+        // Auth (NIP-42)
+        // Relay response format: ["AUTH", <challenge>]
+        if v[0] == "AUTH" {
+            if v.len() != 2 {
+                return Err(MessageHandleError::InvalidMessageFormat);
+            }
+
+            let challenge: String = serde_json::from_value(v[1].clone())
+                .map_err(|_| MessageHandleError::JsonDeserializationFailed)?;
+
+            return Ok(Self::new_auth(challenge));
+        }
+
         Err(MessageHandleError::InvalidMessageFormat)
     }
 }
@@ -300,4 +408,58 @@ mod tests {
             MessageHandleError::JsonDeserializationFailed
         );
     }
+
+    #[test]
+    fn test_handle_valid_closed() -> Result<()> {
+        let valid_closed_msg = r#"["CLOSED", "random-subscription-id", "rate-limited: slow down"]"#;
+        let handled_valid_closed_msg = RelayMessage::new_closed(
+            String::from("random-subscription-id"),
+            String::from("rate-limited: slow down"),
+        );
+
+        assert_eq!(
+            RelayMessage::from_json(valid_closed_msg)?,
+            handled_valid_closed_msg
+        );
+        assert_eq!(
+            RelayMessage::from_json(r#"["CLOSED", "random-subscription-id"]"#).unwrap_err(),
+            MessageHandleError::InvalidMessageFormat
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_handle_auth() -> Result<()> {
+        assert_eq!(
+            RelayMessage::from_json(r#"["AUTH", "challenge-string"]"#)?,
+            RelayMessage::new_auth("challenge-string")
+        );
+        assert_eq!(
+            RelayMessage::new_auth("challenge-string").to_json(),
+            r#"["AUTH","challenge-string"]"#
+        );
+        assert_eq!(
+            RelayMessage::from_json(r#"["AUTH"]"#).unwrap_err(),
+            MessageHandleError::InvalidMessageFormat
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_machine_readable_prefix() {
+        assert_eq!(
+            MachineReadablePrefix::parse("rate-limited: slow down"),
+            Some(MachineReadablePrefix::RateLimited)
+        );
+        assert_eq!(
+            MachineReadablePrefix::parse(
+                "auth-required: we only accept events from registered users"
+            ),
+            Some(MachineReadablePrefix::AuthRequired)
+        );
+        assert_eq!(MachineReadablePrefix::parse("hello"), None);
+        assert_eq!(MachineReadablePrefix::parse("unknown: prefix"), None);
+    }
 }
@@ -2,16 +2,94 @@
 // Copyright (c) 2022 Yuki Kishimoto
 // Distributed under the MIT software license
 
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
 use bitcoin::secp256k1::XOnlyPublicKey;
+use serde::de::{Deserialize, Deserializer};
+use serde::ser::{SerializeMap, Serializer};
+use serde_json::Value;
+
+use crate::event::TagKind;
+use crate::util::nips::nip19::Coordinate;
+use crate::{Event, Kind, Sha256Hash};
+
+#[derive(Debug, Eq, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// Not hex or longer than 64 chars
+    #[error("invalid hex prefix")]
+    InvalidHexPrefix,
+}
+
+/// Hex prefix of an event id or a public key (full values are prefixes too)
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/01.md>
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct HexPrefix(String);
+
+impl HexPrefix {
+    /// Check if `hex` starts with the prefix
+    pub fn matches(&self, hex: &str) -> bool {
+        hex.starts_with(&self.0)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for HexPrefix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for HexPrefix {
+    type Err = Error;
 
-use crate::{Kind, Sha256Hash};
+    fn from_str(prefix: &str) -> Result<Self, Self::Err> {
+        if prefix.is_empty() || prefix.len() > 64 || !prefix.chars().all(|c| c.is_ascii_hexdigit())
+        {
+            return Err(Error::InvalidHexPrefix);
+        }
+        Ok(Self(prefix.to_lowercase()))
+    }
+}
+
+impl TryFrom<String> for HexPrefix {
+    type Error = Error;
+
+    fn try_from(prefix: String) -> Result<Self, Self::Error> {
+        Self::from_str(&prefix)
+    }
+}
+
+impl From<HexPrefix> for String {
+    fn from(prefix: HexPrefix) -> Self {
+        prefix.0
+    }
+}
+
+impl From<Sha256Hash> for HexPrefix {
+    fn from(id: Sha256Hash) -> Self {
+        Self(id.to_string())
+    }
+}
+
+impl From<XOnlyPublicKey> for HexPrefix {
+    fn from(public_key: XOnlyPublicKey) -> Self {
+        Self(public_key.to_string())
+    }
+}
 
 #[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
 pub struct SubscriptionFilter {
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub ids: Option<Vec<String>>,
+    pub ids: Option<Vec<HexPrefix>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub authors: Option<Vec<XOnlyPublicKey>>,
+    pub authors: Option<Vec<HexPrefix>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub kinds: Option<Vec<Kind>>,
     #[serde(rename = "#e")]
@@ -20,12 +98,60 @@ pub struct SubscriptionFilter {
     #[serde(rename = "#p")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub pubkeys: Option<Vec<XOnlyPublicKey>>,
+    #[serde(rename = "#d")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identifiers: Option<Vec<String>>,
+    /// Full-text search query (NIP-50)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub since: Option<u64>, // unix timestamp seconds
     #[serde(skip_serializing_if = "Option::is_none")]
     pub until: Option<u64>, // unix timestamp seconds
     #[serde(skip_serializing_if = "Option::is_none")]
     pub limit: Option<u16>,
+    /// Other single-letter tag queries (`#t`, `#r`, `#a`, ...)
+    #[serde(
+        flatten,
+        serialize_with = "serialize_generic_tags",
+        deserialize_with = "deserialize_generic_tags"
+    )]
+    pub generic_tags: BTreeMap<char, Vec<String>>,
+}
+
+fn serialize_generic_tags<S>(
+    generic_tags: &BTreeMap<char, Vec<String>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let mut map = serializer.serialize_map(Some(generic_tags.len()))?;
+    for (tag, values) in generic_tags.iter() {
+        map.serialize_entry(&format!("#{}", tag), values)?;
+    }
+    map.end()
+}
+
+fn deserialize_generic_tags<'de, D>(
+    deserializer: D,
+) -> Result<BTreeMap<char, Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let map = BTreeMap::<String, Value>::deserialize(deserializer)?;
+    Ok(map
+        .into_iter()
+        .filter_map(|(key, value)| {
+            let mut chars = key.strip_prefix('#')?.chars();
+            match (chars.next(), chars.next()) {
+                (Some(tag), None) if tag.is_ascii_alphabetic() => {
+                    Some((tag, serde_json::from_value(value).ok()?))
+                }
+                _ => None,
+            }
+        })
+        .collect())
 }
 
 impl Default for SubscriptionFilter {
@@ -41,41 +167,56 @@ impl SubscriptionFilter {
             kinds: None,
             events: None,
             pubkeys: None,
+            identifiers: None,
+            search: None,
             since: None,
             until: None,
             authors: None,
             limit: None,
+            generic_tags: BTreeMap::new(),
         }
     }
 
-    /// Set subscription id
-    pub fn id(self, id: impl Into<String>) -> Self {
+    /// Set event id (or id prefix)
+    pub fn id<T>(self, id: T) -> Self
+    where
+        T: Into<HexPrefix>,
+    {
         Self {
             ids: Some(vec![id.into()]),
             ..self
         }
     }
 
-    /// Set subscription ids
-    pub fn ids(self, ids: impl Into<Vec<String>>) -> Self {
+    /// Set event ids (or id prefixes)
+    pub fn ids<T>(self, ids: Vec<T>) -> Self
+    where
+        T: Into<HexPrefix>,
+    {
         Self {
-            ids: Some(ids.into()),
+            ids: Some(ids.into_iter().map(|id| id.into()).collect()),
             ..self
         }
     }
 
-    /// Set author
-    pub fn author(self, author: XOnlyPublicKey) -> Self {
+    /// Set author (or public key prefix)
+    pub fn author<T>(self, author: T) -> Self
+    where
+        T: Into<HexPrefix>,
+    {
         Self {
-            authors: Some(vec![author]),
+            authors: Some(vec![author.into()]),
             ..self
         }
     }
 
-    /// Set authors
-    pub fn authors(self, authors: Vec<XOnlyPublicKey>) -> Self {
+    /// Set authors (or public key prefixes)
+    pub fn authors<T>(self, authors: Vec<T>) -> Self
+    where
+        T: Into<HexPrefix>,
+    {
         Self {
-            authors: Some(authors),
+            authors: Some(authors.into_iter().map(|a| a.into()).collect()),
             ..self
         }
     }
@@ -128,6 +269,105 @@ impl SubscriptionFilter {
         }
     }
 
+    /// Set identifier (`#d`) of parameterized replaceable events
+    pub fn identifier<S>(self, identifier: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            identifiers: Some(vec![identifier.into()]),
+            ..self
+        }
+    }
+
+    /// Set identifiers (`#d`) of parameterized replaceable events
+    pub fn identifiers<S>(self, identifiers: Vec<S>) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            identifiers: Some(identifiers.into_iter().map(|i| i.into()).collect()),
+            ..self
+        }
+    }
+
+    /// Set kind, author and identifier of a parameterized replaceable event
+    pub fn coordinate(self, coordinate: &Coordinate) -> Self {
+        self.kind(coordinate.kind)
+            .author(coordinate.public_key)
+            .identifier(coordinate.identifier.clone())
+    }
+
+    /// Add a single-letter tag query (ex. `#t`), matching events with any of `values`
+    ///
+    /// `e`, `p` and `d` are added to [`Self::events`], [`Self::pubkeys`] and [`Self::identifiers`]
+    /// (invalid event ids and public keys are skipped). Tags that aren't ASCII letters are ignored.
+    pub fn custom_tag<S>(self, tag: char, values: Vec<S>) -> Self
+    where
+        S: Into<String>,
+    {
+        let values: Vec<String> = values.into_iter().map(|v| v.into()).collect();
+        match tag {
+            'e' => {
+                let ids = values.iter().filter_map(|v| Sha256Hash::from_str(v).ok());
+                Self {
+                    events: Some(
+                        self.events
+                            .unwrap_or_default()
+                            .into_iter()
+                            .chain(ids)
+                            .collect(),
+                    ),
+                    ..self
+                }
+            }
+            'p' => {
+                let pubkeys = values
+                    .iter()
+                    .filter_map(|v| XOnlyPublicKey::from_str(v).ok());
+                Self {
+                    pubkeys: Some(
+                        self.pubkeys
+                            .unwrap_or_default()
+                            .into_iter()
+                            .chain(pubkeys)
+                            .collect(),
+                    ),
+                    ..self
+                }
+            }
+            'd' => Self {
+                identifiers: Some([self.identifiers.unwrap_or_default(), values].concat()),
+                ..self
+            },
+            tag if tag.is_ascii_alphabetic() => {
+                let mut generic_tags = self.generic_tags;
+                generic_tags.entry(tag).or_default().extend(values);
+                Self {
+                    generic_tags,
+                    ..self
+                }
+            }
+            tag => {
+                log::warn!("Invalid tag query: {}", tag);
+                self
+            }
+        }
+    }
+
+    /// Set full-text search query
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/50.md>
+    pub fn search<S>(self, value: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            search: Some(value.into()),
+            ..self
+        }
+    }
+
     /// Set since unix timestamp
     pub fn since(self, since: u64) -> Self {
         Self {
@@ -144,11 +384,282 @@ impl SubscriptionFilter {
         }
     }
 
-    /// Set limit
+    /// Set max number of stored events returned by the relay in the initial query
     pub fn limit(self, limit: u16) -> Self {
         Self {
             limit: Some(limit),
             ..self
         }
     }
+
+    /// Check if the event matches the filter (`limit` is ignored)
+    ///
+    /// `ids` and `authors` are matched as prefixes. `search` matches if the content contains
+    /// every term of the query (case-insensitive); `key:value` extensions are ignored.
+    pub fn match_event(&self, event: &Event) -> bool {
+        if let Some(ids) = &self.ids {
+            let id = event.id.to_string();
+            if !ids.iter().any(|prefix| prefix.matches(&id)) {
+                return false;
+            }
+        }
+
+        if let Some(authors) = &self.authors {
+            let pubkey = event.pubkey.to_string();
+            if !authors.iter().any(|prefix| prefix.matches(&pubkey)) {
+                return false;
+            }
+        }
+
+        if let Some(kinds) = &self.kinds {
+            if !kinds.contains(&event.kind) {
+                return false;
+            }
+        }
+
+        if let Some(events) = &self.events {
+            let matched = event.tags.iter().any(|t| {
+                matches!(t.kind(), Ok(TagKind::E))
+                    && t.content()
+                        .map(|id| events.iter().any(|e| e.to_string() == id))
+                        .unwrap_or(false)
+            });
+            if !matched {
+                return false;
+            }
+        }
+
+        if let Some(pubkeys) = &self.pubkeys {
+            let matched = event.tags.iter().any(|t| {
+                matches!(t.kind(), Ok(TagKind::P))
+                    && t.content()
+                        .map(|pk| pubkeys.iter().any(|p| p.to_string() == pk))
+                        .unwrap_or(false)
+            });
+            if !matched {
+                return false;
+            }
+        }
+
+        if let Some(identifiers) = &self.identifiers {
+            match event.identifier() {
+                Some(identifier) if identifiers.iter().any(|i| i == identifier) => (),
+                _ => return false,
+            }
+        }
+
+        for (tag, values) in self.generic_tags.iter() {
+            let tag = tag.to_string();
+            let matched = event.tags.iter().any(|t| {
+                let t = t.as_vec();
+                t.first() == Some(&tag) && t.get(1).map(|v| values.contains(v)).unwrap_or(false)
+            });
+            if !matched {
+                return false;
+            }
+        }
+
+        if let Some(search) = &self.search {
+            let content = event.content.to_lowercase();
+            if !search
+                .split_whitespace()
+                .filter(|term| !term.contains(':'))
+                .all(|term| content.contains(&term.to_lowercase()))
+            {
+                return false;
+            }
+        }
+
+        if let Some(since) = self.since {
+            if event.created_at < since {
+                return false;
+            }
+        }
+
+        if let Some(until) = self.until {
+            if event.created_at > until {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::TagData;
+    use crate::{EventBuilder, Keys, KindBase, Result, Tag};
+
+    #[test]
+    fn test_match_event() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        let other = Keys::generate_from_os_random();
+        let event =
+            EventBuilder::new_text_note("Hello", &[Tag::new(TagData::PubKey(other.public_key()))])
+                .to_event(&keys)?;
+
+        assert!(SubscriptionFilter::new().match_event(&event));
+        assert!(SubscriptionFilter::new()
+            .author(keys.public_key())
+            .kind(Kind::Base(KindBase::TextNote))
+            .pubkey(other.public_key())
+            .since(event.created_at)
+            .match_event(&event));
+        assert!(SubscriptionFilter::new()
+            .id(HexPrefix::from_str(&event.id.to_string()[..8])?)
+            .match_event(&event));
+
+        assert!(!SubscriptionFilter::new()
+            .author(other.public_key())
+            .match_event(&event));
+        assert!(!SubscriptionFilter::new()
+            .kind(Kind::Base(KindBase::Metadata))
+            .match_event(&event));
+        assert!(!SubscriptionFilter::new()
+            .pubkey(keys.public_key())
+            .match_event(&event));
+        assert!(!SubscriptionFilter::new()
+            .event(event.id)
+            .match_event(&event));
+        assert!(!SubscriptionFilter::new()
+            .until(event.created_at - 1)
+            .match_event(&event));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_identifier() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        let event = EventBuilder::new(Kind::Custom(30001), "", &[])
+            .identifier("bookmarks")
+            .to_event(&keys)?;
+
+        assert_eq!(event.identifier(), Some("bookmarks"));
+        let coordinate = event.coordinate().unwrap();
+        assert!(SubscriptionFilter::new()
+            .coordinate(&coordinate)
+            .match_event(&event));
+        assert!(!SubscriptionFilter::new()
+            .identifier("mute")
+            .match_event(&event));
+        assert_eq!(
+            serde_json::to_string(&SubscriptionFilter::new().identifier("bookmarks"))?,
+            r##"{"#d":["bookmarks"]}"##
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_search() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        let event = EventBuilder::new_text_note("Hello Nostr world", &[]).to_event(&keys)?;
+
+        assert!(SubscriptionFilter::new()
+            .search("nostr hello")
+            .match_event(&event));
+        assert!(SubscriptionFilter::new()
+            .search("nostr language:en")
+            .match_event(&event));
+        assert!(!SubscriptionFilter::new()
+            .search("bitcoin")
+            .match_event(&event));
+        assert_eq!(
+            serde_json::to_string(
+                &SubscriptionFilter::new()
+                    .kind(Kind::Base(KindBase::TextNote))
+                    .search("nostr")
+            )?,
+            r#"{"kinds":[1],"search":"nostr"}"#
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_custom_tag() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        let event = EventBuilder::new_text_note(
+            "#nostr",
+            &[Tag::from(vec![String::from("t"), String::from("nostr")])],
+        )
+        .to_event(&keys)?;
+
+        let filter = SubscriptionFilter::new()
+            .custom_tag('t', vec!["bitcoin", "nostr"])
+            .custom_tag('d', vec!["bookmarks"]);
+        assert_eq!(
+            serde_json::to_string(&filter)?,
+            r##"{"#d":["bookmarks"],"#t":["bitcoin","nostr"]}"##
+        );
+        let json = r##"{"#t":["bitcoin","nostr"],"#d":["bookmarks"],"#invalid":["x"],"#r":"x"}"##;
+        assert_eq!(serde_json::from_str::<SubscriptionFilter>(json)?, filter);
+
+        assert!(SubscriptionFilter::new()
+            .custom_tag('t', vec!["nostr"])
+            .match_event(&event));
+        assert!(!SubscriptionFilter::new()
+            .custom_tag('t', vec!["bitcoin"])
+            .match_event(&event));
+        assert!(SubscriptionFilter::new()
+            .custom_tag(
+                'p',
+                vec![keys.public_key().to_string(), String::from("invalid")]
+            )
+            .pubkeys
+            .is_some());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_limit() -> Result<()> {
+        let filter = SubscriptionFilter::new()
+            .kind(Kind::Base(KindBase::TextNote))
+            .limit(10);
+        assert_eq!(
+            serde_json::to_string(&filter)?,
+            r#"{"kinds":[1],"limit":10}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<SubscriptionFilter>(r#"{"kinds":[1],"limit":10}"#)?,
+            filter
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_hex_prefix() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        let other = Keys::generate_from_os_random();
+        let event = EventBuilder::new_text_note("Hello", &[]).to_event(&keys)?;
+
+        let pubkey = keys.public_key().to_string();
+        let filter = SubscriptionFilter::new().authors(vec![
+            HexPrefix::from(other.public_key()),
+            HexPrefix::from_str(&pubkey[..5].to_uppercase())?,
+        ]);
+        assert!(filter.match_event(&event));
+        assert_eq!(
+            serde_json::to_string(&filter)?,
+            format!(
+                r#"{{"authors":["{}","{}"]}}"#,
+                other.public_key(),
+                &pubkey[..5]
+            )
+        );
+
+        assert_eq!(HexPrefix::from_str(""), Err(Error::InvalidHexPrefix));
+        assert_eq!(HexPrefix::from_str("xyz"), Err(Error::InvalidHexPrefix));
+        assert_eq!(
+            HexPrefix::from_str(&"0".repeat(65)),
+            Err(Error::InvalidHexPrefix)
+        );
+        assert!(serde_json::from_str::<SubscriptionFilter>(r#"{"ids":["xyz"]}"#).is_err());
+
+        Ok(())
+    }
 }
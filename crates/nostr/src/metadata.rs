@@ -1,16 +1,26 @@
 // Copyright (c) 2022 Yuki Kishimoto
 // Distributed under the MIT software license
 
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use serde_json::Value;
 use url::Url;
 
+use crate::util::nips::nip39::Identity;
+use crate::{Event, Kind, KindBase};
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     /// Error serializing or deserializing JSON data
     #[error("json error: {0}")]
     Json(#[from] serde_json::Error),
+    /// Invalid event kind
+    #[error("invalid event kind")]
+    InvalidKind,
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Metadata {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
@@ -28,6 +38,52 @@ pub struct Metadata {
     pub lud06: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub lud16: Option<String>,
+    /// Fields not handled by this struct, preserved as they are
+    #[serde(flatten)]
+    pub custom: BTreeMap<String, Value>,
+    /// External identities (NIP-39), published as `i` tags of the event
+    #[serde(skip)]
+    pub identities: Vec<Identity>,
+}
+
+impl PartialOrd for Metadata {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Metadata {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // `serde_json::Value` isn't `Ord`: custom fields are compared by their JSON
+        let custom = |m: &Self| -> Vec<(String, String)> {
+            m.custom
+                .iter()
+                .map(|(k, v)| (k.clone(), v.to_string()))
+                .collect()
+        };
+        (
+            &self.name,
+            &self.display_name,
+            &self.about,
+            &self.website,
+            &self.picture,
+            &self.nip05,
+            &self.lud06,
+            &self.lud16,
+        )
+            .cmp(&(
+                &other.name,
+                &other.display_name,
+                &other.about,
+                &other.website,
+                &other.picture,
+                &other.nip05,
+                &other.lud06,
+                &other.lud16,
+            ))
+            .then_with(|| custom(self).cmp(&custom(other)))
+            .then_with(|| self.identities.cmp(&other.identities))
+    }
 }
 
 impl Default for Metadata {
@@ -47,6 +103,8 @@ impl Metadata {
             nip05: None,
             lud06: None,
             lud16: None,
+            custom: BTreeMap::new(),
+            identities: Vec::new(),
         }
     }
 
@@ -61,6 +119,21 @@ impl Metadata {
         Ok(serde_json::to_string(&self)?)
     }
 
+    /// Parse metadata and external identities (NIP-39) from a kind 0 event
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        if event.kind != Kind::Base(KindBase::Metadata) {
+            return Err(Error::InvalidKind);
+        }
+
+        let mut metadata = Self::from_json(&event.content)?;
+        metadata.identities = event
+            .tags
+            .iter()
+            .filter_map(|t| Identity::from_tag(t).ok())
+            .collect();
+        Ok(metadata)
+    }
+
     /// Set name
     pub fn name<S>(self, name: S) -> Self
     where
@@ -142,6 +215,21 @@ impl Metadata {
             ..self
         }
     }
+
+    /// Add external identity (NIP-39)
+    pub fn identity(mut self, identity: Identity) -> Self {
+        self.identities.push(identity);
+        self
+    }
+
+    /// Set custom field
+    pub fn custom_field<S>(mut self, key: S, value: Value) -> Self
+    where
+        S: Into<String>,
+    {
+        self.custom.insert(key.into(), value);
+        self
+    }
 }
 
 #[cfg(test)]
@@ -160,4 +248,38 @@ mod tests {
                 .display_name("")
         );
     }
+
+    #[test]
+    fn test_preserve_custom_fields() {
+        let content = r#"{"name":"myname","banner":"https://example.com/banner.png","bot":false}"#;
+        let metadata = Metadata::from_json(content).unwrap();
+        assert_eq!(
+            metadata,
+            Metadata::new()
+                .name("myname")
+                .custom_field("banner", Value::from("https://example.com/banner.png"))
+                .custom_field("bot", Value::from(false))
+        );
+
+        let metadata = metadata.about("Description");
+        assert_eq!(
+            metadata.as_json().unwrap(),
+            r#"{"name":"myname","about":"Description","banner":"https://example.com/banner.png","bot":false}"#
+        );
+    }
+
+    #[test]
+    fn test_ord() {
+        let a = Metadata::new().name("a");
+        let b = Metadata::new().name("b");
+        assert!(a < b);
+        assert!(a.clone().custom_field("bot", Value::from(false)) > a);
+
+        let set: std::collections::BTreeSet<Metadata> =
+            vec![b.clone(), a.clone(), b].into_iter().collect();
+        assert_eq!(
+            set.into_iter().collect::<Vec<_>>(),
+            vec![a, Metadata::new().name("b")]
+        );
+    }
 }
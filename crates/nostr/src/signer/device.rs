@@ -0,0 +1,314 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use bitcoin::secp256k1::schnorr::Signature;
+use bitcoin::secp256k1::{Message, XOnlyPublicKey};
+
+use super::{Error, NostrSigner, SignerFuture};
+use crate::key::Keys;
+#[cfg(feature = "nip04")]
+use crate::util::nips::nip04;
+use crate::{Event, Sha256Hash, UnsignedEvent, SECP256K1};
+
+/// Request sent to the device
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "method", content = "params", rename_all = "snake_case")]
+pub enum DeviceRequest {
+    GetPublicKey,
+    /// Sign the event id (BIP-340 schnorr signature)
+    SignSchnorr(Sha256Hash),
+    Nip04Encrypt {
+        public_key: XOnlyPublicKey,
+        text: String,
+    },
+    Nip04Decrypt {
+        public_key: XOnlyPublicKey,
+        ciphertext: String,
+    },
+}
+
+/// Response of the device
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "result", content = "value", rename_all = "snake_case")]
+pub enum DeviceResponse {
+    PublicKey(XOnlyPublicKey),
+    Signature(Signature),
+    /// NIP-04 encrypted or decrypted text
+    Text(String),
+    /// The user rejected the request on the device
+    Rejected,
+    /// Device error
+    Error(String),
+}
+
+impl DeviceRequest {
+    /// Deserialize `DeviceRequest` from JSON string
+    pub fn from_json<S>(json: S) -> Result<Self, serde_json::Error>
+    where
+        S: Into<String>,
+    {
+        serde_json::from_str(&json.into())
+    }
+
+    /// Serialize `DeviceRequest` as JSON string (i.e. to write it on a serial port)
+    pub fn as_json(&self) -> String {
+        serde_json::json!(self).to_string()
+    }
+}
+
+impl DeviceResponse {
+    /// Deserialize `DeviceResponse` from JSON string
+    pub fn from_json<S>(json: S) -> Result<Self, serde_json::Error>
+    where
+        S: Into<String>,
+    {
+        serde_json::from_str(&json.into())
+    }
+
+    /// Serialize `DeviceResponse` as JSON string
+    pub fn as_json(&self) -> String {
+        serde_json::json!(self).to_string()
+    }
+}
+
+type DeviceFuture = Pin<Box<dyn Future<Output = Result<DeviceResponse, String>> + Send>>;
+
+/// Transport to the device (serial port, HID, ...)
+///
+/// The callback delivers the request to the device and waits for its response.
+#[derive(Clone)]
+pub struct DeviceTransport {
+    func: Arc<dyn Fn(DeviceRequest) -> DeviceFuture + Send + Sync>,
+}
+
+impl fmt::Debug for DeviceTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeviceTransport").finish()
+    }
+}
+
+impl DeviceTransport {
+    /// New `DeviceTransport`
+    ///
+    /// The callback returns an error if the device can't be reached.
+    pub fn new<F, Fut>(func: F) -> Self
+    where
+        F: Fn(DeviceRequest) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<DeviceResponse, String>> + Send + 'static,
+    {
+        Self {
+            func: Arc::new(move |req| Box::pin(func(req))),
+        }
+    }
+
+    async fn send(&self, req: DeviceRequest) -> Result<DeviceResponse, Error> {
+        match (self.func)(req).await.map_err(Error::Backend)? {
+            DeviceResponse::Rejected => Err(Error::Rejected),
+            DeviceResponse::Error(e) => Err(Error::Backend(e)),
+            res => Ok(res),
+        }
+    }
+}
+
+/// Signer that routes the requests to an external device (i.e. hardware signer)
+///
+/// The secret key never leaves the device: events are signed by sending their id.
+#[derive(Debug, Clone)]
+pub struct DeviceSigner {
+    transport: DeviceTransport,
+    public_key: Arc<Mutex<Option<XOnlyPublicKey>>>,
+}
+
+impl DeviceSigner {
+    /// New `DeviceSigner`
+    pub fn new(transport: DeviceTransport) -> Self {
+        Self {
+            transport,
+            public_key: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    fn cached_public_key(&self) -> Option<XOnlyPublicKey> {
+        self.public_key.lock().ok().and_then(|pk| *pk)
+    }
+
+    async fn get_public_key(&self) -> Result<XOnlyPublicKey, Error> {
+        if let Some(public_key) = self.cached_public_key() {
+            return Ok(public_key);
+        }
+
+        match self.transport.send(DeviceRequest::GetPublicKey).await? {
+            DeviceResponse::PublicKey(public_key) => {
+                if let Ok(mut pk) = self.public_key.lock() {
+                    *pk = Some(public_key);
+                }
+                Ok(public_key)
+            }
+            res => Err(Error::Backend(format!("unexpected response: {:?}", res))),
+        }
+    }
+
+    async fn sign(&self, unsigned: UnsignedEvent) -> Result<Event, Error> {
+        if unsigned.pubkey != self.get_public_key().await? {
+            return Err(Error::Unsigned(
+                crate::event::unsigned::Error::PublicKeyMismatch,
+            ));
+        }
+
+        match self
+            .transport
+            .send(DeviceRequest::SignSchnorr(unsigned.id))
+            .await?
+        {
+            DeviceResponse::Signature(sig) => Ok(unsigned.add_signature(sig)?),
+            res => Err(Error::Backend(format!("unexpected response: {:?}", res))),
+        }
+    }
+
+    async fn text(&self, req: DeviceRequest) -> Result<String, Error> {
+        match self.transport.send(req).await? {
+            DeviceResponse::Text(text) => Ok(text),
+            res => Err(Error::Backend(format!("unexpected response: {:?}", res))),
+        }
+    }
+}
+
+impl NostrSigner for DeviceSigner {
+    fn public_key(&self) -> SignerFuture<'_, XOnlyPublicKey> {
+        Box::pin(self.get_public_key())
+    }
+
+    fn sign_event(&self, unsigned: UnsignedEvent) -> SignerFuture<'_, Event> {
+        Box::pin(self.sign(unsigned))
+    }
+
+    fn nip04_encrypt(&self, public_key: XOnlyPublicKey, text: String) -> SignerFuture<'_, String> {
+        Box::pin(self.text(DeviceRequest::Nip04Encrypt { public_key, text }))
+    }
+
+    fn nip04_decrypt(
+        &self,
+        public_key: XOnlyPublicKey,
+        ciphertext: String,
+    ) -> SignerFuture<'_, String> {
+        Box::pin(self.text(DeviceRequest::Nip04Decrypt {
+            public_key,
+            ciphertext,
+        }))
+    }
+}
+
+/// Reference software device, backed by [`Keys`]
+///
+/// Useful for tests and device emulators.
+#[derive(Debug, Clone)]
+pub struct SoftwareDevice {
+    keys: Keys,
+}
+
+impl SoftwareDevice {
+    /// New `SoftwareDevice`
+    pub fn new(keys: Keys) -> Self {
+        Self { keys }
+    }
+
+    /// Handle a request
+    pub fn handle(&self, req: DeviceRequest) -> DeviceResponse {
+        let result: Result<DeviceResponse, Error> = match req {
+            DeviceRequest::GetPublicKey => Ok(DeviceResponse::PublicKey(self.keys.public_key())),
+            DeviceRequest::SignSchnorr(id) => self.sign_schnorr(id),
+            #[cfg(feature = "nip04")]
+            DeviceRequest::Nip04Encrypt { public_key, text } => self
+                .keys
+                .secret_key()
+                .map_err(Error::from)
+                .and_then(|sk| Ok(nip04::encrypt(&sk, &public_key, text)?))
+                .map(DeviceResponse::Text),
+            #[cfg(feature = "nip04")]
+            DeviceRequest::Nip04Decrypt {
+                public_key,
+                ciphertext,
+            } => self
+                .keys
+                .secret_key()
+                .map_err(Error::from)
+                .and_then(|sk| Ok(nip04::decrypt(&sk, &public_key, ciphertext)?))
+                .map(DeviceResponse::Text),
+            #[cfg(not(feature = "nip04"))]
+            DeviceRequest::Nip04Encrypt { .. } | DeviceRequest::Nip04Decrypt { .. } => {
+                Err(Error::Unsupported)
+            }
+        };
+        result.unwrap_or_else(|e| DeviceResponse::Error(e.to_string()))
+    }
+
+    fn sign_schnorr(&self, id: Sha256Hash) -> Result<DeviceResponse, Error> {
+        let message = Message::from_slice(&id).map_err(|e| Error::Backend(e.to_string()))?;
+        Ok(DeviceResponse::Signature(
+            SECP256K1.sign_schnorr(&message, &self.keys.key_pair()?),
+        ))
+    }
+
+    /// Get a [`DeviceTransport`] that delivers the requests to this device
+    pub fn transport(self) -> DeviceTransport {
+        DeviceTransport::new(move |req| {
+            let res = self.handle(req);
+            async move { Ok(res) }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signer::tests::block_on;
+    use crate::{EventBuilder, Result};
+
+    #[test]
+    fn test_device_signer() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        let signer = DeviceSigner::new(SoftwareDevice::new(keys.clone()).transport());
+
+        assert_eq!(
+            block_on(NostrSigner::public_key(&signer))?,
+            keys.public_key()
+        );
+
+        let event = block_on(EventBuilder::new_text_note("Signed on device", &[]).sign(&signer))?;
+        assert_eq!(event.pubkey, keys.public_key());
+        assert!(event.verify().is_ok());
+
+        let other = Keys::generate_from_os_random();
+        let unsigned =
+            EventBuilder::new_text_note("Wrong author", &[]).to_unsigned_event(other.public_key());
+        assert!(block_on(signer.sign_event(unsigned)).is_err());
+
+        #[cfg(feature = "nip04")]
+        {
+            let ciphertext =
+                block_on(signer.nip04_encrypt(other.public_key(), String::from("Hi")))?;
+            let text = block_on(other.nip04_decrypt(keys.public_key(), ciphertext))?;
+            assert_eq!(text, "Hi");
+        }
+
+        let rejecting = DeviceSigner::new(DeviceTransport::new(|_| async {
+            Ok(DeviceResponse::Rejected)
+        }));
+        assert!(matches!(
+            block_on(NostrSigner::public_key(&rejecting)),
+            Err(Error::Rejected)
+        ));
+
+        let req = DeviceRequest::SignSchnorr(event.id);
+        assert_eq!(DeviceRequest::from_json(req.as_json())?, req);
+        let res = DeviceResponse::Signature(event.sig);
+        assert_eq!(DeviceResponse::from_json(res.as_json())?, res);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,169 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::fmt;
+use std::future::{self, Future};
+use std::pin::Pin;
+
+use bitcoin::secp256k1::XOnlyPublicKey;
+
+use crate::event::unsigned;
+use crate::key::{self, Keys};
+#[cfg(feature = "nip04")]
+use crate::util::nips::nip04;
+use crate::{Event, EventBuilder, UnsignedEvent};
+
+pub mod device;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Key error
+    #[error("key error: {0}")]
+    Key(#[from] key::Error),
+    /// Unsigned event error
+    #[error("unsigned event error: {0}")]
+    Unsigned(#[from] unsigned::Error),
+    /// NIP04 error
+    #[cfg(feature = "nip04")]
+    #[error("nip04 error: {0}")]
+    NIP04(#[from] nip04::Error),
+    /// Error of the signer backend (i.e. NIP-46 timeout, device disconnected)
+    #[error("signer error: {0}")]
+    Backend(String),
+    /// Operation not supported by the signer
+    #[error("operation not supported by the signer")]
+    Unsupported,
+    /// The request was rejected by the user
+    #[error("rejected by the user")]
+    Rejected,
+}
+
+/// Future returned by the [`NostrSigner`] methods
+pub type SignerFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, Error>> + Send + 'a>>;
+
+/// Signer of the user events
+///
+/// Implemented by [`Keys`] and by external signers (i.e. NIP-46 remote signers, hardware devices),
+/// so they can be used interchangeably.
+pub trait NostrSigner: fmt::Debug + Send + Sync {
+    /// Get the public key of the user
+    fn public_key(&self) -> SignerFuture<'_, XOnlyPublicKey>;
+
+    /// Sign an [`UnsignedEvent`] of the user
+    fn sign_event(&self, unsigned: UnsignedEvent) -> SignerFuture<'_, Event>;
+
+    /// Encrypt text for `public_key` (NIP-04)
+    fn nip04_encrypt(&self, public_key: XOnlyPublicKey, text: String) -> SignerFuture<'_, String>;
+
+    /// Decrypt content received from `public_key` (NIP-04)
+    fn nip04_decrypt(
+        &self,
+        public_key: XOnlyPublicKey,
+        ciphertext: String,
+    ) -> SignerFuture<'_, String>;
+}
+
+impl NostrSigner for Keys {
+    fn public_key(&self) -> SignerFuture<'_, XOnlyPublicKey> {
+        Box::pin(future::ready(Ok(Keys::public_key(self))))
+    }
+
+    fn sign_event(&self, unsigned: UnsignedEvent) -> SignerFuture<'_, Event> {
+        Box::pin(future::ready(unsigned.sign(self).map_err(Error::from)))
+    }
+
+    #[cfg(feature = "nip04")]
+    fn nip04_encrypt(&self, public_key: XOnlyPublicKey, text: String) -> SignerFuture<'_, String> {
+        let result = self
+            .secret_key()
+            .map_err(Error::from)
+            .and_then(|sk| Ok(nip04::encrypt(&sk, &public_key, text)?));
+        Box::pin(future::ready(result))
+    }
+
+    #[cfg(not(feature = "nip04"))]
+    fn nip04_encrypt(&self, _: XOnlyPublicKey, _: String) -> SignerFuture<'_, String> {
+        Box::pin(future::ready(Err(Error::Unsupported)))
+    }
+
+    #[cfg(feature = "nip04")]
+    fn nip04_decrypt(
+        &self,
+        public_key: XOnlyPublicKey,
+        ciphertext: String,
+    ) -> SignerFuture<'_, String> {
+        let result = self
+            .secret_key()
+            .map_err(Error::from)
+            .and_then(|sk| Ok(nip04::decrypt(&sk, &public_key, ciphertext)?));
+        Box::pin(future::ready(result))
+    }
+
+    #[cfg(not(feature = "nip04"))]
+    fn nip04_decrypt(&self, _: XOnlyPublicKey, _: String) -> SignerFuture<'_, String> {
+        Box::pin(future::ready(Err(Error::Unsupported)))
+    }
+}
+
+impl EventBuilder {
+    /// Build `Event` signed by `signer`
+    pub async fn sign<T>(self, signer: &T) -> Result<Event, Error>
+    where
+        T: NostrSigner + ?Sized,
+    {
+        let public_key: XOnlyPublicKey = signer.public_key().await?;
+        signer.sign_event(self.to_unsigned_event(public_key)).await
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::Result;
+
+    pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+        use std::sync::Arc;
+        use std::task::{Context, Poll, Wake};
+
+        struct Noop;
+        impl Wake for Noop {
+            fn wake(self: Arc<Self>) {}
+        }
+
+        let waker = Arc::new(Noop).into();
+        let mut cx = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    #[test]
+    fn test_keys_signer() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        let signer: &dyn NostrSigner = &keys;
+
+        let event = block_on(EventBuilder::new_text_note("Signed", &[]).sign(signer))?;
+        assert_eq!(event.pubkey, keys.public_key());
+        assert!(event.verify().is_ok());
+
+        let public_keys = Keys::from_public_key(keys.public_key());
+        assert!(matches!(
+            block_on(EventBuilder::new_text_note("Not signed", &[]).sign(&public_keys)),
+            Err(Error::Unsigned(..))
+        ));
+
+        #[cfg(feature = "nip04")]
+        {
+            let receiver = Keys::generate_from_os_random();
+            let ciphertext =
+                block_on(signer.nip04_encrypt(receiver.public_key(), String::from("Secret")))?;
+            let text = block_on(receiver.nip04_decrypt(keys.public_key(), ciphertext))?;
+            assert_eq!(text, "Secret");
+        }
+
+        Ok(())
+    }
+}
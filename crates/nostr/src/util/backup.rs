@@ -0,0 +1,119 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use url::Url;
+
+use crate::util::nips::nip49::{self, KeySecurity};
+use crate::{Event, Keys};
+
+/// Current version of the bundle format
+pub const IDENTITY_BUNDLE_VERSION: u8 = 1;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Key error
+    #[error("key error: {0}")]
+    Key(#[from] crate::key::Error),
+    /// NIP49 error
+    #[error("nip49 error: {0}")]
+    NIP49(#[from] nip49::Error),
+    /// Error serializing or deserializing JSON data
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+    /// Unsupported bundle version
+    #[error("unsupported bundle version: {0}")]
+    UnsupportedVersion(u8),
+}
+
+/// Portable identity backup
+///
+/// The secret key is encrypted with the password (NIP-49 `ncryptsec`).
+/// Relays and events (contact list, lists, ...) are kept as they are: the events are already
+/// signed, so they can be published again after the import.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdentityBundle {
+    pub version: u8,
+    pub ncryptsec: String,
+    #[serde(default)]
+    pub relays: Vec<Url>,
+    #[serde(default)]
+    pub events: Vec<Event>,
+}
+
+impl IdentityBundle {
+    /// New `IdentityBundle`
+    pub fn new(
+        keys: &Keys,
+        password: &str,
+        relays: Vec<Url>,
+        events: Vec<Event>,
+    ) -> Result<Self, Error> {
+        let ncryptsec = nip49::encrypt(
+            &keys.secret_key()?,
+            password,
+            nip49::DEFAULT_LOG_N,
+            KeySecurity::Unknown,
+        )?;
+
+        Ok(Self {
+            version: IDENTITY_BUNDLE_VERSION,
+            ncryptsec,
+            relays,
+            events,
+        })
+    }
+
+    /// Deserialize `IdentityBundle` from JSON string
+    pub fn from_json<S>(json: S) -> Result<Self, Error>
+    where
+        S: Into<String>,
+    {
+        let bundle: Self = serde_json::from_str(&json.into())?;
+        if bundle.version != IDENTITY_BUNDLE_VERSION {
+            return Err(Error::UnsupportedVersion(bundle.version));
+        }
+        Ok(bundle)
+    }
+
+    /// Serialize `IdentityBundle` as JSON string
+    pub fn as_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Decrypt keys with password
+    pub fn keys(&self, password: &str) -> Result<Keys, Error> {
+        let (secret_key, _) = nip49::decrypt(&self.ncryptsec, password)?;
+        Ok(Keys::new(secret_key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Contact, EventBuilder, Result};
+
+    #[test]
+    fn test_identity_bundle() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        let contact_list = EventBuilder::set_contact_list(vec![Contact::new(
+            Keys::generate_from_os_random().public_key(),
+            "wss://relay.damus.io",
+            "alice",
+        )])
+        .to_event(&keys)?;
+
+        let bundle = IdentityBundle::new(
+            &keys,
+            "password",
+            vec![Url::parse("wss://relay.damus.io")?],
+            vec![contact_list],
+        )?;
+
+        let bundle = IdentityBundle::from_json(bundle.as_json()?)?;
+        assert_eq!(bundle.keys("password")?, keys);
+        assert!(bundle.keys("wrong").is_err());
+        assert!(bundle.events[0].verify().is_ok());
+
+        Ok(())
+    }
+}
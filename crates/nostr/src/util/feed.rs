@@ -0,0 +1,282 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use crate::util::time::{to_rfc2822, to_rfc3339};
+use crate::{Event, Kind, KindBase};
+
+/// Max length of the title generated for text notes
+const NOTE_TITLE_LEN: usize = 80;
+
+/// Item of a [`Feed`]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FeedItem {
+    /// Event id
+    pub id: String,
+    /// Author public key
+    pub author: String,
+    pub title: String,
+    pub summary: Option<String>,
+    pub content: String,
+    /// Publication timestamp
+    pub published: u64,
+    /// Last update timestamp
+    pub updated: u64,
+}
+
+impl FeedItem {
+    /// Create feed item from a long-form article (NIP-23) or a text note
+    pub fn from_event(event: &Event) -> Self {
+        let mut title: Option<String> = None;
+        let mut summary: Option<String> = None;
+        let mut published: Option<u64> = None;
+
+        if event.kind == Kind::Base(KindBase::LongFormTextNote) {
+            for tag in event.tags.iter() {
+                let tag = tag.as_vec();
+                match (tag.first().map(|t| t.as_str()), tag.get(1)) {
+                    (Some("title"), Some(value)) => title = Some(value.clone()),
+                    (Some("summary"), Some(value)) => summary = Some(value.clone()),
+                    (Some("published_at"), Some(value)) => published = value.parse().ok(),
+                    _ => (),
+                }
+            }
+        }
+
+        let title = title.unwrap_or_else(|| {
+            let line = event.content.lines().next().unwrap_or_default();
+            match line.char_indices().nth(NOTE_TITLE_LEN) {
+                Some((index, _)) => format!("{}…", &line[..index]),
+                None => line.to_string(),
+            }
+        });
+
+        Self {
+            id: event.id.to_string(),
+            author: event.pubkey.to_string(),
+            title,
+            summary,
+            content: event.content.clone(),
+            published: published.unwrap_or(event.created_at),
+            updated: event.created_at,
+        }
+    }
+}
+
+/// RSS/Atom feed of nostr events
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Feed {
+    pub title: String,
+    /// Url of the website the feed refers to
+    pub link: String,
+    pub description: String,
+    /// Prefix of the item links (the event id is appended)
+    pub item_link_prefix: Option<String>,
+    pub items: Vec<FeedItem>,
+}
+
+impl Feed {
+    /// New empty `Feed`
+    pub fn new<S>(title: S, link: S, description: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            title: title.into(),
+            link: link.into(),
+            description: description.into(),
+            item_link_prefix: None,
+            items: Vec::new(),
+        }
+    }
+
+    /// Set item link prefix (ex. `https://example.com/e/`)
+    pub fn item_link_prefix<S>(self, prefix: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            item_link_prefix: Some(prefix.into()),
+            ..self
+        }
+    }
+
+    /// Add events, sorted from the newest
+    pub fn events(mut self, events: &[Event]) -> Self {
+        self.items.extend(events.iter().map(FeedItem::from_event));
+        self.items
+            .sort_by_key(|item| std::cmp::Reverse(item.published));
+        self
+    }
+
+    fn item_link(&self, item: &FeedItem) -> Option<String> {
+        self.item_link_prefix
+            .as_ref()
+            .map(|prefix| format!("{}{}", prefix, item.id))
+    }
+
+    /// Generate RSS 2.0 document
+    pub fn to_rss(&self) -> String {
+        let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        xml.push_str(r#"<rss version="2.0"><channel>"#);
+        xml.push_str(&format!(
+            "<title>{}</title><link>{}</link><description>{}</description>",
+            escape(&self.title),
+            escape(&self.link),
+            escape(&self.description)
+        ));
+
+        if let Some(item) = self.items.first() {
+            xml.push_str(&format!(
+                "<lastBuildDate>{}</lastBuildDate>",
+                to_rfc2822(item.published)
+            ));
+        }
+
+        for item in self.items.iter() {
+            xml.push_str("<item>");
+            xml.push_str(&format!("<title>{}</title>", escape(&item.title)));
+            if let Some(link) = self.item_link(item) {
+                xml.push_str(&format!("<link>{}</link>", escape(&link)));
+            }
+            xml.push_str(&format!(r#"<guid isPermaLink="false">{}</guid>"#, item.id));
+            xml.push_str(&format!(
+                "<pubDate>{}</pubDate>",
+                to_rfc2822(item.published)
+            ));
+            xml.push_str(&format!(
+                "<description>{}</description>",
+                escape(item.summary.as_ref().unwrap_or(&item.content))
+            ));
+            xml.push_str("</item>");
+        }
+
+        xml.push_str("</channel></rss>");
+        xml
+    }
+
+    /// Generate Atom document
+    pub fn to_atom(&self) -> String {
+        let updated = self
+            .items
+            .iter()
+            .map(|item| item.updated)
+            .max()
+            .unwrap_or_default();
+
+        let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        xml.push_str(r#"<feed xmlns="http://www.w3.org/2005/Atom">"#);
+        xml.push_str(&format!(
+            r#"<title>{}</title><subtitle>{}</subtitle><link href="{}"/><id>{}</id><updated>{}</updated>"#,
+            escape(&self.title),
+            escape(&self.description),
+            escape(&self.link),
+            escape(&self.link),
+            to_rfc3339(updated)
+        ));
+
+        for item in self.items.iter() {
+            xml.push_str("<entry>");
+            xml.push_str(&format!("<title>{}</title>", escape(&item.title)));
+            if let Some(link) = self.item_link(item) {
+                xml.push_str(&format!(r#"<link href="{}"/>"#, escape(&link)));
+            }
+            xml.push_str(&format!("<id>urn:nostr:{}</id>", item.id));
+            xml.push_str(&format!("<author><name>{}</name></author>", item.author));
+            xml.push_str(&format!(
+                "<published>{}</published><updated>{}</updated>",
+                to_rfc3339(item.published),
+                to_rfc3339(item.updated)
+            ));
+            if let Some(summary) = &item.summary {
+                xml.push_str(&format!("<summary>{}</summary>", escape(summary)));
+            }
+            xml.push_str(&format!(
+                r#"<content type="text">{}</content>"#,
+                escape(&item.content)
+            ));
+            xml.push_str("</entry>");
+        }
+
+        xml.push_str("</feed>");
+        xml
+    }
+}
+
+/// Escape XML special chars
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EventBuilder, Keys, Result, Tag};
+
+    fn article(keys: &Keys) -> Result<Event> {
+        let tags = vec![
+            Tag::from(vec![String::from("d"), String::from("my-article")]),
+            Tag::from(vec![String::from("title"), String::from("Nostr & feeds")]),
+            Tag::from(vec![String::from("summary"), String::from("A summary")]),
+            Tag::from(vec![
+                String::from("published_at"),
+                String::from("1673778600"),
+            ]),
+        ];
+        Ok(EventBuilder::new(
+            Kind::Base(KindBase::LongFormTextNote),
+            "# Title\nBody <b>",
+            &tags,
+        )
+        .to_event(keys)?)
+    }
+
+    #[test]
+    fn test_feed_item() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+
+        let item = FeedItem::from_event(&article(&keys)?);
+        assert_eq!(item.title, "Nostr & feeds");
+        assert_eq!(item.summary, Some(String::from("A summary")));
+        assert_eq!(item.published, 1673778600);
+
+        let note = EventBuilder::new_text_note("First line\nSecond line", &[]).to_event(&keys)?;
+        let item = FeedItem::from_event(&note);
+        assert_eq!(item.title, "First line");
+        assert_eq!(item.summary, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rss_and_atom() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        let event = article(&keys)?;
+        let feed = Feed::new("My blog", "https://example.com", "Articles")
+            .item_link_prefix("https://example.com/e/")
+            .events(std::slice::from_ref(&event));
+
+        let rss = feed.to_rss();
+        assert!(rss.contains("<title>Nostr &amp; feeds</title>"));
+        assert!(rss.contains(&format!("<link>https://example.com/e/{}</link>", event.id)));
+        assert!(rss.contains("<pubDate>Sun, 15 Jan 2023 10:30:00 +0000</pubDate>"));
+        assert!(rss.contains("<description>A summary</description>"));
+
+        let atom = feed.to_atom();
+        assert!(atom.contains("<published>2023-01-15T10:30:00Z</published>"));
+        assert!(atom.contains("# Title\nBody &lt;b&gt;"));
+        assert!(atom.contains(&format!("<id>urn:nostr:{}</id>", event.id)));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,210 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use bitcoin::secp256k1::XOnlyPublicKey;
+use keyring::Entry;
+
+use super::{Error, KeyStorage, Keyfile};
+use crate::key::Keys;
+use crate::util::nips::nip49;
+
+/// Keys stored in the OS keychain (macOS Keychain, Windows Credential Manager or Secret Service)
+///
+/// The entry holds the same passphrase-encrypted keyfile used by [`KeyStore`](super::KeyStore).
+#[derive(Debug, Clone)]
+pub struct KeyringStorage {
+    service: String,
+    account: String,
+    log_n: u8,
+}
+
+impl KeyringStorage {
+    /// New `KeyringStorage` for the keychain entry identified by `service` and `account`
+    pub fn new<S>(service: S, account: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            service: service.into(),
+            account: account.into(),
+            log_n: nip49::DEFAULT_LOG_N,
+        }
+    }
+
+    /// Set scrypt `log_n` used when saving (default: [`nip49::DEFAULT_LOG_N`])
+    pub fn log_n(self, log_n: u8) -> Self {
+        Self { log_n, ..self }
+    }
+
+    /// Keychain service name
+    pub fn service(&self) -> &str {
+        &self.service
+    }
+
+    /// Keychain account name
+    pub fn account(&self) -> &str {
+        &self.account
+    }
+
+    fn entry(&self) -> Result<Entry, Error> {
+        Ok(Entry::new(&self.service, &self.account)?)
+    }
+
+    fn read(&self) -> Result<Keyfile, Error> {
+        Keyfile::from_slice(self.entry()?.get_password()?.as_bytes())
+    }
+}
+
+impl KeyStorage for KeyringStorage {
+    fn exists(&self) -> Result<bool, Error> {
+        match self.entry()?.get_password() {
+            Ok(_) => Ok(true),
+            Err(keyring::Error::NoEntry) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save(&self, keys: &Keys, passphrase: &str) -> Result<(), Error> {
+        let keyfile = Keyfile::new(keys, passphrase, self.log_n)?;
+        Ok(self
+            .entry()?
+            .set_password(&serde_json::to_string(&keyfile)?)?)
+    }
+
+    fn load(&self, passphrase: &str) -> Result<Keys, Error> {
+        self.read()?.decrypt(passphrase)
+    }
+
+    fn public_key(&self) -> Result<XOnlyPublicKey, Error> {
+        Ok(self.read()?.public_key)
+    }
+
+    fn delete(&self) -> Result<(), Error> {
+        Ok(self.entry()?.delete_password()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::any::Any;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use keyring::credential::{Credential, CredentialApi, CredentialBuilderApi};
+    use once_cell::sync::Lazy;
+
+    use super::*;
+    use crate::Result;
+
+    /// In-memory keychain: unlike `keyring::mock`, entries of the same service and account
+    /// share the password, as in the OS keychains
+    static KEYCHAIN: Lazy<Mutex<HashMap<(String, String), String>>> =
+        Lazy::new(|| Mutex::new(HashMap::new()));
+
+    struct MemoryCredential(String, String);
+
+    impl MemoryCredential {
+        fn key(&self) -> (String, String) {
+            (self.0.clone(), self.1.clone())
+        }
+    }
+
+    impl CredentialApi for MemoryCredential {
+        fn set_password(&self, password: &str) -> keyring::Result<()> {
+            KEYCHAIN
+                .lock()
+                .unwrap()
+                .insert(self.key(), password.to_string());
+            Ok(())
+        }
+
+        fn get_password(&self) -> keyring::Result<String> {
+            KEYCHAIN
+                .lock()
+                .unwrap()
+                .get(&self.key())
+                .cloned()
+                .ok_or(keyring::Error::NoEntry)
+        }
+
+        fn delete_password(&self) -> keyring::Result<()> {
+            KEYCHAIN
+                .lock()
+                .unwrap()
+                .remove(&self.key())
+                .map(|_| ())
+                .ok_or(keyring::Error::NoEntry)
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    struct MemoryCredentialBuilder;
+
+    impl CredentialBuilderApi for MemoryCredentialBuilder {
+        fn build(
+            &self,
+            _target: Option<&str>,
+            service: &str,
+            user: &str,
+        ) -> keyring::Result<Box<Credential>> {
+            Ok(Box::new(MemoryCredential(
+                service.to_string(),
+                user.to_string(),
+            )))
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    #[test]
+    fn test_keyring_storage() -> Result<()> {
+        keyring::set_default_credential_builder(Box::new(MemoryCredentialBuilder));
+
+        let storage = KeyringStorage::new("nostr-sdk", "alice").log_n(4);
+        let other = KeyringStorage::new("nostr-sdk", "bob").log_n(4);
+        assert!(!storage.exists()?);
+
+        let keys = Keys::generate_from_os_random();
+        storage.save(&keys, "passphrase")?;
+        assert!(storage.exists()?);
+        assert!(!other.exists()?);
+        assert_eq!(storage.public_key()?, keys.public_key());
+        assert_eq!(storage.load("passphrase")?, keys);
+        assert!(matches!(
+            storage.load("wrong"),
+            Err(Error::NIP49(nip49::Error::Decryption))
+        ));
+
+        // The keychain holds the encrypted keyfile, never the secret key
+        let stored = Entry::new("nostr-sdk", "alice")?.get_password()?;
+        assert!(stored.contains("ncryptsec1"));
+        assert!(!stored.contains(&keys.secret_key()?.display_secret().to_string()));
+
+        storage.rotate("passphrase", "new passphrase")?;
+        assert!(storage.load("passphrase").is_err());
+        assert_eq!(storage.load("new passphrase")?, keys);
+
+        storage.delete()?;
+        assert!(!storage.exists()?);
+        assert!(matches!(
+            storage.delete(),
+            Err(Error::Keyring(keyring::Error::NoEntry))
+        ));
+        assert!(matches!(
+            storage.public_key(),
+            Err(Error::Keyring(keyring::Error::NoEntry))
+        ));
+
+        // Entry not written by `KeyringStorage`
+        Entry::new("nostr-sdk", "bob")?.set_password("hunter2")?;
+        assert!(other.exists()?);
+        assert!(matches!(other.load("hunter2"), Err(Error::Json(_))));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,237 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use bitcoin::secp256k1::XOnlyPublicKey;
+
+use crate::key::{self, Keys};
+use crate::util::nips::nip49::{self, KeySecurity};
+
+#[cfg(feature = "keyring")]
+pub mod keychain;
+
+#[cfg(feature = "keyring")]
+pub use self::keychain::KeyringStorage;
+
+/// Current version of the keyfile format
+pub const KEYFILE_VERSION: u8 = 1;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// I/O error
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    /// Key error
+    #[error("key error: {0}")]
+    Key(#[from] key::Error),
+    /// NIP49 error
+    #[error("nip49 error: {0}")]
+    NIP49(#[from] nip49::Error),
+    /// Error serializing or deserializing JSON data
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+    /// Unsupported keyfile version
+    #[error("unsupported keyfile version: {0}")]
+    UnsupportedVersion(u8),
+    /// The decrypted key doesn't match the public key of the keyfile
+    #[error("public key mismatch")]
+    PublicKeyMismatch,
+    /// OS keychain error
+    #[cfg(feature = "keyring")]
+    #[error("keyring error: {0}")]
+    Keyring(#[from] keyring::Error),
+}
+
+/// Storage backend for passphrase-encrypted keys
+pub trait KeyStorage {
+    /// Check if keys are stored
+    fn exists(&self) -> Result<bool, Error>;
+
+    /// Encrypt `keys` with `passphrase` and save them, replacing the stored ones
+    fn save(&self, keys: &Keys, passphrase: &str) -> Result<(), Error>;
+
+    /// Load and decrypt the keys
+    fn load(&self, passphrase: &str) -> Result<Keys, Error>;
+
+    /// Get the public key, without the passphrase
+    fn public_key(&self) -> Result<XOnlyPublicKey, Error>;
+
+    /// Delete the stored keys
+    fn delete(&self) -> Result<(), Error>;
+
+    /// Re-encrypt the keys with a new passphrase
+    fn rotate(&self, old_passphrase: &str, new_passphrase: &str) -> Result<(), Error> {
+        let keys = self.load(old_passphrase)?;
+        self.save(&keys, new_passphrase)
+    }
+}
+
+/// Content of the keyfile
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Keyfile {
+    version: u8,
+    public_key: XOnlyPublicKey,
+    /// Secret key encrypted with the passphrase (NIP-49: scrypt + XChaCha20-Poly1305)
+    ncryptsec: String,
+}
+
+impl Keyfile {
+    fn new(keys: &Keys, passphrase: &str, log_n: u8) -> Result<Self, Error> {
+        Ok(Self {
+            version: KEYFILE_VERSION,
+            public_key: keys.public_key(),
+            ncryptsec: nip49::encrypt(
+                &keys.secret_key()?,
+                passphrase,
+                log_n,
+                KeySecurity::Unknown,
+            )?,
+        })
+    }
+
+    fn from_slice(data: &[u8]) -> Result<Self, Error> {
+        let keyfile: Self = serde_json::from_slice(data)?;
+        if keyfile.version != KEYFILE_VERSION {
+            return Err(Error::UnsupportedVersion(keyfile.version));
+        }
+        Ok(keyfile)
+    }
+
+    fn decrypt(&self, passphrase: &str) -> Result<Keys, Error> {
+        let (secret_key, _) = nip49::decrypt(&self.ncryptsec, passphrase)?;
+        let keys = Keys::new(secret_key);
+        if keys.public_key() != self.public_key {
+            return Err(Error::PublicKeyMismatch);
+        }
+        Ok(keys)
+    }
+}
+
+/// Keys persisted to disk, encrypted with a passphrase
+///
+/// The keyfile is written atomically and, on unix, readable only by the owner.
+#[derive(Debug, Clone)]
+pub struct KeyStore {
+    path: PathBuf,
+    log_n: u8,
+}
+
+impl KeyStore {
+    /// New `KeyStore` backed by the keyfile at `path`
+    pub fn new<P>(path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            log_n: nip49::DEFAULT_LOG_N,
+        }
+    }
+
+    /// Set scrypt `log_n` used when saving (default: [`nip49::DEFAULT_LOG_N`])
+    pub fn log_n(self, log_n: u8) -> Self {
+        Self { log_n, ..self }
+    }
+
+    /// Keyfile path
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn read(&self) -> Result<Keyfile, Error> {
+        Keyfile::from_slice(&fs::read(&self.path)?)
+    }
+
+    fn write(&self, keyfile: &Keyfile) -> Result<(), Error> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+
+        let mut file = options.open(&tmp_path)?;
+        file.write_all(&serde_json::to_vec(keyfile)?)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+impl KeyStorage for KeyStore {
+    fn exists(&self) -> Result<bool, Error> {
+        Ok(self.path.exists())
+    }
+
+    fn save(&self, keys: &Keys, passphrase: &str) -> Result<(), Error> {
+        self.write(&Keyfile::new(keys, passphrase, self.log_n)?)
+    }
+
+    fn load(&self, passphrase: &str) -> Result<Keys, Error> {
+        self.read()?.decrypt(passphrase)
+    }
+
+    fn public_key(&self) -> Result<XOnlyPublicKey, Error> {
+        Ok(self.read()?.public_key)
+    }
+
+    fn delete(&self) -> Result<(), Error> {
+        Ok(fs::remove_file(&self.path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Result;
+
+    #[test]
+    fn test_keystore() -> Result<()> {
+        let path = std::env::temp_dir()
+            .join(format!(
+                "nostr-keystore-{}",
+                crate::util::time::timestamp_nanos()
+            ))
+            .join("keys.json");
+        let store = KeyStore::new(&path).log_n(4);
+        assert!(!store.exists()?);
+
+        let keys = Keys::generate_from_os_random();
+        store.save(&keys, "passphrase")?;
+        assert!(store.exists()?);
+        assert_eq!(store.public_key()?, keys.public_key());
+        assert_eq!(store.load("passphrase")?, keys);
+        assert!(matches!(
+            store.load("wrong"),
+            Err(Error::NIP49(nip49::Error::Decryption))
+        ));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&path)?.permissions().mode() & 0o777, 0o600);
+        }
+
+        store.rotate("passphrase", "new passphrase")?;
+        assert!(store.load("passphrase").is_err());
+        assert_eq!(store.load("new passphrase")?, keys);
+
+        store.delete()?;
+        assert!(!store.exists()?);
+        fs::remove_dir(path.parent().unwrap())?;
+
+        Ok(())
+    }
+}
@@ -1,5 +1,11 @@
 // Copyright (c) 2022 Yuki Kishimoto
 // Distributed under the MIT software license
 
+#[cfg(feature = "nip49")]
+pub mod backup;
+pub mod feed;
+#[cfg(feature = "nip49")]
+pub mod keystore;
 pub mod nips;
+pub mod shamir;
 pub mod time;
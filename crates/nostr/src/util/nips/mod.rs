@@ -1,6 +1,8 @@
 // Copyright (c) 2022 Yuki Kishimoto
 // Distributed under the MIT software license
 
+#[cfg(feature = "nip03")]
+pub mod nip03;
 #[cfg(feature = "nip04")]
 pub mod nip04;
 #[cfg(feature = "nip05")]
@@ -10,4 +12,41 @@ pub mod nip06;
 #[cfg(feature = "nip11")]
 pub mod nip11;
 pub mod nip13;
+pub mod nip15;
+pub mod nip18;
+pub mod nip19;
+pub mod nip21;
+pub mod nip23;
 pub mod nip26;
+pub mod nip27;
+pub mod nip28;
+pub mod nip29;
+pub mod nip34;
+pub mod nip38;
+pub mod nip39;
+#[cfg(feature = "nip44")]
+pub mod nip44;
+#[cfg(feature = "nip46")]
+pub mod nip46;
+#[cfg(feature = "nip47")]
+pub mod nip47;
+#[cfg(feature = "nip49")]
+pub mod nip49;
+#[cfg(feature = "nip51")]
+pub mod nip51;
+pub mod nip56;
+pub mod nip57;
+pub mod nip58;
+#[cfg(feature = "nip59")]
+pub mod nip59;
+pub mod nip65;
+pub mod nip66;
+pub mod nip84;
+pub mod nip89;
+pub mod nip90;
+pub mod nip96;
+#[cfg(feature = "nip98")]
+pub mod nip98;
+pub mod nip99;
+#[cfg(test)]
+mod test_util;
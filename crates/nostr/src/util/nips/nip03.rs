@@ -0,0 +1,360 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use bitcoin::hashes::{ripemd160, sha1, sha256, Hash};
+
+use crate::event::TagKind;
+use crate::{Event, Kind, KindBase, Sha256Hash};
+
+/// OpenTimestamps proof file header
+const HEADER_MAGIC: &[u8] = b"\x00OpenTimestamps\x00\x00Proof\x00\xbf\x89\xe2\xe8\x84\xe8\x92\x94";
+const BITCOIN_ATTESTATION: [u8; 8] = [0x05, 0x88, 0x96, 0x0d, 0x73, 0xd7, 0x19, 0x01];
+const MAX_DEPTH: usize = 256;
+const MAX_MSG_LEN: usize = 4096;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Base64 decoding error
+    #[error("base64 error: {0}")]
+    Base64(#[from] base64::DecodeError),
+    /// Invalid event kind
+    #[error("invalid event kind")]
+    InvalidKind,
+    /// The attestation doesn't reference the event
+    #[error("event mismatch")]
+    EventMismatch,
+    /// Invalid or truncated proof
+    #[error("invalid proof")]
+    InvalidProof,
+    /// Unsupported operation in the proof
+    #[error("unsupported operation: {0:#04x}")]
+    UnsupportedOp(u8),
+    /// The proof has no Bitcoin attestation yet (only pending ones)
+    #[error("no bitcoin attestation")]
+    NoBitcoinAttestation,
+}
+
+/// Bitcoin block attestation
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct BitcoinAttestation {
+    /// Block height
+    pub height: u64,
+    /// Commitment that must match the merkle root of the block header
+    pub merkle_root: [u8; 32],
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let end = self.pos.checked_add(len).ok_or(Error::InvalidProof)?;
+        let bytes = self.data.get(self.pos..end).ok_or(Error::InvalidProof)?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, Error> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn varuint(&mut self) -> Result<u64, Error> {
+        let mut value: u64 = 0;
+        let mut shift = 0;
+        loop {
+            let b = self.byte()?;
+            if shift > 63 {
+                return Err(Error::InvalidProof);
+            }
+            value |= ((b & 0x7f) as u64) << shift;
+            if b & 0x80 == 0 {
+                return Ok(value);
+            }
+            shift += 7;
+        }
+    }
+
+    fn varbytes(&mut self) -> Result<&'a [u8], Error> {
+        let len = self.varuint()? as usize;
+        if len > MAX_MSG_LEN {
+            return Err(Error::InvalidProof);
+        }
+        self.bytes(len)
+    }
+}
+
+/// Parse the timestamp tree, collecting the Bitcoin attestations of `msg`
+fn parse_timestamp(
+    reader: &mut Reader,
+    msg: &[u8],
+    depth: usize,
+    attestations: &mut Vec<BitcoinAttestation>,
+) -> Result<(), Error> {
+    if depth > MAX_DEPTH {
+        return Err(Error::InvalidProof);
+    }
+
+    let mut tag = reader.byte()?;
+    while tag == 0xff {
+        let branch = reader.byte()?;
+        parse_op(reader, branch, msg, depth, attestations)?;
+        tag = reader.byte()?;
+    }
+    parse_op(reader, tag, msg, depth, attestations)
+}
+
+fn parse_op(
+    reader: &mut Reader,
+    tag: u8,
+    msg: &[u8],
+    depth: usize,
+    attestations: &mut Vec<BitcoinAttestation>,
+) -> Result<(), Error> {
+    let result: Vec<u8> = match tag {
+        0x00 => {
+            let attestation_tag = reader.bytes(8)?;
+            let payload = reader.varbytes()?;
+            if attestation_tag == BITCOIN_ATTESTATION {
+                let mut payload = Reader {
+                    data: payload,
+                    pos: 0,
+                };
+                attestations.push(BitcoinAttestation {
+                    height: payload.varuint()?,
+                    merkle_root: msg.try_into().map_err(|_| Error::InvalidProof)?,
+                });
+            }
+            return Ok(());
+        }
+        0x02 => sha1::Hash::hash(msg).to_vec(),
+        0x03 => ripemd160::Hash::hash(msg).to_vec(),
+        0x08 => sha256::Hash::hash(msg).to_vec(),
+        0xf0 => [msg, reader.varbytes()?].concat(),
+        0xf1 => [reader.varbytes()?, msg].concat(),
+        0xf2 => msg.iter().rev().copied().collect(),
+        0xf3 => bitcoin::hashes::hex::ToHex::to_hex(msg).into_bytes(),
+        op => return Err(Error::UnsupportedOp(op)),
+    };
+
+    if result.len() > MAX_MSG_LEN {
+        return Err(Error::InvalidProof);
+    }
+
+    parse_timestamp(reader, &result, depth + 1, attestations)
+}
+
+/// Parse an OpenTimestamps proof of `digest` (SHA-256) and get its Bitcoin attestations
+pub fn bitcoin_attestations(
+    ots: &[u8],
+    digest: &Sha256Hash,
+) -> Result<Vec<BitcoinAttestation>, Error> {
+    let mut reader = Reader { data: ots, pos: 0 };
+    if reader.bytes(HEADER_MAGIC.len())? != HEADER_MAGIC || reader.varuint()? != 1 {
+        return Err(Error::InvalidProof);
+    }
+    match reader.byte()? {
+        0x08 => (),
+        op => return Err(Error::UnsupportedOp(op)),
+    }
+    if reader.bytes(32)? != digest.as_ref() {
+        return Err(Error::EventMismatch);
+    }
+
+    let mut attestations = Vec::new();
+    parse_timestamp(&mut reader, digest.as_ref(), 0, &mut attestations)?;
+    Ok(attestations)
+}
+
+/// Verify an OpenTimestamps attestation (kind 1040) of `event`
+///
+/// Check that the attestation references `event` and that the proof commits to its id.
+/// Return the Bitcoin attestations: each `merkle_root` must be checked against the
+/// block header at `height`.
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/03.md>
+pub fn verify(attestation: &Event, event: &Event) -> Result<Vec<BitcoinAttestation>, Error> {
+    if attestation.kind != Kind::Base(KindBase::OpenTimestamps) {
+        return Err(Error::InvalidKind);
+    }
+
+    let referenced = attestation
+        .tags
+        .iter()
+        .find(|t| matches!(t.kind(), Ok(TagKind::E)))
+        .and_then(|t| t.content());
+    if referenced != Some(event.id.to_string().as_str()) {
+        return Err(Error::EventMismatch);
+    }
+
+    let ots: Vec<u8> = base64::decode(attestation.content.trim())?;
+    let attestations = bitcoin_attestations(&ots, &event.id)?;
+    if attestations.is_empty() {
+        return Err(Error::NoBitcoinAttestation);
+    }
+    Ok(attestations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EventBuilder, Keys, Result, Tag};
+
+    fn ots_header(digest: &Sha256Hash) -> Vec<u8> {
+        let mut ots: Vec<u8> = HEADER_MAGIC.to_vec();
+        ots.extend([0x01, 0x08]);
+        ots.extend(digest.as_ref());
+        ots
+    }
+
+    #[test]
+    fn test_opentimestamps() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        let event = EventBuilder::new_text_note("Timestamp me", &[]).to_event(&keys)?;
+
+        // Pending calendar attestation and Bitcoin attestation of sha256(id || "abc")
+        let mut ots: Vec<u8> = HEADER_MAGIC.to_vec();
+        ots.extend([0x01, 0x08]);
+        ots.extend(event.id.as_ref());
+        ots.extend([0xff, 0x00]);
+        ots.extend([0x83, 0xdf, 0xe3, 0x0d, 0x2e, 0xf9, 0x0c, 0x8e]);
+        ots.extend([0x04, 0x03, b'u', b'r', b'l']);
+        ots.extend([0xf0, 0x03, b'a', b'b', b'c', 0x08, 0x00]);
+        ots.extend(BITCOIN_ATTESTATION);
+        ots.extend([0x03, 0xc0, 0x84, 0x3d]);
+
+        let attestation = EventBuilder::opentimestamps(event.id, None, &ots).to_event(&keys)?;
+        assert_eq!(attestation.kind, Kind::Base(KindBase::OpenTimestamps));
+
+        let expected = sha256::Hash::hash(&[event.id.as_ref(), b"abc"].concat());
+        assert_eq!(
+            verify(&attestation, &event)?,
+            vec![BitcoinAttestation {
+                height: 1_000_000,
+                merkle_root: expected.into_inner(),
+            }]
+        );
+
+        let other = EventBuilder::new_text_note("Other", &[]).to_event(&keys)?;
+        assert!(matches!(
+            verify(&attestation, &other),
+            Err(Error::EventMismatch)
+        ));
+
+        ots.truncate(ots.len() - 3);
+        let truncated = EventBuilder::opentimestamps(event.id, None, &ots).to_event(&keys)?;
+        assert!(matches!(
+            verify(&truncated, &event),
+            Err(Error::InvalidProof)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_opentimestamps_spec_example() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        let event = EventBuilder::new_text_note("Timestamp me", &[]).to_event(&keys)?;
+
+        let mut ots = ots_header(&event.id);
+        ots.push(0x00);
+        ots.extend(BITCOIN_ATTESTATION);
+        ots.extend([0x03, 0xc0, 0x84, 0x3d]);
+
+        let tags: Vec<Tag> = serde_json::from_str(&format!(
+            r#"[["e", "{}", "wss://relay.damus.io"], ["alt", "opentimestamps attestation"]]"#,
+            event.id
+        ))?;
+        let attestation = EventBuilder::new(
+            Kind::Base(KindBase::OpenTimestamps),
+            base64::encode(&ots),
+            &tags,
+        )
+        .to_event(&keys)?;
+        assert_eq!(
+            verify(&attestation, &event)?,
+            vec![BitcoinAttestation {
+                height: 1_000_000,
+                merkle_root: event.id.into_inner(),
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_opentimestamps_invalid() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        let event = EventBuilder::new_text_note("Timestamp me", &[]).to_event(&keys)?;
+        let mut valid = ots_header(&event.id);
+        valid.push(0x00);
+        valid.extend(BITCOIN_ATTESTATION);
+        valid.extend([0x03, 0xc0, 0x84, 0x3d]);
+        let content = base64::encode(&valid);
+
+        let note = EventBuilder::new_text_note(&content, &[]).to_event(&keys)?;
+        assert!(matches!(verify(&note, &event), Err(Error::InvalidKind)));
+
+        // Missing or mismatching event reference
+        let attestation = EventBuilder::new(Kind::Base(KindBase::OpenTimestamps), &content, &[])
+            .to_event(&keys)?;
+        assert!(matches!(
+            verify(&attestation, &event),
+            Err(Error::EventMismatch)
+        ));
+
+        let attestation = EventBuilder::opentimestamps(event.id, None, b"").to_event(&keys)?;
+        let attestation = EventBuilder::new(
+            Kind::Base(KindBase::OpenTimestamps),
+            "not base64!",
+            &attestation.tags,
+        )
+        .to_event(&keys)?;
+        assert!(matches!(
+            verify(&attestation, &event),
+            Err(Error::Base64(_))
+        ));
+
+        // Malformed proofs
+        let mut bad_magic = valid.clone();
+        bad_magic[1] = b'X';
+        let mut bad_version = valid.clone();
+        bad_version[HEADER_MAGIC.len()] = 0x02;
+        let mut pending = ots_header(&event.id);
+        pending.push(0x00);
+        pending.extend([0x83, 0xdf, 0xe3, 0x0d, 0x2e, 0xf9, 0x0c, 0x8e]);
+        pending.extend([0x04, 0x03, b'u', b'r', b'l']);
+        let mut too_deep = ots_header(&event.id);
+        too_deep.extend([0x08; MAX_DEPTH + 1]);
+        let mut too_long = ots_header(&event.id);
+        too_long.extend([0xf0, 0x81, 0x40]);
+        let mut unsupported_file_hash = valid.clone();
+        unsupported_file_hash[HEADER_MAGIC.len() + 1] = 0x02;
+        let mut unsupported_op = ots_header(&event.id);
+        unsupported_op.push(0x77);
+        let mut other_digest = ots_header(&Sha256Hash::hash(b"other"));
+        other_digest.extend(&valid[valid.len() - 13..]);
+
+        for (ots, error) in [
+            (Vec::new(), Error::InvalidProof),
+            (bad_magic, Error::InvalidProof),
+            (bad_version, Error::InvalidProof),
+            (valid[..valid.len() - 3].to_vec(), Error::InvalidProof),
+            (too_deep, Error::InvalidProof),
+            (too_long, Error::InvalidProof),
+            (unsupported_file_hash, Error::UnsupportedOp(0x02)),
+            (unsupported_op, Error::UnsupportedOp(0x77)),
+            (other_digest, Error::EventMismatch),
+            (pending, Error::NoBitcoinAttestation),
+        ] {
+            let attestation = EventBuilder::opentimestamps(event.id, None, &ots).to_event(&keys)?;
+            assert_eq!(
+                verify(&attestation, &event).unwrap_err().to_string(),
+                error.to_string()
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -1,11 +1,19 @@
 // Copyright (c) 2022 Yuki Kishimoto
 // Distributed under the MIT software license
 
+use std::net::SocketAddr;
 use std::str::FromStr;
+use std::time::Duration;
 
 use bitcoin::secp256k1::XOnlyPublicKey;
-use reqwest::blocking::Client;
+use reqwest::{redirect, Proxy};
 use serde_json::Value;
+use url::{Host, Url};
+
+use super::nip19::Profile;
+
+/// Timeout of the well-known document request
+const TIMEOUT: Duration = Duration::from_secs(10);
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -22,32 +30,190 @@ pub enum Error {
     Secp256k1(#[from] bitcoin::secp256k1::Error),
 }
 
-/// Verify NIP-05
-pub fn verify(public_key: XOnlyPublicKey, nip05: &str) -> Result<(), Error> {
-    let data: Vec<&str> = nip05.split('@').collect();
-    if data.len() != 2 {
+/// Split `name@domain` (a bare `domain` is the same as `_@domain`) and get the well-known url
+///
+/// The local part must only contain `a-z0-9-_.` (case-insensitive) and the domain must be a
+/// valid host (no port, path or query).
+fn well_known_url(nip05: &str) -> Result<(String, Url), Error> {
+    let (name, domain) = match nip05.split_once('@') {
+        Some((name, domain)) => (name, domain),
+        None => ("_", nip05),
+    };
+
+    let name = name.to_lowercase();
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| matches!(c, 'a'..='z' | '0'..='9' | '-' | '_' | '.'))
+    {
         return Err(Error::InvalidFormat);
     }
+    // `Host::parse` would percent-decode and IDNA-map the domain
+    if !domain
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '[' | ']' | ':'))
+    {
+        return Err(Error::InvalidFormat);
+    }
+    let host = Host::parse(domain).map_err(|_| Error::InvalidFormat)?;
+
+    let mut url = Url::parse(&format!("https://{}/.well-known/nostr.json", host))
+        .map_err(|_| Error::InvalidFormat)?;
+    url.query_pairs_mut().append_pair("name", &name);
+    Ok((name, url))
+}
+
+/// Get the public key and the relays of `name` from the well-known document
+fn profile_from_json(json: &Value, name: &str) -> Result<Profile, Error> {
+    let public_key: XOnlyPublicKey = json
+        .get("names")
+        .and_then(|names| names.get(name))
+        .and_then(|value| value.as_str())
+        .ok_or(Error::ImpossibleToVerify)
+        .and_then(|pubkey| Ok(XOnlyPublicKey::from_str(pubkey)?))?;
 
-    let name: &str = data[0];
-    let domain: &str = data[1];
+    let relays: Vec<String> = json
+        .get("relays")
+        .and_then(|relays| relays.get(public_key.to_string()))
+        .and_then(|relays| relays.as_array())
+        .map(|relays| {
+            relays
+                .iter()
+                .filter_map(|r| r.as_str().map(|r| r.to_string()))
+                .collect()
+        })
+        .unwrap_or_default();
 
-    let url = format!("https://{}/.well-known/nostr.json?name={}", domain, name);
+    Ok(Profile::new(public_key, relays))
+}
 
-    let req = Client::new().get(url);
+/// Verify NIP-05
+///
+/// Blocking: inside an async runtime use [`verify_async`].
+pub fn verify(
+    public_key: XOnlyPublicKey,
+    nip05: &str,
+    proxy: Option<SocketAddr>,
+) -> Result<(), Error> {
+    let (name, url) = well_known_url(nip05)?;
+    let mut builder = reqwest::blocking::Client::builder()
+        .redirect(redirect::Policy::none())
+        .timeout(TIMEOUT);
 
-    let res = req.send()?;
+    if let Some(proxy) = proxy {
+        let proxy = format!("socks5h://{}", proxy);
+        builder = builder.proxy(Proxy::all(proxy)?);
+    }
+
+    let client = builder.build()?;
+    let res = client.get(url).send()?;
     let json: Value = serde_json::from_str(&res.text()?)?;
 
-    if let Some(names) = json.get("names") {
-        if let Some(value) = names.get(name) {
-            if let Some(pubkey) = value.as_str() {
-                if XOnlyPublicKey::from_str(pubkey)? == public_key {
-                    return Ok(());
-                }
-            }
-        }
+    if profile_from_json(&json, &name)?.public_key == public_key {
+        Ok(())
+    } else {
+        Err(Error::ImpossibleToVerify)
     }
+}
 
-    Err(Error::ImpossibleToVerify)
+/// Verify NIP-05
+pub async fn verify_async(
+    public_key: XOnlyPublicKey,
+    nip05: &str,
+    proxy: Option<SocketAddr>,
+) -> Result<(), Error> {
+    if get_profile(nip05, proxy).await?.public_key == public_key {
+        Ok(())
+    } else {
+        Err(Error::ImpossibleToVerify)
+    }
+}
+
+/// Resolve a NIP-05 address (`name@domain`) to its public key and relays
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/05.md>
+pub async fn get_profile(nip05: &str, proxy: Option<SocketAddr>) -> Result<Profile, Error> {
+    let (name, url) = well_known_url(nip05)?;
+    let mut builder = reqwest::Client::builder()
+        .redirect(redirect::Policy::none())
+        .timeout(TIMEOUT);
+
+    if let Some(proxy) = proxy {
+        let proxy = format!("socks5h://{}", proxy);
+        builder = builder.proxy(Proxy::all(proxy)?);
+    }
+
+    let client = builder.build()?;
+    let res = client.get(url).send().await?;
+    let json: Value = serde_json::from_str(&res.text().await?)?;
+    profile_from_json(&json, &name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_from_json() {
+        let pubkey = "b0635d6a9851d3aed0cd6c495b282167acf761729078d975fc341b22650b07b9";
+        let json: Value = serde_json::from_str(&format!(
+            r#"{{"names":{{"bob":"{pubkey}"}},"relays":{{"{pubkey}":["wss://relay.example.com","wss://relay2.example.com"]}}}}"#
+        ))
+        .unwrap();
+
+        let (name, url) = well_known_url("Bob@example.com").unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://example.com/.well-known/nostr.json?name=bob"
+        );
+
+        let profile = profile_from_json(&json, &name).unwrap();
+        assert_eq!(profile.public_key.to_string(), pubkey);
+        assert_eq!(profile.relays.len(), 2);
+
+        assert!(profile_from_json(&json, "alice").is_err());
+        assert_eq!(well_known_url("example.com").unwrap().0, String::from("_"));
+        assert!(well_known_url("@example.com").is_err());
+    }
+
+    #[test]
+    fn test_well_known_url() {
+        let (name, url) = well_known_url("bob.smith-1_2@Sub.Example.com").unwrap();
+        assert_eq!(name, "bob.smith-1_2");
+        assert_eq!(
+            url.as_str(),
+            "https://sub.example.com/.well-known/nostr.json?name=bob.smith-1_2"
+        );
+        assert_eq!(
+            well_known_url("bob@127.0.0.1").unwrap().1.as_str(),
+            "https://127.0.0.1/.well-known/nostr.json?name=bob"
+        );
+
+        // Local part outside of the allowed charset
+        for nip05 in [
+            "bob&admin=1@example.com",
+            "bob#@example.com",
+            "b%6fb@example.com",
+            "bob smith@example.com",
+            "bób@example.com",
+        ] {
+            assert!(well_known_url(nip05).is_err(), "{}", nip05);
+        }
+
+        // Not a host
+        for nip05 in [
+            "bob@",
+            "bob@bob@example.com",
+            "bob@example.com/evil",
+            "bob@example.com?name=alice",
+            "bob@example.com#fragment",
+            "bob@example.com:8080",
+            "bob@user:pass@example.com",
+            "bob@exa mple.com",
+            "bob@ex%61mple.com",
+            "bob@bücher.de",
+        ] {
+            assert!(well_known_url(nip05).is_err(), "{}", nip05);
+        }
+    }
 }
@@ -6,12 +6,16 @@ use std::str::FromStr;
 use bip39::Mnemonic;
 use bitcoin::hashes::hmac::{Hmac, HmacEngine};
 use bitcoin::hashes::{sha512, Hash, HashEngine};
-use bitcoin::secp256k1::Secp256k1;
-use bitcoin::util::bip32::{DerivationPath, ExtendedPrivKey};
+pub use bitcoin::util::bip32::DerivationPath;
+use bitcoin::util::bip32::ExtendedPrivKey;
 use bitcoin::Network;
 
 use crate::key::Keys;
 use crate::util::time;
+use crate::SECP256K1;
+
+/// NIP-06 purpose and coin type (`m/44'/1237'`)
+const PURPOSE_COIN_TYPE: &str = "m/44'/1237'";
 
 #[derive(Debug, Eq, PartialEq, thiserror::Error)]
 pub enum Error {
@@ -28,6 +32,20 @@ pub trait FromMnemonic: Sized {
     fn from_mnemonic<S>(mnemonic: S, passphrase: Option<S>) -> Result<Self, Self::Err>
     where
         S: Into<String>;
+    fn from_mnemonic_with_account<S>(
+        mnemonic: S,
+        passphrase: Option<S>,
+        account: u32,
+    ) -> Result<Self, Self::Err>
+    where
+        S: Into<String>;
+    fn from_mnemonic_with_path<S>(
+        mnemonic: S,
+        passphrase: Option<S>,
+        path: &DerivationPath,
+    ) -> Result<Self, Self::Err>
+    where
+        S: Into<String>;
 }
 
 pub trait GenerateMnemonic {
@@ -40,15 +58,38 @@ impl FromMnemonic for Keys {
 
     /// Derive keys from BIP-39 mnemonics (ENGLISH wordlist).
     fn from_mnemonic<S>(mnemonic: S, passphrase: Option<S>) -> Result<Self, Self::Err>
+    where
+        S: Into<String>,
+    {
+        Self::from_mnemonic_with_account(mnemonic, passphrase, 0)
+    }
+
+    /// Derive keys of `account` from BIP-39 mnemonics (`m/44'/1237'/<account>'/0/0`)
+    fn from_mnemonic_with_account<S>(
+        mnemonic: S,
+        passphrase: Option<S>,
+        account: u32,
+    ) -> Result<Self, Self::Err>
+    where
+        S: Into<String>,
+    {
+        let path = DerivationPath::from_str(&format!("{}/{}'/0/0", PURPOSE_COIN_TYPE, account))?;
+        Self::from_mnemonic_with_path(mnemonic, passphrase, &path)
+    }
+
+    /// Derive keys from BIP-39 mnemonics with a custom derivation path
+    fn from_mnemonic_with_path<S>(
+        mnemonic: S,
+        passphrase: Option<S>,
+        path: &DerivationPath,
+    ) -> Result<Self, Self::Err>
     where
         S: Into<String>,
     {
         let mnemonic = Mnemonic::from_str(&mnemonic.into())?;
         let seed = mnemonic.to_seed(passphrase.map(|p| p.into()).unwrap_or_default());
         let root_key = ExtendedPrivKey::new_master(Network::Bitcoin, &seed)?;
-        let path = DerivationPath::from_str("m/44'/1237'/0'/0/0")?;
-        let secp = Secp256k1::new();
-        let child_xprv = root_key.derive_priv(&secp, &path)?;
+        let child_xprv = root_key.derive_priv(&SECP256K1, path)?;
         Ok(Self::new(child_xprv.private_key))
     }
 }
@@ -87,4 +128,25 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_nip06_account() -> Result<()> {
+        let mnemonic: &str = "equal dragon fabric refuse stable cherry smoke allow alley easy never medal attend together lumber movie what sad siege weather matrix buffalo state shoot";
+
+        let keys = Keys::from_mnemonic_with_account(mnemonic, None, 0)?;
+        assert_eq!(keys, Keys::from_mnemonic(mnemonic, None)?);
+
+        let account = Keys::from_mnemonic_with_account(mnemonic, None, 1)?;
+        assert_ne!(account, keys);
+
+        let path = DerivationPath::from_str("m/44'/1237'/1'/0/0")?;
+        assert_eq!(
+            Keys::from_mnemonic_with_path(mnemonic, None, &path)?,
+            account
+        );
+
+        assert!(Keys::from_mnemonic_with_account(mnemonic, None, 1 << 31).is_err());
+
+        Ok(())
+    }
 }
@@ -2,10 +2,11 @@
 // Copyright (c) 2022 Yuki Kishimoto
 // Distributed under the MIT software license
 
+use std::collections::HashMap;
 use std::net::SocketAddr;
 
-use reqwest::blocking::Client;
 use reqwest::Proxy;
+use serde_json::Value;
 use url::Url;
 
 #[derive(Debug, thiserror::Error)]
@@ -20,31 +21,191 @@ pub enum Error {
     InaccessibleInformationDocument,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Relay information document
+///
+/// All fields are optional: relays often omit some of them. Unknown fields are kept in `custom`.
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/11.md>
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct RelayInformationDocument {
-    pub id: String,
-    pub name: String,
-    pub description: String,
-    pub pubkey: String,
-    pub contact: String,
-    pub supported_nips: Vec<u16>,
-    pub software: String,
-    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Public key of the relay admin
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pubkey: Option<String>,
+    /// Alternative contact (ex. email, url)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contact: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supported_nips: Option<Vec<u16>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub software: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Icon url
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    /// Server limitations
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limitation: Option<Limitation>,
+    /// Event retention policies
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retention: Option<Vec<Retention>>,
+    /// Countries whose laws may affect the relay (ISO 3166-1 alpha-2 codes)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relay_countries: Option<Vec<String>>,
+    /// Languages spoken on the relay (IETF language tags)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language_tags: Option<Vec<String>>,
+    /// Community preferences (ex. `sfw-only`, `bitcoin-only`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    /// Url of the posting policy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub posting_policy: Option<String>,
+    /// Url where the user can pay for the relay
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payments_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fees: Option<Fees>,
+    /// Unknown fields
+    #[serde(flatten)]
+    pub custom: HashMap<String, Value>,
+}
+
+/// Limitations imposed by the relay on clients
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Limitation {
+    /// Maximum bytes of an incoming JSON message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_message_length: Option<u64>,
+    /// Maximum active subscriptions on a single connection
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_subscriptions: Option<u64>,
+    /// Maximum filters in a subscription
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_filters: Option<u64>,
+    /// Maximum value of the `limit` field of a subscription filter
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_limit: Option<u64>,
+    /// Maximum length of a subscription id
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_subid_length: Option<u64>,
+    /// Maximum number of tags of an event
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_event_tags: Option<u64>,
+    /// Maximum number of characters of the event content
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_content_length: Option<u64>,
+    /// Minimum NIP-13 proof of work difficulty for published events
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_pow_difficulty: Option<u8>,
+    /// Relay requires NIP-42 authentication before any other action
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_required: Option<bool>,
+    /// Relay requires payment before a new connection may perform any action
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payment_required: Option<bool>,
+    /// Relay accepts events only from some users (ex. whitelisted)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restricted_writes: Option<bool>,
+    /// Oldest accepted `created_at` (seconds in the past)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at_lower_limit: Option<u64>,
+    /// Newest accepted `created_at` (seconds in the future)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at_upper_limit: Option<u64>,
+}
+
+/// Kinds affected by a retention policy: a single kind or a range (`[start, end]`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RetentionKind {
+    Single(u64),
+    Range(u64, u64),
+}
+
+/// Event retention policy
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Retention {
+    /// Kinds affected by the policy (all kinds if `None`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kinds: Option<Vec<RetentionKind>>,
+    /// Seconds the events are kept (`0` means not stored, `None` means forever)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<u64>,
+    /// Maximum number of events kept
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<u64>,
+}
+
+/// Relay fee
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fee {
+    pub amount: u64,
+    /// Ex. `msats`
+    pub unit: String,
+    /// Period in seconds (subscriptions only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period: Option<u64>,
+    /// Kinds affected (publication fees only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kinds: Option<Vec<u64>>,
+}
+
+/// Relay fees
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fees {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub admission: Vec<Fee>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subscription: Vec<Fee>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub publication: Vec<Fee>,
+}
+
+impl RelayInformationDocument {
+    /// Check if the relay requires payment
+    pub fn payment_required(&self) -> bool {
+        self.limitation
+            .as_ref()
+            .and_then(|l| l.payment_required)
+            .unwrap_or(false)
+    }
+
+    /// Check if the relay requires NIP-42 authentication
+    pub fn auth_required(&self) -> bool {
+        self.limitation
+            .as_ref()
+            .and_then(|l| l.auth_required)
+            .unwrap_or(false)
+    }
+
+    /// Get the maximum `limit` allowed by the relay for a subscription filter
+    pub fn max_limit(&self) -> Option<u64> {
+        self.limitation.as_ref().and_then(|l| l.max_limit)
+    }
 }
 
 /// Get Relay Information Document
+///
+/// Blocking: inside an async runtime use [`get_relay_information_document_async`].
 pub fn get_relay_information_document(
     url: Url,
     proxy: Option<SocketAddr>,
 ) -> Result<RelayInformationDocument, Error> {
-    let mut builder = Client::builder();
+    let mut builder = reqwest::blocking::Client::builder();
 
     if let Some(proxy) = proxy {
         let proxy = format!("socks5h://{}", proxy);
         builder = builder.proxy(Proxy::all(proxy)?);
     }
 
-    let client: Client = builder.build()?;
+    let client = builder.build()?;
 
     let req = client.get(url).header("Accept", "application/nostr+json");
 
@@ -56,3 +217,108 @@ pub fn get_relay_information_document(
         Err(_) => Err(Error::InaccessibleInformationDocument),
     }
 }
+
+/// Get Relay Information Document
+pub async fn get_relay_information_document_async(
+    url: Url,
+    proxy: Option<SocketAddr>,
+) -> Result<RelayInformationDocument, Error> {
+    let mut builder = reqwest::Client::builder();
+
+    if let Some(proxy) = proxy {
+        let proxy = format!("socks5h://{}", proxy);
+        builder = builder.proxy(Proxy::all(proxy)?);
+    }
+
+    let client = builder.build()?;
+
+    let req = client.get(url).header("Accept", "application/nostr+json");
+
+    match req.send().await {
+        Ok(response) => match response.json().await {
+            Ok(json) => Ok(json),
+            Err(_) => Err(Error::InvalidInformationDocument),
+        },
+        Err(_) => Err(Error::InaccessibleInformationDocument),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payment_required() {
+        let json = r#"{"id":"wss://relay.example.com","name":"Example","description":"","pubkey":"","contact":"","supported_nips":[1,11],"software":"","version":"","limitation":{"payment_required":true},"payments_url":"https://example.com/pay"}"#;
+        let info: RelayInformationDocument = serde_json::from_str(json).unwrap();
+        assert!(info.payment_required());
+        assert_eq!(
+            info.payments_url,
+            Some(String::from("https://example.com/pay"))
+        );
+
+        let json = r#"{"id":"wss://relay.example.com","name":"Example","description":"","pubkey":"","contact":"","supported_nips":[1,11],"software":"","version":""}"#;
+        let info: RelayInformationDocument = serde_json::from_str(json).unwrap();
+        assert!(!info.payment_required());
+    }
+
+    #[test]
+    fn test_max_limit() {
+        let json = r#"{"id":"wss://relay.example.com","name":"Example","description":"","pubkey":"","contact":"","supported_nips":[1,11],"software":"","version":"","limitation":{"max_limit":500}}"#;
+        let info: RelayInformationDocument = serde_json::from_str(json).unwrap();
+        assert_eq!(info.max_limit(), Some(500));
+        assert!(!info.payment_required());
+    }
+
+    #[test]
+    fn test_extended_document() {
+        let json = r#"{"name":"Example","supported_nips":[1,11,42],"icon":"https://example.com/icon.png","limitation":{"max_message_length":16384,"max_subscriptions":20,"auth_required":true,"payment_required":false},"retention":[{"kinds":[0,1,[5,7],[40,49]],"time":3600},{"kinds":[[40000,49999]],"time":100},{"count":1000}],"relay_countries":["CA","US"],"fees":{"admission":[{"amount":1000000,"unit":"msats"}],"publication":[{"kinds":[4],"amount":100,"unit":"msats"}]},"x-custom":{"foo":"bar"}}"#;
+        let info: RelayInformationDocument = serde_json::from_str(json).unwrap();
+        assert_eq!(info.name, Some(String::from("Example")));
+        assert_eq!(info.id, None);
+        assert!(info.auth_required());
+        assert!(!info.payment_required());
+        assert_eq!(
+            info.limitation.as_ref().and_then(|l| l.max_message_length),
+            Some(16384)
+        );
+        let retention = info.retention.clone().unwrap();
+        assert_eq!(
+            retention[0].kinds.as_ref().unwrap()[2],
+            RetentionKind::Range(5, 7)
+        );
+        assert_eq!(retention[2].count, Some(1000));
+        assert_eq!(info.fees.as_ref().unwrap().admission[0].amount, 1000000);
+        assert!(info.custom.contains_key("x-custom"));
+
+        // Unknown fields are preserved
+        let serialized: RelayInformationDocument =
+            serde_json::from_str(&serde_json::to_string(&info).unwrap()).unwrap();
+        assert_eq!(serialized, info);
+
+        let info: RelayInformationDocument = serde_json::from_str("{}").unwrap();
+        assert_eq!(info, RelayInformationDocument::default());
+    }
+
+    #[test]
+    fn test_invalid_document() {
+        for json in [
+            r#"[]"#,
+            r#"{"name":1}"#,
+            r#"{"supported_nips":["NIP-01"]}"#,
+            r#"{"supported_nips":[70000]}"#,
+            r#"{"limitation":{"max_limit":-1}}"#,
+            r#"{"limitation":{"auth_required":"yes"}}"#,
+            r#"{"limitation":{"min_pow_difficulty":300}}"#,
+            r#"{"retention":[{"kinds":[[1,2,3]]}]}"#,
+            r#"{"retention":[{"kinds":["text"]}]}"#,
+            r#"{"fees":{"admission":[{"amount":1000}]}}"#,
+        ] {
+            assert!(
+                serde_json::from_str::<RelayInformationDocument>(json).is_err(),
+                "{}",
+                json
+            );
+        }
+    }
+}
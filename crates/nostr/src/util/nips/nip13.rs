@@ -2,8 +2,121 @@
 // Copyright (c) 2022 Yuki Kishimoto
 // Distributed under the MIT software license
 
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
 use crate::Sha256Hash;
 
+/// Hashes between two progress reports
+pub const PROGRESS_INTERVAL: u64 = 10_000;
+
+#[derive(Debug, Eq, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// Mining cancelled with the [`CancellationToken`]
+    #[error("proof of work cancelled")]
+    Cancelled,
+}
+
+/// Token to abort a running proof of work mining
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// New `CancellationToken`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel the mining
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Check if the token has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Proof of work mining options
+#[derive(Clone, Default)]
+pub struct PowOptions {
+    threads: Option<NonZeroUsize>,
+    cancellation_token: Option<CancellationToken>,
+    progress: Option<Arc<dyn Fn(u64) + Send + Sync>>,
+}
+
+impl fmt::Debug for PowOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PowOptions")
+            .field("threads", &self.threads)
+            .field("cancellation_token", &self.cancellation_token)
+            .field("progress", &self.progress.is_some())
+            .finish()
+    }
+}
+
+impl PowOptions {
+    /// New default `PowOptions`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set number of worker threads (default: available parallelism)
+    pub fn threads(self, threads: NonZeroUsize) -> Self {
+        Self {
+            threads: Some(threads),
+            ..self
+        }
+    }
+
+    /// Set cancellation token
+    pub fn cancellation_token(self, token: CancellationToken) -> Self {
+        Self {
+            cancellation_token: Some(token),
+            ..self
+        }
+    }
+
+    /// Set progress callback, called with the number of hashes computed so far
+    /// every [`PROGRESS_INTERVAL`] hashes
+    pub fn progress<F>(self, callback: F) -> Self
+    where
+        F: Fn(u64) + Send + Sync + 'static,
+    {
+        Self {
+            progress: Some(Arc::new(callback)),
+            ..self
+        }
+    }
+
+    /// Number of worker threads
+    pub fn get_threads(&self) -> usize {
+        self.threads
+            .or_else(|| thread::available_parallelism().ok())
+            .map(|n| n.get())
+            .unwrap_or(1)
+    }
+
+    /// Check if the mining has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation_token
+            .as_ref()
+            .map(|t| t.is_cancelled())
+            .unwrap_or(false)
+    }
+
+    /// Report progress to the callback, if any
+    pub fn report_progress(&self, hashes: u64) {
+        if let Some(progress) = &self.progress {
+            progress(hashes);
+        }
+    }
+}
+
 /// Gets the number of leading zero bits of a hash. Result is between 0 and 255.
 pub fn get_leading_zero_bits(h: Sha256Hash) -> u8 {
     let mut res = 0_u8;
@@ -376,7 +489,7 @@ pub mod tests {
 
     #[test]
     fn check_find_prefixes_for_pow() {
-        assert_eq!(get_prefixes_for_difficulty(0).is_empty(), true);
+        assert!(get_prefixes_for_difficulty(0).is_empty());
 
         assert_eq!(
             get_prefixes_for_difficulty(1),
@@ -422,4 +535,33 @@ pub mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_pow_options() {
+        use crate::event::builder::Error as BuilderError;
+        use crate::{EventBuilder, Keys};
+
+        let keys = Keys::generate_from_os_random();
+
+        let options = PowOptions::new().threads(NonZeroUsize::new(4).unwrap());
+        let event = EventBuilder::new_text_note("Mined", &[])
+            .to_pow_event_with_options(&keys, 8, &options)
+            .unwrap();
+        assert!(event.check_pow(8));
+        assert!(event.verify().is_ok());
+
+        let token = CancellationToken::new();
+        let cancel = token.clone();
+        let options = PowOptions::new()
+            .cancellation_token(token)
+            .progress(move |hashes| {
+                assert!(hashes >= PROGRESS_INTERVAL);
+                cancel.cancel();
+            });
+        assert!(matches!(
+            EventBuilder::new_text_note("Never mined", &[])
+                .to_pow_event_with_options(&keys, 255, &options),
+            Err(BuilderError::NIP13(Error::Cancelled))
+        ));
+    }
 }
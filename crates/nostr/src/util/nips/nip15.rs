@@ -0,0 +1,588 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use serde_json::{json, Value};
+
+use crate::event::{TagData, TagKind};
+use crate::{Event, Kind, KindBase, Tag};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Error serializing or deserializing JSON data
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+    /// Invalid event kind
+    #[error("invalid event kind")]
+    InvalidKind,
+    /// Unknown checkout message type
+    #[error("unknown message type")]
+    UnknownMessageType,
+}
+
+/// Shipping zone of a stall
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShippingZone {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Base cost, in the stall currency
+    pub cost: f64,
+    pub regions: Vec<String>,
+}
+
+/// Stall (kind 30017)
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/15.md>
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Stall {
+    /// Stall id (`d` tag)
+    pub id: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub currency: String,
+    pub shipping: Vec<ShippingZone>,
+}
+
+impl Stall {
+    /// New `Stall`
+    pub fn new<S>(id: S, name: S, currency: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            description: None,
+            currency: currency.into(),
+            shipping: Vec::new(),
+        }
+    }
+
+    /// Set description
+    pub fn description<S>(self, description: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            description: Some(description.into()),
+            ..self
+        }
+    }
+
+    /// Add shipping zone
+    pub fn shipping(mut self, zone: ShippingZone) -> Self {
+        self.shipping.push(zone);
+        self
+    }
+
+    pub fn as_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Get stall tags
+    pub fn tags(&self) -> Vec<Tag> {
+        vec![Tag::new(TagData::Identifier(self.id.clone()))]
+    }
+
+    /// Parse stall from a kind 30017 event
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        if event.kind != Kind::Base(KindBase::Stall) {
+            return Err(Error::InvalidKind);
+        }
+        Ok(serde_json::from_str(&event.content)?)
+    }
+}
+
+/// Extra cost for shipping a product to a zone
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProductShipping {
+    /// Shipping zone id
+    pub id: String,
+    pub cost: f64,
+}
+
+/// Product (kind 30018)
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/15.md>
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Product {
+    /// Product id (`d` tag)
+    pub id: String,
+    pub stall_id: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub images: Vec<String>,
+    pub currency: String,
+    pub price: f64,
+    /// Available quantity (`None` for unlimited, ex. digital goods)
+    pub quantity: Option<u64>,
+    /// Specifications (`[key, value]`)
+    #[serde(default)]
+    pub specs: Vec<(String, String)>,
+    #[serde(default)]
+    pub shipping: Vec<ProductShipping>,
+    /// Categories (`t` tags)
+    #[serde(skip)]
+    pub categories: Vec<String>,
+}
+
+impl Product {
+    /// New `Product`
+    pub fn new<S>(id: S, stall_id: S, name: S, currency: S, price: f64) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            id: id.into(),
+            stall_id: stall_id.into(),
+            name: name.into(),
+            description: None,
+            images: Vec::new(),
+            currency: currency.into(),
+            price,
+            quantity: None,
+            specs: Vec::new(),
+            shipping: Vec::new(),
+            categories: Vec::new(),
+        }
+    }
+
+    /// Set description
+    pub fn description<S>(self, description: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            description: Some(description.into()),
+            ..self
+        }
+    }
+
+    /// Add image url
+    pub fn image<S>(mut self, url: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.images.push(url.into());
+        self
+    }
+
+    /// Set available quantity
+    pub fn quantity(self, quantity: u64) -> Self {
+        Self {
+            quantity: Some(quantity),
+            ..self
+        }
+    }
+
+    /// Add specification
+    pub fn spec<S>(mut self, key: S, value: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.specs.push((key.into(), value.into()));
+        self
+    }
+
+    /// Add extra shipping cost for a zone of the stall
+    pub fn shipping(mut self, shipping: ProductShipping) -> Self {
+        self.shipping.push(shipping);
+        self
+    }
+
+    /// Add category
+    pub fn category<S>(mut self, category: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.categories.push(category.into());
+        self
+    }
+
+    pub fn as_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Get product tags
+    pub fn tags(&self) -> Vec<Tag> {
+        let mut tags = vec![Tag::new(TagData::Identifier(self.id.clone()))];
+        tags.extend(
+            self.categories
+                .iter()
+                .map(|c| Tag::from(vec![String::from("t"), c.clone()])),
+        );
+        tags
+    }
+
+    /// Parse product from a kind 30018 event
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        if event.kind != Kind::Base(KindBase::Product) {
+            return Err(Error::InvalidKind);
+        }
+        let mut product: Self = serde_json::from_str(&event.content)?;
+        product.categories = event
+            .tags
+            .iter()
+            .filter(|t| matches!(t.kind(), Ok(TagKind::Custom(k)) if k == "t"))
+            .filter_map(|t| t.content().map(|c| c.to_string()))
+            .collect();
+        Ok(product)
+    }
+}
+
+/// Customer contact details
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomerContact {
+    /// Public key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nostr: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phone: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+}
+
+/// Ordered item
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderItem {
+    pub product_id: String,
+    pub quantity: u64,
+}
+
+/// Payment option
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaymentOption {
+    /// `url`, `btc`, `ln`, `lnurl`
+    #[serde(rename = "type")]
+    pub payment_type: String,
+    pub link: String,
+}
+
+/// Order, sent by the customer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Order {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    pub contact: CustomerContact,
+    pub items: Vec<OrderItem>,
+    pub shipping_id: String,
+}
+
+/// Payment request, sent by the merchant
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaymentRequest {
+    /// Order id
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    pub payment_options: Vec<PaymentOption>,
+}
+
+/// Order status update, sent by the merchant
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderStatus {
+    /// Order id
+    pub id: String,
+    pub message: String,
+    pub paid: bool,
+    pub shipped: bool,
+}
+
+/// Checkout message, exchanged as encrypted direct message
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/15.md>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckoutMessage {
+    /// Type 0
+    Order(Order),
+    /// Type 1
+    PaymentRequest(PaymentRequest),
+    /// Type 2
+    OrderStatus(OrderStatus),
+}
+
+impl CheckoutMessage {
+    pub fn as_json(&self) -> Result<String, Error> {
+        let (message_type, mut value) = match self {
+            Self::Order(order) => (0, json!(order)),
+            Self::PaymentRequest(request) => (1, json!(request)),
+            Self::OrderStatus(status) => (2, json!(status)),
+        };
+        if let Value::Object(map) = &mut value {
+            map.insert(String::from("type"), json!(message_type));
+        }
+        Ok(serde_json::to_string(&value)?)
+    }
+
+    pub fn from_json<S>(json: S) -> Result<Self, Error>
+    where
+        S: Into<String>,
+    {
+        let value: Value = serde_json::from_str(&json.into())?;
+        match value.get("type").and_then(|t| t.as_u64()) {
+            Some(0) => Ok(Self::Order(serde_json::from_value(value)?)),
+            Some(1) => Ok(Self::PaymentRequest(serde_json::from_value(value)?)),
+            Some(2) => Ok(Self::OrderStatus(serde_json::from_value(value)?)),
+            _ => Err(Error::UnknownMessageType),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::nips::test_util::build_event;
+    use crate::{EventBuilder, Keys, Result};
+
+    const PRODUCT: &str =
+        r#""id": "product-1", "stall_id": "stall-1", "name": "Wallet", "currency": "USD""#;
+
+    fn product(fields: &str, tags_json: &str) -> Result<Product, Error> {
+        let content = format!("{{{}{}}}", PRODUCT, fields);
+        Product::from_event(&build_event(
+            Kind::Base(KindBase::Product),
+            &content,
+            tags_json,
+        ))
+    }
+
+    #[test]
+    fn test_marketplace() -> Result<()> {
+        let merchant = Keys::generate_from_os_random();
+
+        let stall = Stall::new("stall-1", "Hardware", "USD").shipping(ShippingZone {
+            id: String::from("eu"),
+            name: Some(String::from("Europe")),
+            cost: 10.0,
+            regions: vec![String::from("Italy"), String::from("Germany")],
+        });
+        let event = EventBuilder::new_stall(&stall)?.to_event(&merchant)?;
+        assert_eq!(event.identifier(), Some("stall-1"));
+        assert_eq!(Stall::from_event(&event)?, stall);
+
+        let product = Product::new("product-1", "stall-1", "Hardware wallet", "USD", 79.99)
+            .image("https://example.com/wallet.png")
+            .quantity(10)
+            .spec("color", "black")
+            .shipping(ProductShipping {
+                id: String::from("eu"),
+                cost: 5.0,
+            })
+            .category("bitcoin");
+        let event = EventBuilder::new_product(&product)?.to_event(&merchant)?;
+        assert_eq!(event.identifier(), Some("product-1"));
+        assert_eq!(Product::from_event(&event)?, product);
+        assert!(matches!(Stall::from_event(&event), Err(Error::InvalidKind)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_product_quantity() -> Result<()> {
+        // `null` (or missing) for unlimited quantity, i.e. digital goods
+        assert_eq!(
+            product(r#", "price": 1, "quantity": null"#, "[]")?.quantity,
+            None
+        );
+        assert_eq!(product(r#", "price": 1"#, "[]")?.quantity, None);
+        // Sold out
+        assert_eq!(
+            product(r#", "price": 1, "quantity": 0"#, "[]")?.quantity,
+            Some(0)
+        );
+        assert!(matches!(
+            product(r#", "price": 1, "quantity": -1"#, "[]"),
+            Err(Error::Json(_))
+        ));
+
+        // The price is a number, also without decimals
+        assert_eq!(product(r#", "price": 21"#, "[]")?.price, 21.0);
+        assert!(matches!(
+            product(r#", "price": "79.99""#, "[]"),
+            Err(Error::Json(_))
+        ));
+        assert!(matches!(product("", "[]"), Err(Error::Json(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_product_categories() -> Result<()> {
+        // Categories are `t` tags, so that relays can filter them, not part of the content
+        let product = product(
+            r#", "price": 1, "categories": ["ignored"]"#,
+            r#"[["d","product-1"],["t","bitcoin"],["t"],["t","wallet"]]"#,
+        )?;
+        assert_eq!(product.categories, vec!["bitcoin", "wallet"]);
+
+        let json: Value = serde_json::from_str(&product.as_json()?)?;
+        assert!(json.get("categories").is_none());
+        let tags: Vec<Vec<String>> = product.tags().iter().map(|t| t.as_vec()).collect();
+        assert_eq!(
+            tags,
+            vec![
+                vec![String::from("d"), String::from("product-1")],
+                vec![String::from("t"), String::from("bitcoin")],
+                vec![String::from("t"), String::from("wallet")],
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stall_shipping_zones() -> Result<()> {
+        let event = build_event(
+            Kind::Base(KindBase::Stall),
+            r#"{
+                "id": "stall-1",
+                "name": "Hardware",
+                "description": "Bitcoin hardware",
+                "currency": "USD",
+                "shipping": [{"id": "eu", "name": "Europe", "cost": 10.5, "regions": ["Italy", "Germany"]}, {"id": "world", "cost": 0, "regions": []}]
+            }"#,
+            r#"[["d","stall-1"]]"#,
+        );
+        assert_eq!(
+            Stall::from_event(&event)?,
+            Stall::new("stall-1", "Hardware", "USD")
+                .description("Bitcoin hardware")
+                .shipping(ShippingZone {
+                    id: String::from("eu"),
+                    name: Some(String::from("Europe")),
+                    cost: 10.5,
+                    regions: vec![String::from("Italy"), String::from("Germany")],
+                })
+                .shipping(ShippingZone {
+                    id: String::from("world"),
+                    name: None,
+                    cost: 0.0,
+                    regions: Vec::new(),
+                })
+        );
+
+        // The zones must have a cost and the regions
+        for content in [
+            r#"{"id": "stall-1", "name": "Hardware"}"#,
+            r#"{"id": "stall-1", "name": "Hardware", "currency": "USD", "shipping": [{"id": "eu"}]}"#,
+        ] {
+            let event = build_event(Kind::Base(KindBase::Stall), content, "[]");
+            assert!(matches!(Stall::from_event(&event), Err(Error::Json(_))));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_checkout_message_type() -> Result<()> {
+        let status = CheckoutMessage::OrderStatus(OrderStatus {
+            id: String::from("order-1"),
+            message: String::from("Shipped"),
+            paid: true,
+            shipped: true,
+        });
+        let json = status.as_json()?;
+        assert!(json.contains(r#""type":2"#));
+        assert_eq!(CheckoutMessage::from_json(json)?, status);
+
+        // The type must be a known number
+        for json in [
+            r#"{"id": "order-1"}"#,
+            r#"{"id": "order-1", "type": "0"}"#,
+            r#"{"id": "order-1", "type": 3}"#,
+        ] {
+            assert!(matches!(
+                CheckoutMessage::from_json(json),
+                Err(Error::UnknownMessageType)
+            ));
+        }
+        // A known type with the fields of another one
+        for json in [
+            "not json",
+            r#"{"id": "order-1", "type": 0, "message": "Shipped", "paid": true, "shipped": true}"#,
+            r#"{"id": "order-1", "type": 1, "payment_options": [{"link": "lnbc100n1..."}]}"#,
+            r#"{"id": "order-1", "type": 2, "message": "Shipped"}"#,
+        ] {
+            assert!(matches!(
+                CheckoutMessage::from_json(json),
+                Err(Error::Json(_))
+            ));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_checkout_spec_examples() -> Result<()> {
+        assert_eq!(
+            CheckoutMessage::from_json(
+                r#"{
+                    "id": "order-1",
+                    "type": 0,
+                    "name": "Satoshi",
+                    "address": "Via Roma 1",
+                    "message": "Ring twice",
+                    "contact": {"nostr": "npub1sn0wdenkukak0d9dfczzeacvhkrgz92ak56egt7vdgzn8pv2wfqqhrjdv9", "email": "satoshi@example.com"},
+                    "items": [{"product_id": "product-1", "quantity": 2}],
+                    "shipping_id": "eu"
+                }"#
+            )?,
+            CheckoutMessage::Order(Order {
+                id: String::from("order-1"),
+                name: Some(String::from("Satoshi")),
+                address: Some(String::from("Via Roma 1")),
+                message: Some(String::from("Ring twice")),
+                contact: CustomerContact {
+                    nostr: Some(String::from(
+                        "npub1sn0wdenkukak0d9dfczzeacvhkrgz92ak56egt7vdgzn8pv2wfqqhrjdv9"
+                    )),
+                    phone: None,
+                    email: Some(String::from("satoshi@example.com")),
+                },
+                items: vec![OrderItem {
+                    product_id: String::from("product-1"),
+                    quantity: 2,
+                }],
+                shipping_id: String::from("eu"),
+            })
+        );
+
+        assert_eq!(
+            CheckoutMessage::from_json(
+                r#"{
+                    "id": "order-1",
+                    "type": 1,
+                    "message": "Pay here",
+                    "payment_options": [
+                        {"type": "ln", "link": "lnbc100n1..."},
+                        {"type": "btc", "link": "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh"}
+                    ]
+                }"#
+            )?,
+            CheckoutMessage::PaymentRequest(PaymentRequest {
+                id: String::from("order-1"),
+                message: Some(String::from("Pay here")),
+                payment_options: vec![
+                    PaymentOption {
+                        payment_type: String::from("ln"),
+                        link: String::from("lnbc100n1..."),
+                    },
+                    PaymentOption {
+                        payment_type: String::from("btc"),
+                        link: String::from("bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh"),
+                    },
+                ],
+            })
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,172 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use crate::event::{self, TagKind};
+use crate::{Event, Kind, KindBase};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Event error
+    #[error("event error: {0}")]
+    Event(#[from] event::Error),
+    /// Invalid event kind
+    #[error("invalid event kind")]
+    InvalidKind,
+    /// The reposted event is not embedded in the content
+    #[error("reposted event not embedded")]
+    NotEmbedded,
+    /// The embedded event doesn't match the `e` tag
+    #[error("event mismatch")]
+    EventMismatch,
+}
+
+/// Check if the event is a repost (kind 6 or 16)
+pub fn is_repost(event: &Event) -> bool {
+    matches!(
+        event.kind,
+        Kind::Base(KindBase::Boost) | Kind::Base(KindBase::GenericRepost)
+    )
+}
+
+/// Get the reposted event embedded in a repost
+///
+/// The embedded event must be referenced by the `e` tag and have a valid signature.
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/18.md>
+pub fn reposted_event(repost: &Event) -> Result<Event, Error> {
+    if !is_repost(repost) {
+        return Err(Error::InvalidKind);
+    }
+
+    if repost.content.trim().is_empty() {
+        return Err(Error::NotEmbedded);
+    }
+
+    let event: Event = Event::from_json(&repost.content)?;
+    let referenced = repost
+        .tags
+        .iter()
+        .find(|t| matches!(t.kind(), Ok(TagKind::E)))
+        .and_then(|t| t.content());
+    if referenced != Some(event.id.to_string().as_str()) {
+        return Err(Error::EventMismatch);
+    }
+
+    Ok(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::nips::test_util::tags;
+    use crate::{EventBuilder, Keys, Result};
+
+    #[test]
+    fn test_repost() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+
+        let note = EventBuilder::new_text_note("Hello", &[]).to_event(&keys)?;
+        let repost = EventBuilder::repost(&note, None).to_event(&keys)?;
+        assert_eq!(repost.kind, Kind::Base(KindBase::Boost));
+        assert_eq!(reposted_event(&repost)?, note);
+
+        // Any other kind is a generic repost, with the `k` tag
+        let article = EventBuilder::new(Kind::Base(KindBase::LongFormTextNote), "Article", &[])
+            .identifier("my-article")
+            .to_event(&keys)?;
+        let repost = EventBuilder::repost(&article, None).to_event(&keys)?;
+        assert_eq!(repost.kind, Kind::Base(KindBase::GenericRepost));
+        assert!(repost.tags.iter().any(|t| t.as_vec() == ["k", "30023"]));
+        assert!(repost
+            .tags
+            .iter()
+            .any(|t| matches!(t.kind(), Ok(TagKind::A))));
+        assert_eq!(reposted_event(&repost)?, article);
+
+        assert!(matches!(reposted_event(&note), Err(Error::InvalidKind)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_first_e_tag_is_the_reposted_event() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        let note = EventBuilder::new_text_note("Hello", &[]).to_event(&keys)?;
+        let other = EventBuilder::new_text_note("Other", &[]).to_event(&keys)?;
+        let content = note.as_json()?;
+
+        let repost = |tags_json: String| {
+            EventBuilder::new(Kind::Base(KindBase::Boost), &content, &tags(&tags_json))
+                .to_event(&keys)
+        };
+
+        let ok = repost(format!(
+            r#"[["e","{}","wss://relay.damus.io"],["e","{}"]]"#,
+            note.id, other.id
+        ))?;
+        assert_eq!(reposted_event(&ok)?, note);
+
+        // Other events may be referenced, but not before the reposted one
+        let mismatch = repost(format!(r#"[["e","{}"],["e","{}"]]"#, other.id, note.id))?;
+        assert!(matches!(
+            reposted_event(&mismatch),
+            Err(Error::EventMismatch)
+        ));
+        let missing = repost(format!(r#"[["p","{}"]]"#, keys.public_key()))?;
+        assert!(matches!(
+            reposted_event(&missing),
+            Err(Error::EventMismatch)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_repost_of_repost() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        let note = EventBuilder::new_text_note("Hello", &[]).to_event(&keys)?;
+        let repost = EventBuilder::repost(&note, None).to_event(&keys)?;
+        let repost_of_repost = EventBuilder::repost(&repost, None).to_event(&keys)?;
+
+        // Only one level is unwrapped
+        assert_eq!(repost_of_repost.kind, Kind::Base(KindBase::GenericRepost));
+        let reposted = reposted_event(&repost_of_repost)?;
+        assert_eq!(reposted, repost);
+        assert_eq!(reposted_event(&reposted)?, note);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_repost_without_embedded_event() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        let note = EventBuilder::new_text_note("Hello", &[]).to_event(&keys)?;
+        let e_tag = tags(&format!(r#"[["e","{}"]]"#, note.id));
+
+        // i.e. reposts of NIP-70 protected events, only referenced by the tags
+        for content in ["", "  \n"] {
+            let repost =
+                EventBuilder::new(Kind::Base(KindBase::Boost), content, &e_tag).to_event(&keys)?;
+            assert!(matches!(reposted_event(&repost), Err(Error::NotEmbedded)));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tampered_embedded_event() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        let note = EventBuilder::new_text_note("Hello", &[]).to_event(&keys)?;
+        let e_tag = tags(&format!(r#"[["e","{}"]]"#, note.id));
+
+        // The `e` tag matches, but the content doesn't match the id and the signature
+        let tampered = note.as_json()?.replace("Hello", "Hallo");
+        for content in [String::from("not json"), String::from("{}"), tampered] {
+            let repost =
+                EventBuilder::new(Kind::Base(KindBase::Boost), &content, &e_tag).to_event(&keys)?;
+            assert!(matches!(reposted_event(&repost), Err(Error::Event(_))));
+        }
+
+        Ok(())
+    }
+}
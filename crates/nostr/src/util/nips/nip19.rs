@@ -0,0 +1,528 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::fmt;
+use std::str::FromStr;
+use std::string::FromUtf8Error;
+
+use bitcoin::bech32::{self, FromBase32, ToBase32, Variant};
+use bitcoin::hashes::Hash;
+use bitcoin::secp256k1::{SecretKey, XOnlyPublicKey};
+
+pub use crate::key::{FromBech32, ToBech32};
+use crate::{Kind, Sha256Hash};
+
+const PREFIX_BECH32_SECRET_KEY: &str = "nsec";
+const PREFIX_BECH32_PUBLIC_KEY: &str = "npub";
+const PREFIX_BECH32_NOTE: &str = "note";
+const PREFIX_BECH32_PROFILE: &str = "nprofile";
+const PREFIX_BECH32_EVENT: &str = "nevent";
+const PREFIX_BECH32_COORDINATE: &str = "naddr";
+
+const SPECIAL: u8 = 0;
+const RELAY: u8 = 1;
+const AUTHOR: u8 = 2;
+const KIND: u8 = 3;
+
+#[derive(Debug, Eq, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// Bech32 error
+    #[error("bech32 error: {0}")]
+    Bech32(#[from] bech32::Error),
+    /// Unknown prefix or wrong variant
+    #[error("wrong prefix or variant")]
+    WrongPrefixOrVariant,
+    /// Field missing
+    #[error("field missing: {0}")]
+    FieldMissing(String),
+    /// Invalid TLV
+    #[error("invalid TLV")]
+    TLV,
+    /// UTF-8 error
+    #[error("utf-8 error: {0}")]
+    Utf8(#[from] FromUtf8Error),
+    /// Hash error
+    #[error("hash error: {0}")]
+    Hash(#[from] bitcoin::hashes::Error),
+    /// Secp256k1 error
+    #[error("secp256k1 error: {0}")]
+    Secp256k1(#[from] bitcoin::secp256k1::Error),
+    /// Invalid coordinate (`kind:pubkey:d`)
+    #[error("invalid coordinate")]
+    InvalidCoordinate,
+}
+
+fn decode(s: String, prefix: &str) -> Result<Vec<u8>, Error> {
+    let (hrp, data, checksum) = bech32::decode(&s)?;
+    if hrp != prefix || checksum != Variant::Bech32 {
+        return Err(Error::WrongPrefixOrVariant);
+    }
+    Ok(Vec::<u8>::from_base32(&data)?)
+}
+
+fn encode(prefix: &str, data: Vec<u8>) -> Result<String, Error> {
+    Ok(bech32::encode(prefix, data.to_base32(), Variant::Bech32)?)
+}
+
+fn push_tlv(data: &mut Vec<u8>, t: u8, value: &[u8]) -> Result<(), Error> {
+    let len: u8 = value.len().try_into().map_err(|_| Error::TLV)?;
+    data.push(t);
+    data.push(len);
+    data.extend_from_slice(value);
+    Ok(())
+}
+
+/// Fields of a TLV entity (unknown types are ignored)
+#[derive(Default)]
+struct Tlv {
+    special: Option<Vec<u8>>,
+    relays: Vec<String>,
+    author: Option<XOnlyPublicKey>,
+    kind: Option<Kind>,
+}
+
+impl Tlv {
+    fn parse(mut data: &[u8]) -> Result<Self, Error> {
+        let mut tlv = Self::default();
+
+        while !data.is_empty() {
+            let t = data[0];
+            let len = *data.get(1).ok_or(Error::TLV)? as usize;
+            let value = data.get(2..2 + len).ok_or(Error::TLV)?;
+
+            match t {
+                SPECIAL if tlv.special.is_none() => tlv.special = Some(value.to_vec()),
+                RELAY => tlv.relays.push(String::from_utf8(value.to_vec())?),
+                AUTHOR if tlv.author.is_none() => {
+                    tlv.author = Some(XOnlyPublicKey::from_slice(value)?)
+                }
+                KIND if tlv.kind.is_none() => {
+                    let bytes: [u8; 4] = value.try_into().map_err(|_| Error::TLV)?;
+                    tlv.kind = Some(Kind::from(u32::from_be_bytes(bytes) as u64));
+                }
+                _ => (),
+            }
+
+            data = &data[2 + len..];
+        }
+
+        Ok(tlv)
+    }
+
+    fn special(&self) -> Result<&[u8], Error> {
+        self.special
+            .as_deref()
+            .ok_or_else(|| Error::FieldMissing(String::from("special")))
+    }
+}
+
+impl ToBech32 for Sha256Hash {
+    type Err = Error;
+
+    fn to_bech32(&self) -> Result<String, Self::Err> {
+        encode(PREFIX_BECH32_NOTE, self.to_vec())
+    }
+}
+
+impl FromBech32 for Sha256Hash {
+    type Err = Error;
+
+    fn from_bech32<S>(s: S) -> Result<Self, Self::Err>
+    where
+        S: Into<String>,
+    {
+        let data = decode(s.into(), PREFIX_BECH32_NOTE)?;
+        Ok(Sha256Hash::from_slice(&data)?)
+    }
+}
+
+/// Profile reference (`nprofile`)
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Profile {
+    pub public_key: XOnlyPublicKey,
+    /// Relays where the profile is likely to be found
+    pub relays: Vec<String>,
+}
+
+impl Profile {
+    /// New `Profile`
+    pub fn new<S>(public_key: XOnlyPublicKey, relays: Vec<S>) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            public_key,
+            relays: relays.into_iter().map(|u| u.into()).collect(),
+        }
+    }
+}
+
+impl ToBech32 for Profile {
+    type Err = Error;
+
+    fn to_bech32(&self) -> Result<String, Self::Err> {
+        let mut data = Vec::new();
+        push_tlv(&mut data, SPECIAL, &self.public_key.serialize())?;
+        for relay in self.relays.iter() {
+            push_tlv(&mut data, RELAY, relay.as_bytes())?;
+        }
+        encode(PREFIX_BECH32_PROFILE, data)
+    }
+}
+
+impl FromBech32 for Profile {
+    type Err = Error;
+
+    fn from_bech32<S>(s: S) -> Result<Self, Self::Err>
+    where
+        S: Into<String>,
+    {
+        let tlv = Tlv::parse(&decode(s.into(), PREFIX_BECH32_PROFILE)?)?;
+        Ok(Self {
+            public_key: XOnlyPublicKey::from_slice(tlv.special()?)?,
+            relays: tlv.relays,
+        })
+    }
+}
+
+/// Event reference (`nevent`)
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Nip19Event {
+    pub event_id: Sha256Hash,
+    pub author: Option<XOnlyPublicKey>,
+    pub kind: Option<Kind>,
+    /// Relays where the event is likely to be found
+    pub relays: Vec<String>,
+}
+
+impl Nip19Event {
+    /// New `Nip19Event`
+    pub fn new<S>(event_id: Sha256Hash, relays: Vec<S>) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            event_id,
+            author: None,
+            kind: None,
+            relays: relays.into_iter().map(|u| u.into()).collect(),
+        }
+    }
+
+    /// Set author
+    pub fn author(self, author: XOnlyPublicKey) -> Self {
+        Self {
+            author: Some(author),
+            ..self
+        }
+    }
+
+    /// Set kind
+    pub fn kind(self, kind: Kind) -> Self {
+        Self {
+            kind: Some(kind),
+            ..self
+        }
+    }
+}
+
+impl ToBech32 for Nip19Event {
+    type Err = Error;
+
+    fn to_bech32(&self) -> Result<String, Self::Err> {
+        let mut data = Vec::new();
+        push_tlv(&mut data, SPECIAL, &self.event_id)?;
+        for relay in self.relays.iter() {
+            push_tlv(&mut data, RELAY, relay.as_bytes())?;
+        }
+        if let Some(author) = &self.author {
+            push_tlv(&mut data, AUTHOR, &author.serialize())?;
+        }
+        if let Some(kind) = self.kind {
+            push_tlv(&mut data, KIND, &(u64::from(kind) as u32).to_be_bytes())?;
+        }
+        encode(PREFIX_BECH32_EVENT, data)
+    }
+}
+
+impl FromBech32 for Nip19Event {
+    type Err = Error;
+
+    fn from_bech32<S>(s: S) -> Result<Self, Self::Err>
+    where
+        S: Into<String>,
+    {
+        let tlv = Tlv::parse(&decode(s.into(), PREFIX_BECH32_EVENT)?)?;
+        Ok(Self {
+            event_id: Sha256Hash::from_slice(tlv.special()?)?,
+            author: tlv.author,
+            kind: tlv.kind,
+            relays: tlv.relays,
+        })
+    }
+}
+
+/// Parameterized replaceable event coordinate (`naddr`)
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Coordinate {
+    pub kind: Kind,
+    pub public_key: XOnlyPublicKey,
+    /// `d` tag identifier
+    pub identifier: String,
+    /// Relays where the event is likely to be found
+    pub relays: Vec<String>,
+}
+
+impl Coordinate {
+    /// New `Coordinate`
+    pub fn new<S>(kind: Kind, public_key: XOnlyPublicKey, identifier: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            kind,
+            public_key,
+            identifier: identifier.into(),
+            relays: Vec::new(),
+        }
+    }
+
+    /// Set relays
+    pub fn relays<S>(self, relays: Vec<S>) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            relays: relays.into_iter().map(|u| u.into()).collect(),
+            ..self
+        }
+    }
+}
+
+/// Format as `<kind>:<pubkey>:<d>` (relays are ignored)
+impl fmt::Display for Coordinate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}",
+            u64::from(self.kind),
+            self.public_key,
+            self.identifier
+        )
+    }
+}
+
+/// Parse `<kind>:<pubkey>:<d>`
+impl FromStr for Coordinate {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(3, ':');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(kind), Some(public_key), Some(identifier)) => Ok(Self::new(
+                Kind::from(kind.parse::<u64>().map_err(|_| Error::InvalidCoordinate)?),
+                XOnlyPublicKey::from_str(public_key)?,
+                identifier,
+            )),
+            _ => Err(Error::InvalidCoordinate),
+        }
+    }
+}
+
+impl ToBech32 for Coordinate {
+    type Err = Error;
+
+    fn to_bech32(&self) -> Result<String, Self::Err> {
+        let mut data = Vec::new();
+        push_tlv(&mut data, SPECIAL, self.identifier.as_bytes())?;
+        for relay in self.relays.iter() {
+            push_tlv(&mut data, RELAY, relay.as_bytes())?;
+        }
+        push_tlv(&mut data, AUTHOR, &self.public_key.serialize())?;
+        push_tlv(
+            &mut data,
+            KIND,
+            &(u64::from(self.kind) as u32).to_be_bytes(),
+        )?;
+        encode(PREFIX_BECH32_COORDINATE, data)
+    }
+}
+
+impl FromBech32 for Coordinate {
+    type Err = Error;
+
+    fn from_bech32<S>(s: S) -> Result<Self, Self::Err>
+    where
+        S: Into<String>,
+    {
+        let tlv = Tlv::parse(&decode(s.into(), PREFIX_BECH32_COORDINATE)?)?;
+        Ok(Self {
+            identifier: String::from_utf8(tlv.special()?.to_vec())?,
+            public_key: tlv
+                .author
+                .ok_or_else(|| Error::FieldMissing(String::from("author")))?,
+            kind: tlv
+                .kind
+                .ok_or_else(|| Error::FieldMissing(String::from("kind")))?,
+            relays: tlv.relays,
+        })
+    }
+}
+
+/// Any NIP-19 entity
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Nip19 {
+    /// `npub`
+    Pubkey(XOnlyPublicKey),
+    /// `nsec`
+    Secret(SecretKey),
+    /// `note`
+    EventId(Sha256Hash),
+    /// `nprofile`
+    Profile(Profile),
+    /// `nevent`
+    Event(Nip19Event),
+    /// `naddr`
+    Coordinate(Coordinate),
+}
+
+impl FromBech32 for Nip19 {
+    type Err = Error;
+
+    fn from_bech32<S>(s: S) -> Result<Self, Self::Err>
+    where
+        S: Into<String>,
+    {
+        let s: String = s.into();
+        let (hrp, ..) = bech32::decode(&s)?;
+        match hrp.as_str() {
+            PREFIX_BECH32_PUBLIC_KEY => Ok(Self::Pubkey(XOnlyPublicKey::from_slice(&decode(
+                s,
+                PREFIX_BECH32_PUBLIC_KEY,
+            )?)?)),
+            PREFIX_BECH32_SECRET_KEY => Ok(Self::Secret(SecretKey::from_slice(&decode(
+                s,
+                PREFIX_BECH32_SECRET_KEY,
+            )?)?)),
+            PREFIX_BECH32_NOTE => Ok(Self::EventId(Sha256Hash::from_bech32(s)?)),
+            PREFIX_BECH32_PROFILE => Ok(Self::Profile(Profile::from_bech32(s)?)),
+            PREFIX_BECH32_EVENT => Ok(Self::Event(Nip19Event::from_bech32(s)?)),
+            PREFIX_BECH32_COORDINATE => Ok(Self::Coordinate(Coordinate::from_bech32(s)?)),
+            _ => Err(Error::WrongPrefixOrVariant),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{Keys, KindBase};
+
+    #[test]
+    fn test_nprofile_vector() {
+        let nprofile = "nprofile1qqsrhuxx8l9ex335q7he0f09aej04zpazpl0ne2cgukyawd24mayt8gpp4mhxue69uhhytnc9e3k7mgpz4mhxue69uhkg6nzv9ejuumpv34kytnrdaksjlyr9p";
+        let profile = Profile::from_bech32(nprofile).unwrap();
+        assert_eq!(
+            profile.public_key,
+            XOnlyPublicKey::from_str(
+                "3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d"
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            profile.relays,
+            vec!["wss://r.x.com", "wss://djbas.sadkb.com"]
+        );
+        assert_eq!(profile.to_bech32().unwrap(), nprofile);
+    }
+
+    #[test]
+    fn test_note_and_nevent() {
+        let event_id = Sha256Hash::from_str(
+            "d94a3f4dd87b9a3b0bed183b32e916fa29c8020107845d1752d72697fe5309a5",
+        )
+        .unwrap();
+        let note = event_id.to_bech32().unwrap();
+        assert!(note.starts_with("note1"));
+        assert_eq!(Sha256Hash::from_bech32(note.clone()).unwrap(), event_id);
+        assert_eq!(Nip19::from_bech32(note).unwrap(), Nip19::EventId(event_id));
+
+        let author = XOnlyPublicKey::from_str(
+            "3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d",
+        )
+        .unwrap();
+        let event = Nip19Event::new(event_id, vec!["wss://relay.damus.io"])
+            .author(author)
+            .kind(Kind::Base(KindBase::TextNote));
+        let nevent = event.to_bech32().unwrap();
+        assert_eq!(Nip19Event::from_bech32(nevent).unwrap(), event);
+    }
+
+    #[test]
+    fn test_naddr() {
+        let public_key = XOnlyPublicKey::from_str(
+            "3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d",
+        )
+        .unwrap();
+        let coordinate = Coordinate::new(
+            Kind::Base(KindBase::LongFormTextNote),
+            public_key,
+            "my-article",
+        )
+        .relays(vec!["wss://relay.damus.io"]);
+        let coordinate_str =
+            "30023:3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d:my-article";
+        assert_eq!(coordinate.to_string(), coordinate_str);
+        assert_eq!(
+            Coordinate::from_str(coordinate_str).unwrap(),
+            coordinate.clone().relays(Vec::<String>::new())
+        );
+        assert_eq!(
+            Coordinate::from_str("30023:abc").unwrap_err(),
+            Error::InvalidCoordinate
+        );
+
+        let naddr = coordinate.to_bech32().unwrap();
+        assert!(naddr.starts_with("naddr1"));
+        assert_eq!(
+            Nip19::from_bech32(naddr).unwrap(),
+            Nip19::Coordinate(coordinate)
+        );
+    }
+
+    #[test]
+    fn test_npub() {
+        assert_eq!(
+            Nip19::from_bech32("npub10elfcs4fr0l0r8af98jlmgdh9c8tcxjvz9qkw038js35mp4dma8qzvjptg")
+                .unwrap(),
+            Nip19::Pubkey(
+                XOnlyPublicKey::from_str(
+                    "7e7e9c42a91bfef19fa929e5fda1b72e0ebc1a4c1141673e2794234d86addf4e"
+                )
+                .unwrap()
+            )
+        );
+        assert_eq!(
+            Nip19::from_bech32("nfoo1qqqqqqqqqqqqqq").unwrap_err(),
+            Error::Bech32(bech32::Error::InvalidChecksum)
+        );
+    }
+
+    #[test]
+    fn test_shared_trait() {
+        fn parse<T: FromBech32>(s: &str) -> Option<T> {
+            T::from_bech32(s).ok()
+        }
+
+        let keys: Keys =
+            parse("nsec1j4c6269y9w0q2er2xjw8sv2ehyrtfxq3jwgdlxj6qfn8z4gjsq5qfvfk99").unwrap();
+        let npub = keys.public_key().to_bech32().unwrap();
+        assert_eq!(
+            parse::<Nip19>(&npub),
+            Some(Nip19::Pubkey(keys.public_key()))
+        );
+        assert!(parse::<Keys>(&npub).is_none());
+    }
+}
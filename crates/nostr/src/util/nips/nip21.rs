@@ -0,0 +1,198 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::str::FromStr;
+
+use bitcoin::secp256k1::XOnlyPublicKey;
+
+use super::nip19::{self, Coordinate, FromBech32, Nip19, Nip19Event, Profile};
+use crate::key::{self, ToBech32};
+use crate::Sha256Hash;
+
+/// URI scheme
+pub const SCHEME: &str = "nostr";
+
+#[derive(Debug, Eq, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// Key error
+    #[error("key error: {0}")]
+    Key(#[from] key::Error),
+    /// NIP19 error
+    #[error("nip19 error: {0}")]
+    NIP19(#[from] nip19::Error),
+    /// The URI doesn't start with `nostr:`
+    #[error("invalid scheme")]
+    InvalidScheme,
+    /// `nsec` can't be shared with a `nostr:` URI
+    #[error("secret key not allowed")]
+    SecretKeyNotAllowed,
+}
+
+/// `nostr:` URI
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/21.md>
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum NostrURI {
+    /// `npub`
+    Pubkey(XOnlyPublicKey),
+    /// `nprofile`
+    Profile(Profile),
+    /// `note`
+    EventId(Sha256Hash),
+    /// `nevent`
+    Event(Nip19Event),
+    /// `naddr`
+    Coordinate(Coordinate),
+}
+
+impl NostrURI {
+    /// Serialize as `nostr:<bech32>`
+    pub fn to_nostr_uri(&self) -> Result<String, Error> {
+        let bech32: String = match self {
+            Self::Pubkey(public_key) => public_key.to_bech32()?,
+            Self::Profile(profile) => profile.to_bech32()?,
+            Self::EventId(id) => id.to_bech32()?,
+            Self::Event(event) => event.to_bech32()?,
+            Self::Coordinate(coordinate) => coordinate.to_bech32()?,
+        };
+        Ok(format!("{}:{}", SCHEME, bech32))
+    }
+}
+
+impl TryFrom<Nip19> for NostrURI {
+    type Error = Error;
+
+    fn try_from(value: Nip19) -> Result<Self, Self::Error> {
+        match value {
+            Nip19::Pubkey(public_key) => Ok(Self::Pubkey(public_key)),
+            Nip19::Secret(..) => Err(Error::SecretKeyNotAllowed),
+            Nip19::EventId(id) => Ok(Self::EventId(id)),
+            Nip19::Profile(profile) => Ok(Self::Profile(profile)),
+            Nip19::Event(event) => Ok(Self::Event(event)),
+            Nip19::Coordinate(coordinate) => Ok(Self::Coordinate(coordinate)),
+        }
+    }
+}
+
+impl FromStr for NostrURI {
+    type Err = Error;
+
+    /// Parse `nostr:<bech32>` URI (the scheme is case-insensitive)
+    fn from_str(uri: &str) -> Result<Self, Self::Err> {
+        let (scheme, bech32) = uri.split_once(':').ok_or(Error::InvalidScheme)?;
+        if !scheme.eq_ignore_ascii_case(SCHEME) {
+            return Err(Error::InvalidScheme);
+        }
+        Self::try_from(Nip19::from_bech32(bech32.to_lowercase())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::nips::test_util::public_key;
+    use crate::{Kind, KindBase};
+
+    const NPUB_URI: &str = "nostr:npub1sn0wdenkukak0d9dfczzeacvhkrgz92ak56egt7vdgzn8pv2wfqqhrjdv9";
+
+    #[test]
+    fn test_nostr_uri() {
+        let parsed = NostrURI::from_str(NPUB_URI).unwrap();
+        assert!(matches!(parsed, NostrURI::Pubkey(..)));
+        assert_eq!(parsed.to_nostr_uri().unwrap(), NPUB_URI);
+
+        let coordinate = NostrURI::Coordinate(Coordinate::new(
+            Kind::Base(KindBase::LongFormTextNote),
+            public_key(),
+            "my-article",
+        ));
+        let naddr = coordinate.to_nostr_uri().unwrap();
+        assert!(naddr.starts_with("nostr:naddr1"));
+        assert_eq!(NostrURI::from_str(&naddr).unwrap(), coordinate);
+    }
+
+    #[test]
+    fn test_spec_examples() {
+        let note = "nostr:note1fntxtkcy9pjwucqwa9mddn7v03wwwsu9j330jj350nvhpky2tuaspk6nqc";
+        match NostrURI::from_str(note).unwrap() {
+            NostrURI::EventId(id) => assert_eq!(
+                id.to_string(),
+                "4cd665db042864ee600ee976d6cfcc7c5ce743859462f94a347cd970d88a5f3b"
+            ),
+            _ => panic!("expected note"),
+        }
+
+        // TLV entities keep the relay hints
+        let nprofile = "nostr:nprofile1qqsrhuxx8l9ex335q7he0f09aej04zpazpl0ne2cgukyawd24mayt8gpp4mhxue69uhhytnc9e3k7mgpz4mhxue69uhkg6nzv9ejuumpv34kytnrdaksjlyr9p";
+        match NostrURI::from_str(nprofile).unwrap() {
+            NostrURI::Profile(profile) => assert_eq!(profile.relays.len(), 2),
+            _ => panic!("expected nprofile"),
+        }
+        let nevent = "nostr:nevent1qqstna2yrezu5wghjvswqqculvvwxsrcvu7uc0f78gan4xqhvz49d9spr3mhxue69uhkummnw3ez6un9d3shjtn4de6x2argwghx6egpr4mhxue69uhkummnw3ez6ur4vgh8wetvd3hhyer9wghxuet5nxnepm";
+        match NostrURI::from_str(nevent).unwrap() {
+            NostrURI::Event(event) => assert_eq!(event.relays.len(), 2),
+            _ => panic!("expected nevent"),
+        }
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        let parsed = NostrURI::from_str(NPUB_URI).unwrap();
+
+        // i.e. URIs upper-cased by QR codes
+        assert_eq!(
+            NostrURI::from_str(&NPUB_URI.to_uppercase()).unwrap(),
+            parsed
+        );
+        let (_, bech32) = NPUB_URI.split_once(':').unwrap();
+        assert_eq!(
+            NostrURI::from_str(&format!("Nostr:{}", bech32)).unwrap(),
+            parsed
+        );
+        // Always serialized lower-case
+        assert_eq!(parsed.to_nostr_uri().unwrap(), NPUB_URI);
+    }
+
+    #[test]
+    fn test_secret_key_not_allowed() {
+        assert_eq!(
+            NostrURI::from_str(
+                "nostr:nsec1j4c6269y9w0q2er2xjw8sv2ehyrtfxq3jwgdlxj6qfn8z4gjsq5qfvfk99"
+            )
+            .unwrap_err(),
+            Error::SecretKeyNotAllowed
+        );
+    }
+
+    #[test]
+    fn test_invalid_scheme() {
+        let (_, bech32) = NPUB_URI.split_once(':').unwrap();
+        for uri in [
+            String::new(),
+            String::from("nostr"),
+            // Bare bech32 entity
+            bech32.to_string(),
+            format!("web+nostr:{}", bech32),
+            format!("https://njump.me/{}", bech32),
+        ] {
+            assert_eq!(NostrURI::from_str(&uri).unwrap_err(), Error::InvalidScheme);
+        }
+    }
+
+    #[test]
+    fn test_invalid_entity() {
+        for uri in [
+            "nostr:",
+            "nostr:npub1invalid",
+            // Wrong checksum
+            "nostr:npub1sn0wdenkukak0d9dfczzeacvhkrgz92ak56egt7vdgzn8pv2wfqqhrjdv8",
+            // Valid bech32, not a NIP-19 entity
+            "nostr:lnurl1dp68gurn8ghj7um9wfmxjcm99e3k7mf0v9cxj0m385ekvcenxc6r2c35xvukxefcv5mkvv34x5ekzd3ev56nyd3hxqurzepexejxxepnxscrvwfnv9nxzcn9xq6xyefhvgcxxcmyxymnserxfq5fns",
+        ] {
+            assert!(matches!(
+                NostrURI::from_str(uri).unwrap_err(),
+                Error::NIP19(..)
+            ));
+        }
+    }
+}
@@ -0,0 +1,266 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use crate::event::TagKind;
+use crate::{Event, Kind, KindBase, Tag};
+
+#[derive(Debug, Eq, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// Invalid event kind
+    #[error("invalid event kind")]
+    InvalidKind,
+    /// Identifier (`d` tag) not found
+    #[error("identifier not found")]
+    IdentifierNotFound,
+    /// Invalid `published_at` timestamp
+    #[error("invalid published_at")]
+    InvalidPublishedAt,
+}
+
+/// Long-form content metadata (kind 30023 and 30024 drafts)
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct LongFormContent {
+    /// Article identifier (`d` tag)
+    pub identifier: String,
+    pub title: Option<String>,
+    pub summary: Option<String>,
+    /// Image url
+    pub image: Option<String>,
+    /// Timestamp of the first publication
+    pub published_at: Option<u64>,
+}
+
+impl LongFormContent {
+    /// New `LongFormContent`
+    pub fn new<S>(identifier: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            identifier: identifier.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Set title
+    pub fn title<S>(self, title: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            title: Some(title.into()),
+            ..self
+        }
+    }
+
+    /// Set summary
+    pub fn summary<S>(self, summary: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            summary: Some(summary.into()),
+            ..self
+        }
+    }
+
+    /// Set image
+    pub fn image<S>(self, image: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            image: Some(image.into()),
+            ..self
+        }
+    }
+
+    /// Set published at
+    pub fn published_at(self, timestamp: u64) -> Self {
+        Self {
+            published_at: Some(timestamp),
+            ..self
+        }
+    }
+
+    /// Parse long-form content metadata from a kind 30023 or 30024 event
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        if event.kind != Kind::Base(KindBase::LongFormTextNote)
+            && event.kind != Kind::Base(KindBase::LongFormTextNoteDraft)
+        {
+            return Err(Error::InvalidKind);
+        }
+
+        let identifier = event
+            .tags
+            .iter()
+            .find(|t| matches!(t.kind(), Ok(TagKind::D)))
+            .and_then(|t| t.content())
+            .ok_or(Error::IdentifierNotFound)?;
+
+        let mut content = Self::new(identifier);
+
+        for tag in event.tags.iter() {
+            let tag = tag.as_vec();
+            match (tag.first().map(|t| t.as_str()), tag.get(1)) {
+                (Some("title"), Some(title)) => content.title = Some(title.clone()),
+                (Some("summary"), Some(summary)) => content.summary = Some(summary.clone()),
+                (Some("image"), Some(image)) => content.image = Some(image.clone()),
+                (Some("published_at"), Some(timestamp)) => {
+                    content.published_at =
+                        Some(timestamp.parse().map_err(|_| Error::InvalidPublishedAt)?)
+                }
+                _ => (),
+            }
+        }
+
+        Ok(content)
+    }
+
+    /// Get long-form content tags
+    pub fn tags(&self) -> Vec<Tag> {
+        let mut tags = vec![Tag::from(vec![
+            TagKind::D.to_string(),
+            self.identifier.clone(),
+        ])];
+
+        if let Some(title) = &self.title {
+            tags.push(Tag::from(vec![String::from("title"), title.clone()]));
+        }
+        if let Some(summary) = &self.summary {
+            tags.push(Tag::from(vec![String::from("summary"), summary.clone()]));
+        }
+        if let Some(image) = &self.image {
+            tags.push(Tag::from(vec![String::from("image"), image.clone()]));
+        }
+        if let Some(published_at) = self.published_at {
+            tags.push(Tag::from(vec![
+                String::from("published_at"),
+                published_at.to_string(),
+            ]));
+        }
+
+        tags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::nips::test_util::build_event;
+    use crate::{EventBuilder, Keys, Result};
+
+    fn article(tags_json: &str) -> Result<LongFormContent, Error> {
+        let event = build_event(Kind::Base(KindBase::LongFormTextNote), "", tags_json);
+        LongFormContent::from_event(&event)
+    }
+
+    #[test]
+    fn test_long_form_content() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        let metadata = LongFormContent::new("lorem-ipsum")
+            .title("Lorem Ipsum")
+            .summary("Dolor sit amet")
+            .image("https://example.com/cover.png")
+            .published_at(1296962229);
+
+        let event =
+            EventBuilder::long_form_text_note("# Lorem Ipsum", &metadata).to_event(&keys)?;
+        assert_eq!(event.kind, Kind::Base(KindBase::LongFormTextNote));
+        assert_eq!(LongFormContent::from_event(&event)?, metadata);
+
+        let event = EventBuilder::new_text_note("", &[]).to_event(&keys)?;
+        assert_eq!(
+            LongFormContent::from_event(&event).unwrap_err(),
+            Error::InvalidKind
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_draft() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        let metadata = LongFormContent::new("lorem-ipsum").title("Lorem Ipsum");
+
+        // Same metadata and identifier, but a different replaceable kind
+        let draft =
+            EventBuilder::long_form_text_note_draft("# Lorem", &metadata).to_event(&keys)?;
+        assert_eq!(draft.kind, Kind::Base(KindBase::LongFormTextNoteDraft));
+        assert_eq!(LongFormContent::from_event(&draft)?, metadata);
+        assert_eq!(draft.identifier(), Some("lorem-ipsum"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_published_at_kept_on_edit() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        let metadata = LongFormContent::new("lorem-ipsum").published_at(1296962229);
+
+        // `published_at` is the first publication, not the `created_at` of the last edit
+        let first = EventBuilder::long_form_text_note("# Lorem", &metadata).to_event(&keys)?;
+        let edit = EventBuilder::long_form_text_note("# Lorem Ipsum", &metadata)
+            .custom_created_at(first.created_at + 3600)
+            .to_event(&keys)?;
+        assert_ne!(first.created_at, edit.created_at);
+        assert_eq!(
+            LongFormContent::from_event(&edit)?.published_at,
+            Some(1296962229)
+        );
+
+        assert_eq!(
+            article(r#"[["d","lorem-ipsum"],["published_at","-1"]]"#),
+            Err(Error::InvalidPublishedAt)
+        );
+        assert_eq!(
+            article(r#"[["d","lorem-ipsum"],["published_at","yesterday"]]"#),
+            Err(Error::InvalidPublishedAt)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_identifier() {
+        // Required, but may be empty (NIP-33)
+        assert_eq!(article(r#"[]"#), Err(Error::IdentifierNotFound));
+        assert_eq!(
+            article(r#"[["d"],["title","Lorem Ipsum"]]"#),
+            Err(Error::IdentifierNotFound)
+        );
+        assert_eq!(article(r#"[["d",""]]"#), Ok(LongFormContent::new("")));
+
+        // The first `d` tag is the identifier
+        assert_eq!(
+            article(r#"[["d","lorem-ipsum"],["d","other"]]"#).map(|a| a.identifier),
+            Ok(String::from("lorem-ipsum"))
+        );
+    }
+
+    #[test]
+    fn test_spec_example() {
+        // References (`e`, `a`) and hashtags aren't part of the metadata
+        assert_eq!(
+            article(
+                r#"[
+                    ["d", "lorem-ipsum"],
+                    ["title", "Lorem Ipsum"],
+                    ["published_at", "1296962229"],
+                    ["t", "placeholder"],
+                    ["e", "b3e392b11f5d4f28321cedd09303a748acfd0487aea5a7450b3481c60b6e4f87", "wss://relay.example.com"],
+                    ["a", "30023:a695f6b60119d9521934a691347d9f78e8770b56da16bb255ee286ddf9fda919:ipsum", "wss://relay.nostr.org"]
+                ]"#
+            ),
+            Ok(LongFormContent::new("lorem-ipsum")
+                .title("Lorem Ipsum")
+                .published_at(1296962229))
+        );
+
+        // Tags without a value are skipped
+        assert_eq!(
+            article(r#"[["d","lorem-ipsum"],["title"],["summary"],["published_at"]]"#),
+            Ok(LongFormContent::new("lorem-ipsum"))
+        );
+    }
+}
@@ -1,12 +1,16 @@
 // Copyright (c) 2022 Yuki Kishimoto
 // Distributed under the MIT software license
 
+use std::fmt;
+use std::str::FromStr;
+
 use bitcoin::hashes::Hash;
 use bitcoin::secp256k1::schnorr::Signature;
-use bitcoin::secp256k1::{KeyPair, Message, Secp256k1, XOnlyPublicKey};
+use bitcoin::secp256k1::{KeyPair, Message, XOnlyPublicKey};
 
+use crate::event::{TagData, TagKind};
 use crate::key::{self, Keys};
-use crate::Sha256Hash;
+use crate::{Event, Kind, Sha256Hash, Tag, SECP256K1};
 
 #[derive(Debug, Eq, PartialEq, thiserror::Error)]
 pub enum Error {
@@ -15,6 +19,15 @@ pub enum Error {
     Key(#[from] key::Error),
     #[error("secp256k1 error: {0}")]
     Secp256k1(#[from] bitcoin::secp256k1::Error),
+    /// Invalid conditions string
+    #[error("invalid conditions")]
+    InvalidConditions,
+    /// Event doesn't satisfy the delegation conditions
+    #[error("conditions not satisfied")]
+    ConditionsNotSatisfied,
+    /// Invalid delegation tag
+    #[error("invalid delegation tag")]
+    InvalidTag,
 }
 
 pub fn sign_delegation(
@@ -22,10 +35,400 @@ pub fn sign_delegation(
     delegatee_pk: XOnlyPublicKey,
     conditions: String,
 ) -> Result<Signature, Error> {
-    let secp = Secp256k1::new();
     let keypair: &KeyPair = &keys.key_pair()?;
+    let message = delegation_token(&delegatee_pk, &conditions)?;
+    Ok(SECP256K1.sign_schnorr(&message, keypair))
+}
+
+/// Verify delegation token signature
+pub fn verify_delegation_signature(
+    delegator_pk: &XOnlyPublicKey,
+    sig: &Signature,
+    delegatee_pk: &XOnlyPublicKey,
+    conditions: &str,
+) -> Result<(), Error> {
+    let message = delegation_token(delegatee_pk, conditions)?;
+    Ok(SECP256K1.verify_schnorr(sig, &message, delegator_pk)?)
+}
+
+fn delegation_token(delegatee_pk: &XOnlyPublicKey, conditions: &str) -> Result<Message, Error> {
     let unhashed_token: String = format!("nostr:delegation:{}:{}", delegatee_pk, conditions);
     let hashed_token = Sha256Hash::hash(unhashed_token.as_bytes());
-    let message = Message::from_slice(&hashed_token)?;
-    Ok(secp.sign_schnorr(&message, keypair))
+    Ok(Message::from_slice(&hashed_token)?)
+}
+
+/// Delegation condition
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Condition {
+    /// `kind=<kind>`
+    Kind(u64),
+    /// `created_at><timestamp>`
+    CreatedAfter(u64),
+    /// `created_at<<timestamp>`
+    CreatedBefore(u64),
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Kind(kind) => write!(f, "kind={}", kind),
+            Self::CreatedAfter(timestamp) => write!(f, "created_at>{}", timestamp),
+            Self::CreatedBefore(timestamp) => write!(f, "created_at<{}", timestamp),
+        }
+    }
+}
+
+impl FromStr for Condition {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |value: &str| value.parse::<u64>().map_err(|_| Error::InvalidConditions);
+        if let Some(kind) = s.strip_prefix("kind=") {
+            Ok(Self::Kind(parse(kind)?))
+        } else if let Some(timestamp) = s.strip_prefix("created_at>") {
+            Ok(Self::CreatedAfter(parse(timestamp)?))
+        } else if let Some(timestamp) = s.strip_prefix("created_at<") {
+            Ok(Self::CreatedBefore(parse(timestamp)?))
+        } else {
+            Err(Error::InvalidConditions)
+        }
+    }
+}
+
+/// Delegation conditions (ex. `kind=1&created_at>1674834236&created_at<1677426236`)
+///
+/// All the conditions must be satisfied; multiple `kind` conditions are alternatives.
+///
+/// Parsed conditions keep the original string, since it's what the delegator signed:
+/// unknown conditions are kept in it but not evaluated.
+#[derive(Debug, Clone, Default)]
+pub struct Conditions {
+    conditions: Vec<Condition>,
+    raw: Option<String>,
+}
+
+/// Equal if serialized (and signed) as the same string
+impl PartialEq for Conditions {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_string() == other.to_string()
+    }
+}
+
+impl Eq for Conditions {}
+
+impl Conditions {
+    /// New empty `Conditions`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add condition
+    pub fn add_condition(mut self, condition: Condition) -> Self {
+        if let Some(raw) = self.raw.take() {
+            self.raw = Some(if raw.is_empty() {
+                condition.to_string()
+            } else {
+                format!("{}&{}", raw, condition)
+            });
+        }
+        self.conditions.push(condition);
+        self
+    }
+
+    /// Get the known conditions
+    pub fn conditions(&self) -> &[Condition] {
+        &self.conditions
+    }
+
+    /// Check if an event of `kind` created at `created_at` satisfies the conditions
+    pub fn evaluate(&self, kind: Kind, created_at: u64) -> bool {
+        let kind: u64 = kind.into();
+        let mut kinds = self.conditions.iter().filter_map(|c| match c {
+            Condition::Kind(k) => Some(*k),
+            _ => None,
+        });
+        let kind_allowed = {
+            let mut kinds = kinds.by_ref().peekable();
+            kinds.peek().is_none() || kinds.any(|k| k == kind)
+        };
+
+        kind_allowed
+            && self.conditions.iter().all(|c| match c {
+                Condition::Kind(_) => true,
+                Condition::CreatedAfter(timestamp) => created_at > *timestamp,
+                Condition::CreatedBefore(timestamp) => created_at < *timestamp,
+            })
+    }
+}
+
+impl fmt::Display for Conditions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.raw {
+            Some(raw) => write!(f, "{}", raw),
+            None => {
+                let conditions: Vec<String> =
+                    self.conditions.iter().map(|c| c.to_string()).collect();
+                write!(f, "{}", conditions.join("&"))
+            }
+        }
+    }
+}
+
+impl FromStr for Conditions {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut conditions: Vec<Condition> = Vec::new();
+        for condition in s.split('&').filter(|c| !c.is_empty()) {
+            match Condition::from_str(condition) {
+                Ok(condition) => conditions.push(condition),
+                // Malformed known condition
+                Err(e)
+                    if ["kind=", "created_at>", "created_at<"]
+                        .iter()
+                        .any(|prefix| condition.starts_with(prefix)) =>
+                {
+                    return Err(e)
+                }
+                Err(_) => log::debug!("Unknown delegation condition: {}", condition),
+            }
+        }
+
+        Ok(Self {
+            conditions,
+            raw: Some(s.to_string()),
+        })
+    }
+}
+
+/// Delegation of event signing (`delegation` tag)
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/26.md>
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Delegation {
+    /// Delegator (root) public key
+    pub delegator: XOnlyPublicKey,
+    pub conditions: Conditions,
+    /// Delegation token signature
+    pub sig: Signature,
+}
+
+impl Delegation {
+    /// Sign a delegation for `delegatee_pk` with the delegator keys
+    pub fn new(
+        delegator_keys: &Keys,
+        delegatee_pk: XOnlyPublicKey,
+        conditions: Conditions,
+    ) -> Result<Self, Error> {
+        let sig = sign_delegation(delegator_keys, delegatee_pk, conditions.to_string())?;
+        Ok(Self {
+            delegator: delegator_keys.public_key(),
+            conditions,
+            sig,
+        })
+    }
+
+    /// Parse `delegation` tag
+    pub fn from_tag(tag: &Tag) -> Result<Self, Error> {
+        let tag = tag.as_vec();
+        if tag.len() != 4 || TagKind::from(&tag[0]) != TagKind::Delegation {
+            return Err(Error::InvalidTag);
+        }
+
+        Ok(Self {
+            delegator: XOnlyPublicKey::from_str(&tag[1])?,
+            conditions: Conditions::from_str(&tag[2])?,
+            sig: Signature::from_str(&tag[3])?,
+        })
+    }
+
+    /// Get `delegation` tag
+    pub fn tag(&self) -> Tag {
+        Tag::new(TagData::Delegation {
+            delegator_pk: self.delegator,
+            conditions: self.conditions.to_string(),
+            sig: self.sig,
+        })
+    }
+
+    /// Verify the delegation token for `delegatee_pk`
+    ///
+    /// The signature is checked against the conditions string as parsed from the tag.
+    pub fn verify(&self, delegatee_pk: &XOnlyPublicKey) -> Result<(), Error> {
+        verify_delegation_signature(
+            &self.delegator,
+            &self.sig,
+            delegatee_pk,
+            &self.conditions.to_string(),
+        )
+    }
+}
+
+/// Verify the delegation of an event
+///
+/// Return the delegator public key if the event is delegated, `None` if it has no `delegation` tag.
+pub fn verify_delegated_event(event: &Event) -> Result<Option<XOnlyPublicKey>, Error> {
+    let tag = match event
+        .tags
+        .iter()
+        .find(|t| matches!(t.kind(), Ok(TagKind::Delegation)))
+    {
+        Some(tag) => tag,
+        None => return Ok(None),
+    };
+
+    let delegation = Delegation::from_tag(tag)?;
+    delegation.verify(&event.pubkey)?;
+
+    if !delegation.conditions.evaluate(event.kind, event.created_at) {
+        return Err(Error::ConditionsNotSatisfied);
+    }
+
+    Ok(Some(delegation.delegator))
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::secp256k1::SecretKey;
+
+    use super::*;
+    use crate::{EventBuilder, KindBase};
+
+    #[test]
+    fn test_sign_verify() {
+        let delegator_keys = Keys::new(
+            SecretKey::from_str("ee35e8bb71131c02c1d7e73231daa48e9953d329a4b701f7133c8f46dd21139c")
+                .unwrap(),
+        );
+        let delegatee = XOnlyPublicKey::from_str(
+            "477318cfb5427b9cfc66a9fa376150c1ddbc62115ae27cef72417eb959691396",
+        )
+        .unwrap();
+        let conditions = "kind=1&created_at>1674834236&created_at<1677426236";
+
+        let sig = sign_delegation(&delegator_keys, delegatee, conditions.to_string()).unwrap();
+        let delegator = delegator_keys.public_key();
+        assert!(verify_delegation_signature(&delegator, &sig, &delegatee, conditions).is_ok());
+        assert!(verify_delegation_signature(&delegator, &sig, &delegatee, "kind=1").is_err());
+        assert!(verify_delegation_signature(&delegatee, &sig, &delegatee, conditions).is_err());
+    }
+
+    #[test]
+    fn test_conditions() {
+        let conditions =
+            Conditions::from_str("kind=1&created_at>1674834236&created_at<1677426236").unwrap();
+        assert_eq!(
+            conditions.to_string(),
+            "kind=1&created_at>1674834236&created_at<1677426236"
+        );
+        assert!(conditions.evaluate(Kind::Base(KindBase::TextNote), 1674834237));
+        assert!(!conditions.evaluate(Kind::Base(KindBase::Metadata), 1674834237));
+        assert!(!conditions.evaluate(Kind::Base(KindBase::TextNote), 1677426236));
+        assert!(Conditions::new().evaluate(Kind::Custom(123), 0));
+        assert_eq!(
+            Conditions::from_str("kind=a").unwrap_err(),
+            Error::InvalidConditions
+        );
+
+        // Unknown conditions are kept but not evaluated
+        let conditions = Conditions::from_str("kind=1&tags=p").unwrap();
+        assert_eq!(conditions.conditions(), &[Condition::Kind(1)]);
+        assert_eq!(conditions.to_string(), "kind=1&tags=p");
+        assert_eq!(
+            conditions
+                .add_condition(Condition::CreatedBefore(10))
+                .to_string(),
+            "kind=1&tags=p&created_at<10"
+        );
+        assert_eq!(
+            Conditions::new()
+                .add_condition(Condition::Kind(1))
+                .add_condition(Condition::CreatedAfter(5))
+                .to_string(),
+            "kind=1&created_at>5"
+        );
+    }
+
+    #[test]
+    fn test_non_canonical_conditions() {
+        let delegator_keys = Keys::generate_from_os_random();
+        let delegatee_keys = Keys::generate_from_os_random();
+
+        // Reordered, zero-padded and with an unknown condition
+        let conditions = "created_at<4102444800&kind=01&unknown>3";
+        let sig = sign_delegation(
+            &delegator_keys,
+            delegatee_keys.public_key(),
+            conditions.to_string(),
+        )
+        .unwrap();
+        let tag = Tag::new(TagData::Delegation {
+            delegator_pk: delegator_keys.public_key(),
+            conditions: conditions.to_string(),
+            sig,
+        });
+        let delegation = Delegation::from_tag(&tag).unwrap();
+        assert_eq!(delegation.tag(), tag);
+
+        let event = EventBuilder::new_text_note("Delegated", &[])
+            .delegation(&delegation)
+            .to_event(&delegatee_keys)
+            .unwrap();
+        assert_eq!(
+            verify_delegated_event(&event).unwrap(),
+            Some(delegator_keys.public_key())
+        );
+
+        let event = EventBuilder::new(Kind::Base(KindBase::Reaction), "+", &[])
+            .delegation(&delegation)
+            .to_event(&delegatee_keys)
+            .unwrap();
+        assert_eq!(
+            verify_delegated_event(&event).unwrap_err(),
+            Error::ConditionsNotSatisfied
+        );
+    }
+
+    #[test]
+    fn test_delegated_event() {
+        let delegator_keys = Keys::new(
+            SecretKey::from_str("ee35e8bb71131c02c1d7e73231daa48e9953d329a4b701f7133c8f46dd21139c")
+                .unwrap(),
+        );
+        let delegatee_keys = Keys::generate_from_os_random();
+
+        let conditions = Conditions::new().add_condition(Condition::Kind(1));
+        let delegation =
+            Delegation::new(&delegator_keys, delegatee_keys.public_key(), conditions).unwrap();
+        assert_eq!(Delegation::from_tag(&delegation.tag()).unwrap(), delegation);
+
+        let event = EventBuilder::new_text_note("Delegated", &[])
+            .delegation(&delegation)
+            .to_event(&delegatee_keys)
+            .unwrap();
+        assert_eq!(
+            verify_delegated_event(&event).unwrap(),
+            Some(delegator_keys.public_key())
+        );
+
+        let event = EventBuilder::new(Kind::Base(KindBase::Reaction), "+", &[])
+            .delegation(&delegation)
+            .to_event(&delegatee_keys)
+            .unwrap();
+        assert_eq!(
+            verify_delegated_event(&event).unwrap_err(),
+            Error::ConditionsNotSatisfied
+        );
+
+        let event = EventBuilder::new_text_note("Stolen", &[])
+            .delegation(&delegation)
+            .to_event(&Keys::generate_from_os_random())
+            .unwrap();
+        assert!(verify_delegated_event(&event).is_err());
+
+        let event = EventBuilder::new_text_note("Not delegated", &[])
+            .to_event(&delegatee_keys)
+            .unwrap();
+        assert_eq!(verify_delegated_event(&event).unwrap(), None);
+    }
 }
@@ -0,0 +1,195 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::str::FromStr;
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use super::nip21::NostrURI;
+use crate::event::{TagData, TagKind};
+use crate::Tag;
+
+/// `nostr:` URIs and raw `npub`, `nprofile`, `note`, `nevent` and `naddr` strings
+///
+/// Strings that are part of urls (ex. `https://njump.me/npub1...`) are not matched.
+static REGEX_REFERENCE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)(^|[^\w/:.=])((?:nostr:)?(?:npub|nprofile|note|nevent|naddr)1[qpzry9x8gf2tvdw0s3jn54khce6mua7l]+)")
+        .expect("Invalid regex")
+});
+
+fn reference_tag(uri: &NostrURI) -> Tag {
+    let with_relay = |kind: TagKind, value: String, relays: &[String]| {
+        let mut tag = vec![kind.to_string(), value];
+        if let Some(relay) = relays.first() {
+            tag.push(relay.clone());
+        }
+        Tag::from(tag)
+    };
+
+    match uri {
+        NostrURI::Pubkey(public_key) => Tag::new(TagData::PubKey(*public_key)),
+        NostrURI::Profile(profile) => {
+            with_relay(TagKind::P, profile.public_key.to_string(), &profile.relays)
+        }
+        NostrURI::EventId(id) => Tag::new(TagData::EventId(*id)),
+        NostrURI::Event(event) => with_relay(TagKind::E, event.event_id.to_string(), &event.relays),
+        NostrURI::Coordinate(coordinate) => Tag::new(TagData::Coordinate(coordinate.clone())),
+    }
+}
+
+/// Rewrite the references of a text note to canonical `nostr:` URIs and get their tags
+///
+/// Invalid references are left untouched. Use the result with [`EventBuilder::new_text_note`](crate::EventBuilder::new_text_note).
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/27.md>
+pub fn extract_references<S>(content: S) -> (String, Vec<Tag>)
+where
+    S: Into<String>,
+{
+    let content: String = content.into();
+    let mut tags: Vec<Tag> = Vec::new();
+
+    let content = REGEX_REFERENCE.replace_all(&content, |caps: &Captures| {
+        let reference: &str = &caps[2];
+        let bech32: &str = match reference.get(..6) {
+            Some(scheme) if scheme.eq_ignore_ascii_case("nostr:") => &reference[6..],
+            _ => reference,
+        };
+        match NostrURI::from_str(&format!("nostr:{}", bech32))
+            .and_then(|uri| Ok((uri.to_nostr_uri()?, uri)))
+        {
+            Ok((canonical, uri)) => {
+                let tag = reference_tag(&uri);
+                if !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+                format!("{}{}", &caps[1], canonical)
+            }
+            Err(_) => caps[0].to_string(),
+        }
+    });
+
+    (content.into_owned(), tags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key::ToBech32;
+    use crate::util::nips::nip19::Profile;
+    use crate::util::nips::test_util::public_key;
+
+    #[test]
+    fn test_extract_references() {
+        let public_key = public_key();
+        let npub = public_key.to_bech32().unwrap();
+
+        let (content, tags) = extract_references(format!("Hello nostr:{}!", npub));
+        assert_eq!(content, format!("Hello nostr:{}!", npub));
+        assert_eq!(tags, vec![Tag::new(TagData::PubKey(public_key))]);
+    }
+
+    #[test]
+    fn test_canonical_uri() {
+        let public_key = public_key();
+        let npub = public_key.to_bech32().unwrap();
+
+        // Raw entities get the scheme, upper-case ones are lower-cased
+        let (content, tags) = extract_references(format!(
+            "Hello {}, follow NOSTR:{}",
+            npub,
+            npub.to_uppercase()
+        ));
+        assert_eq!(
+            content,
+            format!("Hello nostr:{}, follow nostr:{}", npub, npub)
+        );
+        // Same reference, one tag
+        assert_eq!(tags, vec![Tag::new(TagData::PubKey(public_key))]);
+    }
+
+    #[test]
+    fn test_relay_hint() {
+        let public_key = public_key();
+        let profile = Profile::new(
+            public_key,
+            vec![
+                String::from("wss://relay.example.com"),
+                String::from("wss://relay2.example.com"),
+            ],
+        );
+        let nprofile = profile.to_bech32().unwrap();
+
+        // Only the first relay, and a different tag than the bare public key
+        let (_, tags) = extract_references(format!(
+            "nostr:{} aka nostr:{}",
+            nprofile,
+            public_key.to_bech32().unwrap()
+        ));
+        assert_eq!(
+            tags,
+            vec![
+                Tag::from(vec![
+                    String::from("p"),
+                    public_key.to_string(),
+                    String::from("wss://relay.example.com"),
+                ]),
+                Tag::new(TagData::PubKey(public_key)),
+            ]
+        );
+
+        // Spec example: no relay
+        let (_, tags) = extract_references(
+            "hello nostr:nprofile1qqszclxx9f5haga8sfjjrulaxncvkfekj097t6f3pu65f86rvg49ehqj6f9dh",
+        );
+        assert_eq!(
+            tags,
+            vec![Tag::from(vec![
+                String::from("p"),
+                String::from("2c7cc62a697ea3a7826521f3fd34f0cb273693cbe5e9310f35449f43622a5cdc"),
+            ])]
+        );
+
+        let (_, tags) = extract_references("See nevent1qqstna2yrezu5wghjvswqqculvvwxsrcvu7uc0f78gan4xqhvz49d9spr3mhxue69uhkummnw3ez6un9d3shjtn4de6x2argwghx6egpr4mhxue69uhkummnw3ez6ur4vgh8wetvd3hhyer9wghxuet5nxnepm");
+        assert_eq!(tags.len(), 1);
+        assert!(matches!(tags[0].kind(), Ok(TagKind::E)));
+        assert_eq!(tags[0].as_vec().len(), 3);
+    }
+
+    #[test]
+    fn test_references_in_urls() {
+        let npub = public_key().to_bech32().unwrap();
+
+        // Part of urls or of other tokens
+        for content in [
+            format!("hi x{}", npub),
+            format!("hi https://njump.me/{}", npub),
+            format!("hi ?p={}", npub),
+            format!("hi example.com:{}", npub),
+        ] {
+            assert_eq!(extract_references(content.clone()), (content, Vec::new()));
+        }
+
+        // Punctuation around a reference
+        let (content, tags) = extract_references(format!("({})", npub));
+        assert_eq!(content, format!("(nostr:{})", npub));
+        assert_eq!(tags.len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_references_untouched() {
+        for content in [
+            "Invalid npub1abc and nsec stay as they are",
+            // Bad checksum
+            "hi nostr:npub1sn0wdenkukak0d9dfczzeacvhkrgz92ak56egt7vdgzn8pv2wfqqhrjdv8",
+            // Secret keys are never referenced
+            "hi nostr:nsec1j4c6269y9w0q2er2xjw8sv2ehyrtfxq3jwgdlxj6qfn8z4gjsq5qfvfk99",
+        ] {
+            assert_eq!(
+                extract_references(content),
+                (String::from(content), Vec::new())
+            );
+        }
+    }
+}
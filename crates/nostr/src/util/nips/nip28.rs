@@ -0,0 +1,390 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::str::FromStr;
+
+use bitcoin::secp256k1::XOnlyPublicKey;
+use serde_json::Value;
+use url::Url;
+
+use crate::event::TagKind;
+use crate::metadata::Metadata;
+use crate::{Event, Kind, KindBase, Sha256Hash};
+
+#[derive(Debug, Eq, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// Not a channel event
+    #[error("invalid event kind")]
+    InvalidKind,
+    /// Invalid channel metadata
+    #[error("invalid metadata")]
+    InvalidMetadata,
+    /// Tag missing
+    #[error("tag missing: {0}")]
+    TagMissing(String),
+    /// Invalid tag value
+    #[error("invalid tag: {0}")]
+    InvalidTag(String),
+}
+
+/// Public chat channel event (kinds 40-44)
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/28.md>
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChannelEvent {
+    /// Channel creation (kind 40), the channel id is the event id
+    Creation { metadata: Metadata },
+    /// Channel metadata update (kind 41)
+    Metadata {
+        channel_id: Sha256Hash,
+        relay_url: Option<Url>,
+        metadata: Metadata,
+    },
+    /// Channel message (kind 42)
+    Message {
+        channel_id: Sha256Hash,
+        relay_url: Option<Url>,
+        /// Replied message
+        reply_to: Option<Sha256Hash>,
+        content: String,
+    },
+    /// Hide message (kind 43)
+    HideMessage {
+        message_id: Sha256Hash,
+        reason: Option<String>,
+    },
+    /// Mute user (kind 44)
+    MuteUser {
+        pubkey: XOnlyPublicKey,
+        reason: Option<String>,
+    },
+}
+
+impl ChannelEvent {
+    /// Parse channel event
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        match event.kind {
+            Kind::Base(KindBase::ChannelCreation) => Ok(Self::Creation {
+                metadata: Metadata::from_json(&event.content)
+                    .map_err(|_| Error::InvalidMetadata)?,
+            }),
+            Kind::Base(KindBase::ChannelMetadata) => {
+                let (channel_id, relay_url) = root_tag(event)?;
+                Ok(Self::Metadata {
+                    channel_id,
+                    relay_url,
+                    metadata: Metadata::from_json(&event.content)
+                        .map_err(|_| Error::InvalidMetadata)?,
+                })
+            }
+            Kind::Base(KindBase::ChannelMessage) => {
+                let (channel_id, relay_url) = root_tag(event)?;
+                let reply_to = e_tags(event)
+                    .find(|tag| tag.get(3).map(|m| m.as_str()) == Some("reply"))
+                    .map(|tag| parse_event_id(&tag[1]))
+                    .transpose()?;
+                Ok(Self::Message {
+                    channel_id,
+                    relay_url,
+                    reply_to,
+                    content: event.content.clone(),
+                })
+            }
+            Kind::Base(KindBase::ChannelHideMessage) => {
+                let tag = e_tags(event)
+                    .next()
+                    .ok_or_else(|| Error::TagMissing(String::from("e")))?;
+                Ok(Self::HideMessage {
+                    message_id: parse_event_id(&tag[1])?,
+                    reason: reason(&event.content),
+                })
+            }
+            Kind::Base(KindBase::ChannelMuteUser) => {
+                let pubkey = event
+                    .tags
+                    .iter()
+                    .find(|t| matches!(t.kind(), Ok(TagKind::P)))
+                    .and_then(|t| t.content())
+                    .ok_or_else(|| Error::TagMissing(String::from("p")))?;
+                Ok(Self::MuteUser {
+                    pubkey: XOnlyPublicKey::from_str(pubkey)
+                        .map_err(|_| Error::InvalidTag(String::from("p")))?,
+                    reason: reason(&event.content),
+                })
+            }
+            _ => Err(Error::InvalidKind),
+        }
+    }
+}
+
+fn e_tags(event: &Event) -> impl Iterator<Item = Vec<String>> + '_ {
+    event
+        .tags
+        .iter()
+        .filter(|t| matches!(t.kind(), Ok(TagKind::E)))
+        .map(|t| t.as_vec())
+        .filter(|t| t.len() >= 2)
+}
+
+fn parse_event_id(id: &str) -> Result<Sha256Hash, Error> {
+    Sha256Hash::from_str(id).map_err(|_| Error::InvalidTag(String::from("e")))
+}
+
+/// Get the channel id and the relay url: the `e` tag marked as root or the first one
+fn root_tag(event: &Event) -> Result<(Sha256Hash, Option<Url>), Error> {
+    let tag = e_tags(event)
+        .find(|tag| tag.get(3).map(|m| m.as_str()) == Some("root"))
+        .or_else(|| e_tags(event).next())
+        .ok_or_else(|| Error::TagMissing(String::from("e")))?;
+
+    let relay_url = tag.get(2).and_then(|url| Url::parse(url).ok());
+    Ok((parse_event_id(&tag[1])?, relay_url))
+}
+
+fn reason(content: &str) -> Option<String> {
+    serde_json::from_str::<Value>(content)
+        .ok()
+        .and_then(|v| v.get("reason").and_then(|r| r.as_str()).map(String::from))
+        .filter(|r| !r.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::nips::test_util::{build_event, public_key};
+    use crate::{EventBuilder, Keys, Result};
+
+    const CHANNEL: &str = "b3e392b11f5d4f28321cedd09303a748acfd0487aea5a7450b3481c60b6e4f87";
+    const MESSAGE: &str = "a695f6b60119d9521934a691347d9f78e8770b56da16bb255ee286ddf9fda919";
+
+    fn parse(kind: KindBase, content: &str, tags_json: &str) -> Result<ChannelEvent, Error> {
+        ChannelEvent::from_event(&build_event(Kind::Base(kind), content, tags_json))
+    }
+
+    fn id(id: &str) -> Sha256Hash {
+        Sha256Hash::from_str(id).unwrap()
+    }
+
+    #[test]
+    fn test_channel_events() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        let relay_url = Url::parse("wss://relay.damus.io")?;
+
+        let creation = EventBuilder::new_channel(Metadata::new().name("nostr"))?.to_event(&keys)?;
+        match ChannelEvent::from_event(&creation)? {
+            ChannelEvent::Creation { metadata } => {
+                assert_eq!(metadata.name, Some(String::from("nostr")))
+            }
+            e => panic!("unexpected channel event: {:?}", e),
+        }
+
+        let msg = EventBuilder::new_channel_msg(creation.id, relay_url.clone(), "Hello")
+            .to_event(&keys)?;
+        let reply =
+            EventBuilder::new_channel_msg_reply(creation.id, relay_url.clone(), msg.id, "Hi")
+                .to_event(&keys)?;
+        assert_eq!(
+            ChannelEvent::from_event(&reply)?,
+            ChannelEvent::Message {
+                channel_id: creation.id,
+                relay_url: Some(relay_url),
+                reply_to: Some(msg.id),
+                content: String::from("Hi"),
+            }
+        );
+
+        let hide = EventBuilder::hide_channel_msg(msg.id, Some("spam")).to_event(&keys)?;
+        assert_eq!(
+            ChannelEvent::from_event(&hide)?,
+            ChannelEvent::HideMessage {
+                message_id: msg.id,
+                reason: Some(String::from("spam")),
+            }
+        );
+
+        let mute =
+            EventBuilder::mute_channel_user::<String>(keys.public_key(), None).to_event(&keys)?;
+        assert_eq!(
+            ChannelEvent::from_event(&mute)?,
+            ChannelEvent::MuteUser {
+                pubkey: keys.public_key(),
+                reason: None,
+            }
+        );
+
+        let note = EventBuilder::new_text_note("", &[]).to_event(&keys)?;
+        assert_eq!(
+            ChannelEvent::from_event(&note).unwrap_err(),
+            Error::InvalidKind
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_root_marker() {
+        // The reply is listed first (spec example): the channel is the `root`
+        let message = parse(
+            KindBase::ChannelMessage,
+            "Hi",
+            &format!(
+                r#"[["e","{}","wss://nostr.example.com","reply"],["e","{}","wss://nostr.example.com","root"],["p","{}"]]"#,
+                MESSAGE,
+                CHANNEL,
+                public_key()
+            ),
+        );
+        assert_eq!(
+            message,
+            Ok(ChannelEvent::Message {
+                channel_id: id(CHANNEL),
+                relay_url: Some(Url::parse("wss://nostr.example.com").unwrap()),
+                reply_to: Some(id(MESSAGE)),
+                content: String::from("Hi"),
+            })
+        );
+
+        // Channel metadata updates use the same tag
+        let metadata = parse(
+            KindBase::ChannelMetadata,
+            r#"{"name":"Demo Channel"}"#,
+            &format!(r#"[["e","{}","","root"]]"#, CHANNEL),
+        );
+        assert_eq!(
+            metadata,
+            Ok(ChannelEvent::Metadata {
+                channel_id: id(CHANNEL),
+                relay_url: None,
+                metadata: Metadata::new().name("Demo Channel"),
+            })
+        );
+    }
+
+    #[test]
+    fn test_positional_tags() {
+        // Without markers the first `e` tag is the channel, and there is no reply
+        let message = parse(
+            KindBase::ChannelMessage,
+            "Hi",
+            &format!(r#"[["e","{}"],["e","{}"]]"#, CHANNEL, MESSAGE),
+        );
+        assert_eq!(
+            message,
+            Ok(ChannelEvent::Message {
+                channel_id: id(CHANNEL),
+                relay_url: None,
+                reply_to: None,
+                content: String::from("Hi"),
+            })
+        );
+
+        // `e` tags without a value are skipped
+        let message = parse(
+            KindBase::ChannelMessage,
+            "",
+            &format!(r#"[["e"],["e","{}","not a url"]]"#, CHANNEL),
+        );
+        assert!(matches!(
+            message,
+            Ok(ChannelEvent::Message { channel_id, relay_url: None, .. }) if channel_id == id(CHANNEL)
+        ));
+        assert_eq!(
+            parse(KindBase::ChannelMessage, "", r#"[["e"]]"#),
+            Err(Error::TagMissing(String::from("e")))
+        );
+        assert_eq!(
+            parse(
+                KindBase::ChannelMessage,
+                "",
+                &format!(
+                    r#"[["e","{}","","root"],["e","not an id","","reply"]]"#,
+                    CHANNEL
+                )
+            ),
+            Err(Error::InvalidTag(String::from("e")))
+        );
+    }
+
+    #[test]
+    fn test_moderation_reason() {
+        let e_tag = format!(r#"[["e","{}"]]"#, MESSAGE);
+        let hide = |content: &str| match parse(KindBase::ChannelHideMessage, content, &e_tag) {
+            Ok(ChannelEvent::HideMessage { reason, .. }) => reason,
+            e => panic!("unexpected channel event: {:?}", e),
+        };
+
+        // The reason is in a JSON content, any other content is ignored
+        assert_eq!(hide(r#"{"reason":"Spam"}"#), Some(String::from("Spam")));
+        assert_eq!(hide(r#"{"reason":""}"#), None);
+        assert_eq!(hide(r#"{"reason":42}"#), None);
+        assert_eq!(hide("Spam"), None);
+        assert_eq!(hide(""), None);
+
+        let pubkey = public_key();
+        assert_eq!(
+            parse(
+                KindBase::ChannelMuteUser,
+                r#"{"reason":"Posting spam"}"#,
+                &format!(r#"[["p","{}"]]"#, pubkey)
+            ),
+            Ok(ChannelEvent::MuteUser {
+                pubkey,
+                reason: Some(String::from("Posting spam")),
+            })
+        );
+        assert_eq!(
+            parse(
+                KindBase::ChannelMuteUser,
+                "",
+                r#"[["p","not a public key"]]"#
+            ),
+            Err(Error::InvalidTag(String::from("p")))
+        );
+        assert_eq!(
+            parse(KindBase::ChannelMuteUser, "", r#"[]"#),
+            Err(Error::TagMissing(String::from("p")))
+        );
+        assert_eq!(
+            parse(KindBase::ChannelHideMessage, "", r#"[]"#),
+            Err(Error::TagMissing(String::from("e")))
+        );
+    }
+
+    #[test]
+    fn test_channel_metadata() {
+        // Creation and updates carry the metadata as content
+        assert_eq!(
+            parse(
+                KindBase::ChannelCreation,
+                r#"{"name": "Demo Channel", "about": "A test channel.", "picture": "https://placekitten.com/200/200"}"#,
+                r#"[]"#
+            ),
+            Ok(ChannelEvent::Creation {
+                metadata: Metadata::new()
+                    .name("Demo Channel")
+                    .about("A test channel.")
+                    .picture(Url::parse("https://placekitten.com/200/200").unwrap())
+            })
+        );
+        assert_eq!(
+            parse(KindBase::ChannelCreation, "not json", r#"[]"#),
+            Err(Error::InvalidMetadata)
+        );
+        assert_eq!(
+            parse(
+                KindBase::ChannelMetadata,
+                "not json",
+                &format!(r#"[["e","{}"]]"#, CHANNEL)
+            ),
+            Err(Error::InvalidMetadata)
+        );
+        assert_eq!(
+            parse(
+                KindBase::ChannelMetadata,
+                r#"{"name":"Demo Channel"}"#,
+                r#"[]"#
+            ),
+            Err(Error::TagMissing(String::from("e")))
+        );
+    }
+}
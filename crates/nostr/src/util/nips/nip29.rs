@@ -0,0 +1,320 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::fmt;
+use std::str::FromStr;
+
+use url::Url;
+
+use crate::event::TagKind;
+use crate::{Event, Kind, KindBase, Tag};
+
+#[derive(Debug, Eq, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// Invalid group id
+    #[error("invalid group id")]
+    InvalidGroupId,
+    /// Invalid event kind
+    #[error("invalid event kind")]
+    InvalidKind,
+    /// Group id not found in event tags
+    #[error("group id not found")]
+    GroupIdNotFound,
+}
+
+/// Group identifier (`<host>'<group-id>`)
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct GroupId {
+    /// Host of the relay
+    pub host: String,
+    /// Id of the group on the relay
+    pub id: String,
+}
+
+impl GroupId {
+    /// New `GroupId`
+    pub fn new<S>(host: S, id: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            host: host.into(),
+            id: id.into(),
+        }
+    }
+
+    /// Get url of the relay hosting the group
+    pub fn relay_url(&self) -> Result<Url, url::ParseError> {
+        Url::parse(&format!("wss://{}", self.host))
+    }
+}
+
+impl fmt::Display for GroupId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}'{}", self.host, self.id)
+    }
+}
+
+impl FromStr for GroupId {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('\'') {
+            Some((host, id)) if !host.is_empty() && !id.is_empty() => Ok(Self::new(host, id)),
+            _ => Err(Error::InvalidGroupId),
+        }
+    }
+}
+
+/// Group metadata (kind 39000)
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct GroupMetadata {
+    /// Group id (without host)
+    pub id: String,
+    pub name: Option<String>,
+    pub picture: Option<String>,
+    pub about: Option<String>,
+    /// Everyone can read the group messages
+    pub public: bool,
+    /// Everyone can join the group without approval
+    pub open: bool,
+}
+
+impl GroupMetadata {
+    /// New `GroupMetadata`
+    pub fn new<S>(id: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            id: id.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Set name
+    pub fn name<S>(self, name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            name: Some(name.into()),
+            ..self
+        }
+    }
+
+    /// Set picture
+    pub fn picture<S>(self, picture: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            picture: Some(picture.into()),
+            ..self
+        }
+    }
+
+    /// Set about
+    pub fn about<S>(self, about: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            about: Some(about.into()),
+            ..self
+        }
+    }
+
+    /// Set public
+    pub fn public(self, public: bool) -> Self {
+        Self { public, ..self }
+    }
+
+    /// Set open
+    pub fn open(self, open: bool) -> Self {
+        Self { open, ..self }
+    }
+
+    /// Parse group metadata from a kind 39000 event
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        if event.kind != Kind::Base(KindBase::GroupMetadata) {
+            return Err(Error::InvalidKind);
+        }
+
+        let id = event
+            .tags
+            .iter()
+            .find(|t| matches!(t.kind(), Ok(TagKind::D)))
+            .and_then(|t| t.content())
+            .ok_or(Error::GroupIdNotFound)?;
+
+        let mut metadata = Self::new(id);
+
+        for tag in event.tags.iter() {
+            let tag = tag.as_vec();
+            match (tag.first().map(|t| t.as_str()), tag.get(1)) {
+                (Some("name"), Some(name)) => metadata.name = Some(name.clone()),
+                (Some("picture"), Some(picture)) => metadata.picture = Some(picture.clone()),
+                (Some("about"), Some(about)) => metadata.about = Some(about.clone()),
+                (Some("public"), _) => metadata.public = true,
+                (Some("open"), _) => metadata.open = true,
+                _ => (),
+            }
+        }
+
+        Ok(metadata)
+    }
+
+    /// Get metadata tags (without the group id)
+    pub fn tags(&self) -> Vec<Tag> {
+        let mut tags = Vec::new();
+
+        if let Some(name) = &self.name {
+            tags.push(Tag::from(vec![String::from("name"), name.clone()]));
+        }
+        if let Some(picture) = &self.picture {
+            tags.push(Tag::from(vec![String::from("picture"), picture.clone()]));
+        }
+        if let Some(about) = &self.about {
+            tags.push(Tag::from(vec![String::from("about"), about.clone()]));
+        }
+
+        let access = if self.public { "public" } else { "private" };
+        tags.push(Tag::from(vec![String::from(access)]));
+
+        let membership = if self.open { "open" } else { "closed" };
+        tags.push(Tag::from(vec![String::from(membership)]));
+
+        tags
+    }
+}
+
+/// Get the group id (`h` tag) of a group event
+pub fn get_group_id(event: &Event) -> Option<&str> {
+    event
+        .tags
+        .iter()
+        .find(|t| matches!(t.kind(), Ok(TagKind::H)))
+        .and_then(|t| t.content())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::nips::test_util::build_event;
+    use crate::{EventBuilder, Keys, Result};
+
+    fn metadata(tags_json: &str) -> Result<GroupMetadata, Error> {
+        GroupMetadata::from_event(&build_event(
+            Kind::Base(KindBase::GroupMetadata),
+            "",
+            tags_json,
+        ))
+    }
+
+    #[test]
+    fn test_group_id() -> Result<()> {
+        let group_id = GroupId::from_str("groups.nostr.com'abcdef")?;
+        assert_eq!(group_id, GroupId::new("groups.nostr.com", "abcdef"));
+        assert_eq!(group_id.to_string(), "groups.nostr.com'abcdef");
+        assert_eq!(group_id.relay_url()?, Url::parse("wss://groups.nostr.com")?);
+
+        for group_id in ["groups.nostr.com", "'abcdef", "groups.nostr.com'", ""] {
+            assert_eq!(
+                GroupId::from_str(group_id).unwrap_err(),
+                Error::InvalidGroupId
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_group_id_host() -> Result<()> {
+        // The host may have a port, the id is everything after the first `'`
+        let group_id = GroupId::from_str("localhost:4433'abc'def")?;
+        assert_eq!(group_id, GroupId::new("localhost:4433", "abc'def"));
+        assert_eq!(group_id.relay_url()?, Url::parse("wss://localhost:4433")?);
+        assert_eq!(GroupId::from_str(&group_id.to_string())?, group_id);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_group_metadata() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        let metadata = GroupMetadata::new("abcdef")
+            .name("Nostr devs")
+            .about("Talk about nostr")
+            .public(true);
+
+        let mut tags = vec![Tag::from(vec![String::from("d"), String::from("abcdef")])];
+        tags.extend(metadata.tags());
+        let event =
+            EventBuilder::new(Kind::Base(KindBase::GroupMetadata), "", &tags).to_event(&keys)?;
+        assert_eq!(GroupMetadata::from_event(&event)?, metadata);
+
+        let event = EventBuilder::new_text_note("", &[]).to_event(&keys)?;
+        assert_eq!(
+            GroupMetadata::from_event(&event).unwrap_err(),
+            Error::InvalidKind
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_access_and_membership_flags() {
+        // Value-less tags, written also when private and closed
+        let tags: Vec<Vec<String>> = GroupMetadata::new("abcdef")
+            .tags()
+            .iter()
+            .map(|t| t.as_vec())
+            .collect();
+        assert_eq!(
+            tags,
+            vec![vec![String::from("private")], vec![String::from("closed")]]
+        );
+
+        assert_eq!(
+            metadata(r#"[["d","abcdef"],["private"],["closed"]]"#),
+            Ok(GroupMetadata::new("abcdef"))
+        );
+        assert_eq!(
+            metadata(r#"[["d","abcdef"],["public"],["open"]]"#),
+            Ok(GroupMetadata::new("abcdef").public(true).open(true))
+        );
+        // Missing flags are the most restrictive
+        assert_eq!(
+            metadata(r#"[["d","abcdef"],["name","Nostr devs"]]"#),
+            Ok(GroupMetadata::new("abcdef").name("Nostr devs"))
+        );
+    }
+
+    #[test]
+    fn test_group_metadata_id() {
+        assert_eq!(
+            metadata(r#"[["name","Nostr devs"]]"#),
+            Err(Error::GroupIdNotFound)
+        );
+        assert_eq!(metadata(r#"[["d"]]"#), Err(Error::GroupIdNotFound));
+        // The `h` tag is for the group events, not the metadata
+        assert_eq!(metadata(r#"[["h","abcdef"]]"#), Err(Error::GroupIdNotFound));
+    }
+
+    #[test]
+    fn test_group_chat_msg() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        let group_id = GroupId::new("groups.nostr.com", "abcdef");
+        let event = EventBuilder::new_group_chat_msg(&group_id, "Hello").to_event(&keys)?;
+
+        // Only the id: the host is the relay the event is sent to
+        assert_eq!(event.kind, Kind::Base(KindBase::GroupChatMessage));
+        assert_eq!(get_group_id(&event), Some("abcdef"));
+
+        let event = build_event(Kind::Base(KindBase::GroupChatMessage), "", r#"[["h"]]"#);
+        assert_eq!(get_group_id(&event), None);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,633 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::str::FromStr;
+
+use bitcoin::secp256k1::XOnlyPublicKey;
+use url::Url;
+
+use super::nip19::Coordinate;
+use crate::event::{TagData, TagKind};
+use crate::{Event, Kind, KindBase, Tag};
+
+#[derive(Debug, Eq, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// Invalid event kind
+    #[error("invalid event kind")]
+    InvalidKind,
+    /// Identifier (`d` tag) not found
+    #[error("identifier not found")]
+    IdentifierNotFound,
+    /// Repository (`a` tag) not found
+    #[error("repository not found")]
+    RepositoryNotFound,
+}
+
+/// Get the values of all the tags of `kind` (ex. `["clone", "url1", "url2"]`)
+fn tag_values(event: &Event, kind: &str) -> Vec<String> {
+    event
+        .tags
+        .iter()
+        .map(|t| t.as_vec())
+        .filter(|t| t.first().map(|k| k.as_str()) == Some(kind))
+        .flat_map(|t| t.into_iter().skip(1))
+        .collect()
+}
+
+fn tag_value(event: &Event, kind: &str) -> Option<String> {
+    tag_values(event, kind).into_iter().next()
+}
+
+fn repository(event: &Event) -> Result<Coordinate, Error> {
+    event
+        .tags
+        .iter()
+        .filter(|t| matches!(t.kind(), Ok(TagKind::A)))
+        .filter_map(|t| t.content().and_then(|c| Coordinate::from_str(c).ok()))
+        .find(|c| c.kind == Kind::Base(KindBase::GitRepositoryAnnouncement))
+        .ok_or(Error::RepositoryNotFound)
+}
+
+/// Earliest unique commit id (`r` tag marked with `euc`)
+fn earliest_unique_commit(event: &Event) -> Option<String> {
+    event.tags.iter().find_map(|t| {
+        let tag = t.as_vec();
+        match (tag.first().map(|k| k.as_str()), tag.get(1), tag.get(2)) {
+            (Some("r"), Some(commit), Some(marker)) if marker == "euc" => Some(commit.clone()),
+            _ => None,
+        }
+    })
+}
+
+fn euc_tag(commit: &str) -> Tag {
+    Tag::from(vec![
+        String::from("r"),
+        commit.to_string(),
+        String::from("euc"),
+    ])
+}
+
+/// Git repository announcement (kind 30617)
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/34.md>
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct GitRepositoryAnnouncement {
+    /// Repository identifier (`d` tag), usually kebab-case short name
+    pub id: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// Urls for browsing the repository online
+    pub web: Vec<String>,
+    /// Urls for `git clone`
+    pub clone: Vec<String>,
+    /// Relays that this repository will monitor for patches and issues
+    pub relays: Vec<Url>,
+    /// Earliest unique commit id, to identify forks of the same repository
+    pub euc: Option<String>,
+    /// Other recognized maintainers
+    pub maintainers: Vec<XOnlyPublicKey>,
+}
+
+impl GitRepositoryAnnouncement {
+    /// New `GitRepositoryAnnouncement`
+    pub fn new<S>(id: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            id: id.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Set name
+    pub fn name<S>(self, name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            name: Some(name.into()),
+            ..self
+        }
+    }
+
+    /// Set description
+    pub fn description<S>(self, description: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            description: Some(description.into()),
+            ..self
+        }
+    }
+
+    /// Add web url
+    pub fn web<S>(mut self, url: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.web.push(url.into());
+        self
+    }
+
+    /// Add clone url
+    pub fn clone_url<S>(mut self, url: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.clone.push(url.into());
+        self
+    }
+
+    /// Add relay
+    pub fn relay(mut self, url: Url) -> Self {
+        self.relays.push(url);
+        self
+    }
+
+    /// Set earliest unique commit id
+    pub fn euc<S>(self, commit: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            euc: Some(commit.into()),
+            ..self
+        }
+    }
+
+    /// Add maintainer
+    pub fn maintainer(mut self, public_key: XOnlyPublicKey) -> Self {
+        self.maintainers.push(public_key);
+        self
+    }
+
+    /// Get the repository coordinate, to be referenced by patches and issues
+    pub fn coordinate(&self, owner: XOnlyPublicKey) -> Coordinate {
+        Coordinate::new(
+            Kind::Base(KindBase::GitRepositoryAnnouncement),
+            owner,
+            self.id.clone(),
+        )
+    }
+
+    /// Get repository announcement tags
+    pub fn tags(&self) -> Vec<Tag> {
+        let mut tags = vec![Tag::new(TagData::Identifier(self.id.clone()))];
+
+        if let Some(name) = &self.name {
+            tags.push(Tag::from(vec![String::from("name"), name.clone()]));
+        }
+        if let Some(description) = &self.description {
+            tags.push(Tag::from(vec![
+                String::from("description"),
+                description.clone(),
+            ]));
+        }
+        let lists = [
+            ("web", self.web.clone()),
+            ("clone", self.clone.clone()),
+            (
+                "relays",
+                self.relays.iter().map(|u| u.to_string()).collect(),
+            ),
+            (
+                "maintainers",
+                self.maintainers.iter().map(|pk| pk.to_string()).collect(),
+            ),
+        ];
+        for (kind, values) in lists.into_iter() {
+            if !values.is_empty() {
+                tags.push(Tag::from([vec![kind.to_string()], values].concat()));
+            }
+        }
+        if let Some(euc) = &self.euc {
+            tags.push(euc_tag(euc));
+        }
+
+        tags
+    }
+
+    /// Parse repository announcement from a kind 30617 event
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        if event.kind != Kind::Base(KindBase::GitRepositoryAnnouncement) {
+            return Err(Error::InvalidKind);
+        }
+
+        Ok(Self {
+            id: event
+                .identifier()
+                .ok_or(Error::IdentifierNotFound)?
+                .to_string(),
+            name: tag_value(event, "name"),
+            description: tag_value(event, "description"),
+            web: tag_values(event, "web"),
+            clone: tag_values(event, "clone"),
+            relays: tag_values(event, "relays")
+                .iter()
+                .filter_map(|u| Url::parse(u).ok())
+                .collect(),
+            euc: earliest_unique_commit(event),
+            maintainers: tag_values(event, "maintainers")
+                .iter()
+                .filter_map(|pk| XOnlyPublicKey::from_str(pk).ok())
+                .collect(),
+        })
+    }
+}
+
+/// Git patch (kind 1617)
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/34.md>
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct GitPatch {
+    /// Repository coordinate (`30617:<owner>:<id>`)
+    pub repository: Coordinate,
+    /// Output of `git format-patch`
+    pub content: String,
+    /// Earliest unique commit id of the repository
+    pub euc: Option<String>,
+    /// First patch of a patch set (`t` tag `root`)
+    pub root: bool,
+    pub commit: Option<String>,
+    pub parent_commit: Option<String>,
+}
+
+impl GitPatch {
+    /// New `GitPatch`
+    pub fn new<S>(repository: Coordinate, content: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            repository,
+            content: content.into(),
+            euc: None,
+            root: false,
+            commit: None,
+            parent_commit: None,
+        }
+    }
+
+    /// Set earliest unique commit id of the repository
+    pub fn euc<S>(self, commit: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            euc: Some(commit.into()),
+            ..self
+        }
+    }
+
+    /// Mark as first patch of a patch set
+    pub fn root(self) -> Self {
+        Self { root: true, ..self }
+    }
+
+    /// Set commit id and parent commit id
+    pub fn commit<S>(self, commit: S, parent_commit: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            commit: Some(commit.into()),
+            parent_commit: Some(parent_commit.into()),
+            ..self
+        }
+    }
+
+    /// Get patch tags
+    pub fn tags(&self) -> Vec<Tag> {
+        let mut tags = vec![
+            Tag::new(TagData::Coordinate(self.repository.clone())),
+            Tag::new(TagData::PubKey(self.repository.public_key)),
+        ];
+        if let Some(euc) = &self.euc {
+            tags.push(Tag::from(vec![String::from("r"), euc.clone()]));
+        }
+        if self.root {
+            tags.push(Tag::from(vec![String::from("t"), String::from("root")]));
+        }
+        if let Some(commit) = &self.commit {
+            tags.push(Tag::from(vec![String::from("commit"), commit.clone()]));
+            tags.push(Tag::from(vec![String::from("r"), commit.clone()]));
+        }
+        if let Some(parent_commit) = &self.parent_commit {
+            tags.push(Tag::from(vec![
+                String::from("parent-commit"),
+                parent_commit.clone(),
+            ]));
+        }
+        tags
+    }
+
+    /// Parse patch from a kind 1617 event
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        if event.kind != Kind::Base(KindBase::GitPatch) {
+            return Err(Error::InvalidKind);
+        }
+
+        let commit = tag_value(event, "commit");
+        Ok(Self {
+            repository: repository(event)?,
+            content: event.content.clone(),
+            euc: tag_values(event, "r")
+                .into_iter()
+                .find(|r| Some(r) != commit.as_ref()),
+            root: tag_values(event, "t").iter().any(|t| t == "root"),
+            commit,
+            parent_commit: tag_value(event, "parent-commit"),
+        })
+    }
+}
+
+/// Git issue (kind 1621)
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/34.md>
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct GitIssue {
+    /// Repository coordinate (`30617:<owner>:<id>`)
+    pub repository: Coordinate,
+    /// Markdown text
+    pub content: String,
+    pub subject: Option<String>,
+    /// Labels (`t` tags)
+    pub labels: Vec<String>,
+}
+
+impl GitIssue {
+    /// New `GitIssue`
+    pub fn new<S>(repository: Coordinate, content: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            repository,
+            content: content.into(),
+            subject: None,
+            labels: Vec::new(),
+        }
+    }
+
+    /// Set subject
+    pub fn subject<S>(self, subject: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            subject: Some(subject.into()),
+            ..self
+        }
+    }
+
+    /// Add label
+    pub fn label<S>(mut self, label: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.labels.push(label.into());
+        self
+    }
+
+    /// Get issue tags
+    pub fn tags(&self) -> Vec<Tag> {
+        let mut tags = vec![
+            Tag::new(TagData::Coordinate(self.repository.clone())),
+            Tag::new(TagData::PubKey(self.repository.public_key)),
+        ];
+        if let Some(subject) = &self.subject {
+            tags.push(Tag::new(TagData::Subject(subject.clone())));
+        }
+        for label in self.labels.iter() {
+            tags.push(Tag::from(vec![String::from("t"), label.clone()]));
+        }
+        tags
+    }
+
+    /// Parse issue from a kind 1621 event
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        if event.kind != Kind::Base(KindBase::GitIssue) {
+            return Err(Error::InvalidKind);
+        }
+
+        Ok(Self {
+            repository: repository(event)?,
+            content: event.content.clone(),
+            subject: event.subject().map(|s| s.to_string()),
+            labels: tag_values(event, "t"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::nips::test_util::{build_event, public_key};
+    use crate::{EventBuilder, Keys, Result};
+
+    fn announcement(tags_json: &str) -> Result<GitRepositoryAnnouncement, Error> {
+        GitRepositoryAnnouncement::from_event(&build_event(
+            Kind::Base(KindBase::GitRepositoryAnnouncement),
+            "",
+            tags_json,
+        ))
+    }
+
+    fn patch(tags_json: &str) -> Result<GitPatch, Error> {
+        GitPatch::from_event(&build_event(Kind::Base(KindBase::GitPatch), "", tags_json))
+    }
+
+    #[test]
+    fn test_git() -> Result<()> {
+        let owner = Keys::generate_from_os_random();
+        let contributor = Keys::generate_from_os_random();
+
+        let repo = GitRepositoryAnnouncement::new("nostr-rs-sdk")
+            .name("nostr-rs-sdk")
+            .description("Nostr SDK")
+            .web("https://github.com/mcdallas/nostr-rs-sdk")
+            .clone_url("https://github.com/mcdallas/nostr-rs-sdk.git")
+            .relay(Url::parse("wss://relay.damus.io")?)
+            .euc("e902f0f")
+            .maintainer(contributor.public_key());
+        let event = EventBuilder::git_repository_announcement(&repo).to_event(&owner)?;
+        assert_eq!(GitRepositoryAnnouncement::from_event(&event)?, repo);
+        let coordinate = event.coordinate().unwrap();
+        assert_eq!(coordinate, repo.coordinate(owner.public_key()));
+
+        let patch = GitPatch::new(coordinate.clone(), "From e902f0f...")
+            .euc("e902f0f")
+            .root()
+            .commit("fd2c0cb", "1dcee45");
+        let event = EventBuilder::git_patch(&patch).to_event(&contributor)?;
+        assert_eq!(GitPatch::from_event(&event)?, patch);
+
+        let issue = GitIssue::new(coordinate, "It doesn't build")
+            .subject("Build failure")
+            .label("bug");
+        let event = EventBuilder::git_issue(&issue).to_event(&contributor)?;
+        assert_eq!(event.subject(), Some("Build failure"));
+        assert_eq!(GitIssue::from_event(&event)?, issue);
+        assert_eq!(GitPatch::from_event(&event), Err(Error::InvalidKind));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_multi_value_tags() -> Result<()> {
+        let maintainer = public_key();
+
+        // Many values in one tag, or the same tag repeated
+        let repo = announcement(&format!(
+            r#"[
+                ["d", "nostr-rs-sdk"],
+                ["web", "https://github.com/mcdallas/nostr-rs-sdk", "https://gitworkshop.dev/nostr-rs-sdk"],
+                ["clone", "https://github.com/mcdallas/nostr-rs-sdk.git"],
+                ["clone", "git@github.com:mcdallas/nostr-rs-sdk.git"],
+                ["relays", "wss://relay.damus.io", "not a url", "wss://nos.lol"],
+                ["maintainers", "not a public key", "{}"]
+            ]"#,
+            maintainer
+        ))?;
+        assert_eq!(
+            repo,
+            GitRepositoryAnnouncement::new("nostr-rs-sdk")
+                .web("https://github.com/mcdallas/nostr-rs-sdk")
+                .web("https://gitworkshop.dev/nostr-rs-sdk")
+                .clone_url("https://github.com/mcdallas/nostr-rs-sdk.git")
+                .clone_url("git@github.com:mcdallas/nostr-rs-sdk.git")
+                .relay(Url::parse("wss://relay.damus.io")?)
+                .relay(Url::parse("wss://nos.lol")?)
+                .maintainer(maintainer)
+        );
+
+        // Written as a single tag each
+        let tags: Vec<Vec<String>> = repo.tags().iter().map(|t| t.as_vec()).collect();
+        assert_eq!(tags.iter().filter(|t| t[0] == "clone").count(), 1);
+        assert!(tags.contains(&vec![
+            String::from("relays"),
+            String::from("wss://relay.damus.io/"),
+            String::from("wss://nos.lol/"),
+        ]));
+
+        assert_eq!(
+            announcement(r#"[["name","Nostr SDK"]]"#),
+            Err(Error::IdentifierNotFound)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_earliest_unique_commit() -> Result<()> {
+        // Only the `r` tag marked as `euc` in the announcement
+        assert_eq!(announcement(r#"[["d","repo"],["r","e902f0f"]]"#)?.euc, None);
+        assert_eq!(
+            announcement(r#"[["d","repo"],["r","fd2c0cb"],["r","e902f0f","euc"]]"#)?.euc,
+            Some(String::from("e902f0f"))
+        );
+
+        // In a patch the `r` tags are the euc and the commit: the one that isn't the commit
+        let coordinate = format!("30617:{}:repo", public_key());
+        let patch = patch(&format!(
+            r#"[["a","{}"],["commit","fd2c0cb"],["r","fd2c0cb"],["r","e902f0f"],["parent-commit","1dcee45"]]"#,
+            coordinate
+        ))?;
+        assert_eq!(patch.euc.as_deref(), Some("e902f0f"));
+        assert_eq!(patch.commit.as_deref(), Some("fd2c0cb"));
+        assert_eq!(patch.parent_commit.as_deref(), Some("1dcee45"));
+        assert!(!patch.root);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_repository_coordinate() -> Result<()> {
+        let owner = public_key();
+        let repo = format!("30617:{}:repo", owner);
+        let article = format!("30023:{}:repo", owner);
+
+        // Other coordinates (i.e. the article discussing the patch) are skipped
+        let patch = patch(&format!(
+            r#"[["a","{}"],["a","30617:not a public key:repo"],["a","{}"],["t","root"]]"#,
+            article, repo
+        ))?;
+        assert_eq!(patch.repository.to_string(), repo);
+        assert!(patch.root);
+
+        for tags in [
+            String::from("[]"),
+            String::from(r#"[["a","30617:not a public key:repo"]]"#),
+            format!(r#"[["a","{}"]]"#, article),
+        ] {
+            assert_eq!(
+                GitPatch::from_event(&build_event(Kind::Base(KindBase::GitPatch), "", &tags)),
+                Err(Error::RepositoryNotFound)
+            );
+            assert_eq!(
+                GitIssue::from_event(&build_event(Kind::Base(KindBase::GitIssue), "", &tags)),
+                Err(Error::RepositoryNotFound)
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_spec_examples() -> Result<()> {
+        let owner = public_key();
+        let coordinate = Coordinate::new(
+            Kind::Base(KindBase::GitRepositoryAnnouncement),
+            owner,
+            "nostr-rs-sdk",
+        );
+
+        // Unknown tags (signature and committer) are ignored
+        let event = build_event(
+            Kind::Base(KindBase::GitPatch),
+            "From fd2c0cb...",
+            &format!(
+                r#"[
+                    ["a", "{coordinate}"],
+                    ["r", "e902f0f"],
+                    ["p", "{owner}"],
+                    ["t", "root"],
+                    ["commit", "fd2c0cb"],
+                    ["r", "fd2c0cb"],
+                    ["parent-commit", "1dcee45"],
+                    ["commit-pgp-sig", "-----BEGIN PGP SIGNATURE-----"],
+                    ["committer", "Alice", "alice@example.com", "1700000000", "60"]
+                ]"#,
+                coordinate = coordinate,
+                owner = owner
+            ),
+        );
+        assert_eq!(
+            GitPatch::from_event(&event)?,
+            GitPatch::new(coordinate.clone(), "From fd2c0cb...")
+                .euc("e902f0f")
+                .root()
+                .commit("fd2c0cb", "1dcee45")
+        );
+
+        let event = build_event(
+            Kind::Base(KindBase::GitIssue),
+            "It doesn't build",
+            &format!(
+                r#"[["a", "{}"], ["p", "{}"], ["subject", "Build failure"], ["t", "bug"], ["t", "ci"]]"#,
+                coordinate, owner
+            ),
+        );
+        assert_eq!(
+            GitIssue::from_event(&event)?,
+            GitIssue::new(coordinate, "It doesn't build")
+                .subject("Build failure")
+                .label("bug")
+                .label("ci")
+        );
+
+        Ok(())
+    }
+}
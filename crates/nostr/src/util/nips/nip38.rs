@@ -0,0 +1,274 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::fmt;
+use std::str::FromStr;
+
+use bitcoin::secp256k1::XOnlyPublicKey;
+use url::Url;
+
+use super::nip19::Coordinate;
+use crate::event::{TagData, TagKind};
+use crate::{Event, Kind, KindBase, Sha256Hash, Tag};
+
+#[derive(Debug, Eq, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// Invalid event kind
+    #[error("invalid event kind")]
+    InvalidKind,
+    /// Status type (`d` tag) not found
+    #[error("status type not found")]
+    StatusTypeNotFound,
+}
+
+/// User status type (`d` tag)
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum StatusType {
+    General,
+    Music,
+    Custom(String),
+}
+
+impl fmt::Display for StatusType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::General => write!(f, "general"),
+            Self::Music => write!(f, "music"),
+            Self::Custom(status_type) => write!(f, "{}", status_type),
+        }
+    }
+}
+
+impl<S> From<S> for StatusType
+where
+    S: Into<String>,
+{
+    fn from(s: S) -> Self {
+        let s: String = s.into();
+        match s.as_str() {
+            "general" => Self::General,
+            "music" => Self::Music,
+            _ => Self::Custom(s),
+        }
+    }
+}
+
+/// Reference linked to a status
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum StatusLink {
+    /// `r` tag
+    Url(Url),
+    /// `p` tag
+    PubKey(XOnlyPublicKey),
+    /// `e` tag
+    Event(Sha256Hash),
+    /// `a` tag
+    Coordinate(Coordinate),
+}
+
+impl StatusLink {
+    fn tag(&self) -> Tag {
+        match self {
+            Self::Url(url) => Tag::from(vec![String::from("r"), url.to_string()]),
+            Self::PubKey(pk) => Tag::new(TagData::PubKey(*pk)),
+            Self::Event(id) => Tag::new(TagData::EventId(*id)),
+            Self::Coordinate(coordinate) => Tag::new(TagData::Coordinate(coordinate.clone())),
+        }
+    }
+
+    fn from_tag(tag: &Tag) -> Option<Self> {
+        let content = tag.content()?;
+        match tag.kind().ok()? {
+            TagKind::Custom(kind) if kind == "r" => Url::parse(content).ok().map(Self::Url),
+            TagKind::P => XOnlyPublicKey::from_str(content).ok().map(Self::PubKey),
+            TagKind::E => Sha256Hash::from_str(content).ok().map(Self::Event),
+            TagKind::A => Coordinate::from_str(content).ok().map(Self::Coordinate),
+            _ => None,
+        }
+    }
+}
+
+/// User status (kind 30315)
+///
+/// An empty `content` clears the status.
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/38.md>
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct UserStatus {
+    pub status_type: StatusType,
+    pub content: String,
+    /// Expiration unix timestamp
+    pub expiration: Option<u64>,
+    pub link: Option<StatusLink>,
+}
+
+impl UserStatus {
+    /// New `UserStatus`
+    pub fn new<S>(status_type: StatusType, content: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            status_type,
+            content: content.into(),
+            expiration: None,
+            link: None,
+        }
+    }
+
+    /// Set expiration unix timestamp
+    pub fn expiration(self, timestamp: u64) -> Self {
+        Self {
+            expiration: Some(timestamp),
+            ..self
+        }
+    }
+
+    /// Set link
+    pub fn link(self, link: StatusLink) -> Self {
+        Self {
+            link: Some(link),
+            ..self
+        }
+    }
+
+    /// Get user status tags
+    pub fn tags(&self) -> Vec<Tag> {
+        let mut tags = vec![Tag::new(TagData::Identifier(self.status_type.to_string()))];
+        if let Some(expiration) = self.expiration {
+            tags.push(Tag::new(TagData::Expiration(expiration)));
+        }
+        if let Some(link) = &self.link {
+            tags.push(link.tag());
+        }
+        tags
+    }
+
+    /// Parse user status from a kind 30315 event
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        if event.kind != Kind::Base(KindBase::UserStatus) {
+            return Err(Error::InvalidKind);
+        }
+
+        Ok(Self {
+            status_type: StatusType::from(event.identifier().ok_or(Error::StatusTypeNotFound)?),
+            content: event.content.clone(),
+            expiration: event.expiration(),
+            link: event.tags.iter().find_map(StatusLink::from_tag),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::nips::test_util::{build_event, public_key};
+    use crate::{EventBuilder, Keys, Result};
+
+    fn status(content: &str, tags_json: &str) -> Result<UserStatus, Error> {
+        UserStatus::from_event(&build_event(
+            Kind::Base(KindBase::UserStatus),
+            content,
+            tags_json,
+        ))
+    }
+
+    #[test]
+    fn test_user_status() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+
+        let status = UserStatus::new(StatusType::Music, "Intergalatic - Beastie Boys")
+            .expiration(1692845589)
+            .link(StatusLink::Url(Url::parse(
+                "spotify:search:Intergalatic%20-%20Beastie%20Boys",
+            )?));
+        let event = EventBuilder::user_status(&status).to_event(&keys)?;
+        assert_eq!(event.identifier(), Some("music"));
+        assert_eq!(event.expiration(), Some(1692845589));
+        assert_eq!(UserStatus::from_event(&event)?, status);
+
+        let note = EventBuilder::new_text_note("", &[]).to_event(&keys)?;
+        assert_eq!(UserStatus::from_event(&note), Err(Error::InvalidKind));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_clear_status() -> Result<()> {
+        // An empty content clears the status of that type
+        let cleared = status("", r#"[["d","general"]]"#)?;
+        assert_eq!(cleared, UserStatus::new(StatusType::General, ""));
+        assert!(cleared.content.is_empty());
+
+        assert_eq!(
+            status("Sign up for nostrasia!", r#"[["r","https://nostr.world"]]"#),
+            Err(Error::StatusTypeNotFound)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_status_type() {
+        assert_eq!(StatusType::from("general"), StatusType::General);
+        assert_eq!(StatusType::from("music"), StatusType::Music);
+        // Types are case sensitive
+        assert_eq!(
+            StatusType::from("Music"),
+            StatusType::Custom(String::from("Music"))
+        );
+        assert_eq!(
+            StatusType::Custom(String::from("gaming")).to_string(),
+            "gaming"
+        );
+    }
+
+    #[test]
+    fn test_status_links() -> Result<()> {
+        let public_key = public_key();
+        let event_id = Sha256Hash::from_str(
+            "7469af3be8c8e06e1b50ef1caceba30392ddc0b6614507398b7d7daa4c218e96",
+        )?;
+        let coordinate = Coordinate::new(
+            Kind::Base(KindBase::LongFormTextNote),
+            public_key,
+            "my-article",
+        );
+
+        for link in [
+            StatusLink::PubKey(public_key),
+            StatusLink::Event(event_id),
+            StatusLink::Coordinate(coordinate),
+        ] {
+            let status = UserStatus::new(StatusType::General, "Reading").link(link);
+            let event =
+                EventBuilder::user_status(&status).to_event(&Keys::generate_from_os_random())?;
+            assert_eq!(UserStatus::from_event(&event)?, status);
+        }
+
+        // The first valid reference is the link
+        let status = status(
+            "Reading",
+            &format!(
+                r#"[["d","general"],["p","not a public key"],["e"],["e","{}"],["r","https://nostr.world"]]"#,
+                event_id
+            ),
+        )?;
+        assert_eq!(status.link, Some(StatusLink::Event(event_id)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_expiration() -> Result<()> {
+        let status = status("Gaming", r#"[["d","gaming"],["expiration","soon"]]"#)?;
+        assert_eq!(
+            status.status_type,
+            StatusType::Custom(String::from("gaming"))
+        );
+        assert_eq!(status.expiration, None);
+        assert_eq!(status.link, None);
+
+        Ok(())
+    }
+}
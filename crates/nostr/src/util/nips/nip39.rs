@@ -0,0 +1,263 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::fmt;
+use std::str::FromStr;
+
+use bitcoin::secp256k1::XOnlyPublicKey;
+use url::Url;
+
+use crate::key::{self, ToBech32};
+use crate::Tag;
+
+/// Text that the proof must contain, followed by the bech32 public key
+pub const PROOF_TEXT: &str = "Verifying that I control the following Nostr public key:";
+
+#[derive(Debug, Eq, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// Unknown platform
+    #[error("unknown platform")]
+    UnknownPlatform,
+    /// Invalid `i` tag
+    #[error("invalid identity tag")]
+    InvalidTag,
+    /// Impossible to build the proof url
+    #[error("invalid proof url")]
+    InvalidProofUrl,
+}
+
+/// External identity platform
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum ExternalIdentity {
+    GitHub,
+    Twitter,
+    Mastodon,
+    Telegram,
+}
+
+impl fmt::Display for ExternalIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::GitHub => write!(f, "github"),
+            Self::Twitter => write!(f, "twitter"),
+            Self::Mastodon => write!(f, "mastodon"),
+            Self::Telegram => write!(f, "telegram"),
+        }
+    }
+}
+
+impl FromStr for ExternalIdentity {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "github" => Ok(Self::GitHub),
+            "twitter" => Ok(Self::Twitter),
+            "mastodon" => Ok(Self::Mastodon),
+            "telegram" => Ok(Self::Telegram),
+            _ => Err(Error::UnknownPlatform),
+        }
+    }
+}
+
+/// External identity claim (`i` tag of the metadata event)
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/39.md>
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Identity {
+    pub platform: ExternalIdentity,
+    /// Username (GitHub, Twitter), `instance/@username` (Mastodon) or user id (Telegram)
+    pub ident: String,
+    /// Gist id (GitHub), tweet id (Twitter), post id (Mastodon) or `channel/message_id` (Telegram)
+    pub proof: String,
+}
+
+impl Identity {
+    /// New `Identity`
+    pub fn new<S>(platform: ExternalIdentity, ident: S, proof: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            platform,
+            ident: ident.into(),
+            proof: proof.into(),
+        }
+    }
+
+    /// Get the url of the proof
+    pub fn proof_url(&self) -> Result<Url, Error> {
+        let url = match self.platform {
+            ExternalIdentity::GitHub => {
+                format!("https://gist.github.com/{}/{}", self.ident, self.proof)
+            }
+            ExternalIdentity::Twitter => {
+                format!("https://twitter.com/{}/status/{}", self.ident, self.proof)
+            }
+            ExternalIdentity::Mastodon => format!("https://{}/{}", self.ident, self.proof),
+            ExternalIdentity::Telegram => format!("https://t.me/{}", self.proof),
+        };
+        Url::parse(&url).map_err(|_| Error::InvalidProofUrl)
+    }
+
+    /// Get the text that the proof must contain for `public_key`
+    pub fn proof_text(public_key: &XOnlyPublicKey) -> Result<String, key::Error> {
+        Ok(format!("{} {}", PROOF_TEXT, public_key.to_bech32()?))
+    }
+
+    /// Get identity tag
+    pub fn tag(&self) -> Tag {
+        Tag::from(vec![
+            String::from("i"),
+            format!("{}:{}", self.platform, self.ident),
+            self.proof.clone(),
+        ])
+    }
+
+    /// Parse identity from `i` tag
+    pub fn from_tag(tag: &Tag) -> Result<Self, Error> {
+        let tag = tag.as_vec();
+        if tag.first().map(|t| t.as_str()) != Some("i") {
+            return Err(Error::InvalidTag);
+        }
+        let (platform, ident) = tag
+            .get(1)
+            .and_then(|i| i.split_once(':'))
+            .ok_or(Error::InvalidTag)?;
+        Ok(Self {
+            platform: platform.parse()?,
+            ident: ident.to_string(),
+            proof: tag.get(2).cloned().ok_or(Error::InvalidTag)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::nips::test_util::{build_event, public_key, tags};
+    use crate::{EventBuilder, Keys, Kind, KindBase, Metadata, Result};
+
+    const SPEC_TAGS: &str = r#"[
+        ["i", "github:semisol", "9721ce4ee4fceb91c9711ca2a6c9a5ab"],
+        ["i", "twitter:semisol_public", "1619358434134196225"],
+        ["i", "mastodon:bitcoinhackers.org/@semisol", "109775066355589974"],
+        ["i", "telegram:1087295469", "nostrdirectory/770"]
+    ]"#;
+
+    fn identity(tag_json: &str) -> Result<Identity, Error> {
+        Identity::from_tag(&serde_json::from_str(tag_json).unwrap())
+    }
+
+    #[test]
+    fn test_metadata_identities() -> Result<()> {
+        let github = Identity::new(
+            ExternalIdentity::GitHub,
+            "semisol",
+            "9721ce4ee4fceb91c9711ca2a6c9a5ab",
+        );
+        let metadata = Metadata::new().name("semisol").identity(github);
+        let event = EventBuilder::set_metadata(metadata.clone())?
+            .to_event(&Keys::generate_from_os_random())?;
+        assert_eq!(
+            event.tags[0].as_vec(),
+            vec!["i", "github:semisol", "9721ce4ee4fceb91c9711ca2a6c9a5ab"]
+        );
+        assert_eq!(Metadata::from_event(&event)?, metadata);
+
+        // The identities live in the tags, not in the content
+        let event = build_event(
+            Kind::Base(KindBase::Metadata),
+            r#"{"name": "semisol"}"#,
+            SPEC_TAGS,
+        );
+        assert_eq!(Metadata::from_event(&event)?.identities.len(), 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_proof_urls() -> Result<()> {
+        let identities: Vec<Identity> = tags(SPEC_TAGS)
+            .iter()
+            .map(Identity::from_tag)
+            .collect::<Result<_, Error>>()?;
+        assert_eq!(
+            identities
+                .iter()
+                .map(|i| i.proof_url().map(|u| u.to_string()))
+                .collect::<Result<Vec<_>, Error>>()?,
+            vec![
+                "https://gist.github.com/semisol/9721ce4ee4fceb91c9711ca2a6c9a5ab",
+                "https://twitter.com/semisol_public/status/1619358434134196225",
+                "https://bitcoinhackers.org/@semisol/109775066355589974",
+                // The Telegram proof is the public channel message, not the user id
+                "https://t.me/nostrdirectory/770",
+            ]
+        );
+
+        assert_eq!(
+            Identity::new(ExternalIdentity::Mastodon, "", "109775066355589974").proof_url(),
+            Err(Error::InvalidProofUrl)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_proof_text() -> Result<()> {
+        let public_key = public_key();
+        let text = Identity::proof_text(&public_key)?;
+        assert!(text.starts_with(PROOF_TEXT));
+        assert!(text.ends_with(&public_key.to_bech32()?));
+        assert!(text.contains(" npub1"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_identity_tag() -> Result<()> {
+        // Only the first `:` separates the platform
+        let mastodon = identity(r#"["i", "mastodon:infosec.exchange/@a:b", "1"]"#)?;
+        assert_eq!(mastodon.platform, ExternalIdentity::Mastodon);
+        assert_eq!(mastodon.ident, "infosec.exchange/@a:b");
+        assert_eq!(Identity::from_tag(&mastodon.tag())?, mastodon);
+
+        for (tag, error) in [
+            (r#"["r", "github:semisol", "9721ce4e"]"#, Error::InvalidTag),
+            (r#"["i"]"#, Error::InvalidTag),
+            (r#"["i", "semisol", "9721ce4e"]"#, Error::InvalidTag),
+            (r#"["i", "github:semisol"]"#, Error::InvalidTag),
+            // Platform names are lowercase
+            (
+                r#"["i", "GitHub:semisol", "9721ce4e"]"#,
+                Error::UnknownPlatform,
+            ),
+            (
+                r#"["i", "facebook:semisol", "123"]"#,
+                Error::UnknownPlatform,
+            ),
+        ] {
+            assert_eq!(identity(tag), Err(error));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_identities_skipped() -> Result<()> {
+        let event = build_event(
+            Kind::Base(KindBase::Metadata),
+            "{}",
+            r#"[["i", "facebook:semisol", "123"], ["i", "github:semisol"], ["i", "github:semisol", "9721ce4ee4fceb91c9711ca2a6c9a5ab"]]"#,
+        );
+        assert_eq!(
+            Metadata::from_event(&event)?.identities,
+            vec![Identity::new(
+                ExternalIdentity::GitHub,
+                "semisol",
+                "9721ce4ee4fceb91c9711ca2a6c9a5ab"
+            )]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,336 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::str::FromStr;
+
+use bitcoin::hashes::hmac::{Hmac, HmacEngine};
+use bitcoin::hashes::sha256::Hash as Sha256;
+use bitcoin::hashes::{Hash, HashEngine};
+use bitcoin::secp256k1::{ecdh, PublicKey, SecretKey, XOnlyPublicKey};
+use chacha20::cipher::{KeyIvInit, StreamCipher};
+use chacha20::ChaCha20;
+
+const VERSION: u8 = 0x02;
+const SALT: &[u8] = b"nip44-v2";
+const MIN_PLAINTEXT_LEN: usize = 1;
+const MAX_PLAINTEXT_LEN: usize = 65535;
+
+#[derive(Debug, Eq, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// Secp256k1 error
+    #[error("secp256k1 error: {0}")]
+    Secp256k1(#[from] bitcoin::secp256k1::Error),
+    /// Plaintext must be between 1 and 65535 bytes
+    #[error("invalid plaintext length")]
+    InvalidPlaintextLength,
+    /// Invalid payload
+    #[error("invalid payload")]
+    InvalidPayload,
+    /// Unknown encryption version
+    #[error("unknown version: {0}")]
+    UnknownVersion(u8),
+    /// Invalid MAC
+    #[error("invalid MAC")]
+    InvalidMac,
+    /// Invalid padding
+    #[error("invalid padding")]
+    InvalidPadding,
+    /// Error while decoding from base64
+    #[error("error while decoding from base64")]
+    Base64Decode,
+    /// Error while encoding to UTF-8
+    #[error("error while encoding to UTF-8")]
+    Utf8Encode,
+}
+
+/// Conversation key shared by two users
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/44.md>
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct ConversationKey([u8; 32]);
+
+impl std::fmt::Debug for ConversationKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ConversationKey")
+            .field(&"<sensitive>")
+            .finish()
+    }
+}
+
+impl ConversationKey {
+    /// Derive conversation key (`HKDF-extract` of the ECDH shared x coordinate)
+    pub fn derive(sk: &SecretKey, pk: &XOnlyPublicKey) -> Result<Self, Error> {
+        let pk = PublicKey::from_str(&format!("02{}", pk))?;
+        let ssp = ecdh::shared_secret_point(&pk, sk);
+        Ok(Self(hmac_sha256(SALT, &[&ssp[..32]])))
+    }
+
+    /// Get conversation key as bytes
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+/// Encrypt content with NIP-44 (version 2)
+pub fn encrypt<T>(sk: &SecretKey, pk: &XOnlyPublicKey, content: T) -> Result<String, Error>
+where
+    T: AsRef<[u8]>,
+{
+    let conversation_key = ConversationKey::derive(sk, pk)?;
+    encrypt_with_conversation_key(&conversation_key, content)
+}
+
+/// Encrypt content with an already derived [`ConversationKey`]
+pub fn encrypt_with_conversation_key<T>(
+    conversation_key: &ConversationKey,
+    content: T,
+) -> Result<String, Error>
+where
+    T: AsRef<[u8]>,
+{
+    let nonce: [u8; 32] = bitcoin::secp256k1::rand::random();
+    encrypt_with_nonce(conversation_key, content.as_ref(), nonce)
+}
+
+/// Decrypt NIP-44 payload
+pub fn decrypt<S>(sk: &SecretKey, pk: &XOnlyPublicKey, payload: S) -> Result<String, Error>
+where
+    S: AsRef<str>,
+{
+    let conversation_key = ConversationKey::derive(sk, pk)?;
+    decrypt_with_conversation_key(&conversation_key, payload)
+}
+
+/// Decrypt NIP-44 payload with an already derived [`ConversationKey`]
+pub fn decrypt_with_conversation_key<S>(
+    conversation_key: &ConversationKey,
+    payload: S,
+) -> Result<String, Error>
+where
+    S: AsRef<str>,
+{
+    let payload: &str = payload.as_ref();
+    if payload.starts_with('#') {
+        return Err(Error::UnknownVersion(0));
+    }
+    if payload.len() < 132 || payload.len() > 87472 {
+        return Err(Error::InvalidPayload);
+    }
+
+    let data: Vec<u8> = base64::decode(payload).map_err(|_| Error::Base64Decode)?;
+    if data.len() < 99 || data.len() > 65603 {
+        return Err(Error::InvalidPayload);
+    }
+    if data[0] != VERSION {
+        return Err(Error::UnknownVersion(data[0]));
+    }
+
+    let nonce: &[u8] = &data[1..33];
+    let ciphertext: &[u8] = &data[33..data.len() - 32];
+    let mac: &[u8] = &data[data.len() - 32..];
+
+    let (chacha_key, chacha_nonce, hmac_key) = message_keys(conversation_key, nonce);
+    if !constant_time_eq(&hmac_sha256(&hmac_key, &[nonce, ciphertext]), mac) {
+        return Err(Error::InvalidMac);
+    }
+
+    let mut padded: Vec<u8> = ciphertext.to_vec();
+    ChaCha20::new(&chacha_key.into(), &chacha_nonce.into()).apply_keystream(&mut padded);
+
+    String::from_utf8(unpad(&padded)?.to_vec()).map_err(|_| Error::Utf8Encode)
+}
+
+fn encrypt_with_nonce(
+    conversation_key: &ConversationKey,
+    plaintext: &[u8],
+    nonce: [u8; 32],
+) -> Result<String, Error> {
+    let (chacha_key, chacha_nonce, hmac_key) = message_keys(conversation_key, &nonce);
+
+    let mut ciphertext: Vec<u8> = pad(plaintext)?;
+    ChaCha20::new(&chacha_key.into(), &chacha_nonce.into()).apply_keystream(&mut ciphertext);
+    let mac = hmac_sha256(&hmac_key, &[&nonce, &ciphertext]);
+
+    let mut payload: Vec<u8> = Vec::with_capacity(1 + 32 + ciphertext.len() + 32);
+    payload.push(VERSION);
+    payload.extend_from_slice(&nonce);
+    payload.extend_from_slice(&ciphertext);
+    payload.extend_from_slice(&mac);
+
+    Ok(base64::encode(payload))
+}
+
+/// Get ChaCha20 key, ChaCha20 nonce and HMAC key (`HKDF-expand` of the conversation key)
+fn message_keys(
+    conversation_key: &ConversationKey,
+    nonce: &[u8],
+) -> ([u8; 32], [u8; 12], [u8; 32]) {
+    let mut okm: Vec<u8> = Vec::with_capacity(96);
+    let mut previous: Vec<u8> = Vec::new();
+    for counter in 1..=3u8 {
+        let block = hmac_sha256(conversation_key.as_bytes(), &[&previous, nonce, &[counter]]);
+        okm.extend_from_slice(&block);
+        previous = block.to_vec();
+    }
+
+    let mut chacha_key = [0u8; 32];
+    let mut chacha_nonce = [0u8; 12];
+    let mut hmac_key = [0u8; 32];
+    chacha_key.copy_from_slice(&okm[..32]);
+    chacha_nonce.copy_from_slice(&okm[32..44]);
+    hmac_key.copy_from_slice(&okm[44..76]);
+    (chacha_key, chacha_nonce, hmac_key)
+}
+
+fn hmac_sha256(key: &[u8], data: &[&[u8]]) -> [u8; 32] {
+    let mut engine: HmacEngine<Sha256> = HmacEngine::new(key);
+    for d in data.iter() {
+        engine.input(d);
+    }
+    Hmac::<Sha256>::from_engine(engine).into_inner()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .zip(b.iter())
+            .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+fn calc_padded_len(len: usize) -> usize {
+    if len <= 32 {
+        return 32;
+    }
+    let next_power: usize = 1 << (usize::BITS - (len - 1).leading_zeros());
+    let chunk: usize = if next_power <= 256 {
+        32
+    } else {
+        next_power / 8
+    };
+    chunk * ((len - 1) / chunk + 1)
+}
+
+/// Prefix the plaintext with its length (u16 big-endian) and pad it with zeros
+fn pad(plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+    let len: usize = plaintext.len();
+    if !(MIN_PLAINTEXT_LEN..=MAX_PLAINTEXT_LEN).contains(&len) {
+        return Err(Error::InvalidPlaintextLength);
+    }
+
+    let mut padded: Vec<u8> = Vec::with_capacity(2 + calc_padded_len(len));
+    padded.extend_from_slice(&(len as u16).to_be_bytes());
+    padded.extend_from_slice(plaintext);
+    padded.resize(2 + calc_padded_len(len), 0);
+    Ok(padded)
+}
+
+fn unpad(padded: &[u8]) -> Result<&[u8], Error> {
+    if padded.len() < 2 {
+        return Err(Error::InvalidPadding);
+    }
+    let len: usize = u16::from_be_bytes([padded[0], padded[1]]) as usize;
+    if len < MIN_PLAINTEXT_LEN || padded.len() != 2 + calc_padded_len(len) {
+        return Err(Error::InvalidPadding);
+    }
+    Ok(&padded[2..2 + len])
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::hashes::hex::{FromHex, ToHex};
+    use bitcoin::secp256k1::KeyPair;
+
+    use super::*;
+    use crate::{Result, SECP256K1};
+
+    fn keys(sk: &str) -> Result<(SecretKey, XOnlyPublicKey)> {
+        let sk = SecretKey::from_str(sk)?;
+        let pk = XOnlyPublicKey::from_keypair(&KeyPair::from_secret_key(&SECP256K1, &sk)).0;
+        Ok((sk, pk))
+    }
+
+    #[test]
+    fn test_conversation_key() -> Result<()> {
+        let (sk1, pk1) = keys("0000000000000000000000000000000000000000000000000000000000000001")?;
+        let (sk2, pk2) = keys("0000000000000000000000000000000000000000000000000000000000000002")?;
+
+        let conversation_key = ConversationKey::derive(&sk1, &pk2)?;
+        assert_eq!(
+            conversation_key.as_bytes().to_hex(),
+            "c41c775356fd92eadc63ff5a0dc1da211b268cbea22316767095b2871ea1412d"
+        );
+        assert_eq!(ConversationKey::derive(&sk2, &pk1)?, conversation_key);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_vector() -> Result<()> {
+        let (sk1, pk1) = keys("0000000000000000000000000000000000000000000000000000000000000001")?;
+        let (sk2, pk2) = keys("0000000000000000000000000000000000000000000000000000000000000002")?;
+
+        let nonce: Vec<u8> =
+            Vec::from_hex("0000000000000000000000000000000000000000000000000000000000000001")?;
+        let mut nonce_bytes = [0u8; 32];
+        nonce_bytes.copy_from_slice(&nonce);
+
+        let payload = "AgAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABee0G5VSK0/9YypIObAtDKfYEAjD35uVkHyB0F4DwrcNaCXlCWZKaArsGrY6M9wnuTMxWfp1RTN9Xga8no+kF5Vsb";
+        let conversation_key = ConversationKey::derive(&sk1, &pk2)?;
+        assert_eq!(
+            encrypt_with_nonce(&conversation_key, b"a", nonce_bytes)?,
+            payload
+        );
+        assert_eq!(decrypt(&sk2, &pk1, payload)?, "a");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() -> Result<()> {
+        let (sender_sk, sender_pk) =
+            keys("6b911fd37cdf5c81d4c0adb1ab7fa822ed253ab0ad9aa18d77257c88b29b718e")?;
+        let (receiver_sk, receiver_pk) =
+            keys("7b911fd37cdf5c81d4c0adb1ab7fa822ed253ab0ad9aa18d77257c88b29b718e")?;
+
+        for content in ["Saturn, bringer of old age", &"x".repeat(1000)] {
+            let payload = encrypt(&sender_sk, &receiver_pk, content)?;
+            assert_eq!(decrypt(&receiver_sk, &sender_pk, &payload)?, content);
+        }
+
+        assert_eq!(
+            encrypt(&sender_sk, &receiver_pk, "").unwrap_err(),
+            Error::InvalidPlaintextLength
+        );
+
+        // Tampered payload
+        let mut data = base64::decode(encrypt(&sender_sk, &receiver_pk, "hello")?)?;
+        data[40] ^= 1;
+        assert_eq!(
+            decrypt(&receiver_sk, &sender_pk, base64::encode(data)).unwrap_err(),
+            Error::InvalidMac
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_padded_len() {
+        for (len, padded) in [
+            (1, 32),
+            (32, 32),
+            (33, 64),
+            (37, 64),
+            (64, 64),
+            (65, 96),
+            (100, 128),
+            (256, 256),
+            (257, 320),
+            (383, 384),
+            (1025, 1280),
+            (65535, 65536),
+        ] {
+            assert_eq!(calc_padded_len(len), padded);
+        }
+    }
+}
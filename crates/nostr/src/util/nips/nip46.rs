@@ -0,0 +1,596 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::fmt;
+use std::str::FromStr;
+
+use bitcoin::hashes::hex::ToHex;
+use bitcoin::secp256k1::XOnlyPublicKey;
+use url::form_urlencoded::Serializer;
+use url::Url;
+
+use super::nip04;
+use crate::event::unsigned::{self, UnsignedEvent};
+use crate::key::{self, Keys};
+use crate::{Event, Kind, KindBase};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Error serializing or deserializing JSON data
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+    /// Key error
+    #[error("key error: {0}")]
+    Key(#[from] key::Error),
+    /// NIP04 error
+    #[error("nip04 error: {0}")]
+    NIP04(#[from] nip04::Error),
+    /// Unsigned event error
+    #[error("unsigned event error: {0}")]
+    Unsigned(#[from] unsigned::Error),
+    /// Invalid Nostr Connect URI
+    #[error("invalid uri")]
+    InvalidURI,
+    /// Invalid event kind
+    #[error("invalid event kind")]
+    InvalidKind,
+    /// Unknown method
+    #[error("unsupported method: {0}")]
+    UnsupportedMethod(String),
+    /// Invalid request params
+    #[error("invalid params")]
+    InvalidParams,
+    /// Not a request
+    #[error("not a request")]
+    NotRequest,
+    /// Not a response
+    #[error("not a response")]
+    NotResponse,
+    /// Error returned by the remote signer
+    #[error("response error: {0}")]
+    Response(String),
+}
+
+/// App metadata shared in a `nostrconnect://` URI
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct NostrConnectMetadata {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<Url>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icons: Option<Vec<Url>>,
+}
+
+impl NostrConnectMetadata {
+    /// New `NostrConnectMetadata`
+    pub fn new<S>(name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Set url
+    pub fn url(self, url: Url) -> Self {
+        Self {
+            url: Some(url),
+            ..self
+        }
+    }
+
+    /// Set description
+    pub fn description<S>(self, description: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            description: Some(description.into()),
+            ..self
+        }
+    }
+
+    /// Set icons
+    pub fn icons(self, icons: Vec<Url>) -> Self {
+        Self {
+            icons: Some(icons),
+            ..self
+        }
+    }
+}
+
+/// Nostr Connect URI
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/46.md>
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum NostrConnectURI {
+    /// `bunker://<signer-pubkey>?relay=<relay>&secret=<secret>`, shared by the remote signer
+    Bunker {
+        signer_public_key: XOnlyPublicKey,
+        relays: Vec<Url>,
+        secret: Option<String>,
+    },
+    /// `nostrconnect://<app-pubkey>?relay=<relay>&metadata=<json>`, shared by the app
+    Client {
+        public_key: XOnlyPublicKey,
+        relays: Vec<Url>,
+        metadata: NostrConnectMetadata,
+    },
+}
+
+impl NostrConnectURI {
+    /// Get relays
+    pub fn relays(&self) -> &[Url] {
+        match self {
+            Self::Bunker { relays, .. } => relays,
+            Self::Client { relays, .. } => relays,
+        }
+    }
+}
+
+impl FromStr for NostrConnectURI {
+    type Err = Error;
+
+    fn from_str(uri: &str) -> Result<Self, Self::Err> {
+        let url = Url::parse(uri).map_err(|_| Error::InvalidURI)?;
+        let public_key = url
+            .host_str()
+            .and_then(|pk| XOnlyPublicKey::from_str(pk).ok())
+            .ok_or(Error::InvalidURI)?;
+
+        let mut relays: Vec<Url> = Vec::new();
+        let mut secret: Option<String> = None;
+        let mut metadata: Option<NostrConnectMetadata> = None;
+
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "relay" => relays.push(Url::parse(&value).map_err(|_| Error::InvalidURI)?),
+                "secret" => secret = Some(value.to_string()),
+                "metadata" => metadata = Some(serde_json::from_str(&value)?),
+                _ => (),
+            }
+        }
+
+        if relays.is_empty() {
+            return Err(Error::InvalidURI);
+        }
+
+        match url.scheme() {
+            "bunker" => Ok(Self::Bunker {
+                signer_public_key: public_key,
+                relays,
+                secret,
+            }),
+            "nostrconnect" => Ok(Self::Client {
+                public_key,
+                relays,
+                metadata: metadata.ok_or(Error::InvalidURI)?,
+            }),
+            _ => Err(Error::InvalidURI),
+        }
+    }
+}
+
+impl fmt::Display for NostrConnectURI {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut query = Serializer::new(String::new());
+        for relay in self.relays().iter() {
+            query.append_pair("relay", relay.as_str());
+        }
+
+        match self {
+            Self::Bunker {
+                signer_public_key,
+                secret,
+                ..
+            } => {
+                if let Some(secret) = secret {
+                    query.append_pair("secret", secret);
+                }
+                write!(f, "bunker://{}?{}", signer_public_key, query.finish())
+            }
+            Self::Client {
+                public_key,
+                metadata,
+                ..
+            } => {
+                query.append_pair("metadata", &serde_json::json!(metadata).to_string());
+                write!(f, "nostrconnect://{}?{}", public_key, query.finish())
+            }
+        }
+    }
+}
+
+/// Nostr Connect request
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Request {
+    /// Connect to the remote signer
+    Connect {
+        signer_public_key: XOnlyPublicKey,
+        secret: Option<String>,
+    },
+    /// Get the public key of the user
+    GetPublicKey,
+    /// Sign an event
+    SignEvent(UnsignedEvent),
+    /// Encrypt text (NIP-04)
+    Nip04Encrypt {
+        public_key: XOnlyPublicKey,
+        text: String,
+    },
+    /// Decrypt content (NIP-04)
+    Nip04Decrypt {
+        public_key: XOnlyPublicKey,
+        ciphertext: String,
+    },
+    Ping,
+}
+
+impl Request {
+    /// Get request method
+    pub fn method(&self) -> &str {
+        match self {
+            Self::Connect { .. } => "connect",
+            Self::GetPublicKey => "get_public_key",
+            Self::SignEvent(_) => "sign_event",
+            Self::Nip04Encrypt { .. } => "nip04_encrypt",
+            Self::Nip04Decrypt { .. } => "nip04_decrypt",
+            Self::Ping => "ping",
+        }
+    }
+
+    /// Get request params
+    pub fn params(&self) -> Result<Vec<String>, Error> {
+        Ok(match self {
+            Self::Connect {
+                signer_public_key,
+                secret,
+            } => {
+                let mut params = vec![signer_public_key.to_string()];
+                if let Some(secret) = secret {
+                    params.push(secret.clone());
+                }
+                params
+            }
+            Self::GetPublicKey | Self::Ping => Vec::new(),
+            Self::SignEvent(unsigned) => vec![unsigned.as_json()?],
+            Self::Nip04Encrypt { public_key, text } => vec![public_key.to_string(), text.clone()],
+            Self::Nip04Decrypt {
+                public_key,
+                ciphertext,
+            } => vec![public_key.to_string(), ciphertext.clone()],
+        })
+    }
+
+    /// Parse request from method and params
+    pub fn parse(method: &str, params: &[String]) -> Result<Self, Error> {
+        let public_key = |index: usize| -> Result<XOnlyPublicKey, Error> {
+            params
+                .get(index)
+                .and_then(|pk| XOnlyPublicKey::from_str(pk).ok())
+                .ok_or(Error::InvalidParams)
+        };
+        let param = |index: usize| -> Result<String, Error> {
+            params.get(index).cloned().ok_or(Error::InvalidParams)
+        };
+
+        match method {
+            "connect" => Ok(Self::Connect {
+                signer_public_key: public_key(0)?,
+                secret: params.get(1).cloned(),
+            }),
+            "get_public_key" => Ok(Self::GetPublicKey),
+            "sign_event" => Ok(Self::SignEvent(UnsignedEvent::from_json(param(0)?)?)),
+            "nip04_encrypt" => Ok(Self::Nip04Encrypt {
+                public_key: public_key(0)?,
+                text: param(1)?,
+            }),
+            "nip04_decrypt" => Ok(Self::Nip04Decrypt {
+                public_key: public_key(0)?,
+                ciphertext: param(1)?,
+            }),
+            "ping" => Ok(Self::Ping),
+            method => Err(Error::UnsupportedMethod(method.to_string())),
+        }
+    }
+}
+
+/// Nostr Connect message, sent NIP-04 encrypted in a kind 24133 event
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Message {
+    Request {
+        id: String,
+        method: String,
+        params: Vec<String>,
+    },
+    Response {
+        id: String,
+        result: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+}
+
+impl Message {
+    /// New request message with random id
+    pub fn request(req: Request) -> Result<Self, Error> {
+        let id: [u8; 16] = bitcoin::secp256k1::rand::random();
+        Ok(Self::Request {
+            id: id.to_hex(),
+            method: req.method().to_string(),
+            params: req.params()?,
+        })
+    }
+
+    /// New response message
+    pub fn response<S>(id: S, result: Option<String>, error: Option<String>) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::Response {
+            id: id.into(),
+            result,
+            error,
+        }
+    }
+
+    /// Get message id
+    pub fn id(&self) -> &str {
+        match self {
+            Self::Request { id, .. } => id,
+            Self::Response { id, .. } => id,
+        }
+    }
+
+    /// Check if it's a response
+    pub fn is_response(&self) -> bool {
+        matches!(self, Self::Response { .. })
+    }
+
+    /// Get the [`Request`] of a request message
+    pub fn to_request(&self) -> Result<Request, Error> {
+        match self {
+            Self::Request { method, params, .. } => Request::parse(method, params),
+            Self::Response { .. } => Err(Error::NotRequest),
+        }
+    }
+
+    /// Get the result of a response message
+    pub fn result(&self) -> Result<&str, Error> {
+        match self {
+            Self::Response {
+                error: Some(error), ..
+            } => Err(Error::Response(error.clone())),
+            Self::Response { result, .. } => Ok(result.as_deref().unwrap_or_default()),
+            Self::Request { .. } => Err(Error::NotResponse),
+        }
+    }
+
+    /// Decrypt message from a kind 24133 event addressed to `keys`
+    pub fn from_event(keys: &Keys, event: &Event) -> Result<Self, Error> {
+        if event.kind != Kind::Base(KindBase::NostrConnect) {
+            return Err(Error::InvalidKind);
+        }
+
+        let json = nip04::decrypt(&keys.secret_key()?, &event.pubkey, &event.content)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    /// Get message as json string
+    pub fn as_json(&self) -> String {
+        serde_json::json!(self).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EventBuilder, Result};
+
+    #[test]
+    fn test_parse_uri() -> Result<()> {
+        let uri = "bunker://79dff8f82963424e0bb02708a22e44b4980893e3a4be0fa3cb60a43b946764e3?relay=wss%3A%2F%2Frelay.nsec.app%2F&secret=abcd";
+        let uri = NostrConnectURI::from_str(uri)?;
+        assert_eq!(
+            uri,
+            NostrConnectURI::Bunker {
+                signer_public_key: XOnlyPublicKey::from_str(
+                    "79dff8f82963424e0bb02708a22e44b4980893e3a4be0fa3cb60a43b946764e3"
+                )?,
+                relays: vec![Url::parse("wss://relay.nsec.app")?],
+                secret: Some(String::from("abcd")),
+            }
+        );
+        assert_eq!(NostrConnectURI::from_str(&uri.to_string())?, uri);
+
+        let keys = Keys::generate_from_os_random();
+        let uri = NostrConnectURI::Client {
+            public_key: keys.public_key(),
+            relays: vec![Url::parse("wss://relay.damus.io")?],
+            metadata: NostrConnectMetadata::new("Example").description("Example app"),
+        };
+        assert_eq!(NostrConnectURI::from_str(&uri.to_string())?, uri);
+
+        assert!(NostrConnectURI::from_str("bunker://invalid?relay=wss://relay.damus.io").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_message() -> Result<()> {
+        let app_keys = Keys::generate_from_os_random();
+        let signer_keys = Keys::generate_from_os_random();
+
+        let unsigned =
+            EventBuilder::new_text_note("Hello", &[]).to_unsigned_event(signer_keys.public_key());
+        let msg = Message::request(Request::SignEvent(unsigned.clone()))?;
+
+        let event = EventBuilder::nostr_connect(&app_keys, signer_keys.public_key(), &msg)?
+            .to_event(&app_keys)?;
+        assert_eq!(event.kind, Kind::Base(KindBase::NostrConnect));
+
+        let received = Message::from_event(&signer_keys, &event)?;
+        assert_eq!(received, msg);
+        assert_eq!(received.to_request()?, Request::SignEvent(unsigned));
+
+        let response = Message::response(msg.id(), None, Some(String::from("denied")));
+        let response = Message::from_event(
+            &app_keys,
+            &EventBuilder::nostr_connect(&signer_keys, app_keys.public_key(), &response)?
+                .to_event(&signer_keys)?,
+        )?;
+        assert!(response.is_response());
+        assert!(matches!(response.result(), Err(Error::Response(e)) if e == "denied"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_uri_spec_examples() -> Result<()> {
+        let public_key = XOnlyPublicKey::from_str(
+            "83f3b2ae6aa368e8275397b9c26cf550101d63ebaab900d19dd4a4429f5ad8f5",
+        )?;
+
+        let uri = NostrConnectURI::from_str("bunker://83f3b2ae6aa368e8275397b9c26cf550101d63ebaab900d19dd4a4429f5ad8f5?relay=wss://relay1.example.com&relay=wss%3A%2F%2Frelay2.example2.com")?;
+        assert_eq!(
+            uri,
+            NostrConnectURI::Bunker {
+                signer_public_key: public_key,
+                relays: vec![
+                    Url::parse("wss://relay1.example.com")?,
+                    Url::parse("wss://relay2.example2.com")?
+                ],
+                secret: None,
+            }
+        );
+
+        let uri = NostrConnectURI::from_str("nostrconnect://83f3b2ae6aa368e8275397b9c26cf550101d63ebaab900d19dd4a4429f5ad8f5?relay=wss%3A%2F%2Frelay.damus.io&metadata=%7B%22name%22%3A%22Example%22%2C%22url%22%3A%22https%3A%2F%2Fexample.com%2F%22%7D&perms=sign_event%3A1")?;
+        assert_eq!(
+            uri,
+            NostrConnectURI::Client {
+                public_key,
+                relays: vec![Url::parse("wss://relay.damus.io")?],
+                metadata: NostrConnectMetadata::new("Example")
+                    .url(Url::parse("https://example.com")?),
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_uri_invalid() {
+        let public_key = "83f3b2ae6aa368e8275397b9c26cf550101d63ebaab900d19dd4a4429f5ad8f5";
+        for uri in [
+            String::from("bunker"),
+            String::from("bunker://?relay=wss://relay.damus.io"),
+            format!("bunker://{}", public_key),
+            format!("bunker://{}?secret=abcd", public_key),
+            format!("bunker://{}?relay=relay.damus.io", public_key),
+            format!("nostrconnect://{}?relay=wss://relay.damus.io", public_key),
+            format!("nostr://{}?relay=wss://relay.damus.io", public_key),
+        ] {
+            assert!(
+                matches!(NostrConnectURI::from_str(&uri), Err(Error::InvalidURI)),
+                "{}",
+                uri
+            );
+        }
+
+        assert!(matches!(
+            NostrConnectURI::from_str(&format!(
+                "nostrconnect://{}?relay=wss://relay.damus.io&metadata=Example",
+                public_key
+            )),
+            Err(Error::Json(_))
+        ));
+    }
+
+    #[test]
+    fn test_message_spec_examples() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        let unsigned =
+            EventBuilder::new_text_note("Hello", &[]).to_unsigned_event(keys.public_key());
+
+        let msg: Message = serde_json::from_str(&format!(
+            r#"{{"id": "e4a1", "method": "sign_event", "params": [{}]}}"#,
+            serde_json::json!(unsigned.as_json()?)
+        ))?;
+        assert_eq!(msg.id(), "e4a1");
+        assert_eq!(msg.to_request()?, Request::SignEvent(unsigned));
+
+        let msg: Message = serde_json::from_str(&format!(
+            r#"{{"id": "e4a2", "method": "connect", "params": ["{}", "0s8j2djs"]}}"#,
+            keys.public_key()
+        ))?;
+        assert_eq!(
+            msg.to_request()?,
+            Request::Connect {
+                signer_public_key: keys.public_key(),
+                secret: Some(String::from("0s8j2djs")),
+            }
+        );
+
+        let msg: Message = serde_json::from_str(r#"{"id": "e4a2", "result": "ack"}"#)?;
+        assert!(msg.is_response());
+        assert_eq!(msg.result()?, "ack");
+        let msg: Message =
+            serde_json::from_str(r#"{"id": "e4a3", "result": null, "error": "denied"}"#)?;
+        assert!(matches!(msg.result(), Err(Error::Response(e)) if e == "denied"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_message_invalid() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        let public_key = keys.public_key().to_string();
+
+        for (method, params) in [
+            ("connect", vec![]),
+            ("connect", vec!["not a public key"]),
+            ("sign_event", vec![]),
+            ("nip04_encrypt", vec![public_key.as_str()]),
+            ("nip04_decrypt", vec!["not a public key", "ciphertext"]),
+        ] {
+            let params: Vec<String> = params.into_iter().map(String::from).collect();
+            assert!(matches!(
+                Request::parse(method, &params),
+                Err(Error::InvalidParams)
+            ));
+        }
+        assert!(matches!(
+            Request::parse("sign_event", &[String::from("{}")]),
+            Err(Error::Unsigned(_))
+        ));
+        assert!(matches!(
+            Request::parse("nip44_encrypt", &[]),
+            Err(Error::UnsupportedMethod(m)) if m == "nip44_encrypt"
+        ));
+
+        let response = Message::response("e4a1", Some(String::from("ack")), None);
+        assert!(matches!(response.to_request(), Err(Error::NotRequest)));
+        let request = Message::request(Request::Ping)?;
+        assert!(matches!(request.result(), Err(Error::NotResponse)));
+
+        // Events of another kind or not encrypted for us
+        let event = EventBuilder::new_text_note(request.as_json(), &[]).to_event(&keys)?;
+        assert!(matches!(
+            Message::from_event(&keys, &event),
+            Err(Error::InvalidKind)
+        ));
+        let event = EventBuilder::new(Kind::Base(KindBase::NostrConnect), request.as_json(), &[])
+            .to_event(&keys)?;
+        assert!(matches!(
+            Message::from_event(&keys, &event),
+            Err(Error::NIP04(_))
+        ));
+
+        Ok(())
+    }
+}
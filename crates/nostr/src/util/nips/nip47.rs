@@ -0,0 +1,478 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::fmt;
+use std::str::FromStr;
+
+use bitcoin::secp256k1::{SecretKey, XOnlyPublicKey};
+use serde_json::{json, Value};
+use url::form_urlencoded::Serializer;
+use url::Url;
+
+use super::nip04;
+use crate::event::TagKind;
+use crate::{Event, Kind, KindBase, Sha256Hash};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Error serializing or deserializing JSON data
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+    /// NIP04 error
+    #[error("nip04 error: {0}")]
+    NIP04(#[from] nip04::Error),
+    /// Invalid Nostr Wallet Connect URI
+    #[error("invalid uri")]
+    InvalidURI,
+    /// Invalid event kind
+    #[error("invalid event kind")]
+    InvalidKind,
+    /// The response is for another method
+    #[error("unexpected result type")]
+    UnexpectedResultType,
+    /// The response has neither result nor error
+    #[error("result missing")]
+    ResultMissing,
+    /// Error returned by the wallet service
+    #[error("{0}")]
+    Response(NIP47Error),
+}
+
+/// Nostr Wallet Connect URI
+///
+/// `nostr+walletconnect://<wallet-pubkey>?relay=<relay>&secret=<secret>&lud16=<lud16>`
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/47.md>
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct NostrWalletConnectURI {
+    /// Wallet service public key
+    pub public_key: XOnlyPublicKey,
+    pub relay_url: Url,
+    /// Secret key used by the client to sign and encrypt the requests
+    pub secret: SecretKey,
+    /// Lightning address of the wallet
+    pub lud16: Option<String>,
+}
+
+impl NostrWalletConnectURI {
+    /// New `NostrWalletConnectURI`
+    pub fn new(
+        public_key: XOnlyPublicKey,
+        relay_url: Url,
+        secret: SecretKey,
+        lud16: Option<String>,
+    ) -> Self {
+        Self {
+            public_key,
+            relay_url,
+            secret,
+            lud16,
+        }
+    }
+}
+
+impl FromStr for NostrWalletConnectURI {
+    type Err = Error;
+
+    fn from_str(uri: &str) -> Result<Self, Self::Err> {
+        let url = Url::parse(uri).map_err(|_| Error::InvalidURI)?;
+        if url.scheme() != "nostr+walletconnect" && url.scheme() != "nostrwalletconnect" {
+            return Err(Error::InvalidURI);
+        }
+
+        let public_key = url
+            .host_str()
+            .and_then(|pk| XOnlyPublicKey::from_str(pk).ok())
+            .ok_or(Error::InvalidURI)?;
+
+        let mut relay_url: Option<Url> = None;
+        let mut secret: Option<SecretKey> = None;
+        let mut lud16: Option<String> = None;
+
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "relay" => relay_url = Some(Url::parse(&value).map_err(|_| Error::InvalidURI)?),
+                "secret" => {
+                    secret = Some(SecretKey::from_str(&value).map_err(|_| Error::InvalidURI)?)
+                }
+                "lud16" => lud16 = Some(value.to_string()),
+                _ => (),
+            }
+        }
+
+        Ok(Self {
+            public_key,
+            relay_url: relay_url.ok_or(Error::InvalidURI)?,
+            secret: secret.ok_or(Error::InvalidURI)?,
+            lud16,
+        })
+    }
+}
+
+impl fmt::Display for NostrWalletConnectURI {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut query = Serializer::new(String::new());
+        query.append_pair("relay", self.relay_url.as_str());
+        query.append_pair("secret", &self.secret.display_secret().to_string());
+        if let Some(lud16) = &self.lud16 {
+            query.append_pair("lud16", lud16);
+        }
+        write!(
+            f,
+            "nostr+walletconnect://{}?{}",
+            self.public_key,
+            query.finish()
+        )
+    }
+}
+
+/// Nostr Wallet Connect request
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Request {
+    /// Pay a bolt11 invoice
+    PayInvoice {
+        invoice: String,
+        /// Amount in millisats, for zero-amount invoices
+        amount: Option<u64>,
+    },
+    GetBalance,
+    GetInfo,
+}
+
+impl Request {
+    /// Get request method
+    pub fn method(&self) -> &str {
+        match self {
+            Self::PayInvoice { .. } => "pay_invoice",
+            Self::GetBalance => "get_balance",
+            Self::GetInfo => "get_info",
+        }
+    }
+
+    /// Get request as json string
+    pub fn as_json(&self) -> String {
+        let params: Value = match self {
+            Self::PayInvoice { invoice, amount } => match amount {
+                Some(amount) => json!({ "invoice": invoice, "amount": amount }),
+                None => json!({ "invoice": invoice }),
+            },
+            Self::GetBalance | Self::GetInfo => json!({}),
+        };
+        json!({ "method": self.method(), "params": params }).to_string()
+    }
+}
+
+/// Error code of a wallet service response
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    RateLimited,
+    NotImplemented,
+    InsufficientBalance,
+    QuotaExceeded,
+    Restricted,
+    Unauthorized,
+    Internal,
+    #[serde(other)]
+    Other,
+}
+
+/// Error of a wallet service response
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct NIP47Error {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl fmt::Display for NIP47Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: {}", self.code, self.message)
+    }
+}
+
+/// `pay_invoice` result
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PayInvoiceResponseResult {
+    pub preimage: String,
+}
+
+/// `get_balance` result
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct GetBalanceResponseResult {
+    /// Balance in millisats
+    pub balance: u64,
+}
+
+/// `get_info` result
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct GetInfoResponseResult {
+    #[serde(default)]
+    pub alias: Option<String>,
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub pubkey: Option<String>,
+    #[serde(default)]
+    pub network: Option<String>,
+    #[serde(default)]
+    pub block_height: Option<u32>,
+    #[serde(default)]
+    pub block_hash: Option<String>,
+    /// Supported methods
+    #[serde(default)]
+    pub methods: Vec<String>,
+}
+
+/// Nostr Wallet Connect response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+    pub result_type: String,
+    #[serde(default)]
+    pub error: Option<NIP47Error>,
+    #[serde(default)]
+    pub result: Option<Value>,
+}
+
+impl Response {
+    /// Decrypt response from a kind 23195 event
+    pub fn from_event(uri: &NostrWalletConnectURI, event: &Event) -> Result<Self, Error> {
+        if event.kind != Kind::Base(KindBase::WalletConnectResponse) {
+            return Err(Error::InvalidKind);
+        }
+
+        let json = nip04::decrypt(&uri.secret, &event.pubkey, &event.content)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    /// Get the id of the request event (`e` tag) of a kind 23195 event
+    pub fn request_id(event: &Event) -> Option<Sha256Hash> {
+        event
+            .tags
+            .iter()
+            .find(|t| matches!(t.kind(), Ok(TagKind::E)))
+            .and_then(|t| t.content())
+            .and_then(|id| Sha256Hash::from_str(id).ok())
+    }
+
+    /// Get `pay_invoice` result
+    pub fn to_pay_invoice(&self) -> Result<PayInvoiceResponseResult, Error> {
+        self.result_of("pay_invoice")
+    }
+
+    /// Get `get_balance` result
+    pub fn to_get_balance(&self) -> Result<GetBalanceResponseResult, Error> {
+        self.result_of("get_balance")
+    }
+
+    /// Get `get_info` result
+    pub fn to_get_info(&self) -> Result<GetInfoResponseResult, Error> {
+        self.result_of("get_info")
+    }
+
+    fn result_of<T>(&self, method: &str) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        if let Some(error) = &self.error {
+            return Err(Error::Response(error.clone()));
+        }
+        if self.result_type != method {
+            return Err(Error::UnexpectedResultType);
+        }
+        let result = self.result.clone().ok_or(Error::ResultMissing)?;
+        Ok(serde_json::from_value(result)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EventBuilder, Keys, Result, Tag};
+
+    #[test]
+    fn test_parse_uri() -> Result<()> {
+        let uri = "nostr+walletconnect://b889ff5b1513b641e2a139f661a661364979c5beee91842f8f0ef42ab558e9d4?relay=wss%3A%2F%2Frelay.damus.io%2F&secret=71a8c14c1407c113601079c4302dab36460f0ccd0ad506f1f2dc73b5100e4f3c&lud16=nostr%40nostr.com";
+        let uri = NostrWalletConnectURI::from_str(uri)?;
+        assert_eq!(
+            uri,
+            NostrWalletConnectURI::new(
+                XOnlyPublicKey::from_str(
+                    "b889ff5b1513b641e2a139f661a661364979c5beee91842f8f0ef42ab558e9d4"
+                )?,
+                Url::parse("wss://relay.damus.io")?,
+                SecretKey::from_str(
+                    "71a8c14c1407c113601079c4302dab36460f0ccd0ad506f1f2dc73b5100e4f3c"
+                )?,
+                Some(String::from("nostr@nostr.com")),
+            )
+        );
+        assert_eq!(NostrWalletConnectURI::from_str(&uri.to_string())?, uri);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_request_response() -> Result<()> {
+        let wallet = Keys::generate_from_os_random();
+        let client = Keys::generate_from_os_random();
+        let uri = NostrWalletConnectURI::new(
+            wallet.public_key(),
+            Url::parse("wss://relay.damus.io")?,
+            client.secret_key()?,
+            None,
+        );
+
+        let request = Request::PayInvoice {
+            invoice: String::from("lnbc210n1..."),
+            amount: None,
+        };
+        let event =
+            EventBuilder::nostr_wallet_connect_request(&uri, &request)?.to_event(&client)?;
+        assert_eq!(event.kind, Kind::Base(KindBase::WalletConnectRequest));
+        assert_eq!(
+            nip04::decrypt(&wallet.secret_key()?, &client.public_key(), &event.content)?,
+            request.as_json()
+        );
+
+        let content = r#"{"result_type":"pay_invoice","result":{"preimage":"0123456789abcdef"}}"#;
+        let response = EventBuilder::new(
+            Kind::Base(KindBase::WalletConnectResponse),
+            nip04::encrypt(&wallet.secret_key()?, &client.public_key(), content)?,
+            &[Tag::from(vec![String::from("e"), event.id.to_string()])],
+        )
+        .to_event(&wallet)?;
+        assert_eq!(Response::request_id(&response), Some(event.id));
+
+        let response = Response::from_event(&uri, &response)?;
+        assert_eq!(response.to_pay_invoice()?.preimage, "0123456789abcdef");
+        assert!(matches!(
+            response.to_get_balance(),
+            Err(Error::UnexpectedResultType)
+        ));
+
+        let response: Response = serde_json::from_str(
+            r#"{"result_type":"get_balance","error":{"code":"UNAUTHORIZED","message":"no"}}"#,
+        )?;
+        assert!(
+            matches!(response.to_get_balance(), Err(Error::Response(e)) if e.code == ErrorCode::Unauthorized)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_response_spec_examples() -> Result<()> {
+        let response: Response = serde_json::from_str(
+            r#"{"result_type": "get_balance", "result": {"balance": 10000, "max_amount": 100000, "budget_renewal": "monthly"}}"#,
+        )?;
+        assert_eq!(response.to_get_balance()?.balance, 10000);
+
+        let response: Response = serde_json::from_str(
+            r#"{
+                "result_type": "get_info",
+                "result": {
+                    "alias": "Alby",
+                    "color": "3399ff",
+                    "pubkey": "b889ff5b1513b641e2a139f661a661364979c5beee91842f8f0ef42ab558e9d4",
+                    "network": "mainnet",
+                    "block_height": 800000,
+                    "block_hash": "00000000000000000002a7c4c1e48d76c5a37902165a270156b7a8d72728a054",
+                    "methods": ["pay_invoice", "get_balance", "get_info"]
+                }
+            }"#,
+        )?;
+        let info = response.to_get_info()?;
+        assert_eq!(info.alias.as_deref(), Some("Alby"));
+        assert_eq!(info.block_height, Some(800000));
+        assert_eq!(info.methods, vec!["pay_invoice", "get_balance", "get_info"]);
+
+        let response: Response = serde_json::from_str(
+            r#"{"result_type": "pay_invoice", "error": {"code": "PAYMENT_FAILED", "message": "No route"}}"#,
+        )?;
+        assert!(matches!(
+            response.to_pay_invoice(),
+            Err(Error::Response(NIP47Error { code: ErrorCode::Other, message })) if message == "No route"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_uri_and_response() -> Result<()> {
+        let public_key = "b889ff5b1513b641e2a139f661a661364979c5beee91842f8f0ef42ab558e9d4";
+        let secret = "71a8c14c1407c113601079c4302dab36460f0ccd0ad506f1f2dc73b5100e4f3c";
+        for uri in [
+            String::from("nostr+walletconnect"),
+            format!("nostr+walletconnect://{}?secret={}", public_key, secret),
+            format!(
+                "nostr+walletconnect://{}?relay=wss://relay.damus.io",
+                public_key
+            ),
+            format!(
+                "nostr+walletconnect://{}?relay=relay.damus.io&secret={}",
+                public_key, secret
+            ),
+            format!(
+                "nostr+walletconnect://{}?relay=wss://relay.damus.io&secret=abcd",
+                public_key
+            ),
+            format!(
+                "nostr+walletconnect://npub?relay=wss://relay.damus.io&secret={}",
+                secret
+            ),
+            format!(
+                "nostr://{}?relay=wss://relay.damus.io&secret={}",
+                public_key, secret
+            ),
+        ] {
+            assert!(
+                matches!(
+                    NostrWalletConnectURI::from_str(&uri),
+                    Err(Error::InvalidURI)
+                ),
+                "{}",
+                uri
+            );
+        }
+
+        let response: Response = serde_json::from_str(r#"{"result_type": "get_balance"}"#)?;
+        assert!(matches!(
+            response.to_get_balance(),
+            Err(Error::ResultMissing)
+        ));
+        let response: Response = serde_json::from_str(
+            r#"{"result_type": "get_balance", "result": {"balance": "10000"}}"#,
+        )?;
+        assert!(matches!(response.to_get_balance(), Err(Error::Json(_))));
+        assert!(serde_json::from_str::<Response>(r#"{"result": {"balance": 10000}}"#).is_err());
+
+        let wallet = Keys::generate_from_os_random();
+        let uri = NostrWalletConnectURI::new(
+            wallet.public_key(),
+            Url::parse("wss://relay.damus.io")?,
+            wallet.secret_key()?,
+            None,
+        );
+        let event = EventBuilder::new(
+            Kind::Base(KindBase::WalletConnectResponse),
+            "not encrypted",
+            &[Tag::from(vec![
+                String::from("e"),
+                String::from("not an event id"),
+            ])],
+        )
+        .to_event(&wallet)?;
+        assert_eq!(Response::request_id(&event), None);
+        assert!(matches!(
+            Response::from_event(&uri, &event),
+            Err(Error::NIP04(_))
+        ));
+        let event = EventBuilder::new_text_note("", &[]).to_event(&wallet)?;
+        assert!(matches!(
+            Response::from_event(&uri, &event),
+            Err(Error::InvalidKind)
+        ));
+
+        Ok(())
+    }
+}
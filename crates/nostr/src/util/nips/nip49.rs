@@ -0,0 +1,225 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use bitcoin::bech32::{self, FromBase32, ToBase32, Variant};
+use bitcoin::secp256k1::rand::rngs::OsRng;
+use bitcoin::secp256k1::rand::RngCore;
+use bitcoin::secp256k1::SecretKey;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use scrypt::Params;
+use unicode_normalization::UnicodeNormalization;
+
+const PREFIX_BECH32_NCRYPTSEC: &str = "ncryptsec";
+const VERSION: u8 = 0x02;
+const SALT_SIZE: usize = 16;
+const NONCE_SIZE: usize = 24;
+const CIPHERTEXT_SIZE: usize = 48;
+const PAYLOAD_SIZE: usize = 1 + 1 + SALT_SIZE + NONCE_SIZE + 1 + CIPHERTEXT_SIZE;
+
+/// Default scrypt `log_n`
+pub const DEFAULT_LOG_N: u8 = 16;
+/// Max scrypt `log_n` accepted (`2^22` rounds need 4 GiB of memory)
+pub const MAX_LOG_N: u8 = 22;
+
+#[derive(Debug, Eq, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// Bech32 error
+    #[error("bech32 error: {0}")]
+    Bech32(#[from] bech32::Error),
+    /// Invalid scrypt params
+    #[error("invalid scrypt params")]
+    InvalidScryptParams,
+    /// Scrypt `log_n` too high
+    #[error("scrypt log_n too high: {0} (max {MAX_LOG_N})")]
+    LogNTooHigh(u8),
+    /// Invalid ncryptsec
+    #[error("invalid ncryptsec")]
+    InvalidNcryptsec,
+    /// Unsupported version
+    #[error("unsupported version: {0}")]
+    UnsupportedVersion(u8),
+    /// Wrong password or corrupted data
+    #[error("impossible to decrypt: wrong password or corrupted data")]
+    Decryption,
+    /// Secp256k1 error
+    #[error("secp256k1 error: {0}")]
+    Secp256k1(#[from] bitcoin::secp256k1::Error),
+}
+
+/// How the secret key was handled before the encryption
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum KeySecurity {
+    /// The key has been known to be handled insecurely
+    Weak = 0x00,
+    /// The key has NOT been known to be handled insecurely
+    Medium = 0x01,
+    /// The client does not track this data
+    Unknown = 0x02,
+}
+
+impl TryFrom<u8> for KeySecurity {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x00 => Ok(Self::Weak),
+            0x01 => Ok(Self::Medium),
+            0x02 => Ok(Self::Unknown),
+            _ => Err(Error::InvalidNcryptsec),
+        }
+    }
+}
+
+fn derive_key(password: &str, salt: &[u8], log_n: u8) -> Result<[u8; 32], Error> {
+    if log_n > MAX_LOG_N {
+        return Err(Error::LogNTooHigh(log_n));
+    }
+    let password: String = password.nfkc().collect();
+    let params = Params::new(log_n, 8, 1).map_err(|_| Error::InvalidScryptParams)?;
+    let mut key = [0u8; 32];
+    scrypt::scrypt(password.as_bytes(), salt, &params, &mut key)
+        .map_err(|_| Error::InvalidScryptParams)?;
+    Ok(key)
+}
+
+/// Encrypt secret key with password (`ncryptsec`)
+///
+/// `log_n` is the scrypt work factor: every increment doubles time and memory (max [`MAX_LOG_N`]).
+pub fn encrypt(
+    secret_key: &SecretKey,
+    password: &str,
+    log_n: u8,
+    key_security: KeySecurity,
+) -> Result<String, Error> {
+    let mut salt = [0u8; SALT_SIZE];
+    let mut nonce = [0u8; NONCE_SIZE];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce);
+
+    let key = derive_key(password, &salt, log_n)?;
+    let cipher = XChaCha20Poly1305::new(&key.into());
+    let ciphertext = cipher
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: &secret_key.secret_bytes(),
+                aad: &[key_security as u8],
+            },
+        )
+        .map_err(|_| Error::Decryption)?;
+
+    let mut payload: Vec<u8> = Vec::with_capacity(PAYLOAD_SIZE);
+    payload.push(VERSION);
+    payload.push(log_n);
+    payload.extend_from_slice(&salt);
+    payload.extend_from_slice(&nonce);
+    payload.push(key_security as u8);
+    payload.extend_from_slice(&ciphertext);
+
+    Ok(bech32::encode(
+        PREFIX_BECH32_NCRYPTSEC,
+        payload.to_base32(),
+        Variant::Bech32,
+    )?)
+}
+
+/// Decrypt `ncryptsec` with password
+pub fn decrypt(ncryptsec: &str, password: &str) -> Result<(SecretKey, KeySecurity), Error> {
+    let (hrp, data, checksum) = bech32::decode(ncryptsec)?;
+    if hrp != PREFIX_BECH32_NCRYPTSEC || checksum != Variant::Bech32 {
+        return Err(Error::InvalidNcryptsec);
+    }
+
+    let payload = Vec::<u8>::from_base32(&data)?;
+    if payload.len() != PAYLOAD_SIZE {
+        return Err(Error::InvalidNcryptsec);
+    }
+
+    let version = payload[0];
+    if version != VERSION {
+        return Err(Error::UnsupportedVersion(version));
+    }
+
+    let log_n = payload[1];
+    let salt = &payload[2..2 + SALT_SIZE];
+    let nonce = &payload[2 + SALT_SIZE..2 + SALT_SIZE + NONCE_SIZE];
+    let key_security_byte = payload[2 + SALT_SIZE + NONCE_SIZE];
+    let ciphertext = &payload[PAYLOAD_SIZE - CIPHERTEXT_SIZE..];
+
+    let key = derive_key(password, salt, log_n)?;
+    let cipher = XChaCha20Poly1305::new(&key.into());
+    let secret_bytes = cipher
+        .decrypt(
+            XNonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: &[key_security_byte],
+            },
+        )
+        .map_err(|_| Error::Decryption)?;
+
+    Ok((
+        SecretKey::from_slice(&secret_bytes)?,
+        KeySecurity::try_from(key_security_byte)?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let secret_key =
+            SecretKey::from_str("3501454135014541350145413501453fefb02227e449e57cf4d3a3ce05378683")
+                .unwrap();
+
+        let ncryptsec = encrypt(&secret_key, "nostr", 4, KeySecurity::Medium).unwrap();
+        assert!(ncryptsec.starts_with("ncryptsec1"));
+        assert_eq!(
+            decrypt(&ncryptsec, "nostr").unwrap(),
+            (secret_key, KeySecurity::Medium)
+        );
+        assert_eq!(decrypt(&ncryptsec, "wrong").unwrap_err(), Error::Decryption);
+    }
+
+    #[test]
+    fn test_decrypt_vector() {
+        let ncryptsec = "ncryptsec1qgg9947rlpvqu76pj5ecreduf9jxhselq2nae2kghhvd5g7dgjtcxfqtd67p9m0w57lspw8gsq6yphnm8623nsl8xn9j4jdzz84zm3frztj3z7s35vpzmqf6ksu8r89qk5z2zxfmu5gv8th8wclt0h4p";
+        let (secret_key, _) = decrypt(ncryptsec, "nostr").unwrap();
+        assert_eq!(
+            secret_key.display_secret().to_string(),
+            "3501454135014541350145413501453fefb02227e449e57cf4d3a3ce05378683"
+        );
+    }
+
+    #[test]
+    fn test_log_n_too_high() {
+        let secret_key =
+            SecretKey::from_str("3501454135014541350145413501453fefb02227e449e57cf4d3a3ce05378683")
+                .unwrap();
+        assert_eq!(
+            encrypt(&secret_key, "nostr", 23, KeySecurity::Medium).unwrap_err(),
+            Error::LogNTooHigh(23)
+        );
+
+        // Tampered `log_n` must be rejected before running scrypt
+        let ncryptsec = encrypt(&secret_key, "nostr", 4, KeySecurity::Medium).unwrap();
+        let (_, data, _) = bech32::decode(&ncryptsec).unwrap();
+        let mut payload = Vec::<u8>::from_base32(&data).unwrap();
+        payload[1] = 63;
+        let ncryptsec = bech32::encode(
+            PREFIX_BECH32_NCRYPTSEC,
+            payload.to_base32(),
+            Variant::Bech32,
+        )
+        .unwrap();
+        assert_eq!(
+            decrypt(&ncryptsec, "nostr").unwrap_err(),
+            Error::LogNTooHigh(63)
+        );
+    }
+}
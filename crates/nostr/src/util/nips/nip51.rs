@@ -0,0 +1,416 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::str::FromStr;
+
+use bitcoin::secp256k1::XOnlyPublicKey;
+
+use super::nip04;
+use super::nip19::Coordinate;
+use crate::event::{TagData, TagKind};
+use crate::key::{self, Keys};
+use crate::{Event, Kind, KindBase, Sha256Hash, Tag};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Key error
+    #[error("key error: {0}")]
+    Key(#[from] key::Error),
+    /// Error serializing or deserializing JSON data
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+    /// NIP04 error
+    #[error("nip04 error: {0}")]
+    NIP04(#[from] nip04::Error),
+    /// Invalid event kind
+    #[error("invalid event kind")]
+    InvalidKind,
+    /// Identifier (`d` tag) not found
+    #[error("identifier not found")]
+    IdentifierNotFound,
+}
+
+/// Standard list kind
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ListKind {
+    /// Mute list (kind 10000)
+    Mute,
+    /// Pin list (kind 10001)
+    Pin,
+    /// Bookmarks (kind 10003)
+    Bookmarks,
+    /// Categorized people list (kind 30000), with its identifier (`d` tag)
+    People(String),
+}
+
+impl ListKind {
+    /// Get event kind
+    pub fn kind(&self) -> Kind {
+        match self {
+            Self::Mute => Kind::Base(KindBase::MuteList),
+            Self::Pin => Kind::Base(KindBase::PinList),
+            Self::Bookmarks => Kind::Base(KindBase::Bookmarks),
+            Self::People(..) => Kind::Base(KindBase::PeopleList),
+        }
+    }
+}
+
+/// List item
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ListItem {
+    /// Public key (`p` tag)
+    PubKey(XOnlyPublicKey),
+    /// Event id (`e` tag)
+    Event(Sha256Hash),
+    /// Parameterized replaceable event (`a` tag)
+    Coordinate(Coordinate),
+    /// Hashtag (`t` tag)
+    Hashtag(String),
+    /// Muted word (`word` tag)
+    Word(String),
+    /// Any other tag
+    Tag(Tag),
+}
+
+impl ListItem {
+    /// Parse item from tag
+    pub fn from_tag(tag: &Tag) -> Self {
+        let content = tag.content();
+        let item = match (tag.kind(), content) {
+            (Ok(TagKind::P), Some(pk)) => XOnlyPublicKey::from_str(pk).ok().map(Self::PubKey),
+            (Ok(TagKind::E), Some(id)) => Sha256Hash::from_str(id).ok().map(Self::Event),
+            (Ok(TagKind::A), Some(coordinate)) => Coordinate::from_str(coordinate).ok().map(|c| {
+                let relays: Vec<String> = tag.as_vec().into_iter().skip(2).take(1).collect();
+                Self::Coordinate(c.relays(relays))
+            }),
+            (Ok(TagKind::Custom(kind)), Some(value)) if kind == "t" => {
+                Some(Self::Hashtag(value.to_string()))
+            }
+            (Ok(TagKind::Custom(kind)), Some(value)) if kind == "word" => {
+                Some(Self::Word(value.to_string()))
+            }
+            _ => None,
+        };
+        item.unwrap_or_else(|| Self::Tag(tag.clone()))
+    }
+
+    /// Get item as tag
+    pub fn tag(&self) -> Tag {
+        match self {
+            Self::PubKey(pk) => Tag::new(TagData::PubKey(*pk)),
+            Self::Event(id) => Tag::new(TagData::EventId(*id)),
+            Self::Coordinate(coordinate) => Tag::new(TagData::Coordinate(coordinate.clone())),
+            Self::Hashtag(hashtag) => Tag::from(vec![String::from("t"), hashtag.clone()]),
+            Self::Word(word) => Tag::from(vec![String::from("word"), word.clone()]),
+            Self::Tag(tag) => tag.clone(),
+        }
+    }
+}
+
+/// NIP-51 list, with public and private (encrypted) items
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/51.md>
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct List {
+    pub kind: ListKind,
+    /// Public items (event tags)
+    pub public: Vec<ListItem>,
+    /// Private items, NIP-04 encrypted in the event content
+    pub private: Vec<ListItem>,
+}
+
+impl List {
+    /// New empty `List`
+    pub fn new(kind: ListKind) -> Self {
+        Self {
+            kind,
+            public: Vec::new(),
+            private: Vec::new(),
+        }
+    }
+
+    /// Add public item
+    pub fn add_public(&mut self, item: ListItem) {
+        if !self.public.contains(&item) {
+            self.public.push(item);
+        }
+    }
+
+    /// Add private item
+    pub fn add_private(&mut self, item: ListItem) {
+        if !self.private.contains(&item) {
+            self.private.push(item);
+        }
+    }
+
+    /// Remove item from both public and private items
+    ///
+    /// Return `true` if the item was in the list.
+    pub fn remove(&mut self, item: &ListItem) -> bool {
+        let len = self.public.len() + self.private.len();
+        self.public.retain(|i| i != item);
+        self.private.retain(|i| i != item);
+        len != self.public.len() + self.private.len()
+    }
+
+    /// Check if the list contains the item (public or private)
+    pub fn contains(&self, item: &ListItem) -> bool {
+        self.public.contains(item) || self.private.contains(item)
+    }
+
+    /// Parse list from event
+    ///
+    /// The private items are decrypted only if `keys` are the ones of the author.
+    pub fn from_event(keys: &Keys, event: &Event) -> Result<Self, Error> {
+        let kind = match event.kind {
+            Kind::Base(KindBase::MuteList) => ListKind::Mute,
+            Kind::Base(KindBase::PinList) => ListKind::Pin,
+            Kind::Base(KindBase::Bookmarks) => ListKind::Bookmarks,
+            Kind::Base(KindBase::PeopleList) => ListKind::People(
+                event
+                    .tags
+                    .iter()
+                    .find(|t| matches!(t.kind(), Ok(TagKind::D)))
+                    .and_then(|t| t.content())
+                    .ok_or(Error::IdentifierNotFound)?
+                    .to_string(),
+            ),
+            _ => return Err(Error::InvalidKind),
+        };
+
+        let mut list = Self::new(kind);
+        list.public = event
+            .tags
+            .iter()
+            .filter(|t| !matches!(t.kind(), Ok(TagKind::D)))
+            .map(ListItem::from_tag)
+            .collect();
+
+        if !event.content.is_empty() && event.pubkey == keys.public_key() {
+            let json = nip04::decrypt(&keys.secret_key()?, &event.pubkey, &event.content)?;
+            let tags: Vec<Tag> = serde_json::from_str(&json)?;
+            list.private = tags.iter().map(ListItem::from_tag).collect();
+        }
+
+        Ok(list)
+    }
+
+    /// Get public tags
+    pub fn tags(&self) -> Vec<Tag> {
+        let mut tags = Vec::new();
+        if let ListKind::People(identifier) = &self.kind {
+            tags.push(Tag::new(TagData::Identifier(identifier.clone())));
+        }
+        tags.extend(self.public.iter().map(|i| i.tag()));
+        tags
+    }
+
+    /// Get event content: private items NIP-04 encrypted to self (empty if none)
+    pub fn encrypted_content(&self, keys: &Keys) -> Result<String, Error> {
+        if self.private.is_empty() {
+            return Ok(String::new());
+        }
+
+        let tags: Vec<Tag> = self.private.iter().map(|i| i.tag()).collect();
+        let json = serde_json::to_string(&tags)?;
+        Ok(nip04::encrypt(
+            &keys.secret_key()?,
+            &keys.public_key(),
+            json,
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::nips::test_util::{build_event, public_key, tags};
+    use crate::{EventBuilder, Result};
+
+    /// List event with the private tags encrypted to the author
+    fn list_event(keys: &Keys, kind: KindBase, public: &str, private: &str) -> Result<Event> {
+        let content = nip04::encrypt(&keys.secret_key()?, &keys.public_key(), private)?;
+        Ok(EventBuilder::new(Kind::Base(kind), &content, &tags(public)).to_event(keys)?)
+    }
+
+    #[test]
+    fn test_list() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        let muted = public_key();
+
+        let mut list = List::new(ListKind::Mute);
+        list.add_public(ListItem::PubKey(muted));
+        list.add_public(ListItem::Hashtag(String::from("bitcoin")));
+        list.add_private(ListItem::Word(String::from("shitcoin")));
+        list.add_private(ListItem::Word(String::from("shitcoin")));
+        assert_eq!(list.private.len(), 1);
+
+        let event = EventBuilder::list(&keys, &list)?.to_event(&keys)?;
+        assert_eq!(event.kind, Kind::Base(KindBase::MuteList));
+        assert!(!event.content.contains("shitcoin"));
+        assert_eq!(List::from_event(&keys, &event)?, list);
+
+        // No private items, no content
+        let mut pins = List::new(ListKind::Pin);
+        pins.add_public(ListItem::Hashtag(String::from("nostr")));
+        assert_eq!(pins.encrypted_content(&keys)?, "");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_private_items() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        let event = list_event(
+            &keys,
+            KindBase::MuteList,
+            r#"[["t", "bitcoin"]]"#,
+            r#"[["word", "shitcoin"]]"#,
+        )?;
+        let private = vec![ListItem::Word(String::from("shitcoin"))];
+        assert_eq!(List::from_event(&keys, &event)?.private, private);
+
+        // Only the author can decrypt them, anyone else gets the public items
+        let other = List::from_event(&Keys::generate_from_os_random(), &event)?;
+        assert_eq!(
+            other.public,
+            vec![ListItem::Hashtag(String::from("bitcoin"))]
+        );
+        assert!(other.private.is_empty());
+
+        // The author without the secret key can't
+        assert!(matches!(
+            List::from_event(&Keys::from_public_key(keys.public_key()), &event),
+            Err(Error::Key(_))
+        ));
+
+        // Not NIP-04 encrypted, or not a list of tags
+        let event = EventBuilder::new(Kind::Base(KindBase::Bookmarks), "not encrypted", &[])
+            .to_event(&keys)?;
+        assert!(matches!(
+            List::from_event(&keys, &event),
+            Err(Error::NIP04(_))
+        ));
+        let event = list_event(&keys, KindBase::Bookmarks, "[]", "not json")?;
+        assert!(matches!(
+            List::from_event(&keys, &event),
+            Err(Error::Json(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_public_and_private() {
+        let word = ListItem::Word(String::from("shitcoin"));
+        let mut list = List::new(ListKind::Mute);
+        list.add_public(word.clone());
+        list.add_private(word.clone());
+        list.add_public(ListItem::Hashtag(String::from("bitcoin")));
+        assert!(list.contains(&word));
+
+        assert!(list.remove(&word));
+        assert!(!list.contains(&word));
+        assert!(!list.remove(&word));
+        assert_eq!(list.public.len(), 1);
+    }
+
+    #[test]
+    fn test_people_list() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+
+        let mut people = List::new(ListKind::People(String::from("friends")));
+        people.add_private(ListItem::PubKey(public_key()));
+        people.add_public(ListItem::Tag(Tag::from(vec![
+            String::from("title"),
+            String::from("Friends"),
+        ])));
+        let event = EventBuilder::list(&keys, &people)?.to_event(&keys)?;
+        assert_eq!(event.identifier(), Some("friends"));
+        // The identifier isn't an item
+        assert_eq!(List::from_event(&keys, &event)?, people);
+
+        let event = build_event(Kind::Base(KindBase::PeopleList), "", "[]");
+        assert!(matches!(
+            List::from_event(&keys, &event),
+            Err(Error::IdentifierNotFound)
+        ));
+
+        let event = build_event(Kind::Base(KindBase::TextNote), "", "[]");
+        assert!(matches!(
+            List::from_event(&keys, &event),
+            Err(Error::InvalidKind)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_spec_example() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        let event = list_event(
+            &keys,
+            KindBase::MuteList,
+            r#"[
+                ["p", "07caba282f76441955b695551c3c5c742e5b9202a3784780f8086fdcdc1da3a9"],
+                ["p", "a55c15f5e41d5aebd236eca5e0142789c5385703f1a7485aa4b38d94fd18dcc4"],
+                ["t", "bitcoin"],
+                ["e", "b3e392b11f5d4f28321cedd09303a748acfd0487aea5a7450b3481c60b6e4f87"]
+            ]"#,
+            r#"[["p", "b3e392b11f5d4f28321cedd09303a748acfd0487aea5a7450b3481c60b6e4f87"], ["word", "shitcoin"]]"#,
+        )?;
+        assert_eq!(
+            List::from_event(&keys, &event)?,
+            List {
+                kind: ListKind::Mute,
+                public: vec![
+                    ListItem::PubKey(XOnlyPublicKey::from_str(
+                        "07caba282f76441955b695551c3c5c742e5b9202a3784780f8086fdcdc1da3a9"
+                    )?),
+                    ListItem::PubKey(XOnlyPublicKey::from_str(
+                        "a55c15f5e41d5aebd236eca5e0142789c5385703f1a7485aa4b38d94fd18dcc4"
+                    )?),
+                    ListItem::Hashtag(String::from("bitcoin")),
+                    ListItem::Event(Sha256Hash::from_str(
+                        "b3e392b11f5d4f28321cedd09303a748acfd0487aea5a7450b3481c60b6e4f87"
+                    )?),
+                ],
+                private: vec![
+                    ListItem::PubKey(XOnlyPublicKey::from_str(
+                        "b3e392b11f5d4f28321cedd09303a748acfd0487aea5a7450b3481c60b6e4f87"
+                    )?),
+                    ListItem::Word(String::from("shitcoin")),
+                ],
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_items() -> Result<()> {
+        // Only the first relay hint of a coordinate is kept
+        let coordinate = format!("30023:{}:lorem-ipsum", public_key());
+        let tag: Tag = serde_json::from_str(&format!(
+            r#"["a", "{}", "wss://relay.damus.io", "wss://nos.lol"]"#,
+            coordinate
+        ))?;
+        assert_eq!(
+            ListItem::from_tag(&tag),
+            ListItem::Coordinate(
+                Coordinate::from_str(&coordinate)?.relays(vec!["wss://relay.damus.io"])
+            )
+        );
+
+        // Unparsable items are kept as raw tags
+        let tags = tags(
+            r#"[["p", "not a public key"], ["e"], ["a", "30023:not a public key:ipsum"], ["t"], ["emoji", "soapbox"]]"#,
+        );
+        for tag in tags.into_iter() {
+            assert_eq!(ListItem::from_tag(&tag), ListItem::Tag(tag.clone()));
+            assert_eq!(ListItem::from_tag(&tag).tag(), tag);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,311 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::fmt;
+use std::str::FromStr;
+
+use bitcoin::secp256k1::XOnlyPublicKey;
+
+use crate::event::TagKind;
+use crate::{Event, Kind, KindBase, Sha256Hash, Tag};
+
+#[derive(Debug, Eq, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// Invalid event kind
+    #[error("invalid event kind")]
+    InvalidKind,
+    /// Unknown report type
+    #[error("unknown report type")]
+    UnknownReportType,
+    /// Reported public key or event not found
+    #[error("target not found")]
+    TargetNotFound,
+}
+
+/// Report type
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum ReportType {
+    /// Depictions of nudity, porn, etc
+    Nudity,
+    /// Virus, trojan horse, worm, malware, etc
+    Malware,
+    /// Profanity, hateful speech, etc
+    Profanity,
+    /// Something which may be illegal in some jurisdiction
+    Illegal,
+    /// Spam
+    Spam,
+    /// Someone pretending to be someone else
+    Impersonation,
+    /// Reports that don't fit in the above categories
+    Other,
+}
+
+impl fmt::Display for ReportType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Nudity => write!(f, "nudity"),
+            Self::Malware => write!(f, "malware"),
+            Self::Profanity => write!(f, "profanity"),
+            Self::Illegal => write!(f, "illegal"),
+            Self::Spam => write!(f, "spam"),
+            Self::Impersonation => write!(f, "impersonation"),
+            Self::Other => write!(f, "other"),
+        }
+    }
+}
+
+impl FromStr for ReportType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nudity" => Ok(Self::Nudity),
+            "malware" => Ok(Self::Malware),
+            "profanity" => Ok(Self::Profanity),
+            "illegal" => Ok(Self::Illegal),
+            "spam" => Ok(Self::Spam),
+            "impersonation" => Ok(Self::Impersonation),
+            "other" => Ok(Self::Other),
+            _ => Err(Error::UnknownReportType),
+        }
+    }
+}
+
+/// Reported public key or event
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ReportTarget {
+    PubKey(XOnlyPublicKey),
+    Event {
+        id: Sha256Hash,
+        /// Author of the event
+        author: XOnlyPublicKey,
+    },
+}
+
+/// Report (kind 1984)
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/56.md>
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Report {
+    pub target: ReportTarget,
+    pub report_type: ReportType,
+    /// Additional information (event content)
+    pub reason: String,
+}
+
+impl Report {
+    /// Get report tags
+    pub fn tags(target: &ReportTarget, report_type: ReportType) -> Vec<Tag> {
+        match target {
+            ReportTarget::PubKey(pk) => vec![Tag::from(vec![
+                TagKind::P.to_string(),
+                pk.to_string(),
+                report_type.to_string(),
+            ])],
+            ReportTarget::Event { id, author } => vec![
+                Tag::from(vec![
+                    TagKind::E.to_string(),
+                    id.to_string(),
+                    report_type.to_string(),
+                ]),
+                Tag::from(vec![TagKind::P.to_string(), author.to_string()]),
+            ],
+        }
+    }
+
+    /// Parse report from a kind 1984 event
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        if event.kind != Kind::Base(KindBase::Reporting) {
+            return Err(Error::InvalidKind);
+        }
+
+        let tag_of = |kind: TagKind| -> Option<Vec<String>> {
+            event
+                .tags
+                .iter()
+                .find(|t| t.kind().ok().as_ref() == Some(&kind))
+                .map(|t| t.as_vec())
+        };
+
+        let pubkey_tag = tag_of(TagKind::P).ok_or(Error::TargetNotFound)?;
+        let author = pubkey_tag
+            .get(1)
+            .and_then(|pk| XOnlyPublicKey::from_str(pk).ok())
+            .ok_or(Error::TargetNotFound)?;
+
+        let (target, report_type) = match tag_of(TagKind::E) {
+            Some(event_tag) => (
+                ReportTarget::Event {
+                    id: event_tag
+                        .get(1)
+                        .and_then(|id| Sha256Hash::from_str(id).ok())
+                        .ok_or(Error::TargetNotFound)?,
+                    author,
+                },
+                event_tag.get(2).cloned(),
+            ),
+            None => (ReportTarget::PubKey(author), pubkey_tag.get(2).cloned()),
+        };
+
+        Ok(Self {
+            target,
+            report_type: report_type.ok_or(Error::UnknownReportType)?.parse()?,
+            reason: event.content.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::nips::test_util::{build_event, public_key};
+    use crate::{EventBuilder, Keys, Result};
+
+    const EVENT_ID: &str = "b3e392b11f5d4f28321cedd09303a748acfd0487aea5a7450b3481c60b6e4f87";
+
+    fn report(content: &str, tags_json: &str) -> Result<Report, Error> {
+        Report::from_event(&build_event(
+            Kind::Base(KindBase::Reporting),
+            content,
+            tags_json,
+        ))
+    }
+
+    #[test]
+    fn test_report() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        let spammer = Keys::generate_from_os_random();
+
+        let note = EventBuilder::new_text_note("Illegal stuff", &[]).to_event(&spammer)?;
+        let target = ReportTarget::Event {
+            id: note.id,
+            author: note.pubkey,
+        };
+        let event =
+            EventBuilder::report(target.clone(), ReportType::Illegal, "Scam").to_event(&keys)?;
+        assert_eq!(event.kind, Kind::Base(KindBase::Reporting));
+        assert_eq!(
+            Report::from_event(&event)?,
+            Report {
+                target,
+                report_type: ReportType::Illegal,
+                reason: String::from("Scam"),
+            }
+        );
+
+        assert_eq!(Report::from_event(&note), Err(Error::InvalidKind));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_report_types() {
+        for report_type in [
+            ReportType::Nudity,
+            ReportType::Malware,
+            ReportType::Profanity,
+            ReportType::Illegal,
+            ReportType::Spam,
+            ReportType::Impersonation,
+            ReportType::Other,
+        ] {
+            assert_eq!(report_type.to_string().parse(), Ok(report_type));
+        }
+        assert_eq!("Spam".parse::<ReportType>(), Err(Error::UnknownReportType));
+    }
+
+    #[test]
+    fn test_public_key_report() -> Result<()> {
+        let reported = public_key();
+
+        // Labels (NIP-32) are ignored
+        assert_eq!(
+            report(
+                "",
+                &format!(
+                    r#"[["p","{}","nudity"],["L","social.nos.ontology"],["l","NS-nud","social.nos.ontology"]]"#,
+                    reported
+                )
+            )?,
+            Report {
+                target: ReportTarget::PubKey(reported),
+                report_type: ReportType::Nudity,
+                reason: String::new(),
+            }
+        );
+
+        // The type is on the `p` tag
+        assert_eq!(
+            report("", &format!(r#"[["p","{}"]]"#, reported)),
+            Err(Error::UnknownReportType)
+        );
+        assert_eq!(
+            report("", r#"[["p","not a public key","spam"]]"#),
+            Err(Error::TargetNotFound)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_event_report() -> Result<()> {
+        let author = public_key();
+        let id = Sha256Hash::from_str(EVENT_ID)?;
+
+        assert_eq!(
+            report(
+                "He's insulting the king!",
+                &format!(r#"[["e","{}","illegal"],["p","{}"]]"#, EVENT_ID, author)
+            )?,
+            Report {
+                target: ReportTarget::Event { id, author },
+                report_type: ReportType::Illegal,
+                reason: String::from("He's insulting the king!"),
+            }
+        );
+
+        // The type of an event report is on the `e` tag, not on the `p` tag
+        assert_eq!(
+            report(
+                "",
+                &format!(r#"[["e","{}"],["p","{}","spam"]]"#, EVENT_ID, author)
+            ),
+            Err(Error::UnknownReportType)
+        );
+
+        // The author is required
+        assert_eq!(
+            report("", &format!(r#"[["e","{}","spam"]]"#, EVENT_ID)),
+            Err(Error::TargetNotFound)
+        );
+        assert_eq!(
+            report(
+                "",
+                &format!(r#"[["e","not an event id","spam"],["p","{}"]]"#, author)
+            ),
+            Err(Error::TargetNotFound)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tags() -> Result<()> {
+        let author = public_key();
+        let id = Sha256Hash::from_str(EVENT_ID)?;
+        let tags: Vec<Vec<String>> =
+            Report::tags(&ReportTarget::Event { id, author }, ReportType::Malware)
+                .iter()
+                .map(|t| t.as_vec())
+                .collect();
+        assert_eq!(
+            tags,
+            vec![
+                vec![String::from("e"), id.to_string(), String::from("malware")],
+                vec![String::from("p"), author.to_string()],
+            ]
+        );
+        Ok(())
+    }
+}
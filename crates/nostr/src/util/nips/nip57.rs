@@ -0,0 +1,465 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::str::FromStr;
+
+use bitcoin::secp256k1::XOnlyPublicKey;
+
+use crate::event::{TagData, TagKind};
+use crate::{Event, Kind, KindBase, Sha256Hash, Tag};
+
+#[derive(Debug, Eq, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// Invalid event kind
+    #[error("invalid event kind")]
+    InvalidKind,
+    /// Tag missing
+    #[error("tag missing: {0}")]
+    TagMissing(String),
+    /// Invalid tag value
+    #[error("invalid tag: {0}")]
+    InvalidTag(String),
+    /// Invalid zap request in `description` tag
+    #[error("invalid zap request")]
+    InvalidZapRequest,
+}
+
+/// Zap request data (kind 9734)
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ZapRequestData {
+    /// Recipient public key
+    pub public_key: XOnlyPublicKey,
+    /// Relays where the zap receipt should be published
+    pub relays: Vec<String>,
+    /// Amount in millisats
+    pub amount: Option<u64>,
+    /// Bech32 encoded lnurl pay url of the recipient
+    pub lnurl: Option<String>,
+    /// Zapped event
+    pub event_id: Option<Sha256Hash>,
+}
+
+impl ZapRequestData {
+    /// New `ZapRequestData`
+    pub fn new<S>(public_key: XOnlyPublicKey, relays: Vec<S>) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            public_key,
+            relays: relays.into_iter().map(|u| u.into()).collect(),
+            amount: None,
+            lnurl: None,
+            event_id: None,
+        }
+    }
+
+    /// Set amount (millisats)
+    pub fn amount(self, amount: u64) -> Self {
+        Self {
+            amount: Some(amount),
+            ..self
+        }
+    }
+
+    /// Set lnurl
+    pub fn lnurl<S>(self, lnurl: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            lnurl: Some(lnurl.into()),
+            ..self
+        }
+    }
+
+    /// Set zapped event
+    pub fn event_id(self, event_id: Sha256Hash) -> Self {
+        Self {
+            event_id: Some(event_id),
+            ..self
+        }
+    }
+
+    /// Parse zap request data from a kind 9734 event
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        if event.kind != Kind::Base(KindBase::ZapRequest) {
+            return Err(Error::InvalidKind);
+        }
+
+        let public_key = find_tag(event, "p").ok_or(Error::TagMissing(String::from("p")))?;
+        let mut data = Self::new(
+            XOnlyPublicKey::from_str(public_key)
+                .map_err(|_| Error::InvalidTag(String::from("p")))?,
+            Vec::<String>::new(),
+        );
+
+        for tag in event.tags.iter() {
+            let tag = tag.as_vec();
+            match (tag.first().map(|t| t.as_str()), tag.get(1)) {
+                (Some("relays"), _) => data.relays = tag[1..].to_vec(),
+                (Some("amount"), Some(amount)) => {
+                    data.amount = Some(
+                        amount
+                            .parse()
+                            .map_err(|_| Error::InvalidTag(String::from("amount")))?,
+                    )
+                }
+                (Some("lnurl"), Some(lnurl)) => data.lnurl = Some(lnurl.clone()),
+                (Some("e"), Some(event_id)) => {
+                    data.event_id = Some(
+                        Sha256Hash::from_str(event_id)
+                            .map_err(|_| Error::InvalidTag(String::from("e")))?,
+                    )
+                }
+                _ => (),
+            }
+        }
+
+        Ok(data)
+    }
+
+    /// Get zap request tags
+    pub fn tags(&self) -> Vec<Tag> {
+        let mut relays = vec![String::from("relays")];
+        relays.extend(self.relays.iter().cloned());
+
+        let mut tags = vec![Tag::from(relays)];
+
+        if let Some(amount) = self.amount {
+            tags.push(Tag::from(vec![String::from("amount"), amount.to_string()]));
+        }
+        if let Some(lnurl) = &self.lnurl {
+            tags.push(Tag::from(vec![String::from("lnurl"), lnurl.clone()]));
+        }
+
+        tags.push(Tag::new(TagData::PubKey(self.public_key)));
+
+        if let Some(event_id) = self.event_id {
+            tags.push(Tag::new(TagData::EventId(event_id)));
+        }
+
+        tags
+    }
+}
+
+/// Zap receipt (kind 9735)
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ZapReceipt {
+    /// Recipient public key
+    pub recipient: XOnlyPublicKey,
+    /// Zapped event
+    pub event_id: Option<Sha256Hash>,
+    /// Paid invoice
+    pub bolt11: String,
+    /// Payment preimage
+    pub preimage: Option<String>,
+    /// Zap request (kind 9734) embedded in the `description` tag
+    pub zap_request: Event,
+}
+
+impl ZapReceipt {
+    /// Parse zap receipt from a kind 9735 event
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        if event.kind != Kind::Base(KindBase::ZapReceipt) {
+            return Err(Error::InvalidKind);
+        }
+
+        let recipient = find_tag(event, "p").ok_or(Error::TagMissing(String::from("p")))?;
+        let bolt11 = find_tag(event, "bolt11").ok_or(Error::TagMissing(String::from("bolt11")))?;
+        let description =
+            find_tag(event, "description").ok_or(Error::TagMissing(String::from("description")))?;
+
+        let zap_request = Event::from_json(description).map_err(|_| Error::InvalidZapRequest)?;
+        if zap_request.kind != Kind::Base(KindBase::ZapRequest) {
+            return Err(Error::InvalidZapRequest);
+        }
+
+        Ok(Self {
+            recipient: XOnlyPublicKey::from_str(recipient)
+                .map_err(|_| Error::InvalidTag(String::from("p")))?,
+            event_id: find_tag(event, "e")
+                .map(Sha256Hash::from_str)
+                .transpose()
+                .map_err(|_| Error::InvalidTag(String::from("e")))?,
+            bolt11: bolt11.to_string(),
+            preimage: find_tag(event, "preimage").map(|p| p.to_string()),
+            zap_request,
+        })
+    }
+
+    /// Get the public key of the zap sender
+    pub fn sender(&self) -> XOnlyPublicKey {
+        self.zap_request.pubkey
+    }
+
+    /// Get the requested amount (millisats)
+    pub fn amount(&self) -> Option<u64> {
+        find_tag(&self.zap_request, "amount").and_then(|a| a.parse().ok())
+    }
+}
+
+/// Get zap receipt tags for a zap request
+pub(crate) fn zap_receipt_tags(
+    bolt11: String,
+    preimage: Option<String>,
+    zap_request: &Event,
+) -> Vec<Tag> {
+    let mut tags: Vec<Tag> = zap_request
+        .tags
+        .iter()
+        .filter(|t| matches!(t.kind(), Ok(TagKind::P) | Ok(TagKind::E)))
+        .cloned()
+        .collect();
+
+    tags.push(Tag::from(vec![String::from("bolt11"), bolt11]));
+    tags.push(Tag::from(vec![
+        String::from("description"),
+        serde_json::json!(zap_request).to_string(),
+    ]));
+
+    if let Some(preimage) = preimage {
+        tags.push(Tag::from(vec![String::from("preimage"), preimage]));
+    }
+
+    tags
+}
+
+fn find_tag<'a>(event: &'a Event, kind: &str) -> Option<&'a str> {
+    event
+        .tags
+        .iter()
+        .find(|t| t.as_vec().first().map(|k| k.as_str()) == Some(kind))
+        .and_then(|t| t.content())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EventBuilder, Keys, Result};
+
+    fn tag(tag: &[&str]) -> Tag {
+        Tag::from(tag.iter().map(|t| t.to_string()).collect::<Vec<String>>())
+    }
+
+    #[test]
+    fn test_zap_request() -> Result<()> {
+        let sender = Keys::generate_from_os_random();
+        let recipient = Keys::generate_from_os_random();
+
+        let data = ZapRequestData::new(recipient.public_key(), vec!["wss://relay.damus.io"])
+            .amount(21000)
+            .lnurl("lnurl1dp68gurn8ghj7um9wfmxjcm99e3k7mf0v9cxj0m385ekvcenxc6r2c35xvukxefcv5mkvv34x5ekzd3ev56nyd3hxqurzepexejxxepnxscrvwfnv9nxzcn9xq6xyefhvgcxxcmyxymnserxfq5fns");
+        let zap_request =
+            EventBuilder::new_zap_request(data.clone(), "Great post!").to_event(&sender)?;
+
+        assert_eq!(zap_request.kind, Kind::Base(KindBase::ZapRequest));
+        assert_eq!(ZapRequestData::from_event(&zap_request)?, data);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_zap_receipt() -> Result<()> {
+        let sender = Keys::generate_from_os_random();
+        let recipient = Keys::generate_from_os_random();
+        let lnurl_server = Keys::generate_from_os_random();
+        let event_id = EventBuilder::new_text_note("Zap me", &[])
+            .to_event(&recipient)?
+            .id;
+
+        let data = ZapRequestData::new(recipient.public_key(), vec!["wss://relay.damus.io"])
+            .amount(21000)
+            .event_id(event_id);
+        let zap_request = EventBuilder::new_zap_request(data, "").to_event(&sender)?;
+
+        let receipt = EventBuilder::new_zap_receipt("lnbc210n1...", None, &zap_request)
+            .to_event(&lnurl_server)?;
+        let receipt = ZapReceipt::from_event(&receipt)?;
+
+        assert_eq!(receipt.recipient, recipient.public_key());
+        assert_eq!(receipt.sender(), sender.public_key());
+        assert_eq!(receipt.event_id, Some(event_id));
+        assert_eq!(receipt.amount(), Some(21000));
+        assert_eq!(receipt.bolt11, "lnbc210n1...");
+        assert_eq!(receipt.zap_request, zap_request);
+
+        assert_eq!(
+            ZapReceipt::from_event(&zap_request).unwrap_err(),
+            Error::InvalidKind
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_zap_spec_examples() -> Result<()> {
+        let sender = Keys::generate_from_os_random();
+        let lnurl_server = Keys::generate_from_os_random();
+
+        let tags: Vec<Tag> = serde_json::from_str(
+            r#"[
+                ["relays", "wss://nostr-pub.wellorder.com", "wss://anotherrelay.example.com"],
+                ["amount", "21000"],
+                ["lnurl", "lnurl1dp68gurn8ghj7um5v93kketj9ehx2amn9uh8wetvdskkkmn0wahz7mrww4excup0dajx2mrv92x9xp"],
+                ["p", "04c915daefee38317fa734444acee390a8269fe5810b2241e5e6dd343dfbecc9"],
+                ["e", "9ae37aa68f48645127299e9453eb5d908a0cbb6058ff340d528ed4d37c8994fb"]
+            ]"#,
+        )?;
+        let zap_request =
+            EventBuilder::new(Kind::Base(KindBase::ZapRequest), "Zap!", &tags).to_event(&sender)?;
+        assert_eq!(
+            ZapRequestData::from_event(&zap_request)?,
+            ZapRequestData::new(
+                XOnlyPublicKey::from_str(
+                    "04c915daefee38317fa734444acee390a8269fe5810b2241e5e6dd343dfbecc9"
+                )?,
+                vec![
+                    "wss://nostr-pub.wellorder.com",
+                    "wss://anotherrelay.example.com"
+                ],
+            )
+            .amount(21000)
+            .lnurl("lnurl1dp68gurn8ghj7um5v93kketj9ehx2amn9uh8wetvdskkkmn0wahz7mrww4excup0dajx2mrv92x9xp")
+            .event_id(Sha256Hash::from_str(
+                "9ae37aa68f48645127299e9453eb5d908a0cbb6058ff340d528ed4d37c8994fb"
+            )?)
+        );
+
+        let tags = vec![
+            tag(&[
+                "p",
+                "04c915daefee38317fa734444acee390a8269fe5810b2241e5e6dd343dfbecc9",
+            ]),
+            tag(&["P", &sender.public_key().to_string()]),
+            tag(&[
+                "e",
+                "9ae37aa68f48645127299e9453eb5d908a0cbb6058ff340d528ed4d37c8994fb",
+            ]),
+            tag(&["bolt11", "lnbc10u1p3unwfusp5t9r3yymhpfqculx78u027lxspgxcr2n2987mx2j55nnfs95nxnzqpp5jmrh92pfld78spqs78v9euf2385t83uvpwk9ldrlvf6ch7tpascqhp5zvkrmemgth3tufcvflmzjzfvjt023nazlhljz2n9hattj4f8jq8qxqyjw5qcqpjrzjqtc4fc44feggv7065fqe5m4ytjarg3repr5j9el35xhmtfexc42yczarjuqqfzqqqqqqqqlgqqqqqqgq9q9qxpqysgq079nkq507a5tw7xgttmj4u990j7wfggtrasah5gd4ywfr2pjcn29383tphp4t48gquelz9z78p4cq7ml3nrrphw5w6eckhjwmhezhnqpy6gyf0"]),
+            tag(&["description", &zap_request.as_json()?]),
+            tag(&[
+                "preimage",
+                "5d006d2cf1e73c7148e7519a4c68adc81642ce0e25a432b2434c99f97344c15f",
+            ]),
+        ];
+        let receipt = EventBuilder::new(Kind::Base(KindBase::ZapReceipt), "", &tags)
+            .to_event(&lnurl_server)?;
+        let receipt = ZapReceipt::from_event(&receipt)?;
+        assert_eq!(
+            receipt.recipient.to_string(),
+            "04c915daefee38317fa734444acee390a8269fe5810b2241e5e6dd343dfbecc9"
+        );
+        assert_eq!(receipt.sender(), sender.public_key());
+        assert_eq!(
+            receipt.event_id,
+            zap_request.tags[4]
+                .content()
+                .map(Sha256Hash::from_str)
+                .transpose()?
+        );
+        assert_eq!(receipt.amount(), Some(21000));
+        assert!(receipt.bolt11.starts_with("lnbc10u1p3unwfusp5"));
+        assert_eq!(
+            receipt.preimage.as_deref(),
+            Some("5d006d2cf1e73c7148e7519a4c68adc81642ce0e25a432b2434c99f97344c15f")
+        );
+        assert_eq!(receipt.zap_request, zap_request);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_zap_invalid_tags() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        let public_key = keys.public_key().to_string();
+
+        for (tags, error) in [
+            (
+                vec![vec!["amount", "21000"]],
+                Error::TagMissing(String::from("p")),
+            ),
+            (
+                vec![vec!["p", "not a public key"]],
+                Error::InvalidTag(String::from("p")),
+            ),
+            (
+                vec![vec!["p", &public_key], vec!["amount", "21 sats"]],
+                Error::InvalidTag(String::from("amount")),
+            ),
+            (
+                vec![vec!["p", &public_key], vec!["e", "not an event id"]],
+                Error::InvalidTag(String::from("e")),
+            ),
+        ] {
+            let tags: Vec<Tag> = tags.into_iter().map(|t| tag(&t)).collect();
+            let event =
+                EventBuilder::new(Kind::Base(KindBase::ZapRequest), "", &tags).to_event(&keys)?;
+            assert_eq!(ZapRequestData::from_event(&event), Err(error));
+        }
+
+        let zap_request = EventBuilder::new_zap_request(
+            ZapRequestData::new(keys.public_key(), vec!["wss://relay.damus.io"]),
+            "",
+        )
+        .to_event(&keys)?
+        .as_json()?;
+        let text_note = EventBuilder::new_text_note("", &[])
+            .to_event(&keys)?
+            .as_json()?;
+        for (tags, error) in [
+            (
+                vec![vec!["bolt11", "lnbc"], vec!["description", &zap_request]],
+                Error::TagMissing(String::from("p")),
+            ),
+            (
+                vec![vec!["p", &public_key], vec!["description", &zap_request]],
+                Error::TagMissing(String::from("bolt11")),
+            ),
+            (
+                vec![vec!["p", &public_key], vec!["bolt11", "lnbc"]],
+                Error::TagMissing(String::from("description")),
+            ),
+            (
+                vec![
+                    vec!["p", &public_key],
+                    vec!["bolt11", "lnbc"],
+                    vec!["description", "not an event"],
+                ],
+                Error::InvalidZapRequest,
+            ),
+            (
+                vec![
+                    vec!["p", &public_key],
+                    vec!["bolt11", "lnbc"],
+                    vec!["description", &text_note],
+                ],
+                Error::InvalidZapRequest,
+            ),
+            (
+                vec![
+                    vec!["p", "not a public key"],
+                    vec!["bolt11", "lnbc"],
+                    vec!["description", &zap_request],
+                ],
+                Error::InvalidTag(String::from("p")),
+            ),
+            (
+                vec![
+                    vec!["p", &public_key],
+                    vec!["e", "not an event id"],
+                    vec!["bolt11", "lnbc"],
+                    vec!["description", &zap_request],
+                ],
+                Error::InvalidTag(String::from("e")),
+            ),
+        ] {
+            let tags: Vec<Tag> = tags.into_iter().map(|t| tag(&t)).collect();
+            let event =
+                EventBuilder::new(Kind::Base(KindBase::ZapReceipt), "", &tags).to_event(&keys)?;
+            assert_eq!(ZapReceipt::from_event(&event), Err(error));
+        }
+
+        Ok(())
+    }
+}
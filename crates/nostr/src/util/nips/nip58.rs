@@ -0,0 +1,577 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::fmt;
+use std::str::FromStr;
+
+use bitcoin::secp256k1::XOnlyPublicKey;
+
+use super::nip19::Coordinate;
+use crate::event::{TagData, TagKind};
+use crate::{Event, Kind, KindBase, Sha256Hash, Tag};
+
+/// Identifier of the profile badges event
+pub const PROFILE_BADGES_IDENTIFIER: &str = "profile_badges";
+
+#[derive(Debug, Eq, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// Invalid event kind
+    #[error("invalid event kind")]
+    InvalidKind,
+    /// Identifier (`d` tag) not found
+    #[error("identifier not found")]
+    IdentifierNotFound,
+    /// Invalid image dimensions
+    #[error("invalid dimensions")]
+    InvalidDimensions,
+    /// Badge coordinate (`a` tag) not found or invalid
+    #[error("badge not found")]
+    BadgeNotFound,
+    /// The badge award is for another badge
+    #[error("badge award mismatch")]
+    BadgeAwardMismatch,
+    /// The badge has not been awarded to the public key
+    #[error("badge not awarded")]
+    BadgeNotAwarded,
+}
+
+/// Image dimensions (`<width>x<height>`)
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct ImageDimensions {
+    pub width: u64,
+    pub height: u64,
+}
+
+impl fmt::Display for ImageDimensions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+impl FromStr for ImageDimensions {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (width, height) = s.split_once('x').ok_or(Error::InvalidDimensions)?;
+        Ok(Self {
+            width: width.parse().map_err(|_| Error::InvalidDimensions)?,
+            height: height.parse().map_err(|_| Error::InvalidDimensions)?,
+        })
+    }
+}
+
+/// Badge image url, with optional dimensions
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct BadgeImage {
+    pub url: String,
+    pub dimensions: Option<ImageDimensions>,
+}
+
+impl BadgeImage {
+    /// New `BadgeImage`
+    pub fn new<S>(url: S, dimensions: Option<ImageDimensions>) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            url: url.into(),
+            dimensions,
+        }
+    }
+
+    fn tag(&self, kind: &str) -> Tag {
+        let mut tag = vec![kind.to_string(), self.url.clone()];
+        if let Some(dimensions) = self.dimensions {
+            tag.push(dimensions.to_string());
+        }
+        Tag::from(tag)
+    }
+
+    fn from_tag(tag: &[String]) -> Option<Self> {
+        Some(Self {
+            url: tag.get(1)?.clone(),
+            dimensions: tag.get(2).and_then(|d| d.parse().ok()),
+        })
+    }
+}
+
+/// Badge definition (kind 30009)
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/58.md>
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct BadgeDefinition {
+    /// Badge identifier (`d` tag)
+    pub identifier: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub image: Option<BadgeImage>,
+    pub thumbnails: Vec<BadgeImage>,
+}
+
+impl BadgeDefinition {
+    /// New `BadgeDefinition`
+    pub fn new<S>(identifier: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            identifier: identifier.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Set name
+    pub fn name<S>(self, name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            name: Some(name.into()),
+            ..self
+        }
+    }
+
+    /// Set description
+    pub fn description<S>(self, description: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            description: Some(description.into()),
+            ..self
+        }
+    }
+
+    /// Set image
+    pub fn image(self, image: BadgeImage) -> Self {
+        Self {
+            image: Some(image),
+            ..self
+        }
+    }
+
+    /// Add thumbnail
+    pub fn thumbnail(mut self, thumbnail: BadgeImage) -> Self {
+        self.thumbnails.push(thumbnail);
+        self
+    }
+
+    /// Parse badge definition from a kind 30009 event
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        if event.kind != Kind::Base(KindBase::BadgeDefinition) {
+            return Err(Error::InvalidKind);
+        }
+
+        let mut badge = Self::new(event.identifier().ok_or(Error::IdentifierNotFound)?);
+
+        for tag in event.tags.iter() {
+            let tag = tag.as_vec();
+            match (tag.first().map(|t| t.as_str()), tag.get(1)) {
+                (Some("name"), Some(name)) => badge.name = Some(name.clone()),
+                (Some("description"), Some(description)) => {
+                    badge.description = Some(description.clone())
+                }
+                (Some("image"), Some(_)) => badge.image = BadgeImage::from_tag(&tag),
+                (Some("thumb"), Some(_)) => badge.thumbnails.extend(BadgeImage::from_tag(&tag)),
+                _ => (),
+            }
+        }
+
+        Ok(badge)
+    }
+
+    /// Get badge definition tags
+    pub fn tags(&self) -> Vec<Tag> {
+        let mut tags = vec![Tag::new(TagData::Identifier(self.identifier.clone()))];
+
+        if let Some(name) = &self.name {
+            tags.push(Tag::from(vec![String::from("name"), name.clone()]));
+        }
+        if let Some(description) = &self.description {
+            tags.push(Tag::from(vec![
+                String::from("description"),
+                description.clone(),
+            ]));
+        }
+        if let Some(image) = &self.image {
+            tags.push(image.tag("image"));
+        }
+        for thumbnail in self.thumbnails.iter() {
+            tags.push(thumbnail.tag("thumb"));
+        }
+
+        tags
+    }
+}
+
+/// Badge award (kind 8)
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/58.md>
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct BadgeAward {
+    /// Coordinate of the badge definition
+    pub badge: Coordinate,
+    pub awarded: Vec<XOnlyPublicKey>,
+}
+
+impl BadgeAward {
+    /// Get badge award tags for a kind 30009 badge definition event
+    pub fn tags(badge_definition: &Event, awarded: &[XOnlyPublicKey]) -> Result<Vec<Tag>, Error> {
+        if badge_definition.kind != Kind::Base(KindBase::BadgeDefinition) {
+            return Err(Error::InvalidKind);
+        }
+        if badge_definition.identifier().is_none() {
+            return Err(Error::IdentifierNotFound);
+        }
+        let coordinate = badge_definition.coordinate().ok_or(Error::BadgeNotFound)?;
+
+        let mut tags = vec![Tag::new(TagData::Coordinate(coordinate))];
+        tags.extend(awarded.iter().map(|pk| Tag::new(TagData::PubKey(*pk))));
+        Ok(tags)
+    }
+
+    /// Parse badge award from a kind 8 event
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        if event.kind != Kind::Base(KindBase::BadgeAward) {
+            return Err(Error::InvalidKind);
+        }
+
+        let badge = event
+            .tags
+            .iter()
+            .find(|t| matches!(t.kind(), Ok(TagKind::A)))
+            .and_then(|t| t.content())
+            .and_then(|c| Coordinate::from_str(c).ok())
+            .filter(|c| c.kind == Kind::Base(KindBase::BadgeDefinition))
+            .ok_or(Error::BadgeNotFound)?;
+
+        let awarded = event
+            .tags
+            .iter()
+            .filter(|t| matches!(t.kind(), Ok(TagKind::P)))
+            .filter_map(|t| t.content())
+            .filter_map(|pk| XOnlyPublicKey::from_str(pk).ok())
+            .collect();
+
+        Ok(Self { badge, awarded })
+    }
+}
+
+/// Profile badges (kind 30008): ordered pairs of badge definition and badge award
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/58.md>
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ProfileBadges {
+    pub badges: Vec<(Coordinate, Sha256Hash)>,
+}
+
+impl ProfileBadges {
+    /// Get profile badges tags
+    ///
+    /// Every pair is made of a kind 30009 badge definition and of a kind 8 award
+    /// of that badge to `public_key`.
+    pub fn tags(badges: &[(Event, Event)], public_key: &XOnlyPublicKey) -> Result<Vec<Tag>, Error> {
+        let mut tags = vec![Tag::new(TagData::Identifier(String::from(
+            PROFILE_BADGES_IDENTIFIER,
+        )))];
+
+        for (definition, award) in badges.iter() {
+            if definition.kind != Kind::Base(KindBase::BadgeDefinition) {
+                return Err(Error::InvalidKind);
+            }
+            let coordinate = definition.coordinate().ok_or(Error::BadgeNotFound)?;
+
+            let badge_award = BadgeAward::from_event(award)?;
+            if badge_award.badge != coordinate || award.pubkey != definition.pubkey {
+                return Err(Error::BadgeAwardMismatch);
+            }
+            if !badge_award.awarded.contains(public_key) {
+                return Err(Error::BadgeNotAwarded);
+            }
+
+            tags.push(Tag::new(TagData::Coordinate(coordinate)));
+            tags.push(Tag::new(TagData::EventId(award.id)));
+        }
+
+        Ok(tags)
+    }
+
+    /// Parse profile badges from a kind 30008 event
+    ///
+    /// Unpaired `a` or `e` tags are ignored.
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        if event.kind != Kind::Base(KindBase::ProfileBadges) {
+            return Err(Error::InvalidKind);
+        }
+        if event.identifier() != Some(PROFILE_BADGES_IDENTIFIER) {
+            return Err(Error::IdentifierNotFound);
+        }
+
+        let mut badges = Vec::new();
+        let mut coordinate: Option<Coordinate> = None;
+        for tag in event.tags.iter() {
+            match (tag.kind(), tag.content()) {
+                (Ok(TagKind::A), Some(c)) => coordinate = Coordinate::from_str(c).ok(),
+                (Ok(TagKind::E), Some(id)) => {
+                    if let (Some(c), Ok(id)) = (coordinate.take(), Sha256Hash::from_str(id)) {
+                        badges.push((c, id));
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        Ok(Self { badges })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::nips::test_util::{build_event, public_key};
+    use crate::{EventBuilder, Keys, Result};
+
+    fn definition(identifier: &str, issuer: &Keys) -> Result<Event> {
+        Ok(EventBuilder::define_badge(&BadgeDefinition::new(identifier)).to_event(issuer)?)
+    }
+
+    fn award(definition: &Event, awarded: &[XOnlyPublicKey], issuer: &Keys) -> Result<Event> {
+        Ok(EventBuilder::award_badge(definition, awarded)?.to_event(issuer)?)
+    }
+
+    #[test]
+    fn test_badge_definition() -> Result<()> {
+        let definition = BadgeDefinition::new("bravery")
+            .name("Medal of Bravery")
+            .description("Awarded to users demonstrating bravery")
+            .image(BadgeImage::new(
+                "https://nostr.academy/awards/bravery.png",
+                Some(ImageDimensions {
+                    width: 1024,
+                    height: 1024,
+                }),
+            ))
+            .thumbnail(BadgeImage::new(
+                "https://nostr.academy/awards/bravery_256x256.png",
+                Some(ImageDimensions::from_str("256x256")?),
+            ))
+            .thumbnail(BadgeImage::new(
+                "https://nostr.academy/awards/bravery_64x64.png",
+                None,
+            ));
+        let event =
+            EventBuilder::define_badge(&definition).to_event(&Keys::generate_from_os_random())?;
+        assert_eq!(BadgeDefinition::from_event(&event)?, definition);
+
+        // Invalid dimensions are dropped, images without url are skipped
+        let event = build_event(
+            Kind::Base(KindBase::BadgeDefinition),
+            "",
+            r#"[["d", "bravery"], ["image", "https://nostr.academy/awards/bravery.png", "large"], ["thumb"]]"#,
+        );
+        assert_eq!(
+            BadgeDefinition::from_event(&event)?,
+            BadgeDefinition::new("bravery").image(BadgeImage::new(
+                "https://nostr.academy/awards/bravery.png",
+                None
+            ))
+        );
+
+        let event = build_event(
+            Kind::Base(KindBase::BadgeDefinition),
+            "",
+            r#"[["name", "Medal of Bravery"]]"#,
+        );
+        assert_eq!(
+            BadgeDefinition::from_event(&event),
+            Err(Error::IdentifierNotFound)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_image_dimensions() -> Result<()> {
+        let dimensions = ImageDimensions::from_str("1024x768")?;
+        assert_eq!(dimensions.width, 1024);
+        assert_eq!(dimensions.height, 768);
+        assert_eq!(dimensions.to_string(), "1024x768");
+
+        for dimensions in [
+            "",
+            "1024",
+            "x1024",
+            "1024x",
+            "axb",
+            "1024X768",
+            "1024x1024x1",
+        ] {
+            assert_eq!(
+                ImageDimensions::from_str(dimensions),
+                Err(Error::InvalidDimensions)
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_badge_award() -> Result<()> {
+        let issuer = Keys::generate_from_os_random();
+        let (bob, charlie) = (public_key(), public_key());
+
+        let definition = definition("bravery", &issuer)?;
+        let award = BadgeAward::from_event(&award(&definition, &[bob, charlie], &issuer)?)?;
+        assert_eq!(
+            award.badge.to_string(),
+            format!("30009:{}:bravery", issuer.public_key())
+        );
+        assert_eq!(award.awarded, vec![bob, charlie]);
+
+        // Only badge definitions can be awarded
+        let note = EventBuilder::new_text_note("", &[]).to_event(&issuer)?;
+        assert_eq!(BadgeAward::tags(&note, &[bob]), Err(Error::InvalidKind));
+
+        // Awards of something else than a badge definition
+        let article = format!("30023:{}:bravery", issuer.public_key());
+        for tags in [
+            String::from("[]"),
+            String::from(r#"[["a", "30009:not a public key:bravery"]]"#),
+            format!(r#"[["a", "{}"]]"#, article),
+        ] {
+            let event = build_event(Kind::Base(KindBase::BadgeAward), "", &tags);
+            assert_eq!(BadgeAward::from_event(&event), Err(Error::BadgeNotFound));
+        }
+
+        // Invalid awarded public keys are skipped
+        let event = build_event(
+            Kind::Base(KindBase::BadgeAward),
+            "",
+            &format!(
+                r#"[["a", "30009:{}:bravery"], ["p", "not a public key"], ["p"], ["p", "{}", "wss://relay"]]"#,
+                issuer.public_key(),
+                bob
+            ),
+        );
+        assert_eq!(BadgeAward::from_event(&event)?.awarded, vec![bob]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_profile_badges() -> Result<()> {
+        let issuer = Keys::generate_from_os_random();
+        let user = Keys::generate_from_os_random();
+
+        let bravery = definition("bravery", &issuer)?;
+        let honor = definition("honor", &issuer)?;
+        let bravery_award = award(&bravery, &[user.public_key()], &issuer)?;
+        let honor_award = award(&honor, &[user.public_key()], &issuer)?;
+
+        // The order chosen by the user is kept
+        let badges = vec![
+            (honor.clone(), honor_award.clone()),
+            (bravery.clone(), bravery_award.clone()),
+        ];
+        let event = EventBuilder::profile_badges(&badges, &user.public_key())?.to_event(&user)?;
+        assert_eq!(event.identifier(), Some(PROFILE_BADGES_IDENTIFIER));
+        assert_eq!(
+            ProfileBadges::from_event(&event)?.badges,
+            vec![
+                (honor.coordinate().unwrap(), honor_award.id),
+                (bravery.coordinate().unwrap(), bravery_award.id),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_profile_badges_validation() -> Result<()> {
+        let issuer = Keys::generate_from_os_random();
+        let user = Keys::generate_from_os_random();
+        let bravery = definition("bravery", &issuer)?;
+        let bravery_award = award(&bravery, &[user.public_key()], &issuer)?;
+
+        // Not awarded to that public key
+        assert_eq!(
+            ProfileBadges::tags(
+                &[(bravery.clone(), bravery_award.clone())],
+                &issuer.public_key()
+            ),
+            Err(Error::BadgeNotAwarded)
+        );
+
+        // Award of another badge
+        let honor = definition("honor", &issuer)?;
+        assert_eq!(
+            ProfileBadges::tags(&[(honor, bravery_award.clone())], &user.public_key()),
+            Err(Error::BadgeAwardMismatch)
+        );
+
+        // Award of the same badge, but not by its issuer
+        let forged = EventBuilder::new(
+            Kind::Base(KindBase::BadgeAward),
+            "",
+            &BadgeAward::tags(&bravery, &[user.public_key()])?,
+        )
+        .to_event(&user)?;
+        assert_eq!(
+            ProfileBadges::tags(&[(bravery.clone(), forged)], &user.public_key()),
+            Err(Error::BadgeAwardMismatch)
+        );
+
+        // Definition and award swapped
+        assert_eq!(
+            ProfileBadges::tags(&[(bravery_award, bravery)], &user.public_key()),
+            Err(Error::InvalidKind)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_profile_badges_pairs() -> Result<()> {
+        let badge = format!("30009:{}:bravery", public_key());
+        let award = EventBuilder::new_text_note("", &[])
+            .to_event(&Keys::generate_from_os_random())?
+            .id;
+
+        // Only `a` tags immediately followed by an `e` tag make a pair
+        let event = build_event(
+            Kind::Base(KindBase::ProfileBadges),
+            "",
+            &format!(
+                r#"[
+                    ["d", "profile_badges"],
+                    ["e", "{award}"],
+                    ["a", "30009:not a public key:bravery"],
+                    ["e", "{award}"],
+                    ["a", "{badge}"],
+                    ["e", "not an event id"],
+                    ["a", "{badge}"],
+                    ["a", "{badge}"],
+                    ["e", "{award}", "wss://nostr.academy"]
+                ]"#,
+                award = award,
+                badge = badge
+            ),
+        );
+        assert_eq!(
+            ProfileBadges::from_event(&event)?.badges,
+            vec![(Coordinate::from_str(&badge)?, award)]
+        );
+
+        let event = build_event(
+            Kind::Base(KindBase::ProfileBadges),
+            "",
+            r#"[["d", "badges"]]"#,
+        );
+        assert_eq!(
+            ProfileBadges::from_event(&event),
+            Err(Error::IdentifierNotFound)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,241 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use bitcoin::secp256k1::XOnlyPublicKey;
+
+use super::nip44;
+use crate::event::unsigned::{self, UnsignedEvent};
+use crate::event::{self, TagData};
+use crate::key::{self, Keys};
+use crate::util::time::timestamp;
+use crate::{Event, EventBuilder, Kind, KindBase, Tag};
+
+/// Max random tweak of the seal and gift wrap timestamps (2 days)
+const RANGE_RANDOM_TIMESTAMP_TWEAK: u64 = 2 * 24 * 60 * 60;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Key error
+    #[error("key error: {0}")]
+    Key(#[from] key::Error),
+    /// Event error
+    #[error("event error: {0}")]
+    Event(#[from] event::Error),
+    /// Unsigned event error
+    #[error("unsigned event error: {0}")]
+    Unsigned(#[from] unsigned::Error),
+    /// NIP44 error
+    #[error("nip44 error: {0}")]
+    NIP44(#[from] nip44::Error),
+    /// Not a gift wrap
+    #[error("not a gift wrap")]
+    NotGiftWrap,
+    /// Not a seal
+    #[error("not a seal")]
+    NotSeal,
+    /// Rumor id mismatch or author different from the seal one
+    #[error("invalid rumor")]
+    InvalidRumor,
+}
+
+/// Content of an unwrapped gift wrap
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct UnwrappedGift {
+    /// Author of the seal (and of the rumor)
+    pub sender: XOnlyPublicKey,
+    pub rumor: UnsignedEvent,
+}
+
+/// Seal the rumor (kind 13): NIP-44 encrypted for `receiver` and signed by `sender_keys`
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/59.md>
+pub fn seal(
+    sender_keys: &Keys,
+    receiver: &XOnlyPublicKey,
+    rumor: &UnsignedEvent,
+) -> Result<Event, Error> {
+    let content = nip44::encrypt(&sender_keys.secret_key()?, receiver, rumor.as_json()?)?;
+    let builder = EventBuilder::new(Kind::Base(KindBase::Seal), content, &[]);
+    sign_with_tweaked_timestamp(builder, sender_keys)
+}
+
+/// Seal the rumor and wrap it (kind 1059) with ephemeral keys for `receiver`
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/59.md>
+pub fn gift_wrap(
+    sender_keys: &Keys,
+    receiver: &XOnlyPublicKey,
+    rumor: &UnsignedEvent,
+) -> Result<Event, Error> {
+    let seal: Event = seal(sender_keys, receiver, rumor)?;
+
+    let keys = Keys::generate_from_os_random();
+    let content = nip44::encrypt(&keys.secret_key()?, receiver, seal.as_json()?)?;
+    let builder = EventBuilder::new(
+        Kind::Base(KindBase::GiftWrap),
+        content,
+        &[Tag::new(TagData::PubKey(*receiver))],
+    );
+    sign_with_tweaked_timestamp(builder, &keys)
+}
+
+/// Unwrap a gift wrap received by `receiver_keys`, checking the seal signature
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/59.md>
+pub fn unwrap(receiver_keys: &Keys, gift_wrap: &Event) -> Result<UnwrappedGift, Error> {
+    if gift_wrap.kind != Kind::Base(KindBase::GiftWrap) {
+        return Err(Error::NotGiftWrap);
+    }
+
+    let secret_key = receiver_keys.secret_key()?;
+
+    let seal: Event = Event::from_json(nip44::decrypt(
+        &secret_key,
+        &gift_wrap.pubkey,
+        &gift_wrap.content,
+    )?)?;
+    if seal.kind != Kind::Base(KindBase::Seal) {
+        return Err(Error::NotSeal);
+    }
+
+    let rumor: UnsignedEvent =
+        UnsignedEvent::from_json(nip44::decrypt(&secret_key, &seal.pubkey, &seal.content)?)?;
+    let id = EventBuilder::gen_id(
+        &rumor.pubkey,
+        rumor.created_at,
+        &rumor.kind,
+        &rumor.tags,
+        &rumor.content,
+    );
+    if rumor.pubkey != seal.pubkey || rumor.id != id {
+        return Err(Error::InvalidRumor);
+    }
+
+    Ok(UnwrappedGift {
+        sender: seal.pubkey,
+        rumor,
+    })
+}
+
+/// Sign the event with a random timestamp up to 2 days in the past
+fn sign_with_tweaked_timestamp(builder: EventBuilder, keys: &Keys) -> Result<Event, Error> {
+    let tweak: u64 = bitcoin::secp256k1::rand::random::<u64>() % RANGE_RANDOM_TIMESTAMP_TWEAK;
+    Ok(builder
+        .custom_created_at(timestamp().saturating_sub(tweak))
+        .to_unsigned_event(keys.public_key())
+        .sign(keys)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Result;
+
+    #[test]
+    fn test_gift_wrap() -> Result<()> {
+        let sender = Keys::generate_from_os_random();
+        let receiver = Keys::generate_from_os_random();
+
+        let rumor =
+            EventBuilder::new_text_note("Secret", &[]).to_unsigned_event(sender.public_key());
+        let event = gift_wrap(&sender, &receiver.public_key(), &rumor)?;
+
+        assert_eq!(event.kind, Kind::Base(KindBase::GiftWrap));
+        assert_ne!(event.pubkey, sender.public_key());
+        assert!(event.created_at <= timestamp());
+        event.verify()?;
+
+        let unwrapped = unwrap(&receiver, &event)?;
+        assert_eq!(unwrapped.sender, sender.public_key());
+        assert_eq!(unwrapped.rumor, rumor);
+
+        // Only the receiver can unwrap it
+        assert!(unwrap(&sender, &event).is_err());
+
+        // Rumor author different from the seal one
+        let impostor = Keys::generate_from_os_random();
+        let event = gift_wrap(&impostor, &receiver.public_key(), &rumor)?;
+        assert!(matches!(
+            unwrap(&receiver, &event),
+            Err(Error::InvalidRumor)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_private_msg() -> Result<()> {
+        let sender = Keys::generate_from_os_random();
+        let receiver = Keys::generate_from_os_random();
+
+        let rumor = EventBuilder::private_msg_rumor(receiver.public_key(), "Hello")
+            .to_unsigned_event(sender.public_key());
+        let event = gift_wrap(&sender, &receiver.public_key(), &rumor)?;
+
+        let unwrapped = unwrap(&receiver, &event)?;
+        assert_eq!(
+            unwrapped.rumor.kind,
+            Kind::Base(KindBase::PrivateDirectMessage)
+        );
+        assert_eq!(unwrapped.rumor.content, "Hello");
+        assert_eq!(
+            unwrapped.rumor.tags,
+            vec![Tag::new(TagData::PubKey(receiver.public_key()))]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unwrap_invalid() -> Result<()> {
+        let sender = Keys::generate_from_os_random();
+        let receiver = Keys::generate_from_os_random();
+        let rumor =
+            EventBuilder::new_text_note("Secret", &[]).to_unsigned_event(sender.public_key());
+
+        // Wrap arbitrary content as the sender would do
+        let wrap = |content: String| -> Result<Event> {
+            let keys = Keys::generate_from_os_random();
+            let content = nip44::encrypt(&keys.secret_key()?, &receiver.public_key(), content)?;
+            Ok(EventBuilder::new(
+                Kind::Base(KindBase::GiftWrap),
+                content,
+                &[Tag::new(TagData::PubKey(receiver.public_key()))],
+            )
+            .to_event(&keys)?)
+        };
+        let seal_of = |kind: KindBase, rumor: String| -> Result<Event> {
+            let content = nip44::encrypt(&sender.secret_key()?, &receiver.public_key(), rumor)?;
+            Ok(EventBuilder::new(Kind::Base(kind), content, &[]).to_event(&sender)?)
+        };
+
+        let note = EventBuilder::new_text_note("", &[]).to_event(&sender)?;
+        assert!(matches!(unwrap(&receiver, &note), Err(Error::NotGiftWrap)));
+
+        let event = EventBuilder::new(Kind::Base(KindBase::GiftWrap), "not encrypted", &[])
+            .to_event(&sender)?;
+        assert!(matches!(unwrap(&receiver, &event), Err(Error::NIP44(_))));
+
+        let event = wrap(String::from("not an event"))?;
+        assert!(matches!(unwrap(&receiver, &event), Err(Error::Event(_))));
+
+        let event = wrap(seal_of(KindBase::TextNote, rumor.as_json()?)?.as_json()?)?;
+        assert!(matches!(unwrap(&receiver, &event), Err(Error::NotSeal)));
+
+        let event = wrap(seal_of(KindBase::Seal, String::from("{}"))?.as_json()?)?;
+        assert!(matches!(unwrap(&receiver, &event), Err(Error::Unsigned(_))));
+
+        // Rumor content changed after the id was computed
+        let tampered = rumor.as_json()?.replace("Secret", "Public");
+        let event = wrap(seal_of(KindBase::Seal, tampered)?.as_json()?)?;
+        assert!(matches!(
+            unwrap(&receiver, &event),
+            Err(Error::InvalidRumor)
+        ));
+
+        let event = wrap(seal_of(KindBase::Seal, rumor.as_json()?)?.as_json()?)?;
+        assert_eq!(unwrap(&receiver, &event)?.rumor, rumor);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::fmt;
+use std::str::FromStr;
+
+use url::Url;
+
+use crate::event::TagKind;
+use crate::{Event, Kind, KindBase, Tag};
+
+#[derive(Debug, Eq, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// Invalid relay metadata
+    #[error("invalid relay metadata")]
+    InvalidRelayMetadata,
+}
+
+/// Relay usage marker of a relay list entry
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum RelayMetadata {
+    /// The user reads from this relay (others publish mentions of the user here)
+    Read,
+    /// The user writes to this relay (others fetch the user's events here)
+    Write,
+}
+
+impl fmt::Display for RelayMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Read => write!(f, "read"),
+            Self::Write => write!(f, "write"),
+        }
+    }
+}
+
+impl FromStr for RelayMetadata {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read" => Ok(Self::Read),
+            "write" => Ok(Self::Write),
+            _ => Err(Error::InvalidRelayMetadata),
+        }
+    }
+}
+
+/// Relay list `r` tags
+pub fn relay_list_tags(list: &[(Url, Option<RelayMetadata>)]) -> Vec<Tag> {
+    list.iter()
+        .map(|(url, metadata)| {
+            let mut tag = vec![String::from("r"), url.to_string()];
+            if let Some(metadata) = metadata {
+                tag.push(metadata.to_string());
+            }
+            Tag::from(tag)
+        })
+        .collect()
+}
+
+/// Extract the relays from a relay list (kind 10002)
+///
+/// A relay without marker is used both for reading and writing.
+/// Return an empty list if the event isn't a relay list.
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/65.md>
+pub fn extract_relay_list(event: &Event) -> Vec<(Url, Option<RelayMetadata>)> {
+    if event.kind != Kind::Base(KindBase::RelayList) {
+        return Vec::new();
+    }
+
+    event
+        .tags
+        .iter()
+        .filter(|tag| matches!(tag.kind(), Ok(TagKind::Custom(kind)) if kind == "r"))
+        .filter_map(|tag| {
+            let url = Url::parse(tag.content()?).ok()?;
+            let metadata = match tag.as_vec().get(2) {
+                Some(metadata) => Some(RelayMetadata::from_str(metadata).ok()?),
+                None => None,
+            };
+            Some((url, metadata))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EventBuilder, Keys, Result};
+
+    #[test]
+    fn test_relay_list() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+
+        let list = vec![
+            (Url::parse("wss://relay.damus.io")?, None),
+            (Url::parse("wss://nostr.wine")?, Some(RelayMetadata::Write)),
+            (Url::parse("wss://nos.lol")?, Some(RelayMetadata::Read)),
+        ];
+        let event = EventBuilder::relay_list(&list).to_event(&keys)?;
+        assert_eq!(event.kind, Kind::Base(KindBase::RelayList));
+        assert_eq!(extract_relay_list(&event), list);
+
+        let invalid = Tag::from(vec![
+            String::from("r"),
+            String::from("wss://relay.example.com"),
+            String::from("readwrite"),
+        ]);
+        let event =
+            EventBuilder::new(Kind::Base(KindBase::RelayList), "", &[invalid]).to_event(&keys)?;
+        assert!(extract_relay_list(&event).is_empty());
+
+        let note = EventBuilder::new_text_note("", &[]).to_event(&keys)?;
+        assert!(extract_relay_list(&note).is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,533 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::time::Duration;
+
+use url::Url;
+
+use crate::event::TagData;
+use crate::{Event, Kind, KindBase, SubscriptionFilter, Tag};
+
+#[derive(Debug, Eq, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// Invalid event kind
+    #[error("invalid event kind")]
+    InvalidKind,
+    /// Relay url (`d` tag) not found or invalid
+    #[error("invalid relay url")]
+    InvalidRelayUrl,
+    /// Invalid `frequency` tag
+    #[error("invalid frequency")]
+    InvalidFrequency,
+}
+
+fn single_tag(kind: &str, value: String) -> Tag {
+    Tag::from(vec![kind.to_string(), value])
+}
+
+/// Relay discovery (kind 30166), published by relay monitors
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/66.md>
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RelayDiscovery {
+    /// Relay url (`d` tag)
+    pub relay_url: Url,
+    /// Round-trip time to open a connection
+    pub rtt_open: Option<Duration>,
+    /// Round-trip time of a `REQ`
+    pub rtt_read: Option<Duration>,
+    /// Round-trip time of an `EVENT`
+    pub rtt_write: Option<Duration>,
+    /// Network (`clearnet`, `tor`, `i2p`, `loki`)
+    pub network: Option<String>,
+    /// Relay type (ex. `PublicOutbox`)
+    pub relay_type: Option<String>,
+    pub supported_nips: Vec<u16>,
+    /// Requirements (ex. `auth`, `payment`, `!auth`)
+    pub requirements: Vec<String>,
+    pub topics: Vec<String>,
+    pub geohash: Option<String>,
+    /// NIP-11 document, as JSON (optional)
+    pub info: Option<String>,
+}
+
+impl RelayDiscovery {
+    /// New `RelayDiscovery`
+    pub fn new(relay_url: Url) -> Self {
+        Self {
+            relay_url,
+            rtt_open: None,
+            rtt_read: None,
+            rtt_write: None,
+            network: None,
+            relay_type: None,
+            supported_nips: Vec::new(),
+            requirements: Vec::new(),
+            topics: Vec::new(),
+            geohash: None,
+            info: None,
+        }
+    }
+
+    /// Set round-trip times (open, read, write)
+    pub fn rtt(
+        self,
+        open: Option<Duration>,
+        read: Option<Duration>,
+        write: Option<Duration>,
+    ) -> Self {
+        Self {
+            rtt_open: open,
+            rtt_read: read,
+            rtt_write: write,
+            ..self
+        }
+    }
+
+    /// Set network
+    pub fn network<S>(self, network: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            network: Some(network.into()),
+            ..self
+        }
+    }
+
+    /// Set relay type
+    pub fn relay_type<S>(self, relay_type: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            relay_type: Some(relay_type.into()),
+            ..self
+        }
+    }
+
+    /// Set supported NIPs
+    pub fn supported_nips(self, nips: Vec<u16>) -> Self {
+        Self {
+            supported_nips: nips,
+            ..self
+        }
+    }
+
+    /// Add requirement
+    pub fn requirement<S>(mut self, requirement: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.requirements.push(requirement.into());
+        self
+    }
+
+    /// Add topic
+    pub fn topic<S>(mut self, topic: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.topics.push(topic.into());
+        self
+    }
+
+    /// Set geohash
+    pub fn geohash<S>(self, geohash: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            geohash: Some(geohash.into()),
+            ..self
+        }
+    }
+
+    /// Set NIP-11 document (JSON)
+    pub fn info<S>(self, info: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            info: Some(info.into()),
+            ..self
+        }
+    }
+
+    /// Get relay discovery tags
+    pub fn tags(&self) -> Vec<Tag> {
+        let mut tags = vec![Tag::new(TagData::Identifier(self.relay_url.to_string()))];
+
+        for (kind, rtt) in [
+            ("rtt-open", self.rtt_open),
+            ("rtt-read", self.rtt_read),
+            ("rtt-write", self.rtt_write),
+        ] {
+            if let Some(rtt) = rtt {
+                tags.push(single_tag(kind, rtt.as_millis().to_string()));
+            }
+        }
+        if let Some(network) = &self.network {
+            tags.push(single_tag("n", network.clone()));
+        }
+        if let Some(relay_type) = &self.relay_type {
+            tags.push(single_tag("T", relay_type.clone()));
+        }
+        for nip in self.supported_nips.iter() {
+            tags.push(single_tag("N", nip.to_string()));
+        }
+        for requirement in self.requirements.iter() {
+            tags.push(single_tag("R", requirement.clone()));
+        }
+        for topic in self.topics.iter() {
+            tags.push(single_tag("t", topic.clone()));
+        }
+        if let Some(geohash) = &self.geohash {
+            tags.push(single_tag("g", geohash.clone()));
+        }
+
+        tags
+    }
+
+    /// Parse relay discovery from a kind 30166 event
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        if event.kind != Kind::Base(KindBase::RelayDiscovery) {
+            return Err(Error::InvalidKind);
+        }
+
+        let relay_url = event
+            .identifier()
+            .and_then(|d| Url::parse(d).ok())
+            .ok_or(Error::InvalidRelayUrl)?;
+        let mut discovery = Self::new(relay_url);
+        if !event.content.is_empty() {
+            discovery.info = Some(event.content.clone());
+        }
+
+        for tag in event.tags.iter() {
+            let tag = tag.as_vec();
+            let (kind, value) = match (tag.first(), tag.get(1)) {
+                (Some(kind), Some(value)) => (kind.as_str(), value.clone()),
+                _ => continue,
+            };
+            let rtt = || value.parse().ok().map(Duration::from_millis);
+            match kind {
+                "rtt-open" => discovery.rtt_open = rtt(),
+                "rtt-read" => discovery.rtt_read = rtt(),
+                "rtt-write" => discovery.rtt_write = rtt(),
+                "n" => discovery.network = Some(value),
+                "T" => discovery.relay_type = Some(value),
+                "N" => discovery.supported_nips.extend(value.parse::<u16>().ok()),
+                "R" => discovery.requirements.push(value),
+                "t" => discovery.topics.push(value),
+                "g" => discovery.geohash = Some(value),
+                _ => (),
+            }
+        }
+
+        Ok(discovery)
+    }
+
+    /// Filter for the discovery events of `relay_url`
+    pub fn filter(relay_url: &Url) -> SubscriptionFilter {
+        SubscriptionFilter::new()
+            .kind(Kind::Base(KindBase::RelayDiscovery))
+            .identifier(relay_url.to_string())
+    }
+}
+
+/// Relay monitor announcement (kind 10166)
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/66.md>
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RelayMonitor {
+    /// How often the monitor publishes discovery events
+    pub frequency: Duration,
+    /// Timeouts, with the optional check they apply to (ex. `open`, `read`)
+    pub timeouts: Vec<(Duration, Option<String>)>,
+    /// Checks performed (ex. `open`, `read`, `write`, `auth`, `nip11`, `dns`, `geo`)
+    pub checks: Vec<String>,
+    pub geohash: Option<String>,
+}
+
+impl RelayMonitor {
+    /// New `RelayMonitor`
+    pub fn new(frequency: Duration) -> Self {
+        Self {
+            frequency,
+            timeouts: Vec::new(),
+            checks: Vec::new(),
+            geohash: None,
+        }
+    }
+
+    /// Add timeout
+    pub fn timeout<S>(mut self, timeout: Duration, check: Option<S>) -> Self
+    where
+        S: Into<String>,
+    {
+        self.timeouts.push((timeout, check.map(|c| c.into())));
+        self
+    }
+
+    /// Add check
+    pub fn check<S>(mut self, check: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.checks.push(check.into());
+        self
+    }
+
+    /// Set geohash
+    pub fn geohash<S>(self, geohash: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            geohash: Some(geohash.into()),
+            ..self
+        }
+    }
+
+    /// Get relay monitor tags
+    pub fn tags(&self) -> Vec<Tag> {
+        let mut tags = vec![single_tag(
+            "frequency",
+            self.frequency.as_secs().to_string(),
+        )];
+        for (timeout, check) in self.timeouts.iter() {
+            let mut tag = vec![String::from("timeout"), timeout.as_millis().to_string()];
+            if let Some(check) = check {
+                tag.push(check.clone());
+            }
+            tags.push(Tag::from(tag));
+        }
+        for check in self.checks.iter() {
+            tags.push(single_tag("c", check.clone()));
+        }
+        if let Some(geohash) = &self.geohash {
+            tags.push(single_tag("g", geohash.clone()));
+        }
+        tags
+    }
+
+    /// Parse relay monitor announcement from a kind 10166 event
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        if event.kind != Kind::Base(KindBase::RelayMonitorAnnouncement) {
+            return Err(Error::InvalidKind);
+        }
+
+        let mut frequency: Option<Duration> = None;
+        let mut monitor = Self::new(Duration::ZERO);
+        for tag in event.tags.iter() {
+            let tag = tag.as_vec();
+            match (tag.first().map(|t| t.as_str()), tag.get(1)) {
+                (Some("frequency"), Some(value)) => {
+                    frequency = value.parse().ok().map(Duration::from_secs)
+                }
+                (Some("timeout"), Some(value)) => {
+                    if let Ok(timeout) = value.parse() {
+                        monitor
+                            .timeouts
+                            .push((Duration::from_millis(timeout), tag.get(2).cloned()));
+                    }
+                }
+                (Some("c"), Some(check)) => monitor.checks.push(check.clone()),
+                (Some("g"), Some(geohash)) => monitor.geohash = Some(geohash.clone()),
+                _ => (),
+            }
+        }
+        monitor.frequency = frequency.ok_or(Error::InvalidFrequency)?;
+
+        Ok(monitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::nips::test_util::build_event;
+    use crate::{EventBuilder, Keys, Result};
+
+    fn discovery(content: &str, tags_json: &str) -> Result<RelayDiscovery, Error> {
+        RelayDiscovery::from_event(&build_event(
+            Kind::Base(KindBase::RelayDiscovery),
+            content,
+            tags_json,
+        ))
+    }
+
+    fn monitor(tags_json: &str) -> Result<RelayMonitor, Error> {
+        RelayMonitor::from_event(&build_event(
+            Kind::Base(KindBase::RelayMonitorAnnouncement),
+            "",
+            tags_json,
+        ))
+    }
+
+    #[test]
+    fn test_relay_discovery() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        let relay_url = Url::parse("wss://relay.damus.io")?;
+
+        let discovery = RelayDiscovery::new(relay_url.clone())
+            .rtt(
+                Some(Duration::from_millis(234)),
+                Some(Duration::from_millis(150)),
+                None,
+            )
+            .network("clearnet")
+            .supported_nips(vec![1, 11, 40])
+            .requirement("!payment")
+            .topic("bitcoin")
+            .info(r#"{"name":"damus"}"#);
+        let event = EventBuilder::relay_discovery(&discovery).to_event(&keys)?;
+        assert_eq!(RelayDiscovery::from_event(&event)?, discovery);
+
+        // Only the discovery events of that relay
+        assert!(RelayDiscovery::filter(&relay_url).match_event(&event));
+        assert!(!RelayDiscovery::filter(&Url::parse("wss://nos.lol")?).match_event(&event));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_round_trip_times() -> Result<()> {
+        // Milliseconds: sub-millisecond precision is lost
+        let rtt = RelayDiscovery::new(Url::parse("wss://some.relay")?).rtt(
+            Some(Duration::from_micros(234_900)),
+            None,
+            Some(Duration::ZERO),
+        );
+        let tags: Vec<Vec<String>> = rtt.tags().iter().map(|t| t.as_vec()).collect();
+        assert_eq!(tags[1], vec!["rtt-open", "234"]);
+        assert_eq!(tags[2], vec!["rtt-write", "0"]);
+
+        let parsed = discovery(
+            "",
+            r#"[["d", "wss://some.relay/"], ["rtt-open", "fast"], ["rtt-read", "-1"], ["rtt-write", "12"]]"#,
+        )?;
+        assert_eq!(parsed.rtt_open, None);
+        assert_eq!(parsed.rtt_read, None);
+        assert_eq!(parsed.rtt_write, Some(Duration::from_millis(12)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_relay_discovery_spec_example() -> Result<()> {
+        assert_eq!(
+            discovery(
+                "{}",
+                r#"[
+                    ["d", "wss://some.relay/"],
+                    ["n", "clearnet"],
+                    ["N", "40"],
+                    ["N", "33"],
+                    ["R", "!payment"],
+                    ["R", "auth"],
+                    ["g", "ww8p1r4t8"],
+                    ["l", "en", "ISO-639-1"],
+                    ["t", "nsfw"],
+                    ["rtt-open", "234"],
+                    ["T", "PrivateInbox"]
+                ]"#
+            )?,
+            RelayDiscovery::new(Url::parse("wss://some.relay/")?)
+                .rtt(Some(Duration::from_millis(234)), None, None)
+                .network("clearnet")
+                .relay_type("PrivateInbox")
+                .supported_nips(vec![40, 33])
+                .requirement("!payment")
+                .requirement("auth")
+                .topic("nsfw")
+                .geohash("ww8p1r4t8")
+                .info("{}")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_relay_discovery_invalid_tags() -> Result<()> {
+        for tags in [r#"[]"#, r#"[["d"]]"#, r#"[["d", "some.relay"]]"#] {
+            assert_eq!(discovery("", tags), Err(Error::InvalidRelayUrl));
+        }
+
+        // NIP numbers out of range, tags without a value and an empty content are skipped
+        assert_eq!(
+            discovery(
+                "",
+                r#"[["d", "wss://some.relay/"], ["N", "NIP-40"], ["N", "70000"], ["n"], ["R"]]"#
+            )?,
+            RelayDiscovery::new(Url::parse("wss://some.relay/")?)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_relay_monitor() -> Result<()> {
+        // Frequency in seconds, timeouts in milliseconds
+        let announcement = RelayMonitor::new(Duration::from_secs(3600))
+            .timeout(Duration::from_millis(5000), Some("open"))
+            .check("open")
+            .check("read");
+        let tags: Vec<Vec<String>> = announcement.tags().iter().map(|t| t.as_vec()).collect();
+        assert_eq!(tags[0], vec!["frequency", "3600"]);
+        assert_eq!(tags[1], vec!["timeout", "5000", "open"]);
+
+        let event = EventBuilder::relay_monitor_announcement(&announcement)
+            .to_event(&Keys::generate_from_os_random())?;
+        assert_eq!(RelayMonitor::from_event(&event)?, announcement);
+        assert_eq!(RelayDiscovery::from_event(&event), Err(Error::InvalidKind));
+
+        // Spec example: a timeout without check applies to all of them
+        assert_eq!(
+            monitor(
+                r#"[
+                    ["timeout", "5000", "open"],
+                    ["timeout", "3000", "read"],
+                    ["timeout", "2000"],
+                    ["frequency", "3600"],
+                    ["c", "ws"],
+                    ["c", "nip11"],
+                    ["c", "ssl"],
+                    ["g", "ihdbsd"]
+                ]"#
+            )?,
+            RelayMonitor::new(Duration::from_secs(3600))
+                .timeout(Duration::from_millis(5000), Some("open"))
+                .timeout(Duration::from_millis(3000), Some("read"))
+                .timeout::<String>(Duration::from_millis(2000), None)
+                .check("ws")
+                .check("nip11")
+                .check("ssl")
+                .geohash("ihdbsd")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_relay_monitor_frequency() -> Result<()> {
+        for tags in [
+            r#"[]"#,
+            r#"[["frequency"]]"#,
+            r#"[["frequency", "hourly"]]"#,
+            r#"[["frequency", "-3600"]]"#,
+            r#"[["timeout", "5000", "open"], ["c", "ws"]]"#,
+        ] {
+            assert_eq!(monitor(tags), Err(Error::InvalidFrequency));
+        }
+
+        // The frequency is required, the rest is skipped when invalid
+        assert_eq!(
+            monitor(r#"[["frequency", "3600"], ["timeout", "open", "5000"], ["timeout"], ["c"]]"#)?,
+            RelayMonitor::new(Duration::from_secs(3600))
+        );
+
+        Ok(())
+    }
+}
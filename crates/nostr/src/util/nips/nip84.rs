@@ -0,0 +1,297 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::str::FromStr;
+
+use bitcoin::secp256k1::XOnlyPublicKey;
+use url::Url;
+
+use super::nip19::Coordinate;
+use crate::event::{TagData, TagKind};
+use crate::{Event, Kind, KindBase, Sha256Hash, Tag};
+
+#[derive(Debug, Eq, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// Invalid event kind
+    #[error("invalid event kind")]
+    InvalidKind,
+}
+
+/// Source of a highlight
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum HighlightSource {
+    /// `e` tag
+    Event(Sha256Hash),
+    /// `a` tag
+    Coordinate(Coordinate),
+    /// `r` tag
+    Url(Url),
+}
+
+impl HighlightSource {
+    fn tag(&self) -> Tag {
+        match self {
+            Self::Event(id) => Tag::new(TagData::EventId(*id)),
+            Self::Coordinate(coordinate) => Tag::new(TagData::Coordinate(coordinate.clone())),
+            Self::Url(url) => Tag::from(vec![String::from("r"), url.to_string()]),
+        }
+    }
+
+    fn from_tag(tag: &Tag) -> Option<Self> {
+        let content = tag.content()?;
+        match tag.kind().ok()? {
+            TagKind::E => Sha256Hash::from_str(content).ok().map(Self::Event),
+            TagKind::A => Coordinate::from_str(content).ok().map(Self::Coordinate),
+            TagKind::Custom(kind) if kind == "r" => Url::parse(content).ok().map(Self::Url),
+            _ => None,
+        }
+    }
+}
+
+/// Attribution of the highlighted content (`p` tag)
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Attribution {
+    pub public_key: XOnlyPublicKey,
+    pub relay_url: Option<Url>,
+    /// Role (ex. `author`, `editor`)
+    pub role: Option<String>,
+}
+
+/// Highlight (kind 9802)
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/84.md>
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Highlight {
+    /// Highlighted text
+    pub content: String,
+    pub source: Option<HighlightSource>,
+    /// Surrounding text, when the highlight is only a portion of a paragraph
+    pub context: Option<String>,
+    pub attributions: Vec<Attribution>,
+}
+
+impl Highlight {
+    /// New `Highlight`
+    pub fn new<S>(content: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            content: content.into(),
+            source: None,
+            context: None,
+            attributions: Vec::new(),
+        }
+    }
+
+    /// Set source
+    pub fn source(self, source: HighlightSource) -> Self {
+        Self {
+            source: Some(source),
+            ..self
+        }
+    }
+
+    /// Set context
+    pub fn context<S>(self, context: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            context: Some(context.into()),
+            ..self
+        }
+    }
+
+    /// Add attribution
+    pub fn attribution(mut self, attribution: Attribution) -> Self {
+        self.attributions.push(attribution);
+        self
+    }
+
+    /// Get highlight tags
+    pub fn tags(&self) -> Vec<Tag> {
+        let mut tags = Vec::new();
+        if let Some(source) = &self.source {
+            tags.push(source.tag());
+        }
+        if let Some(context) = &self.context {
+            tags.push(Tag::from(vec![String::from("context"), context.clone()]));
+        }
+        for attribution in self.attributions.iter() {
+            let mut tag = vec![TagKind::P.to_string(), attribution.public_key.to_string()];
+            match (&attribution.relay_url, &attribution.role) {
+                (relay_url, Some(role)) => {
+                    tag.push(
+                        relay_url
+                            .as_ref()
+                            .map(|u| u.to_string())
+                            .unwrap_or_default(),
+                    );
+                    tag.push(role.clone());
+                }
+                (Some(relay_url), None) => tag.push(relay_url.to_string()),
+                (None, None) => (),
+            }
+            tags.push(Tag::from(tag));
+        }
+        tags
+    }
+
+    /// Parse highlight from a kind 9802 event
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        if event.kind != Kind::Base(KindBase::Highlight) {
+            return Err(Error::InvalidKind);
+        }
+
+        let mut highlight = Self::new(event.content.clone());
+        highlight.source = event.tags.iter().find_map(HighlightSource::from_tag);
+        for tag in event.tags.iter() {
+            let tag = tag.as_vec();
+            match (tag.first().map(|t| t.as_str()), tag.get(1)) {
+                (Some("context"), Some(context)) => highlight.context = Some(context.clone()),
+                (Some("p"), Some(public_key)) => {
+                    if let Ok(public_key) = XOnlyPublicKey::from_str(public_key) {
+                        highlight.attributions.push(Attribution {
+                            public_key,
+                            relay_url: tag.get(2).and_then(|u| Url::parse(u).ok()),
+                            role: tag.get(3).cloned(),
+                        });
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        Ok(highlight)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::nips::test_util::{build_event, public_key};
+    use crate::{EventBuilder, Keys, Result};
+
+    #[test]
+    fn test_highlight() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+
+        let highlight = Highlight::new("The quick brown fox")
+            .source(HighlightSource::Url(Url::parse(
+                "https://example.com/article",
+            )?))
+            .context("The quick brown fox jumps over the lazy dog")
+            .attribution(Attribution {
+                public_key: public_key(),
+                relay_url: None,
+                role: Some(String::from("author")),
+            });
+        let event = EventBuilder::highlight(&highlight).to_event(&keys)?;
+        assert_eq!(event.kind, Kind::Base(KindBase::Highlight));
+        assert_eq!(Highlight::from_event(&event)?, highlight);
+
+        let note = EventBuilder::new_text_note("", &[]).to_event(&keys)?;
+        assert_eq!(Highlight::from_event(&note), Err(Error::InvalidKind));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_attribution_relay_and_role() -> Result<()> {
+        let author = public_key();
+        let relay_url = Url::parse("wss://relay.example.com")?;
+        let attribution = |relay_url: Option<Url>, role: Option<&str>| Attribution {
+            public_key: author,
+            relay_url,
+            role: role.map(String::from),
+        };
+
+        // The relay is a positional field: an empty one is written before the role
+        let highlight = Highlight::new("text")
+            .attribution(attribution(None, None))
+            .attribution(attribution(Some(relay_url.clone()), None))
+            .attribution(attribution(None, Some("editor")))
+            .attribution(attribution(Some(relay_url.clone()), Some("author")));
+        let tags: Vec<Vec<String>> = highlight.tags().iter().map(|t| t.as_vec()).collect();
+        let author = author.to_string();
+        let relay = relay_url.to_string();
+        assert_eq!(
+            tags,
+            vec![
+                vec![String::from("p"), author.clone()],
+                vec![String::from("p"), author.clone(), relay.clone()],
+                vec![
+                    String::from("p"),
+                    author.clone(),
+                    String::new(),
+                    String::from("editor")
+                ],
+                vec![String::from("p"), author, relay, String::from("author")],
+            ]
+        );
+
+        let event =
+            EventBuilder::highlight(&highlight).to_event(&Keys::generate_from_os_random())?;
+        assert_eq!(
+            Highlight::from_event(&event)?.attributions,
+            highlight.attributions
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_first_valid_source() -> Result<()> {
+        let author = public_key();
+
+        // Unparsable sources are skipped, the first valid one is used
+        let event = build_event(
+            Kind::Base(KindBase::Highlight),
+            "text",
+            &format!(
+                r#"[["e","not an event id"],["a","30023:not a public key:id"],["a","30023:{}:my-article"],["r","https://example.com/article"]]"#,
+                author
+            ),
+        );
+        assert_eq!(
+            Highlight::from_event(&event)?.source,
+            Some(HighlightSource::Coordinate(Coordinate::new(
+                Kind::Base(KindBase::LongFormTextNote),
+                author,
+                "my-article"
+            )))
+        );
+
+        // Only invalid sources, attributions and an empty context
+        let event = build_event(
+            Kind::Base(KindBase::Highlight),
+            "text",
+            r#"[["r","not a url"],["p","not a public key"],["context"]]"#,
+        );
+        let highlight = Highlight::from_event(&event)?;
+        assert_eq!(highlight, Highlight::new("text"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_non_text_highlight() -> Result<()> {
+        // Highlight of a non-text media: no content, the source is the media url
+        let event = build_event(
+            Kind::Base(KindBase::Highlight),
+            "",
+            r#"[["r","https://example.com/podcast.mp3"]]"#,
+        );
+        let highlight = Highlight::from_event(&event)?;
+        assert!(highlight.content.is_empty());
+        assert_eq!(
+            highlight.source,
+            Some(HighlightSource::Url(Url::parse(
+                "https://example.com/podcast.mp3"
+            )?))
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,466 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::str::FromStr;
+
+use super::nip19::Coordinate;
+use crate::event::{TagData, TagKind};
+use crate::metadata::{self, Metadata};
+use crate::{Event, Kind, KindBase, SubscriptionFilter, Tag};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Metadata error
+    #[error("metadata error: {0}")]
+    Metadata(#[from] metadata::Error),
+    /// Invalid event kind
+    #[error("invalid event kind")]
+    InvalidKind,
+    /// Identifier (`d` tag) not found or invalid
+    #[error("identifier not found")]
+    IdentifierNotFound,
+}
+
+/// Platform on which a handler can be opened
+///
+/// The `url` may contain `<bech32>`, to be replaced with the NIP-19 entity of the event.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct HandlerPlatform {
+    /// Platform name (`web`, `ios`, `android`, ...)
+    pub platform: String,
+    pub url: String,
+    /// NIP-19 entity type handled by this url (`nevent`, `nprofile`, `naddr`, ...)
+    pub entity: Option<String>,
+}
+
+impl HandlerPlatform {
+    /// New `HandlerPlatform`
+    pub fn new<S>(platform: S, url: S, entity: Option<S>) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            platform: platform.into(),
+            url: url.into(),
+            entity: entity.map(|e| e.into()),
+        }
+    }
+}
+
+/// Handler information (kind 31990): the kinds handled by an application
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/89.md>
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct HandlerInformation {
+    /// Handler identifier (`d` tag)
+    pub identifier: String,
+    /// Application profile, if different from the one of the publishing key
+    pub metadata: Option<Metadata>,
+    /// Handled kinds (`k` tags)
+    pub kinds: Vec<Kind>,
+    pub platforms: Vec<HandlerPlatform>,
+}
+
+impl HandlerInformation {
+    /// New `HandlerInformation`
+    pub fn new<S>(identifier: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            identifier: identifier.into(),
+            metadata: None,
+            kinds: Vec::new(),
+            platforms: Vec::new(),
+        }
+    }
+
+    /// Set metadata
+    pub fn metadata(self, metadata: Metadata) -> Self {
+        Self {
+            metadata: Some(metadata),
+            ..self
+        }
+    }
+
+    /// Add handled kind
+    pub fn kind(mut self, kind: Kind) -> Self {
+        self.kinds.push(kind);
+        self
+    }
+
+    /// Add platform
+    pub fn platform(mut self, platform: HandlerPlatform) -> Self {
+        self.platforms.push(platform);
+        self
+    }
+
+    /// Check if the application handles `kind`
+    pub fn handles(&self, kind: Kind) -> bool {
+        self.kinds.contains(&kind)
+    }
+
+    /// Get event content (metadata json or empty)
+    pub fn content(&self) -> Result<String, Error> {
+        match &self.metadata {
+            Some(metadata) => Ok(metadata.as_json()?),
+            None => Ok(String::new()),
+        }
+    }
+
+    /// Get handler information tags
+    pub fn tags(&self) -> Vec<Tag> {
+        let mut tags = vec![Tag::new(TagData::Identifier(self.identifier.clone()))];
+        tags.extend(
+            self.kinds
+                .iter()
+                .map(|k| Tag::from(vec![String::from("k"), u64::from(*k).to_string()])),
+        );
+        for p in self.platforms.iter() {
+            let mut tag = vec![p.platform.clone(), p.url.clone()];
+            if let Some(entity) = &p.entity {
+                tag.push(entity.clone());
+            }
+            tags.push(Tag::from(tag));
+        }
+        tags
+    }
+
+    /// Parse handler information from a kind 31990 event
+    ///
+    /// Every tag with an url, other than `d`, `k`, `a`, `e` and `p`, is treated as platform.
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        if event.kind != Kind::Base(KindBase::HandlerInformation) {
+            return Err(Error::InvalidKind);
+        }
+
+        let mut info = Self::new(event.identifier().ok_or(Error::IdentifierNotFound)?);
+        if !event.content.is_empty() {
+            info.metadata = Some(Metadata::from_json(&event.content)?);
+        }
+
+        for tag in event.tags.iter() {
+            let tag = tag.as_vec();
+            match (tag.first().map(|t| t.as_str()), tag.get(1)) {
+                (Some("k"), Some(kind)) => {
+                    if let Ok(kind) = kind.parse::<u64>() {
+                        info.kinds.push(Kind::from(kind));
+                    }
+                }
+                (Some("d" | "a" | "e" | "p"), _) => (),
+                (Some(platform), Some(url)) if url.contains("://") => {
+                    info.platforms.push(HandlerPlatform {
+                        platform: platform.to_string(),
+                        url: url.clone(),
+                        entity: tag.get(2).cloned(),
+                    })
+                }
+                _ => (),
+            }
+        }
+
+        Ok(info)
+    }
+}
+
+/// Recommended handler
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RecommendedHandler {
+    /// Coordinate of the kind 31990 handler information event (with relay hint)
+    pub handler: Coordinate,
+    /// Platform for which the handler is recommended
+    pub platform: Option<String>,
+}
+
+/// Handler recommendation (kind 31989): the handlers recommended by a user for a kind
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/89.md>
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct HandlerRecommendation {
+    /// Recommended kind (`d` tag)
+    pub kind: Kind,
+    pub handlers: Vec<RecommendedHandler>,
+}
+
+impl HandlerRecommendation {
+    /// New `HandlerRecommendation`
+    pub fn new(kind: Kind) -> Self {
+        Self {
+            kind,
+            handlers: Vec::new(),
+        }
+    }
+
+    /// Add recommended handler
+    pub fn handler(mut self, handler: Coordinate, platform: Option<String>) -> Self {
+        self.handlers.push(RecommendedHandler { handler, platform });
+        self
+    }
+
+    /// Get handler recommendation tags
+    pub fn tags(&self) -> Vec<Tag> {
+        let mut tags = vec![Tag::new(TagData::Identifier(
+            u64::from(self.kind).to_string(),
+        ))];
+        for h in self.handlers.iter() {
+            let mut tag = vec![
+                TagKind::A.to_string(),
+                h.handler.to_string(),
+                h.handler.relays.first().cloned().unwrap_or_default(),
+            ];
+            if let Some(platform) = &h.platform {
+                tag.push(platform.clone());
+            }
+            tags.push(Tag::from(tag));
+        }
+        tags
+    }
+
+    /// Parse handler recommendation from a kind 31989 event
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        if event.kind != Kind::Base(KindBase::HandlerRecommendation) {
+            return Err(Error::InvalidKind);
+        }
+
+        let kind = event
+            .identifier()
+            .and_then(|k| k.parse::<u64>().ok())
+            .ok_or(Error::IdentifierNotFound)?;
+        let mut recommendation = Self::new(Kind::from(kind));
+
+        for tag in event.tags.iter() {
+            if !matches!(tag.kind(), Ok(TagKind::A)) {
+                continue;
+            }
+            let tag = tag.as_vec();
+            if let Some(coordinate) = tag.get(1).and_then(|c| Coordinate::from_str(c).ok()) {
+                let relays: Vec<String> = tag
+                    .get(2)
+                    .filter(|r| !r.is_empty())
+                    .cloned()
+                    .into_iter()
+                    .collect();
+                recommendation.handlers.push(RecommendedHandler {
+                    handler: coordinate.relays(relays),
+                    platform: tag.get(3).cloned(),
+                });
+            }
+        }
+
+        Ok(recommendation)
+    }
+
+    /// Filter for the recommendations of handlers for `kind`
+    pub fn filter(kind: Kind) -> SubscriptionFilter {
+        SubscriptionFilter::new()
+            .kind(Kind::Base(KindBase::HandlerRecommendation))
+            .identifier(u64::from(kind).to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::nips::test_util::{build_event, public_key};
+    use crate::{EventBuilder, Keys, Result};
+
+    fn information(content: &str, tags_json: &str) -> Result<HandlerInformation, Error> {
+        HandlerInformation::from_event(&build_event(
+            Kind::Base(KindBase::HandlerInformation),
+            content,
+            tags_json,
+        ))
+    }
+
+    fn recommendation(tags_json: &str) -> Result<HandlerRecommendation, Error> {
+        HandlerRecommendation::from_event(&build_event(
+            Kind::Base(KindBase::HandlerRecommendation),
+            "",
+            tags_json,
+        ))
+    }
+
+    #[test]
+    fn test_handler_information() -> Result<()> {
+        let info = HandlerInformation::new("zapstr")
+            .metadata(Metadata::new().name("zapstr"))
+            .kind(Kind::Custom(31337))
+            .platform(HandlerPlatform::new(
+                "web",
+                "https://zapstr.live/a/<bech32>",
+                Some("naddr"),
+            ));
+        let event =
+            EventBuilder::handler_information(&info)?.to_event(&Keys::generate_from_os_random())?;
+        let parsed = HandlerInformation::from_event(&event)?;
+        assert_eq!(parsed, info);
+        assert!(parsed.handles(Kind::Custom(31337)));
+        assert!(!parsed.handles(Kind::Base(KindBase::TextNote)));
+
+        // Without metadata the profile of the publishing key is used: empty content
+        let info = HandlerInformation::new("zapstr");
+        assert_eq!(info.content()?, "");
+        assert_eq!(information("", r#"[["d", "zapstr"]]"#)?, info);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_handler_information_spec_example() -> Result<()> {
+        assert_eq!(
+            information(
+                r#"{"name":"zapstr"}"#,
+                r#"[
+                    ["d", "1685968093690"],
+                    ["k", "31337"],
+                    ["web", "https://zapstr.live/a/<bech32>", "naddr"],
+                    ["web", "https://zapstr.live/p/<bech32>", "nprofile"],
+                    ["web", "https://zapstr.live/e/<bech32>"],
+                    ["ios", "zapstr://a/<bech32>"]
+                ]"#
+            )?,
+            HandlerInformation::new("1685968093690")
+                .metadata(Metadata::new().name("zapstr"))
+                .kind(Kind::Custom(31337))
+                .platform(HandlerPlatform::new(
+                    "web",
+                    "https://zapstr.live/a/<bech32>",
+                    Some("naddr"),
+                ))
+                .platform(HandlerPlatform::new(
+                    "web",
+                    "https://zapstr.live/p/<bech32>",
+                    Some("nprofile"),
+                ))
+                .platform(HandlerPlatform::new(
+                    "web",
+                    "https://zapstr.live/e/<bech32>",
+                    None,
+                ))
+                .platform(HandlerPlatform::new("ios", "zapstr://a/<bech32>", None))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_platforms() -> Result<()> {
+        // Only tags with an url are platforms, reference tags never are
+        let info = information(
+            "",
+            r#"[
+                ["d", "zapstr"],
+                ["k", "note"],
+                ["k"],
+                ["web"],
+                ["web", "zapstr.live"],
+                ["p", "https://zapstr.live"],
+                ["e", "https://zapstr.live"],
+                ["android", "zapstr://a/<bech32>", "naddr"]
+            ]"#,
+        )?;
+        assert!(info.kinds.is_empty());
+        assert_eq!(
+            info.platforms,
+            vec![HandlerPlatform::new(
+                "android",
+                "zapstr://a/<bech32>",
+                Some("naddr")
+            )]
+        );
+
+        assert!(matches!(
+            information("", "[]"),
+            Err(Error::IdentifierNotFound)
+        ));
+        assert!(matches!(
+            information("not json", r#"[["d", "zapstr"]]"#),
+            Err(Error::Metadata(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_handler_recommendation() -> Result<()> {
+        let app = public_key();
+        let handler = Coordinate::new(Kind::Base(KindBase::HandlerInformation), app, "zapstr");
+
+        let recommendation = HandlerRecommendation::new(Kind::Custom(31337))
+            .handler(
+                handler.clone().relays(vec!["wss://relay.damus.io"]),
+                Some(String::from("web")),
+            )
+            // Platform without relay hint
+            .handler(handler.clone(), Some(String::from("ios")));
+        let event = EventBuilder::handler_recommendation(&recommendation)
+            .to_event(&Keys::generate_from_os_random())?;
+        assert_eq!(event.identifier(), Some("31337"));
+        assert_eq!(
+            event.tags[2].as_vec(),
+            vec![
+                String::from("a"),
+                handler.to_string(),
+                String::new(),
+                String::from("ios")
+            ]
+        );
+        assert_eq!(HandlerRecommendation::from_event(&event)?, recommendation);
+
+        assert!(HandlerRecommendation::filter(Kind::Custom(31337)).match_event(&event));
+        assert!(!HandlerRecommendation::filter(Kind::Custom(31338)).match_event(&event));
+        assert!(matches!(
+            HandlerInformation::from_event(&event),
+            Err(Error::InvalidKind)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_handler_recommendation_spec_example() -> Result<()> {
+        let app = public_key();
+        let app1 = format!("31990:{}:1685968093690", app);
+        let app2 = format!("31990:{}:ios", app);
+        assert_eq!(
+            recommendation(&format!(
+                r#"[
+                    ["d", "31337"],
+                    ["a", "{}", "wss://relay1", "ios"],
+                    ["a", "{}", "wss://relay2", "web"]
+                ]"#,
+                app2, app1
+            ))?,
+            HandlerRecommendation::new(Kind::Custom(31337))
+                .handler(
+                    Coordinate::from_str(&app2)?.relays(vec!["wss://relay1"]),
+                    Some(String::from("ios")),
+                )
+                .handler(
+                    Coordinate::from_str(&app1)?.relays(vec!["wss://relay2"]),
+                    Some(String::from("web")),
+                )
+        );
+
+        // The identifier is the recommended kind
+        for tags in [r#"[]"#, r#"[["d", "zapstr"]]"#] {
+            assert!(matches!(
+                recommendation(tags),
+                Err(Error::IdentifierNotFound)
+            ));
+        }
+
+        // Invalid handlers are skipped
+        let handler = format!("31990:{}:zapstr", app);
+        assert_eq!(
+            recommendation(&format!(
+                r#"[["d", "31337"], ["a"], ["a", "31990:not a public key:zapstr"], ["a", "{}"]]"#,
+                handler
+            ))?,
+            HandlerRecommendation::new(Kind::Custom(31337))
+                .handler(Coordinate::from_str(&handler)?, None)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,781 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use bitcoin::secp256k1::XOnlyPublicKey;
+
+use crate::event::{self, TagKind};
+use crate::{Event, Kind, KindBase, Sha256Hash, Tag};
+
+/// Job request kinds
+pub const JOB_REQUEST_RANGE: RangeInclusive<u64> = 5000..=5999;
+/// Job result kinds
+pub const JOB_RESULT_RANGE: RangeInclusive<u64> = 6000..=6999;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Event error
+    #[error("event error: {0}")]
+    Event(#[from] event::Error),
+    /// Invalid event kind
+    #[error("invalid event kind")]
+    InvalidKind,
+    /// Unknown job input type
+    #[error("unknown input type")]
+    UnknownInputType,
+    /// Unknown job status
+    #[error("unknown status")]
+    UnknownStatus,
+    /// Job request `e` tag or customer `p` tag not found
+    #[error("job request not found")]
+    RequestNotFound,
+}
+
+/// Job input type
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum JobInputType {
+    /// Url to be fetched
+    Url,
+    /// Event id
+    Event,
+    /// Output of a previous job (job request event id)
+    Job,
+    /// Raw text
+    Text,
+}
+
+impl fmt::Display for JobInputType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Url => write!(f, "url"),
+            Self::Event => write!(f, "event"),
+            Self::Job => write!(f, "job"),
+            Self::Text => write!(f, "text"),
+        }
+    }
+}
+
+impl FromStr for JobInputType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "url" => Ok(Self::Url),
+            "event" => Ok(Self::Event),
+            "job" => Ok(Self::Job),
+            "text" => Ok(Self::Text),
+            _ => Err(Error::UnknownInputType),
+        }
+    }
+}
+
+/// Job input (`i` tag)
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct JobInput {
+    pub data: String,
+    pub input_type: JobInputType,
+    /// Relay where the input event or job can be found
+    pub relay: Option<String>,
+    /// How the input should be used by the job
+    pub marker: Option<String>,
+}
+
+impl JobInput {
+    /// New `JobInput`
+    pub fn new<S>(data: S, input_type: JobInputType) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            data: data.into(),
+            input_type,
+            relay: None,
+            marker: None,
+        }
+    }
+
+    /// Set relay hint
+    pub fn relay<S>(self, relay: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            relay: Some(relay.into()),
+            ..self
+        }
+    }
+
+    /// Set marker
+    pub fn marker<S>(self, marker: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            marker: Some(marker.into()),
+            ..self
+        }
+    }
+
+    fn tag(&self) -> Tag {
+        let mut tag = vec![
+            String::from("i"),
+            self.data.clone(),
+            self.input_type.to_string(),
+        ];
+        if self.relay.is_some() || self.marker.is_some() {
+            tag.push(self.relay.clone().unwrap_or_default());
+        }
+        if let Some(marker) = &self.marker {
+            tag.push(marker.clone());
+        }
+        Tag::from(tag)
+    }
+
+    fn from_tag(tag: &[String]) -> Result<Self, Error> {
+        Ok(Self {
+            data: tag.get(1).cloned().unwrap_or_default(),
+            input_type: tag.get(2).ok_or(Error::UnknownInputType)?.parse()?,
+            relay: tag.get(3).filter(|r| !r.is_empty()).cloned(),
+            marker: tag.get(4).cloned(),
+        })
+    }
+}
+
+/// Job request (kinds 5000-5999)
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/90.md>
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct JobRequest {
+    pub kind: Kind,
+    pub inputs: Vec<JobInput>,
+    /// Expected output MIME type
+    pub output: Option<String>,
+    /// Job parameters (`param` tags)
+    pub params: Vec<(String, String)>,
+    /// Max amount the customer is willing to pay (millisats)
+    pub bid: Option<u64>,
+    /// Relays where the service providers should publish the results
+    pub relays: Vec<String>,
+    /// Service providers the customer is interested in (all, if empty)
+    pub service_providers: Vec<XOnlyPublicKey>,
+}
+
+impl JobRequest {
+    /// New `JobRequest`
+    ///
+    /// Return [`Error::InvalidKind`] if `kind` is not a job request kind.
+    pub fn new(kind: Kind) -> Result<Self, Error> {
+        if !kind.is_job_request() {
+            return Err(Error::InvalidKind);
+        }
+
+        Ok(Self {
+            kind,
+            inputs: Vec::new(),
+            output: None,
+            params: Vec::new(),
+            bid: None,
+            relays: Vec::new(),
+            service_providers: Vec::new(),
+        })
+    }
+
+    /// Add input
+    pub fn input(mut self, input: JobInput) -> Self {
+        self.inputs.push(input);
+        self
+    }
+
+    /// Set output MIME type
+    pub fn output<S>(self, output: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            output: Some(output.into()),
+            ..self
+        }
+    }
+
+    /// Add parameter
+    pub fn param<S>(mut self, name: S, value: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.params.push((name.into(), value.into()));
+        self
+    }
+
+    /// Set bid (millisats)
+    pub fn bid(self, bid: u64) -> Self {
+        Self {
+            bid: Some(bid),
+            ..self
+        }
+    }
+
+    /// Add relay for the results
+    pub fn relay<S>(mut self, relay: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.relays.push(relay.into());
+        self
+    }
+
+    /// Add service provider
+    pub fn service_provider(mut self, public_key: XOnlyPublicKey) -> Self {
+        self.service_providers.push(public_key);
+        self
+    }
+
+    /// Kind of the results of this job
+    pub fn result_kind(&self) -> Kind {
+        Kind::from(u64::from(self.kind) + 1000)
+    }
+
+    /// Get job request tags
+    pub fn tags(&self) -> Vec<Tag> {
+        let mut tags: Vec<Tag> = self.inputs.iter().map(|i| i.tag()).collect();
+        if let Some(output) = &self.output {
+            tags.push(Tag::from(vec![String::from("output"), output.clone()]));
+        }
+        for (name, value) in self.params.iter() {
+            tags.push(Tag::from(vec![
+                String::from("param"),
+                name.clone(),
+                value.clone(),
+            ]));
+        }
+        if let Some(bid) = self.bid {
+            tags.push(Tag::from(vec![String::from("bid"), bid.to_string()]));
+        }
+        if !self.relays.is_empty() {
+            let mut tag = vec![String::from("relays")];
+            tag.extend(self.relays.iter().cloned());
+            tags.push(Tag::from(tag));
+        }
+        for pk in self.service_providers.iter() {
+            tags.push(Tag::from(vec![TagKind::P.to_string(), pk.to_string()]));
+        }
+        tags
+    }
+
+    /// Parse job request from a kind 5000-5999 event
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        let mut request = Self::new(event.kind)?;
+
+        for tag in event.tags.iter() {
+            let tag = tag.as_vec();
+            match tag.first().map(|t| t.as_str()) {
+                Some("i") => request.inputs.push(JobInput::from_tag(&tag)?),
+                Some("output") => request.output = tag.get(1).cloned(),
+                Some("param") => {
+                    if let (Some(name), Some(value)) = (tag.get(1), tag.get(2)) {
+                        request.params.push((name.clone(), value.clone()));
+                    }
+                }
+                Some("bid") => request.bid = tag.get(1).and_then(|b| b.parse().ok()),
+                Some("relays") => request.relays = tag.into_iter().skip(1).collect(),
+                Some("p") => {
+                    if let Some(pk) = tag.get(1).and_then(|pk| XOnlyPublicKey::from_str(pk).ok()) {
+                        request.service_providers.push(pk);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        Ok(request)
+    }
+}
+
+/// Payment requested by the service provider (`amount` tag)
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct JobAmount {
+    /// Millisats
+    pub millisats: u64,
+    pub bolt11: Option<String>,
+}
+
+impl JobAmount {
+    fn tag(&self) -> Tag {
+        let mut tag = vec![String::from("amount"), self.millisats.to_string()];
+        if let Some(bolt11) = &self.bolt11 {
+            tag.push(bolt11.clone());
+        }
+        Tag::from(tag)
+    }
+
+    fn from_event(event: &Event) -> Option<Self> {
+        let tag = event
+            .tags
+            .iter()
+            .map(|t| t.as_vec())
+            .find(|t| t.first().map(|t| t.as_str()) == Some("amount"))?;
+        Some(Self {
+            millisats: tag.get(1)?.parse().ok()?,
+            bolt11: tag.get(2).cloned(),
+        })
+    }
+}
+
+/// Get the tags referencing the job request, common to results and feedbacks
+fn request_tags(request: &Event) -> Vec<Tag> {
+    vec![
+        Tag::from(vec![TagKind::E.to_string(), request.id.to_string()]),
+        Tag::from(vec![TagKind::P.to_string(), request.pubkey.to_string()]),
+    ]
+}
+
+/// Get the job request id and the customer from a result or feedback
+fn request_of(event: &Event) -> Result<(Sha256Hash, XOnlyPublicKey), Error> {
+    let tag_value = |kind: TagKind| -> Option<String> {
+        event
+            .tags
+            .iter()
+            .find(|t| t.kind().ok().as_ref() == Some(&kind))
+            .and_then(|t| t.content().map(|c| c.to_string()))
+    };
+
+    Ok((
+        tag_value(TagKind::E)
+            .and_then(|id| Sha256Hash::from_str(&id).ok())
+            .ok_or(Error::RequestNotFound)?,
+        tag_value(TagKind::P)
+            .and_then(|pk| XOnlyPublicKey::from_str(&pk).ok())
+            .ok_or(Error::RequestNotFound)?,
+    ))
+}
+
+/// Job result (kinds 6000-6999)
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/90.md>
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct JobResult {
+    pub request_id: Sha256Hash,
+    pub customer: XOnlyPublicKey,
+    pub amount: Option<JobAmount>,
+    /// Job output (event content)
+    pub payload: String,
+}
+
+impl JobResult {
+    /// Get job result tags
+    pub fn tags(request: &Event, amount: Option<JobAmount>) -> Result<Vec<Tag>, Error> {
+        let mut tags = vec![Tag::from(vec![String::from("request"), request.as_json()?])];
+        tags.extend(request_tags(request));
+        tags.extend(
+            request
+                .tags
+                .iter()
+                .filter(|t| matches!(t.kind(), Ok(TagKind::Custom(k)) if k == "i"))
+                .cloned(),
+        );
+        if let Some(amount) = amount {
+            tags.push(amount.tag());
+        }
+        Ok(tags)
+    }
+
+    /// Parse job result from a kind 6000-6999 event
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        if !event.kind.is_job_result() {
+            return Err(Error::InvalidKind);
+        }
+
+        let (request_id, customer) = request_of(event)?;
+        Ok(Self {
+            request_id,
+            customer,
+            amount: JobAmount::from_event(event),
+            payload: event.content.clone(),
+        })
+    }
+}
+
+/// Job status
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum JobStatus {
+    /// The service provider requires payment before continuing
+    PaymentRequired,
+    Processing,
+    Error,
+    Success,
+    /// Partial result (in the feedback content)
+    Partial,
+}
+
+impl fmt::Display for JobStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::PaymentRequired => write!(f, "payment-required"),
+            Self::Processing => write!(f, "processing"),
+            Self::Error => write!(f, "error"),
+            Self::Success => write!(f, "success"),
+            Self::Partial => write!(f, "partial"),
+        }
+    }
+}
+
+impl FromStr for JobStatus {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "payment-required" => Ok(Self::PaymentRequired),
+            "processing" => Ok(Self::Processing),
+            "error" => Ok(Self::Error),
+            "success" => Ok(Self::Success),
+            "partial" => Ok(Self::Partial),
+            _ => Err(Error::UnknownStatus),
+        }
+    }
+}
+
+/// Job feedback (kind 7000)
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/90.md>
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct JobFeedback {
+    pub request_id: Sha256Hash,
+    pub customer: XOnlyPublicKey,
+    pub status: JobStatus,
+    /// Human readable status information
+    pub extra_info: Option<String>,
+    pub amount: Option<JobAmount>,
+    /// Partial result (event content)
+    pub payload: String,
+}
+
+impl JobFeedback {
+    /// Get job feedback tags
+    pub fn tags(
+        request: &Event,
+        status: JobStatus,
+        extra_info: Option<String>,
+        amount: Option<JobAmount>,
+    ) -> Vec<Tag> {
+        let mut status = vec![String::from("status"), status.to_string()];
+        if let Some(extra_info) = extra_info {
+            status.push(extra_info);
+        }
+        let mut tags = vec![Tag::from(status)];
+        tags.extend(request_tags(request));
+        if let Some(amount) = amount {
+            tags.push(amount.tag());
+        }
+        tags
+    }
+
+    /// Parse job feedback from a kind 7000 event
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        if event.kind != Kind::Base(KindBase::JobFeedback) {
+            return Err(Error::InvalidKind);
+        }
+
+        let status = event
+            .tags
+            .iter()
+            .map(|t| t.as_vec())
+            .find(|t| t.first().map(|t| t.as_str()) == Some("status"))
+            .ok_or(Error::UnknownStatus)?;
+
+        let (request_id, customer) = request_of(event)?;
+        Ok(Self {
+            request_id,
+            customer,
+            status: status.get(1).ok_or(Error::UnknownStatus)?.parse()?,
+            extra_info: status.get(2).cloned(),
+            amount: JobAmount::from_event(event),
+            payload: event.content.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::nips::test_util::{build_event, public_key};
+    use crate::{EventBuilder, Keys, Result};
+
+    const REQUEST_ID: &str = "b3e392b11f5d4f28321cedd09303a748acfd0487aea5a7450b3481c60b6e4f87";
+
+    fn request(tags_json: &str) -> Result<JobRequest, Error> {
+        JobRequest::from_event(&build_event(Kind::Custom(5002), "", tags_json))
+    }
+
+    fn feedback(tags_json: &str) -> Result<JobFeedback, Error> {
+        JobFeedback::from_event(&build_event(
+            Kind::Base(KindBase::JobFeedback),
+            "",
+            tags_json,
+        ))
+    }
+
+    #[test]
+    fn test_job() -> Result<()> {
+        let customer = Keys::generate_from_os_random();
+        let provider = Keys::generate_from_os_random();
+
+        let job = JobRequest::new(Kind::Custom(5001))?
+            .input(JobInput::new(
+                "https://example.com/podcast.mp3",
+                JobInputType::Url,
+            ))
+            .output("text/plain")
+            .param("lang", "en")
+            .bid(5000)
+            .relay("wss://relay.damus.io")
+            .service_provider(provider.public_key());
+        let request = EventBuilder::job_request(&job).to_event(&customer)?;
+        assert_eq!(JobRequest::from_event(&request)?, job);
+
+        let result = EventBuilder::job_result(&request, "A podcast about nostr", None)?
+            .to_event(&provider)?;
+        assert_eq!(result.kind, job.result_kind());
+        assert_eq!(
+            JobResult::from_event(&result)?,
+            JobResult {
+                request_id: request.id,
+                customer: customer.public_key(),
+                amount: None,
+                payload: String::from("A podcast about nostr"),
+            }
+        );
+
+        // The result embeds the request and copies its inputs
+        let embedded = result
+            .tags
+            .iter()
+            .find(|t| matches!(t.kind(), Ok(TagKind::Custom(k)) if k == "request"))
+            .and_then(|t| t.content())
+            .unwrap();
+        assert_eq!(Event::from_json(embedded)?, request);
+        assert!(result.tags.contains(&request.tags[0]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_job_kinds() -> Result<()> {
+        for kind in [4999, 6000, 7000] {
+            assert!(matches!(
+                JobRequest::new(Kind::from(kind)),
+                Err(Error::InvalidKind)
+            ));
+        }
+        assert_eq!(
+            JobRequest::new(Kind::from(5000))?.result_kind(),
+            Kind::from(6000)
+        );
+        assert_eq!(
+            JobRequest::new(Kind::from(5999))?.result_kind(),
+            Kind::from(6999)
+        );
+
+        // A request isn't a result, a result isn't a request
+        let event = build_event(
+            Kind::Custom(5002),
+            "",
+            &format!(r#"[["e", "{}"], ["p", "{}"]]"#, REQUEST_ID, public_key()),
+        );
+        assert!(matches!(
+            JobResult::from_event(&event),
+            Err(Error::InvalidKind)
+        ));
+        let event = build_event(Kind::Custom(6002), "", "[]");
+        assert!(matches!(
+            JobRequest::from_event(&event),
+            Err(Error::InvalidKind)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_job_inputs() -> Result<()> {
+        // A marker without relay keeps the relay position
+        let input = JobInput::new("Summarize", JobInputType::Text).marker("prompt");
+        assert_eq!(
+            input.tag().as_vec(),
+            vec!["i", "Summarize", "text", "", "prompt"]
+        );
+        assert_eq!(JobInput::from_tag(&input.tag().as_vec())?, input);
+
+        // Output of a previous job
+        let job = JobRequest::new(Kind::Custom(5002))?
+            .input(JobInput::new(REQUEST_ID, JobInputType::Job).relay("wss://relay.damus.io"));
+        assert_eq!(
+            request(&format!(
+                r#"[["i", "{}", "job", "wss://relay.damus.io"]]"#,
+                REQUEST_ID
+            ))?,
+            job
+        );
+
+        // A request with an unknown input can't be processed
+        for tags in [
+            r#"[["i", "https://bitcoin.org/bitcoin.pdf"]]"#,
+            r#"[["i", "https://bitcoin.org/bitcoin.pdf", "pdf"]]"#,
+        ] {
+            assert!(matches!(request(tags), Err(Error::UnknownInputType)));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_job_request_spec_example() -> Result<()> {
+        let provider = public_key();
+        assert_eq!(
+            request(&format!(
+                r#"[
+                    ["i", "https://bitcoin.org/bitcoin.pdf", "url"],
+                    ["i", "{}", "event", "wss://relay.damus.io", "source"],
+                    ["param", "lang", "es"],
+                    ["output", "text/plain"],
+                    ["bid", "5000"],
+                    ["relays", "wss://relay.damus.io", "wss://nos.lol"],
+                    ["p", "{}"],
+                    ["t", "bitcoin"]
+                ]"#,
+                REQUEST_ID, provider
+            ))?,
+            JobRequest::new(Kind::Custom(5002))?
+                .input(JobInput::new(
+                    "https://bitcoin.org/bitcoin.pdf",
+                    JobInputType::Url
+                ))
+                .input(
+                    JobInput::new(REQUEST_ID, JobInputType::Event)
+                        .relay("wss://relay.damus.io")
+                        .marker("source")
+                )
+                .param("lang", "es")
+                .output("text/plain")
+                .bid(5000)
+                .relay("wss://relay.damus.io")
+                .relay("wss://nos.lol")
+                .service_provider(provider)
+        );
+
+        // Invalid params, bids and service providers are skipped
+        assert_eq!(
+            request(r#"[["param", "lang"], ["bid", "5000 msats"], ["p", "not a public key"]]"#)?,
+            JobRequest::new(Kind::Custom(5002))?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_job_feedback() -> Result<()> {
+        let customer = public_key();
+        let request_id = Sha256Hash::from_str(REQUEST_ID)?;
+
+        assert_eq!(
+            feedback(&format!(
+                r#"[
+                    ["status", "payment-required", "Pay first"],
+                    ["amount", "5000", "lnbc50n1..."],
+                    ["e", "{}", "wss://relay.damus.io"],
+                    ["p", "{}"]
+                ]"#,
+                REQUEST_ID, customer
+            ))?,
+            JobFeedback {
+                request_id,
+                customer,
+                status: JobStatus::PaymentRequired,
+                extra_info: Some(String::from("Pay first")),
+                amount: Some(JobAmount {
+                    millisats: 5000,
+                    bolt11: Some(String::from("lnbc50n1...")),
+                }),
+                payload: String::new(),
+            }
+        );
+
+        // Partial results are in the content
+        let request = build_event(Kind::Custom(5002), "", "[]");
+        let event = EventBuilder::new(
+            Kind::Base(KindBase::JobFeedback),
+            "Bitcoin: un",
+            &JobFeedback::tags(&request, JobStatus::Partial, None, None),
+        )
+        .to_event(&Keys::generate_from_os_random())?;
+        let partial = JobFeedback::from_event(&event)?;
+        assert_eq!(partial.status, JobStatus::Partial);
+        assert_eq!(partial.payload, "Bitcoin: un");
+        assert_eq!(partial.customer, request.pubkey);
+
+        for tags in [
+            format!(r#"[["e", "{}"], ["p", "{}"]]"#, REQUEST_ID, customer),
+            format!(
+                r#"[["status"], ["e", "{}"], ["p", "{}"]]"#,
+                REQUEST_ID, customer
+            ),
+            format!(
+                r#"[["status", "done"], ["e", "{}"], ["p", "{}"]]"#,
+                REQUEST_ID, customer
+            ),
+        ] {
+            assert!(matches!(feedback(&tags), Err(Error::UnknownStatus)));
+        }
+        assert!(matches!(
+            feedback(r#"[["status", "processing"]]"#),
+            Err(Error::RequestNotFound)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_job_result_references() -> Result<()> {
+        let customer = public_key();
+        for tags in [
+            format!(r#"[["p", "{}"]]"#, customer),
+            format!(r#"[["e", "{}"]]"#, REQUEST_ID),
+            format!(r#"[["e", "not an event id"], ["p", "{}"]]"#, customer),
+            format!(r#"[["e", "{}"], ["p", "not a public key"]]"#, REQUEST_ID),
+        ] {
+            let event = build_event(Kind::Custom(6002), "", &tags);
+            assert!(matches!(
+                JobResult::from_event(&event),
+                Err(Error::RequestNotFound)
+            ));
+        }
+
+        // Amount without invoice, invalid amounts are ignored
+        let event = build_event(
+            Kind::Custom(6002),
+            "",
+            &format!(
+                r#"[["e", "{}"], ["p", "{}"], ["amount", "5000"]]"#,
+                REQUEST_ID, customer
+            ),
+        );
+        assert_eq!(
+            JobResult::from_event(&event)?.amount,
+            Some(JobAmount {
+                millisats: 5000,
+                bolt11: None
+            })
+        );
+        let event = build_event(
+            Kind::Custom(6002),
+            "",
+            &format!(
+                r#"[["e", "{}"], ["p", "{}"], ["amount", "5 sats"]]"#,
+                REQUEST_ID, customer
+            ),
+        );
+        assert_eq!(JobResult::from_event(&event)?.amount, None);
+
+        Ok(())
+    }
+}
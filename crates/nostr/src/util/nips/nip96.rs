@@ -0,0 +1,222 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use url::Url;
+
+use crate::Tag;
+
+/// Path of the server configuration document
+pub const WELL_KNOWN_PATH: &str = "/.well-known/nostr/nip96.json";
+
+/// Get the url of the server configuration document
+pub fn well_known_url(server_url: &Url) -> Result<Url, url::ParseError> {
+    server_url.join(WELL_KNOWN_PATH)
+}
+
+/// HTTP file storage server configuration
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/96.md>
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerConfig {
+    /// Url of the upload API
+    pub api_url: Url,
+    /// Url from which the files are served, if different from `api_url`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub download_url: Option<Url>,
+    /// Server that actually handles the uploads (the `api_url` is ignored)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delegated_to_url: Option<Url>,
+    #[serde(default)]
+    pub supported_nips: Vec<u16>,
+    /// Terms of service
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tos_url: Option<Url>,
+    /// Accepted MIME types (empty if any)
+    #[serde(default)]
+    pub content_types: Vec<String>,
+}
+
+/// Upload status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UploadStatus {
+    Success,
+    Error,
+    Processing,
+}
+
+/// NIP-94 file metadata returned by the server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Nip94Event {
+    pub tags: Vec<Tag>,
+    #[serde(default)]
+    pub content: String,
+}
+
+impl Nip94Event {
+    /// Get the value of the first tag of `kind`
+    pub fn tag_value(&self, kind: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|t| t.kind().map(|k| k.to_string() == kind).unwrap_or(false))
+            .and_then(|t| t.content())
+    }
+
+    /// Get the url of the uploaded file (`url` tag)
+    pub fn url(&self) -> Option<Url> {
+        self.tag_value("url").and_then(|url| Url::parse(url).ok())
+    }
+
+    /// Get the SHA-256 hash of the original file (`ox` tag)
+    pub fn original_hash(&self) -> Option<&str> {
+        self.tag_value("ox")
+    }
+}
+
+/// Upload response
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadResponse {
+    pub status: UploadStatus,
+    #[serde(default)]
+    pub message: String,
+    /// Url to poll while the file is being processed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub processing_url: Option<Url>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nip94_event: Option<Nip94Event>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Result;
+
+    #[test]
+    fn test_upload_response() -> Result<()> {
+        let server = Url::parse("https://nostr.build/api")?;
+        assert_eq!(
+            well_known_url(&server)?.as_str(),
+            "https://nostr.build/.well-known/nostr/nip96.json"
+        );
+
+        let config: ServerConfig = serde_json::from_str(
+            r#"{"api_url":"https://nostr.build/api/v2/nip96/upload","download_url":"https://media.nostr.build","supported_nips":[94,96,98],"content_types":["image/jpeg"]}"#,
+        )?;
+        assert_eq!(config.supported_nips, vec![94, 96, 98]);
+        assert!(config.delegated_to_url.is_none());
+
+        let response: UploadResponse = serde_json::from_str(
+            r#"{"status":"success","message":"Upload successful.","nip94_event":{"tags":[["url","https://image.nostr.build/abc.jpg"],["ox","719171db19525d9d08dd69cb716a18158a249b7b3b3ec4bbdec5698dca104b7b"],["m","image/jpeg"]],"content":""}}"#,
+        )?;
+        assert_eq!(response.status, UploadStatus::Success);
+        let event = response.nip94_event.unwrap();
+        assert_eq!(
+            event.url(),
+            Some(Url::parse("https://image.nostr.build/abc.jpg")?)
+        );
+        assert_eq!(event.tag_value("m"), Some("image/jpeg"));
+        assert_eq!(
+            event.original_hash(),
+            Some("719171db19525d9d08dd69cb716a18158a249b7b3b3ec4bbdec5698dca104b7b")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_upload_response_spec_examples() -> Result<()> {
+        let config: ServerConfig = serde_json::from_str(
+            r#"{
+                "api_url": "https://your-file-server.example/custom-api-path",
+                "download_url": "https://a-cdn.example/a-path",
+                "delegated_to_url": "https://your-file-server.example",
+                "supported_nips": [60],
+                "tos_url": "https://your-file-server.example/terms-of-service",
+                "content_types": ["image/jpeg", "video/webm", "audio/*"],
+                "plans": {
+                    "free": {
+                        "name": "Free Tier",
+                        "is_nip98_required": true,
+                        "max_byte_size": 10485760,
+                        "file_expiration": [14, 90]
+                    }
+                }
+            }"#,
+        )?;
+        assert_eq!(
+            config,
+            ServerConfig {
+                api_url: Url::parse("https://your-file-server.example/custom-api-path")?,
+                download_url: Some(Url::parse("https://a-cdn.example/a-path")?),
+                delegated_to_url: Some(Url::parse("https://your-file-server.example")?),
+                supported_nips: vec![60],
+                tos_url: Some(Url::parse(
+                    "https://your-file-server.example/terms-of-service"
+                )?),
+                content_types: vec![
+                    String::from("image/jpeg"),
+                    String::from("video/webm"),
+                    String::from("audio/*"),
+                ],
+            }
+        );
+
+        let response: UploadResponse = serde_json::from_str(
+            r#"{
+                "status": "processing",
+                "message": "Processing. Please check again later for updated status.",
+                "processing_url": "https://your-file-server.example/custom-api-path/processing/719171db",
+                "nip94_event": {
+                    "tags": [
+                        ["url", "https://your-file-server.example/custom-api-path/719171db19525d9d08dd69cb716a18158a249b7b3b3ec4bbdec5698dca104b7b.png"],
+                        ["ox", "719171db19525d9d08dd69cb716a18158a249b7b3b3ec4bbdec5698dca104b7b"],
+                        ["x", "543244319525d9d08dd69cb716a18158a249b7b3b3ec4bbde5435543acb34443"],
+                        ["m", "image/png"],
+                        ["dim", "800x600"]
+                    ],
+                    "content": ""
+                }
+            }"#,
+        )?;
+        assert_eq!(response.status, UploadStatus::Processing);
+        assert!(response.processing_url.is_some());
+        let event = response.nip94_event.unwrap();
+        assert_eq!(event.tag_value("dim"), Some("800x600"));
+        assert_eq!(
+            event.original_hash(),
+            Some("719171db19525d9d08dd69cb716a18158a249b7b3b3ec4bbdec5698dca104b7b")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_upload_response_invalid() -> Result<()> {
+        for json in [
+            r#"{"download_url": "https://a-cdn.example"}"#,
+            r#"{"api_url": "custom-api-path"}"#,
+            r#"{"api_url": "https://your-file-server.example", "supported_nips": ["96"]}"#,
+        ] {
+            assert!(serde_json::from_str::<ServerConfig>(json).is_err());
+        }
+
+        for json in [
+            r#"{"message": "Upload successful."}"#,
+            r#"{"status": "uploaded"}"#,
+            r#"{"status": "success", "nip94_event": {"content": ""}}"#,
+        ] {
+            assert!(serde_json::from_str::<UploadResponse>(json).is_err());
+        }
+
+        let response: UploadResponse = serde_json::from_str(
+            r#"{"status": "error", "message": "File too large", "nip94_event": {"tags": [["url", "abc.jpg"], ["ox"]]}}"#,
+        )?;
+        assert_eq!(response.status, UploadStatus::Error);
+        let event = response.nip94_event.unwrap();
+        assert_eq!(event.url(), None);
+        assert_eq!(event.original_hash(), None);
+        assert_eq!(event.tag_value("m"), None);
+
+        Ok(())
+    }
+}
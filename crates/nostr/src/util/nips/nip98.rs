@@ -0,0 +1,440 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::fmt;
+use std::str::FromStr;
+
+use bitcoin::hashes::sha256::Hash as Sha256Hash;
+use bitcoin::hashes::Hash;
+use url::Url;
+
+use crate::event::{self, TagKind};
+use crate::util::time::timestamp;
+use crate::{Event, Kind, KindBase, Tag};
+
+/// Authorization header scheme
+pub const AUTH_SCHEME: &str = "Nostr";
+/// Max difference between the auth event `created_at` and the current time (seconds)
+pub const TIME_WINDOW: u64 = 60;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Base64 decoding error
+    #[error("base64 error: {0}")]
+    Base64(#[from] base64::DecodeError),
+    /// Event error
+    #[error("event error: {0}")]
+    Event(#[from] event::Error),
+    /// Invalid `Authorization` header
+    #[error("invalid authorization header")]
+    InvalidHeader,
+    /// Invalid event kind
+    #[error("invalid event kind")]
+    InvalidKind,
+    /// Unknown HTTP method
+    #[error("unknown method")]
+    UnknownMethod,
+    /// Auth event created too far from now
+    #[error("invalid created_at")]
+    InvalidTimestamp,
+    /// The auth event is for another url
+    #[error("url mismatch")]
+    UrlMismatch,
+    /// The auth event is for another method
+    #[error("method mismatch")]
+    MethodMismatch,
+    /// The request body doesn't match the auth event payload hash
+    #[error("payload mismatch")]
+    PayloadMismatch,
+}
+
+/// HTTP method
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum HttpMethod {
+    GET,
+    POST,
+    PUT,
+    PATCH,
+    DELETE,
+}
+
+impl fmt::Display for HttpMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::GET => write!(f, "GET"),
+            Self::POST => write!(f, "POST"),
+            Self::PUT => write!(f, "PUT"),
+            Self::PATCH => write!(f, "PATCH"),
+            Self::DELETE => write!(f, "DELETE"),
+        }
+    }
+}
+
+impl FromStr for HttpMethod {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "GET" => Ok(Self::GET),
+            "POST" => Ok(Self::POST),
+            "PUT" => Ok(Self::PUT),
+            "PATCH" => Ok(Self::PATCH),
+            "DELETE" => Ok(Self::DELETE),
+            _ => Err(Error::UnknownMethod),
+        }
+    }
+}
+
+/// Data of an HTTP auth event (kind 27235)
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/98.md>
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct HttpData {
+    /// Absolute request url, including query parameters
+    pub url: Url,
+    pub method: HttpMethod,
+    /// SHA-256 hash of the request body
+    pub payload: Option<Sha256Hash>,
+}
+
+impl HttpData {
+    /// New `HttpData`
+    pub fn new(url: Url, method: HttpMethod) -> Self {
+        Self {
+            url,
+            method,
+            payload: None,
+        }
+    }
+
+    /// Set the hash of the request body
+    pub fn payload(self, payload: Sha256Hash) -> Self {
+        Self {
+            payload: Some(payload),
+            ..self
+        }
+    }
+
+    /// Get HTTP auth tags
+    pub fn tags(&self) -> Vec<Tag> {
+        let mut tags = vec![
+            Tag::from(vec![String::from("u"), self.url.to_string()]),
+            Tag::from(vec![String::from("method"), self.method.to_string()]),
+        ];
+        if let Some(payload) = self.payload {
+            tags.push(Tag::from(vec![
+                String::from("payload"),
+                payload.to_string(),
+            ]));
+        }
+        tags
+    }
+
+    /// Parse HTTP auth data from a kind 27235 event
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        if event.kind != Kind::Base(KindBase::HttpAuth) {
+            return Err(Error::InvalidKind);
+        }
+
+        let tag_value = |kind: &str| -> Option<&str> {
+            event
+                .tags
+                .iter()
+                .find(|t| matches!(t.kind(), Ok(TagKind::Custom(k)) if k == kind))
+                .and_then(|t| t.content())
+        };
+
+        Ok(Self {
+            url: tag_value("u")
+                .and_then(|u| Url::parse(u).ok())
+                .ok_or(Error::UrlMismatch)?,
+            method: tag_value("method").ok_or(Error::UnknownMethod)?.parse()?,
+            payload: match tag_value("payload") {
+                Some(payload) => {
+                    Some(Sha256Hash::from_str(payload).map_err(|_| Error::PayloadMismatch)?)
+                }
+                None => None,
+            },
+        })
+    }
+}
+
+/// Encode the HTTP auth event as `Authorization` header value (`Nostr <base64>`)
+pub fn authorization_header(event: &Event) -> Result<String, Error> {
+    Ok(format!(
+        "{} {}",
+        AUTH_SCHEME,
+        base64::encode(event.as_json()?)
+    ))
+}
+
+/// Verify the `Authorization` header of an HTTP request
+///
+/// Check the signature, the kind and the `created_at` of the auth event and that it was
+/// made for this `url` and `method`. If the request has a `body`, the `payload` tag must match it.
+///
+/// Return the auth event, whose `pubkey` is the authenticated user.
+pub fn verify_authorization_header(
+    header: &str,
+    url: &Url,
+    method: HttpMethod,
+    body: Option<&[u8]>,
+) -> Result<Event, Error> {
+    let encoded = header
+        .strip_prefix(AUTH_SCHEME)
+        .and_then(|h| h.strip_prefix(' '))
+        .ok_or(Error::InvalidHeader)?;
+    let json =
+        String::from_utf8(base64::decode(encoded.trim())?).map_err(|_| Error::InvalidHeader)?;
+    let event = Event::from_json(json)?;
+
+    let data = HttpData::from_event(&event)?;
+
+    let now = timestamp();
+    if event.created_at.abs_diff(now) > TIME_WINDOW {
+        return Err(Error::InvalidTimestamp);
+    }
+    if &data.url != url {
+        return Err(Error::UrlMismatch);
+    }
+    if data.method != method {
+        return Err(Error::MethodMismatch);
+    }
+    if let Some(body) = body {
+        if data.payload != Some(Sha256Hash::hash(body)) {
+            return Err(Error::PayloadMismatch);
+        }
+    }
+
+    Ok(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::nips::test_util::build_event;
+    use crate::{EventBuilder, Keys, Result};
+
+    const URL: &str = "https://api.snort.social/api/v1/n5sp/list";
+
+    fn header(data: &HttpData, created_at: u64) -> Result<String> {
+        let event = EventBuilder::http_auth(data)
+            .custom_created_at(created_at)
+            .to_event(&Keys::generate_from_os_random())?;
+        Ok(authorization_header(&event)?)
+    }
+
+    #[test]
+    fn test_http_auth() -> Result<()> {
+        let keys = Keys::generate_from_os_random();
+        let url = Url::parse("https://api.example.com/upload?id=1")?;
+        let body = b"{\"hello\":\"world\"}";
+
+        let data = HttpData::new(url.clone(), HttpMethod::POST).payload(Sha256Hash::hash(body));
+        let event = EventBuilder::http_auth(&data).to_event(&keys)?;
+        assert_eq!(event.kind, Kind::Base(KindBase::HttpAuth));
+        assert_eq!(HttpData::from_event(&event)?, data);
+
+        let header = authorization_header(&event)?;
+        assert!(header.starts_with("Nostr "));
+        let verified = verify_authorization_header(&header, &url, HttpMethod::POST, Some(body))?;
+        assert_eq!(verified.pubkey, keys.public_key());
+
+        assert!(matches!(
+            verify_authorization_header(&header, &url, HttpMethod::PUT, Some(body)),
+            Err(Error::MethodMismatch)
+        ));
+        // The query parameters are part of the url
+        assert!(matches!(
+            verify_authorization_header(
+                &header,
+                &Url::parse("https://api.example.com/upload?id=2")?,
+                HttpMethod::POST,
+                Some(body)
+            ),
+            Err(Error::UrlMismatch)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_spec_example() -> Result<()> {
+        let url = Url::parse(URL)?;
+        let event = build_event(
+            Kind::Base(KindBase::HttpAuth),
+            "",
+            r#"[["u", "https://api.snort.social/api/v1/n5sp/list"], ["method", "GET"]]"#,
+        );
+        assert_eq!(
+            HttpData::from_event(&event)?,
+            HttpData::new(url.clone(), HttpMethod::GET)
+        );
+        assert_eq!(
+            verify_authorization_header(
+                &authorization_header(&event)?,
+                &url,
+                HttpMethod::GET,
+                None
+            )?,
+            event
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_payload() -> Result<()> {
+        let url = Url::parse(URL)?;
+        let body: &[u8] = b"{\"hello\":\"world\"}";
+        let now = timestamp();
+
+        // A request with a body requires the hash of that body
+        let with_payload = header(
+            &HttpData::new(url.clone(), HttpMethod::POST).payload(Sha256Hash::hash(body)),
+            now,
+        )?;
+        let without_payload = header(&HttpData::new(url.clone(), HttpMethod::POST), now)?;
+        for (header, body) in [(&with_payload, &b"other"[..]), (&without_payload, body)] {
+            assert!(matches!(
+                verify_authorization_header(header, &url, HttpMethod::POST, Some(body)),
+                Err(Error::PayloadMismatch)
+            ));
+        }
+
+        // Without body the payload isn't checked
+        assert!(verify_authorization_header(&with_payload, &url, HttpMethod::POST, None).is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_time_window() -> Result<()> {
+        let url = Url::parse(URL)?;
+        let data = HttpData::new(url.clone(), HttpMethod::GET);
+        let now = timestamp();
+
+        for created_at in [now - TIME_WINDOW + 5, now + TIME_WINDOW - 5] {
+            assert!(verify_authorization_header(
+                &header(&data, created_at)?,
+                &url,
+                HttpMethod::GET,
+                None
+            )
+            .is_ok());
+        }
+        for created_at in [now - TIME_WINDOW - 10, now + TIME_WINDOW + 10] {
+            assert!(matches!(
+                verify_authorization_header(
+                    &header(&data, created_at)?,
+                    &url,
+                    HttpMethod::GET,
+                    None
+                ),
+                Err(Error::InvalidTimestamp)
+            ));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_authorization_header() -> Result<()> {
+        let url = Url::parse(URL)?;
+        let keys = Keys::generate_from_os_random();
+        let data = HttpData::new(url.clone(), HttpMethod::GET);
+        let event = EventBuilder::http_auth(&data).to_event(&keys)?;
+        let encoded = base64::encode(event.as_json()?);
+
+        // Surrounding whitespace is tolerated, the scheme is case sensitive
+        assert!(verify_authorization_header(
+            &format!("Nostr  {} ", encoded),
+            &url,
+            HttpMethod::GET,
+            None
+        )
+        .is_ok());
+        for header in [
+            format!("nostr {}", encoded),
+            format!("Nostr{}", encoded),
+            format!("Bearer {}", encoded),
+        ] {
+            assert!(matches!(
+                verify_authorization_header(&header, &url, HttpMethod::GET, None),
+                Err(Error::InvalidHeader)
+            ));
+        }
+
+        // Forged event: content changed after signing
+        let mut forged = event.clone();
+        forged.content = String::from("forged");
+        assert!(matches!(
+            verify_authorization_header(
+                &format!("Nostr {}", base64::encode(forged.as_json()?)),
+                &url,
+                HttpMethod::GET,
+                None
+            ),
+            Err(Error::Event(_))
+        ));
+
+        // Other kinds, not base64, not an event
+        let note =
+            EventBuilder::new(Kind::Base(KindBase::TextNote), "", &data.tags()).to_event(&keys)?;
+        assert!(matches!(
+            verify_authorization_header(&authorization_header(&note)?, &url, HttpMethod::GET, None),
+            Err(Error::InvalidKind)
+        ));
+        assert!(matches!(
+            verify_authorization_header("Nostr not base64!", &url, HttpMethod::GET, None),
+            Err(Error::Base64(_))
+        ));
+        assert!(matches!(
+            verify_authorization_header(
+                &format!("Nostr {}", base64::encode("{}")),
+                &url,
+                HttpMethod::GET,
+                None
+            ),
+            Err(Error::Event(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_tags() -> Result<()> {
+        for (tags, error) in [
+            (r#"[["method", "GET"]]"#, Error::UrlMismatch),
+            // Absolute url required
+            (
+                r#"[["u", "/api/v1/n5sp/list"], ["method", "GET"]]"#,
+                Error::UrlMismatch,
+            ),
+            (
+                r#"[["u", "https://api.snort.social"]]"#,
+                Error::UnknownMethod,
+            ),
+            (
+                r#"[["u", "https://api.snort.social"], ["method", "get"]]"#,
+                Error::UnknownMethod,
+            ),
+            (
+                r#"[["u", "https://api.snort.social"], ["method", "HEAD"]]"#,
+                Error::UnknownMethod,
+            ),
+            (
+                r#"[["u", "https://api.snort.social"], ["method", "POST"], ["payload", "not a hash"]]"#,
+                Error::PayloadMismatch,
+            ),
+        ] {
+            let event = build_event(Kind::Base(KindBase::HttpAuth), "", tags);
+            assert_eq!(
+                HttpData::from_event(&event).unwrap_err().to_string(),
+                error.to_string()
+            );
+        }
+
+        Ok(())
+    }
+}
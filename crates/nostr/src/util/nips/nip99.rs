@@ -0,0 +1,381 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use super::nip58::ImageDimensions;
+use crate::event::TagKind;
+use crate::{Event, Kind, KindBase, Tag};
+
+#[derive(Debug, Eq, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// Invalid event kind
+    #[error("invalid event kind")]
+    InvalidKind,
+    /// Identifier (`d` tag) not found
+    #[error("identifier not found")]
+    IdentifierNotFound,
+    /// Invalid `published_at` timestamp
+    #[error("invalid published_at")]
+    InvalidPublishedAt,
+    /// Invalid `price` tag
+    #[error("invalid price")]
+    InvalidPrice,
+}
+
+/// Listing price
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Price {
+    /// Amount, as decimal string (ex. `50`, `15.99`)
+    pub amount: String,
+    /// ISO 4217 currency code (or `BTC`, `SATS`, ...)
+    pub currency: String,
+    /// Payment frequency for recurring prices (`hour`, `day`, `week`, `month`, `year`)
+    pub frequency: Option<String>,
+}
+
+impl Price {
+    /// New `Price`
+    pub fn new<S>(amount: S, currency: S, frequency: Option<S>) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            amount: amount.into(),
+            currency: currency.into(),
+            frequency: frequency.map(|f| f.into()),
+        }
+    }
+}
+
+/// Classified listing (kind 30402)
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/99.md>
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ClassifiedListing {
+    /// Listing identifier (`d` tag)
+    pub identifier: String,
+    pub title: Option<String>,
+    /// Short tagline
+    pub summary: Option<String>,
+    /// Timestamp of the first publication
+    pub published_at: Option<u64>,
+    pub location: Option<String>,
+    pub price: Option<Price>,
+    /// Image urls, with optional dimensions
+    pub images: Vec<(String, Option<ImageDimensions>)>,
+    /// Categories (`t` tags)
+    pub hashtags: Vec<String>,
+}
+
+impl ClassifiedListing {
+    /// New `ClassifiedListing`
+    pub fn new<S>(identifier: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            identifier: identifier.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Set title
+    pub fn title<S>(self, title: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            title: Some(title.into()),
+            ..self
+        }
+    }
+
+    /// Set summary
+    pub fn summary<S>(self, summary: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            summary: Some(summary.into()),
+            ..self
+        }
+    }
+
+    /// Set published at
+    pub fn published_at(self, timestamp: u64) -> Self {
+        Self {
+            published_at: Some(timestamp),
+            ..self
+        }
+    }
+
+    /// Set location
+    pub fn location<S>(self, location: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            location: Some(location.into()),
+            ..self
+        }
+    }
+
+    /// Set price
+    pub fn price(self, price: Price) -> Self {
+        Self {
+            price: Some(price),
+            ..self
+        }
+    }
+
+    /// Add image
+    pub fn image<S>(mut self, url: S, dimensions: Option<ImageDimensions>) -> Self
+    where
+        S: Into<String>,
+    {
+        self.images.push((url.into(), dimensions));
+        self
+    }
+
+    /// Add hashtag
+    pub fn hashtag<S>(mut self, hashtag: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.hashtags.push(hashtag.into());
+        self
+    }
+
+    /// Parse classified listing from a kind 30402 event
+    pub fn from_event(event: &Event) -> Result<Self, Error> {
+        if event.kind != Kind::Base(KindBase::ClassifiedListing) {
+            return Err(Error::InvalidKind);
+        }
+
+        let mut listing = Self::new(event.identifier().ok_or(Error::IdentifierNotFound)?);
+
+        for tag in event.tags.iter() {
+            let tag = tag.as_vec();
+            match (tag.first().map(|t| t.as_str()), tag.get(1)) {
+                (Some("title"), Some(title)) => listing.title = Some(title.clone()),
+                (Some("summary"), Some(summary)) => listing.summary = Some(summary.clone()),
+                (Some("location"), Some(location)) => listing.location = Some(location.clone()),
+                (Some("published_at"), Some(timestamp)) => {
+                    listing.published_at =
+                        Some(timestamp.parse().map_err(|_| Error::InvalidPublishedAt)?)
+                }
+                (Some("price"), Some(amount)) => {
+                    listing.price = Some(Price {
+                        amount: amount.clone(),
+                        currency: tag.get(2).cloned().ok_or(Error::InvalidPrice)?,
+                        frequency: tag.get(3).cloned(),
+                    })
+                }
+                (Some("image"), Some(url)) => listing
+                    .images
+                    .push((url.clone(), tag.get(2).and_then(|d| d.parse().ok()))),
+                (Some("t"), Some(hashtag)) => listing.hashtags.push(hashtag.clone()),
+                _ => (),
+            }
+        }
+
+        Ok(listing)
+    }
+
+    /// Get classified listing tags
+    pub fn tags(&self) -> Vec<Tag> {
+        let mut tags = vec![Tag::from(vec![
+            TagKind::D.to_string(),
+            self.identifier.clone(),
+        ])];
+
+        if let Some(title) = &self.title {
+            tags.push(Tag::from(vec![String::from("title"), title.clone()]));
+        }
+        if let Some(summary) = &self.summary {
+            tags.push(Tag::from(vec![String::from("summary"), summary.clone()]));
+        }
+        if let Some(published_at) = self.published_at {
+            tags.push(Tag::from(vec![
+                String::from("published_at"),
+                published_at.to_string(),
+            ]));
+        }
+        if let Some(location) = &self.location {
+            tags.push(Tag::from(vec![String::from("location"), location.clone()]));
+        }
+        if let Some(price) = &self.price {
+            let mut tag = vec![
+                String::from("price"),
+                price.amount.clone(),
+                price.currency.clone(),
+            ];
+            if let Some(frequency) = &price.frequency {
+                tag.push(frequency.clone());
+            }
+            tags.push(Tag::from(tag));
+        }
+        for (url, dimensions) in self.images.iter() {
+            let mut tag = vec![String::from("image"), url.clone()];
+            if let Some(dimensions) = dimensions {
+                tag.push(dimensions.to_string());
+            }
+            tags.push(Tag::from(tag));
+        }
+        for hashtag in self.hashtags.iter() {
+            tags.push(Tag::from(vec![String::from("t"), hashtag.clone()]));
+        }
+
+        tags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::nips::test_util::build_event;
+    use crate::{EventBuilder, Keys, Result};
+
+    fn listing(tags_json: &str) -> Result<ClassifiedListing, Error> {
+        ClassifiedListing::from_event(&build_event(
+            Kind::Base(KindBase::ClassifiedListing),
+            "Lorem ipsum dolor sit amet",
+            tags_json,
+        ))
+    }
+
+    #[test]
+    fn test_classified_listing() -> Result<()> {
+        let listing = ClassifiedListing::new("lorem-ipsum")
+            .title("Apartment for rent")
+            .summary("2 rooms, city center")
+            .published_at(1296962229)
+            .location("Milan")
+            .price(Price::new("1200", "EUR", Some("month")))
+            .image(
+                "https://example.com/apartment.jpg",
+                Some(ImageDimensions {
+                    width: 1024,
+                    height: 768,
+                }),
+            )
+            .image("https://example.com/kitchen.jpg", None)
+            .hashtag("housing");
+
+        // The description is the (markdown) content, not part of the listing
+        let event = EventBuilder::classified_listing("# Description", &listing)
+            .to_event(&Keys::generate_from_os_random())?;
+        assert_eq!(event.kind, Kind::Base(KindBase::ClassifiedListing));
+        assert_eq!(event.content, "# Description");
+        assert_eq!(ClassifiedListing::from_event(&event)?, listing);
+
+        let note = build_event(
+            Kind::Base(KindBase::TextNote),
+            "",
+            r#"[["d", "lorem-ipsum"]]"#,
+        );
+        assert_eq!(
+            ClassifiedListing::from_event(&note),
+            Err(Error::InvalidKind)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_spec_example() -> Result<()> {
+        // References to other events are allowed but not part of the listing
+        assert_eq!(
+            listing(
+                r#"[
+                    ["d", "lorem-ipsum"],
+                    ["title", "Lorem Ipsum"],
+                    ["published_at", "1296962229"],
+                    ["t", "electronics"],
+                    ["image", "https://url.to.img", "256x256"],
+                    ["summary", "More lorem ipsum that is a little more than the title"],
+                    ["location", "NYC"],
+                    ["price", "100", "USD"],
+                    ["e", "b3e392b11f5d4f28321cedd09303a748acfd0487aea5a7450b3481c60b6e4f87", "wss://relay.example.com"],
+                    ["a", "30023:a695f6b60119d9521934a691347d9f78e8770b56da16bb255ee286ddf9fda919:ipsum", "wss://relay.nostr.org"]
+                ]"#
+            )?,
+            ClassifiedListing::new("lorem-ipsum")
+                .title("Lorem Ipsum")
+                .published_at(1296962229)
+                .hashtag("electronics")
+                .image(
+                    "https://url.to.img",
+                    Some(ImageDimensions {
+                        width: 256,
+                        height: 256,
+                    })
+                )
+                .summary("More lorem ipsum that is a little more than the title")
+                .location("NYC")
+                .price(Price::new("100", "USD", None))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_price() -> Result<()> {
+        for (tag, price) in [
+            // Recurring prices
+            (
+                r#"["price", "15", "EUR", "month"]"#,
+                Price::new("15", "EUR", Some("month")),
+            ),
+            (
+                r#"["price", "50000", "GBP", "year"]"#,
+                Price::new("50000", "GBP", Some("year")),
+            ),
+            // Amounts are kept as written, not rounded
+            (
+                r#"["price", "15.990", "USD"]"#,
+                Price::new("15.990", "USD", None),
+            ),
+            (
+                r#"["price", "0.00010000", "BTC"]"#,
+                Price::new("0.00010000", "BTC", None),
+            ),
+        ] {
+            let listing = listing(&format!(r#"[["d", "id"], {}]"#, tag))?;
+            assert_eq!(listing.price.as_ref(), Some(&price));
+            assert_eq!(
+                ClassifiedListing::new("id").price(price).tags()[1],
+                serde_json::from_str::<Tag>(tag)?
+            );
+        }
+
+        // The currency is required
+        assert_eq!(
+            listing(r#"[["d", "id"], ["price", "100"]]"#),
+            Err(Error::InvalidPrice)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_tags() -> Result<()> {
+        assert_eq!(
+            listing(r#"[["title", "No identifier"]]"#),
+            Err(Error::IdentifierNotFound)
+        );
+        assert_eq!(
+            listing(r#"[["d", "id"], ["published_at", "yesterday"]]"#),
+            Err(Error::InvalidPublishedAt)
+        );
+
+        // Invalid image dimensions and incomplete tags are ignored
+        assert_eq!(
+            listing(
+                r#"[["d", "id"], ["image", "https://url.to.img", "big"], ["image"], ["title"], ["t"]]"#
+            )?,
+            ClassifiedListing::new("id").image("https://url.to.img", None)
+        );
+
+        Ok(())
+    }
+}
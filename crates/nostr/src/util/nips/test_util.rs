@@ -0,0 +1,25 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Helpers shared by the NIPs tests
+
+use bitcoin::secp256k1::XOnlyPublicKey;
+
+use crate::{Event, EventBuilder, Keys, Kind, Tag};
+
+/// Random public key
+pub(crate) fn public_key() -> XOnlyPublicKey {
+    Keys::generate_from_os_random().public_key()
+}
+
+/// Parse the tags from a JSON array (i.e. copied from the NIP)
+pub(crate) fn tags(json: &str) -> Vec<Tag> {
+    serde_json::from_str(json).unwrap()
+}
+
+/// Event signed with random keys, with the tags parsed from a JSON array
+pub(crate) fn build_event(kind: Kind, content: &str, tags_json: &str) -> Event {
+    EventBuilder::new(kind, content, &tags(tags_json))
+        .to_event(&Keys::generate_from_os_random())
+        .unwrap()
+}
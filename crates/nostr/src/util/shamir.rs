@@ -0,0 +1,347 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use bitcoin::bech32::{self, FromBase32, ToBase32, Variant};
+use bitcoin::hashes::sha256::Hash as Sha256Hash;
+use bitcoin::hashes::Hash;
+use bitcoin::secp256k1::rand::rngs::OsRng;
+use bitcoin::secp256k1::rand::RngCore;
+use bitcoin::secp256k1::SecretKey;
+use zeroize::Zeroizing;
+
+const PREFIX_BECH32_SHARE: &str = "nshare";
+const ID_SIZE: usize = 4;
+const SECRET_SIZE: usize = 32;
+const CHECKSUM_SIZE: usize = 4;
+/// Secret followed by its checksum: shared together, so a share reveals nothing about either
+const SHARED_SIZE: usize = SECRET_SIZE + CHECKSUM_SIZE;
+const SHARE_SIZE: usize = ID_SIZE + 1 + 1 + SHARED_SIZE;
+
+#[derive(Debug, Eq, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// Bech32 error
+    #[error("bech32 error: {0}")]
+    Bech32(#[from] bech32::Error),
+    /// Threshold must be between 1 and the number of shares
+    #[error("invalid threshold")]
+    InvalidThreshold,
+    /// Invalid share
+    #[error("invalid share")]
+    InvalidShare,
+    /// Shares of different secrets or with different thresholds
+    #[error("shares mismatch")]
+    SharesMismatch,
+    /// Same share provided more than once
+    #[error("duplicate share")]
+    DuplicateShare,
+    /// Not enough shares
+    #[error("not enough shares: {found} of {threshold}")]
+    NotEnoughShares {
+        /// Required shares
+        threshold: u8,
+        /// Provided shares
+        found: usize,
+    },
+    /// The reconstructed secret doesn't match its checksum
+    #[error("impossible to reconstruct the secret")]
+    Reconstruction,
+    /// Secp256k1 error
+    #[error("secp256k1 error: {0}")]
+    Secp256k1(#[from] bitcoin::secp256k1::Error),
+}
+
+/// Split secret key into `shares` shares (`nshare1...`) with Shamir's Secret Sharing
+///
+/// Any `threshold` of the shares are enough to reconstruct the secret key.
+///
+/// The shares of a split have a random identifier, used to match them. The checksum verifying
+/// the reconstruction is shared with the secret (as in SLIP-39).
+pub fn split(secret_key: &SecretKey, threshold: u8, shares: u8) -> Result<Vec<String>, Error> {
+    if threshold == 0 || threshold > shares {
+        return Err(Error::InvalidThreshold);
+    }
+
+    let mut id = [0u8; ID_SIZE];
+    OsRng.fill_bytes(&mut id);
+
+    let mut secret = Zeroizing::new([0u8; SHARED_SIZE]);
+    secret[..SECRET_SIZE].copy_from_slice(&secret_key.secret_bytes());
+    let checksum = checksum(&secret[..SECRET_SIZE]);
+    secret[SECRET_SIZE..].copy_from_slice(&checksum);
+
+    // One polynomial of degree `threshold - 1` for each byte of the secret
+    let mut coefficients: Zeroizing<Vec<[u8; SHARED_SIZE]>> =
+        Zeroizing::new(Vec::with_capacity(threshold as usize));
+    coefficients.push(*secret);
+    for _ in 1..threshold {
+        let mut coefficient = [0u8; SHARED_SIZE];
+        OsRng.fill_bytes(&mut coefficient);
+        coefficients.push(coefficient);
+    }
+
+    let mut result: Vec<String> = Vec::with_capacity(shares as usize);
+    for x in 1..=shares {
+        let mut payload: Vec<u8> = Vec::with_capacity(SHARE_SIZE);
+        payload.extend_from_slice(&id);
+        payload.push(threshold);
+        payload.push(x);
+        for i in 0..SHARED_SIZE {
+            // Horner's method
+            let y = coefficients
+                .iter()
+                .rev()
+                .fold(0u8, |acc, c| gf_mul(acc, x) ^ c[i]);
+            payload.push(y);
+        }
+        result.push(bech32::encode(
+            PREFIX_BECH32_SHARE,
+            payload.to_base32(),
+            Variant::Bech32,
+        )?);
+    }
+
+    Ok(result)
+}
+
+/// Reconstruct secret key from shares
+pub fn combine<S>(shares: &[S]) -> Result<SecretKey, Error>
+where
+    S: AsRef<str>,
+{
+    let shares: Vec<Share> = shares
+        .iter()
+        .map(|s| Share::decode(s.as_ref()))
+        .collect::<Result<_, _>>()?;
+
+    let first = shares.first().ok_or(Error::NotEnoughShares {
+        threshold: 1,
+        found: 0,
+    })?;
+
+    for (index, share) in shares.iter().enumerate() {
+        if share.id != first.id || share.threshold != first.threshold {
+            return Err(Error::SharesMismatch);
+        }
+        if shares[..index].iter().any(|s| s.x == share.x) {
+            return Err(Error::DuplicateShare);
+        }
+    }
+
+    if shares.len() < first.threshold as usize {
+        return Err(Error::NotEnoughShares {
+            threshold: first.threshold,
+            found: shares.len(),
+        });
+    }
+
+    // Lagrange interpolation at x = 0
+    let shares = &shares[..first.threshold as usize];
+    let mut secret = Zeroizing::new([0u8; SHARED_SIZE]);
+    for (i, share) in shares.iter().enumerate() {
+        let mut basis: u8 = 1;
+        for (j, other) in shares.iter().enumerate() {
+            if i != j {
+                basis = gf_mul(basis, gf_div(other.x, other.x ^ share.x));
+            }
+        }
+        for (byte, y) in secret.iter_mut().zip(share.y.iter()) {
+            *byte ^= gf_mul(*y, basis);
+        }
+    }
+
+    if checksum(&secret[..SECRET_SIZE])[..] != secret[SECRET_SIZE..] {
+        return Err(Error::Reconstruction);
+    }
+
+    Ok(SecretKey::from_slice(&secret[..SECRET_SIZE])?)
+}
+
+struct Share {
+    id: [u8; ID_SIZE],
+    threshold: u8,
+    x: u8,
+    y: [u8; SHARED_SIZE],
+}
+
+impl Share {
+    fn decode(share: &str) -> Result<Self, Error> {
+        let (hrp, data, checksum) = bech32::decode(share)?;
+        if hrp != PREFIX_BECH32_SHARE || checksum != Variant::Bech32 {
+            return Err(Error::InvalidShare);
+        }
+
+        let payload = Vec::<u8>::from_base32(&data)?;
+        if payload.len() != SHARE_SIZE {
+            return Err(Error::InvalidShare);
+        }
+
+        let threshold = payload[ID_SIZE];
+        let x = payload[ID_SIZE + 1];
+        if threshold == 0 || x == 0 {
+            return Err(Error::InvalidShare);
+        }
+
+        let mut id = [0u8; ID_SIZE];
+        id.copy_from_slice(&payload[..ID_SIZE]);
+        let mut y = [0u8; SHARED_SIZE];
+        y.copy_from_slice(&payload[ID_SIZE + 2..]);
+
+        Ok(Self {
+            id,
+            threshold,
+            x,
+            y,
+        })
+    }
+}
+
+/// First bytes of the secret hash, used to verify the reconstruction
+fn checksum(secret: &[u8]) -> [u8; CHECKSUM_SIZE] {
+    let hash = Sha256Hash::hash(secret);
+    let mut checksum = [0u8; CHECKSUM_SIZE];
+    checksum.copy_from_slice(&hash[..CHECKSUM_SIZE]);
+    checksum
+}
+
+/// Multiplication in GF(2^8) (AES polynomial)
+///
+/// Constant time: masks instead of branches, since the operands are secret.
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut result: u8 = 0;
+    for _ in 0..8 {
+        result ^= a & (b & 1).wrapping_neg();
+        let carry = (a >> 7).wrapping_neg();
+        a = (a << 1) ^ (0x1b & carry);
+        b >>= 1;
+    }
+    result
+}
+
+/// Division in GF(2^8): `a * b^254`
+fn gf_div(a: u8, b: u8) -> u8 {
+    let mut inverse: u8 = 1;
+    for _ in 0..254 {
+        inverse = gf_mul(inverse, b);
+    }
+    gf_mul(a, inverse)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_split_combine() {
+        let secret_key =
+            SecretKey::from_str("6b911fd37cdf5c81d4c0adb1ab7fa822ed253ab0ad9aa18d77257c88b29b718e")
+                .unwrap();
+
+        let shares = split(&secret_key, 3, 5).unwrap();
+        assert_eq!(shares.len(), 5);
+        assert!(shares.iter().all(|s| s.starts_with("nshare1")));
+
+        assert_eq!(combine(&shares[..3]).unwrap(), secret_key);
+        assert_eq!(combine(&shares[2..]).unwrap(), secret_key);
+        assert_eq!(
+            combine(&[&shares[4], &shares[0], &shares[2]]).unwrap(),
+            secret_key
+        );
+        assert_eq!(combine(&shares).unwrap(), secret_key);
+
+        assert_eq!(
+            combine(&shares[..2]).unwrap_err(),
+            Error::NotEnoughShares {
+                threshold: 3,
+                found: 2
+            }
+        );
+        assert_eq!(
+            combine(&[&shares[0], &shares[0], &shares[1]]).unwrap_err(),
+            Error::DuplicateShare
+        );
+    }
+
+    #[test]
+    fn test_shares_mismatch() {
+        let shares_a = split(&SecretKey::from_slice(&[1u8; 32]).unwrap(), 2, 3).unwrap();
+        let shares_b = split(&SecretKey::from_slice(&[2u8; 32]).unwrap(), 2, 3).unwrap();
+        assert_eq!(
+            combine(&[&shares_a[0], &shares_b[1]]).unwrap_err(),
+            Error::SharesMismatch
+        );
+
+        // The identifier is random: two splits of the same secret don't match
+        let secret_key = SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let shares_c = split(&secret_key, 2, 3).unwrap();
+        let id = |share: &str| Share::decode(share).unwrap().id;
+        assert_ne!(id(&shares_a[0]), id(&shares_c[0]));
+        assert_eq!(
+            combine(&[&shares_a[0], &shares_c[1]]).unwrap_err(),
+            Error::SharesMismatch
+        );
+    }
+
+    #[test]
+    fn test_corrupted_share() {
+        let secret_key = SecretKey::from_slice(&[3u8; 32]).unwrap();
+        let shares = split(&secret_key, 2, 2).unwrap();
+
+        // Valid encoding, altered value
+        let mut share = Share::decode(&shares[1]).unwrap();
+        share.y[0] ^= 0x01;
+        let mut payload: Vec<u8> = share.id.to_vec();
+        payload.push(share.threshold);
+        payload.push(share.x);
+        payload.extend_from_slice(&share.y);
+        let corrupted =
+            bech32::encode(PREFIX_BECH32_SHARE, payload.to_base32(), Variant::Bech32).unwrap();
+
+        assert_eq!(
+            combine(&[&shares[0], &corrupted]).unwrap_err(),
+            Error::Reconstruction
+        );
+    }
+
+    #[test]
+    fn test_gf_mul() {
+        // Reference: shift-and-add with branches
+        fn reference(mut a: u8, mut b: u8) -> u8 {
+            let mut result: u8 = 0;
+            while b != 0 {
+                if b & 1 != 0 {
+                    result ^= a;
+                }
+                let carry = a & 0x80 != 0;
+                a <<= 1;
+                if carry {
+                    a ^= 0x1b;
+                }
+                b >>= 1;
+            }
+            result
+        }
+
+        for a in 0..=255u8 {
+            for b in 0..=255u8 {
+                assert_eq!(gf_mul(a, b), reference(a, b));
+            }
+        }
+        assert_eq!(gf_mul(0x57, 0x83), 0xc1);
+        assert_eq!(gf_div(gf_mul(0x57, 0x83), 0x83), 0x57);
+    }
+
+    #[test]
+    fn test_invalid_threshold() {
+        let secret_key = SecretKey::from_slice(&[1u8; 32]).unwrap();
+        assert_eq!(
+            split(&secret_key, 0, 3).unwrap_err(),
+            Error::InvalidThreshold
+        );
+        assert_eq!(
+            split(&secret_key, 4, 3).unwrap_err(),
+            Error::InvalidThreshold
+        );
+    }
+}
@@ -18,3 +18,76 @@ pub fn timestamp_nanos() -> u128 {
         .expect("Invalid system time")
         .as_nanos()
 }
+
+/// Split a timestamp in (year, month, day, hours, minutes, seconds, weekday)
+fn to_datetime(timestamp: u64) -> (u64, u64, u64, u64, u64, u64, u64) {
+    let days = timestamp / 86400;
+    let secs = timestamp % 86400;
+
+    // Days to civil date (http://howardhinnant.github.io/date_algorithms.html)
+    let z = days + 719468;
+    let era = z / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    // 1970-01-01 was a Thursday
+    let weekday = (days + 4) % 7;
+
+    (
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60,
+        weekday,
+    )
+}
+
+/// Format timestamp as RFC 3339 (ex. `2023-01-15T10:30:00Z`)
+pub fn to_rfc3339(timestamp: u64) -> String {
+    let (year, month, day, hours, minutes, seconds, _) = to_datetime(timestamp);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, hours, minutes, seconds
+    )
+}
+
+/// Format timestamp as RFC 2822 (ex. `Sun, 15 Jan 2023 10:30:00 +0000`)
+pub fn to_rfc2822(timestamp: u64) -> String {
+    const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    let (year, month, day, hours, minutes, seconds, weekday) = to_datetime(timestamp);
+    format!(
+        "{}, {:02} {} {:04} {:02}:{:02}:{:02} +0000",
+        WEEKDAYS[weekday as usize],
+        day,
+        MONTHS[(month - 1) as usize],
+        year,
+        hours,
+        minutes,
+        seconds
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_date_format() {
+        assert_eq!(to_rfc3339(0), "1970-01-01T00:00:00Z");
+        assert_eq!(to_rfc3339(1673778600), "2023-01-15T10:30:00Z");
+        assert_eq!(to_rfc3339(951782400), "2000-02-29T00:00:00Z");
+        assert_eq!(to_rfc2822(0), "Thu, 01 Jan 1970 00:00:00 +0000");
+        assert_eq!(to_rfc2822(1673778600), "Sun, 15 Jan 2023 10:30:00 +0000");
+    }
+}